@@ -0,0 +1,722 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    panic::AssertUnwindSafe,
+    sync::{
+        Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Instant,
+};
+
+use actix_web::{
+    Error, HttpResponse, Responder, ResponseError,
+    body::{self, BoxBody, MessageBody},
+    delete,
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    get,
+    http::{StatusCode, header},
+    middleware::Next,
+    post, put, web,
+};
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+
+// GET / users 默认和最大的分页大小
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+// 用户名的最大字符数
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct User {
+    pub id: u32,
+    pub name: String,
+}
+
+// POST / users 的请求体，id 由服务端分配
+#[derive(Deserialize)]
+pub struct NewUser {
+    // 兼容旧客户端，传入已存在的 id 时返回 409
+    id: Option<u32>,
+    name: String,
+}
+
+impl NewUser {
+    // 校验请求体，返回所有不合法的字段
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if self.id == Some(0) {
+            errors.push(FieldError::new("id", "must be at least 1"));
+        }
+        validate_name(&self.name, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// PUT / users / {id} 的请求体
+#[derive(Deserialize)]
+pub struct UpdateUser {
+    name: String,
+}
+
+impl UpdateUser {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        validate_name(&self.name, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// 用户名不能为空（只有空白也不行），最多 MAX_NAME_LEN 个字符
+fn validate_name(name: &str, errors: &mut Vec<FieldError>) {
+    if name.trim().is_empty() {
+        errors.push(FieldError::new("name", "must not be empty"));
+    } else if name.chars().count() > MAX_NAME_LEN {
+        errors.push(FieldError::new(
+            "name",
+            &format!("must be at most {} characters", MAX_NAME_LEN),
+        ));
+    }
+}
+
+// GET / users 的查询参数
+#[derive(Deserialize)]
+pub struct UserQuery {
+    offset: Option<usize>,
+    limit: Option<usize>,
+    // 按名字过滤，不区分大小写
+    name_contains: Option<String>,
+}
+
+// GET / users 的返回结果，total 是过滤后、分页前的用户数
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UserPage {
+    pub total: usize,
+    pub items: Vec<User>,
+}
+
+// 校验失败的字段和原因
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: &str) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+// 所有错误响应的 JSON：{"error": {"code": ..., "message": ...}}，
+// 校验失败时额外带上 fields
+#[derive(Serialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Serialize)]
+struct ErrorDetail {
+    // 状态码的标准描述，比如 not_found、unprocessable_entity
+    code: String,
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<FieldError>,
+}
+
+fn error_response(status: StatusCode, message: String, fields: Vec<FieldError>) -> HttpResponse {
+    let code = status
+        .canonical_reason()
+        .unwrap_or("Unknown")
+        .to_lowercase()
+        .replace(' ', "_");
+    HttpResponse::build(status).json(ErrorBody {
+        error: ErrorDetail {
+            code,
+            message,
+            fields,
+        },
+    })
+}
+
+fn bad_request(message: String) -> HttpResponse {
+    error_response(StatusCode::BAD_REQUEST, message, Vec::new())
+}
+
+fn not_found(message: String) -> HttpResponse {
+    error_response(StatusCode::NOT_FOUND, message, Vec::new())
+}
+
+// 请求体校验失败时返回 422
+fn validation_failed(fields: Vec<FieldError>) -> HttpResponse {
+    error_response(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "invalid request body".to_string(),
+        fields,
+    )
+}
+
+// 中间件中无法直接构造响应时返回的错误
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        error_response(self.status, self.message.clone(), Vec::new())
+    }
+}
+
+// 把所有不是 JSON 的错误响应（404、400、405 等）以及 handler 中的 panic
+// 转换成统一的 JSON 错误格式，原来的文本响应体作为 message
+pub async fn json_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    // panic 后拿不到原来的请求，只能返回错误，由 actix 调用 ApiError::error_response 生成响应。
+    // 不把 panic 的内容返回给客户端
+    let res = match AssertUnwindSafe(next.call(req)).catch_unwind().await {
+        Ok(res) => res?.map_into_boxed_body(),
+        Err(_) => {
+            return Err(ApiError {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                message: "internal server error".to_string(),
+            }
+            .into());
+        }
+    };
+
+    let status = res.status();
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return Ok(res);
+    }
+
+    let (req, res) = res.into_parts();
+    let bytes = body::to_bytes(res.into_body()).await.unwrap_or_default();
+    let message = match std::str::from_utf8(&bytes) {
+        Ok(text) if !text.is_empty() => text.to_string(),
+        _ => status
+            .canonical_reason()
+            .unwrap_or("Unknown")
+            .to_lowercase(),
+    };
+    Ok(ServiceResponse::new(
+        req,
+        error_response(status, message, Vec::new()),
+    ))
+}
+
+// 记录每个请求的方法、路径、状态码和耗时
+pub async fn log_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let start = Instant::now();
+    let method = req.method().clone();
+    let path = req.path().to_string();
+    let res = next.call(req).await;
+    let status = match &res {
+        Ok(res) => res.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+    println!(
+        "{} {} {} {:?}",
+        method,
+        path,
+        status.as_u16(),
+        start.elapsed()
+    );
+    res
+}
+
+// 内存数据库，id 从 1 开始自增
+pub struct UserDB {
+    users: Mutex<HashMap<u32, User>>,
+    next_id: AtomicU32,
+}
+
+impl UserDB {
+    pub fn new() -> Self {
+        UserDB {
+            users: Mutex::new(HashMap::new()),
+            next_id: AtomicU32::new(1),
+        }
+    }
+
+    // 分配新的 id 并插入用户
+    pub fn insert(&self, name: String) -> User {
+        let mut users = self.users.lock().unwrap();
+        self.insert_locked(&mut users, name)
+    }
+
+    fn insert_locked(&self, users: &mut HashMap<u32, User>, name: String) -> User {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let user = User { id, name };
+        users.insert(id, user.clone());
+        user
+    }
+}
+
+impl Default for UserDB {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// GET / users?offset=&limit=&name_contains= - 按 id 排序分页获取用户
+#[get("/users")]
+async fn get_users(query: web::Query<UserQuery>, db: web::Data<UserDB>) -> impl Responder {
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit > MAX_LIMIT {
+        return bad_request(format!("limit must be at most {}", MAX_LIMIT));
+    }
+    let name_contains = query.name_contains.as_ref().map(|name| name.to_lowercase());
+
+    let users = db.users.lock().unwrap();
+    let mut matched = users
+        .values()
+        .filter(|user| match &name_contains {
+            Some(name) => user.name.to_lowercase().contains(name),
+            None => true,
+        })
+        .collect::<Vec<&User>>();
+    matched.sort_by_key(|user| user.id);
+
+    let page = UserPage {
+        total: matched.len(),
+        items: matched
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect(),
+    };
+    HttpResponse::Ok().json(page)
+}
+
+// GET / users / {id} - 获取指定用户
+#[get("/users/{id}")]
+async fn get_user(id: web::Path<u32>, db: web::Data<UserDB>) -> impl Responder {
+    let users = db.users.lock().unwrap();
+    match users.get(&id) {
+        Some(user) => HttpResponse::Ok().json(user),
+        None => not_found(format!("User {} not found", id)),
+    }
+}
+
+// POST / users - 创建用户，返回 201 和新用户的地址，请求体不合法时返回 422
+#[post("/users")]
+async fn create_user(user: web::Json<NewUser>, db: web::Data<UserDB>) -> impl Responder {
+    if let Err(fields) = user.validate() {
+        return validation_failed(fields);
+    }
+    let NewUser { id, name } = user.into_inner();
+    let mut users = db.users.lock().unwrap();
+    if let Some(id) = id
+        && users.contains_key(&id)
+    {
+        return error_response(
+            StatusCode::CONFLICT,
+            format!("User {} already exists", id),
+            Vec::new(),
+        );
+    }
+
+    let user = db.insert_locked(&mut users, name);
+    HttpResponse::Created()
+        .insert_header((header::LOCATION, format!("/users/{}", user.id)))
+        .json(user)
+}
+
+// PUT / users / {id} - 修改用户名，请求体不合法时返回 422
+#[put("/users/{id}")]
+async fn update_user(
+    id: web::Path<u32>,
+    user: web::Json<UpdateUser>,
+    db: web::Data<UserDB>,
+) -> impl Responder {
+    if let Err(fields) = user.validate() {
+        return validation_failed(fields);
+    }
+    let mut users = db.users.lock().unwrap();
+    match users.get_mut(&id) {
+        Some(existing) => {
+            existing.name = user.into_inner().name;
+            HttpResponse::Ok().json(existing.clone())
+        }
+        None => not_found(format!("User {} not found", id)),
+    }
+}
+
+// DELETE / users / {id} - 删除用户
+#[delete("/users/{id}")]
+async fn delete_user(id: web::Path<u32>, db: web::Data<UserDB>) -> impl Responder {
+    let mut users = db.users.lock().unwrap();
+    match users.remove(&id) {
+        Some(_) => HttpResponse::Ok().json(format!("User {} deleted", id)),
+        None => not_found(format!("User {} not found", id)),
+    }
+}
+
+// 注册所有接口
+pub fn config(cfg: &mut web::ServiceConfig) {
+    // 查询参数无法解析（比如 limit 为负数）时返回 400 和 JSON 错误
+    let query_config = web::QueryConfig::default().error_handler(|err, _req| {
+        let resp = bad_request(err.to_string());
+        InternalError::from_response(err, resp).into()
+    });
+    // 请求体不是合法的 JSON 或缺少字段时同样返回 400
+    let json_config = web::JsonConfig::default().error_handler(|err, _req| {
+        let resp = bad_request(err.to_string());
+        InternalError::from_response(err, resp).into()
+    });
+
+    cfg.app_data(query_config)
+        .app_data(json_config)
+        .service(get_users)
+        .service(get_user)
+        .service(create_user)
+        .service(update_user)
+        .service(delete_user);
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App, HttpResponse, body::MessageBody, dev::ServiceResponse, http::StatusCode, http::header,
+        middleware::from_fn, test, web,
+    };
+
+    use super::{
+        FieldError, MAX_NAME_LEN, NewUser, User, UserDB, UserPage, config, json_errors,
+        log_requests,
+    };
+
+    // 断言响应是统一的 JSON 错误格式，返回 error 对象
+    async fn assert_error(
+        resp: ServiceResponse<impl MessageBody>,
+        status: StatusCode,
+        code: &str,
+    ) -> serde_json::Value {
+        assert_eq!(resp.status(), status);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"]["code"], code, "{}", body);
+        assert!(body["error"]["message"].is_string(), "{}", body);
+        body["error"].clone()
+    }
+
+    #[actix_web::test]
+    async fn test_validate_new_user() {
+        let user = NewUser {
+            id: None,
+            name: "Alice".to_string(),
+        };
+        assert_eq!(user.validate(), Ok(()));
+
+        let user = NewUser {
+            id: Some(0),
+            name: " ".to_string(),
+        };
+        assert_eq!(
+            user.validate(),
+            Err(vec![
+                FieldError::new("id", "must be at least 1"),
+                FieldError::new("name", "must not be empty"),
+            ])
+        );
+
+        // 按字符而不是字节计算长度
+        let user = NewUser {
+            id: Some(1),
+            name: "用".repeat(MAX_NAME_LEN),
+        };
+        assert_eq!(user.validate(), Ok(()));
+        let user = NewUser {
+            id: Some(1),
+            name: "a".repeat(MAX_NAME_LEN + 1),
+        };
+        assert_eq!(
+            user.validate(),
+            Err(vec![FieldError::new(
+                "name",
+                "must be at most 64 characters"
+            )])
+        );
+    }
+
+    async fn panics() -> HttpResponse {
+        panic!("boom")
+    }
+
+    #[actix_web::test]
+    async fn test_error_responses() {
+        let db = web::Data::new(UserDB::new());
+        db.insert("Alice".to_string());
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(json_errors))
+                .wrap(from_fn(log_requests))
+                .app_data(db.clone())
+                .configure(config)
+                .route("/panic", web::get().to(panics)),
+        )
+        .await;
+
+        // 422：列出所有不合法的字段，不创建用户
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(serde_json::json!({ "id": 0, "name": "" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let error = assert_error(
+            resp,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "unprocessable_entity",
+        )
+        .await;
+        let fields: Vec<FieldError> = serde_json::from_value(error["fields"].clone()).unwrap();
+        assert_eq!(
+            fields.iter().map(|f| f.field.as_str()).collect::<Vec<_>>(),
+            vec!["id", "name"]
+        );
+        assert_eq!(db.users.lock().unwrap().len(), 1);
+
+        let req = test::TestRequest::put()
+            .uri("/users/1")
+            .set_json(serde_json::json!({ "name": "a".repeat(MAX_NAME_LEN + 1) }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let error = assert_error(
+            resp,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "unprocessable_entity",
+        )
+        .await;
+        assert_eq!(error["fields"][0]["field"], "name");
+
+        // 400：请求体不是合法的 JSON、缺少字段
+        for body in ["{", r#"{"id": 1}"#] {
+            let req = test::TestRequest::post()
+                .uri("/users")
+                .insert_header((header::CONTENT_TYPE, "application/json"))
+                .set_payload(body)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_error(resp, StatusCode::BAD_REQUEST, "bad_request").await;
+        }
+
+        // 404：用户不存在、路由不存在、路径参数无法解析
+        let req = test::TestRequest::get().uri("/users/9").to_request();
+        let resp = test::call_service(&app, req).await;
+        let error = assert_error(resp, StatusCode::NOT_FOUND, "not_found").await;
+        assert_eq!(error["message"], "User 9 not found");
+        for uri in ["/nope", "/users/abc"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_error(resp, StatusCode::NOT_FOUND, "not_found").await;
+        }
+
+        // 409
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(serde_json::json!({ "id": 1, "name": "Mallory" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_error(resp, StatusCode::CONFLICT, "conflict").await;
+
+        // 500：panic 的内容不会返回给客户端，之后的请求不受影响。
+        // 错误由 actix 转换成响应，这里手动转换
+        let req = test::TestRequest::get().uri("/panic").to_request();
+        let err = test::try_call_service(&app, req).await.err().unwrap();
+        let resp = ServiceResponse::new(
+            test::TestRequest::default().to_http_request(),
+            err.error_response(),
+        );
+        let error = assert_error(
+            resp,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_server_error",
+        )
+        .await;
+        assert_eq!(error["message"], "internal server error");
+        let req = test::TestRequest::get().uri("/users/1").to_request();
+        let user: User = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(user.name, "Alice");
+    }
+
+    #[actix_web::test]
+    async fn test_user_flow() {
+        let db = web::Data::new(UserDB::new());
+        db.insert("Alice".to_string());
+        let app = test::init_service(App::new().app_data(db.clone()).configure(config)).await;
+
+        // 客户端传入的新 id 被忽略，由服务端分配
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(serde_json::json!({ "id": 100, "name": "Bob" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), "/users/2");
+        let user: User = test::read_body_json(resp).await;
+        assert_eq!(
+            user,
+            User {
+                id: 2,
+                name: "Bob".to_string()
+            }
+        );
+
+        // 传入已存在的 id 返回 409，不覆盖原来的用户
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(serde_json::json!({ "id": 1, "name": "Mallory" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = test::TestRequest::put()
+            .uri("/users/2")
+            .set_json(serde_json::json!({ "name": "Bobby" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get().uri("/users/2").to_request();
+        let user: User = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(user.name, "Bobby");
+        let req = test::TestRequest::get().uri("/users/1").to_request();
+        let user: User = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(user.name, "Alice");
+
+        let req = test::TestRequest::delete().uri("/users/2").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = test::TestRequest::delete().uri("/users/2").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::get().uri("/users/2").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_update_missing_user() {
+        let db = web::Data::new(UserDB::new());
+        let app = test::init_service(App::new().app_data(db).configure(config)).await;
+
+        let req = test::TestRequest::put()
+            .uri("/users/1")
+            .set_json(serde_json::json!({ "name": "Bob" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // 删除后分配的 id 不会重复
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(serde_json::json!({ "name": "Carol" }))
+            .to_request();
+        let user: User = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(user.id, 1);
+        let req = test::TestRequest::delete().uri("/users/1").to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(serde_json::json!({ "name": "Dave" }))
+            .to_request();
+        let user: User = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(user.id, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_list_users() {
+        let db = web::Data::new(UserDB::new());
+        for name in ["Alice", "bob", "Carol", "BOBBY", "Dave", "Bobo"] {
+            db.insert(name.to_string());
+        }
+        let app = test::init_service(App::new().app_data(db).configure(config)).await;
+
+        let req = test::TestRequest::get().uri("/users").to_request();
+        let page: UserPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page.total, 6);
+        let ids = page.items.iter().map(|user| user.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5, 6]);
+
+        // 过滤和分页一起使用
+        let req = test::TestRequest::get()
+            .uri("/users?name_contains=Bob&offset=1&limit=1")
+            .to_request();
+        let page: UserPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            page,
+            UserPage {
+                total: 3,
+                items: vec![User {
+                    id: 4,
+                    name: "BOBBY".to_string()
+                }],
+            }
+        );
+
+        // 超出范围时返回空页
+        let req = test::TestRequest::get()
+            .uri("/users?name_contains=bob&offset=3")
+            .to_request();
+        let page: UserPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page.total, 3);
+        assert!(page.items.is_empty());
+        let req = test::TestRequest::get()
+            .uri("/users?name_contains=zed")
+            .to_request();
+        let page: UserPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((page.total, page.items.len()), (0, 0));
+    }
+
+    #[actix_web::test]
+    async fn test_list_users_invalid_query() {
+        let db = web::Data::new(UserDB::new());
+        let app = test::init_service(App::new().app_data(db).configure(config)).await;
+
+        for uri in ["/users?limit=-1", "/users?limit=1000", "/users?offset=abc"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_error(resp, StatusCode::BAD_REQUEST, "bad_request").await;
+        }
+    }
+}
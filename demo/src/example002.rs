@@ -1,6 +1,8 @@
 struct WordIterator<'s> {
     position: usize,
     string: &'s str,
+    // 为 true 时标点也作为分隔符，"brillig," 得到 "brillig"
+    split_punctuation: bool,
 }
 
 impl<'s> WordIterator<'s> {
@@ -8,18 +10,40 @@ impl<'s> WordIterator<'s> {
         WordIterator {
             position: 0,
             string,
+            split_punctuation: false,
+        }
+    }
+
+    fn with_punctuation(string: &'s str) -> WordIterator<'s> {
+        WordIterator {
+            split_punctuation: true,
+            ..WordIterator::new(string)
         }
     }
 
     fn next_word(&mut self) -> Option<&str> {
-        let start_of_word = &self.string[self.position..];
-        let index_of_next_space = start_of_word.find(' ').unwrap_or(start_of_word.len());
-        if start_of_word.len() != 0 {
-            self.position += index_of_next_space + 1;
-            return Some(&start_of_word[..index_of_next_space]);
-        } else {
+        self.next()
+    }
+
+    fn is_separator(&self, c: char) -> bool {
+        c.is_whitespace() || (self.split_punctuation && c.is_ascii_punctuation())
+    }
+}
+
+impl<'s> Iterator for WordIterator<'s> {
+    type Item = &'s str;
+
+    fn next(&mut self) -> Option<&'s str> {
+        let rest = &self.string[self.position..];
+        // 跳过连续的分隔符，只剩分隔符时结束
+        let Some(start) = rest.find(|c| !self.is_separator(c)) else {
+            self.position = self.string.len();
             return None;
-        }
+        };
+        let word = &rest[start..];
+        let len = word.find(|c| self.is_separator(c)).unwrap_or(word.len());
+        self.position += start + len;
+        Some(&word[..len])
     }
 }
 
@@ -39,4 +63,66 @@ mod tests {
         let word_b = word_iterator.next_word();
         assert_eq!(word_b, Some("brillig,"));
     }
+
+    #[test]
+    fn test_word_iterator_whitespace() {
+        let words: Vec<&str> = WordIterator::new("  Did  gyre\tand\n gimble   ").collect();
+        assert_eq!(words, vec!["Did", "gyre", "and", "gimble"]);
+
+        let mut word_iterator = WordIterator::new("   ");
+        assert_eq!(word_iterator.next(), None);
+        assert_eq!(word_iterator.next(), None);
+        assert_eq!(WordIterator::new("").next(), None);
+
+        let mut count = 0;
+        for word in WordIterator::new("中文 和  English") {
+            assert!(!word.is_empty());
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_word_iterator_punctuation() {
+        let words: Vec<&str> =
+            WordIterator::with_punctuation("Twas brillig, and the slithy toves // Did gyre.")
+                .collect();
+        assert_eq!(
+            words,
+            vec![
+                "Twas", "brillig", "and", "the", "slithy", "toves", "Did", "gyre"
+            ]
+        );
+    }
+
+    // 随机生成 ASCII 字符串，和标准库的切分结果对比
+    #[test]
+    fn test_word_iterator_random() {
+        let alphabet = b"ab Z9 \t\n,.;!-'";
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next_random = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for _ in 0..1000 {
+            let len = (next_random() % 24) as usize;
+            let text: String = (0..len)
+                .map(|_| alphabet[(next_random() % alphabet.len() as u64) as usize] as char)
+                .collect();
+
+            let words: Vec<&str> = WordIterator::new(&text).collect();
+            let expected: Vec<&str> = text.split_whitespace().collect();
+            assert_eq!(words, expected, "{:?}", text);
+
+            let words: Vec<&str> = WordIterator::with_punctuation(&text).collect();
+            let expected: Vec<&str> = text
+                .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+                .filter(|word| !word.is_empty())
+                .collect();
+            assert_eq!(words, expected, "{:?}", text);
+        }
+    }
 }
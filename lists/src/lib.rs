@@ -4,6 +4,7 @@ pub mod my1;
 pub mod my2;
 pub mod second;
 pub mod third;
+pub mod third_arc;
 pub mod third1;
 pub mod unsafe_code;
 pub mod unsafe_list;
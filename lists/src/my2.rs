@@ -17,8 +17,46 @@ type Link<T> = *mut Node<T>;
 pub struct DoublyLinkedList<T> {
     head: Link<T>,
     tail: Link<T>,
+    // 所有增删节点的地方（包括 CursorMut）都要同步维护
+    len: usize,
 }
 
+/// 裸指针默认既不是 Send 也不是 Sync，这里手动实现。
+///
+/// 链表独占所有节点：节点只通过 `Box::into_raw` 分配、`Box::from_raw` 释放，
+/// 裸指针只在链表内部（以及借用了链表的 Iter、IterMut、CursorMut）中使用，不会被共享到链表之外，
+/// 所以链表的所有权语义和 `Vec<T>`/`Box<T>` 相同：
+///
+/// - Send：把链表移动到另一个线程，等于把所有 T 移动过去，要求 T: Send；
+/// - Sync：通过 `&DoublyLinkedList<T>` 只能得到 `&T`（iter、front、back），
+///   修改都需要 `&mut self`，所以多个线程共享 `&DoublyLinkedList<T>` 等于共享 `&T`，要求 T: Sync。
+///
+/// 元素不满足条件时无法编译，比如 `Rc` 不能跨线程移动：
+///
+/// ```compile_fail,E0277
+/// use std::rc::Rc;
+/// use lists::my2::DoublyLinkedList;
+///
+/// let mut list = DoublyLinkedList::new();
+/// list.push_back(Rc::new(1));
+/// std::thread::spawn(move || drop(list));
+/// ```
+///
+/// `Cell` 不能在线程间共享：
+///
+/// ```compile_fail,E0277
+/// use std::cell::Cell;
+/// use lists::my2::DoublyLinkedList;
+///
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<DoublyLinkedList<Cell<i32>>>();
+/// ```
+///
+/// Iter、IterMut 和 CursorMut 仍然包含裸指针，保持 !Send 和 !Sync。
+unsafe impl<T: Send> Send for DoublyLinkedList<T> {}
+
+unsafe impl<T: Sync> Sync for DoublyLinkedList<T> {}
+
 struct Node<T> {
     elem: T,
     next: Link<T>,
@@ -30,9 +68,39 @@ impl<T> DoublyLinkedList<T> {
         Self {
             head: null_mut(),
             tail: null_mut(),
+            len: 0,
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        unsafe { self.head.as_ref().map(|node| &node.elem) }
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.head.as_mut().map(|node| &mut node.elem) }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        unsafe { self.tail.as_ref().map(|node| &node.elem) }
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.tail.as_mut().map(|node| &mut node.elem) }
+    }
+
+    // 从前往后依次 drop 所有元素，之后链表可以继续使用
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
     /*
     Box::new(...) 会在堆上创建一个节点；
     Box::into_raw(...) 会转成裸指针，我们负责后续手动回收；
@@ -56,6 +124,7 @@ impl<T> DoublyLinkedList<T> {
 
             self.head = node;
         }
+        self.len += 1;
     }
 
     pub fn push_back(&mut self, elem: T) {
@@ -74,6 +143,7 @@ impl<T> DoublyLinkedList<T> {
 
             self.tail = node;
         }
+        self.len += 1;
     }
 
     /*
@@ -94,6 +164,7 @@ impl<T> DoublyLinkedList<T> {
                 self.tail = null_mut();
             }
 
+            self.len -= 1;
             Some(boxed.elem)
         }
     }
@@ -110,6 +181,133 @@ impl<T> DoublyLinkedList<T> {
                 self.head = null_mut();
             }
 
+            self.len -= 1;
+            Some(boxed.elem)
+        }
+    }
+}
+
+/*
+CursorMut 指向链表中的一个节点，可以前后移动，并在当前位置插入和删除。
+cur 为空指针时指向一个“幽灵”位置，它位于 tail 之后、head 之前：
+    在幽灵位置 move_next 回到 head，move_prev 回到 tail；
+    insert_before 插入到末尾，insert_after 插入到开头。
+CursorMut 持有链表的 &mut，所以使用游标期间不能再通过其他方式访问链表。
+ */
+pub struct CursorMut<'a, T> {
+    cur: Link<T>,
+    list: &'a mut DoublyLinkedList<T>,
+}
+
+impl<T> DoublyLinkedList<T> {
+    // 返回指向第一个元素的游标，链表为空时指向幽灵位置
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            cur: self.head,
+            list: self,
+        }
+    }
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    pub fn move_next(&mut self) {
+        unsafe {
+            self.cur = if self.cur.is_null() {
+                self.list.head
+            } else {
+                (*self.cur).next
+            };
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        unsafe {
+            self.cur = if self.cur.is_null() {
+                self.list.tail
+            } else {
+                (*self.cur).prev
+            };
+        }
+    }
+
+    // 返回的引用借用了游标，在引用失效前不能移动游标或修改链表
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe { self.cur.as_mut().map(|node| &mut node.elem) }
+    }
+
+    // 在当前节点之前插入，游标仍然指向当前节点
+    pub fn insert_before(&mut self, elem: T) {
+        if self.cur.is_null() {
+            self.list.push_back(elem);
+            return;
+        }
+
+        unsafe {
+            let prev = (*self.cur).prev;
+            let node = Box::into_raw(Box::new(Node {
+                elem,
+                next: self.cur,
+                prev,
+            }));
+
+            (*self.cur).prev = node;
+            // 当前节点是 head 时，新节点成为 head
+            if prev.is_null() {
+                self.list.head = node;
+            } else {
+                (*prev).next = node;
+            }
+        }
+        self.list.len += 1;
+    }
+
+    // 在当前节点之后插入，游标仍然指向当前节点
+    pub fn insert_after(&mut self, elem: T) {
+        if self.cur.is_null() {
+            self.list.push_front(elem);
+            return;
+        }
+
+        unsafe {
+            let next = (*self.cur).next;
+            let node = Box::into_raw(Box::new(Node {
+                elem,
+                next,
+                prev: self.cur,
+            }));
+
+            (*self.cur).next = node;
+            // 当前节点是 tail 时，新节点成为 tail
+            if next.is_null() {
+                self.list.tail = node;
+            } else {
+                (*next).prev = node;
+            }
+        }
+        self.list.len += 1;
+    }
+
+    // 删除当前节点并返回它的元素，游标移动到下一个节点
+    // 删除的是唯一的节点时，head 和 tail 都变为空，游标指向幽灵位置
+    pub fn remove_current(&mut self) -> Option<T> {
+        unsafe {
+            let node = NonNull::new(self.cur)?;
+            let boxed = Box::from_raw(node.as_ptr());
+
+            if boxed.prev.is_null() {
+                self.list.head = boxed.next;
+            } else {
+                (*boxed.prev).next = boxed.next;
+            }
+
+            if boxed.next.is_null() {
+                self.list.tail = boxed.prev;
+            } else {
+                (*boxed.next).prev = boxed.prev;
+            }
+
+            self.cur = boxed.next;
+            self.list.len -= 1;
             Some(boxed.elem)
         }
     }
@@ -117,7 +315,7 @@ impl<T> DoublyLinkedList<T> {
 
 impl<T> Drop for DoublyLinkedList<T> {
     fn drop(&mut self) {
-        unsafe { while let Some(_) = self.pop_front() {} }
+        self.clear();
     }
 }
 
@@ -186,6 +384,9 @@ impl<'a, T> Iterator for IterMut<'a, T> {
 
 #[cfg(test)]
 mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
     use super::DoublyLinkedList;
 
     #[test]
@@ -219,4 +420,273 @@ mod tests {
         let remaining_values: Vec<_> = list.iter().collect();
         assert_eq!(remaining_values, vec![&12, &13]);
     }
+
+    // 检查 head/tail、len 以及每个节点的 prev/next 是否一致，返回从前往后的元素
+    fn check_links<T>(list: &DoublyLinkedList<T>) -> Vec<&T> {
+        let mut forward = Vec::new();
+        unsafe {
+            let mut prev = std::ptr::null_mut();
+            let mut cur = list.head;
+            while !cur.is_null() {
+                assert_eq!((*cur).prev, prev);
+                forward.push(&(*cur).elem);
+                prev = cur;
+                cur = (*cur).next;
+            }
+            assert_eq!(list.tail, prev);
+        }
+        assert_eq!(list.len(), forward.len());
+        assert_eq!(list.is_empty(), forward.is_empty());
+        forward
+    }
+
+    #[test]
+    fn test_len_and_accessors() {
+        let mut list = DoublyLinkedList::new();
+        assert_eq!((list.len(), list.is_empty()), (0, true));
+        assert_eq!((list.front(), list.back()), (None, None));
+        assert_eq!(list.front_mut(), None);
+        assert_eq!(list.back_mut(), None);
+
+        list.push_back(2);
+        assert_eq!((list.front(), list.back()), (Some(&2), Some(&2)));
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!((list.len(), list.is_empty()), (3, false));
+        assert_eq!((list.front(), list.back()), (Some(&1), Some(&3)));
+
+        *list.front_mut().unwrap() = 10;
+        *list.back_mut().unwrap() *= 10;
+        assert_eq!(check_links(&list), vec![&10, &2, &30]);
+
+        assert_eq!(list.pop_back(), Some(30));
+        assert_eq!(list.pop_front(), Some(10));
+        assert_eq!(list.len(), 1);
+        list.clear();
+        assert_eq!(list.len(), 0);
+        assert_eq!((list.front(), list.back()), (None, None));
+        // clear 之后可以继续使用
+        list.push_front(4);
+        assert_eq!(check_links(&list), vec![&4]);
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<DoublyLinkedList<String>>();
+
+        let mut list = DoublyLinkedList::new();
+        for i in 0..100 {
+            list.push_back(i.to_string());
+        }
+
+        // 把链表移动到另一个线程修改，再移动回来
+        let mut list = std::thread::spawn(move || {
+            for elem in list.iter_mut() {
+                elem.push('!');
+            }
+            list.pop_front();
+            list
+        })
+        .join()
+        .unwrap();
+        assert_eq!(list.len(), 99);
+        assert_eq!(list.front().map(String::as_str), Some("1!"));
+
+        // 多个线程同时读取 &DoublyLinkedList
+        let list_ref = &list;
+        let lens = std::thread::scope(|s| {
+            let handles = (0..4)
+                .map(|_| s.spawn(move || list_ref.iter().map(String::len).sum::<usize>()))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert!(lens.iter().all(|&len| len == lens[0]));
+        list.push_back("end".to_string());
+        assert_eq!(check_links(&list).len(), 100);
+    }
+
+    #[test]
+    fn test_cursor_move() {
+        let mut list = DoublyLinkedList::<i32>::new();
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.remove_current(), None);
+
+        let mut list = DoublyLinkedList::new();
+        list.push_back(1);
+        list.push_back(2);
+        list.push_back(3);
+
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!(cursor.current(), Some(&mut 1));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 3));
+        // 越过 tail 到达幽灵位置，再回到 head
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 1));
+        cursor.move_prev();
+        assert_eq!(cursor.current(), None);
+        cursor.move_prev();
+        *cursor.current().unwrap() *= 10;
+        assert_eq!(check_links(&list), vec![&1, &2, &30]);
+    }
+
+    #[test]
+    fn test_cursor_insert() {
+        let mut list = DoublyLinkedList::new();
+        let mut cursor = list.cursor_front_mut();
+        // 空链表中在幽灵位置插入
+        cursor.insert_before(2);
+        cursor.insert_after(1);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(check_links(&list), vec![&1, &2]);
+
+        let mut cursor = list.cursor_front_mut();
+        // 在 head 之前插入
+        cursor.insert_before(0);
+        assert_eq!(cursor.current(), Some(&mut 1));
+        cursor.insert_after(15);
+        cursor.move_next();
+        cursor.move_next();
+        // 在 tail 之后插入
+        cursor.insert_after(3);
+        assert_eq!(cursor.current(), Some(&mut 2));
+        cursor.insert_before(17);
+        assert_eq!(check_links(&list), vec![&0, &1, &15, &17, &2, &3]);
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_front(), Some(0));
+    }
+
+    #[test]
+    fn test_cursor_remove() {
+        let mut list = DoublyLinkedList::new();
+        list.push_back(1);
+
+        // 删除唯一的元素
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.remove_current(), None);
+        assert!(list.head.is_null() && list.tail.is_null());
+        list.push_front(2);
+        assert_eq!(check_links(&list), vec![&2]);
+
+        for i in 3..=5 {
+            list.push_back(i);
+        }
+        let mut cursor = list.cursor_front_mut();
+        // 删除 head，游标移动到下一个节点
+        assert_eq!(cursor.remove_current(), Some(2));
+        assert_eq!(cursor.current(), Some(&mut 3));
+        cursor.move_next();
+        cursor.move_next();
+        // 删除 tail，游标移动到幽灵位置
+        assert_eq!(cursor.remove_current(), Some(5));
+        assert_eq!(cursor.current(), None);
+        cursor.move_prev();
+        cursor.move_prev();
+        // 删除中间的节点
+        cursor.insert_after(7);
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(7));
+        assert_eq!(cursor.current(), Some(&mut 4));
+        assert_eq!(check_links(&list), vec![&3, &4]);
+    }
+
+    // 被 drop 时计数，用于检查是否有泄漏或重复释放
+    struct DropCounter(u32, Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+
+    // 被 drop 时记录编号，用于检查 drop 的顺序和次数
+    struct DropLog(u32, Rc<RefCell<Vec<u32>>>);
+
+    impl Drop for DropLog {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    #[test]
+    fn test_clear_drop() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut list = DoublyLinkedList::new();
+        for i in 1..=3 {
+            list.push_back(DropLog(i, log.clone()));
+        }
+        list.push_front(DropLog(0, log.clone()));
+        let mut cursor = list.cursor_front_mut();
+        cursor.move_next();
+        cursor.insert_after(DropLog(10, log.clone()));
+
+        // 每个元素从前往后恰好 drop 一次
+        list.clear();
+        assert_eq!(*log.borrow(), vec![0, 1, 10, 2, 3]);
+        assert!(list.is_empty());
+        list.clear();
+        assert_eq!(log.borrow().len(), 5);
+
+        list.push_back(DropLog(4, log.clone()));
+        drop(list);
+        assert_eq!(*log.borrow(), vec![0, 1, 10, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_cursor_drop() {
+        let drops = Rc::new(Cell::new(0));
+        let mut created = 0;
+        let mut removed = 0;
+        let mut seed = 1u32;
+        let mut list = DoublyLinkedList::new();
+        let ops = if cfg!(miri) { 200 } else { 5000 };
+
+        {
+            let mut cursor = list.cursor_front_mut();
+            for _ in 0..ops {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                match (seed >> 16) % 6 {
+                    0 => cursor.move_next(),
+                    1 => cursor.move_prev(),
+                    2 => {
+                        cursor.insert_before(DropCounter(created, drops.clone()));
+                        created += 1;
+                    }
+                    3 => {
+                        cursor.insert_after(DropCounter(created, drops.clone()));
+                        created += 1;
+                    }
+                    4 => {
+                        if let Some(elem) = cursor.remove_current() {
+                            drop(elem);
+                            removed += 1;
+                        }
+                    }
+                    _ => {
+                        if let Some(elem) = cursor.current() {
+                            elem.0 += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        assert_eq!(drops.get(), removed);
+        let remaining = check_links(&list).len();
+        assert_eq!(remaining + removed, created as usize);
+        drop(list);
+        assert_eq!(drops.get(), created as usize);
+    }
 }
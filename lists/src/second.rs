@@ -7,6 +7,8 @@ map 会对 Some(x) 中的值进行映射，最终返回一个新的 Some(y) 值
 
 */
 
+use std::fmt;
+
 pub struct List<T> {
     head: Link<T>,
 }
@@ -51,6 +53,35 @@ impl<T> List<T> {
     pub fn peek_mut(&mut self) -> Option<&mut T> {
         self.head.as_mut().map(|node| &mut node.elem)
     }
+
+    // 把 other 的所有节点移动到 self 的末尾，other 变为空链表
+    // 只需要遍历一次 self 找到末尾的 None，然后把 other.head 接上去，不需要克隆元素
+    pub fn append(&mut self, other: &mut List<T>) {
+        let mut link = &mut self.head;
+        while let Some(node) = link {
+            link = &mut node.next;
+        }
+        *link = other.head.take();
+    }
+
+    // 保留前 at 个元素，返回剩下的元素组成的链表
+    // at 超过链表长度时返回空链表
+    pub fn split_off(&mut self, at: usize) -> List<T> {
+        if at == 0 {
+            return List {
+                head: self.head.take(),
+            };
+        }
+
+        // 找到第 at 个节点，从它的 next 处断开
+        let mut node = self.head.as_deref_mut();
+        for _ in 1..at {
+            node = node.and_then(|node| node.next.as_deref_mut());
+        }
+        List {
+            head: node.and_then(|node| node.next.take()),
+        }
+    }
 }
 
 impl<T> Drop for List<T> {
@@ -62,10 +93,58 @@ impl<T> Drop for List<T> {
     }
 }
 
+// 从迭代器构造链表，元素顺序和迭代器一致（而不是 push 的逆序）
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = List::new();
+        list.extend(iter);
+        list
+    }
+}
+
+// 和 Vec 一样追加到末尾：先找到末尾的 None，然后依次接上新节点，
+// link 始终指向最后一个节点的 next，不需要反转
+impl<T> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut link = &mut self.head;
+        while let Some(node) = link {
+            link = &mut node.next;
+        }
+        for elem in iter {
+            let node = link.insert(Box::new(Node { elem, next: None }));
+            link = &mut node.next;
+        }
+    }
+}
+
+// 格式和 Vec 一样：[1, 2, 3]
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+// 逐个克隆元素，通过 FromIterator 保持原来的顺序
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
 pub struct IntoIter<T>(List<T>);
 
-impl<T> List<T> {
-    pub fn into_iter(self) -> IntoIter<T> {
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
         IntoIter(self)
     }
 }
@@ -97,6 +176,14 @@ impl<T> List<T> {
     }
 }
 
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
 impl<'a, T> Iterator for Iter<'a, T> {
     type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
@@ -122,6 +209,14 @@ impl<T> List<T> {
     }
 }
 
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
 impl<'a, T> Iterator for IterMut<'a, T> {
     type Item = &'a mut T;
     fn next(&mut self) -> Option<Self::Item> {
@@ -137,6 +232,9 @@ impl<'a, T> Iterator for IterMut<'a, T> {
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
     use super::*;
 
     #[test]
@@ -201,6 +299,183 @@ mod tests {
         assert_eq!(iter.next(), Some(&mut 2));
         assert_eq!(iter.next(), Some(&mut 1));
     }
+
+    fn from_vec<T>(elems: Vec<T>) -> List<T> {
+        let mut list = List::new();
+        for elem in elems.into_iter().rev() {
+            list.push(elem);
+        }
+        list
+    }
+
+    #[test]
+    fn append() {
+        let mut list = from_vec(vec![1, 2]);
+        let mut other = from_vec(vec![3, 4, 5]);
+        list.append(&mut other);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2, &3, &4, &5]);
+        assert_eq!(other.peek(), None);
+
+        // 两边为空的情况
+        list.append(&mut other);
+        assert_eq!(list.iter().count(), 5);
+        let mut empty = List::new();
+        empty.append(&mut list);
+        assert_eq!(empty.into_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(list.pop(), None);
+
+        // 合并后的链表仍然可以正常修改
+        let mut list = from_vec(vec![1]);
+        list.append(&mut from_vec(vec![2]));
+        list.push(0);
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn split_off() {
+        let mut list = from_vec(vec![1, 2, 3, 4, 5]);
+        let rest = list.split_off(2);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2]);
+        assert_eq!(rest.iter().collect::<Vec<_>>(), vec![&3, &4, &5]);
+
+        // at 为 0 时取走全部元素
+        let mut all = list.split_off(0);
+        assert_eq!(list.peek(), None);
+        assert_eq!(all.iter().collect::<Vec<_>>(), vec![&1, &2]);
+
+        // at 等于或超过长度时返回空链表
+        assert_eq!(all.split_off(2).peek(), None);
+        assert_eq!(all.split_off(10).peek(), None);
+        assert_eq!(List::<i32>::new().split_off(1).peek(), None);
+        assert_eq!(all.iter().collect::<Vec<_>>(), vec![&1, &2]);
+
+        all.append(&mut from_vec(vec![3]));
+        assert_eq!(all.split_off(1).into_iter().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(all.into_iter().collect::<Vec<_>>(), vec![1]);
+    }
+
+    // 被 drop 时计数，用于检查是否重复释放或泄漏
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn append_split_off_drop() {
+        let drops = Rc::new(Cell::new(0));
+        let new_list = |n: usize| from_vec((0..n).map(|_| DropCounter(drops.clone())).collect());
+
+        let mut list = new_list(3);
+        let mut other = new_list(4);
+        list.append(&mut other);
+        drop(other);
+        assert_eq!(drops.get(), 0);
+
+        let rest = list.split_off(5);
+        assert_eq!(drops.get(), 0);
+        drop(rest);
+        assert_eq!(drops.get(), 2);
+        let mut none = list.split_off(10);
+        none.append(&mut new_list(1));
+        drop(none);
+        assert_eq!(drops.get(), 3);
+        drop(list);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn from_iter() {
+        let list = List::from_iter([1, 2, 3]);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2, &3]);
+        let list: List<i32> = (1..=3).collect();
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let mut list = std::iter::empty::<i32>().collect::<List<_>>();
+        assert_eq!(list.peek(), None);
+        // 构造后可以继续 push 和 pop
+        list.push(1);
+        assert_eq!(list.pop(), Some(1));
+    }
+
+    #[test]
+    fn extend() {
+        let mut list = List::new();
+        list.extend(vec![1, 2]);
+        list.extend(3..=4);
+        list.extend(Vec::new());
+        list.push(0);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&0, &1, &2, &3, &4]);
+        assert_eq!(list.split_off(3).into_iter().collect::<Vec<_>>(), vec![3, 4]);
+        list.extend([5]);
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), vec![0, 1, 2, 5]);
+    }
+
+    #[test]
+    fn into_iterator() {
+        let mut list: List<i32> = [1, 2, 3].into_iter().collect();
+
+        let mut sum = 0;
+        for elem in &list {
+            sum += elem;
+        }
+        assert_eq!(sum, 6);
+
+        for elem in &mut list {
+            *elem *= 10;
+        }
+
+        let mut elems = Vec::new();
+        for elem in list {
+            elems.push(elem);
+        }
+        assert_eq!(elems, vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn debug() {
+        let list: List<i32> = [1, 2, 3].into_iter().collect();
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+        assert_eq!(format!("{:?}", List::<i32>::new()), "[]");
+        let list: List<&str> = ["a"].into_iter().collect();
+        assert_eq!(format!("{:?}", list), "[\"a\"]");
+    }
+
+    #[test]
+    fn eq() {
+        // collect 得到的链表和手动逆序 push 的链表相等
+        let collected: List<i32> = [1, 2, 3].into_iter().collect();
+        let mut pushed = List::new();
+        pushed.push(3);
+        pushed.push(2);
+        pushed.push(1);
+        assert_eq!(collected, pushed);
+
+        pushed.push(0);
+        assert_ne!(collected, pushed);
+        pushed.pop();
+        *pushed.peek_mut().unwrap() = 10;
+        assert_ne!(collected, pushed);
+        assert_eq!(List::<i32>::new(), List::new());
+        assert_ne!(List::new(), collected);
+    }
+
+    #[test]
+    fn clone() {
+        let mut list: List<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        let mut cloned = list.clone();
+        assert_eq!(list, cloned);
+
+        // 修改其中一个不影响另一个
+        cloned.peek_mut().unwrap().push('!');
+        cloned.push("z".to_string());
+        list.pop();
+        assert_eq!(format!("{:?}", list), r#"["b", "c"]"#);
+        assert_eq!(format!("{:?}", cloned), r#"["z", "a!", "b", "c"]"#);
+        assert_eq!(List::<String>::new().clone(), List::new());
+    }
 }
 
 // run test: cargo test --test-threads=1 --lib -- --nocapture
@@ -2,6 +2,8 @@ use std::rc::Rc;
 
 pub struct List<T> {
     head: Link<T>,
+    // 链表长度，每个 List 各自保存，len() 不需要遍历
+    len: usize,
 }
 
 type Link<T> = Option<Rc<Node<T>>>;
@@ -13,7 +15,7 @@ struct Node<T> {
 
 impl<T> List<T> {
     pub fn new() -> Self {
-        List { head: None }
+        List { head: None, len: 0 }
     }
 
     /*
@@ -39,6 +41,7 @@ impl<T> List<T> {
                 elem,
                 next: self.head.clone(),
             })),
+            len: self.len + 1,
         }
     }
 
@@ -56,12 +59,21 @@ impl<T> List<T> {
                     如果是 Some(rc) ⇒ 克隆 Rc (引用计数+1) ⇒ 返回 Some(new_rc)
              */
             head: self.head.as_ref().and_then(|node| node.next.clone()),
+            len: self.len.saturating_sub(1),
         }
     }
 
     pub fn head(&self) -> Option<&T> {
         self.head.as_ref().map(|node| &node.elem)
     }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 }
 
 impl<T> Drop for List<T> {
@@ -116,6 +128,21 @@ mod tests {
         let list = list.tail();
         assert_eq!(list.head(), None);
     }
+
+    #[test]
+    fn len() {
+        let list = List::new();
+        assert_eq!(list.len(), 0);
+        assert!(list.is_empty());
+
+        let list3 = list.prepend(1).prepend(2).prepend(3);
+        let list2 = list3.tail();
+        assert_eq!((list3.len(), list2.len()), (3, 2));
+        // 共享节点的链表各自保存长度
+        let other = list2.prepend(4).prepend(5);
+        assert_eq!((list3.len(), other.len()), (3, 4));
+        assert_eq!(list.tail().tail().len(), 0);
+    }
 }
 
 pub struct Iter<'a, T> {
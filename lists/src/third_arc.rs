@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+/*
+和 third.rs 中的持久化链表相同，只是把 Rc 换成了 Arc：
+    Rc 的引用计数不是原子操作，所以 Rc 既不是 Send 也不是 Sync，不能在线程间共享；
+    Arc 使用原子操作维护引用计数，T: Send + Sync 时 ArcList<T> 可以发送到其他线程，
+    多个线程可以同时持有同一个快照，在上面 prepend 出各自的新链表。
+ */
+pub struct ArcList<T> {
+    head: Link<T>,
+    // 链表长度，每个 ArcList 各自保存，len() 不需要遍历
+    len: usize,
+}
+
+type Link<T> = Option<Arc<Node<T>>>;
+
+struct Node<T> {
+    elem: T,
+    next: Link<T>,
+}
+
+impl<T> ArcList<T> {
+    pub fn new() -> Self {
+        ArcList { head: None, len: 0 }
+    }
+
+    pub fn prepend(&self, elem: T) -> ArcList<T> {
+        ArcList {
+            head: Some(Arc::new(Node {
+                elem,
+                next: self.head.clone(),
+            })),
+            len: self.len + 1,
+        }
+    }
+
+    pub fn tail(&self) -> ArcList<T> {
+        ArcList {
+            head: self.head.as_ref().and_then(|node| node.next.clone()),
+            len: self.len.saturating_sub(1),
+        }
+    }
+
+    pub fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.elem)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T> Default for ArcList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for ArcList<T> {
+    // 逐个释放节点，避免递归 drop 长链表时栈溢出
+    // Arc::into_inner 只在最后一个持有者那里返回节点，其他线程同时释放同一个节点时
+    // 也保证有且只有一方继续往后释放，不会像 try_unwrap 那样双方都失败导致后面的节点递归 drop
+    fn drop(&mut self) {
+        let mut head = self.head.take();
+        while let Some(node) = head {
+            match Arc::into_inner(node) {
+                Some(mut node) => head = node.next.take(),
+                None => break,
+            }
+        }
+    }
+}
+
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+}
+
+impl<T> ArcList<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head.as_deref(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            &node.elem
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let list = ArcList::new();
+        assert_eq!(list.head(), None);
+        assert!(list.is_empty());
+
+        let list = list.prepend(1).prepend(2).prepend(3);
+        assert_eq!(list.head(), Some(&3));
+        assert_eq!(list.len(), 3);
+
+        let list = list.tail();
+        assert_eq!(list.head(), Some(&2));
+        assert_eq!(list.len(), 2);
+
+        let list = list.tail().tail();
+        assert_eq!(list.head(), None);
+        assert_eq!(list.len(), 0);
+        assert_eq!(list.tail().len(), 0);
+    }
+
+    #[test]
+    fn iter() {
+        let list = ArcList::new().prepend(1).prepend(2).prepend(3);
+
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn share_between_threads() {
+        let mut base = ArcList::new();
+        for i in 0..50_000 {
+            base = base.prepend(i);
+        }
+
+        // 每个线程在同一个快照上构建自己的链表，并在线程内释放
+        let lens = thread::scope(|s| {
+            let handles = (0..4)
+                .map(|t| {
+                    let base = &base;
+                    s.spawn(move || {
+                        let mut list = base.tail();
+                        for i in 0..50_000 {
+                            list = list.prepend(t * 100_000 + i);
+                        }
+                        assert_eq!(list.head(), Some(&(t * 100_000 + 49_999)));
+                        assert_eq!(list.iter().count(), list.len());
+                        list.len()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(lens, vec![99_999; 4]);
+
+        // 快照不受影响
+        assert_eq!(base.len(), 50_000);
+        assert_eq!(base.head(), Some(&49_999));
+
+        // 释放 10 万个节点的链表不会栈溢出
+        let mut long = base.tail();
+        for i in 0..50_001 {
+            long = long.prepend(i);
+        }
+        drop(base);
+        assert_eq!(long.len(), 100_000);
+        drop(long);
+    }
+}
@@ -1,50 +1,258 @@
+use std::env;
 use std::error::Error;
 use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use regex::{Regex, RegexBuilder};
 
 #[derive(Debug)]
 pub struct Config {
-    _query: String,
+    query: String,
     file_path: String,
+    // Set by -i or the IGNORE_CASE environment variable
+    ignore_case: bool,
+    // Set by -e, the query is a regular expression
+    regex: bool,
+    // file_path is a directory that is searched recursively
+    recursive: bool,
+    // Lines of context printed before and after each match, set by -B, -A and -C
+    before_context: usize,
+    after_context: usize,
+    // Set by -n, prefix every printed line with its line number
+    line_numbers: bool,
+    // Set by -c, print only the number of matching lines
+    count: bool,
+}
+
+// A line of a searched file with its 1-based line number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'a> {
+    pub line_no: usize,
+    pub line: &'a str,
 }
 
 impl Config {
     pub fn new(args: &[String]) -> Config {
         let query = args[0].clone();
         let file_path = args[1].clone();
+        let recursive = Path::new(&file_path).is_dir();
 
         Config {
-            _query: query,
+            query,
             file_path,
+            ignore_case: false,
+            regex: false,
+            recursive,
+            before_context: 0,
+            after_context: 0,
+            line_numbers: false,
+            count: false,
         }
     }
 
-    pub fn build(args: &[String]) -> Result<Config, &'static str> {
-        if args.len() < 2 {
-            return Err("not enough arguments");
+    // args[0] is the program name, flags may appear anywhere after it
+    pub fn build(args: &[String]) -> Result<Config, String> {
+        let mut ignore_case = env::var("IGNORE_CASE").is_ok();
+        let mut regex = false;
+        let mut line_numbers = false;
+        let mut count = false;
+        // -A and -B take precedence over -C whatever the order
+        let (mut before, mut after, mut context) = (None, None, None);
+        let mut positional = Vec::new();
+
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-i" => ignore_case = true,
+                "-e" => regex = true,
+                "-n" => line_numbers = true,
+                "-c" => count = true,
+                flag @ ("-A" | "-B" | "-C") => {
+                    let lines = args
+                        .next()
+                        .and_then(|lines| lines.parse::<usize>().ok())
+                        .ok_or_else(|| format!("flag {flag} expects a number of lines"))?;
+                    match flag {
+                        "-A" => after = Some(lines),
+                        "-B" => before = Some(lines),
+                        _ => context = Some(lines),
+                    }
+                }
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!(
+                        "unknown flag {flag}, expected -i, -e, -n, -c, -A, -B or -C"
+                    ));
+                }
+                _ => positional.push(arg.clone()),
+            }
         }
 
-        let query = args[0].clone();
-        let file_path = args[1].clone();
+        let mut positional = positional.into_iter();
+        let (Some(query), Some(file_path)) = (positional.next(), positional.next()) else {
+            return Err("not enough arguments, usage: minigrep [-i] [-e] [-n] [-c] [-A n] [-B n] [-C n] query file_path".to_string());
+        };
+        if let Some(arg) = positional.next() {
+            return Err(format!("unexpected argument {arg}"));
+        }
+
+        let recursive = Path::new(&file_path).is_dir();
 
         Ok(Config {
-            _query: query,
+            query,
             file_path,
+            ignore_case,
+            regex,
+            recursive,
+            before_context: before.or(context).unwrap_or(0),
+            after_context: after.or(context).unwrap_or(0),
+            line_numbers,
+            count,
         })
     }
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+// Returns whether any line matched
+pub fn run(config: Config) -> Result<bool, Box<dyn Error>> {
+    let searcher = Searcher::new(&config)?;
+    let mut out = io::stdout().lock();
+
+    if config.recursive {
+        return Ok(searcher.search_dir(Path::new(&config.file_path), &mut out)?);
+    }
+
     // let contents =
     //     fs::read_to_string(config.file_path).expect("Should have been able to read the file");
-    let contents = fs::read_to_string(config.file_path)?;
+    let contents = fs::read_to_string(&config.file_path)?;
+    Ok(searcher.print(&contents, None, &mut out)?)
+}
 
-    println!("write content is: {contents}");
+// Picks the search function for a Config
+struct Searcher<'c> {
+    config: &'c Config,
+    // Compiled once when the query is a regex
+    re: Option<Regex>,
+}
 
-    for line in search(&config._query, &contents) {
-        println!("{line}.");
+impl<'c> Searcher<'c> {
+    fn new(config: &'c Config) -> Result<Searcher<'c>, regex::Error> {
+        let re = if config.regex {
+            // -i makes the whole expression case-insensitive
+            let re = RegexBuilder::new(&config.query)
+                .case_insensitive(config.ignore_case)
+                .build()?;
+            Some(re)
+        } else {
+            None
+        };
+        Ok(Searcher { config, re })
     }
 
-    Ok(())
+    fn search<'a>(&self, contents: &'a str) -> Vec<Match<'a>> {
+        let results = match &self.re {
+            Some(re) => search_regex(re, contents),
+            None if self.config.ignore_case => {
+                search_case_insensitive(&self.config.query, contents)
+            }
+            None => search(&self.config.query, contents),
+        };
+        with_line_numbers(contents, &results)
+    }
+
+    // Print the matches in contents with the configured context, or just their count
+    // with -c. Lines are prefixed with path when searching a directory, matches use :
+    // as the separator and context lines use -. Returns whether any line matched.
+    fn print(&self, contents: &str, path: Option<&Path>, out: &mut impl Write) -> io::Result<bool> {
+        let matches = self.search(contents);
+        let prefix = path.map(|path| path.display().to_string());
+
+        if self.config.count {
+            match &prefix {
+                Some(prefix) => writeln!(out, "{prefix}:{}", matches.len())?,
+                None => writeln!(out, "{}", matches.len())?,
+            }
+            return Ok(!matches.is_empty());
+        }
+
+        let (before, after) = (self.config.before_context, self.config.after_context);
+        let mut matched = matches.iter().map(|m| m.line_no).peekable();
+        for (i, group) in context_groups(contents, &matches, before, after)
+            .iter()
+            .enumerate()
+        {
+            // Without context every group is a single match, like grep no separator is printed
+            if i > 0 && (before > 0 || after > 0) {
+                writeln!(out, "--")?;
+            }
+            for line in group {
+                let sep = match matched.next_if_eq(&line.line_no) {
+                    Some(_) => ':',
+                    None => '-',
+                };
+                if let Some(prefix) = &prefix {
+                    write!(out, "{prefix}{sep}")?;
+                }
+                if self.config.line_numbers {
+                    write!(out, "{}{sep}", line.line_no)?;
+                }
+                writeln!(out, "{}", line.line)?;
+            }
+        }
+
+        Ok(!matches.is_empty())
+    }
+
+    // Search every readable text file under dir, printing matches as path:line.
+    // Files that can't be read are reported to stderr and skipped.
+    fn search_dir(&self, dir: &Path, out: &mut impl Write) -> io::Result<bool> {
+        let mut entries =
+            match fs::read_dir(dir).and_then(|entries| entries.collect::<io::Result<Vec<_>>>()) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    eprintln!("{}: {e}", dir.display());
+                    return Ok(false);
+                }
+            };
+        entries.sort_by_key(|entry| entry.path());
+
+        let mut matched = false;
+        for entry in entries {
+            let path = entry.path();
+            let is_dir = match entry.file_type() {
+                Ok(file_type) => file_type.is_dir(),
+                Err(e) => {
+                    eprintln!("{}: {e}", path.display());
+                    continue;
+                }
+            };
+
+            if is_dir {
+                // Skip hidden directories such as .git
+                if !entry.file_name().to_string_lossy().starts_with('.') {
+                    matched |= self.search_dir(&path, out)?;
+                }
+            } else if path.is_file() {
+                let bytes = match fs::read(&path) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        eprintln!("{}: {e}", path.display());
+                        continue;
+                    }
+                };
+                // Skip binary files
+                if bytes.contains(&0) {
+                    continue;
+                }
+                let Ok(contents) = String::from_utf8(bytes) else {
+                    continue;
+                };
+
+                matched |= self.print(&contents, Some(&path), out)?;
+            }
+        }
+
+        Ok(matched)
+    }
 }
 
 pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
@@ -58,6 +266,75 @@ pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
     return items;
 }
 
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    let query = query.to_lowercase();
+    let mut items = Vec::new();
+
+    for line in contents.lines() {
+        if line.to_lowercase().contains(&query) {
+            items.push(line);
+        }
+    }
+    items
+}
+
+pub fn search_regex<'a>(re: &Regex, contents: &'a str) -> Vec<&'a str> {
+    contents.lines().filter(|line| re.is_match(line)).collect()
+}
+
+// Pair matched lines with their 1-based line numbers. The search functions return
+// slices of contents in order, so each match is found by its position in contents.
+pub fn with_line_numbers<'a>(contents: &'a str, matches: &[&'a str]) -> Vec<Match<'a>> {
+    let mut matches = matches.iter().peekable();
+    let mut items = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        if let Some(&&matched) = matches.peek()
+            && std::ptr::eq(matched, line)
+        {
+            items.push(Match {
+                line_no: index + 1,
+                line,
+            });
+            matches.next();
+        }
+    }
+    items
+}
+
+// Group matches with up to before and after lines of context around each. Groups whose
+// contexts overlap or touch are merged, so no line is printed twice.
+pub fn context_groups<'a>(
+    contents: &'a str,
+    matches: &[Match<'a>],
+    before: usize,
+    after: usize,
+) -> Vec<Vec<Match<'a>>> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for m in matches {
+        let start = m.line_no.saturating_sub(before).max(1);
+        let end = (m.line_no + after).min(lines.len());
+        match ranges.last_mut() {
+            Some((_, last_end)) if start <= *last_end + 1 => *last_end = end.max(*last_end),
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            (start..=end)
+                .map(|line_no| Match {
+                    line_no,
+                    line: lines[line_no - 1],
+                })
+                .collect()
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -72,4 +349,236 @@ Pick three.";
 
         assert_eq!(vec!["safe, fast, productive."], search(query, contents));
     }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        std::iter::once("minigrep")
+            .chain(args.iter().copied())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let query = "rUsT";
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+        assert_eq!(
+            vec!["Rust:", "Trust me."],
+            search_case_insensitive(query, contents)
+        );
+        assert_eq!(vec!["Trust me."], search("rust", contents));
+        assert_eq!(
+            vec!["École"],
+            search_case_insensitive("éCOLE", "École\nEcole")
+        );
+    }
+
+    #[test]
+    fn regex_search() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+        let re = Regex::new(r"^[A-Z]\w+:$|three").unwrap();
+        assert_eq!(vec!["Rust:", "Pick three."], search_regex(&re, contents));
+
+        // -i applies to the whole expression
+        let config = Config::build(&args(&["-e", "^rust|ME\\.$", "-i", "poem.txt"])).unwrap();
+        assert!(config.regex && config.ignore_case);
+        let re = RegexBuilder::new(&config.query)
+            .case_insensitive(config.ignore_case)
+            .build()
+            .unwrap();
+        assert_eq!(vec!["Rust:", "Trust me."], search_regex(&re, contents));
+
+        // Without -e the query is matched literally
+        assert!(search_case_insensitive("^rust", contents).is_empty());
+    }
+
+    #[test]
+    fn line_numbers() {
+        let contents = "one\ntwo\none again\n\none";
+        let results = search("one", contents);
+        assert_eq!(
+            vec![(1, "one"), (3, "one again"), (5, "one")],
+            with_line_numbers(contents, &results)
+                .into_iter()
+                .map(|m| (m.line_no, m.line))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    // Matches on lines 2, 4, 5 and 10 of 12
+    const CLUSTERED: &str = "\
+a
+needle 1
+b
+needle 2
+needle 3
+c
+d
+e
+f
+needle 4
+g
+h";
+
+    fn line_nos(groups: &[Vec<Match>]) -> Vec<Vec<usize>> {
+        groups
+            .iter()
+            .map(|group| group.iter().map(|m| m.line_no).collect())
+            .collect()
+    }
+
+    #[test]
+    fn context() {
+        let matches = with_line_numbers(CLUSTERED, &search("needle", CLUSTERED));
+        assert_eq!(
+            line_nos(&context_groups(CLUSTERED, &matches, 0, 0)),
+            vec![vec![2], vec![4, 5], vec![10]]
+        );
+        // Overlapping contexts are merged without repeating lines
+        assert_eq!(
+            line_nos(&context_groups(CLUSTERED, &matches, 1, 1)),
+            vec![vec![1, 2, 3, 4, 5, 6], vec![9, 10, 11]]
+        );
+        assert_eq!(
+            line_nos(&context_groups(CLUSTERED, &matches, 0, 2)),
+            vec![vec![2, 3, 4, 5, 6, 7], vec![10, 11, 12]]
+        );
+        // Adjacent groups are merged, context is clamped to the file
+        assert_eq!(
+            line_nos(&context_groups(CLUSTERED, &matches, 3, 0)),
+            vec![vec![1, 2, 3, 4, 5], vec![7, 8, 9, 10]]
+        );
+        assert_eq!(
+            line_nos(&context_groups(CLUSTERED, &matches, 4, 0)),
+            vec![(1..=10).collect::<Vec<_>>()]
+        );
+        assert_eq!(
+            line_nos(&context_groups(CLUSTERED, &matches, 2, 5)),
+            vec![(1..=12).collect::<Vec<_>>()]
+        );
+        assert_eq!(
+            context_groups(CLUSTERED, &matches, 0, 0)[1][0].line,
+            "needle 2"
+        );
+    }
+
+    fn print(flags: &[&str], contents: &str) -> String {
+        let mut all = flags.to_vec();
+        all.extend(["needle", "poem.txt"]);
+        let config = Config::build(&args(&all)).unwrap();
+        let mut out = Vec::new();
+        Searcher::new(&config)
+            .unwrap()
+            .print(contents, None, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn print_context() {
+        assert_eq!(
+            print(&[], CLUSTERED),
+            "needle 1\nneedle 2\nneedle 3\nneedle 4\n"
+        );
+        assert_eq!(
+            print(&["-n", "-A", "1"], CLUSTERED),
+            "2:needle 1\n3-b\n4:needle 2\n5:needle 3\n6-c\n--\n10:needle 4\n11-g\n"
+        );
+        assert_eq!(
+            print(&["-B", "1"], CLUSTERED),
+            "a\nneedle 1\nb\nneedle 2\nneedle 3\n--\nf\nneedle 4\n"
+        );
+        assert_eq!(print(&["-c"], CLUSTERED), "4\n");
+        assert_eq!(print(&["-c", "-C", "2"], "hay"), "0\n");
+    }
+
+    #[test]
+    fn build_config() {
+        let config = Config::build(&args(&["-i", "to", "poem.txt"])).unwrap();
+        assert_eq!(config.query, "to");
+        assert_eq!(config.file_path, "poem.txt");
+        assert!(config.ignore_case);
+        assert!(!config.regex);
+        assert!(!config.line_numbers && !config.count);
+        assert_eq!((config.before_context, config.after_context), (0, 0));
+
+        let config =
+            Config::build(&args(&["-C", "2", "-n", "to", "-A", "1", "-c", "poem.txt"])).unwrap();
+        assert_eq!(
+            (config.query.as_str(), config.file_path.as_str()),
+            ("to", "poem.txt")
+        );
+        assert_eq!((config.before_context, config.after_context), (2, 1));
+        assert!(config.line_numbers && config.count);
+
+        assert_eq!(
+            Config::build(&args(&["-x", "to", "poem.txt"])).unwrap_err(),
+            "unknown flag -x, expected -i, -e, -n, -c, -A, -B or -C"
+        );
+        assert_eq!(
+            Config::build(&args(&["to", "poem.txt", "-B"])).unwrap_err(),
+            "flag -B expects a number of lines"
+        );
+        assert!(Config::build(&args(&["-A", "x", "to", "poem.txt"])).is_err());
+        assert!(Config::build(&args(&["-e", "to"])).is_err());
+        assert_eq!(
+            Config::build(&args(&["to", "poem.txt", "extra"])).unwrap_err(),
+            "unexpected argument extra"
+        );
+    }
+
+    #[test]
+    fn search_dir_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("a.txt"), "needle one\nhay\n").unwrap();
+        fs::write(root.join("src/b.rs"), "hay\nhay\nNeedle two\n").unwrap();
+        fs::write(root.join("src/nested/c.md"), "needle three").unwrap();
+        fs::write(root.join(".git/config"), "needle hidden").unwrap();
+        fs::write(root.join("bin.dat"), b"needle\0binary").unwrap();
+        fs::write(root.join("latin1.txt"), b"needle \xff").unwrap();
+
+        let config = Config::build(&args(&["-i", "-n", "needle", root.to_str().unwrap()])).unwrap();
+        assert!(config.recursive);
+        let mut out = Vec::new();
+        let matched = Searcher::new(&config)
+            .unwrap()
+            .search_dir(root, &mut out)
+            .unwrap();
+        assert!(matched);
+
+        let root = root.display();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{root}/a.txt:1:needle one\n{root}/src/b.rs:3:Needle two\n{root}/src/nested/c.md:1:needle three\n"
+            )
+        );
+    }
+
+    #[test]
+    fn search_dir_no_match() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "hay").unwrap();
+        // A missing directory is reported but does not abort the search
+        let missing = dir.path().join("missing");
+
+        let config = Config::build(&args(&["needle", dir.path().to_str().unwrap()])).unwrap();
+        let searcher = Searcher::new(&config).unwrap();
+        let mut out = Vec::new();
+        assert!(!searcher.search_dir(dir.path(), &mut out).unwrap());
+        assert!(!searcher.search_dir(&missing, &mut out).unwrap());
+        assert!(out.is_empty());
+    }
 }
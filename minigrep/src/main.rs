@@ -12,8 +12,13 @@ fn main() {
 
     println!("Params: {:?}", config);
 
-    if let Err(e) = minigrep::run(config) {
-        println!("Application error: {e}");
-        process::exit(1);
+    match minigrep::run(config) {
+        Ok(true) => {}
+        // Nothing matched
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            println!("Application error: {e}");
+            process::exit(1);
+        }
     }
 }
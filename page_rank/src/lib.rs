@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+// PageRank 的参数
+#[derive(Debug, Clone, Copy)]
+pub struct PageRankOptions {
+    // 阻尼系数，沿着边跳转的概率
+    pub damping: f64,
+    // 最多迭代的次数
+    pub max_iterations: usize,
+    // 两次迭代之间所有分数变化的绝对值之和小于 epsilon 时提前结束，None 表示固定迭代 max_iterations 次
+    pub epsilon: Option<f64>,
+}
+
+impl Default for PageRankOptions {
+    fn default() -> Self {
+        Self {
+            damping: 0.85,
+            max_iterations: 100,
+            epsilon: Some(1e-10),
+        }
+    }
+}
+
+// 根据带权重的边 (from, to, weight) 计算每个节点的 PageRank，返回的分数之和为 1
+// 节点按照权重比例把分数分给指向的节点，重复的边权重相加；
+// 没有出边（或者出边权重都为 0）的节点把分数平均分给所有节点。
+pub fn pagerank_from_edges(
+    edges: &[(String, String, f64)],
+    opts: PageRankOptions,
+) -> HashMap<String, f64> {
+    assert!(
+        (0.0..=1.0).contains(&opts.damping),
+        "damping factor should be between 0 and 1"
+    );
+
+    // 构建图，节点的权重是名字，边的权重是边的权重
+    let mut graph = DiGraph::<&str, f64>::new();
+    let mut index = HashMap::<&str, NodeIndex>::new();
+    for (from, to, weight) in edges {
+        let from = *index.entry(from).or_insert_with(|| graph.add_node(from));
+        let to = *index.entry(to).or_insert_with(|| graph.add_node(to));
+        graph.add_edge(from, to, *weight);
+    }
+
+    let n = graph.node_count();
+    if n == 0 {
+        return HashMap::new();
+    }
+
+    let mut out_weights = vec![0.0; n];
+    for edge in graph.edge_references() {
+        out_weights[edge.source().index()] += edge.weight();
+    }
+
+    let d = opts.damping;
+    let mut ranks = vec![1.0 / n as f64; n];
+    for _ in 0..opts.max_iterations {
+        // 没有出边的节点的分数
+        let dangling: f64 = (0..n)
+            .filter(|&i| out_weights[i] <= 0.0)
+            .map(|i| ranks[i])
+            .sum();
+
+        let base = (1.0 - d) / n as f64 + d * dangling / n as f64;
+        let mut next = vec![base; n];
+        for edge in graph.edge_references() {
+            let from = edge.source().index();
+            if out_weights[from] > 0.0 {
+                next[edge.target().index()] += d * ranks[from] * edge.weight() / out_weights[from];
+            }
+        }
+
+        let delta: f64 = ranks.iter().zip(&next).map(|(a, b)| (a - b).abs()).sum();
+        ranks = next;
+        if opts.epsilon.is_some_and(|epsilon| delta < epsilon) {
+            break;
+        }
+    }
+
+    graph
+        .node_indices()
+        .map(|i| (graph[i].to_string(), ranks[i.index()]))
+        .collect()
+}
+
+// 读取 CSV 格式的边列表，每行为 from,to[,weight]，没有权重时为 1.0
+// 跳过空行、# 开头的注释，以及第一行的表头 from,to[,weight]
+pub fn from_csv_reader<R: BufRead>(reader: R) -> io::Result<Vec<(String, String, f64)>> {
+    let mut edges = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if i == 0 && fields[0].eq_ignore_ascii_case("from") {
+            continue;
+        }
+        let invalid = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", i + 1, message),
+            )
+        };
+
+        let weight = match fields.as_slice() {
+            [_, _] => 1.0,
+            [_, _, weight] => match weight.parse::<f64>() {
+                Ok(weight) if weight.is_finite() && weight >= 0.0 => weight,
+                _ => return Err(invalid(format!("invalid weight {:?}", weight))),
+            },
+            _ => {
+                return Err(invalid(format!(
+                    "expected from,to[,weight], got {:?}",
+                    line
+                )));
+            }
+        };
+        if fields[0].is_empty() || fields[1].is_empty() {
+            return Err(invalid("empty node name".to_string()));
+        }
+        edges.push((fields[0].to_string(), fields[1].to_string(), weight));
+    }
+    Ok(edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use petgraph::Graph;
+
+    fn edges(edges: &[(&str, &str)]) -> Vec<(String, String, f64)> {
+        edges
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string(), 1.0))
+            .collect()
+    }
+
+    fn assert_sum_to_one(scores: &HashMap<String, f64>) {
+        let sum: f64 = scores.values().sum();
+        assert!((sum - 1.0).abs() < 1e-9, "sum = {}", sum);
+    }
+
+    // petgraph 0.6 的实现对有边相连的节点省略了随机跳转的部分，
+    // 只有在每个节点地位相同的图上才和标准的 PageRank 完全一致，其他图上只比较排名
+    fn petgraph_scores(edges: &[(&str, &str)], names: &[&str]) -> Vec<f64> {
+        let mut graph = Graph::<(), ()>::new();
+        let nodes: Vec<_> = names.iter().map(|_| graph.add_node(())).collect();
+        let position = |name: &str| names.iter().position(|n| *n == name).unwrap();
+        for (from, to) in edges {
+            graph.add_edge(nodes[position(from)], nodes[position(to)], ());
+        }
+        petgraph::algo::page_rank(&graph, 0.85, 100)
+    }
+
+    #[test]
+    fn test_match_petgraph() {
+        let names = ["a", "b", "c", "d"];
+        let cycle = [("a", "b"), ("b", "c"), ("c", "d"), ("d", "a")];
+        let scores = pagerank_from_edges(&edges(&cycle), PageRankOptions::default());
+        assert_sum_to_one(&scores);
+        for (name, expected) in names.iter().zip(petgraph_scores(&cycle, &names)) {
+            assert!((scores[*name] - expected).abs() < 1e-9, "{}", name);
+        }
+
+        let graph = [("a", "b"), ("b", "a"), ("b", "c"), ("c", "a"), ("d", "a")];
+        let scores = pagerank_from_edges(&edges(&graph), PageRankOptions::default());
+        assert_sum_to_one(&scores);
+        let expected: HashMap<&str, f64> = names
+            .iter()
+            .copied()
+            .zip(petgraph_scores(&graph, &names))
+            .collect();
+        let mut by_score = names.to_vec();
+        by_score.sort_by(|x, y| scores[*y].total_cmp(&scores[*x]));
+        let mut by_expected = names.to_vec();
+        by_expected.sort_by(|x, y| expected[y].total_cmp(&expected[x]));
+        assert_eq!(by_score, by_expected);
+        for name in names {
+            assert!((scores[name] - expected[name]).abs() < 0.01, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_weights_and_dangling() {
+        // c 没有出边，分数平均分给所有节点
+        let weighted = vec![
+            ("a".to_string(), "b".to_string(), 3.0),
+            ("a".to_string(), "c".to_string(), 1.0),
+            ("b".to_string(), "a".to_string(), 1.0),
+        ];
+        let scores = pagerank_from_edges(&weighted, PageRankOptions::default());
+        assert_eq!(scores.len(), 3);
+        assert_sum_to_one(&scores);
+        assert!(scores["b"] > scores["c"]);
+
+        // 权重按比例缩放后结果不变
+        let scaled: Vec<_> = weighted
+            .iter()
+            .map(|(from, to, weight)| (from.clone(), to.clone(), weight * 10.0))
+            .collect();
+        let scaled_scores = pagerank_from_edges(&scaled, PageRankOptions::default());
+        for (name, score) in &scores {
+            assert!((score - scaled_scores[name]).abs() < 1e-9);
+        }
+
+        // 只有一个没有出边的节点
+        let scores = pagerank_from_edges(&edges(&[("a", "b")]), PageRankOptions::default());
+        assert_sum_to_one(&scores);
+        assert!(scores["b"] > scores["a"]);
+        assert!(pagerank_from_edges(&[], PageRankOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_epsilon() {
+        let graph = edges(&[("a", "b"), ("b", "c"), ("c", "a"), ("a", "c")]);
+        let exact = pagerank_from_edges(
+            &graph,
+            PageRankOptions {
+                epsilon: None,
+                max_iterations: 1000,
+                ..Default::default()
+            },
+        );
+        let early = pagerank_from_edges(
+            &graph,
+            PageRankOptions {
+                epsilon: Some(1e-6),
+                max_iterations: 1000,
+                ..Default::default()
+            },
+        );
+        let few = pagerank_from_edges(
+            &graph,
+            PageRankOptions {
+                epsilon: None,
+                max_iterations: 1,
+                ..Default::default()
+            },
+        );
+        for name in ["a", "b", "c"] {
+            assert!((exact[name] - early[name]).abs() < 1e-5);
+        }
+        assert!((exact["a"] - few["a"]).abs() > 1e-3);
+        assert_sum_to_one(&few);
+    }
+
+    #[test]
+    fn test_from_csv_reader() {
+        let csv = "from,to,weight\n# comment\na,b,2.5\n\n b , c \n";
+        let edges = from_csv_reader(csv.as_bytes()).unwrap();
+        assert_eq!(
+            edges,
+            vec![
+                ("a".to_string(), "b".to_string(), 2.5),
+                ("b".to_string(), "c".to_string(), 1.0),
+            ]
+        );
+
+        let err = from_csv_reader("a,b\na,b,-1\n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: invalid weight \"-1\"");
+        assert!(from_csv_reader("a\n".as_bytes()).is_err());
+        assert!(from_csv_reader("a,,1\n".as_bytes()).is_err());
+    }
+}
@@ -1,20 +1,37 @@
-use petgraph::Graph;
-use petgraph::algo::page_rank; // 需要添加petgraph-algo特性
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::process;
 
+use page_rank::{PageRankOptions, from_csv_reader, pagerank_from_edges};
+
+// 用法：page_rank [edges.csv] [top_k]，没有文件或者文件为 - 时从标准输入读取
 fn main() {
-    println!("Hello, world!");
-    let mut graph = Graph::<(), ()>::new();
-    let a = graph.add_node(()); // 添加节点
-    let b = graph.add_node(());
-    let c = graph.add_node(());
-    
-    graph.add_edge(a, b, ()); // 添加边
-    graph.add_edge(b, c, ());
-    graph.add_edge(c, a, ());
-    
-    let damping_factor = 0.85;
-    let iterations = 20;
-    let pagerank_scores = petgraph::algo::page_rank(&graph, damping_factor, iterations);
-    
-    println!("PageRank scores: {:?}", pagerank_scores);
-}
\ No newline at end of file
+    let args: Vec<String> = env::args().collect();
+    let path = args.get(1).map(String::as_str).unwrap_or("-");
+    let top_k = match args.get(2).map(|k| k.parse::<usize>()) {
+        None => 10,
+        Some(Ok(k)) => k,
+        Some(Err(err)) => {
+            eprintln!("invalid top_k: {}", err);
+            process::exit(1);
+        }
+    };
+
+    let edges = if path == "-" {
+        from_csv_reader(io::stdin().lock())
+    } else {
+        File::open(path).and_then(|file| from_csv_reader(BufReader::new(file)))
+    };
+    let edges = edges.unwrap_or_else(|err| {
+        eprintln!("failed to read {}: {}", path, err);
+        process::exit(1);
+    });
+
+    let scores = pagerank_from_edges(&edges, PageRankOptions::default());
+    let mut scores: Vec<_> = scores.into_iter().collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    for (name, score) in scores.into_iter().take(top_k) {
+        println!("{}\t{:.6}", name, score);
+    }
+}
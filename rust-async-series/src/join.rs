@@ -0,0 +1,181 @@
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/*
+Join 同时轮询两个 future，两个都完成后返回 (F1::Output, F2::Output)。
+先完成的 future 的结果保存在 MaybeDone::Done 中，之后不会再轮询它。
+Join 被 drop 时，还没有完成的 future 也随之被 drop（取消）。
+ */
+pub struct Join<F1: Future, F2: Future> {
+    fut1: MaybeDone<F1>,
+    fut2: MaybeDone<F2>,
+}
+
+enum MaybeDone<F: Future> {
+    Pending(F),
+    Done(F::Output),
+    // 结果已经被取走
+    Taken,
+}
+
+pub fn join2<F1: Future, F2: Future>(fut1: F1, fut2: F2) -> Join<F1, F2> {
+    Join {
+        fut1: MaybeDone::Pending(fut1),
+        fut2: MaybeDone::Pending(fut2),
+    }
+}
+
+impl<F: Future> MaybeDone<F> {
+    // 轮询还没有完成的 future，返回是否已经完成
+    fn poll_done(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> bool {
+        // 安全性：Pending 中的 future 是结构化固定的（structural pinning），
+        // 在被 set 替换（原地 drop）之前不会被移动
+        let output = match unsafe { self.as_mut().get_unchecked_mut() } {
+            MaybeDone::Pending(fut) => match unsafe { Pin::new_unchecked(fut) }.poll(cx) {
+                Poll::Ready(output) => output,
+                Poll::Pending => return false,
+            },
+            MaybeDone::Done(_) => return true,
+            MaybeDone::Taken => panic!("poll after completions"),
+        };
+        // Pin::set 会原地 drop 已完成的 future，再写入结果
+        self.set(MaybeDone::Done(output));
+        true
+    }
+
+    fn take_output(self: Pin<&mut Self>) -> F::Output {
+        // 安全性：只有 Done 状态才会被移动，Done 中的结果没有被固定
+        let this = unsafe { self.get_unchecked_mut() };
+        match this {
+            MaybeDone::Done(_) => match mem::replace(this, MaybeDone::Taken) {
+                MaybeDone::Done(output) => output,
+                _ => unreachable!(),
+            },
+            _ => panic!("take output before completion"),
+        }
+    }
+}
+
+impl<F1: Future, F2: Future> Future for Join<F1, F2> {
+    type Output = (F1::Output, F2::Output);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // 和 Map 一样通过 map_unchecked_mut 投影出两个字段，Join 不会移动 fut1 和 fut2
+        let done1 = unsafe { self.as_mut().map_unchecked_mut(|join| &mut join.fut1) }.poll_done(cx);
+        let done2 = unsafe { self.as_mut().map_unchecked_mut(|join| &mut join.fut2) }.poll_done(cx);
+        if !(done1 && done2) {
+            return Poll::Pending;
+        }
+
+        let output1 =
+            unsafe { self.as_mut().map_unchecked_mut(|join| &mut join.fut1) }.take_output();
+        let output2 =
+            unsafe { self.as_mut().map_unchecked_mut(|join| &mut join.fut2) }.take_output();
+        Poll::Ready((output1, output2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::{Future, ready};
+    use std::pin::{Pin, pin};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll, Waker};
+    use std::time::{Duration, Instant};
+
+    use super::join2;
+    use crate::SleepFuture;
+
+    #[tokio::test]
+    async fn test_join_sleep() {
+        let start = Instant::now();
+        let fut1 = async {
+            SleepFuture::new(Duration::from_millis(200)).await;
+            1
+        };
+        let fut2 = async {
+            SleepFuture::new(Duration::from_millis(400)).await;
+            "two"
+        };
+
+        assert_eq!(join2(fut1, fut2).await, (1, "two"));
+        // 总耗时接近较长的那个，而不是两者之和
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(400), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(550), "{:?}", elapsed);
+    }
+
+    // 记录被轮询的次数，完成后再被轮询时 panic
+    struct CountPolls {
+        polls: Arc<AtomicUsize>,
+        ready_after: usize,
+    }
+
+    impl Future for CountPolls {
+        type Output = usize;
+
+        fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<usize> {
+            let polls = self.polls.fetch_add(1, Ordering::SeqCst) + 1;
+            assert!(polls <= self.ready_after, "polled after completion");
+            if polls == self.ready_after {
+                Poll::Ready(polls)
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn test_join_polls_once_after_done() {
+        let polls1 = Arc::new(AtomicUsize::new(0));
+        let polls2 = Arc::new(AtomicUsize::new(0));
+        let fut1 = CountPolls {
+            polls: polls1.clone(),
+            ready_after: 1,
+        };
+        let fut2 = CountPolls {
+            polls: polls2.clone(),
+            ready_after: 3,
+        };
+
+        let mut join = pin!(join2(fut1, fut2));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(join.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(join.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(join.as_mut().poll(&mut cx), Poll::Ready((1, 3)));
+        assert_eq!(polls1.load(Ordering::SeqCst), 1);
+        assert_eq!(polls2.load(Ordering::SeqCst), 3);
+    }
+
+    // drop 时计数
+    struct DropCount(Arc<AtomicUsize>);
+
+    impl Drop for DropCount {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_join_drop() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let pending = {
+            let guard = DropCount(drops.clone());
+            async move {
+                SleepFuture::new(Duration::from_secs(60)).await;
+                drop(guard);
+            }
+        };
+
+        let mut join = Box::pin(join2(ready(1), pending));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(join.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        // 取消 Join 会 drop 还没有完成的 future
+        drop(join);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,9 +1,13 @@
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::{Arc, Mutex};
-use std::task::{Context, Poll, Waker};
-use std::thread;
-use std::time::Duration;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+mod join;
+mod timer;
+
+pub use join::{Join, join2};
+pub use timer::TimerDriver;
 
 /*
 use rust_async_series::sleep;
@@ -90,29 +94,28 @@ pub async fn sleep(duration: Duration) {
 
 pub struct SleepFuture {
     duration: Duration,
-    state: Arc<Mutex<State>>,
-}
-
-struct State {
-    waker: Option<Waker>,
-    inner_state: InnerState,
+    driver: timer::Handle,
+    state: State,
 }
 
-#[derive(PartialEq)]
-enum InnerState {
+enum State {
     Init,
-    Sleeping,
+    // 已经注册到 TimerDriver
+    Sleeping(timer::TimerKey),
     Done,
 }
 
 impl SleepFuture {
     fn new(duration: Duration) -> Self {
+        Self::with_driver(duration, TimerDriver::global())
+    }
+
+    // 使用指定的 TimerDriver，而不是全局的
+    pub fn with_driver(duration: Duration, driver: &TimerDriver) -> Self {
         Self {
             duration,
-            state: Arc::new(Mutex::new(State {
-                waker: None,
-                inner_state: InnerState::Init,
-            })),
+            driver: driver.handle(),
+            state: State::Init,
         }
     }
 }
@@ -120,37 +123,36 @@ impl SleepFuture {
 impl Future for SleepFuture {
     type Output = ();
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         // Q: 这里 poll 可能被多次调用
-        let mut guard = self.state.lock().unwrap();
-
-        println!("Polling...");
-
-        if guard.inner_state == InnerState::Done {
-            return Poll::Ready(());
-        }
-
-        if guard.inner_state == InnerState::Init {
-            guard.waker = Some(cx.waker().clone());
-            guard.inner_state = InnerState::Sleeping;
-
-            let duration = self.duration;
-            let state_cloned = Arc::clone(&self.state);
-
-            thread::spawn(move || {
-                // Q: 这里不能通过self（线程不安全）
-                println!("Start sleeping for {:?} seconds", duration);
-                thread::sleep(duration);
-                let mut guard = state_cloned.lock().unwrap();
-                guard.inner_state = InnerState::Done;
-                if let Some(waker) = guard.waker.take() {
-                    waker.wake();
+        match self.state {
+            State::Done => Poll::Ready(()),
+            // 第一次 poll 时开始计时，注册到 TimerDriver
+            State::Init => {
+                let deadline = Instant::now() + self.duration;
+                let key = self.driver.register(deadline, cx.waker().clone());
+                self.state = State::Sleeping(key);
+                Poll::Pending
+            }
+            State::Sleeping(key) => {
+                // 到期的定时器已经被 TimerDriver 移除，deregister 只对提前 poll 的情况有效
+                if Instant::now() >= key.0 {
+                    self.driver.deregister(key);
+                    self.state = State::Done;
+                    return Poll::Ready(());
                 }
-                println!("Done sleeping");
-            });
+                self.driver.update(key, cx.waker());
+                Poll::Pending
+            }
         }
+    }
+}
 
-        guard.waker = Some(cx.waker().clone());
-        Poll::Pending
+impl Drop for SleepFuture {
+    // 完成前被 drop（比如超时被取消）时注销定时器
+    fn drop(&mut self) {
+        if let State::Sleeping(key) = self.state {
+            self.driver.deregister(key);
+        }
     }
 }
@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::Waker;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+/*
+所有 SleepFuture 共用一个后台线程：
+    SleepFuture 第一次 poll 时把 (deadline, waker) 注册到 TimerDriver；
+    后台线程等待最早的 deadline，到期后移除定时器并唤醒对应的任务；
+    SleepFuture 在完成前被 drop 时注销定时器，已取消的任务不会再被唤醒。
+这样同时等待 1000 个 sleep 也只需要一个线程，而不是每个 sleep 一个线程。
+ */
+pub struct TimerDriver {
+    handle: Handle,
+    thread: Option<JoinHandle<()>>,
+}
+
+// SleepFuture 持有的 TimerDriver 引用
+#[derive(Clone)]
+pub(crate) struct Handle {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    // 有新的定时器或者需要退出时通知后台线程
+    cond: Condvar,
+}
+
+struct State {
+    // deadline 相同时用递增的 id 区分
+    timers: BTreeMap<TimerKey, Waker>,
+    next_id: u64,
+    shutdown: bool,
+}
+
+pub(crate) type TimerKey = (Instant, u64);
+
+impl TimerDriver {
+    // 创建一个新的 TimerDriver，测试中可以单独创建，被 drop 时后台线程退出
+    pub fn new() -> Self {
+        let inner = Arc::new(Inner {
+            state: Mutex::new(State {
+                timers: BTreeMap::new(),
+                next_id: 0,
+                shutdown: false,
+            }),
+            cond: Condvar::new(),
+        });
+
+        let thread_inner = Arc::clone(&inner);
+        let thread = thread::Builder::new()
+            .name("timer-driver".to_string())
+            .spawn(move || run(&thread_inner))
+            .expect("failed to spawn timer thread");
+
+        Self {
+            handle: Handle { inner },
+            thread: Some(thread),
+        }
+    }
+
+    // 全局的 TimerDriver，第一次使用时创建
+    pub fn global() -> &'static TimerDriver {
+        static DRIVER: OnceLock<TimerDriver> = OnceLock::new();
+        DRIVER.get_or_init(TimerDriver::new)
+    }
+
+    // 还没有到期的定时器数量
+    pub fn pending(&self) -> usize {
+        self.handle.inner.state.lock().unwrap().timers.len()
+    }
+
+    pub(crate) fn handle(&self) -> Handle {
+        self.handle.clone()
+    }
+}
+
+impl Default for TimerDriver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TimerDriver {
+    fn drop(&mut self) {
+        self.handle.inner.state.lock().unwrap().shutdown = true;
+        self.handle.inner.cond.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Handle {
+    pub(crate) fn register(&self, deadline: Instant, waker: Waker) -> TimerKey {
+        let mut state = self.inner.state.lock().unwrap();
+        let key = (deadline, state.next_id);
+        state.next_id += 1;
+        // 新的定时器可能比后台线程正在等待的更早
+        let earliest = state
+            .timers
+            .first_key_value()
+            .is_none_or(|(first, _)| key < *first);
+        state.timers.insert(key, waker);
+        if earliest {
+            self.inner.cond.notify_one();
+        }
+        key
+    }
+
+    // 任务可能在不同的 waker 下被 poll，只保留最新的 waker
+    pub(crate) fn update(&self, key: TimerKey, waker: &Waker) {
+        let mut state = self.inner.state.lock().unwrap();
+        if let Some(old) = state.timers.get_mut(&key)
+            && !old.will_wake(waker)
+        {
+            *old = waker.clone();
+        }
+    }
+
+    pub(crate) fn deregister(&self, key: TimerKey) {
+        self.inner.state.lock().unwrap().timers.remove(&key);
+    }
+}
+
+// 后台线程：唤醒到期的定时器，然后等待下一个 deadline
+fn run(inner: &Inner) {
+    let mut state = inner.state.lock().unwrap();
+    loop {
+        if state.shutdown {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        while let Some(entry) = state.timers.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            expired.push(entry.remove());
+        }
+
+        if !expired.is_empty() {
+            // 唤醒时不持有锁，被唤醒的任务可能马上注册新的定时器
+            drop(state);
+            for waker in expired {
+                waker.wake();
+            }
+            state = inner.state.lock().unwrap();
+            continue;
+        }
+
+        state = match state.timers.first_key_value() {
+            Some(((deadline, _), _)) => {
+                let timeout = deadline.saturating_duration_since(now);
+                inner.cond.wait_timeout(state, timeout).unwrap().0
+            }
+            None => inner.cond.wait(state).unwrap(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::TimerDriver;
+    use crate::SleepFuture;
+
+    // 记录被唤醒的次数
+    struct CountWaker(AtomicUsize);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_wake_after_deadline() {
+        let driver = TimerDriver::new();
+        let count = Arc::new(CountWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(count.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = pin!(SleepFuture::with_driver(Duration::from_millis(50), &driver));
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(driver.pending(), 1);
+
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(count.0.load(Ordering::SeqCst), 1);
+        assert_eq!(driver.pending(), 0);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(()));
+    }
+
+    #[test]
+    fn test_deregister_on_drop() {
+        let driver = TimerDriver::new();
+        let count = Arc::new(CountWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(count.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = Box::pin(SleepFuture::with_driver(Duration::from_millis(50), &driver));
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(driver.pending(), 1);
+
+        // 取消的 sleep 不会再唤醒任务
+        drop(fut);
+        assert_eq!(driver.pending(), 0);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(count.0.load(Ordering::SeqCst), 0);
+    }
+
+    // 当前进程的线程数
+    #[cfg(target_os = "linux")]
+    fn thread_count() -> usize {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap();
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix("Threads:"))
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_many_sleeps() {
+        let driver = Arc::new(TimerDriver::new());
+        let before = thread_count();
+        let start = Instant::now();
+
+        let handles = (0..500)
+            .map(|i| {
+                let driver = driver.clone();
+                tokio::spawn(async move {
+                    SleepFuture::with_driver(Duration::from_millis(100 + i % 100), &driver).await;
+                    i
+                })
+            })
+            .collect::<Vec<_>>();
+        // 让所有任务都开始等待
+        tokio::task::yield_now().await;
+        let mut max_threads = thread_count();
+
+        let mut sum = 0;
+        for handle in handles {
+            sum += handle.await.unwrap();
+            max_threads = max_threads.max(thread_count());
+        }
+        assert_eq!(sum, (0..500).sum::<u64>());
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "{:?}",
+            start.elapsed()
+        );
+        // 其他测试可能同时创建少量线程，但远少于每个 sleep 一个线程
+        assert!(max_threads < before + 50, "{} -> {}", before, max_threads);
+        assert_eq!(driver.pending(), 0);
+    }
+}
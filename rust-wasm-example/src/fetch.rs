@@ -0,0 +1,224 @@
+use js_sys::{Reflect, Uint8Array, JSON};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AbortSignal, ReadableStreamDefaultReader, Request, RequestInit, Response};
+
+// 响应体的大小上限，1 MiB
+pub const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+// fetch_json 失败时 reject 的对象，同时写入生成的 .d.ts
+#[wasm_bindgen(typescript_custom_section)]
+const FETCH_ERROR_TS: &str = r#"
+export interface FetchError {
+    kind: "network" | "aborted" | "http" | "too_large" | "parse";
+    // 收到响应之后的错误才有状态码，否则为 null
+    status: number | null;
+    message: string;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    // 全局的 fetch，不通过 window 调用，在 Worker 和 Node 中也能使用，测试时可以替换为 mock
+    #[wasm_bindgen(js_name = fetch)]
+    fn global_fetch(request: &Request) -> js_sys::Promise;
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchErrorKind {
+    // 请求没有发出或者连接中断
+    Network,
+    // AbortSignal 取消了请求
+    Aborted,
+    // 状态码不是 2xx
+    Http,
+    // 响应体超过 MAX_BODY_BYTES
+    TooLarge,
+    // 响应体不是 UTF-8 编码的 JSON
+    Parse,
+}
+
+// 转换为 JS 对象 {kind, status, message}
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FetchError {
+    pub kind: FetchErrorKind,
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+impl FetchError {
+    pub fn new(kind: FetchErrorKind, status: Option<u16>, message: impl Into<String>) -> Self {
+        FetchError {
+            kind,
+            status,
+            message: message.into(),
+        }
+    }
+
+    // fetch 或读取响应体时 reject 的 JS 错误，取消时是 name 为 AbortError 的 DOMException
+    fn from_js(err: &JsValue, status: Option<u16>) -> Self {
+        let kind = match js_string(err, "name").as_deref() {
+            Some("AbortError") => FetchErrorKind::Aborted,
+            _ => FetchErrorKind::Network,
+        };
+        let message = js_string(err, "message")
+            .or_else(|| err.as_string())
+            .unwrap_or_else(|| "fetch failed".to_string());
+        FetchError::new(kind, status, message)
+    }
+
+    // status 为 None 时是 null 而不是 undefined
+    pub fn to_js(&self) -> JsValue {
+        let serializer = serde_wasm_bindgen::Serializer::new().serialize_missing_as_null(true);
+        self.serialize(&serializer).unwrap_throw()
+    }
+}
+
+// 状态码不是 2xx 时返回 http 错误
+pub fn check_status(status: u16, status_text: &str) -> Result<(), FetchError> {
+    if (200..300).contains(&status) {
+        return Ok(());
+    }
+    let message = format!("HTTP {} {}", status, status_text);
+    Err(FetchError::new(
+        FetchErrorKind::Http,
+        Some(status),
+        message.trim_end(),
+    ))
+}
+
+// 分块接收响应体，超过上限时立即返回错误，不需要读完整个响应体
+pub struct BodyBuffer {
+    bytes: Vec<u8>,
+    max_bytes: usize,
+    status: u16,
+}
+
+impl BodyBuffer {
+    pub fn new(max_bytes: usize, status: u16) -> BodyBuffer {
+        BodyBuffer {
+            bytes: Vec::new(),
+            max_bytes,
+            status,
+        }
+    }
+
+    // Content-Length 已经超过上限时不用读取响应体，无法解析时忽略
+    pub fn check_content_length(&self, content_length: Option<&str>) -> Result<(), FetchError> {
+        match content_length.and_then(|len| len.trim().parse::<usize>().ok()) {
+            Some(len) if len > self.max_bytes => Err(self.too_large(len)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), FetchError> {
+        let len = self.bytes.len() + chunk.len();
+        if len > self.max_bytes {
+            return Err(self.too_large(len));
+        }
+        self.bytes.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    pub fn into_text(self) -> Result<String, FetchError> {
+        let status = self.status;
+        String::from_utf8(self.bytes).map_err(|err| {
+            FetchError::new(
+                FetchErrorKind::Parse,
+                Some(status),
+                format!("response body is not valid UTF-8: {}", err),
+            )
+        })
+    }
+
+    fn too_large(&self, len: usize) -> FetchError {
+        FetchError::new(
+            FetchErrorKind::TooLarge,
+            Some(self.status),
+            format!(
+                "response body is at least {} bytes, limit is {}",
+                len, self.max_bytes
+            ),
+        )
+    }
+}
+
+// GET 请求 url，把响应体解析为 JSON 返回，失败时 reject FetchError
+#[wasm_bindgen]
+pub async fn fetch_json(url: String) -> Result<JsValue, JsValue> {
+    fetch_json_inner(&url, None)
+        .await
+        .map_err(|err| err.to_js())
+}
+
+// 和 fetch_json 相同，signal 触发后 reject kind 为 aborted 的 FetchError
+#[wasm_bindgen]
+pub async fn fetch_json_with_signal(url: String, signal: AbortSignal) -> Result<JsValue, JsValue> {
+    fetch_json_inner(&url, Some(&signal))
+        .await
+        .map_err(|err| err.to_js())
+}
+
+async fn fetch_json_inner(url: &str, signal: Option<&AbortSignal>) -> Result<JsValue, FetchError> {
+    let init = RequestInit::new();
+    init.set_method("GET");
+    init.set_signal(signal);
+    // url 不合法时 Request 构造函数抛出 TypeError
+    let request = Request::new_with_str_and_init(url, &init)
+        .map_err(|err| FetchError::from_js(&err, None))?;
+    request
+        .headers()
+        .set("Accept", "application/json")
+        .map_err(|err| FetchError::from_js(&err, None))?;
+
+    let response: Response = JsFuture::from(global_fetch(&request))
+        .await
+        .map_err(|err| FetchError::from_js(&err, None))?
+        .unchecked_into();
+    let status = response.status();
+    check_status(status, &response.status_text())?;
+
+    let mut body = BodyBuffer::new(MAX_BODY_BYTES, status);
+    let content_length = response.headers().get("Content-Length").ok().flatten();
+    body.check_content_length(content_length.as_deref())?;
+    if let Some(stream) = response.body() {
+        let reader: ReadableStreamDefaultReader = stream.get_reader().unchecked_into();
+        loop {
+            // 每次读取一块 {done, value}
+            let result = JsFuture::from(reader.read())
+                .await
+                .map_err(|err| FetchError::from_js(&err, Some(status)))?;
+            if Reflect::get(&result, &"done".into())
+                .map(|done| done.is_truthy())
+                .unwrap_or(true)
+            {
+                break;
+            }
+            let chunk = Uint8Array::new(&Reflect::get(&result, &"value".into()).unwrap_throw());
+            if let Err(err) = body.push(&chunk.to_vec()) {
+                // 不再读取剩下的响应体
+                let _ = reader.cancel();
+                return Err(err);
+            }
+        }
+    }
+
+    let text = body.into_text()?;
+    JSON::parse(&text).map_err(|err| {
+        let message = js_string(&err, "message").unwrap_or_else(|| "invalid JSON".to_string());
+        FetchError::new(FetchErrorKind::Parse, Some(status), message)
+    })
+}
+
+// 读取 JS 对象的字符串属性，value 不是对象时返回 None
+fn js_string(value: &JsValue, key: &str) -> Option<String> {
+    if !value.is_object() {
+        return None;
+    }
+    Reflect::get(value, &key.into())
+        .ok()
+        .and_then(|value| value.as_string())
+}
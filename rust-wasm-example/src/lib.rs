@@ -1,5 +1,15 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+mod fetch;
+
+pub use fetch::{
+    check_status, fetch_json, fetch_json_with_signal, BodyBuffer, FetchError, FetchErrorKind,
+    MAX_BODY_BYTES,
+};
+
 // 导出一个加法函数到 JavaScript
 #[wasm_bindgen]
 pub fn add(a: i32, b: i32) -> i32 {
@@ -10,4 +20,147 @@ pub fn add(a: i32, b: i32) -> i32 {
 #[wasm_bindgen]
 pub fn greet(name: &str) -> String {
     format!("Hello, {}!", name)
-}
\ No newline at end of file
+}
+
+// 单词及其出现次数，转换为 JS 对象 {word, count}
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WordCount {
+    pub word: String,
+    pub count: usize,
+}
+
+// 流式文本统计，文本分块传入，不需要保存整个输入
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct TextStats {
+    newlines: usize,
+    words: usize,
+    chars: usize,
+    bytes: usize,
+    // 最后一个字符，用于判断最后一行是否以换行结束
+    last_char: Option<char>,
+    // 上一块末尾还没有结束的单词，可能在下一块中继续
+    pending: String,
+    counts: HashMap<String, usize>,
+}
+
+#[wasm_bindgen]
+impl TextStats {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TextStats {
+        TextStats::default()
+    }
+
+    // 统计一块文本，单词可以跨越多个块
+    pub fn feed(&mut self, chunk: &str) {
+        self.bytes += chunk.len();
+        for c in chunk.chars() {
+            self.chars += 1;
+            if c == '\n' {
+                self.newlines += 1;
+            }
+
+            if is_cjk(c) {
+                // 中日文没有空格分隔，每个字单独计数
+                self.finish_word();
+                self.add_word(c.to_string());
+            } else if c.is_alphanumeric() {
+                self.pending.extend(c.to_lowercase());
+            } else if c == '\'' && !self.pending.is_empty() {
+                // 单词中间的撇号，比如 don't
+                self.pending.push(c);
+            } else {
+                self.finish_word();
+            }
+            self.last_char = Some(c);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = TextStats::default();
+    }
+
+    // 行数，和 str::lines 一致，最后一行可以没有换行
+    #[wasm_bindgen(getter)]
+    pub fn lines(&self) -> usize {
+        match self.last_char {
+            Some(c) if c != '\n' => self.newlines + 1,
+            _ => self.newlines,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn words(&self) -> usize {
+        self.words + usize::from(!self.pending.is_empty())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn chars(&self) -> usize {
+        self.chars
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // 出现次数最多的 n 个单词，返回 JS 数组 [{word, count}]
+    pub fn top_words(&self, n: usize) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.top_word_counts(n)).unwrap_throw()
+    }
+}
+
+impl TextStats {
+    // 出现次数最多的 n 个单词，次数相同时按单词排序
+    pub fn top_word_counts(&self, n: usize) -> Vec<WordCount> {
+        let mut counts = self
+            .counts
+            .iter()
+            .map(|(word, &count)| (word.as_str(), count))
+            .collect::<Vec<_>>();
+        // 没有结束的单词也计入
+        let pending = self.pending.trim_end_matches('\'');
+        if !pending.is_empty() {
+            match counts.iter_mut().find(|(word, _)| *word == pending) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((pending, 1)),
+            }
+        }
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+            .into_iter()
+            .take(n)
+            .map(|(word, count)| WordCount {
+                word: word.to_string(),
+                count,
+            })
+            .collect()
+    }
+
+    fn finish_word(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let mut word = std::mem::take(&mut self.pending);
+        word.truncate(word.trim_end_matches('\'').len());
+        self.add_word(word);
+    }
+
+    fn add_word(&mut self, word: String) {
+        self.words += 1;
+        *self.counts.entry(word).or_insert(0) += 1;
+    }
+}
+
+// 中文汉字、日文假名
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2A6DF}'
+    )
+}
@@ -0,0 +1,186 @@
+use rust_wasm_example::{check_status, BodyBuffer, FetchError, FetchErrorKind, MAX_BODY_BYTES};
+use wasm_bindgen_test::*;
+
+// mock 的 fetch 在浏览器中运行：wasm-pack test --headless --chrome
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_check_status() {
+    assert_eq!(check_status(200, "OK"), Ok(()));
+    assert_eq!(check_status(204, ""), Ok(()));
+    assert_eq!(
+        check_status(404, "Not Found"),
+        Err(FetchError::new(
+            FetchErrorKind::Http,
+            Some(404),
+            "HTTP 404 Not Found"
+        ))
+    );
+    // HTTP/2 没有 status text
+    assert_eq!(check_status(500, "").unwrap_err().message, "HTTP 500");
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_body_buffer() {
+    let mut body = BodyBuffer::new(8, 200);
+    assert_eq!(body.check_content_length(Some("8")), Ok(()));
+    assert_eq!(body.check_content_length(Some("abc")), Ok(()));
+    assert_eq!(body.check_content_length(None), Ok(()));
+    let err = body.check_content_length(Some("9")).unwrap_err();
+    assert_eq!(
+        (err.kind, err.status),
+        (FetchErrorKind::TooLarge, Some(200))
+    );
+
+    body.push(b"[1, ").unwrap();
+    body.push(b"2]").unwrap();
+    // 超过上限的块不会写入
+    let err = body.push(b"  [3]").unwrap_err();
+    assert_eq!(err.kind, FetchErrorKind::TooLarge);
+    assert_eq!(
+        err.message,
+        "response body is at least 11 bytes, limit is 8"
+    );
+    assert_eq!(body.into_text().unwrap(), "[1, 2]");
+
+    let mut body = BodyBuffer::new(MAX_BODY_BYTES, 200);
+    body.push(&[0xff, 0xfe]).unwrap();
+    assert_eq!(body.into_text().unwrap_err().kind, FetchErrorKind::Parse);
+}
+
+// 通过替换 globalThis.fetch 模拟服务端，只能在 wasm 中运行
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    use rust_wasm_example::{fetch_json, fetch_json_with_signal, MAX_BODY_BYTES};
+    use serde::Deserialize;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen(inline_js = r#"
+export function mock_fetch(status, body, content_length) {
+    globalThis.fetch = async (request) => {
+        if (request.signal.aborted) {
+            throw new DOMException("The operation was aborted.", "AbortError");
+        }
+        const headers = content_length === undefined ? {} : { "Content-Length": String(content_length) };
+        return new Response(body, { status, headers });
+    };
+}
+
+export function mock_fetch_error(message) {
+    globalThis.fetch = async () => {
+        throw new TypeError(message);
+    };
+}
+
+export function aborted_signal() {
+    const controller = new AbortController();
+    controller.abort();
+    return controller.signal;
+}
+"#)]
+    extern "C" {
+        fn mock_fetch(status: u16, body: &str, content_length: Option<u32>);
+        fn mock_fetch_error(message: &str);
+        fn aborted_signal() -> web_sys::AbortSignal;
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct JsFetchError {
+        kind: String,
+        status: Option<u16>,
+        message: String,
+    }
+
+    fn fetch_error(value: JsValue) -> JsFetchError {
+        // status 为 null 而不是 undefined
+        assert!(js_sys::Reflect::has(&value, &"status".into()).unwrap());
+        serde_wasm_bindgen::from_value(value).unwrap()
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Slideshow {
+        title: String,
+        slides: Vec<String>,
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_fetch_json() {
+        mock_fetch(200, r#"{"title": "Sample", "slides": ["a", "b"]}"#, None);
+        let value = fetch_json("https://httpbin.org/json".to_string())
+            .await
+            .unwrap();
+        let slideshow: Slideshow = serde_wasm_bindgen::from_value(value).unwrap();
+        assert_eq!(
+            slideshow,
+            Slideshow {
+                title: "Sample".to_string(),
+                slides: vec!["a".to_string(), "b".to_string()],
+            }
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_fetch_json_errors() {
+        mock_fetch(404, "not found", None);
+        let err = fetch_error(
+            fetch_json("https://httpbin.org/status/404".to_string())
+                .await
+                .unwrap_err(),
+        );
+        assert_eq!((err.kind.as_str(), err.status), ("http", Some(404)));
+
+        mock_fetch_error("Failed to fetch");
+        let err = fetch_error(
+            fetch_json("https://httpbin.org/json".to_string())
+                .await
+                .unwrap_err(),
+        );
+        assert_eq!(
+            err,
+            JsFetchError {
+                kind: "network".to_string(),
+                status: None,
+                message: "Failed to fetch".to_string(),
+            }
+        );
+
+        mock_fetch(200, "{not json", None);
+        let err = fetch_error(
+            fetch_json("https://httpbin.org/html".to_string())
+                .await
+                .unwrap_err(),
+        );
+        assert_eq!((err.kind.as_str(), err.status), ("parse", Some(200)));
+
+        // Content-Length 超过上限时不读取响应体，没有 Content-Length 时读取到上限为止
+        let body = format!("\"{}\"", "a".repeat(MAX_BODY_BYTES - 2));
+        let large = format!("{} ", body);
+        for content_length in [Some(large.len() as u32), None] {
+            mock_fetch(200, &large, content_length);
+            let err = fetch_error(
+                fetch_json("https://httpbin.org/bytes".to_string())
+                    .await
+                    .unwrap_err(),
+            );
+            assert_eq!((err.kind.as_str(), err.status), ("too_large", Some(200)));
+        }
+        // 正好等于上限时可以解析
+        mock_fetch(200, &body, None);
+        let value = fetch_json("https://httpbin.org/bytes".to_string())
+            .await
+            .unwrap();
+        assert_eq!(value.as_string().map(|s| s.len()), Some(MAX_BODY_BYTES - 2));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_fetch_json_aborted() {
+        mock_fetch(200, "{}", None);
+        let err = fetch_error(
+            fetch_json_with_signal("https://httpbin.org/delay/10".to_string(), aborted_signal())
+                .await
+                .unwrap_err(),
+        );
+        assert_eq!((err.kind.as_str(), err.status), ("aborted", None));
+    }
+}
@@ -0,0 +1,68 @@
+use rust_wasm_example::{TextStats, WordCount};
+use wasm_bindgen_test::*;
+
+fn word_count(word: &str, count: usize) -> WordCount {
+    WordCount {
+        word: word.to_string(),
+        count,
+    }
+}
+
+// unsupported = test：不在 wasm 中时作为普通测试运行
+#[wasm_bindgen_test(unsupported = test)]
+fn test_feed_chunks() {
+    let mut stats = TextStats::new();
+    // 单词和换行跨越多个块
+    stats.feed("The qui");
+    stats.feed("ck fox\nthe l");
+    stats.feed("azy dog, the END");
+    assert_eq!(stats.lines(), 2);
+    assert_eq!(stats.words(), 8);
+    assert_eq!(stats.chars(), 35);
+    assert_eq!(stats.bytes(), 35);
+    assert_eq!(
+        stats.top_word_counts(3),
+        vec![
+            word_count("the", 3),
+            word_count("dog", 1),
+            word_count("end", 1)
+        ]
+    );
+
+    stats.feed("\nend's\n");
+    assert_eq!(stats.lines(), 3);
+    assert_eq!(stats.words(), 9);
+    assert!(stats.top_word_counts(10).contains(&word_count("end's", 1)));
+
+    stats.reset();
+    assert_eq!(
+        (stats.lines(), stats.words(), stats.chars(), stats.bytes()),
+        (0, 0, 0, 0)
+    );
+    assert!(stats.top_word_counts(3).is_empty());
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn test_feed_cjk() {
+    let mut stats = TextStats::new();
+    stats.feed("你好，世");
+    stats.feed("界！hello 你");
+    assert_eq!(stats.words(), 6);
+    assert_eq!(stats.chars(), 13);
+    assert_eq!(stats.bytes(), 27);
+    assert_eq!(
+        stats.top_word_counts(2),
+        vec![word_count("你", 2), word_count("hello", 1)]
+    );
+}
+
+// JsValue 只能在 wasm 中使用，通过 wasm-pack test --node 运行
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+fn test_top_words_js() {
+    let mut stats = TextStats::new();
+    stats.feed("a b ");
+    stats.feed("a");
+    let words: Vec<WordCount> = serde_wasm_bindgen::from_value(stats.top_words(1)).unwrap();
+    assert_eq!(words, vec![word_count("a", 2)]);
+}
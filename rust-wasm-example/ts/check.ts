@@ -0,0 +1,31 @@
+// 检查生成的 pkg/rust_wasm_example.d.ts 中导出的类型，只做类型检查不运行：
+// npx -p typescript tsc --noEmit --strict --lib es2020,dom ts/check.ts
+import init, {
+  fetch_json,
+  fetch_json_with_signal,
+  FetchError,
+  TextStats,
+} from "../pkg/rust_wasm_example";
+
+async function main(): Promise<void> {
+  await init();
+
+  const stats = new TextStats();
+  stats.feed("hello world");
+  const words: number = stats.words;
+  console.log(words);
+
+  const controller = new AbortController();
+  try {
+    const data: unknown = await fetch_json("/data.json");
+    console.log(data, await fetch_json_with_signal("/data.json", controller.signal));
+  } catch (e) {
+    const err = e as FetchError;
+    const kind: "network" | "aborted" | "http" | "too_large" | "parse" = err.kind;
+    const status: number | null = err.status;
+    const message: string = err.message;
+    console.error(kind, status, message);
+  }
+}
+
+main();
@@ -0,0 +1,228 @@
+use std::{collections::HashMap, path::Path};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+
+// 计算密码哈希的轮数，增加暴力破解的成本
+const HASH_ROUNDS: usize = 4096;
+// 每个用户随机生成的盐的字节数
+const SALT_LEN: usize = 16;
+// SHA-256 的输出长度
+const HASH_LEN: usize = 32;
+
+// 认证通过的用户，之后的权限检查可以基于用户实现
+#[derive(Debug, Clone, PartialEq)]
+pub struct User {
+    pub name: String,
+}
+
+// 加盐的密码哈希，不保存明文密码
+#[derive(Debug, Clone, PartialEq)]
+struct PasswordHash {
+    salt: Vec<u8>,
+    hash: Vec<u8>,
+}
+
+impl PasswordHash {
+    fn new(password: &str) -> Result<Self> {
+        let mut salt = vec![0; SALT_LEN];
+        getrandom::fill(&mut salt)
+            .map_err(|e| Error::Internal(format!("failed to generate salt: {}", e)))?;
+        let hash = hash_password(&salt, password);
+        Ok(Self { salt, hash })
+    }
+
+    // 用户不存在时用来校验的哈希，任何密码都不能通过，计算的轮数和真实的用户相同
+    fn dummy() -> Self {
+        Self {
+            salt: vec![0; SALT_LEN],
+            hash: vec![0; HASH_LEN],
+        }
+    }
+
+    // 比较所有的字节，比较的耗时和第一个不同的字节的位置无关
+    fn verify(&self, password: &str) -> bool {
+        let hash = hash_password(&self.salt, password);
+        hash.len() == self.hash.len()
+            && hash
+                .iter()
+                .zip(self.hash.iter())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+fn hash_password(salt: &[u8], password: &str) -> Vec<u8> {
+    let mut hash = Sha256::new()
+        .chain_update(salt)
+        .chain_update(password.as_bytes())
+        .finalize();
+    for _ in 1..HASH_ROUNDS {
+        hash = Sha256::new()
+            .chain_update(hash)
+            .chain_update(salt)
+            .finalize();
+    }
+    hash.to_vec()
+}
+
+// 服务端的用户表
+// 凭据文件中每行一个用户，格式为 name:salt:hash，salt 和 hash 为十六进制，# 开头的行和空行被忽略
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    users: HashMap<String, PasswordHash>,
+}
+
+impl Credentials {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 从凭据文件中读取用户
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut users = HashMap::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || Error::Parse(format!("invalid credentials in line {}", i + 1));
+            let [name, salt, hash] = line.split(':').collect::<Vec<_>>()[..] else {
+                return Err(invalid());
+            };
+            if name.is_empty() {
+                return Err(invalid());
+            }
+            let hash = PasswordHash {
+                salt: decode_hex(salt).ok_or_else(invalid)?,
+                hash: decode_hex(hash).ok_or_else(invalid)?,
+            };
+            users.insert(name.to_string(), hash);
+        }
+        Ok(Self { users })
+    }
+
+    // 添加用户，已经存在时修改密码
+    pub fn add_user(&mut self, name: &str, password: &str) -> Result<()> {
+        if name.is_empty() || name.contains(':') {
+            return Err(Error::Parse(format!("invalid user name {:?}", name)));
+        }
+        self.users
+            .insert(name.to_string(), PasswordHash::new(password)?);
+        Ok(())
+    }
+
+    // 生成凭据文件中的一行
+    pub fn format_user(name: &str, password: &str) -> Result<String> {
+        let mut credentials = Self::new();
+        credentials.add_user(name, password)?;
+        let hash = &credentials.users[name];
+        Ok(format!(
+            "{}:{}:{}",
+            name,
+            encode_hex(&hash.salt),
+            encode_hex(&hash.hash)
+        ))
+    }
+
+    // 校验用户名和密码，失败时不区分用户不存在和密码错误
+    // 用户不存在时也计算一次哈希，不能通过响应时间判断用户是否存在
+    pub fn authenticate(&self, name: &str, password: &str) -> Result<User> {
+        let verified = match self.users.get(name) {
+            Some(hash) => hash.verify(password),
+            None => {
+                // 避免编译器优化掉没有使用结果的计算
+                std::hint::black_box(PasswordHash::dummy().verify(password));
+                false
+            }
+        };
+        match verified {
+            true => Ok(User {
+                name: name.to_string(),
+            }),
+            false => Err(Error::AuthFailed(format!(
+                "invalid password for user {}",
+                name
+            ))),
+        }
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.is_empty() || !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Credentials, PasswordHash, User};
+    use crate::error::{Error, Result};
+
+    #[test]
+    fn test_authenticate() -> Result<()> {
+        let mut credentials = Credentials::new();
+        credentials.add_user("admin", "secret")?;
+        assert_eq!(
+            credentials.authenticate("admin", "secret")?,
+            User {
+                name: "admin".to_string()
+            }
+        );
+        assert!(matches!(
+            credentials.authenticate("admin", "Secret"),
+            Err(Error::AuthFailed(_))
+        ));
+        assert!(matches!(
+            credentials.authenticate("nobody", "secret"),
+            Err(Error::AuthFailed(_))
+        ));
+        assert!(credentials.add_user("a:b", "secret").is_err());
+
+        // 用于不存在的用户的哈希和真实的哈希长度相同，任何密码都不能通过
+        let dummy = PasswordHash::dummy();
+        assert_eq!(dummy.hash.len(), credentials.users["admin"].hash.len());
+        assert!(!dummy.verify(""));
+
+        // 相同的密码使用不同的盐，哈希也不同
+        let line1 = Credentials::format_user("admin", "secret")?;
+        let line2 = Credentials::format_user("admin", "secret")?;
+        assert_ne!(line1, line2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_credentials() -> Result<()> {
+        let content = format!(
+            "# users\n\n{}\n  {}  \n",
+            Credentials::format_user("alice", "a")?,
+            Credentials::format_user("bob", "")?
+        );
+        let credentials = Credentials::parse(&content)?;
+        credentials.authenticate("alice", "a")?;
+        credentials.authenticate("bob", "")?;
+        assert!(credentials.authenticate("bob", "a").is_err());
+
+        // 格式错误时返回所在的行
+        for content in ["alice:00", "alice:0g:00", ":00:00", "alice:000:00"] {
+            assert_eq!(
+                Credentials::parse(&format!("# users\n{}", content)).unwrap_err(),
+                Error::Parse("invalid credentials in line 2".to_string())
+            );
+        }
+        Ok(())
+    }
+}
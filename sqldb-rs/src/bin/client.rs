@@ -1,136 +1,171 @@
-use futures::{SinkExt, TryStreamExt};
+use sqldb_rs::client::{Client, Credentials, OutputMode, is_server_command, split_statements};
+use sqldb_rs::sql::types::DEFAULT_FLOAT_PRECISION;
 use std::{error::Error, net::SocketAddr};
-use tokio::net::TcpStream;
-use tokio_util::codec::FramedRead;
-use tokio_util::codec::{FramedWrite, LinesCodec};
 
 use rustyline::DefaultEditor;
 use rustyline::error::ReadlineError;
 
 use std::env;
 
-const RESPONSE_END: &str = "!!!end!!!";
-
-pub struct Client {
-    addr: SocketAddr,
-    stream: Option<TcpStream>,
-    txn_version: Option<u64>,
-}
-
-impl Client {
-    pub async fn new(addr: SocketAddr) -> Result<Self, Box<dyn Error>> {
-        let stream = match TcpStream::connect(&addr).await {
-            Ok(stream) => Some(stream),
-            Err(e) => {
-                eprintln!("Warning: Failed to connect to server: {}", e);
-                None
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    // client [addr] [--output table|csv|json] [--precision digits] [--execute "sql;"]
+    //        [--user name [--password pwd]] [--max-frame-length bytes]
+    let mut addr = "127.0.0.1:8080".to_string();
+    let mut output = OutputMode::Table;
+    let mut float_precision = DEFAULT_FLOAT_PRECISION;
+    let mut execute = None;
+    let mut user = None;
+    let mut password = None;
+    let mut max_frame_length = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => {
+                let value = args.next().ok_or("missing value for --output")?;
+                output = OutputMode::parse(&value).ok_or_else(|| {
+                    format!("unknown output {}, expected table, csv or json", value)
+                })?;
             }
-        };
-        Ok(Self {
-            addr,
-            stream,
-            txn_version: None,
-        })
-    }
-
-    async fn reconnect(&mut self) -> Result<(), Box<dyn Error>> {
-        match TcpStream::connect(&self.addr).await {
-            Ok(stream) => {
-                self.stream = Some(stream);
-                println!("Successfully reconnected to {}", self.addr);
-                Ok(())
+            "--precision" => {
+                let value = args.next().ok_or("missing value for --precision")?;
+                float_precision = match value.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err(format!("invalid precision {}", value).into()),
+                };
             }
-            Err(e) => {
-                eprintln!("Failed to reconnect: {}", e);
-                Err(e.into())
+            "--execute" | "-e" => execute = Some(args.next().ok_or("missing value for --execute")?),
+            "--user" | "-u" => user = Some(args.next().ok_or("missing value for --user")?),
+            "--password" => password = Some(args.next().ok_or("missing value for --password")?),
+            "--max-frame-length" => {
+                let value = args.next().ok_or("missing value for --max-frame-length")?;
+                max_frame_length = match value.parse::<usize>() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => return Err(format!("invalid max frame length {}", value).into()),
+                };
             }
+            _ => addr = arg,
         }
     }
 
-    pub async fn execute_sql(&mut self, sql_cmd: &str) -> Result<(), Box<dyn Error>> {
-        // 如果没有链接，尝试重新连接
-        if self.stream.is_none() {
-            println!("No connection, trying to reconnect...");
-            self.reconnect().await?;
-        }
-
-        // 尝试发送命令
-        let result = self.execute_sql_internal(sql_cmd).await;
-
-        // 如果执行失败，尝试重连并再次执行
-        if result.is_err() {
-            eprintln!("Connection error, trying to reconnect...");
-            self.reconnect().await?;
-            return self.execute_sql_internal(sql_cmd).await;
-        }
-
-        result
+    let addr = addr.parse::<SocketAddr>()?;
+    if execute.is_none() {
+        println!("Try to connect to {}", addr);
+    }
+    // 指定了用户但没有给出密码时，在终端中输入密码
+    let credentials = match (user, password) {
+        (Some(user), Some(password)) => Some(Credentials { user, password }),
+        (Some(user), None) => Some(Credentials {
+            password: rpassword::prompt_password(format!("Password for {}: ", user))?,
+            user,
+        }),
+        (None, Some(_)) => return Err("--password requires --user".into()),
+        (None, None) => None,
+    };
+    // 认证失败时直接退出，不再重试
+    let mut client = Client::new(addr, credentials)
+        .await
+        .map_err(|e| format!("ERROR {}: {}", e.code(), e))?;
+    client.set_output(output);
+    client.set_float_precision(float_precision);
+    if let Some(max_frame_length) = max_frame_length {
+        client.set_max_frame_length(max_frame_length);
     }
 
-    async fn execute_sql_internal(&mut self, sql_cmd: &str) -> Result<(), Box<dyn Error>> {
-        let stream = self.stream.as_mut().ok_or("No connection available")?;
-        let (r, w) = stream.split();
-        let mut sink = FramedWrite::new(w, LinesCodec::new());
-        let mut stream = FramedRead::new(r, LinesCodec::new());
-
-        // 发送命令并执行
-        sink.send(sql_cmd).await?;
-
-        // 拿到结果并打印
-        while let Some(res) = stream.try_next().await? {
-            if res == RESPONSE_END {
+    // 非交互模式：依次执行给出的语句，遇到错误时停止，并以非 0 的状态码退出
+    if let Some(sql) = execute {
+        let (mut stmts, rest) = split_statements(&sql);
+        if !rest.is_empty() {
+            stmts.push(format!("{};", rest.trim_end()));
+        }
+        let mut ok = true;
+        for stmt in stmts {
+            let output = client.execute_sql(&stmt).await?;
+            client.print(&output);
+            if !output.is_ok() {
+                ok = false;
                 break;
             }
-            // 解析事务命令
-            if res.starts_with("TRANSACTION") {
-                let args = res.split(" ").collect::<Vec<_>>();
-                if args[2] == "COMMIT" || args[2] == "ROLLBACK" {
-                    self.txn_version = None;
-                }
-                if args[2] == "BEGIN" {
-                    let version = args[1].parse::<u64>().unwrap();
-                    self.txn_version = Some(version);
-                }
-            }
-            println!("{}", res);
         }
-
-        Ok(())
-    }
-}
-
-impl Drop for Client {
-    fn drop(&mut self) {
-        if self.txn_version.is_some() {
-            futures::executor::block_on(self.execute_sql("ROLLBACK;")).expect("Rollback failed");
+        // 退出之前回滚没有结束的事务
+        drop(client);
+        if !ok {
+            std::process::exit(1);
         }
+        return Ok(());
     }
-}
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let addr = env::args().nth(1).unwrap_or("127.0.0.1:8080".to_string());
-    println!("Try to connect to {}", addr);
-
-    let addr = addr.parse::<SocketAddr>()?;
-    let mut client = Client::new(addr).await?;
 
     let mut editor = DefaultEditor::new()?;
+    // 还没有以分号结束的输入，可以跨越多行
+    let mut buffer = String::new();
     loop {
-        let prompt = match client.txn_version {
-            Some(version) => format!("sqldb[#{}]>", version),
-            None => "sqldb>".into(),
+        let prompt = match (buffer.is_empty(), client.txn_version()) {
+            (false, _) => "   ->".into(),
+            (true, Some(version)) => format!("sqldb[#{}]>", version),
+            (true, None) => "sqldb>".into(),
         };
         let readline = editor.readline(&prompt);
         match readline {
-            Ok(sql_cmd) => {
-                let sql_cmd = sql_cmd.trim();
-                if sql_cmd.len() > 0 {
-                    if sql_cmd == "exit" || sql_cmd == "quit" {
+            Ok(line) => {
+                let trimmed = line.trim();
+                // \reset 丢弃还没有发送的输入
+                if trimmed == "\\reset" {
+                    buffer.clear();
+                    println!("Query buffer reset (cleared).");
+                    continue;
+                }
+                // \format 查看或者设置结果的格式
+                if let Some(value) = trimmed.strip_prefix("\\format") {
+                    match value.trim() {
+                        "" => println!("Output format is {:?}.", client.output()),
+                        value => match OutputMode::parse(value) {
+                            Some(output) => {
+                                client.set_output(output);
+                                println!("Output format is {:?}.", output);
+                            }
+                            None => println!(
+                                "Unknown output format {}, expected table, csv or json.",
+                                value
+                            ),
+                        },
+                    }
+                    continue;
+                }
+                if buffer.is_empty() {
+                    if trimmed.is_empty() {
+                        continue;
+                    }
+                    if trimmed == "exit" || trimmed == "quit" {
                         break;
                     }
-                    editor.add_history_entry(sql_cmd)?;
-                    client.execute_sql(sql_cmd).await?;
+                    if is_server_command(trimmed) {
+                        editor.add_history_entry(trimmed)?;
+                        let output = client.execute_sql(trimmed).await?;
+                        client.print(&output);
+                        continue;
+                    }
+                }
+
+                // 以 \g 结尾时，不需要分号也立即发送
+                let (line, send) = match trimmed.strip_suffix("\\g") {
+                    Some(line) => (line, true),
+                    None => (line.as_str(), false),
+                };
+                if !buffer.is_empty() {
+                    buffer.push('\n');
+                }
+                buffer.push_str(line);
+
+                // 一次输入（例如粘贴）中可能有多条语句，依次发送，每条语句是一条历史记录
+                let (mut stmts, rest) = split_statements(&buffer);
+                buffer = rest;
+                if send && !buffer.is_empty() {
+                    stmts.push(format!("{};", std::mem::take(&mut buffer).trim_end()));
+                }
+                for stmt in stmts {
+                    editor.add_history_entry(stmt.as_str())?;
+                    let output = client.execute_sql(&stmt).await?;
+                    client.print(&output);
                 }
             }
             Err(ReadlineError::Interrupted) => {
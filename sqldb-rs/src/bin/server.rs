@@ -1,139 +1,125 @@
-use sqldb_rs::sql;
+use sqldb_rs::auth::Credentials;
+use sqldb_rs::server::Server;
 use sqldb_rs::sql::engine::kv::KVEngine;
 use sqldb_rs::storage::disk::DiskEngine;
-use tokio::net::{TcpListener, TcpStream};
-use tokio_stream::StreamExt;
-use tokio_util::codec::{Framed, LinesCodec};
 
-use futures::SinkExt;
 use std::env;
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::net::SocketAddr;
+use std::time::Duration;
 
-use sqldb_rs::error::Result;
+use sqldb_rs::error::{Error, Result};
 
 const DB_PATH: &str = "123";
-const RESPONSE_END: &str = "!!!end!!!";
-
-/// Possible requests our client can send us
-enum SqlRequest {
-    SQL(String),
-    ListTables,
-    TableInfo(String),
-}
-
-impl SqlRequest {
-    pub fn parse(cmd: &str) -> Self {
-        let upper_cmd = cmd.to_uppercase();
-        if upper_cmd == "SHOW TABLES" {
-            return SqlRequest::ListTables;
-        }
-        if upper_cmd.starts_with("SHOW TABLE") {
-            let args = upper_cmd.split_ascii_whitespace().collect::<Vec<_>>();
-            if args.len() == 3 {
-                return SqlRequest::TableInfo(args[2].to_lowercase());
-            }
-        }
-        SqlRequest::SQL(upper_cmd.into())
-    }
-}
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // server hash-password <user> 输入密码后输出凭据文件中的一行
+    if env::args().nth(1).as_deref() == Some("hash-password") {
+        let user = env::args()
+            .nth(2)
+            .ok_or_else(|| Error::Parse("usage: server hash-password <user>".to_string()))?;
+        let password = rpassword::prompt_password("Password: ")?;
+        println!("{}", Credentials::format_user(&user, &password)?);
+        return Ok(());
+    }
+
     // 配置
     let addr = env::args()
         .nth(1)
         .unwrap_or_else(|| "127.0.0.1:8080".to_string());
 
-    // 初始化 TCP 服务
-    let listener = TcpListener::bind(&addr).await?;
-    println!("sqldb server start on, listening on: {addr}");
+    // 每条语句的默认超时时间（毫秒），0 表示不限制，每个连接可以通过 SET timeout = <ms> 修改
+    let timeout = match env::args().nth(2) {
+        Some(ms) => Some(ms.parse::<u64>()?)
+            .filter(|ms| *ms > 0)
+            .map(Duration::from_millis),
+        None => None,
+    };
+
+    // 关闭时等待执行中的语句结束的时间（毫秒），超过之后断开剩余的连接
+    let grace_period = match env::args().nth(3) {
+        Some(ms) => Some(Duration::from_millis(ms.parse::<u64>()?)),
+        None => None,
+    };
+
+    // 认证：SQLDB_AUTH_FILE 为凭据文件，SQLDB_ADMIN_PASSWORD 创建 admin 用户，都没有设置时不需要认证
+    let credentials = match (
+        env::var("SQLDB_AUTH_FILE"),
+        env::var("SQLDB_ADMIN_PASSWORD"),
+    ) {
+        (Err(_), Err(_)) => None,
+        (file, admin_password) => {
+            let mut credentials = match file {
+                Ok(path) => Credentials::load(path)?,
+                Err(_) => Credentials::new(),
+            };
+            if let Ok(password) = admin_password {
+                credentials.add_user("admin", &password)?;
+            }
+            Some(credentials)
+        }
+    };
+
+    // SQLDB_READ_ONLY_ADDR 额外监听一个只读的地址，这个地址上的连接不能修改数据
+    let read_only_addr =
+        match env::var("SQLDB_READ_ONLY_ADDR") {
+            Ok(addr) => Some(addr.parse::<SocketAddr>().map_err(|e| {
+                Error::Parse(format!("invalid SQLDB_READ_ONLY_ADDR {}: {}", addr, e))
+            })?),
+            Err(_) => None,
+        };
+
+    // SQLDB_MAX_FRAME_LENGTH 为请求和响应的最大字节数，客户端需要通过 --max-frame-length 设置相同的值
+    let max_frame_length =
+        match env::var("SQLDB_MAX_FRAME_LENGTH") {
+            Ok(len) => Some(len.parse::<usize>().map_err(|e| {
+                Error::Parse(format!("invalid SQLDB_MAX_FRAME_LENGTH {}: {}", len, e))
+            })?),
+            Err(_) => None,
+        };
 
     // 初始化 DB 实例
     let p = tempfile::tempdir()?.into_path().join("sqldb-log");
     println!("sqldb store int path: {p:?}");
     let kvengine = KVEngine::new(DiskEngine::new(p.clone())?);
-    let shared_engine = Arc::new(Mutex::new(kvengine));
-
-    loop {
-        match listener.accept().await {
-            Ok((socket, _)) => {
-                let db = shared_engine.clone();
-                let mut server_session = ServerSession::new(db.lock()?)?;
 
-                tokio::spawn(async move {
-                    match server_session.handle_request(socket).await {
-                        Ok(_) => {},
-                        Err(e) => {
-                            println!("internal server error {:?}", e);
-                        },
-                    }
-                });
-            }
-            Err(e) => println!("error accepting socket; error = {e:?}"),
-        }
+    // 初始化 TCP 服务
+    let mut server = Server::new(kvengine).with_timeout(timeout);
+    if let Some(grace_period) = grace_period {
+        server = server.with_grace_period(grace_period);
     }
-}
-
-pub struct ServerSession<E: sql::engine::Engine> {
-    session: sql::engine::Session<E>,
-}
-
-// tokio::spawn 需要保证任务中使用的所有数据在任务执行期间都有效。
-// 由于异步任务可能在任意时间执行，Rust 要求所有捕获的
-// 数据都是 'static 的（要么是拥有的数据，要么是静态引用）。
-// tips: tokio::spawn 要求的是：任务捕获的所有数据必须能够独立存在，不依赖于外部作用域。(不在其他作用域中)
-impl<E: sql::engine::Engine + 'static> ServerSession<E> {
-    pub fn new(eng: MutexGuard<E>) -> Result<Self> {
-        Ok(Self {
-            session: eng.session()?,
-        })
+    if let Some(max_frame_length) = max_frame_length {
+        server = server.with_max_frame_length(max_frame_length);
+    }
+    if let Some(read_only_addr) = read_only_addr {
+        server = server.with_read_only_listener(read_only_addr);
+    }
+    if let Some(credentials) = credentials {
+        println!("authentication enabled");
+        server = server.with_credentials(credentials);
+    }
+    let handle = server.listen(&addr).await?;
+    println!("sqldb server start on, listening on: {}", handle.addr());
+    if let Some(addr) = handle.read_only_addr() {
+        println!("read-only connections listening on: {}", addr);
     }
 
-    pub async fn handle_request(&mut self, socket: TcpStream) -> Result<()> {
-        let mut lines = Framed::new(socket, LinesCodec::new());
-
-        while let Some(result) = lines.next().await {
-            match result {
-                Ok(line) => {
-                    // 解析并得到 SqlResquest
-                    let req = SqlRequest::parse(&line);
-
-                    // 执行请求
-                    let response = match req {
-                        SqlRequest::SQL(sql) => match self.session.execute(&sql) {
-                            Ok(rs) => rs.to_string(),
-                            Err(e) => e.to_string(),
-                        },
-                        SqlRequest::ListTables => {
-                            match self.session.get_table_names() {
-                                Ok(names) => names,
-                                Err(e) => e.to_string(),
-                            }
-                        },
-                        SqlRequest::TableInfo(table_name) => {
-                            match self.session.get_table(table_name) {
-                                Ok(tbinfo) => tbinfo,
-                                Err(e) => e.to_string(),
-                            }
-                        }
-                    };
-
-                    // 发送执行结果
-                    if let Err(e) = lines.send(response.as_str()).await {
-                        println!("error on sending response; error = {e:?}");
-                    }
-
-                    // 发送结束标志
-                    if let Err(e) = lines.send(RESPONSE_END).await {
-                        println!("error on sending response; error = {e:?}");
-                    }
-                }
-                Err(e) => {
-                    println!("error on decoding from socket; error = {e:?}");
-                }
-            }
+    // Ctrl-C 之后不再接收新的连接，等待执行中的语句结束，把数据持久化之后退出
+    let trigger = handle.shutdown_trigger();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            println!("shutting down, waiting for running statements");
+            trigger.shutdown();
         }
-
-        Ok(())
+    });
+
+    let aborted = handle.wait().await?;
+    if aborted > 0 {
+        println!(
+            "{} connections were closed before their statements finished",
+            aborted
+        );
     }
+    println!("sqldb server stopped");
+    Ok(())
 }
@@ -0,0 +1,487 @@
+use std::{net::SocketAddr, time::Duration};
+
+use futures::{SinkExt, TryStreamExt};
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
+
+use crate::{
+    error::{Error, Result},
+    protocol::{ClientCodec, DEFAULT_MAX_FRAME_LENGTH, Reply, Request, Response},
+    sql::{executor::ResultSet, types::DEFAULT_FLOAT_PRECISION},
+};
+
+// 连接断开时正在进行的事务已经被服务端回滚，客户端使用的错误码，和 SQLSTATE 的 connection_failure 相同
+const CONNECTION_LOST_CODE: &str = "08006";
+
+// 客户端展示结果的格式，通过 --output 或者 \format 设置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputMode {
+    // 表格，数值类型的列右对齐
+    Table,
+    // 查询结果输出为 CSV，不带分隔线和行数
+    Csv,
+    // 每个结果输出一行 JSON
+    Json,
+}
+
+impl OutputMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "table" => Some(OutputMode::Table),
+            "csv" => Some(OutputMode::Csv),
+            "json" => Some(OutputMode::Json),
+            _ => None,
+        }
+    }
+
+    // float_precision 只影响表格中浮点数的展示，CSV 和 JSON 保留完整的精度
+    pub fn render(&self, rs: &ResultSet, float_precision: usize) -> String {
+        match self {
+            OutputMode::Table => rs.to_table_with_precision(float_precision),
+            OutputMode::Csv => rs.to_csv(),
+            OutputMode::Json => rs.to_json(),
+        }
+    }
+
+    // JSON 格式时错误和结果一样是一行 JSON
+    fn render_error(&self, code: &str, error: &str) -> String {
+        match self {
+            OutputMode::Json => serde_json::json!({ "code": code, "error": error }).to_string(),
+            _ => format!("ERROR {}: {}", code, error),
+        }
+    }
+}
+
+// 一条命令的执行结果，已经按照输出格式渲染
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    // 执行成功，elapsed 为服务端处理请求用的时间
+    Ok { text: String, elapsed: Duration },
+    // 服务端返回的错误，或者连接断开导致事务被回滚
+    Error(String),
+}
+
+impl Output {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Output::Ok { .. })
+    }
+}
+
+// 用户名和密码，每次建立连接之后先发送给服务端认证
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub user: String,
+    pub password: String,
+}
+
+// 命令行客户端使用的连接，连接断开时自动重连
+pub struct Client {
+    addr: SocketAddr,
+    stream: Option<Framed<TcpStream, ClientCodec>>,
+    txn_version: Option<u64>,
+    output: OutputMode,
+    float_precision: usize,
+    credentials: Option<Credentials>,
+    max_frame_length: usize,
+}
+
+impl Client {
+    // 服务端还没有启动时也可以创建，执行命令时再连接；认证失败时返回服务端的错误
+    pub async fn new(addr: SocketAddr, credentials: Option<Credentials>) -> Result<Self> {
+        let stream = match TcpStream::connect(&addr).await {
+            Ok(stream) => Some(Framed::new(stream, ClientCodec::new())),
+            Err(e) => {
+                eprintln!("Warning: Failed to connect to server: {}", e);
+                None
+            }
+        };
+        let mut client = Self {
+            addr,
+            stream,
+            txn_version: None,
+            output: OutputMode::Table,
+            float_precision: DEFAULT_FLOAT_PRECISION,
+            credentials,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+        };
+        client.authenticate().await?;
+        Ok(client)
+    }
+
+    pub fn output(&self) -> OutputMode {
+        self.output
+    }
+
+    pub fn set_output(&mut self, output: OutputMode) {
+        self.output = output;
+    }
+
+    pub fn set_float_precision(&mut self, float_precision: usize) {
+        self.float_precision = float_precision;
+    }
+
+    // 需要和服务端的配置一致，超过这个长度的结果无法读取
+    pub fn set_max_frame_length(&mut self, max_frame_length: usize) {
+        self.max_frame_length = max_frame_length;
+        if let Some(stream) = self.stream.as_mut() {
+            stream.codec_mut().set_max_frame_length(max_frame_length);
+        }
+    }
+
+    // 当前事务的版本，不在事务中时为 None
+    pub fn txn_version(&self) -> Option<u64> {
+        self.txn_version
+    }
+
+    async fn reconnect(&mut self) -> Result<()> {
+        match TcpStream::connect(&self.addr).await {
+            Ok(stream) => {
+                self.stream = Some(Framed::new(
+                    stream,
+                    ClientCodec::with_max_frame_length(self.max_frame_length),
+                ));
+                self.authenticate().await?;
+                println!("Successfully reconnected to {}", self.addr);
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Failed to reconnect: {}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    // 配置了用户时发送认证请求，认证失败时返回服务端的错误
+    async fn authenticate(&mut self) -> Result<()> {
+        let (Some(stream), Some(credentials)) = (self.stream.as_mut(), self.credentials.as_ref())
+        else {
+            return Ok(());
+        };
+        stream
+            .send(Request::Auth {
+                user: credentials.user.clone(),
+                password: credentials.password.clone(),
+            })
+            .await?;
+        match stream.try_next().await? {
+            Some(Reply {
+                response: Response::Error { error, .. },
+                ..
+            }) => {
+                self.stream = None;
+                Err(error)
+            }
+            Some(_) => Ok(()),
+            None => Err(Error::Io("connection closed by server".to_string())),
+        }
+    }
+
+    // 执行一条命令并返回渲染后的结果，重连失败时返回 Err
+    pub async fn execute_sql(&mut self, sql_cmd: &str) -> Result<Output> {
+        // 如果没有链接，尝试重新连接
+        if self.stream.is_none() {
+            println!("No connection, trying to reconnect...");
+            self.reconnect().await?;
+        }
+
+        // 尝试发送命令，命令太长时没有发送任何数据，连接可以继续使用
+        let result = self.execute_sql_internal(sql_cmd).await;
+        match result {
+            Ok(_) => return result,
+            Err(err @ Error::FrameTooLarge { .. }) => {
+                return Ok(Output::Error(
+                    self.output.render_error(err.code(), &err.to_string()),
+                ));
+            }
+            Err(_) => {}
+        }
+
+        // 连接断开时服务端已经回滚了事务，不能在新的连接上重新执行事务中的语句
+        eprintln!("Connection error, trying to reconnect...");
+        self.stream = None;
+        let in_txn = self.txn_version.take().is_some();
+        self.reconnect().await?;
+        if in_txn {
+            return Ok(Output::Error(self.output.render_error(
+                CONNECTION_LOST_CODE,
+                "connection to server was lost, the transaction was rolled back",
+            )));
+        }
+        self.execute_sql_internal(sql_cmd).await
+    }
+
+    async fn execute_sql_internal(&mut self, sql_cmd: &str) -> Result<Output> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| Error::Io("No connection available".to_string()))?;
+
+        // 发送命令并执行
+        stream.send(Request::Execute(sql_cmd.to_string())).await?;
+
+        // 拿到结果
+        let Some(Reply { response, elapsed }) = stream.try_next().await? else {
+            return Err(Error::Io("connection closed by server".to_string()));
+        };
+        let text = match response {
+            Response::Ok(rs) => self.format_result(rs),
+            // 多条语句时每条语句的结果单独展示
+            Response::Batch(results) => results
+                .into_iter()
+                .map(|rs| self.format_result(rs))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Response::Text(text) => text,
+            // 服务端关闭时空闲的连接先收到关闭通知再断开，命令没有被执行，需要重连
+            Response::Error {
+                error: Error::ShuttingDown,
+                ..
+            } => return Err(Error::ShuttingDown),
+            Response::Error { code, error } => {
+                return Ok(Output::Error(
+                    self.output.render_error(&code, &error.to_string()),
+                ));
+            }
+        };
+        Ok(Output::Ok { text, elapsed })
+    }
+
+    // 格式化执行结果，并记录事务状态
+    fn format_result(&mut self, rs: ResultSet) -> String {
+        match rs {
+            ResultSet::Begin { version } => self.txn_version = Some(version),
+            ResultSet::Commit { .. } | ResultSet::Rollback { .. } => self.txn_version = None,
+            _ => {}
+        }
+        self.output.render(&rs, self.float_precision)
+    }
+
+    // 结果输出到标准输出，错误输出到标准错误，JSON 格式时错误也输出到标准输出
+    pub fn print(&self, output: &Output) {
+        match (output, self.output) {
+            // 查询结果的最后一行是 (N rows)，执行时间接在后面，其他结果的执行时间单独一行
+            // CSV 和 JSON 格式只输出结果，方便脚本处理
+            (Output::Ok { text, elapsed }, OutputMode::Table) if text.ends_with(" rows)") => {
+                println!("{} in {:.1?}", text, elapsed)
+            }
+            (Output::Ok { text, elapsed }, OutputMode::Table) => {
+                println!("{}\n(in {:.1?})", text, elapsed)
+            }
+            (Output::Ok { text, .. }, _) | (Output::Error(text), OutputMode::Json) => {
+                println!("{}", text)
+            }
+            (Output::Error(text), _) => eprintln!("{}", text),
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if self.txn_version.is_some() {
+            futures::executor::block_on(self.execute_sql("ROLLBACK;")).expect("Rollback failed");
+        }
+    }
+}
+
+// 按顶层的分号把输入拆分为完整的语句，返回完整的语句和剩余还没有结束的部分
+// 字符串、带双引号的标识符和注释中的分号不会结束语句，两个连续的引号表示引号本身
+// 只有空白和注释的部分会被忽略，没有结束的块注释和字符串留在剩余的部分中
+pub fn split_statements(input: &str) -> (Vec<String>, String) {
+    let mut stmts = Vec::new();
+    // 当前语句的开始位置，以及其中是否有注释之外的内容
+    let mut start = 0;
+    let mut has_content = false;
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                has_content = true;
+                while let Some((_, next)) = chars.next() {
+                    if next == c && chars.next_if(|&(_, n)| n == c).is_none() {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.next_if(|&(_, n)| n == '-').is_some() => {
+                for (_, next) in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.next_if(|&(_, n)| n == '*').is_some() => {
+                let mut prev = None;
+                let mut closed = false;
+                for (_, next) in chars.by_ref() {
+                    if prev == Some('*') && next == '/' {
+                        closed = true;
+                        break;
+                    }
+                    prev = Some(next);
+                }
+                // 块注释还没有结束，需要继续输入
+                has_content |= !closed;
+            }
+            ';' => {
+                if has_content {
+                    stmts.push(input[start..=i].trim().to_string());
+                }
+                start = i + 1;
+                has_content = false;
+            }
+            c if c.is_whitespace() => {}
+            _ => has_content = true,
+        }
+    }
+    let rest = match has_content {
+        true => input[start..].trim_start().to_string(),
+        false => String::new(),
+    };
+    (stmts, rest)
+}
+
+// 由服务端直接处理的命令，例如 SHOW TABLES、FORMAT JSON，不需要以分号结尾
+pub fn is_server_command(line: &str) -> bool {
+    let first = line.split_whitespace().next().unwrap_or_default();
+    ["SHOW", "FORMAT", "RESET", "SET", "SHUTDOWN"]
+        .iter()
+        .any(|cmd| first.eq_ignore_ascii_case(cmd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutputMode, is_server_command, split_statements};
+    use crate::sql::{
+        executor::ResultSet,
+        types::{DEFAULT_FLOAT_PRECISION, DataType, Value},
+    };
+
+    // 检查拆分出的完整语句和剩余的部分
+    fn check(input: &str, stmts: &[&str], rest: &str) {
+        assert_eq!(
+            split_statements(input),
+            (
+                stmts.iter().map(|s| s.to_string()).collect(),
+                rest.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_split_statements() {
+        // 没有分号时等待继续输入
+        check(
+            "create table t (\n  a int",
+            &[],
+            "create table t (\n  a int",
+        );
+        check(
+            "create table t (\n  a int primary key\n);",
+            &["create table t (\n  a int primary key\n);"],
+            "",
+        );
+
+        // 多条语句依次拆分，末尾没有结束的部分留下；空语句被忽略
+        check(
+            "  insert into t values (1);select * from t;\n  select",
+            &["insert into t values (1);", "select * from t;"],
+            "select",
+        );
+        check(";; select 1; ;", &["select 1;"], "");
+
+        // 字符串和带引号的标识符中的分号，以及转义的引号
+        check(
+            "insert into t values ('a;b', 'it''s;'); select \"x;\"\"y\" from t;",
+            &[
+                "insert into t values ('a;b', 'it''s;');",
+                "select \"x;\"\"y\" from t;",
+            ],
+            "",
+        );
+        // 没有结束的字符串，保留其中末尾的空白
+        check(
+            "insert into t values ('a;  ",
+            &[],
+            "insert into t values ('a;  ",
+        );
+        check("select 'it'';", &[], "select 'it'';");
+
+        // 注释中的分号
+        check(
+            "-- 注释;\nselect 1 /* ; */ + 2; -- 结束;",
+            &["-- 注释;\nselect 1 /* ; */ + 2;"],
+            "",
+        );
+        check("-- 只有注释\n/**/", &[], "");
+        check("/* 没有结束; ", &[], "/* 没有结束; ");
+        check("select 1 - 2 / 3;", &["select 1 - 2 / 3;"], "");
+    }
+
+    #[test]
+    fn test_is_server_command() {
+        assert!(is_server_command("show tables"));
+        assert!(is_server_command("SET timeout = 100"));
+        assert!(is_server_command("shutdown"));
+        assert!(!is_server_command("select 1"));
+        assert!(!is_server_command("showx"));
+    }
+
+    #[test]
+    fn test_output_mode() {
+        assert_eq!(OutputMode::parse("CSV"), Some(OutputMode::Csv));
+        assert_eq!(OutputMode::parse("xml"), None);
+
+        let rs = ResultSet::Scan {
+            columns: vec!["a".to_string(), "b".to_string()],
+            types: vec![Some(DataType::Integer), Some(DataType::String)],
+            rows: vec![
+                vec![Value::Integer(1), Value::String("x,y".to_string())],
+                vec![Value::Integer(10), Value::Null],
+            ],
+        };
+        assert_eq!(
+            OutputMode::Table.render(&rs, DEFAULT_FLOAT_PRECISION),
+            "a  |b   \n---+-----\n 1 |x,y \n10 |null\n(2 rows)"
+        );
+        assert_eq!(
+            OutputMode::Csv.render(&rs, DEFAULT_FLOAT_PRECISION),
+            "a,b\n1,\"x,y\"\n10,"
+        );
+        assert_eq!(
+            OutputMode::Json.render(&rs, DEFAULT_FLOAT_PRECISION),
+            r#"{"columns":["a","b"],"rows":[[1,"x,y"],[10,null]],"types":["INTEGER","STRING"]}"#
+        );
+
+        // 表格中的浮点数按精度展示，CSV 保留完整的精度
+        let rs = ResultSet::Scan {
+            columns: vec!["f".to_string()],
+            types: vec![Some(DataType::Float)],
+            rows: vec![vec![Value::Float(2.0 / 3.0)]],
+        };
+        assert_eq!(
+            OutputMode::Table.render(&rs, 3),
+            "f    \n------\n0.667\n(1 rows)"
+        );
+        assert_eq!(OutputMode::Csv.render(&rs, 3), "f\n0.6666666666666666");
+
+        let rs = ResultSet::Delete { count: 3 };
+        assert_eq!(
+            OutputMode::Csv.render(&rs, DEFAULT_FLOAT_PRECISION),
+            "DELETE 3 ROWS."
+        );
+        assert_eq!(
+            OutputMode::Json.render(&rs, DEFAULT_FLOAT_PRECISION),
+            r#"{"message":"DELETE 3 ROWS."}"#
+        );
+
+        // 错误在 JSON 格式中也是一行 JSON
+        assert_eq!(
+            OutputMode::Table.render_error("42601", "unexpected end"),
+            "ERROR 42601: unexpected end"
+        );
+        assert_eq!(
+            OutputMode::Json.render_error("42601", "unexpected end"),
+            r#"{"code":"42601","error":"unexpected end"}"#
+        );
+    }
+}
@@ -1,13 +1,152 @@
-use serde::{de, ser};
+use serde::{Deserialize, Serialize, de, ser};
 use std::{fmt::Display, string::FromUtf8Error};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Error {
     Parse(String),
+    // 语法正确，但是生成执行计划时发现语句不合法，例如 Join 中重复的表名
+    Plan(String),
+    // 插入的行中值的数量和列不一致
+    InvalidRow(String),
+    // 内部错误，说明数据库本身有问题，例如不应该出现的执行结果
     Internal(String),
     WriteConflict,
+    TableNotFound(String),
+    TableExists(String),
+    // 表达式中引用的列不知道属于哪张表时 table 为 None
+    // available 为查找时可以使用的列，为空时不在错误信息中展示
+    ColumnNotFound {
+        table: Option<String>,
+        column: String,
+        available: Vec<String>,
+    },
+    // 主键或者唯一列的值重复
+    DuplicateKey {
+        table: String,
+        column: String,
+        value: String,
+    },
+    // found 为实际的值的描述，例如 FLOAT value 2.5 in row 2
+    TypeMismatch {
+        table: String,
+        column: String,
+        expected: String,
+        found: String,
+    },
+    NotNullViolation {
+        table: String,
+        column: String,
+        row: usize,
+    },
+    // 执行多条语句时，第 index 条（从 1 开始）语句的错误
+    Statement {
+        index: usize,
+        count: usize,
+        source: Box<Error>,
+    },
+    // 数据编码、解码失败
+    Serialization(String),
+    Io(String),
+    // 语句执行超时，参数为超时时间（毫秒）
+    Timeout(u64),
+    // 在只读事务中写入数据
+    ReadOnly,
+    // 服务端正在关闭，连接即将断开
+    ShuttingDown,
+    // 连接没有通过认证，参数为具体的原因
+    AuthFailed(String),
+    // 运算符和操作数的类型不匹配，例如整数列和字符串比较，在执行之前检查
+    OperatorMismatch(String),
+    // 除数为 0
+    DivisionByZero,
+    // 整数运算的结果超出 i64 的范围
+    NumericOverflow,
+    // 已经在事务中时执行 BEGIN
+    ActiveTransaction,
+    // 不在事务中时执行 COMMIT 或者 ROLLBACK
+    NoActiveTransaction,
+    // 不存在的函数，参数为函数名
+    UnknownFunction(String),
+    // 不带表名的列在多个数据源中都存在，参数为列名
+    AmbiguousColumn(String),
+    // 不在 GROUP BY 中的列出现在 select 中
+    Grouping(String),
+    // 表的定义不合法，例如没有主键
+    InvalidDefinition(String),
+    // AS OF 指定的版本还没有分配，latest 为最新的已分配版本
+    VersionNotFound {
+        version: u64,
+        latest: u64,
+    },
+    // INSERT 没有给出非空列的值，并且这个列没有默认值
+    MissingValue {
+        table: String,
+        column: String,
+        row: usize,
+    },
+    // COPY FROM 读取的文件格式不对，例如字段的个数和列不一致
+    BadCopyFormat(String),
+    // 索引名已经存在，或者列上已经有索引
+    IndexExists(String),
+    // 语法上合法，但是还不支持的用法
+    NotSupported(String),
+    // 编码后的消息超过了最大的帧长度，例如查询的结果太大
+    FrameTooLarge {
+        size: usize,
+        max: usize,
+    },
+}
+
+impl Error {
+    // 类似 SQLSTATE 的错误码，客户端根据错误码区分错误的类型，新增错误类型时不要修改已有的错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Parse(_) => "42601",
+            Error::Plan(_) => "42601",
+            Error::InvalidRow(_) => "42601",
+            Error::Internal(_) => "XX000",
+            Error::WriteConflict => "40001",
+            Error::TableNotFound(_) => "42P01",
+            Error::TableExists(_) => "42P07",
+            Error::ColumnNotFound { .. } => "42703",
+            Error::DuplicateKey { .. } => "23505",
+            Error::TypeMismatch { .. } => "42804",
+            Error::NotNullViolation { .. } => "23502",
+            Error::Statement { source, .. } => source.code(),
+            Error::Serialization(_) => "XX001",
+            Error::Io(_) => "58030",
+            Error::Timeout(_) => "57014",
+            Error::ReadOnly => "25006",
+            Error::ShuttingDown => "57P01",
+            Error::AuthFailed(_) => "28000",
+            Error::OperatorMismatch(_) => "42883",
+            Error::DivisionByZero => "22012",
+            Error::NumericOverflow => "22003",
+            Error::ActiveTransaction => "25001",
+            Error::NoActiveTransaction => "25P01",
+            Error::UnknownFunction(_) => "42883",
+            Error::AmbiguousColumn(_) => "42702",
+            Error::Grouping(_) => "42803",
+            Error::InvalidDefinition(_) => "42P16",
+            Error::MissingValue { .. } => "23502",
+            Error::VersionNotFound { .. } => "22023",
+            Error::FrameTooLarge { .. } => "54000",
+            Error::NotSupported(_) => "0A000",
+            Error::IndexExists(_) => "42P07",
+            Error::BadCopyFormat(_) => "22P04",
+        }
+    }
+
+    // 是否为数据库内部的错误，其他错误是用户的输入有问题，连接可以继续使用
+    pub fn is_internal(&self) -> bool {
+        match self {
+            Error::Internal(_) | Error::Serialization(_) | Error::Io(_) => true,
+            Error::Statement { source, .. } => source.is_internal(),
+            _ => false,
+        }
+    }
 }
 
 // impl std::fmt::Display for Error {
@@ -41,29 +180,29 @@ impl<T> From<std::sync::PoisonError<T>> for Error {
     }
 }
 
-// 将 bincode::ErrorKind（bincode 解码错误）自动转换为自定义的 Error::Internal 类型
+// 将 bincode::ErrorKind（bincode 解码错误）自动转换为自定义的 Error::Serialization 类型
 impl From<Box<bincode::ErrorKind>> for Error {
     fn from(value: Box<bincode::ErrorKind>) -> Self {
-        Error::Internal(value.to_string())
+        Error::Serialization(value.to_string())
     }
 }
 
-// 将 std::io::Error（IO 错误）自动转换为自定义的 Error::Internal 类型
+// 将 std::io::Error（IO 错误）自动转换为自定义的 Error::Io 类型
 impl From<std::io::Error> for Error {
     fn from(value: std::io::Error) -> Self {
-        Error::Internal(value.to_string())
+        Error::Io(value.to_string())
     }
 }
 
 impl From<std::array::TryFromSliceError> for Error {
     fn from(value: std::array::TryFromSliceError) -> Self {
-        Error::Internal(value.to_string())
+        Error::Serialization(value.to_string())
     }
 }
 
 impl From<FromUtf8Error> for Error {
     fn from(value: FromUtf8Error) -> Self {
-        Error::Internal(value.to_string())
+        Error::Serialization(value.to_string())
     }
 }
 
@@ -74,7 +213,7 @@ impl ser::Error for Error {
     where
         T: Display,
     {
-        Error::Internal(msg.to_string())
+        Error::Serialization(msg.to_string())
     }
 }
 
@@ -83,7 +222,7 @@ impl de::Error for Error {
     where
         T: Display,
     {
-        Error::Internal(msg.to_string())
+        Error::Serialization(msg.to_string())
     }
 }
 
@@ -91,8 +230,90 @@ impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::Parse(err) => write!(f, "parse error {}", err),
+            Error::Plan(err) => write!(f, "plan error: {}", err),
+            Error::InvalidRow(err) => write!(f, "invalid row: {}", err),
             Error::Internal(err) => write!(f, "internal error {}", err),
             Error::WriteConflict => write!(f, "write conflict, retry transaction"),
+            Error::TableNotFound(table) => write!(f, "table {} does not exist", table),
+            Error::TableExists(table) => write!(f, "table {} already exists", table),
+            Error::ColumnNotFound {
+                table,
+                column,
+                available,
+            } => {
+                write!(f, "column {} does not exist", column)?;
+                if let Some(table) = table {
+                    write!(f, " in table {}", table)?;
+                }
+                if !available.is_empty() {
+                    write!(f, " (available columns: {})", available.join(", "))?;
+                }
+                Ok(())
+            }
+            Error::DuplicateKey {
+                table,
+                column,
+                value,
+            } => write!(
+                f,
+                "duplicate value {} for key column {} in table {}",
+                value, column, table
+            ),
+            Error::TypeMismatch {
+                table,
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "column {} of table {} expects {}, got {}",
+                column, table, expected, found
+            ),
+            Error::NotNullViolation { table, column, row } => write!(
+                f,
+                "column {} of table {} is not nullable, got NULL in row {}",
+                column, table, row
+            ),
+            Error::Statement {
+                index,
+                count,
+                source,
+            } => write!(f, "statement {} of {} failed: {}", index, count, source),
+            Error::Serialization(err) => write!(f, "serialization error {}", err),
+            Error::Io(err) => write!(f, "io error {}", err),
+            Error::Timeout(ms) => write!(f, "statement timeout after {} ms", ms),
+            Error::ReadOnly => write!(f, "cannot write in a read-only transaction"),
+            Error::ShuttingDown => write!(f, "server is shutting down"),
+            Error::AuthFailed(reason) => write!(f, "authentication failed: {}", reason),
+            Error::OperatorMismatch(err) => write!(f, "operator type mismatch: {}", err),
+            Error::DivisionByZero => write!(f, "division by zero"),
+            Error::NumericOverflow => write!(f, "integer overflow"),
+            Error::ActiveTransaction => write!(f, "already in a transaction"),
+            Error::NoActiveTransaction => write!(f, "not in a transaction"),
+            Error::UnknownFunction(name) => write!(f, "function {} does not exist", name),
+            Error::AmbiguousColumn(column) => {
+                write!(f, "column reference {} is ambiguous", column)
+            }
+            Error::Grouping(err) => write!(f, "grouping error: {}", err),
+            Error::InvalidDefinition(err) => write!(f, "invalid table definition: {}", err),
+            Error::VersionNotFound { version, latest } => write!(
+                f,
+                "version {} does not exist, the latest version is {}",
+                version, latest
+            ),
+            Error::MissingValue { table, column, row } => write!(
+                f,
+                "no value given for column {} of table {} in row {}, and the column has no default",
+                column, table, row
+            ),
+            Error::NotSupported(err) => write!(f, "not supported: {}", err),
+            Error::IndexExists(err) => write!(f, "duplicate index: {}", err),
+            Error::BadCopyFormat(err) => write!(f, "bad copy format: {}", err),
+            Error::FrameTooLarge { size, max } => write!(
+                f,
+                "message too large: {} bytes exceeds the maximum frame length of {} bytes",
+                size, max
+            ),
         }
     }
 }
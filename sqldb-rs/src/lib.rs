@@ -1,3 +1,7 @@
+pub mod auth;
+pub mod client;
 pub mod error;
+pub mod protocol;
+pub mod server;
 pub mod sql;
 pub mod storage;
@@ -0,0 +1,244 @@
+use std::{marker::PhantomData, time::Duration};
+
+use bytes::{Bytes, BytesMut};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+
+use crate::{
+    error::{Error, Result},
+    sql::executor::ResultSet,
+};
+
+// 客户端发送的请求
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    // 执行 SQL 语句，或者 SHOW TABLES 等命令
+    Execute(String),
+    // 服务端开启认证时，连接上的第一个请求必须是用户名和密码
+    Auth { user: String, password: String },
+}
+
+// 服务端返回的响应，每个请求对应一个响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+    // SQL 语句的执行结果
+    Ok(ResultSet),
+    // 一次请求中包含多条语句时，按顺序排列的每条语句的执行结果
+    Batch(Vec<ResultSet>),
+    // SHOW TABLES 等命令的输出，以及 JSON 格式的结果
+    Text(String),
+    // code 为类似 SQLSTATE 的错误码，见 Error::code
+    Error { code: String, error: Error },
+}
+
+impl Response {
+    pub fn error(error: Error) -> Self {
+        Response::Error {
+            code: error.code().to_string(),
+            error,
+        }
+    }
+}
+
+// 服务端发送给客户端的消息，elapsed 为服务端处理这个请求用的时间
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reply {
+    pub response: Response,
+    pub elapsed: Duration,
+}
+
+// 默认的最大帧长度，服务端和客户端需要使用相同的配置
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 64 * 1024 * 1024;
+
+// 消息格式：4 字节的长度（大端）+ bincode 编码的消息内容
+// 消息内容中可以包含换行等任意字符，不需要额外的结束标志
+pub struct Codec<D, E> {
+    inner: LengthDelimitedCodec,
+    _marker: PhantomData<(D, E)>,
+}
+
+// 服务端解码请求、编码响应，客户端相反
+pub type ServerCodec = Codec<Request, Reply>;
+pub type ClientCodec = Codec<Reply, Request>;
+
+impl<D, E> Codec<D, E> {
+    pub fn new() -> Self {
+        Self::with_max_frame_length(DEFAULT_MAX_FRAME_LENGTH)
+    }
+
+    // 编码超过 max 的消息返回 Error::FrameTooLarge，解码时超过 max 的帧返回错误
+    pub fn with_max_frame_length(max: usize) -> Self {
+        Self {
+            inner: LengthDelimitedCodec::builder()
+                .max_frame_length(max)
+                .new_codec(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn max_frame_length(&self) -> usize {
+        self.inner.max_frame_length()
+    }
+
+    pub fn set_max_frame_length(&mut self, max: usize) {
+        self.inner.set_max_frame_length(max);
+    }
+}
+
+impl<D, E> Default for Codec<D, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: DeserializeOwned, E> Decoder for Codec<D, E> {
+    type Item = D;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<D>> {
+        match self.inner.decode(src)? {
+            Some(frame) => Ok(Some(bincode::deserialize(&frame)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<D, E: Serialize> Encoder<E> for Codec<D, E> {
+    type Error = Error;
+
+    fn encode(&mut self, item: E, dst: &mut BytesMut) -> Result<()> {
+        let frame = bincode::serialize(&item)?;
+        // 提前检查长度，失败时不会写入任何数据，连接可以继续使用
+        let max = self.max_frame_length();
+        if frame.len() > max {
+            return Err(Error::FrameTooLarge {
+                size: frame.len(),
+                max,
+            });
+        }
+        self.inner.encode(Bytes::from(frame), dst)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use std::time::Duration;
+
+    use super::{ClientCodec, Reply, Request, Response, ServerCodec};
+    use crate::{
+        error::{Error, Result},
+        sql::{
+            executor::ResultSet,
+            types::{DataType, Value},
+        },
+    };
+
+    #[test]
+    fn test_codec_round_trip() -> Result<()> {
+        let mut server = ServerCodec::new();
+        let mut client = ClientCodec::new();
+        let mut buf = BytesMut::new();
+
+        // 多个请求连续写入，按帧逐个解码
+        let requests = vec![
+            Request::Execute("insert into t values (1, 'a\nb');".to_string()),
+            Request::Execute("select * from t where b = '中文\r\n!!!end!!!';".to_string()),
+            Request::Auth {
+                user: "admin".to_string(),
+                password: "p:ss\nword".to_string(),
+            },
+        ];
+        for req in requests.iter() {
+            client.encode(req.clone(), &mut buf)?;
+        }
+        for req in requests {
+            assert_eq!(server.decode(&mut buf)?, Some(req));
+        }
+        assert_eq!(server.decode(&mut buf)?, None);
+
+        let responses = vec![
+            Response::Ok(ResultSet::Scan {
+                columns: vec!["a".to_string(), "名字".to_string()],
+                types: vec![Some(DataType::Integer), None],
+                rows: vec![vec![
+                    Value::Integer(1),
+                    Value::String("第一行\n第二行".to_string()),
+                ]],
+            }),
+            Response::Batch(vec![
+                ResultSet::Insert {
+                    count: 1,
+                    keys: vec![Value::Integer(1)],
+                },
+                ResultSet::Commit { version: 3 },
+            ]),
+            Response::Text("t1\nt2".to_string()),
+            Response::error(Error::Parse("unexpected\nnewline".to_string())),
+            Response::error(Error::TableNotFound("t".to_string())),
+        ];
+        let replies = responses
+            .into_iter()
+            .enumerate()
+            .map(|(i, response)| Reply {
+                response,
+                elapsed: Duration::from_micros(i as u64 * 1500),
+            })
+            .collect::<Vec<_>>();
+        for reply in replies.iter() {
+            server.encode(reply.clone(), &mut buf)?;
+        }
+
+        // 只收到一部分数据时等待后面的数据
+        let mut partial = buf.split_to(5);
+        assert_eq!(client.decode(&mut partial)?, None);
+        partial.unsplit(buf);
+        let mut buf = partial;
+        for reply in replies {
+            assert_eq!(client.decode(&mut buf)?, Some(reply));
+        }
+        assert!(buf.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_max_frame_length() -> Result<()> {
+        let mut server = ServerCodec::with_max_frame_length(64);
+        let mut client = ClientCodec::with_max_frame_length(64);
+        let mut buf = BytesMut::new();
+
+        // 超过最大长度的消息不会写入任何数据，之后的消息可以正常编码
+        let reply = Reply {
+            response: Response::Text("x".repeat(100)),
+            elapsed: Duration::ZERO,
+        };
+        match server.encode(reply, &mut buf) {
+            Err(Error::FrameTooLarge { max, .. }) => assert_eq!(max, 64),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(buf.is_empty());
+        let reply = Reply {
+            response: Response::Text("x".to_string()),
+            elapsed: Duration::ZERO,
+        };
+        server.encode(reply.clone(), &mut buf)?;
+        assert_eq!(client.decode(&mut buf)?, Some(reply));
+
+        // 解码时超过最大长度的帧返回错误
+        let mut large = ServerCodec::new();
+        large.encode(
+            Reply {
+                response: Response::Text("x".repeat(100)),
+                elapsed: Duration::ZERO,
+            },
+            &mut buf,
+        )?;
+        assert!(client.decode(&mut buf).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,605 @@
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::SinkExt;
+use tokio::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::watch,
+    task::{JoinHandle, JoinSet},
+};
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+
+use crate::{
+    auth::{Credentials, User},
+    error::{Error, Result},
+    protocol::{DEFAULT_MAX_FRAME_LENGTH, Reply, Request, Response, ServerCodec},
+    sql::{
+        engine::{Engine, Session},
+        executor::ResultSet,
+        parser::lexer::{Lexer, Token},
+    },
+};
+
+// 关闭服务时默认等待执行中的语句结束的时间
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+// 开启认证时，连接需要在这个时间内完成认证
+const DEFAULT_AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Possible requests our client can send us
+enum SqlRequest {
+    Sql(String),
+    ListTables,
+    TableInfo(String),
+    Format(OutputFormat),
+    // SET timeout = <ms>，0 表示不限制
+    SetTimeout(Result<u64>),
+    // SET readonly = true|false，只读的连接中不能关闭只读
+    SetReadOnly(Result<bool>),
+    // SHOW STATS 展示当前连接的统计信息，RESET STATS 清零
+    ShowStats,
+    ResetStats,
+    // SHOW STATUS 展示存储的大小和活跃事务的数量
+    ShowStatus,
+    // SHUTDOWN 关闭服务，只允许本机的连接执行
+    Shutdown,
+}
+
+/// 结果的输出格式，FORMAT JSON 之后当前连接返回 JSON
+#[derive(Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    fn result(&self, mut results: Vec<ResultSet>) -> Response {
+        match self {
+            OutputFormat::Text if results.len() == 1 => Response::Ok(results.remove(0)),
+            OutputFormat::Text => Response::Batch(results),
+            // 每条语句的结果是一行 JSON
+            OutputFormat::Json => Response::Text(
+                results
+                    .iter()
+                    .map(|rs| rs.to_json())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        }
+    }
+
+    fn message(&self, msg: String) -> Response {
+        match self {
+            OutputFormat::Text => Response::Text(msg),
+            OutputFormat::Json => Response::Text(serde_json::json!({ "message": msg }).to_string()),
+        }
+    }
+
+    fn error(&self, err: Error) -> Response {
+        // 用户的输入错误直接返回给客户端，内部错误还需要在服务端记录下来
+        if err.is_internal() {
+            eprintln!("internal error while executing request: {:?}", err);
+        }
+        match self {
+            OutputFormat::Text => Response::error(err),
+            OutputFormat::Json => Response::Text(
+                serde_json::json!({ "code": err.code(), "error": err.to_string() }).to_string(),
+            ),
+        }
+    }
+}
+
+impl SqlRequest {
+    pub fn parse(cmd: &str) -> Self {
+        // SHOW 命令允许以分号结尾
+        let upper_cmd = cmd.trim().trim_end_matches(';').trim_end().to_uppercase();
+        if upper_cmd == "SHOW TABLES" {
+            return SqlRequest::ListTables;
+        }
+        if upper_cmd.starts_with("SHOW TABLE ") {
+            // 表名和 SQL 中的标识符规则相同：不加引号时转为小写，加双引号时保留大小写
+            let name = cmd.trim().trim_end_matches(';')["SHOW TABLE ".len()..].trim();
+            if let [Ok(Token::Ident(name))] = Lexer::new(name).collect::<Vec<_>>().as_slice() {
+                return SqlRequest::TableInfo(name.clone());
+            }
+        }
+        if upper_cmd == "SHOW STATS" {
+            return SqlRequest::ShowStats;
+        }
+        if upper_cmd == "RESET STATS" {
+            return SqlRequest::ResetStats;
+        }
+        if upper_cmd == "SHOW STATUS" {
+            return SqlRequest::ShowStatus;
+        }
+        if upper_cmd == "SHUTDOWN" {
+            return SqlRequest::Shutdown;
+        }
+        if upper_cmd == "FORMAT JSON" {
+            return SqlRequest::Format(OutputFormat::Json);
+        }
+        if upper_cmd == "FORMAT TEXT" {
+            return SqlRequest::Format(OutputFormat::Text);
+        }
+        if let Some(rest) = upper_cmd.strip_prefix("SET ")
+            && let Some((name, value)) = rest.split_once('=')
+        {
+            let value = value.trim();
+            match name.trim() {
+                "TIMEOUT" => {
+                    return SqlRequest::SetTimeout(value.parse().map_err(|_| {
+                        Error::Parse(format!(
+                            "invalid timeout {}, expected milliseconds",
+                            value.to_lowercase()
+                        ))
+                    }));
+                }
+                "READONLY" => {
+                    return SqlRequest::SetReadOnly(match value {
+                        "TRUE" => Ok(true),
+                        "FALSE" => Ok(false),
+                        _ => Err(Error::Parse(format!(
+                            "invalid readonly {}, expected true or false",
+                            value.to_lowercase()
+                        ))),
+                    });
+                }
+                _ => {}
+            }
+        }
+        // 关键字和标识符不区分大小写，字符串常量需要保持原样
+        SqlRequest::Sql(cmd.trim().into())
+    }
+}
+
+// TCP 服务，通过 listen 启动后返回 ServerHandle，用于关闭服务
+// 关闭时不再接收新的连接，空闲的连接收到关闭通知后断开，
+// 执行中的语句最多等待 grace_period，之后断开剩余的连接，并把数据持久化到磁盘
+pub struct Server<E: Engine> {
+    engine: E,
+    // 每条语句的默认超时时间，每个连接可以通过 SET timeout = <ms> 修改
+    timeout: Option<Duration>,
+    grace_period: Duration,
+    // 为 None 时不需要认证，否则连接上的第一个请求必须是 Request::Auth
+    credentials: Option<Arc<Credentials>>,
+    auth_timeout: Duration,
+    // 第二个监听地址，这个地址上的连接都是只读会话
+    read_only_addr: Option<SocketAddr>,
+    // 请求和响应的最大帧长度，超过时返回 Error::FrameTooLarge
+    max_frame_length: usize,
+}
+
+impl<E> Server<E>
+where
+    E: Engine + Send + 'static,
+    E::Transaction: Send,
+{
+    pub fn new(engine: E) -> Self {
+        Self {
+            engine,
+            timeout: None,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            credentials: None,
+            auth_timeout: DEFAULT_AUTH_TIMEOUT,
+            read_only_addr: None,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    // 开启认证，只有 credentials 中的用户可以执行请求
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(Arc::new(credentials));
+        self
+    }
+
+    pub fn with_auth_timeout(mut self, auth_timeout: Duration) -> Self {
+        self.auth_timeout = auth_timeout;
+        self
+    }
+
+    // 额外监听一个只读的地址，例如给报表使用，这个地址上的连接不能修改数据
+    pub fn with_read_only_listener(mut self, addr: SocketAddr) -> Self {
+        self.read_only_addr = Some(addr);
+        self
+    }
+
+    // 客户端需要使用相同的最大帧长度，否则超过客户端限制的结果无法读取
+    pub fn with_max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    // 使用默认的配置启动服务
+    pub async fn start(addr: impl ToSocketAddrs, engine: E) -> Result<ServerHandle> {
+        Self::new(engine).listen(addr).await
+    }
+
+    // 绑定地址并在后台接收连接，端口为 0 时由系统分配，通过 ServerHandle::addr 获取
+    pub async fn listen(self, addr: impl ToSocketAddrs) -> Result<ServerHandle> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let read_only_listener = match self.read_only_addr {
+            Some(addr) => Some(TcpListener::bind(addr).await?),
+            None => None,
+        };
+        let read_only_addr = match &read_only_listener {
+            Some(listener) => Some(listener.local_addr()?),
+            None => None,
+        };
+        let (sender, receiver) = watch::channel(false);
+        let trigger = ShutdownTrigger(Arc::new(sender));
+        let task =
+            tokio::spawn(self.serve(listener, read_only_listener, trigger.clone(), receiver));
+        Ok(ServerHandle {
+            addr,
+            read_only_addr,
+            trigger,
+            task,
+        })
+    }
+
+    // 接收连接直到收到关闭信号，返回被强制断开的连接数量
+    async fn serve(
+        self,
+        listener: TcpListener,
+        read_only_listener: Option<TcpListener>,
+        trigger: ShutdownTrigger,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> Result<usize> {
+        let mut connections = JoinSet::new();
+        loop {
+            let (accepted, read_only) = tokio::select! {
+                biased;
+                _ = wait_for_shutdown(&mut shutdown) => break,
+                accepted = listener.accept() => (accepted, false),
+                accepted = accept(read_only_listener.as_ref()) => (accepted, true),
+                // 回收已经结束的连接
+                Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+            };
+            match accepted {
+                Ok((socket, peer)) => {
+                    // KVEngine 内部通过 Arc 共享存储，每个连接持有一份克隆即可
+                    let session = match ServerSession::new(
+                        &self.engine,
+                        self.timeout,
+                        peer,
+                        trigger.clone(),
+                        self.credentials.clone(),
+                        read_only,
+                    ) {
+                        Ok(session) => session,
+                        Err(e) => {
+                            println!("internal server error {:?}", e);
+                            continue;
+                        }
+                    };
+                    connections.spawn(handle_connection(
+                        session,
+                        socket,
+                        shutdown.clone(),
+                        self.auth_timeout,
+                        self.max_frame_length,
+                    ));
+                }
+                Err(e) => println!("error accepting socket; error = {e:?}"),
+            }
+        }
+
+        // 不再接收新的连接，等待执行中的语句结束
+        drop(listener);
+        drop(read_only_listener);
+        let drain = async { while connections.join_next().await.is_some() {} };
+        let _ = tokio::time::timeout(self.grace_period, drain).await;
+
+        // 超过等待时间后断开剩余的连接，未提交的事务会被回滚
+        let aborted = connections.len();
+        connections.shutdown().await;
+        self.engine.sync()?;
+        Ok(aborted)
+    }
+}
+
+// 服务的句柄，用于获取监听的地址以及关闭服务
+pub struct ServerHandle {
+    addr: SocketAddr,
+    read_only_addr: Option<SocketAddr>,
+    trigger: ShutdownTrigger,
+    task: JoinHandle<Result<usize>>,
+}
+
+impl ServerHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // 只读连接的监听地址，没有配置时为 None
+    pub fn read_only_addr(&self) -> Option<SocketAddr> {
+        self.read_only_addr
+    }
+
+    // 用于在其他任务中触发关闭，例如收到 Ctrl-C 时
+    pub fn shutdown_trigger(&self) -> ShutdownTrigger {
+        self.trigger.clone()
+    }
+
+    // 关闭服务并等待结束，返回超过等待时间后被强制断开的连接数量
+    pub async fn shutdown(self) -> Result<usize> {
+        self.trigger.shutdown();
+        self.wait().await
+    }
+
+    // 等待服务结束，服务只会在触发关闭之后结束
+    pub async fn wait(self) -> Result<usize> {
+        self.task
+            .await
+            .map_err(|e| Error::Internal(format!("server task failed: {}", e)))?
+    }
+}
+
+// 触发服务关闭，可以克隆之后在多个地方使用
+#[derive(Clone)]
+pub struct ShutdownTrigger(Arc<watch::Sender<bool>>);
+
+impl ShutdownTrigger {
+    pub fn shutdown(&self) {
+        self.0.send_replace(true);
+    }
+}
+
+// 没有监听的地址时一直等待，用于 select 中可选的监听
+async fn accept(listener: Option<&TcpListener>) -> std::io::Result<(TcpStream, SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+// 收到关闭信号时返回，发送端都已经被释放时也当作关闭处理
+async fn wait_for_shutdown(shutdown: &mut watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|shutdown| *shutdown).await;
+}
+
+struct ServerSession<E: Engine> {
+    session: Session<E>,
+    format: OutputFormat,
+    peer: SocketAddr,
+    trigger: ShutdownTrigger,
+    credentials: Option<Arc<Credentials>>,
+    // 认证通过的用户，没有开启认证时为 None
+    user: Option<User>,
+}
+
+impl<E: Engine + 'static> ServerSession<E> {
+    fn new(
+        eng: &E,
+        timeout: Option<Duration>,
+        peer: SocketAddr,
+        trigger: ShutdownTrigger,
+        credentials: Option<Arc<Credentials>>,
+        read_only: bool,
+    ) -> Result<Self> {
+        let mut session = match read_only {
+            true => eng.session_read_only()?,
+            false => eng.session()?,
+        };
+        session.set_timeout(timeout);
+        Ok(Self {
+            session,
+            format: OutputFormat::Text,
+            peer,
+            trigger,
+            credentials,
+            user: None,
+        })
+    }
+
+    // 没有开启认证，或者已经通过认证
+    fn authenticated(&self) -> bool {
+        self.credentials.is_none() || self.user.is_some()
+    }
+
+    // 校验用户名和密码，没有开启认证时直接通过，已经通过认证时不能切换用户
+    fn authenticate(&mut self, user: &str, password: &str) -> Response {
+        if self.user.is_some() {
+            return self
+                .format
+                .error(Error::AuthFailed("already authenticated".to_string()));
+        }
+        if let Some(credentials) = &self.credentials {
+            match credentials.authenticate(user, password) {
+                Ok(user) => self.user = Some(user),
+                Err(e) => return self.format.error(e),
+            }
+        }
+        self.format.message("AUTH OK".to_string())
+    }
+
+    // 权限检查的入口，目前通过认证的用户可以执行所有的请求
+    // 之后的权限系统可以在这里根据 self.user 检查每个请求
+    fn authorize(&self, _req: &SqlRequest) -> Result<()> {
+        Ok(())
+    }
+
+    // 执行请求，语句可能执行很长时间，需要在阻塞线程中调用
+    fn execute(&mut self, req: SqlRequest) -> Response {
+        if let Err(e) = self.authorize(&req) {
+            return self.format.error(e);
+        }
+        match req {
+            SqlRequest::Sql(sql) => match self.session.execute_script(&sql) {
+                Ok(rs) => self.format.result(rs),
+                Err(e) => self.format.error(e),
+            },
+            SqlRequest::ListTables => match self.session.get_table_names() {
+                Ok(names) => self.format.message(names),
+                Err(e) => self.format.error(e),
+            },
+            SqlRequest::TableInfo(table_name) => match self.session.get_table(table_name) {
+                Ok(tbinfo) => self.format.message(tbinfo),
+                Err(e) => self.format.error(e),
+            },
+            SqlRequest::SetTimeout(Ok(ms)) => {
+                self.session
+                    .set_timeout(Some(ms).filter(|ms| *ms > 0).map(Duration::from_millis));
+                self.format.message(format!("SET TIMEOUT = {}", ms))
+            }
+            SqlRequest::SetTimeout(Err(e)) => self.format.error(e),
+            SqlRequest::SetReadOnly(Ok(read_only)) => match self.session.set_read_only(read_only) {
+                Ok(()) => self.format.message(format!("SET READONLY = {}", read_only)),
+                Err(e) => self.format.error(e),
+            },
+            SqlRequest::SetReadOnly(Err(e)) => self.format.error(e),
+            SqlRequest::ShowStats => self.format.message(self.session.stats().to_string()),
+            SqlRequest::ResetStats => {
+                self.session.reset_stats();
+                self.format.message("RESET STATS".to_string())
+            }
+            SqlRequest::ShowStatus => match self.session.status() {
+                Ok(status) => self.format.message(status.to_string()),
+                Err(e) => self.format.error(e),
+            },
+            // 只读的连接不能关闭服务
+            SqlRequest::Shutdown if self.session.read_only() => self.format.error(Error::ReadOnly),
+            SqlRequest::Shutdown if self.peer.ip().is_loopback() => {
+                self.trigger.shutdown();
+                self.format.message("SHUTDOWN".to_string())
+            }
+            SqlRequest::Shutdown => self.format.error(Error::Parse(
+                "SHUTDOWN is only allowed from local connections".to_string(),
+            )),
+            SqlRequest::Format(format) => {
+                self.format = format;
+                self.format.message(match format {
+                    OutputFormat::Text => "FORMAT TEXT".to_string(),
+                    OutputFormat::Json => "FORMAT JSON".to_string(),
+                })
+            }
+        }
+    }
+}
+
+// 处理一个连接上的请求，直到客户端断开或者服务关闭
+async fn handle_connection<E>(
+    mut server_session: ServerSession<E>,
+    socket: TcpStream,
+    mut shutdown: watch::Receiver<bool>,
+    auth_timeout: Duration,
+    max_frame_length: usize,
+) where
+    E: Engine + Send + 'static,
+    E::Transaction: Send,
+{
+    let mut frames = Framed::new(socket, ServerCodec::with_max_frame_length(max_frame_length));
+    let auth_deadline = tokio::time::sleep(auth_timeout);
+    tokio::pin!(auth_deadline);
+
+    loop {
+        // 优先检查关闭信号，空闲的连接收到关闭通知后断开
+        let result = tokio::select! {
+            biased;
+            _ = wait_for_shutdown(&mut shutdown) => {
+                let reply = Reply {
+                    response: server_session.format.error(Error::ShuttingDown),
+                    elapsed: Duration::ZERO,
+                };
+                let _ = frames.send(reply).await;
+                return;
+            }
+            // 没有在规定的时间内完成认证的连接被断开
+            _ = &mut auth_deadline, if !server_session.authenticated() => {
+                let reply = Reply {
+                    response: Response::error(Error::AuthFailed("authentication timeout".to_string())),
+                    elapsed: Duration::ZERO,
+                };
+                let _ = frames.send(reply).await;
+                return;
+            }
+            result = frames.next() => match result {
+                Some(result) => result,
+                None => return,
+            },
+        };
+
+        match result {
+            // 认证失败时返回错误并断开连接
+            Ok(Request::Auth { user, password }) => {
+                let start = Instant::now();
+                let response = server_session.authenticate(&user, &password);
+                let reply = Reply {
+                    response,
+                    elapsed: start.elapsed(),
+                };
+                if frames.send(reply).await.is_err() || !server_session.authenticated() {
+                    return;
+                }
+            }
+            Ok(Request::Execute(_)) if !server_session.authenticated() => {
+                let reply = Reply {
+                    response: Response::error(Error::AuthFailed(
+                        "authentication required".to_string(),
+                    )),
+                    elapsed: Duration::ZERO,
+                };
+                let _ = frames.send(reply).await;
+                return;
+            }
+            Ok(Request::Execute(line)) => {
+                let start = Instant::now();
+                // 解析并得到 SqlResquest
+                let req = SqlRequest::parse(&line);
+
+                // 在阻塞线程中执行请求，不影响同一个线程上的其他连接以及关闭信号的处理
+                // 连接被强制断开时语句会继续执行到结束，之后 session 被释放，未提交的事务被回滚
+                let response;
+                (server_session, response) = match tokio::task::spawn_blocking(move || {
+                    let response = server_session.execute(req);
+                    (server_session, response)
+                })
+                .await
+                {
+                    Ok(result) => result,
+                    Err(e) => {
+                        println!("internal server error {:?}", e);
+                        return;
+                    }
+                };
+
+                // 发送执行结果，以及处理这个请求用的时间
+                let reply = Reply {
+                    response,
+                    elapsed: start.elapsed(),
+                };
+                match frames.send(reply).await {
+                    // 结果太大时没有发送任何数据，返回错误，客户端不会一直等待
+                    Err(err @ Error::FrameTooLarge { .. }) => {
+                        let reply = Reply {
+                            response: server_session.format.error(err),
+                            elapsed: start.elapsed(),
+                        };
+                        if let Err(e) = frames.send(reply).await {
+                            println!("error on sending response; error = {e:?}");
+                        }
+                    }
+                    Err(e) => println!("error on sending response; error = {e:?}"),
+                    Ok(()) => {}
+                }
+            }
+            Err(e) => {
+                println!("error on decoding from socket; error = {e:?}");
+            }
+        }
+    }
+}
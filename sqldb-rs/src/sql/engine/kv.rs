@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -6,12 +8,19 @@ use crate::error::Result;
 use crate::sql::engine::Engine;
 use crate::sql::engine::Transaction;
 use crate::sql::parser::ast::Expression;
-use crate::sql::parser::ast::evaluate_expr;
+use crate::sql::parser::ast::evaluate_expr_single;
+use crate::sql::schema::Index;
 use crate::sql::schema::Table;
+use crate::sql::types::DataType;
 use crate::sql::types::Row;
+use crate::sql::types::Rows;
 use crate::sql::types::Value;
 use crate::storage::keycode_se::serialize_key;
-use crate::storage::{self, engine::Engine as StorageEngine};
+use crate::storage::{
+    self,
+    engine::{Engine as StorageEngine, WriteBatch},
+    mvcc::MvccStats,
+};
 
 #[allow(dead_code)]
 pub struct KVEngine<E: StorageEngine> {
@@ -41,6 +50,26 @@ impl<E: StorageEngine> Engine for KVEngine<E> {
     fn begin(&self) -> Result<Self::Transaction> {
         Ok(Self::Transaction::new(self.storage_mvcc.begin()?))
     }
+
+    fn begin_read_only(&self, as_of: Option<u64>) -> Result<Self::Transaction> {
+        let txn = match as_of {
+            Some(version) => self.storage_mvcc.begin_read_only_at(version)?,
+            None => self.storage_mvcc.begin_read_only()?,
+        };
+        Ok(Self::Transaction::new(txn))
+    }
+
+    fn vacuum(&self) -> Result<usize> {
+        self.storage_mvcc.vacuum()
+    }
+
+    fn status(&self) -> Result<MvccStats> {
+        self.storage_mvcc.stats()
+    }
+
+    fn sync(&self) -> Result<()> {
+        self.storage_mvcc.sync()
+    }
 }
 
 pub struct KVTransaction<E: StorageEngine> {
@@ -52,6 +81,118 @@ impl<E: StorageEngine> KVTransaction<E> {
     pub fn new(txn: storage::mvcc::MvccTransaction<E>) -> Self {
         Self { txn }
     }
+
+    // 写入一行数据的所有索引条目，索引 key 中包含主键，value 为主键
+    fn set_index_entries(&self, table: &Table, id: &Value, row: &Row) -> Result<()> {
+        self.txn.set_batch(Self::index_entries(table, id, row)?)
+    }
+
+    // 构造一行数据的所有索引条目
+    fn index_entries(table: &Table, id: &Value, row: &Row) -> Result<WriteBatch> {
+        let mut entries = Vec::with_capacity(table.indexes.len());
+        for index in &table.indexes {
+            let col_index = table.get_col_index(&index.column)?;
+            let key_enc = Key::Index(
+                table.name.clone(),
+                index.column.clone(),
+                row[col_index].clone(),
+                id.clone(),
+            )
+            .encode()?;
+            entries.push((key_enc, Some(bincode::serialize(id)?)));
+        }
+        Ok(entries)
+    }
+
+    // 校验行的有效性，row_num 为这一行在本批次中的序号（从 1 开始）
+    fn check_row(&self, table: &Table, row: &Row, row_num: usize) -> Result<()> {
+        if row.len() != table.columns.len() {
+            return Err(Error::InvalidRow(format!(
+                "row {} for table {} has {} values, expected {}",
+                row_num,
+                table.name,
+                row.len(),
+                table.columns.len()
+            )));
+        }
+        for (i, col) in table.columns.iter().enumerate() {
+            match row[i].datatype() {
+                None if col.nullable => {}
+                None => {
+                    return Err(Error::NotNullViolation {
+                        table: table.name.clone(),
+                        column: col.name.clone(),
+                        row: row_num,
+                    });
+                }
+                Some(dt) if dt != col.datatype => {
+                    return Err(Error::TypeMismatch {
+                        table: table.name.clone(),
+                        column: col.name.clone(),
+                        expected: col.datatype.to_string(),
+                        found: format!("{} value {} in row {}", dt, row[i], row_num),
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // NaN 和任何值都不相等，无法比较和排序，不允许写入
+    fn check_nan(&self, table: &Table, row: &Row) -> Result<()> {
+        for (col, value) in table.columns.iter().zip(row.iter()) {
+            if let Value::Float(f) = value
+                && f.is_nan()
+            {
+                return Err(Error::TypeMismatch {
+                    table: table.name.clone(),
+                    column: col.name.clone(),
+                    expected: col.datatype.to_string(),
+                    found: "NaN".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // 唯一约束检查，id 为当前行的主键，NULL 值可以重复
+    fn check_unique(&self, table: &Table, id: &Value, row: &Row) -> Result<()> {
+        for (i, col) in table.columns.iter().enumerate() {
+            if !col.unique || col.primary_key || row[i] == Value::Null {
+                continue;
+            }
+            // 有索引时通过索引查找，否则扫描全表
+            let rows = match table.get_index(&col.name) {
+                Some(_) => self.scan_index(table.name.clone(), col.name.clone(), row[i].clone())?,
+                None => self
+                    .scan_table(table.name.clone(), None)?
+                    .collect::<Result<_>>()?,
+            };
+            for other in rows {
+                if other[i] == row[i] && table.get_primary_key(&other)? != *id {
+                    return Err(duplicate_key(table, &col.name, &row[i]));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 删除一行数据的所有索引条目
+    fn delete_index_entries(&self, table: &Table, id: &Value, row: &Row) -> Result<()> {
+        for index in &table.indexes {
+            let col_index = table.get_col_index(&index.column)?;
+            let key_enc = Key::Index(
+                table.name.clone(),
+                index.column.clone(),
+                row[col_index].clone(),
+                id.clone(),
+            )
+            .encode()?;
+            self.txn.delete(key_enc)?;
+        }
+        Ok(())
+    }
 }
 
 impl<E: StorageEngine> Transaction for KVTransaction<E> {
@@ -69,95 +210,130 @@ impl<E: StorageEngine> Transaction for KVTransaction<E> {
         self.txn.version()
     }
 
-    fn create_row(&mut self, table_name: String, row: Row) -> Result<()> {
+    fn create_rows(&mut self, table_name: String, rows: Vec<Row>) -> Result<()> {
         let table = self.must_get_table(table_name.clone())?;
-        // 校验行的有效性
-        for (i, col) in table.columns.iter().enumerate() {
-            match row[i].datatype() {
-                None if col.nullable => {}
-                None => {
-                    return Err(Error::Internal(format!(
-                        "column {} is not nullable",
-                        col.name
-                    )));
+        // 本批次中已经写入的主键和唯一列的值，这些数据还没有写入存储，需要单独检查冲突
+        let mut batch_keys = HashSet::new();
+        let mut batch_unique = HashSet::new();
+        let mut entries = Vec::new();
+
+        for (row_idx, row) in rows.into_iter().enumerate() {
+            self.check_row(&table, &row, row_idx + 1)?;
+            self.check_nan(&table, &row)?;
+
+            // 找到主键
+            let primary_val = table.get_primary_key(&row)?;
+
+            // 主键冲突检查
+            let id_enc = Key::Row(table_name.clone(), primary_val.clone()).encode()?;
+            // 如何主键冲突报错，通过 MVCC 读取，已经删除的数据（包括当前事务删除的）不算冲突
+            if batch_keys.contains(&id_enc) || self.txn.get(id_enc.clone())?.is_some() {
+                return Err(duplicate_key(
+                    &table,
+                    primary_key_name(&table),
+                    &primary_val,
+                ));
+            }
+
+            // 唯一约束检查
+            self.check_unique(&table, &primary_val, &row)?;
+            for (i, col) in table.columns.iter().enumerate() {
+                if !col.unique || col.primary_key || row[i] == Value::Null {
+                    continue;
                 }
-                Some(dt) if dt != col.datatype => {
-                    return Err(Error::Internal(format!(
-                        "column {} has wrong type",
-                        col.name
-                    )));
+                if !batch_unique.insert((i, bincode::serialize(&row[i])?)) {
+                    return Err(duplicate_key(&table, &col.name, &row[i]));
                 }
-                _ => {}
             }
-        }
-
-        // 找到主键
-        let primary_val = table.get_primary_key(&row)?;
 
-        // 主键冲突检查
-        let id_enc = Key::Row(table_name.clone(), primary_val.clone()).encode()?;
-        // 如何主键冲突报错
-        if self.txn.get(id_enc.clone())?.is_some() {
-            return Err(Error::Internal(format!(
-                "Duplicate data for primary key {} in table {}",
-                primary_val, table_name
-            )));
+            // 存储数据和索引
+            entries.extend(Self::index_entries(&table, &primary_val, &row)?);
+            entries.push((id_enc.clone(), Some(bincode::serialize(&row)?)));
+            batch_keys.insert(id_enc);
         }
 
-        // 存储数据
-        // let id = Key::Row(table_name.clone(), row[0].clone());
-        let value = bincode::serialize(&row)?;
-        self.txn.set(id_enc, value)?;
-
-        Ok(())
+        // 所有行一次性写入
+        self.txn.set_batch(entries)
     }
 
     fn update_row(&mut self, table: &Table, id: &Value, row: Row) -> Result<()> {
+        self.check_nan(table, &row)?;
+        self.check_unique(table, id, &row)?;
+
+        // 更新了主键时，新的主键不能和已有的数据冲突
         let new_pk = table.get_primary_key(&row)?;
+        if *id != new_pk && self.read_row_by_pk(table, &new_pk)?.is_some() {
+            return Err(duplicate_key(table, primary_key_name(table), &new_pk));
+        }
+
+        // 删除旧数据的索引条目
+        if !table.indexes.is_empty()
+            && let Some(old_row) = self.read_row_by_pk(table, id)?
+        {
+            self.delete_index_entries(table, id, &old_row)?;
+        }
+
         // 更新了主键，则删除旧的数据
         if *id != new_pk {
             let key_enc = Key::Row(table.name.clone(), id.clone()).encode()?;
             self.txn.delete(key_enc)?;
         }
 
-        let key_enc = Key::Row(table.name.clone(), new_pk).encode()?;
+        let key_enc = Key::Row(table.name.clone(), new_pk.clone()).encode()?;
         let val_enc = bincode::serialize(&row)?;
         self.txn.set(key_enc, val_enc)?;
+
+        self.set_index_entries(table, &new_pk, &row)?;
         Ok(())
     }
 
     fn delete_row(&mut self, table: &Table, id: &Value) -> Result<()> {
+        if !table.indexes.is_empty()
+            && let Some(old_row) = self.read_row_by_pk(table, id)?
+        {
+            self.delete_index_entries(table, id, &old_row)?;
+        }
+
         let key_enc = Key::Row(table.name.clone(), id.clone()).encode()?;
         self.txn.delete(key_enc)?;
         Ok(())
     }
 
-    fn scan_table(&self, table_name: String, filter: Option<Expression>) -> Result<Vec<Row>> {
+    fn read_row_by_pk(&self, table: &Table, pk: &Value) -> Result<Option<Row>> {
+        let key_enc = Key::Row(table.name.clone(), pk.clone()).encode()?;
+        let row = self
+            .txn
+            .get(key_enc)?
+            .map(|bytes| bincode::deserialize(&bytes))
+            .transpose()?;
+        Ok(row)
+    }
+
+    fn scan_table(&self, table_name: String, filter: Option<Expression>) -> Result<Rows<'_>> {
         let table = self.must_get_table(table_name.clone())?;
         let prefix_enc = KeyPrefix::Row(table_name.clone()).encode()?;
         let results = self.txn.scan_prefix(prefix_enc)?;
-
-        let mut rows = Vec::new();
-        for result in results {
-            // 过滤数据
-            let row: Row = bincode::deserialize(&result.value)?;
-            if let Some(expr) = &filter {
-                // let col_index = table.get_col_index(&col)?;
-                // if Value::from_expression(expr.clone()) == row[col_index] {
-                //     rows.push(row);
-                // }
-                let cols = table.columns.iter().map(|c| c.name.clone()).collect();
-                match evaluate_expr(expr, &cols, &row, &cols, &row)? {
-                    Value::Null => {}
-                    Value::Boolean(false) => {}
-                    Value::Boolean(true) => rows.push(row),
-                    _ => return Err(Error::Internal("Unexpected expression".into())),
-                }
-            } else {
-                rows.push(row);
+        let cols = table.qualified_columns(&table.name);
+
+        // MVCC 层已经把表中所有可见行的原始数据读到了内存中，这里只有反序列化和过滤是按需进行的：
+        // 读取到某一行时才反序列化并过滤，上层不再需要数据时（例如 LIMIT）后面的行不会被反序列化
+        let rows = results.into_iter().filter_map(move |result| {
+            let row: Row = match bincode::deserialize(&result.value) {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err.into())),
+            };
+            let expr = match &filter {
+                Some(expr) => expr,
+                None => return Some(Ok(row)),
+            };
+            match evaluate_expr_single(expr, &cols, &row) {
+                Ok(Value::Null) | Ok(Value::Boolean(false)) => None,
+                Ok(Value::Boolean(true)) => Some(Ok(row)),
+                Ok(_) => Some(Err(Error::Internal("Unexpected expression".into()))),
+                Err(err) => Some(Err(err)),
             }
-        }
-        Ok(rows)
+        });
+        Ok(Box::new(rows))
     }
 
     fn get_table_names(&self) -> Result<Vec<String>> {
@@ -174,10 +350,7 @@ impl<E: StorageEngine> Transaction for KVTransaction<E> {
     fn create_table(&mut self, table: Table) -> Result<()> {
         // 判断表是否存在
         if self.get_table(table.name.clone())?.is_some() {
-            return Err(Error::Internal(format!(
-                "table {} already exists.",
-                table.name
-            )));
+            return Err(Error::TableExists(table.name.clone()));
         }
 
         // 判断表是否有效
@@ -190,6 +363,91 @@ impl<E: StorageEngine> Transaction for KVTransaction<E> {
         Ok(())
     }
 
+    fn drop_table(&mut self, table_name: String) -> Result<()> {
+        // 表不存在则报错
+        let table = self.must_get_table(table_name)?;
+
+        // 删除表中的所有数据以及索引
+        self.truncate_table(&table)?;
+
+        // 删除表的元数据
+        self.txn
+            .delete(Key::TableMeta(table.name.clone()).encode()?)?;
+        let key_enc = Key::Table(table.name).encode()?;
+        self.txn.delete(key_enc)?;
+
+        Ok(())
+    }
+
+    fn truncate_table(&mut self, table: &Table) -> Result<usize> {
+        // 按前缀删除，不需要读取每一行计算主键和索引列的值
+        for index in &table.indexes {
+            let prefix_enc = KeyPrefix::index_column(table.name.clone(), index.column.clone())?;
+            self.txn.delete_prefix(prefix_enc)?;
+        }
+        let prefix_enc = KeyPrefix::Row(table.name.clone()).encode()?;
+        self.txn.delete_prefix(prefix_enc)
+    }
+
+    fn create_index(&mut self, table_name: String, index: Index) -> Result<()> {
+        let mut table = self.must_get_table(table_name)?;
+
+        // 校验索引
+        if table.indexes.iter().any(|i| i.name == index.name) {
+            return Err(Error::IndexExists(format!(
+                "index {} already exists in table {}",
+                index.name, table.name
+            )));
+        }
+        if let Some(existing) = table.get_index(&index.column) {
+            return Err(Error::IndexExists(format!(
+                "column {} in table {} already has index {}",
+                index.column, table.name, existing.name
+            )));
+        }
+        let col_index = table.get_col_index(&index.column)?;
+        if table.columns[col_index].datatype == DataType::Float {
+            return Err(Error::NotSupported(format!(
+                "index on FLOAT column {}",
+                index.column
+            )));
+        }
+
+        // 为已有的数据建立索引
+        table.indexes.push(index);
+        let prefix_enc = KeyPrefix::Row(table.name.clone()).encode()?;
+        for result in self.txn.scan_prefix(prefix_enc)? {
+            let row: Row = bincode::deserialize(&result.value)?;
+            self.set_index_entries(&table, &table.get_primary_key(&row)?, &row)?;
+        }
+
+        // 保存表的元数据
+        let key_enc = Key::Table(table.name.clone()).encode()?;
+        self.txn.set(key_enc, bincode::serialize(&table)?)?;
+
+        Ok(())
+    }
+
+    fn scan_index(&self, table_name: String, column: String, value: Value) -> Result<Vec<Row>> {
+        let table = self.must_get_table(table_name)?;
+        let prefix_enc = KeyPrefix::Index(table.name.clone(), column, value).encode()?;
+
+        let mut rows = Vec::new();
+        for result in self.txn.scan_prefix(prefix_enc)? {
+            let id: Value = bincode::deserialize(&result.value)?;
+            match self.read_row_by_pk(&table, &id)? {
+                Some(row) => rows.push(row),
+                None => {
+                    return Err(Error::Internal(format!(
+                        "index entry points to missing row {} in table {}",
+                        id, table.name
+                    )));
+                }
+            }
+        }
+        Ok(rows)
+    }
+
     fn get_table(&self, table_name: String) -> Result<Option<Table>> {
         let key_enc = Key::Table(table_name).encode()?;
         let v = self
@@ -199,12 +457,47 @@ impl<E: StorageEngine> Transaction for KVTransaction<E> {
             .transpose()?;
         Ok(v)
     }
+
+    fn get_auto_increment(&self, table: &Table) -> Result<i64> {
+        let key_enc = Key::TableMeta(table.name.clone()).encode()?;
+        match self.txn.get(key_enc)? {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(0),
+        }
+    }
+
+    // 并发的事务同时分配自增值时，后写入计数器的事务会遇到写冲突
+    fn set_auto_increment(&mut self, table: &Table, value: i64) -> Result<()> {
+        let key_enc = Key::TableMeta(table.name.clone()).encode()?;
+        self.txn.set(key_enc, bincode::serialize(&value)?)
+    }
+}
+
+// 主键或者唯一列上出现重复的值
+fn duplicate_key(table: &Table, column: &str, value: &Value) -> Error {
+    Error::DuplicateKey {
+        table: table.name.clone(),
+        column: column.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn primary_key_name(table: &Table) -> &str {
+    table
+        .columns
+        .iter()
+        .find(|c| c.primary_key)
+        .map_or("", |c| &c.name)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 enum Key {
     Table(String),
     Row(String, Value),
+    // 表名，列名，列值，主键
+    Index(String, String, Value, Value),
+    // 表的自增计数器
+    TableMeta(String),
 }
 
 impl Key {
@@ -222,20 +515,36 @@ enum KeyPrefix {
     /// 反序列化器在拿到前缀 0 后，发现后面没有数据，它既可能是“完整的 Key::Table（但数据缺失，报错）”，也可能是“KeyPrefix::Table”。二者无法区分。
     Table, // 对齐 枚举 Key，序列化占位 (Key::Table(s) 与 KeyPrefix::Table 在序列化后生成的字节前缀 必须不同，否则反序列化时无法区分“这是一个完整的 Key”还是“这是一个前缀”。)
     Row(String),
+    Index(String, String, Value),
 }
 
 impl KeyPrefix {
     pub fn encode(&self) -> Result<Vec<u8>> {
         serialize_key(self)
     }
+
+    // 一列所有索引条目的前缀，即去掉 KeyPrefix::Index 最后的列值，Value::Null 编码后只有一个字节
+    fn index_column(table_name: String, column: String) -> Result<Vec<u8>> {
+        let mut prefix = KeyPrefix::Index(table_name, column, Value::Null).encode()?;
+        prefix.pop();
+        Ok(prefix)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::{Duration, Instant};
+
     use super::KVEngine;
     use crate::{
         error::{Error, Result},
-        sql::{engine::Engine, executor::ResultSet, types::Value},
+        sql::{
+            engine::{Engine, Stats, Transaction},
+            executor::ResultSet,
+            parser::Parser,
+            plan::{Node, Plan},
+            types::{DataType, Value},
+        },
         storage::{disk::DiskEngine, memory::MemoryEngine},
     };
 
@@ -262,6 +571,17 @@ mod tests {
         Ok(())
     }
 
+    // 执行查询，返回结果中第一列的所有数据
+    fn first_column<E: crate::storage::engine::Engine + 'static>(
+        s: &mut crate::sql::engine::Session<KVEngine<E>>,
+        sql: &str,
+    ) -> Result<Vec<Value>> {
+        match s.execute(sql)? {
+            ResultSet::Scan { rows, .. } => Ok(rows.into_iter().map(|r| r[0].clone()).collect()),
+            rs => Err(Error::Internal(format!("unexpected result set {:?}", rs))),
+        }
+    }
+
     #[test]
     fn test_create_table() -> Result<()> {
         let kv_engine = KVEngine::new(MemoryEngine::new());
@@ -276,7 +596,7 @@ mod tests {
 
         // 添加断言验证结果
         match &select_result {
-            crate::sql::engine::ResultSet::Scan { columns, rows } => {
+            crate::sql::engine::ResultSet::Scan { columns, rows, .. } => {
                 assert_eq!(columns, &["a", "b", "c"]);
                 assert_eq!(rows.len(), 1);
                 let row = &rows[0];
@@ -291,6 +611,11 @@ mod tests {
         // 构建期望的 ResultSet::Scan 进行比较
         let expected = crate::sql::engine::ResultSet::Scan {
             columns: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            types: vec![
+                Some(DataType::Integer),
+                Some(DataType::String),
+                Some(DataType::Integer),
+            ],
             rows: vec![vec![
                 crate::sql::types::Value::Integer(1),
                 crate::sql::types::Value::String("a".to_string()),
@@ -324,6 +649,11 @@ mod tests {
         println!("select result after update properties: {:?}", result_set);
         let expected = crate::sql::engine::ResultSet::Scan {
             columns: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            types: vec![
+                Some(DataType::Integer),
+                Some(DataType::String),
+                Some(DataType::Integer),
+            ],
             rows: vec![
                 vec![
                     crate::sql::types::Value::Integer(1),
@@ -355,6 +685,11 @@ mod tests {
         println!("result_set: {:?}", result_set);
         let expected = crate::sql::engine::ResultSet::Scan {
             columns: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            types: vec![
+                Some(DataType::Integer),
+                Some(DataType::String),
+                Some(DataType::Integer),
+            ],
             rows: vec![
                 vec![
                     crate::sql::types::Value::Integer(1),
@@ -389,7 +724,7 @@ mod tests {
         session.execute("insert into t1 values(3, 'c', 3);")?;
         session.execute("delete from t1;")?;
 
-        if let Ok(ResultSet::Scan { columns, rows }) = session.execute("select * from t1;") {
+        if let Ok(ResultSet::Scan { columns, rows, .. }) = session.execute("select * from t1;") {
             assert_eq!(columns, vec!["a", "b", "c"]);
             assert_eq!(rows.len(), 0);
         } else {
@@ -402,7 +737,7 @@ mod tests {
         session.execute("delete from t1 where a = 2;")?;
 
         match session.execute("select * from t1;") {
-            Ok(ResultSet::Scan { columns, rows }) => {
+            Ok(ResultSet::Scan { columns, rows, .. }) => {
                 assert_eq!(columns, vec!["a", "b", "c"]);
                 assert_eq!(rows.len(), 2);
                 Ok(())
@@ -426,7 +761,7 @@ mod tests {
         s.execute("insert into t3 values (7, 87, 82, 9.52);")?;
 
         match s.execute("select * from t3 order by d, c desc;")? {
-            ResultSet::Scan { columns: _, rows } => {
+            ResultSet::Scan { rows, .. } => {
                 for r in rows {
                     println!("{:?}", r);
                 }
@@ -454,7 +789,7 @@ mod tests {
         s.execute("insert into t3 values (7, 87, 82, 9.52);")?;
 
         match s.execute("select * from t3 order by a limit 3 offset 2;")? {
-            ResultSet::Scan { columns: _, rows } => {
+            ResultSet::Scan { rows, .. } => {
                 for r in rows {
                     println!("{:?}", r);
                 }
@@ -467,6 +802,75 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_select_limit_offset_boundary() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b int);")?;
+        s.execute("insert into t1 values (3, 30), (1, 10), (5, 50), (2, 20), (4, 40);")?;
+
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // 没有 order by 时按主键顺序扫描，多次执行结果相同
+        assert_eq!(
+            first_column(&mut s, "select a from t1 limit 2;")?,
+            ints(&[1, 2])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 limit 2 offset 2;")?,
+            ints(&[3, 4])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 limit 2, 2;")?,
+            ints(&[3, 4])
+        );
+
+        // offset 写在前面时依然先跳过再截取
+        assert_eq!(
+            first_column(&mut s, "select a from t1 order by b desc offset 1 limit 3;")?,
+            ints(&[4, 3, 2])
+        );
+
+        // limit 0 和 offset 超过行数时返回空结果，列信息不变
+        match s.execute("select a, b from t1 limit 0;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["a".to_string(), "b".to_string()]);
+                assert!(rows.is_empty());
+            }
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+        assert_eq!(
+            first_column(&mut s, "select a from t1 offset 5;")?,
+            ints(&[])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 offset 100 limit 1;")?,
+            ints(&[])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 offset 4 limit 100;")?,
+            ints(&[5])
+        );
+
+        // 聚合之后也可以 limit/offset
+        assert_eq!(
+            first_column(&mut s, "select count(a) from t1 offset 1;")?,
+            ints(&[])
+        );
+
+        // 非法的值在执行之前报错
+        assert!(matches!(
+            s.execute("select a from t1 limit -1;"),
+            Err(Error::Plan(_))
+        ));
+        assert!(matches!(
+            s.execute("select a from t1 offset 'abc';"),
+            Err(Error::Plan(_))
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn test_select_as() -> Result<()> {
         let p = tempfile::tempdir()?.keep().join("sqldb-log");
@@ -482,7 +886,7 @@ mod tests {
         s.execute("insert into t3 values (7, 87, 82, 9.52);")?;
 
         match s.execute("select a from t3 order by a limit 3 offset 2;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 for col in &columns {
                     print!("{} ", col);
                 }
@@ -497,7 +901,7 @@ mod tests {
         match s.execute(
             "select a as aa, b as bb, c as cc, d as dd from t3 order by a limit 3 offset 2;",
         )? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 for col in &columns {
                     print!("{} ", col);
                 }
@@ -530,7 +934,7 @@ mod tests {
         s.execute("insert into t2 values(20, 'y');")?;
 
         match s.execute("select * from t1 cross join t2;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 for col in &columns {
                     print!("{} ", col);
                 }
@@ -543,7 +947,7 @@ mod tests {
         }
 
         match s.execute("select * from t1 cross join t2;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 // 检查列名
                 assert_eq!(columns, vec!["a", "b", "c", "x", "y"]);
 
@@ -643,7 +1047,7 @@ mod tests {
         s.execute("insert into t2 values(30, 'z');")?;
 
         match s.execute("select * from t1 join t2 on a = x;") {
-            Ok(ResultSet::Scan { columns, rows }) => {
+            Ok(ResultSet::Scan { columns, rows, .. }) => {
                 for col in &columns {
                     print!("{} ", col);
                 }
@@ -660,7 +1064,7 @@ mod tests {
         }
 
         match s.execute("select * from t1 left join t2 on a = x;") {
-            Ok(ResultSet::Scan { columns, rows }) => {
+            Ok(ResultSet::Scan { columns, rows, .. }) => {
                 for col in &columns {
                     print!("{} ", col);
                 }
@@ -677,7 +1081,7 @@ mod tests {
         }
 
         match s.execute("select * from t1 right join t2 on a = x;") {
-            Ok(ResultSet::Scan { columns, rows }) => {
+            Ok(ResultSet::Scan { columns, rows, .. }) => {
                 for col in &columns {
                     print!("{} ", col);
                 }
@@ -711,7 +1115,7 @@ mod tests {
         s.execute("insert into t1 values(30, 'd', 4);")?;
 
         match s.execute("select count(a) from t1;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ count ------");
 
@@ -723,7 +1127,7 @@ mod tests {
         }
 
         match s.execute("select max(a) from t1;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ max ------");
 
@@ -735,7 +1139,7 @@ mod tests {
         }
 
         match s.execute("select min(a) from t1;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ min ------");
 
@@ -747,7 +1151,7 @@ mod tests {
         }
 
         match s.execute("select sum(a) from t1;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ sum ------");
 
@@ -759,7 +1163,7 @@ mod tests {
         }
 
         match s.execute("select avg(a) from t1;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ avg ------");
 
@@ -790,7 +1194,7 @@ mod tests {
         s.execute("insert into t1 values(6, 'dd', 1.4);")?;
 
         match s.execute("select a  from t1 group by a;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ group by ------");
 
@@ -802,7 +1206,7 @@ mod tests {
         }
 
         match s.execute("select b from t1 group by b;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ group by ------");
 
@@ -814,7 +1218,7 @@ mod tests {
         }
 
         match s.execute("select b, min(c), max(a), avg(c) from t1 group by b;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ group by ------");
 
@@ -844,7 +1248,7 @@ mod tests {
         s.execute("insert into t1 values(6, 'dd', 1.4);")?;
 
         match s.execute("select * from t1 where a = 3;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ group by ------");
 
@@ -856,7 +1260,7 @@ mod tests {
         }
 
         match s.execute("select * from t1 where a > 3;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ group by ------");
 
@@ -868,7 +1272,7 @@ mod tests {
         }
 
         match s.execute("select * from t1 where a < 3;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ group by ------");
 
@@ -880,7 +1284,7 @@ mod tests {
         }
 
         match s.execute("select b, sum(c) from t1 group by b having sum > 5;")? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 println!("columns: {:?}", columns);
                 println!("------ group by ------");
 
@@ -896,4 +1300,3746 @@ mod tests {
         std::fs::remove_dir_all(p.parent().unwrap())?;
         Ok(())
     }
+
+    #[test]
+    fn test_select_without_from() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        match s.execute("select 1;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["1"]);
+                assert_eq!(rows, vec![vec![Value::Integer(1)]]);
+            }
+            _ => unreachable!(),
+        }
+
+        match s.execute("select 1 as one, 'hello', 2.5 as f, null;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["one", "'hello'", "f", "NULL"]);
+                assert_eq!(
+                    rows,
+                    vec![vec![
+                        Value::Integer(1),
+                        Value::String("hello".to_string()),
+                        Value::Float(2.5),
+                        Value::Null,
+                    ]]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // 没有 from 子句时不能引用列
+        assert!(s.execute("select a;").is_err());
+        assert!(s.execute("select *;").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_table() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        s.execute("create table t1 (a int primary key, b text);")?;
+        s.execute("create table t11 (a int primary key, b text);")?;
+        s.execute("insert into t1 values (1, 'a'), (2, 'b');")?;
+        s.execute("insert into t11 values (1, 'a');")?;
+
+        assert_eq!(
+            s.execute("drop table t1;")?,
+            ResultSet::DropTable {
+                table_name: "t1".to_string()
+            }
+        );
+        assert!(s.execute("select * from t1;").is_err());
+        assert_eq!(s.get_table_names()?, "t11");
+
+        // 相同前缀的表数据不受影响
+        match s.execute("select * from t11;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows.len(), 1),
+            _ => unreachable!(),
+        }
+
+        // 不存在的表
+        assert!(s.execute("drop table t1;").is_err());
+        s.execute("drop table if exists t1;")?;
+
+        // 重新创建同名表，旧数据已经被删除
+        s.execute("create table t1 (a int primary key, b text);")?;
+        match s.execute("select * from t1;")? {
+            ResultSet::Scan { rows, .. } => assert!(rows.is_empty()),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_clause() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        s.execute("create table t1 (a int primary key, b int, c text);")?;
+        s.execute("insert into t1 values (1, 10, 'x'), (2, 20, 'y'), (3, null, 'x');")?;
+        s.execute("insert into t1 values (6, 5, 'z'), (7, 8, null), (8, 30, 'y');")?;
+
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a >= 6;")?,
+            ints(&[6, 7, 8])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a <= 2;")?,
+            ints(&[1, 2])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where c != 'x';")?,
+            ints(&[2, 6, 8])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 where a > 5 and b < 10 or c = 'x';"
+            )?,
+            ints(&[1, 3, 6, 7])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 where a > 5 and (b < 10 or c = 'x');"
+            )?,
+            ints(&[6, 7])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where not a > 2;")?,
+            ints(&[1, 2])
+        );
+        // NULL 参与比较结果为 NULL，NOT NULL 仍然是 NULL
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where not b > 9;")?,
+            ints(&[6, 7])
+        );
+        // true OR NULL 为 true
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b > 9 or a = 3;")?,
+            ints(&[1, 2, 3, 8])
+        );
+
+        s.execute("update t1 set c = 'w' where b >= 20 and c = 'y';")?;
+        s.execute("delete from t1 where c = 'w' or b < 6;")?;
+        assert_eq!(first_column(&mut s, "select a from t1;")?, ints(&[1, 3, 7]));
+
+        assert!(s.execute("select a from t1 where c > 1;").is_err());
+        assert!(s.execute("select a from t1 where a and true;").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_in_between() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        s.execute("create table t1 (a int primary key, b int, c text);")?;
+        s.execute("insert into t1 values (1, 10, 'x'), (2, 20, 'y'), (3, null, 'x');")?;
+        s.execute("insert into t1 values (6, 5, 'z'), (7, 8, null), (8, 30, 'y');")?;
+
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a in (1, 3, 4 + 4);")?,
+            ints(&[1, 3, 8])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where c in ('y', 'z');")?,
+            ints(&[2, 6, 8])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b between 8 and 20;")?,
+            ints(&[1, 2, 7])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b not between 8 and 20;")?,
+            ints(&[6, 8])
+        );
+        // 整数和浮点数可以比较
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b between 4.5 and 8.0;")?,
+            ints(&[6, 7])
+        );
+        // 和 AND、OR 组合使用
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 where a between 1 and 6 and c not in ('x') or b in (8);"
+            )?,
+            ints(&[2, 6, 7])
+        );
+
+        // NULL IN (...) 为 NULL，列表中有 NULL 且没有匹配时也为 NULL，都会被过滤掉
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b not in (10, 20);")?,
+            ints(&[6, 7, 8])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a not in (1, null);")?,
+            ints(&[])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a in (1, null);")?,
+            ints(&[1])
+        );
+
+        s.execute("update t1 set c = 'w' where a in (1, 2);")?;
+        s.execute("delete from t1 where b between 20 and 30;")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where c = 'w';")?,
+            ints(&[1])
+        );
+
+        assert!(s.execute("select a from t1 where c in (1);").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_in_subquery() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        s.execute("create table users (id int primary key, active bool, score float);")?;
+        s.execute("create table orders (id int primary key, user_id int, amount int);")?;
+        s.execute("insert into users values (1, true, 1.0), (2, false, 2.5), (3, true, null);")?;
+        s.execute(
+            "insert into orders values (10, 1, 100), (11, 2, 200), (12, 3, 300), (13, null, 400), (14, 1, 500);",
+        )?;
+
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select id from orders where user_id in (select id from users where active = true);"
+            )?,
+            ints(&[10, 12, 14])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select id from orders where user_id not in (select id from users where active = true);"
+            )?,
+            ints(&[11])
+        );
+        // 和其他条件、Join、聚合一起使用，子查询中也可以有子查询
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select o.id from orders o join users u on o.user_id = u.id
+                 where amount > 100 and o.user_id in (select id from users where active = true);"
+            )?,
+            ints(&[12, 14])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select sum(amount) from orders where user_id in
+                 (select id from users where id in (select user_id from orders where amount >= 300));"
+            )?,
+            ints(&[900])
+        );
+        // 整数和浮点数按数值比较
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select id from users where id in (select score from users);"
+            )?,
+            ints(&[1])
+        );
+
+        // 子查询没有结果时 IN 为 false，NOT IN 为 true，即使左边为 NULL
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select id from orders where user_id in (select id from users where id > 10);"
+            )?,
+            ints(&[])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select id from orders where user_id not in (select id from users where id > 10);"
+            )?,
+            ints(&[10, 11, 12, 13, 14])
+        );
+        // 和 IN 列表一样：NULL IN (...) 为 NULL，结果中有 NULL 且没有匹配时也为 NULL
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select id from orders where user_id in (select user_id from orders);"
+            )?,
+            ints(&[10, 11, 12, 14])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select id from users where id not in (select user_id from orders where id > 11);"
+            )?,
+            ints(&[])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select id from users where id not in (select user_id from orders where id < 13);"
+            )?,
+            ints(&[])
+        );
+        s.execute("insert into users values (4, false, null);")?;
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select id from users where id not in (select user_id from orders where id < 13);"
+            )?,
+            ints(&[4])
+        );
+
+        // 子查询的结果较多时，外层的每一行都在哈希集合中查找
+        s.execute("create table big (a int primary key);")?;
+        let values = (0..2000).map(|i| format!("({})", i)).collect::<Vec<_>>();
+        s.execute(&format!("insert into big values {};", values.join(", ")))?;
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select count(*) from big where a in (select a from big where a % 2 = 0);"
+            )?,
+            ints(&[1000])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from big where a not in (select a from big where a > 0);"
+            )?,
+            ints(&[0])
+        );
+
+        // 子查询也可以用于 UPDATE 和 DELETE
+        s.execute("update orders set amount = 0 where user_id in (select id from users where active = false);")?;
+        s.execute("delete from orders where id not in (select id from orders where amount > 0);")?;
+        assert_eq!(
+            first_column(&mut s, "select id from orders;")?,
+            ints(&[10, 12, 13, 14])
+        );
+
+        // 子查询只能返回一列
+        match s.execute("select id from orders where user_id in (select id, active from users);") {
+            Err(Error::Plan(msg)) => assert!(msg.contains("only one column"), "{}", msg),
+            res => panic!("unexpected result {:?}", res),
+        }
+        match s.execute("select id from orders where user_id in (select * from users);") {
+            Err(Error::Plan(msg)) => assert!(msg.contains("only one column"), "{}", msg),
+            res => panic!("unexpected result {:?}", res),
+        }
+        // 子查询只能作为 AND 连接的 WHERE 条件，不能引用外层的列
+        for sql in [
+            "select id from orders where id = 1 or user_id in (select id from users);",
+            "delete from orders where id = 1 or user_id in (select id from users);",
+            "select user_id from orders group by user_id having user_id in (select id from users);",
+        ] {
+            match s.execute(sql) {
+                Err(Error::Plan(msg)) => assert!(msg.contains("subquery"), "{}: {}", sql, msg),
+                res => panic!("{}: unexpected result {:?}", sql, res),
+            }
+        }
+        assert!(matches!(
+            s.execute(
+                "select id from orders where user_id in (select id from users where id = amount);"
+            ),
+            Err(Error::ColumnNotFound { .. })
+        ));
+        assert!(matches!(
+            s.execute("select id from orders where user_id in (select active from users);"),
+            Err(Error::OperatorMismatch(_))
+        ));
+
+        // 子查询中的参数和外层的参数一起按顺序编号
+        match s.execute_with_params(
+            "select id from orders where amount > ? and user_id in (select id from users where id < ?);",
+            &[Value::Integer(100), Value::Integer(3)],
+        )? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows, vec![ints(&[14])]),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_like() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        let cases = [
+            ("abc", "abc", true),
+            ("abc", "ab", false),
+            ("abc", "%abc%", true),
+            ("xxabcxx", "%abc%", true),
+            ("abxc", "%abc%", false),
+            ("abc", "a_c", true),
+            ("ac", "a_c", false),
+            ("abbc", "a_c", false),
+            ("abc", "%c", true),
+            ("abcd", "%c", false),
+            ("abc", "a%", true),
+            ("", "%", true),
+            ("", "_", false),
+            ("aaab", "%a%ab", true),
+            ("mississippi", "m%iss%ppi", true),
+            ("mississippi", "m%iss%pi%x", false),
+            ("100%", "100\\%", true),
+            ("1000", "100\\%", false),
+            ("a_c", "a\\_c", true),
+            ("abc", "a\\_c", false),
+            ("中文字符", "中_字%", true),
+        ];
+        for (value, pattern, expected) in cases {
+            let sql = format!("select ('{}' like '{}');", value, pattern);
+            assert_eq!(
+                first_column(&mut s, &sql)?,
+                vec![Value::Boolean(expected)],
+                "{}",
+                sql
+            );
+        }
+
+        s.execute("create table t1 (a int primary key, b text);")?;
+        s.execute("insert into t1 values (1, 'apple'), (2, 'banana'), (3, null), (4, 'grape');")?;
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b like '%an%';")?,
+            ints(&[2])
+        );
+        // NULL LIKE 的结果为 NULL，NOT LIKE 也会过滤掉
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b not like '%an%';")?,
+            ints(&[1, 4])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 where b like '_pple' or b like 'g%';"
+            )?,
+            ints(&[1, 4])
+        );
+
+        // 只能对字符串使用 LIKE
+        match s.execute("select a from t1 where a like '1%';") {
+            Err(Error::OperatorMismatch(msg)) => assert!(msg.contains("LIKE"), "{}", msg),
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_is_null() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        s.execute("create table t1 (a int primary key, b int, c text);")?;
+        s.execute("insert into t1 values (1, 10, 'x'), (2, null, 'y'), (3, null, null);")?;
+        s.execute("insert into t1 values (4, 5, null), (5, 20, 'x'), (6, null, 'x');")?;
+
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // 和 NULL 比较的结果是 NULL，不会匹配任何行
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b = null;")?,
+            ints(&[])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b is null;")?,
+            ints(&[2, 3, 6])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b is not null;")?,
+            ints(&[1, 4, 5])
+        );
+        // 和其他条件组合，NOT (b IS NULL) 和 b IS NOT NULL 相同
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b is null and c = 'x';")?,
+            ints(&[6])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b is null or c is null;")?,
+            ints(&[2, 3, 4, 6])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where not b is null and b > 5;")?,
+            ints(&[1, 5])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b + 1 is null;")?,
+            ints(&[2, 3, 6])
+        );
+
+        // 分组的键可以是 NULL，having 中用 IS NULL 过滤
+        match s.execute("select c, count(a) as cnt from t1 group by c having c is null;")? {
+            ResultSet::Scan { rows, .. } => {
+                assert_eq!(rows, vec![vec![Value::Null, Value::Integer(2)]])
+            }
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select c, count(b) from t1 group by c having c is not null and count(b) > 0 order by c;"
+            )?,
+            vec![Value::String("x".into())]
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select c, max(b) as m from t1 group by c having m is null;"
+            )?,
+            vec![Value::String("y".into())]
+        );
+
+        // 在 update 和 delete 中使用
+        s.execute("update t1 set b = 0 where b is null and c is not null;")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b = 0;")?,
+            ints(&[2, 6])
+        );
+        s.execute("delete from t1 where c is null;")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1;")?,
+            ints(&[1, 2, 5, 6])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_where_clause() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        s.execute("create table t1 (a int primary key, b text);")?;
+        s.execute("create table t2 (x int primary key, y text);")?;
+        s.execute("insert into t1 values (1, 'a'), (2, 'b'), (3, 'c');")?;
+        s.execute("insert into t2 values (1, 'x'), (2, 'y'), (3, 'z');")?;
+
+        match s.execute("select * from t1 join t2 on a = x where a >= 2 and y != 'z';")? {
+            ResultSet::Scan { rows, .. } => {
+                assert_eq!(
+                    rows,
+                    vec![vec![
+                        Value::Integer(2),
+                        Value::String("b".to_string()),
+                        Value::Integer(2),
+                        Value::String("y".to_string()),
+                    ]]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_operators() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        s.execute("create table t1 (a int primary key, b float, c int);")?;
+        s.execute("insert into t1 values (1, 1.5, 1), (2, 2.0, null), (3, 3.5, 3);")?;
+
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // 整数和浮点数之间的比较
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b >= 2;")?,
+            ints(&[2, 3])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b <= 2;")?,
+            ints(&[1, 2])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b <> 2;")?,
+            ints(&[1, 3])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a != 2.0;")?,
+            ints(&[1, 3])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where 3 <= a;")?,
+            ints(&[3])
+        );
+
+        // NULL 参与比较的结果是 NULL，不会被选中
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where c >= 1;")?,
+            ints(&[1, 3])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where c <> 1;")?,
+            ints(&[3])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where c <= null;")?,
+            ints(&[])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_arithmetic() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        s.execute("create table t1 (a int primary key, b int, c float);")?;
+        s.execute(
+            "insert into t1 values (1, 10, 1.5), (2, 2 * 10, null), (3 % 4, 30 - 1, 0.5 + 1);",
+        )?;
+
+        match s.execute("select a + b * 2, (a + b) / 2, c * 2, b % 7 as m from t1;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["a + b * 2", "(a + b) / 2", "c * 2", "m"]);
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec![
+                            Value::Integer(21),
+                            Value::Integer(5),
+                            Value::Float(3.0),
+                            Value::Integer(3)
+                        ],
+                        vec![
+                            Value::Integer(42),
+                            Value::Integer(11),
+                            Value::Null,
+                            Value::Integer(6)
+                        ],
+                        vec![
+                            Value::Integer(61),
+                            Value::Integer(16),
+                            Value::Float(3.0),
+                            Value::Integer(1)
+                        ],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // 整数和浮点数混合运算时结果为浮点数
+        assert_eq!(
+            first_column(&mut s, "select a + c from t1 where a = 1;")?,
+            vec![Value::Float(2.5)]
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b - 25 > a;")?,
+            vec![Value::Integer(3)]
+        );
+
+        s.execute("update t1 set b = b + 1, c = c * a where a >= 2;")?;
+        match s.execute("select b, c from t1;")? {
+            ResultSet::Scan { rows, .. } => {
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec![Value::Integer(10), Value::Float(1.5)],
+                        vec![Value::Integer(21), Value::Null],
+                        vec![Value::Integer(30), Value::Float(4.5)],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // 除零报错而不是 panic
+        assert_eq!(
+            s.execute("select b / 0 from t1;"),
+            Err(Error::DivisionByZero)
+        );
+        assert_eq!(
+            s.execute("select b % (a - 1) from t1;").unwrap_err().code(),
+            "22012"
+        );
+        assert!(s.execute("select c / 0.0 from t1;").is_err());
+        assert!(s.execute("update t1 set b = b / 0;").is_err());
+        assert!(s.execute("select a + 'x' from t1;").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_transaction() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s1 = kvengine.session()?;
+        s1.execute("create table t1 (a int primary key, b int);")?;
+
+        let version = match s1.execute("begin;")? {
+            ResultSet::Begin { version } => version,
+            _ => unreachable!(),
+        };
+        assert_eq!(s1.execute("begin;"), Err(Error::ActiveTransaction));
+        s1.execute("insert into t1 values (1, 1);")?;
+        s1.execute("insert into t1 values (2, 2);")?;
+        // 事务内可以看到自己的修改，其他会话看不到
+        assert_eq!(first_column(&mut s1, "select a from t1;")?.len(), 2);
+        let mut s2 = kvengine.session()?;
+        assert_eq!(first_column(&mut s2, "select a from t1;")?.len(), 0);
+
+        let res = s1.execute("commit;")?;
+        assert_eq!(res.to_string(), format!("TRANSACTION {} COMMIT", version));
+        assert_eq!(first_column(&mut s2, "select a from t1;")?.len(), 2);
+        assert_eq!(s1.execute("commit;"), Err(Error::NoActiveTransaction));
+
+        s1.execute("begin;")?;
+        s1.execute("delete from t1 where a = 1;")?;
+        s1.execute("rollback;")?;
+        assert_eq!(first_column(&mut s1, "select a from t1;")?.len(), 2);
+
+        // 会话断开时未提交的事务会被回滚
+        s2.execute("begin;")?;
+        s2.execute("insert into t1 values (3, 3);")?;
+        drop(s2);
+        assert_eq!(
+            first_column(&mut s1, "select a from t1;")?,
+            vec![Value::Integer(1), Value::Integer(2)]
+        );
+        s1.execute("insert into t1 values (3, 3);")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_isolation() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s1 = kvengine.session()?;
+        let mut s2 = kvengine.session()?;
+        s1.execute("create table t1 (a int primary key, b int);")?;
+        s1.execute("insert into t1 values (1, 10), (2, 20), (3, 30);")?;
+
+        s1.execute("begin;")?;
+        s2.execute("begin;")?;
+        // s2 多次更新同一行，删除和插入其他行
+        s2.execute("update t1 set b = 11 where a = 1;")?;
+        s2.execute("update t1 set b = 12 where a = 1;")?;
+        s2.execute("delete from t1 where a = 2;")?;
+        s2.execute("insert into t1 values (4, 40);")?;
+        assert_eq!(
+            first_column(&mut s2, "select b from t1;")?,
+            vec![Value::Integer(12), Value::Integer(30), Value::Integer(40)]
+        );
+
+        // s1 看不到 s2 没有提交的修改，也不会看到重复的行
+        let snapshot = vec![Value::Integer(10), Value::Integer(20), Value::Integer(30)];
+        assert_eq!(first_column(&mut s1, "select b from t1;")?, snapshot);
+        s2.execute("commit;")?;
+        // s2 提交之后，s1 仍然读取开始时的快照
+        assert_eq!(first_column(&mut s1, "select b from t1;")?, snapshot);
+        assert_eq!(
+            first_column(&mut s1, "select count(*) from t1 where b > 15;")?,
+            vec![Value::Integer(2)]
+        );
+        s1.execute("commit;")?;
+
+        assert_eq!(
+            first_column(&mut s1, "select b from t1;")?,
+            vec![Value::Integer(12), Value::Integer(30), Value::Integer(40)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_transaction() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b int);")?;
+        let commit = |s: &mut crate::sql::engine::Session<_>, sql: &str| -> Result<u64> {
+            match s.execute(sql)? {
+                ResultSet::Commit { version } => Ok(version),
+                _ => unreachable!(),
+            }
+        };
+        s.execute("begin;")?;
+        s.execute("insert into t1 values (1, 10), (2, 20);")?;
+        let v1 = commit(&mut s, "commit;")?;
+        s.execute("begin;")?;
+        s.execute("update t1 set b = 11 where a = 1;")?;
+        s.execute("delete from t1 where a = 2;")?;
+        let v2 = commit(&mut s, "commit;")?;
+        s.execute("insert into t1 values (3, 30);")?;
+
+        // 读取历史版本的数据
+        s.execute(&format!("begin read only as of {};", v1))?;
+        assert_eq!(
+            first_column(&mut s, "select b from t1;")?,
+            vec![Value::Integer(10), Value::Integer(20)]
+        );
+        // 只读事务中不能写入
+        let err = s.execute("insert into t1 values (4, 40);").unwrap_err();
+        assert_eq!(err, Error::ReadOnly);
+        assert_eq!(err.code(), "25006");
+        s.execute("commit;")?;
+
+        match s.execute(&format!("BEGIN READ ONLY AS OF {};", v2))? {
+            ResultSet::Begin { version } => assert_eq!(version, v2),
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            first_column(&mut s, "select b from t1;")?,
+            vec![Value::Integer(11)]
+        );
+        s.execute("rollback;")?;
+
+        // 没有 AS OF 时读取最新的数据，其他会话没有提交的修改不可见
+        let mut s2 = kvengine.session()?;
+        s2.execute("begin;")?;
+        s2.execute("update t1 set b = 0;")?;
+        s.execute("begin read only;")?;
+        assert_eq!(
+            first_column(&mut s, "select b from t1;")?,
+            vec![Value::Integer(11), Value::Integer(30)]
+        );
+        s.execute("commit;")?;
+        s2.execute("commit;")?;
+        assert_eq!(
+            first_column(&mut s, "select b from t1;")?,
+            vec![Value::Integer(0), Value::Integer(0)]
+        );
+
+        let err = s.execute("begin read only as of 1000;").unwrap_err();
+        assert_eq!(err.code(), "22023");
+        assert!(s.execute("begin read only as of -1;").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_session() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b int);")?;
+        s.execute("insert into t1 values (1, 10), (2, 20);")?;
+        let version = match s.execute("begin;")? {
+            ResultSet::Begin { version } => version,
+            _ => unreachable!(),
+        };
+        s.execute("rollback;")?;
+
+        // 修改数据和表结构的语句都被拒绝，数据不变
+        let mut ro = kvengine.session_read_only()?;
+        assert!(ro.read_only());
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("t1.csv");
+        std::fs::write(&path, "a,b\n3,30\n")?;
+        for sql in [
+            "create table t2 (a int primary key);".to_string(),
+            "drop table t1;".to_string(),
+            "drop table if exists t2;".to_string(),
+            "create index i1 on t1 (b);".to_string(),
+            "insert into t1 values (3, 30);".to_string(),
+            "update t1 set b = 0;".to_string(),
+            "delete from t1 where a = 1;".to_string(),
+            "vacuum;".to_string(),
+            format!("copy t1 from '{}';", path.display()),
+            format!("copy t1 to '{}';", path.display()),
+            format!("copy t1 to '{}';", dir.path().join("new.csv").display()),
+        ] {
+            assert_eq!(ro.execute(&sql), Err(Error::ReadOnly), "{}", sql);
+        }
+        assert_eq!(ro.stats().errors, 11);
+        // COPY TO 不会覆盖已有的文件，也不会创建新的文件
+        assert_eq!(std::fs::read_to_string(&path)?, "a,b\n3,30\n");
+        assert!(!dir.path().join("new.csv").exists());
+
+        // 查询可以正常执行，BEGIN 开启的是只读事务
+        assert_eq!(
+            first_column(&mut ro, "select b from t1;")?,
+            vec![Value::Integer(10), Value::Integer(20)]
+        );
+        ro.execute("explain delete from t1 where a = 1;")?;
+        ro.execute("begin;")?;
+        assert_eq!(
+            ro.execute("insert into t1 values (3, 30);"),
+            Err(Error::ReadOnly)
+        );
+        assert_eq!(
+            first_column(&mut ro, "select a from t1 where b = 20;")?,
+            vec![Value::Integer(2)]
+        );
+        ro.execute("commit;")?;
+        ro.execute(&format!("begin read only as of {};", version))?;
+        assert_eq!(
+            first_column(&mut ro, "select count(*) from t1;")?,
+            vec![Value::Integer(2)]
+        );
+        ro.execute("rollback;")?;
+
+        // 只读会话不能关闭只读
+        assert_eq!(ro.set_read_only(false), Err(Error::ReadOnly));
+        assert!(ro.read_only());
+
+        // 普通会话可以切换只读，已经开启的读写事务中也不能再写入
+        s.set_read_only(true)?;
+        assert_eq!(s.execute("delete from t1;"), Err(Error::ReadOnly));
+        s.set_read_only(false)?;
+        s.execute("begin;")?;
+        s.execute("insert into t1 values (3, 30);")?;
+        s.set_read_only(true)?;
+        assert_eq!(
+            s.execute("insert into t1 values (4, 40);"),
+            Err(Error::ReadOnly)
+        );
+        s.execute("commit;")?;
+        assert_eq!(
+            first_column(&mut ro, "select a from t1;")?,
+            vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_coercion() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        // 默认值和列类型不一致时建表失败，错误中包含列名，并且不会创建表
+        for (sql, col) in [
+            (
+                "create table t1 (a int primary key, b int default 'x');",
+                "b",
+            ),
+            (
+                "create table t1 (a int primary key, c bool default 1);",
+                "c",
+            ),
+            (
+                "create table t1 (a int primary key, d text default true);",
+                "d",
+            ),
+            (
+                "create table t1 (a int primary key, e int default 1.5);",
+                "e",
+            ),
+            (
+                "create table t1 (a int primary key, f timestamp default 'abc');",
+                "f",
+            ),
+        ] {
+            match s.execute(sql) {
+                Err(Error::Plan(msg)) => {
+                    assert!(msg.contains(&format!("column {}", col)), "{}: {}", sql, msg)
+                }
+                res => panic!("{}: unexpected result {:?}", sql, res),
+            }
+        }
+        assert_eq!(s.get_table_names()?, "");
+
+        // 整数默认值可以用于浮点数列
+        s.execute(
+            "create table t1 (a int primary key, b float default 1, c float default -2, d int);",
+        )?;
+        assert_eq!(
+            s.get_table("t1".to_string())?,
+            "CREATE TABLE t1 (\n    a INTEGER PRIMARY KEY,\n    b FLOAT NULL DEFAULT 1,\n    c FLOAT NULL DEFAULT -2,\n    d INTEGER NULL DEFAULT NULL)"
+        );
+
+        // 插入整数到浮点数列时转换为浮点数，省略的列通过 pad_row 和 make_row 使用默认值
+        s.execute("insert into t1 values (1, 5, 6, 7), (2);")?;
+        s.execute("insert into t1 (a, c) values (3, 10);")?;
+        match s.execute("select * from t1;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![
+                    vec![
+                        Value::Integer(1),
+                        Value::Float(5.0),
+                        Value::Float(6.0),
+                        Value::Integer(7),
+                    ],
+                    vec![
+                        Value::Integer(2),
+                        Value::Float(1.0),
+                        Value::Float(-2.0),
+                        Value::Null,
+                    ],
+                    vec![
+                        Value::Integer(3),
+                        Value::Float(1.0),
+                        Value::Float(10.0),
+                        Value::Null,
+                    ],
+                ]
+            ),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        // 更新时同样转换
+        s.execute("update t1 set b = 8 where a = 2;")?;
+        assert_eq!(
+            first_column(&mut s, "select b from t1 where a = 2;")?,
+            vec![Value::Float(8.0)]
+        );
+
+        // 其他类型不一致的值依然报错，浮点数不会被截断为整数
+        assert!(s.execute("insert into t1 values (4, true);").is_err());
+        assert!(s.execute("insert into t1 values (4, 'x');").is_err());
+        assert!(
+            s.execute("insert into t1 values (4, 1.0, 1.0, 1.5);")
+                .is_err()
+        );
+        assert_eq!(
+            first_column(&mut s, "select count(a) from t1;")?,
+            vec![Value::Integer(3)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_tables() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        assert_eq!(s.get_table_names()?, "");
+
+        s.execute("create table t2 (a int primary key, b text default 'x', c float not null);")?;
+        s.execute("create table t1 (a int primary key, b bool null default true);")?;
+        assert_eq!(s.get_table_names()?, "t1\nt2");
+
+        assert_eq!(
+            s.get_table("t2".to_string())?,
+            "CREATE TABLE t2 (\n    a INTEGER PRIMARY KEY,\n    b STRING NULL DEFAULT 'x',\n    c FLOAT NOT NULL)"
+        );
+        assert_eq!(
+            s.get_table("t1".to_string())?,
+            "CREATE TABLE t1 (\n    a INTEGER PRIMARY KEY,\n    b BOOLEAN NULL DEFAULT TRUE)"
+        );
+
+        // 表不存在时返回错误而不是 panic
+        let err = s.get_table("t3".to_string()).unwrap_err();
+        assert_eq!(err, Error::TableNotFound("t3".into()));
+        assert_eq!(err.to_string(), "table t3 does not exist");
+
+        // 事务中可以看到未提交的表
+        s.execute("begin;")?;
+        s.execute("create table t3 (a int primary key);")?;
+        assert_eq!(s.get_table_names()?, "t1\nt2\nt3");
+        s.execute("rollback;")?;
+        assert_eq!(s.get_table_names()?, "t1\nt2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_index() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        // c 和 b 的数据相同，b 上有索引，c 上没有，用来比较索引扫描和全表扫描的结果
+        s.execute("create table t1 (a int primary key, b int, c int, d text);")?;
+        s.execute("create index idx_b on t1 (b);")?;
+        for i in 0..3000 {
+            s.execute(&format!(
+                "insert into t1 values ({}, {}, {}, 'v{}');",
+                i,
+                i % 100,
+                i % 100,
+                i
+            ))?;
+        }
+        // 创建索引时为已有数据建立索引
+        s.execute("create index idx_d on t1 (d);")?;
+
+        // 索引列上的等值条件会使用索引扫描
+        let txn = kvengine.begin()?;
+        let plan = Plan::build(Parser::new("select * from t1 where b = 7;").parse()?, &txn)?;
+        assert_eq!(
+            plan,
+            Plan(Node::IndexScan {
+                table_name: "t1".to_string(),
+                alias: None,
+                column: "b".to_string(),
+                value: Value::Integer(7),
+            })
+        );
+        let plan = Plan::build(Parser::new("select * from t1 where c = 7;").parse()?, &txn)?;
+        assert!(matches!(plan, Plan(Node::Scan { .. })));
+        txn.commit()?;
+
+        // 写入的行中值的数量和列不一致
+        let mut txn = kvengine.begin()?;
+        let err = txn
+            .create_row("t1".to_string(), vec![Value::Integer(1)])
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::InvalidRow("row 1 for table t1 has 1 values, expected 4".into())
+        );
+        assert_eq!(err.code(), "42601");
+        txn.rollback()?;
+
+        let by_index = s.execute("select * from t1 where b = 7;")?;
+        let by_scan = s.execute("select * from t1 where c = 7;")?;
+        match &by_index {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows.len(), 30),
+            _ => unreachable!(),
+        }
+        assert_eq!(by_index, by_scan);
+        assert_eq!(
+            s.execute("select a from t1 where b = 7 and a > 2000;")?,
+            s.execute("select a from t1 where c = 7 and a > 2000;")?
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where 'v42' = d;")?,
+            vec![Value::Integer(42)]
+        );
+
+        // 更新和删除数据时维护索引
+        s.execute("update t1 set b = 1000 where a = 7;")?;
+        s.execute("delete from t1 where b = 7 and a < 100;")?;
+        s.execute("update t1 set a = 5000 where a = 107;")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b = 1000;")?,
+            vec![Value::Integer(7)]
+        );
+        assert_eq!(
+            s.execute("select a from t1 where b = 7;")?,
+            s.execute("select a from t1 where c = 7 and a != 7 and a >= 100;")?
+        );
+        assert_eq!(
+            first_column(&mut s, "select b from t1 where a = 5000;")?,
+            vec![Value::Integer(7)]
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where d = 'v107';")?,
+            vec![Value::Integer(5000)]
+        );
+
+        // 事务回滚后索引条目也被回滚
+        s.execute("begin;")?;
+        s.execute("insert into t1 values (9000, 2000, 2000, 'x');")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b = 2000;")?.len(),
+            1
+        );
+        s.execute("rollback;")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b = 2000;")?.len(),
+            0
+        );
+
+        // 索引的错误情况
+        let err = s.execute("create index idx_b on t1 (c);").unwrap_err();
+        assert_eq!(
+            err,
+            Error::IndexExists("index idx_b already exists in table t1".into())
+        );
+        assert_eq!(err.code(), "42P07");
+        let err = s.execute("create index idx_b2 on t1 (b);").unwrap_err();
+        assert_eq!(
+            err,
+            Error::IndexExists("column b in table t1 already has index idx_b".into())
+        );
+        assert_eq!(err.code(), "42P07");
+        assert!(s.execute("create index idx_x on t1 (x);").is_err());
+        assert!(s.execute("create index idx_x on t2 (b);").is_err());
+        s.execute("create table t3 (a int primary key, f float);")?;
+        let err = s.execute("create index idx_f on t3 (f);").unwrap_err();
+        assert_eq!(err.to_string(), "not supported: index on FLOAT column f");
+        assert_eq!(err.code(), "0A000");
+        s.execute("drop table t3;")?;
+
+        // 删除表后重建，旧的索引数据不会残留
+        s.execute("drop table t1;")?;
+        s.execute("create table t1 (a int primary key, b int, c int, d text);")?;
+        s.execute("insert into t1 values (1, 7, 7, 'x');")?;
+        s.execute("create index idx_b on t1 (b);")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where b = 7;")?,
+            vec![Value::Integer(1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_primary_key_lookup() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b int, c text);")?;
+        for i in 0..100 {
+            s.execute(&format!(
+                "insert into t1 values ({}, {}, 'v{}');",
+                i,
+                i % 10,
+                i
+            ))?;
+        }
+
+        // 主键等值条件直接按主键读取，其余条件在读取之后过滤
+        let txn = kvengine.begin()?;
+        let plan = Plan::build(Parser::new("select * from t1 where a = 7;").parse()?, &txn)?;
+        assert_eq!(
+            plan,
+            Plan(Node::KeyLookup {
+                table_name: "t1".to_string(),
+                alias: None,
+                value: Value::Integer(7),
+            })
+        );
+        let plan = Plan::build(
+            Parser::new("select * from t1 where b = 7 and 7 = a;").parse()?,
+            &txn,
+        )?;
+        assert!(
+            matches!(plan, Plan(Node::Filter { source, .. }) if matches!(*source, Node::KeyLookup { .. }))
+        );
+        // 非主键条件、OR 条件、类型不一致的条件仍然全表扫描
+        for sql in [
+            "select * from t1 where b = 7;",
+            "select * from t1 where a = 7 or a = 8;",
+            "select * from t1 where a = 7.0;",
+            "select * from t1 where a > 7;",
+        ] {
+            let plan = Plan::build(Parser::new(sql).parse()?, &txn)?;
+            assert!(matches!(plan, Plan(Node::Scan { .. })), "{}", sql);
+        }
+        txn.commit()?;
+
+        assert_eq!(
+            s.execute("select * from t1 where a = 7;")?,
+            s.execute("select * from t1 where a >= 7 and a <= 7;")?
+        );
+        assert_eq!(
+            first_column(&mut s, "select c from t1 where a = 7;")?,
+            vec![Value::String("v7".into())]
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a = 7 and b = 8;")?,
+            vec![]
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a = 1000;")?,
+            vec![]
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a = 7.0;")?,
+            vec![Value::Integer(7)]
+        );
+
+        // 更新和删除同样按主键读取
+        s.execute("update t1 set b = 100 where a = 7;")?;
+        s.execute("delete from t1 where a = 8;")?;
+        assert_eq!(
+            first_column(&mut s, "select b from t1 where a = 7;")?,
+            vec![Value::Integer(100)]
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a = 8;")?,
+            vec![]
+        );
+        assert_eq!(first_column(&mut s, "select a from t1;")?.len(), 99);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b text unique, c int unique not null);")?;
+        s.execute("create index idx_c on t1 (c);")?;
+        s.execute("insert into t1 values (1, 'x', 1), (2, 'y', 2);")?;
+
+        // 重复值报错，错误信息中包含列名和值
+        assert_eq!(
+            s.execute("insert into t1 values (3, 'x', 3);"),
+            Err(Error::DuplicateKey {
+                table: "t1".into(),
+                column: "b".into(),
+                value: "x".into(),
+            })
+        );
+        // 有索引的列通过索引检查
+        assert_eq!(
+            s.execute("insert into t1 values (3, 'z', 2);"),
+            Err(Error::DuplicateKey {
+                table: "t1".into(),
+                column: "c".into(),
+                value: "2".into(),
+            })
+        );
+
+        // NULL 值可以重复
+        s.execute("insert into t1 values (3, null, 3), (4, null, 4);")?;
+
+        // 更新时检查，更新为自身的值不算重复
+        assert!(s.execute("update t1 set b = 'y' where a = 1;").is_err());
+        assert!(s.execute("update t1 set c = 1 where a = 2;").is_err());
+        s.execute("update t1 set b = 'x', c = 1 where a = 1;")?;
+        s.execute("update t1 set b = 'w' where a = 1;")?;
+        s.execute("insert into t1 values (5, 'x', 5);")?;
+        // 修改主键时也不会和自身冲突
+        s.execute("update t1 set a = 10 where a = 5;")?;
+
+        assert_eq!(
+            first_column(&mut s, "select a from t1;")?,
+            vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3),
+                Value::Integer(4),
+                Value::Integer(10)
+            ]
+        );
+
+        match s.execute("select b from t1;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows.len(), 5),
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            s.get_table("t1".to_string())?,
+            "CREATE TABLE t1 (\n    a INTEGER PRIMARY KEY,\n    b STRING NULL UNIQUE DEFAULT NULL,\n    c INTEGER NOT NULL UNIQUE)\nCREATE INDEX idx_c ON t1 (c)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_negative_numbers() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b float, c int default -5);")?;
+        s.execute("insert into t1 values (-1, -1.5, -10), (2, +2.5, - -3), (-4, -0.5, 1 - -1);")?;
+        s.execute("insert into t1 (a, b) values (-9223372036854775808, 0.0);")?;
+
+        match s.execute("select a, b, c, -c as d from t1 order by a;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["a", "b", "c", "d"]);
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec![
+                            Value::Integer(i64::MIN),
+                            Value::Float(0.0),
+                            Value::Integer(-5),
+                            Value::Integer(5)
+                        ],
+                        vec![
+                            Value::Integer(-4),
+                            Value::Float(-0.5),
+                            Value::Integer(2),
+                            Value::Integer(-2)
+                        ],
+                        vec![
+                            Value::Integer(-1),
+                            Value::Float(-1.5),
+                            Value::Integer(-10),
+                            Value::Integer(10)
+                        ],
+                        vec![
+                            Value::Integer(2),
+                            Value::Float(2.5),
+                            Value::Integer(3),
+                            Value::Integer(-3)
+                        ],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a > -3 order by a;")?,
+            vec![Value::Integer(-1), Value::Integer(2)]
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 where b <= -1.5 or -b = 0.5 order by a;"
+            )?,
+            vec![Value::Integer(-4), Value::Integer(-1)]
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a = -1;")?,
+            vec![Value::Integer(-1)]
+        );
+        // 对 i64 最小值取反会溢出
+        assert_eq!(s.execute("select -a from t1;"), Err(Error::NumericOverflow));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_primary_key_order() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        // 行数据的 key 按主键排序，没有 ORDER BY 时按主键从小到大返回
+        s.execute("create table t1 (a int primary key, b int);")?;
+        s.execute("insert into t1 values (3, 0), (-1, 0), (256, 0), (0, 0), (-256, 0), (-9223372036854775808, 0), (9223372036854775807, 0);")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1;")?,
+            [i64::MIN, -256, -1, 0, 3, 256, i64::MAX]
+                .into_iter()
+                .map(Value::Integer)
+                .collect::<Vec<_>>()
+        );
+
+        s.execute("create table t2 (a float primary key, b int);")?;
+        s.execute("insert into t2 values (1.5, 0), (-0.5, 0), (0.0, 0), (-2.5, 0), (100.0, 0);")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t2;")?,
+            [-2.5, -0.5, 0.0, 1.5, 100.0]
+                .into_iter()
+                .map(Value::Float)
+                .collect::<Vec<_>>()
+        );
+
+        s.execute("create table t3 (a string primary key, b int);")?;
+        s.execute("insert into t3 values ('b', 0), ('ab', 0), ('', 0), ('a', 0), ('中', 0);")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t3;")?,
+            ["", "a", "ab", "b", "中"]
+                .into_iter()
+                .map(|v| Value::String(v.to_string()))
+                .collect::<Vec<_>>()
+        );
+
+        // 按主键查询和删除同样可以使用新的编码
+        s.execute("delete from t1 where a = -1;")?;
+        s.execute("update t2 set b = 1 where a = -0.5;")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where a < 0;")?,
+            vec![Value::Integer(i64::MIN), Value::Integer(-256)]
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t2 where b = 1;")?,
+            vec![Value::Float(-0.5)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_lazily() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b int);")?;
+        for i in 0..2000 {
+            s.execute(&format!("insert into t1 values ({}, {});", i, i % 10))?;
+        }
+
+        // a = 1500 的行计算时会报错，LIMIT 只读取前面的数据，不会处理到这一行
+        assert!(s.execute("select 100 / (a - 1500) from t1;").is_err());
+        assert_eq!(
+            first_column(&mut s, "select 100 / (a - 1500) as c from t1 limit 3;")?,
+            vec![Value::Integer(0), Value::Integer(0), Value::Integer(0)]
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 where 100 / (a - 1500) = 0 and b = 1 limit 2 offset 1;"
+            )?,
+            vec![Value::Integer(11), Value::Integer(21)]
+        );
+
+        // 需要完整输入的 ORDER BY 仍然会处理所有的数据
+        assert!(
+            s.execute("select a from t1 where 100 / (a - 1500) = 0 order by a limit 1;")
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute(
+            "create table t1 (a int primary key, created_at timestamp not null, updated_at datetime default '2024-01-01');",
+        )?;
+        s.execute("insert into t1 (a, created_at) values (1, '2024-03-01 08:30:00');")?;
+        s.execute("insert into t1 values (2, '2023-12-31T23:59:59', '2024-01-02 00:00:00');")?;
+        s.execute("insert into t1 (a, created_at) values (3, '2024-01-01');")?;
+
+        assert!(
+            s.execute("insert into t1 (a, created_at) values (4, '2024-02-30');")
+                .is_err()
+        );
+        assert!(
+            s.execute("insert into t1 (a, created_at) values (4, 100);")
+                .is_err()
+        );
+
+        match s.execute("select * from t1 order by created_at;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["a", "created_at", "updated_at"]);
+                let rows = rows
+                    .iter()
+                    .map(|r| r.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec!["2", "2023-12-31 23:59:59", "2024-01-02 00:00:00"],
+                        vec!["3", "2024-01-01 00:00:00", "2024-01-01 00:00:00"],
+                        vec!["1", "2024-03-01 08:30:00", "2024-01-01 00:00:00"],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // WHERE 中时间戳可以和字符串比较
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 where created_at > '2024-01-01 00:00:00';"
+            )?,
+            vec![Value::Integer(1)]
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t1 where '2024-01-01' = created_at;")?,
+            vec![Value::Integer(3)]
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 where created_at < updated_at order by a;"
+            )?,
+            vec![Value::Integer(2)]
+        );
+        assert!(
+            s.execute("select a from t1 where created_at > 'yesterday';")
+                .is_err()
+        );
+
+        s.execute("update t1 set updated_at = '2025-06-30 12:00:00' where a = 1;")?;
+        assert_eq!(
+            first_column(&mut s, "select updated_at from t1 where a = 1;")?,
+            vec![Value::Timestamp(1751284800)]
+        );
+        assert_eq!(
+            s.get_table("t1".to_string())?,
+            "CREATE TABLE t1 (\n    a INTEGER PRIMARY KEY,\n    created_at TIMESTAMP NOT NULL,\n    updated_at TIMESTAMP NULL DEFAULT '2024-01-01 00:00:00')"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_order() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b float, c text, d int);")?;
+        s.execute(
+            "insert into t1 values
+                (1, 3.5, 'z', 10), (2, null, 'y', 20), (3, -1.5, null, 30), (4, 3.5, 'a', 40),
+                (5, 0.0, 'y', 50), (6, null, 'm', 60), (7, 100.25, null, 70), (8, -1.5, 'a', 80);",
+        )?;
+
+        let expected = s.execute("select b, min(d), count(d) from t1 group by b;")?;
+        match &expected {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                &vec![
+                    vec![Value::Null, Value::Integer(20), Value::Integer(2)],
+                    vec![Value::Float(-1.5), Value::Integer(30), Value::Integer(2)],
+                    vec![Value::Float(0.0), Value::Integer(50), Value::Integer(1)],
+                    vec![Value::Float(3.5), Value::Integer(10), Value::Integer(2)],
+                    vec![Value::Float(100.25), Value::Integer(70), Value::Integer(1)],
+                ]
+            ),
+            _ => unreachable!(),
+        }
+        // 多次执行的结果顺序一致
+        for _ in 0..20 {
+            assert_eq!(
+                s.execute("select b, min(d), count(d) from t1 group by b;")?,
+                expected
+            );
+        }
+
+        assert_eq!(
+            first_column(&mut s, "select c, max(d) from t1 group by c;")?,
+            vec![
+                Value::Null,
+                Value::String("a".into()),
+                Value::String("m".into()),
+                Value::String("y".into()),
+                Value::String("z".into())
+            ]
+        );
+        // GROUP BY 之后的 LIMIT 结果是确定的
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select c, max(d) as m from t1 group by c limit 2 offset 1;"
+            )?,
+            vec![Value::String("a".into()), Value::String("m".into())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_multiple() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b text, c int, d int);")?;
+        s.execute(
+            "insert into t1 values
+                (1, 'x', 1, 10), (2, 'x', null, 20), (3, 'y', 1, 30), (4, 'x', 1, 40),
+                (5, 'y', null, 50), (6, 'x', null, 60), (7, 'y', 2, 70);",
+        )?;
+
+        // 按所有分组列的值排序，NULL 排在最前面
+        match s.execute("select b, c, count(*), sum(d) from t1 group by b, c;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["b", "c", "count", "sum"]);
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec![
+                            Value::String("x".into()),
+                            Value::Null,
+                            Value::Integer(2),
+                            Value::Integer(80)
+                        ],
+                        vec![
+                            Value::String("x".into()),
+                            Value::Integer(1),
+                            Value::Integer(2),
+                            Value::Integer(50)
+                        ],
+                        vec![
+                            Value::String("y".into()),
+                            Value::Null,
+                            Value::Integer(1),
+                            Value::Integer(50)
+                        ],
+                        vec![
+                            Value::String("y".into()),
+                            Value::Integer(1),
+                            Value::Integer(1),
+                            Value::Integer(30)
+                        ],
+                        vec![
+                            Value::String("y".into()),
+                            Value::Integer(2),
+                            Value::Integer(1),
+                            Value::Integer(70)
+                        ],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // 分组列可以只输出一部分，顺序也可以和 group by 不同；group by 中可以使用别名
+        match s.execute("select c as y, b as x, count(*) as n from t1 group by x, y;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["y", "x", "n"]);
+                assert_eq!(
+                    rows[0],
+                    vec![Value::Null, Value::String("x".into()), Value::Integer(2)]
+                );
+                assert_eq!(rows.len(), 5);
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t1 group by c, b;")?,
+            vec![
+                Value::Integer(2),
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(1),
+                Value::Integer(1)
+            ]
+        );
+
+        // 不在 group by 中的列不能出现在 select 中
+        assert_eq!(
+            s.execute("select b, c, d, count(*) from t1 group by b, c;"),
+            Err(Error::Grouping(
+                "d must appear in the GROUP BY clause or aggregate function".into()
+            ))
+        );
+        assert_eq!(
+            s.execute("select b, count(*) from t1;"),
+            Err(Error::Grouping(
+                "b must appear in the GROUP BY clause or aggregate function".into()
+            ))
+        );
+        assert!(matches!(
+            s.execute("select count(*) from t1 group by b, e;"),
+            Err(Error::ColumnNotFound { .. })
+        ));
+
+        // 分组的列和聚合函数之上的表达式还不支持，返回用户的错误
+        for sql in [
+            "select c + 1 from t1 group by c;",
+            "select b, count(*) + 1 from t1 group by b;",
+            "select count(*) + 1 from t1;",
+            "select -sum(d) from t1;",
+            "select 1, count(*) from t1;",
+        ] {
+            let err = s.execute(sql).unwrap_err();
+            assert!(matches!(err, Error::NotSupported(_)), "{}: {:?}", sql, err);
+            assert_eq!(err.code(), "0A000");
+        }
+        // having 中可以使用聚合函数之上的表达式
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select c from t1 group by c having count(*) + 1 > 2;"
+            )?,
+            vec![Value::Null, Value::Integer(1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_float_display() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b float);")?;
+        s.execute("insert into t values (1, 1.0), (2, 1.0), (3, 2.0), (4, 0.1), (5, 0.2);")?;
+
+        // 平均值是无限循环小数，展示时保留 12 位有效数字，结果中的值不受影响
+        let rs = s.execute("select avg(b) as x from t where a <= 3;")?;
+        assert_eq!(
+            first_column(&mut s, "select avg(b) from t where a <= 3;")?,
+            vec![Value::Float(4.0 / 3.0)]
+        );
+        assert_eq!(
+            rs.to_string(),
+            "x            \n--------------\n1.33333333333\n(1 rows)"
+        );
+        assert_eq!(
+            rs.to_string_with_precision(3),
+            "x   \n-----\n1.33\n(1 rows)"
+        );
+        // CSV 和 JSON 保留完整的精度
+        assert_eq!(rs.to_csv(), "x\n1.3333333333333333");
+
+        let rs = s.execute("select sum(b) as s from t where a >= 4;")?;
+        assert_eq!(
+            first_column(&mut s, "select sum(b) from t where a >= 4;")?,
+            vec![Value::Float(0.1 + 0.2)]
+        );
+        assert_eq!(rs.to_table(), "s  \n----\n0.3\n(1 rows)");
+        let rs = s.execute("select 2.50 as f, -0.0 as z;")?;
+        assert_eq!(rs.to_table(), "f   |z\n----+--\n2.5 |0\n(1 rows)");
+
+        // 不允许写入 NaN，无穷大可以写入，展示为 Infinity，计算得到的 NaN 展示为 null
+        assert!(matches!(
+            s.execute_with_params("insert into t values (6, ?);", &[Value::Float(f64::NAN)]),
+            Err(Error::TypeMismatch { found, .. }) if found == "NaN"
+        ));
+        s.execute_with_params(
+            "insert into t values (6, ?);",
+            &[Value::Float(f64::INFINITY)],
+        )?;
+        assert!(matches!(
+            s.execute_with_params("update t set b = ? where a = 1;", &[Value::Float(f64::NAN)]),
+            Err(Error::TypeMismatch { .. })
+        ));
+        assert!(s.execute("update t set b = b - b where a = 6;").is_err());
+        let rs = s.execute("select b, b - b as n from t where a = 6;")?;
+        assert_eq!(
+            rs.to_string(),
+            "b        |n   \n---------+-----\nInfinity |null\n(1 rows)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_star() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b int, c text);")?;
+        s.execute(
+            "insert into t1 values (1, 1, 'x'), (2, null, 'y'), (3, 2, 'x'), (4, null, null);",
+        )?;
+
+        match s.execute("select count(*), count(b) as cnt_b from t1;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["count", "cnt_b"]);
+                assert_eq!(rows, vec![vec![Value::Integer(4), Value::Integer(2)]]);
+            }
+            _ => unreachable!(),
+        }
+
+        match s.execute("select c, count(*) as n from t1 group by c;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![
+                    vec![Value::Null, Value::Integer(1)],
+                    vec![Value::String("x".into()), Value::Integer(2)],
+                    vec![Value::String("y".into()), Value::Integer(1)],
+                ]
+            ),
+            _ => unreachable!(),
+        }
+
+        // 空表的 count(*) 为 0
+        s.execute("delete from t1;")?;
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t1;")?,
+            vec![Value::Integer(0)]
+        );
+
+        assert_eq!(
+            s.execute("select sum(*) from t1;"),
+            Err(Error::UnknownFunction("sum(*)".into()))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_having() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (c1 int primary key, c2 text, c3 float);")?;
+        s.execute(
+            "insert into t values (1, 'a', 1.5), (2, 'a', 2.5), (3, 'b', 10.0), (4, 'c', 0.5),
+                (5, 'c', 0.25), (6, 'c', null), (7, 'b', 3.0);",
+        )?;
+
+        // 引用聚合函数的别名
+        match s.execute("select c2, count(c1) as cnt from t group by c2 having cnt >= 2;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["c2", "cnt"]);
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec![Value::String("a".into()), Value::Integer(2)],
+                        vec![Value::String("b".into()), Value::Integer(2)],
+                        vec![Value::String("c".into()), Value::Integer(3)],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // 直接使用聚合函数，包括没有出现在 select 中的聚合函数，隐藏列不会输出
+        match s.execute("select c2, count(c1) from t group by c2 having count(c1) > 2;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["c2", "count"]);
+                assert_eq!(
+                    rows,
+                    vec![vec![Value::String("c".into()), Value::Integer(3)]]
+                );
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            first_column(&mut s, "select c2 from t group by c2 having sum(c3) > 4.5;")?,
+            vec![Value::String("b".into())]
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select c2, avg(c3) as a from t group by c2 having avg(c3) < 2.0 or a > 6;"
+            )?,
+            vec![Value::String("b".into()), Value::String("c".into())]
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select c2, max(c3) from t group by c2 having max(c3) - min(c3) >= 1 and count(*) = 2;"
+            )?,
+            vec![Value::String("a".into()), Value::String("b".into())]
+        );
+        // 引用分组列
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select c2, sum(c3) from t group by c2 having c2 != 'a';"
+            )?,
+            vec![Value::String("b".into()), Value::String("c".into())]
+        );
+        // 没有 group by 时对整体进行过滤
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t having count(*) > 10;")?,
+            Vec::<Value>::new()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vacuum() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        let kvengine = KVEngine::new(DiskEngine::new(p.clone())?);
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b int);")?;
+        s.execute("insert into t values (1, 0), (2, 0);")?;
+        s.execute("vacuum;")?;
+
+        for _ in 0..50 {
+            s.execute("update t set b = b + 1;")?;
+        }
+        // 每次更新都会写入新的版本，清理后只保留最新的版本
+        match s.execute("vacuum;")? {
+            ResultSet::Vacuum { count } => assert_eq!(count, 100),
+            _ => unreachable!(),
+        }
+        assert_eq!(s.execute("vacuum;")?, ResultSet::Vacuum { count: 0 });
+
+        // 其他会话中的事务不受影响
+        let mut s1 = kvengine.session()?;
+        s1.execute("begin;")?;
+        s.execute("update t set b = 100 where a = 1;")?;
+        s.execute("delete from t where a = 2;")?;
+        s.execute("vacuum;")?;
+        assert_eq!(
+            first_column(&mut s1, "select b from t order by a;")?,
+            vec![Value::Integer(50), Value::Integer(50)]
+        );
+        s1.execute("commit;")?;
+        assert_eq!(
+            first_column(&mut s, "select b from t;")?,
+            vec![Value::Integer(100)]
+        );
+
+        // 只读事务读取的版本也不会被清理
+        s1.execute("begin read only;")?;
+        assert_eq!(
+            first_column(&mut s1, "select b from t;")?,
+            vec![Value::Integer(100)]
+        );
+        s.execute("update t set b = 200;")?;
+        s.execute("vacuum;")?;
+        assert_eq!(
+            first_column(&mut s1, "select b from t;")?,
+            vec![Value::Integer(100)]
+        );
+        s1.execute("commit;")?;
+        // 只读事务结束之后旧的版本可以清理
+        assert_eq!(s.execute("vacuum;")?, ResultSet::Vacuum { count: 1 });
+        assert_eq!(
+            first_column(&mut s1, "select b from t;")?,
+            vec![Value::Integer(200)]
+        );
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_batch() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        let kvengine = KVEngine::new(DiskEngine::new(p.clone())?);
+        let flush_count =
+            || -> Result<u64> { Ok(kvengine.storage_mvcc.storage_engine.lock()?.flush_count()) };
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b int);")?;
+        s.execute("create index idx_b on t (b);")?;
+
+        let values = (0..10000)
+            .map(|i| format!("({}, {})", i, i % 100))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let before = flush_count()?;
+        assert_eq!(
+            s.execute(&format!("insert into t values {};", values))?,
+            ResultSet::Insert {
+                count: 10000,
+                keys: (0..10000).map(Value::Integer).collect(),
+            }
+        );
+        // 逐行写入时每行的数据和索引都需要 flush，批量写入只需要很少的几次
+        assert!(flush_count()? - before < 10);
+
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t;")?,
+            vec![Value::Integer(10000)]
+        );
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t where b = 7;")?,
+            vec![Value::Integer(100)]
+        );
+
+        // 同一批次中的主键冲突和唯一约束冲突
+        s.execute("create table t1 (a int primary key, b int unique);")?;
+        assert!(
+            s.execute("insert into t1 values (1, 1), (2, 2), (1, 3);")
+                .is_err()
+        );
+        assert!(
+            s.execute("insert into t1 values (1, 1), (2, 2), (3, 1);")
+                .is_err()
+        );
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t1;")?,
+            vec![Value::Integer(0)]
+        );
+        s.execute("insert into t1 values (1, null), (2, null), (3, 3);")?;
+        assert_eq!(
+            first_column(&mut s, "select a from t1;")?,
+            vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)]
+        );
+
+        drop(s);
+        drop(kvengine);
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_empty_table() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b text);")?;
+        s.execute("create table t2 (x int primary key, y text);")?;
+        s.execute("insert into t1 values (1, 'a'), (2, 'b');")?;
+
+        // 右表为空的外连接，返回左表所有行，右边填充 NULL
+        match s.execute("select * from t1 left join t2 on a = x;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["a", "b", "x", "y"]);
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec![
+                            Value::Integer(1),
+                            Value::String("a".into()),
+                            Value::Null,
+                            Value::Null
+                        ],
+                        vec![
+                            Value::Integer(2),
+                            Value::String("b".into()),
+                            Value::Null,
+                            Value::Null
+                        ],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // 左表为空的内连接和外连接都没有数据
+        match s.execute("select * from t2 join t1 on x = a;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["x", "y", "a", "b"]);
+                assert!(rows.is_empty());
+            }
+            _ => unreachable!(),
+        }
+        match s.execute("select * from t2 left join t1 on x = a;")? {
+            ResultSet::Scan { rows, .. } => assert!(rows.is_empty()),
+            _ => unreachable!(),
+        }
+
+        // right join 在计划中交换了左右两边，右表的所有行都会返回
+        match s.execute("select * from t2 right join t1 on x = a;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["a", "b", "x", "y"]);
+                assert_eq!(rows.len(), 2);
+                assert!(
+                    rows.iter()
+                        .all(|row| row[2] == Value::Null && row[3] == Value::Null)
+                );
+            }
+            _ => unreachable!(),
+        }
+        match s.execute("select * from t1 right join t2 on a = x;")? {
+            ResultSet::Scan { rows, .. } => assert!(rows.is_empty()),
+            _ => unreachable!(),
+        }
+
+        s.execute("insert into t2 values (2, 'y'), (3, 'z');")?;
+        match s.execute("select * from t1 right join t2 on a = x;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["x", "y", "a", "b"]);
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec![
+                            Value::Integer(2),
+                            Value::String("y".into()),
+                            Value::Integer(2),
+                            Value::String("b".into())
+                        ],
+                        vec![
+                            Value::Integer(3),
+                            Value::String("z".into()),
+                            Value::Null,
+                            Value::Null
+                        ],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_qualified_column() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (id int primary key, v text);")?;
+        s.execute("create table t2 (id int primary key, t1_id int, v text);")?;
+        s.execute("insert into t1 values (1, 'a'), (2, 'b');")?;
+        s.execute("insert into t2 values (10, 1, 'x'), (20, 2, 'y'), (30, 2, 'z');")?;
+
+        // 两张表都有 id 和 v 列，通过表名区分
+        match s.execute(
+            "select t1.id, t2.id, t2.v from t1 join t2 on t1.id = t2.t1_id where t1.v = 'b' order by t2.id desc;",
+        )? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["id", "id", "v"]);
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec![
+                            Value::Integer(2),
+                            Value::Integer(30),
+                            Value::String("z".into())
+                        ],
+                        vec![
+                            Value::Integer(2),
+                            Value::Integer(20),
+                            Value::String("y".into())
+                        ],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // Join 条件的左边可以引用右表的列，只有一张表有的列不需要表名
+        match s
+            .execute("select t1_id, t1.v from t1 join t2 on t2.t1_id = t1.id where t2.id = 10;")?
+        {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["t1_id", "v"]);
+                assert_eq!(
+                    rows,
+                    vec![vec![Value::Integer(1), Value::String("a".into())]]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // 两张表都有的列不带表名时报错
+        for (sql, column) in [
+            ("select id from t1 join t2 on t1.id = t2.t1_id;", "id"),
+            (
+                "select * from t1 join t2 on t1.id = t2.t1_id where v = 'x';",
+                "v",
+            ),
+            (
+                "select * from t1 join t2 on t1.id = t2.t1_id order by id;",
+                "id",
+            ),
+        ] {
+            match s.execute(sql) {
+                Err(err @ Error::AmbiguousColumn(_)) => {
+                    assert_eq!(err, Error::AmbiguousColumn(column.to_string()), "{}", sql);
+                    assert_eq!(err.code(), "42702");
+                }
+                res => panic!("unexpected result {:?}", res),
+            }
+        }
+
+        // 单表查询中带表名的列，仍然可以使用主键查询
+        match s.execute("select t1.v from t1 where t1.id = 2;")? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["v"]);
+                assert_eq!(rows, vec![vec![Value::String("b".into())]]);
+            }
+            _ => unreachable!(),
+        }
+        assert!(s.execute("select t2.v from t1;").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_alias() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table users (id int primary key, name text, manager int);")?;
+        s.execute("create table orders (id int primary key, user_id int, amount int);")?;
+        s.execute("insert into users values (1, 'alice', null), (2, 'bob', 1);")?;
+        s.execute("insert into orders values (10, 1, 5), (20, 2, 7);")?;
+
+        match s.execute(
+            "select u.name, o.amount from users u join orders o on u.id = o.user_id order by o.id;",
+        )? {
+            ResultSet::Scan { columns, rows, .. } => {
+                assert_eq!(columns, vec!["name", "amount"]);
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec![Value::String("alice".into()), Value::Integer(5)],
+                        vec![Value::String("bob".into()), Value::Integer(7)],
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // 自连接时通过别名区分两边的列
+        match s
+            .execute("select e.name, m.name from users as e join users as m on e.manager = m.id;")?
+        {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![vec![
+                    Value::String("bob".into()),
+                    Value::String("alice".into())
+                ]]
+            ),
+            _ => unreachable!(),
+        }
+
+        // 单表查询的别名，主键条件仍然可以按主键读取
+        match s.execute("explain select u.name from users u where u.id = 2 and u.name = 'bob';")? {
+            ResultSet::Explain { plan } => assert_eq!(
+                plan,
+                "Projection: u.name\n  -> Filter: u.name = 'bob'\n    -> Key Lookup: users AS u (primary key = 2)"
+            ),
+            _ => unreachable!(),
+        }
+        match s.execute("select u.name from users u where u.id = 2 and u.name = 'bob';")? {
+            ResultSet::Scan { rows, .. } => {
+                assert_eq!(rows, vec![vec![Value::String("bob".into())]])
+            }
+            _ => unreachable!(),
+        }
+        match s.execute("select name from users u where u.manager = 1;")? {
+            ResultSet::Scan { rows, .. } => {
+                assert_eq!(rows, vec![vec![Value::String("bob".into())]])
+            }
+            _ => unreachable!(),
+        }
+
+        // 同一个查询中表名或别名不能重复
+        for sql in [
+            "select * from users u join orders u on u.id = u.user_id;",
+            "select * from users join users on id = manager;",
+        ] {
+            match s.execute(sql) {
+                Err(Error::Plan(msg)) => {
+                    assert!(msg.contains("specified more than once"), "{}", msg)
+                }
+                res => panic!("unexpected result {:?}", res),
+            }
+        }
+        // 规划时的错误不是语法错误，错误码相同，但是展示为 plan error
+        let err = s
+            .execute("select * from users join users on id = manager;")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "plan error: table name users specified more than once"
+        );
+        assert_eq!(err.code(), "42601");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_keys() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b text);")?;
+
+        // 多行插入按 VALUES 的顺序返回主键
+        let rs = s.execute("insert into t1 values (3, 'c'), (1, 'a'), (2, 'b');")?;
+        assert_eq!(
+            rs,
+            ResultSet::Insert {
+                count: 3,
+                keys: vec![Value::Integer(3), Value::Integer(1), Value::Integer(2)],
+            }
+        );
+        assert_eq!(rs.to_string(), "INSERT 3 ROWS. keys: 3, 1, 2");
+
+        // 主键使用默认值
+        s.execute("create table t2 (a text default 'x', b int primary key default 42);")?;
+        let rs = s.execute("insert into t2 (a) values ('y');")?;
+        assert_eq!(
+            rs,
+            ResultSet::Insert {
+                count: 1,
+                keys: vec![Value::Integer(42)],
+            }
+        );
+        assert_eq!(rs.to_string(), "INSERT 1 ROWS. keys: 42");
+
+        // 字符串主键，超过展示数量时只展示行数
+        s.execute("create table t3 (a text primary key);")?;
+        let rs = s.execute("insert into t3 values ('k1');")?;
+        assert_eq!(rs.to_string(), "INSERT 1 ROWS. keys: k1");
+        let values = (10..21)
+            .map(|i| format!("({}, 'x')", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let rs = s.execute(&format!("insert into t1 values {};", values))?;
+        assert_eq!(rs.to_string(), "INSERT 11 ROWS.");
+        match rs {
+            ResultSet::Insert { keys, .. } => assert_eq!(keys.len(), 11),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_increment() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (id int primary key autoincrement, name text);")?;
+        assert_eq!(
+            s.get_table("t".into())?,
+            "CREATE TABLE t (\n    id INTEGER PRIMARY KEY AUTOINCREMENT,\n    name STRING NULL DEFAULT NULL)"
+        );
+        let insert = |s: &mut crate::sql::engine::Session<_>, sql: &str| -> Result<Vec<Value>> {
+            match s.execute(sql)? {
+                ResultSet::Insert { keys, .. } => Ok(keys),
+                rs => Err(Error::Internal(format!("unexpected result set {:?}", rs))),
+            }
+        };
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // 没有给出主键，或者主键为 NULL 时分配下一个值
+        assert_eq!(
+            insert(&mut s, "insert into t (name) values ('a'), ('b');")?,
+            ints(&[1, 2])
+        );
+        assert_eq!(
+            insert(&mut s, "insert into t values (null, 'c');")?,
+            ints(&[3])
+        );
+        // 给出的值大于计数器时更新计数器，较小的值不影响计数器
+        assert_eq!(
+            insert(
+                &mut s,
+                "insert into t values (10, 'd'), (null, 'e'), (5, 'f');"
+            )?,
+            ints(&[10, 11, 5])
+        );
+        assert_eq!(
+            insert(&mut s, "insert into t (name) values ('g');")?,
+            ints(&[12])
+        );
+        assert!(matches!(
+            s.execute("insert into t values (12, 'h');"),
+            Err(Error::DuplicateKey { .. })
+        ));
+
+        // 计数器和数据一起回滚，回滚的事务不会消耗自增值
+        s.execute("begin;")?;
+        assert_eq!(
+            insert(&mut s, "insert into t (name) values ('x');")?,
+            ints(&[13])
+        );
+        s.execute("rollback;")?;
+        assert_eq!(
+            insert(&mut s, "insert into t (name) values ('y');")?,
+            ints(&[13])
+        );
+
+        // 并发的事务同时分配自增值时产生写冲突，而不是重复的主键
+        let mut s2 = kvengine.session()?;
+        s.execute("begin;")?;
+        s2.execute("begin;")?;
+        assert_eq!(
+            insert(&mut s, "insert into t (name) values ('s1');")?,
+            ints(&[14])
+        );
+        assert_eq!(
+            s2.execute("insert into t (name) values ('s2');"),
+            Err(Error::WriteConflict)
+        );
+        s2.execute("rollback;")?;
+        s.execute("commit;")?;
+        assert_eq!(
+            insert(&mut s2, "insert into t (name) values ('s2');")?,
+            ints(&[15])
+        );
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t;")?,
+            vec![Value::Integer(10)]
+        );
+
+        // 删除表之后重新创建，计数器从头开始
+        s.execute("drop table t;")?;
+        s.execute("create table t (id int primary key auto_increment);")?;
+        assert_eq!(insert(&mut s, "insert into t values (null);")?, ints(&[1]));
+
+        // 自增列必须是整数主键，并且不能有默认值
+        for sql in [
+            "create table t1 (a int primary key, b int autoincrement);",
+            "create table t1 (a text primary key autoincrement);",
+            "create table t1 (a int primary key autoincrement default 1);",
+        ] {
+            assert!(s.execute(sql).is_err(), "{}", sql);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_with_params() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b text, c float, d timestamp);")?;
+
+        // 字符串参数不需要转义引号
+        let rs = s.execute_with_params(
+            "insert into t values (?, ?, ?, ?), (?, ?, null, null);",
+            &[
+                Value::Integer(1),
+                Value::String("it's; 'quoted'".into()),
+                Value::Float(1.5),
+                Value::Timestamp(1751284800),
+                Value::Integer(2),
+                Value::Null,
+            ],
+        )?;
+        assert_eq!(
+            rs,
+            ResultSet::Insert {
+                count: 2,
+                keys: vec![Value::Integer(1), Value::Integer(2)],
+            }
+        );
+
+        match s.execute_with_params(
+            "select a, b, d from t where b = ? and c > ?;",
+            &[Value::String("it's; 'quoted'".into()), Value::Integer(1)],
+        )? {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![vec![
+                    Value::Integer(1),
+                    Value::String("it's; 'quoted'".into()),
+                    Value::Timestamp(1751284800),
+                ]]
+            ),
+            _ => unreachable!(),
+        }
+
+        s.execute_with_params(
+            "update t set b = ? where a = ?;",
+            &[Value::String("x".into()), Value::Integer(2)],
+        )?;
+        match s.execute_with_params(
+            "select b from t where a in (?, ?);",
+            &[Value::Integer(2), Value::Integer(3)],
+        )? {
+            ResultSet::Scan { rows, .. } => {
+                assert_eq!(rows, vec![vec![Value::String("x".into())]])
+            }
+            _ => unreachable!(),
+        }
+
+        // 参数的数量和类型需要正确
+        for (sql, params) in [
+            ("select * from t where a = ?;", vec![]),
+            (
+                "select * from t where a = ?;",
+                vec![Value::Integer(1), Value::Integer(2)],
+            ),
+            ("select * from t;", vec![Value::Integer(1)]),
+        ] {
+            match s.execute_with_params(sql, &params) {
+                Err(Error::Parse(msg)) => assert!(msg.contains("parameters"), "{}", msg),
+                res => panic!("unexpected result {:?}", res),
+            }
+        }
+        assert!(
+            s.execute_with_params(
+                "insert into t values (?, 'y', null, null);",
+                &[Value::String("abc".into())]
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_with_quotes() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b text);")?;
+        s.execute("insert into t values (1, 'it''s'), (2, ''), (3, 'a; b;'), (4, '''');")?;
+
+        match s.execute("select b from t order by a;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![
+                    vec![Value::String("it's".into())],
+                    vec![Value::String("".into())],
+                    vec![Value::String("a; b;".into())],
+                    vec![Value::String("'".into())],
+                ]
+            ),
+            _ => unreachable!(),
+        }
+        match s.execute("select a from t where b = 'it''s' or b = '';")? {
+            ResultSet::Scan { rows, .. } => {
+                assert_eq!(rows, vec![vec![Value::Integer(1)], vec![Value::Integer(2)]])
+            }
+            _ => unreachable!(),
+        }
+
+        // 执行计划中展示的字符串同样转义单引号
+        match s.execute("explain select a from t where b = 'it''s';")? {
+            ResultSet::Explain { plan } => {
+                assert!(plan.contains("b = 'it''s'"), "{}", plan)
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_timeout() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b int);")?;
+        let values = (0..200)
+            .map(|i| format!("({}, {})", i, i % 7))
+            .collect::<Vec<_>>()
+            .join(", ");
+        s.execute(&format!("insert into t values {};", values))?;
+
+        // 没有条件的 cross join 有 800 万行，超时后停止执行
+        let huge = "select count(*) from t x cross join t y cross join t z;";
+        s.set_timeout(Some(Duration::from_millis(20)));
+        let start = Instant::now();
+        let err = s.execute(huge).unwrap_err();
+        assert_eq!(err, Error::Timeout(20));
+        assert_eq!(err.code(), "57014");
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // 聚合同样会检查超时，其他语句不受影响
+        assert_eq!(
+            s.execute("select x.b, count(x.a) from t x cross join t y group by x.b;"),
+            Err(Error::Timeout(20))
+        );
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t;")?,
+            vec![Value::Integer(200)]
+        );
+
+        // 事务中的语句超时后，事务可以继续使用
+        s.execute("begin;")?;
+        s.execute("insert into t values (200, 0);")?;
+        assert_eq!(s.execute(huge), Err(Error::Timeout(20)));
+        s.execute("commit;")?;
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t;")?,
+            vec![Value::Integer(201)]
+        );
+
+        // 每条语句单独计时
+        s.set_timeout(Some(Duration::from_secs(60)));
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t x cross join t y;")?,
+            vec![Value::Integer(201 * 201)]
+        );
+        s.set_timeout(None);
+        assert_eq!(s.timeout(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_script() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        let results = s.execute_script(
+            "create table t (a int primary key, b text);
+             insert into t values (1, 'a;b'), (2, 'c');
+             select b from t where a = 1;",
+        )?;
+        assert_eq!(
+            results,
+            vec![
+                ResultSet::CreateTable {
+                    table_name: "t".into()
+                },
+                ResultSet::Insert {
+                    count: 2,
+                    keys: vec![Value::Integer(1), Value::Integer(2)],
+                },
+                ResultSet::Scan {
+                    columns: vec!["b".into()],
+                    types: vec![Some(DataType::String)],
+                    rows: vec![vec![Value::String("a;b".into())]],
+                },
+            ]
+        );
+
+        // 遇到错误时停止，之前的语句已经各自提交
+        match s.execute_script(
+            "insert into t values (3, 'd'); insert into t values (1, 'x'); insert into t values (4, 'e');",
+        ) {
+            Err(err @ Error::Statement { index: 2, count: 3, .. }) => {
+                // 保留原来的错误类型和错误码
+                assert_eq!(err.code(), "23505");
+                assert_eq!(
+                    err.to_string(),
+                    "statement 2 of 3 failed: duplicate value 1 for key column a in table t"
+                );
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+        match s.execute("select a from t order by a;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![
+                    vec![Value::Integer(1)],
+                    vec![Value::Integer(2)],
+                    vec![Value::Integer(3)],
+                ]
+            ),
+            _ => unreachable!(),
+        }
+
+        // 已经开启事务时，所有语句都在这个事务中执行
+        s.execute("begin;")?;
+        s.execute_script("insert into t values (5, 'f'); delete from t where a = 1;")?;
+        s.execute("rollback;")?;
+        match s.execute("select count(a) from t;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows, vec![vec![Value::Integer(3)]]),
+            _ => unreachable!(),
+        }
+
+        // 只有一条语句时返回原始的错误，语法错误时不执行任何语句
+        assert!(matches!(
+            s.execute_script("commit;"),
+            Err(Error::NoActiveTransaction)
+        ));
+        assert!(matches!(
+            s.execute_script("delete from t; select from t;"),
+            Err(Error::Parse(_))
+        ));
+        match s.execute("select count(a) from t;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows, vec![vec![Value::Integer(3)]]),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_errors() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute(
+            "create table t1 (a int primary key, b text not null, c int, d float default 1.5);",
+        )?;
+
+        let cases = [
+            (
+                "insert into t1 values (1, 'a', 1, 2.5, 3);",
+                "42601",
+                "invalid row: too many values in row 1 for table t1: expected at most 4, got 5",
+            ),
+            (
+                "insert into t1 values (1, 'a', 1), (2);",
+                "23502",
+                "no value given for column b of table t1 in row 2, and the column has no default",
+            ),
+            (
+                "insert into t1 (a, c) values (1, 1);",
+                "23502",
+                "no value given for column b of table t1 in row 1, and the column has no default",
+            ),
+            (
+                "insert into t1 (a, b, c) values (1, 'a', 1), (2, 'b');",
+                "42601",
+                "invalid row: row 2 for table t1 has 2 values, but 3 columns were specified",
+            ),
+            (
+                "insert into t1 (a, b, c) values (1, 'a', 1, 2.0);",
+                "42601",
+                "invalid row: row 1 for table t1 has 4 values, but 3 columns were specified",
+            ),
+            (
+                "insert into t1 (a, x) values (1, 'a');",
+                "42703",
+                "column x does not exist in table t1",
+            ),
+            (
+                "insert into t1 values (1, 'a', 1), (2, 'b', 2.5);",
+                "42804",
+                "column c of table t1 expects INTEGER, got FLOAT value 2.5 in row 2",
+            ),
+            (
+                "insert into t1 values (1, 'a', 1), (2, 'b', 2), (3, null, 3);",
+                "23502",
+                "column b of table t1 is not nullable, got NULL in row 3",
+            ),
+        ];
+        for (sql, code, expected) in cases {
+            match s.execute(sql) {
+                Err(err) => assert_eq!(err.to_string(), expected, "{}", sql),
+                res => panic!("unexpected result {:?} for {}", res, sql),
+            }
+            assert_eq!(s.execute(sql).unwrap_err().code(), code, "{}", sql);
+        }
+
+        // 失败的语句不会写入任何数据
+        assert_eq!(
+            first_column(&mut s, "select count(*) from t1;")?,
+            vec![Value::Integer(0)]
+        );
+        s.execute("insert into t1 (c, a, b) values (3, 1, 'a');")?;
+        assert_eq!(
+            first_column(&mut s, "select d from t1;")?,
+            vec![Value::Float(1.5)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_columns() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b int, c text);")?;
+        s.execute("create index idx_b on t (b);")?;
+        s.execute("insert into t values (1, 10, 'x'), (2, 20, 'y');")?;
+
+        // 不存在的列，即使没有匹配的行也会报错
+        for sql in [
+            "update t set d = 1 where a = 1;",
+            "update t set d = 1 where a = 100;",
+        ] {
+            assert_eq!(
+                s.execute(sql),
+                Err(Error::ColumnNotFound {
+                    table: Some("t".into()),
+                    column: "d".into(),
+                    available: Vec::new(),
+                })
+            );
+        }
+
+        // 按照列名更新，和 SET 中列的顺序无关
+        s.execute("update t set c = 'z', b = b + 1 where a = 1;")?;
+        match s.execute("select * from t where a = 1;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![vec![
+                    Value::Integer(1),
+                    Value::Integer(11),
+                    Value::String("z".into())
+                ]]
+            ),
+            _ => unreachable!(),
+        }
+
+        // 更新后的主键和已有的数据冲突
+        match s.execute("update t set a = 2 where a = 1;") {
+            Err(err @ Error::DuplicateKey { .. }) => {
+                assert_eq!(
+                    err.to_string(),
+                    "duplicate value 2 for key column a in table t"
+                )
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+        match s.execute("select a, b from t order by a;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![
+                    vec![Value::Integer(1), Value::Integer(11)],
+                    vec![Value::Integer(2), Value::Integer(20)],
+                ]
+            ),
+            _ => unreachable!(),
+        }
+
+        // 不冲突时可以更新主键，索引同时更新
+        s.execute("update t set a = 3 where a = 1;")?;
+        match s.execute("select a from t where b = 11;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows, vec![vec![Value::Integer(3)]]),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_expressions() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (id int primary key, a int, b int, c int, d text, e float, f timestamp);")?;
+        s.execute(
+            "insert into t (id, a, b, c, d) values (1, 1, 10, 100, '2024-01-02 03:04:05'), (2, 2, 20, 200, 'x'), (3, 3, 30, 300, null);",
+        )?;
+        let rows = |s: &mut crate::sql::engine::Session<KVEngine<MemoryEngine>>, sql: &str| match s
+            .execute(sql)
+        {
+            Ok(ResultSet::Scan { rows, .. }) => rows,
+            res => panic!("unexpected result {:?}", res),
+        };
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // 自增和引用其他列
+        s.execute("update t set a = a + 1, b = c where id = 3;")?;
+        assert_eq!(
+            rows(&mut s, "select a, b, c from t where id = 3;"),
+            vec![ints(&[4, 300, 300])]
+        );
+
+        // 所有 SET 表达式都使用更新之前的值计算，a = b, b = a 交换两列
+        s.execute("update t set a = b, b = a where id < 3;")?;
+        assert_eq!(
+            rows(&mut s, "select a, b from t order by id;"),
+            vec![ints(&[10, 1]), ints(&[20, 2]), ints(&[4, 300])]
+        );
+
+        // 和 INSERT 一样转换为列的类型：整数转换为浮点数，字符串转换为时间戳
+        s.execute("update t set e = a * 2, f = d where id = 1;")?;
+        match rows(&mut s, "select e, f from t where id = 1;").as_slice() {
+            [row] => {
+                assert_eq!(row[0], Value::Float(20.0));
+                assert_eq!(row[1].to_string(), "2024-01-02 03:04:05");
+            }
+            rows => panic!("unexpected rows {:?}", rows),
+        }
+
+        // 类型不一致时报错，所有的行都不会被修改
+        for sql in [
+            "update t set a = d;",
+            "update t set b = a * 1.5;",
+            "update t set f = e;",
+        ] {
+            assert!(
+                matches!(s.execute(sql), Err(Error::TypeMismatch { .. })),
+                "{}",
+                sql
+            );
+        }
+        // 值的类型只有在执行时才知道，例如字符串不能转换为时间戳
+        assert!(s.execute("update t set f = d where id = 2;").is_err());
+        assert!(matches!(
+            s.execute_with_params("update t set c = ?;", &[Value::String("1".into())]),
+            Err(Error::TypeMismatch { .. })
+        ));
+        assert_eq!(
+            rows(&mut s, "select a, b, c from t order by id;"),
+            vec![
+                ints(&[10, 1, 100]),
+                ints(&[20, 2, 200]),
+                ints(&[4, 300, 300])
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (id int primary key autoincrement, b text);")?;
+        s.execute("create index idx_b on t (b);")?;
+        s.execute("create table t2 (id int primary key, b text);")?;
+        s.execute("insert into t (b) values ('x'), ('y'), ('x');")?;
+        s.execute("insert into t2 values (1, 'x');")?;
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // 回滚之后数据和索引都恢复
+        s.execute("begin;")?;
+        assert_eq!(
+            s.execute("truncate table t;")?,
+            ResultSet::Delete { count: 3 }
+        );
+        assert_eq!(first_column(&mut s, "select id from t;")?, ints(&[]));
+        s.execute("rollback;")?;
+        assert_eq!(
+            first_column(&mut s, "select id from t where b = 'x';")?,
+            ints(&[1, 3])
+        );
+
+        // 其他会话在提交之前看到的仍然是清空之前的数据，之后修改这些行会冲突
+        let mut s2 = kvengine.session()?;
+        s2.execute("begin;")?;
+        s.execute("begin;")?;
+        s.execute("truncate t;")?;
+        assert_eq!(
+            first_column(&mut s2, "select id from t;")?,
+            ints(&[1, 2, 3])
+        );
+        assert_eq!(
+            s2.execute("update t set b = 'z' where id = 2;"),
+            Err(Error::WriteConflict)
+        );
+        s.execute("commit;")?;
+        assert_eq!(
+            first_column(&mut s2, "select id from t;")?,
+            ints(&[1, 2, 3])
+        );
+        s2.execute("rollback;")?;
+
+        // 清空之后可以继续插入，自增列不会重新开始，索引中没有旧的数据
+        assert_eq!(first_column(&mut s2, "select id from t;")?, ints(&[]));
+        s.execute("insert into t (b) values ('x');")?;
+        assert_eq!(
+            first_column(&mut s, "select id from t where b = 'x';")?,
+            ints(&[4])
+        );
+        assert_eq!(first_column(&mut s, "select id from t2;")?, ints(&[1]));
+        assert_eq!(
+            s.execute("truncate table t2;")?,
+            ResultSet::Delete { count: 1 }
+        );
+        assert_eq!(
+            s.execute("truncate table t2;")?,
+            ResultSet::Delete { count: 0 }
+        );
+
+        assert_eq!(
+            s.execute("truncate table t3;"),
+            Err(Error::TableNotFound("t3".into()))
+        );
+        s.set_read_only(true)?;
+        assert_eq!(s.execute("truncate table t;"), Err(Error::ReadOnly));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_then_insert_same_key() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (id int primary key, v text unique);")?;
+        s.execute("create index idx_v on t (v);")?;
+        s.execute("insert into t values (1, 'old');")?;
+
+        // 同一个事务中先删除再插入相同的主键
+        s.execute("begin;")?;
+        s.execute("delete from t where id = 1;")?;
+        s.execute("insert into t values (1, 'new');")?;
+        assert_eq!(
+            first_column(&mut s, "select v from t where id = 1;")?,
+            vec![Value::String("new".into())]
+        );
+        s.execute("commit;")?;
+        assert_eq!(
+            first_column(&mut s, "select v from t;")?,
+            vec![Value::String("new".into())]
+        );
+        assert_eq!(
+            first_column(&mut s, "select id from t where v = 'old';")?,
+            Vec::<Value>::new()
+        );
+
+        // 插入、删除、再插入
+        s.execute("begin;")?;
+        s.execute("insert into t values (2, 'a');")?;
+        s.execute("delete from t where id = 2;")?;
+        s.execute("insert into t values (2, 'b');")?;
+        // 删除之后相同的唯一值也可以再次使用
+        s.execute("delete from t where id = 1;")?;
+        s.execute("insert into t values (3, 'new');")?;
+        s.execute("commit;")?;
+        assert_eq!(
+            first_column(&mut s, "select id from t order by id;")?,
+            vec![Value::Integer(2), Value::Integer(3)]
+        );
+        assert_eq!(
+            first_column(&mut s, "select v from t where id = 2;")?,
+            vec![Value::String("b".into())]
+        );
+
+        // 并发事务：另一个事务删除并重新插入时，当前事务的修改产生写冲突
+        let mut s2 = kvengine.session()?;
+        s.execute("begin;")?;
+        s2.execute("begin;")?;
+        s.execute("delete from t where id = 2;")?;
+        s.execute("insert into t values (2, 'c');")?;
+        assert_eq!(
+            s2.execute("delete from t where id = 2;"),
+            Err(Error::WriteConflict)
+        );
+        s2.execute("rollback;")?;
+        // 未提交的删除和插入对其他事务不可见
+        assert_eq!(
+            first_column(&mut s2, "select v from t where id = 2;")?,
+            vec![Value::String("b".into())]
+        );
+        s.execute("commit;")?;
+        assert_eq!(
+            first_column(&mut s2, "select v from t where id = 2;")?,
+            vec![Value::String("c".into())]
+        );
+
+        // 提交之后，其他事务可以再次删除并插入
+        s2.execute("begin;")?;
+        s2.execute("delete from t where id = 2;")?;
+        s2.execute("insert into t values (2, 'd');")?;
+        s2.execute("commit;")?;
+        assert_eq!(
+            first_column(&mut s, "select v from t where id = 2;")?,
+            vec![Value::String("d".into())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_expression() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b int, c text);")?;
+
+        // 表为空时也会检查排序的列
+        assert!(matches!(
+            s.execute("select * from t order by d;"),
+            Err(Error::ColumnNotFound { .. })
+        ));
+
+        s.execute(
+            "insert into t values (1, 9, 'x'), (2, 3, null), (3, 5, 'y'), (4, null, 'x'), (5, 1, 'y');",
+        )?;
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // 按表达式排序，NULL 参与运算的结果为 NULL
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t where b is not null order by a + b;"
+            )?,
+            ints(&[2, 5, 3, 1])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t where b is not null order by a - b desc;"
+            )?,
+            ints(&[5, 2, 3, 1])
+        );
+
+        // 按 SELECT 列表中的列序号排序
+        assert_eq!(
+            first_column(&mut s, "select a, b from t where b > 1 order by 2 desc;")?,
+            ints(&[1, 3, 2])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a, a * 2 as x from t order by 2 desc;")?,
+            ints(&[5, 4, 3, 2, 1])
+        );
+        assert_eq!(
+            first_column(&mut s, "select * from t where b is not null order by 2;")?,
+            ints(&[5, 2, 3, 1])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select c, a from t where c is not null order by 1, 2 desc;"
+            )?,
+            vec![
+                Value::String("x".into()),
+                Value::String("x".into()),
+                Value::String("y".into()),
+                Value::String("y".into()),
+            ]
+        );
+        match s.execute("select c, count(a) as n from t group by c order by 2 desc, 1;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![
+                    vec![Value::String("x".into()), Value::Integer(2)],
+                    vec![Value::String("y".into()), Value::Integer(2)],
+                    vec![Value::Null, Value::Integer(1)],
+                ]
+            ),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        // 列序号超出范围，或者在没有 GROUP BY 时指向聚合函数
+        assert!(matches!(
+            s.execute("select a, b from t order by 3;"),
+            Err(Error::Plan(msg)) if msg.contains("ORDER BY position 3")
+        ));
+        assert!(matches!(
+            s.execute("select * from t order by 0;"),
+            Err(Error::Plan(msg)) if msg.contains("ORDER BY position 0")
+        ));
+        assert!(matches!(
+            s.execute("select count(a) from t order by 1;"),
+            Err(Error::Plan(msg)) if msg.contains("without GROUP BY")
+        ));
+
+        // NULL 在升序时排在最前，降序时排在最后
+        assert_eq!(
+            first_column(&mut s, "select a from t order by b;")?,
+            ints(&[4, 5, 2, 3, 1])
+        );
+        assert_eq!(
+            first_column(&mut s, "select a from t order by b desc;")?,
+            ints(&[1, 3, 2, 5, 4])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_distinct() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b int, c text);")?;
+        s.execute(
+            "insert into t values (1, 3, 'x'), (2, 1, null), (3, 3, 'y'), (4, null, 'x'), (5, 1, null), (6, null, 'x');",
+        )?;
+        let rows = |s: &mut crate::sql::engine::Session<_>, sql: &str| -> Result<Vec<Vec<Value>>> {
+            match s.execute(sql)? {
+                ResultSet::Scan { rows, .. } => Ok(rows),
+                rs => Err(Error::Internal(format!("unexpected result set {:?}", rs))),
+            }
+        };
+
+        // 保留第一次出现的行，两个 NULL 视为重复
+        assert_eq!(
+            first_column(&mut s, "select distinct b from t;")?,
+            vec![Value::Integer(3), Value::Integer(1), Value::Null]
+        );
+        assert_eq!(
+            rows(&mut s, "select distinct c, b from t;")?,
+            vec![
+                vec![Value::String("x".into()), Value::Integer(3)],
+                vec![Value::Null, Value::Integer(1)],
+                vec![Value::String("y".into()), Value::Integer(3)],
+                vec![Value::String("x".into()), Value::Null],
+            ]
+        );
+        assert_eq!(
+            first_column(&mut s, "select distinct b % 2 as odd from t;")?,
+            vec![Value::Integer(1), Value::Null]
+        );
+
+        // 先排序和去重，再执行 offset 和 limit
+        assert_eq!(
+            first_column(&mut s, "select distinct b from t order by b desc;")?,
+            vec![Value::Integer(3), Value::Integer(1), Value::Null]
+        );
+        assert_eq!(
+            first_column(&mut s, "select distinct b from t order by 1 limit 2;")?,
+            vec![Value::Null, Value::Integer(1)]
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select distinct c from t order by c limit 2 offset 1;"
+            )?,
+            vec![Value::String("x".into()), Value::String("y".into())]
+        );
+        assert_eq!(
+            first_column(&mut s, "select distinct b from t where a > 10;")?,
+            vec![]
+        );
+
+        // 有主键时 select distinct * 不会去掉任何行
+        assert_eq!(rows(&mut s, "select distinct * from t;")?.len(), 6);
+        assert_eq!(
+            rows(&mut s, "select x.b, y.b from t x join t y on x.a = y.b;")?.len(),
+            4
+        );
+        assert_eq!(
+            rows(
+                &mut s,
+                "select distinct x.b, y.b from t x join t y on x.a = y.b;"
+            )?,
+            vec![
+                vec![Value::Integer(3), Value::Integer(1)],
+                vec![Value::Integer(3), Value::Integer(3)],
+            ]
+        );
+
+        // 和聚合一起使用时对聚合的结果去重
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select distinct count(a) from t group by b order by 1;"
+            )?,
+            vec![Value::Integer(2)]
+        );
+
+        match s.execute("explain select distinct b from t order by b limit 1;")? {
+            ResultSet::Explain { plan } => assert_eq!(
+                plan,
+                "Limit: 1\n  -> Distinct\n    -> Projection: b\n      -> Order: b ASC\n        -> Scan: t"
+            ),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_single_source() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b int, c text);")?;
+        s.execute("create table t2 (id int primary key, v int);")?;
+        s.execute(
+            "insert into t1 values (1, 10, 'x'), (2, null, 'y'), (3, 30, null), (4, 5, 'x');",
+        )?;
+        s.execute("insert into t2 values (1, 100), (2, 200), (3, null);")?;
+        let ints = |v: &[i64]| v.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // 多表的 where 条件在 Join 之后由 Filter 计算，结果为 NULL 的行被过滤掉
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select t1.a from t1 join t2 on t1.a = t2.id where t2.v > t1.b or c = 'y';"
+            )?,
+            ints(&[1, 2])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 left join t2 on a = id where v is null and not c = 'x';"
+            )?,
+            ints(&[])
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select a from t1 left join t2 on a = id where v is null or b < 10;"
+            )?,
+            ints(&[3, 4])
+        );
+
+        // 没有聚合时 having 也由 Filter 计算
+        assert_eq!(
+            first_column(&mut s, "select a, b from t1 having b * 2 >= 20;")?,
+            ints(&[1, 3])
+        );
+        // having 在聚合的结果上计算
+        assert_eq!(
+            first_column(
+                &mut s,
+                "select c, count(a) as n from t1 group by c having n > 1 or c is null;"
+            )?,
+            vec![Value::Null, Value::String("x".into())]
+        );
+
+        // 列不存在时错误信息中带有可以使用的列
+        let err = s
+            .execute("select * from t1 join t2 on a = id where t2.x = 1;")
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::ColumnNotFound {
+                table: None,
+                column: "t2.x".into(),
+                available: vec![
+                    "t1.a".into(),
+                    "t1.b".into(),
+                    "t1.c".into(),
+                    "t2.id".into(),
+                    "t2.v".into(),
+                ],
+            }
+        );
+        assert_eq!(err.code(), "42703");
+        assert_eq!(
+            err.to_string(),
+            "column t2.x does not exist (available columns: t1.a, t1.b, t1.c, t2.id, t2.v)"
+        );
+        assert!(matches!(
+            s.execute("select a from t1 having d > 1;"),
+            Err(Error::ColumnNotFound { column, available, .. })
+                if column == "d" && available.len() == 3
+        ));
+        assert!(matches!(
+            s.execute("select c, count(a) as n from t1 group by c having m > 1;"),
+            Err(Error::ColumnNotFound { column, available, .. })
+                if column == "m" && available.contains(&"n".to_string())
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_result_column_types() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b text, c float, d bool);")?;
+        s.execute("create table t2 (id int primary key, ts timestamp);")?;
+        s.execute("insert into t1 values (1, '1', 1.5, true), (2, null, null, false);")?;
+        s.execute("insert into t2 values (1, '2025-06-30 12:00:00');")?;
+        let types =
+            |s: &mut crate::sql::engine::Session<_>, sql: &str| -> Result<Vec<Option<DataType>>> {
+                match s.execute(sql)? {
+                    ResultSet::Scan { types, .. } => Ok(types),
+                    rs => Err(Error::Internal(format!("unexpected result set {:?}", rs))),
+                }
+            };
+        use DataType::*;
+
+        // 类型来自表结构，字符串 '1' 和整数 1 可以区分
+        assert_eq!(
+            types(&mut s, "select * from t1;")?,
+            vec![Some(Integer), Some(String), Some(Float), Some(Boolean)]
+        );
+        assert_eq!(
+            types(&mut s, "select * from t1 where a = 1;")?,
+            vec![Some(Integer), Some(String), Some(Float), Some(Boolean)]
+        );
+
+        // 投影中的别名不影响类型，计算的列按表达式推导类型
+        assert_eq!(
+            types(
+                &mut s,
+                "select b as name, a + 1 as next, a * c, -c, d as flag, null, 'x' as s from t1 order by a;"
+            )?,
+            vec![
+                Some(String),
+                Some(Integer),
+                Some(Float),
+                Some(Float),
+                Some(Boolean),
+                None,
+                Some(String)
+            ]
+        );
+        assert_eq!(
+            types(&mut s, "select 1, 2.5 * 2, 1 + null;")?,
+            vec![Some(Integer), Some(Float), Some(Integer)]
+        );
+
+        // Join 的结果包含两边的列，外连接中填充的 NULL 不改变类型
+        assert_eq!(
+            types(
+                &mut s,
+                "select t1.b, t2.ts as created from t1 left join t2 on t1.a = t2.id;"
+            )?,
+            vec![Some(String), Some(Timestamp)]
+        );
+        assert_eq!(
+            types(&mut s, "select * from t1 right join t2 on a = id;")?,
+            vec![
+                Some(Integer),
+                Some(Timestamp),
+                Some(Integer),
+                Some(String),
+                Some(Float),
+                Some(Boolean)
+            ]
+        );
+
+        // 聚合函数：count 为整数，整数列的 sum 为整数，avg 为浮点数，min 和 max 与输入的列相同
+        assert_eq!(
+            types(
+                &mut s,
+                "select d, count(a), sum(a), avg(c), min(b) as m, max(a) from t1 group by d;"
+            )?,
+            vec![
+                Some(Boolean),
+                Some(Integer),
+                Some(Integer),
+                Some(Float),
+                Some(String),
+                Some(Integer)
+            ]
+        );
+        assert_eq!(types(&mut s, "select sum(c) from t1;")?, vec![Some(Float)]);
+        // 没有数据时同样返回类型
+        assert_eq!(
+            types(&mut s, "select b, max(c) from t1 where a > 10 group by b;")?,
+            vec![Some(String), Some(Float)]
+        );
+        assert_eq!(
+            types(&mut s, "select distinct b from t1 limit 0;")?,
+            vec![Some(String)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_csv() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        let schema = "(a int primary key, b text, c float, d bool, e timestamp)";
+        s.execute(&format!("create table t1 {};", schema))?;
+        s.execute(&format!("create table t2 {};", schema))?;
+        s.execute(
+            "insert into t1 values
+                (1, 'plain', 1.5, true, '2025-06-30 12:00:00'),
+                (2, 'with, comma \"quote\"\nnewline', null, false, null),
+                (3, '', 2.0, null, null),
+                (4, null, -0.25, true, '2024-01-01 00:00:00');",
+        )?;
+
+        // 导出的第一行是列名，字符串中的引号写成两个引号，NULL 为空字段
+        let path = dir.path().join("t1.csv");
+        let path = path.to_str().unwrap();
+        assert_eq!(
+            s.execute(&format!("copy t1 to '{}';", path))?,
+            ResultSet::Copy { count: 4 }
+        );
+        assert_eq!(
+            std::fs::read_to_string(path)?,
+            "a,b,c,d,e\n\
+             1,plain,1.5,true,2025-06-30 12:00:00\n\
+             2,\"with, comma \"\"quote\"\"\nnewline\",,false,\n\
+             3,\"\",2,,\n\
+             4,,-0.25,true,2024-01-01 00:00:00\n"
+        );
+
+        // 导入到另一张表后数据完全一致，空字符串和 NULL 可以区分
+        assert_eq!(
+            s.execute(&format!("copy t2 from '{}';", path))?,
+            ResultSet::Copy { count: 4 }
+        );
+        assert_eq!(
+            s.execute("select * from t2 order by a;")?,
+            s.execute("select * from t1 order by a;")?
+        );
+        match s.execute("select a from t2 where b is null;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows, vec![vec![Value::Integer(4)]]),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        // 列名可以和表中的顺序不同，没有给出的列使用默认值
+        s.execute("create table t3 (a int primary key, b text default 'd', c int);")?;
+        let path = dir.path().join("t3.csv");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "c,a\r\n7,1\r\n,2\r\n")?;
+        s.execute(&format!("copy t3 from '{}' with (header true);", path))?;
+        // 没有列名时按位置对应，缺少的列使用默认值
+        std::fs::write(path, "3,x\n4,\"\",5\n")?;
+        s.execute(&format!("copy t3 from '{}' with (header false);", path))?;
+        match s.execute("select * from t3 order by a;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(
+                rows,
+                vec![
+                    vec![
+                        Value::Integer(1),
+                        Value::String("d".into()),
+                        Value::Integer(7)
+                    ],
+                    vec![Value::Integer(2), Value::String("d".into()), Value::Null],
+                    vec![Value::Integer(3), Value::String("x".into()), Value::Null],
+                    vec![
+                        Value::Integer(4),
+                        Value::String("".into()),
+                        Value::Integer(5)
+                    ],
+                ]
+            ),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        // 类型错误、未知的列以及字段个数不对时报错，已经读取的行不会写入
+        std::fs::write(path, "5,y,6\n6,z,abc\n")?;
+        assert!(matches!(
+            s.execute(&format!("copy t3 from '{}' with (header false);", path)),
+            Err(Error::TypeMismatch { found, .. }) if found == "value 'abc' in line 2"
+        ));
+        std::fs::write(path, "a,x\n5,1\n")?;
+        assert!(matches!(
+            s.execute(&format!("copy t3 from '{}';", path)),
+            Err(Error::ColumnNotFound { .. })
+        ));
+        std::fs::write(path, "5,y,6,7\n")?;
+        let err = s
+            .execute(&format!("copy t3 from '{}' with (header false);", path))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::BadCopyFormat(format!("line 1 of {} has 4 fields, expected 3", path))
+        );
+        assert_eq!(err.code(), "22P04");
+        std::fs::write(path, "5,\"y\n")?;
+        let err = s
+            .execute(&format!("copy t3 from '{}' with (header false);", path))
+            .unwrap_err();
+        assert_eq!(err.code(), "22P04");
+        assert!(s.execute("copy t3 from '/nonexistent/t3.csv';").is_err());
+        match s.execute("select count(*) from t3;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows, vec![vec![Value::Integer(4)]]),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        // 在显式事务中导入，回滚后数据不可见
+        std::fs::write(path, "5,y,6\n6,z,7\n")?;
+        s.execute("begin;")?;
+        s.execute(&format!("copy t3 from '{}' with (header false);", path))?;
+        match s.execute("select count(*) from t3;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows, vec![vec![Value::Integer(6)]]),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+        s.execute("rollback;")?;
+        match s.execute("select count(*) from t3;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows, vec![vec![Value::Integer(4)]]),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_stats() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        let mut s2 = kvengine.session()?;
+
+        // 每条语句在自己的事务中执行并提交
+        s.execute("create table t (a int primary key, b int);")?;
+        s.execute("insert into t values (1, 1), (2, 2), (3, 3);")?;
+        s.execute("select * from t;")?;
+        s.execute("update t set b = 0 where a = 1;")?;
+
+        // 显式事务中的语句在 COMMIT 或 ROLLBACK 时才计入事务数量
+        s.execute("begin;")?;
+        s.execute("delete from t where a = 2;")?;
+        s.execute("select * from t;")?;
+        s.execute("rollback;")?;
+
+        // 执行失败的语句回滚自动开启的事务
+        assert!(s.execute("insert into t values (1, 1);").is_err());
+        // 解析失败的语句没有执行，不计入统计
+        assert!(s.execute("select * from;").is_err());
+
+        // 写冲突
+        s.execute("begin;")?;
+        s2.execute("update t set b = 30 where a = 3;")?;
+        assert_eq!(
+            s.execute("update t set b = 3 where a = 3;"),
+            Err(Error::WriteConflict)
+        );
+        s.execute("rollback;")?;
+
+        // COPY TO 导出的行计入读取的行数
+        let path = dir.path().join("t.csv");
+        s.execute(&format!("copy t to '{}';", path.to_str().unwrap()))?;
+
+        let stats = s.stats().clone();
+        assert!(stats.elapsed > std::time::Duration::ZERO);
+        assert_eq!(
+            stats,
+            Stats {
+                statements: 13,
+                errors: 2,
+                rows_read: 8,
+                rows_written: 5,
+                commits: 5,
+                rollbacks: 3,
+                write_conflicts: 1,
+                elapsed: stats.elapsed,
+            }
+        );
+        // 统计信息只属于当前会话
+        assert_eq!(s2.stats().statements, 1);
+        assert_eq!(s2.stats().commits, 1);
+
+        s.reset_stats();
+        assert_eq!(s.stats(), &Stats::default());
+        s.execute("select * from t;")?;
+        assert_eq!(s.stats().statements, 1);
+        assert_eq!(s.stats().rows_read, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sql_comment() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        s.execute(
+            "-- 用户表
+            create table users (
+                id int primary key, -- 主键
+                /* 用户名，
+                   不能为空 */
+                name text not null,
+                age int /* 可以为 NULL */
+            );",
+        )?;
+        s.execute("insert into users values (1, 'a', 20), (2, 'b', 30); -- 插入数据")?;
+        assert_eq!(
+            first_column(&mut s, "select age /* 年龄 */ from users where id = 2;")?,
+            vec![Value::Integer(30)]
+        );
+        // 注释之外的 - 和 / 仍然是运算符
+        assert_eq!(
+            first_column(&mut s, "select age - 10 / 2 from users where id = 1; -- 15")?,
+            vec![Value::Integer(15)]
+        );
+
+        // 没有结束的块注释是解析错误
+        match s.execute("select * from users /* 没有结束;") {
+            Err(Error::Parse(msg)) => {
+                assert!(msg.contains("Unterminated block comment"), "{}", msg)
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quoted_ident() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+
+        // 关键字加上双引号可以作为列名，带引号的标识符保留大小写
+        s.execute(
+            r#"create table "Orders" (id int primary key, "order" int, "First Name" text, "Total" float);"#,
+        )?;
+        s.execute(r#"insert into "Orders" (id, "order", "First Name", "Total") values (1, 10, 'a', 1.5), (2, 20, 'b', 2.5);"#)?;
+        s.execute(r#"create index idx_order on "Orders" ("order");"#)?;
+        assert_eq!(
+            first_column(
+                &mut s,
+                r#"select "First Name" from "Orders" where "order" = 20;"#
+            )?,
+            vec![Value::String("b".to_string())]
+        );
+        assert_eq!(
+            first_column(
+                &mut s,
+                r#"select o."Total" as "Sum" from "Orders" o order by o."order" desc;"#
+            )?,
+            vec![Value::Float(2.5), Value::Float(1.5)]
+        );
+
+        // 不加引号的标识符转为小写，不能匹配大小写不同的名称
+        assert!(s.execute("select Total from \"Orders\";").is_err());
+        assert!(s.execute("select * from orders;").is_err());
+        assert!(
+            s.execute("create table t (order int primary key);")
+                .is_err()
+        );
+
+        // 需要时输出带引号的标识符
+        assert_eq!(s.get_table_names()?, "\"Orders\"");
+        assert_eq!(
+            s.get_table("Orders".to_string())?,
+            "CREATE TABLE \"Orders\" (\n    id INTEGER PRIMARY KEY,\n    \"order\" INTEGER NULL DEFAULT NULL,\n    \"First Name\" STRING NULL DEFAULT NULL,\n    \"Total\" FLOAT NULL DEFAULT NULL)\nCREATE INDEX idx_order ON \"Orders\" (\"order\")"
+        );
+        let rs = s.execute(
+            r#"select "order", "First Name", "Total", "order" + 1 from "Orders" where id = 1;"#,
+        )?;
+        assert_eq!(
+            rs.to_string(),
+            "\"order\" |\"First Name\" |Total |order + 1\n--------+-------------+------+----------\n10      |a            |1.5   |11       \n(1 rows)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_wildcard() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t (a int primary key, b text);")?;
+        s.execute("create table u (a int primary key, c int);")?;
+        s.execute("insert into t values (1, 'x'), (2, 'y');")?;
+        s.execute("insert into u values (1, 10), (2, 20);")?;
+
+        let scan = |s: &mut crate::sql::engine::Session<KVEngine<MemoryEngine>>, sql: &str| match s
+            .execute(sql)?
+        {
+            ResultSet::Scan { columns, rows, .. } => Ok((columns, rows)),
+            rs => Err(Error::Internal(format!("unexpected result set {:?}", rs))),
+        };
+
+        let (columns, rows) = scan(&mut s, "select * from t order by a;")?;
+        assert_eq!(columns, vec!["a", "b"]);
+        assert_eq!(
+            rows[1],
+            vec![Value::Integer(2), Value::String("y".to_string())]
+        );
+
+        // 通配符和其他列一起出现时按顺序展开，重复的列也会输出
+        let (columns, rows) = scan(&mut s, "select *, a * 10 as x, b from t where a = 1;")?;
+        assert_eq!(columns, vec!["a", "b", "x", "b"]);
+        assert_eq!(
+            rows,
+            vec![vec![
+                Value::Integer(1),
+                Value::String("x".to_string()),
+                Value::Integer(10),
+                Value::String("x".to_string()),
+            ]]
+        );
+
+        // join 之后展开，t.* 只包含表 t 的列，* 包含两张表的所有列
+        let (columns, rows) = scan(
+            &mut s,
+            "select u.*, t.b from t join u on t.a = u.a order by t.a desc;",
+        )?;
+        assert_eq!(columns, vec!["a", "c", "b"]);
+        assert_eq!(
+            rows[0],
+            vec![
+                Value::Integer(2),
+                Value::Integer(20),
+                Value::String("y".to_string())
+            ]
+        );
+        let (columns, _) = scan(&mut s, "select x.c, * from t join u x on t.a = x.a;")?;
+        assert_eq!(columns, vec!["c", "a", "b", "a", "c"]);
+
+        // ORDER BY 的列序号按展开之后的列计算
+        assert_eq!(
+            first_column(&mut s, "select b, t.* from t order by 3 desc;")?,
+            vec![
+                Value::String("y".to_string()),
+                Value::String("x".to_string())
+            ]
+        );
+        // distinct 在展开之后去重
+        assert_eq!(
+            first_column(&mut s, "select distinct t.* from t cross join u;")?.len(),
+            2
+        );
+
+        // 不存在的表、聚合、没有 from 时报错
+        assert!(matches!(
+            s.execute("select v.* from t;"),
+            Err(Error::TableNotFound(_))
+        ));
+        assert!(s.execute("select *, count(a) from t;").is_err());
+        assert!(s.execute("select t.* from t group by a;").is_err());
+        assert!(s.execute("select *, 1;").is_err());
+
+        Ok(())
+    }
 }
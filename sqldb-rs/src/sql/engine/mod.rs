@@ -1,14 +1,24 @@
 pub mod kv;
 
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
 use crate::{
     error::{Error, Result},
     sql::{
-        executor::ResultSet,
-        parser::{Parser, ast::Expression},
+        executor::{Deadline, ResultSet},
+        parser::{
+            Parser,
+            ast::{Expression, Statement},
+            lexer::quote_ident,
+        },
         plan::Plan,
-        schema::Table,
-        types::{Row, Value},
+        schema::{Index, Table},
+        types::{Row, Rows, Value},
     },
+    storage::mvcc::MvccStats,
 };
 
 /*
@@ -20,65 +30,255 @@ pub trait Engine: Clone {
 
     fn begin(&self) -> Result<Self::Transaction>;
 
+    // 开启只读事务，as_of 为读取的历史版本，None 表示读取最新的数据
+    fn begin_read_only(&self, as_of: Option<u64>) -> Result<Self::Transaction>;
+
+    // 清理已经不会再被读取的旧版本数据，返回清理的数量
+    fn vacuum(&self) -> Result<usize>;
+
+    // 存储的大小以及活跃事务的数量
+    fn status(&self) -> Result<MvccStats>;
+
+    // 把已经写入的数据持久化，服务关闭前调用
+    fn sync(&self) -> Result<()>;
+
     fn session(&self) -> Result<Session<Self>> {
         Ok(Session {
             engine: self.clone(),
             txn: None,
+            timeout: None,
+            read_only: false,
+            read_only_locked: false,
+            stats: Stats::default(),
         })
     }
+
+    // 只读会话，修改数据和表结构的语句在执行之前被拒绝，并且不能通过 set_read_only 关闭只读
+    fn session_read_only(&self) -> Result<Session<Self>> {
+        let mut session = self.session()?;
+        session.read_only = true;
+        session.read_only_locked = true;
+        Ok(session)
+    }
+}
+
+// 会话的统计信息，执行语句时累加，RESET STATS 清零
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    // 执行的语句数量，包括执行失败的语句
+    pub statements: u64,
+    // 执行失败的语句数量
+    pub errors: u64,
+    // 查询返回的行数，以及 COPY TO 导出的行数
+    pub rows_read: u64,
+    // INSERT、UPDATE、DELETE 以及 COPY FROM 写入的行数，包括之后回滚的事务中写入的行
+    pub rows_written: u64,
+    // 提交和回滚的事务数量，包括每条语句自动开启的事务
+    pub commits: u64,
+    pub rollbacks: u64,
+    // 因为写冲突失败的语句数量
+    pub write_conflicts: u64,
+    // 所有语句的执行时间之和
+    pub elapsed: Duration,
+}
+
+impl Stats {
+    // 记录一条语句的执行结果，copy_to 表示 COPY 的结果是导出的行数
+    fn record(&mut self, result: &Result<ResultSet>, copy_to: bool, elapsed: Duration) {
+        self.statements += 1;
+        self.elapsed += elapsed;
+        match result {
+            Ok(ResultSet::Scan { rows, .. }) => self.rows_read += rows.len() as u64,
+            Ok(ResultSet::Copy { count }) if copy_to => self.rows_read += *count as u64,
+            Ok(
+                ResultSet::Insert { count, .. }
+                | ResultSet::Update { count }
+                | ResultSet::Delete { count }
+                | ResultSet::Copy { count },
+            ) => self.rows_written += *count as u64,
+            Ok(_) => {}
+            Err(err) => {
+                self.errors += 1;
+                if matches!(err, Error::WriteConflict) {
+                    self.write_conflicts += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "statements: {}", self.statements)?;
+        writeln!(f, "errors: {}", self.errors)?;
+        writeln!(f, "rows read: {}", self.rows_read)?;
+        writeln!(f, "rows written: {}", self.rows_written)?;
+        writeln!(f, "commits: {}", self.commits)?;
+        writeln!(f, "rollbacks: {}", self.rollbacks)?;
+        writeln!(f, "write conflicts: {}", self.write_conflicts)?;
+        write!(f, "elapsed: {:.1?}", self.elapsed)
+    }
 }
 
 // 客户端 session 定义
 pub struct Session<E: Engine> {
     engine: E,
     txn: Option<E::Transaction>,
+    // 每条语句的执行时间限制，None 表示不限制
+    timeout: Option<Duration>,
+    // 只读会话中只能执行查询，事务都以只读的方式开启
+    read_only: bool,
+    // 通过 Engine::session_read_only 创建的会话不能关闭只读
+    read_only_locked: bool,
+    stats: Stats,
 }
 
 impl<E: Engine + 'static> Session<E> {
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    // 对之后执行的语句生效，已经开启的读写事务中也不能再写入
+    pub fn set_read_only(&mut self, read_only: bool) -> Result<()> {
+        if !read_only && self.read_only_locked {
+            return Err(Error::ReadOnly);
+        }
+        self.read_only = read_only;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    // 存储引擎的状态，所有会话共享
+    pub fn status(&self) -> Result<MvccStats> {
+        self.engine.status()
+    }
+
     // 执行客户端 SQL 语句
     pub fn execute(&mut self, sql: &str) -> Result<ResultSet> {
+        self.execute_with_params(sql, &[])
+    }
+
+    // 执行带有 ? 占位符的 SQL 语句，解析之后按顺序绑定参数的值
+    pub fn execute_with_params(&mut self, sql: &str, params: &[Value]) -> Result<ResultSet> {
         // SQL -- Parser --> STMT(AST) -- Planner --> Node(Plan)[data_schema, data_type] --> build_and_do_executor(in Node)
-        match Parser::new(sql).parse()? {
-            super::parser::ast::Statement::Begin if self.txn.is_some() => {
-                Err(Error::Internal("Already in a transaction".into()))
+        let mut stmt = Parser::new(sql).parse()?;
+        stmt.bind_params(params)?;
+        self.execute_statement(stmt)
+    }
+
+    // 依次执行脚本中的多条语句，每条语句和单独执行时一样，在当前事务或者自己的事务中执行
+    // 遇到错误时停止执行，错误信息中带有失败语句的序号
+    pub fn execute_script(&mut self, sql: &str) -> Result<Vec<ResultSet>> {
+        let stmts = Parser::new(sql).parse_all()?;
+        let count = stmts.len();
+        let mut results = Vec::with_capacity(count);
+        for (i, mut stmt) in stmts.into_iter().enumerate() {
+            match stmt
+                .bind_params(&[])
+                .and_then(|_| self.execute_statement(stmt))
+            {
+                Ok(rs) => results.push(rs),
+                // 只有一条语句时保持原来的错误
+                Err(err) if count == 1 => return Err(err),
+                Err(err) => {
+                    return Err(Error::Statement {
+                        index: i + 1,
+                        count,
+                        source: Box::new(err),
+                    });
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    // 执行一条语句，并记录到统计信息中
+    fn execute_statement(&mut self, stmt: Statement) -> Result<ResultSet> {
+        let start = Instant::now();
+        let copy_to = matches!(stmt, Statement::CopyTo { .. });
+        let result = self.run_statement(stmt);
+        self.stats.record(&result, copy_to, start.elapsed());
+        result
+    }
+
+    fn run_statement(&mut self, stmt: Statement) -> Result<ResultSet> {
+        // 只读会话中修改数据的语句不会到达存储层
+        if self.read_only && !stmt.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        match stmt {
+            super::parser::ast::Statement::Begin { .. } if self.txn.is_some() => {
+                Err(Error::ActiveTransaction)
             }
             super::parser::ast::Statement::Commit | super::parser::ast::Statement::Rollback
                 if self.txn.is_none() =>
             {
-                Err(Error::Internal("Not in transaction".into()))
+                Err(Error::NoActiveTransaction)
             }
-            super::parser::ast::Statement::Begin => {
-                let txn = self.engine.begin()?;
+            super::parser::ast::Statement::Begin { read_only, as_of } => {
+                let txn = match read_only || self.read_only {
+                    true => self.engine.begin_read_only(as_of)?,
+                    false => self.engine.begin()?,
+                };
                 let version = txn.version();
                 self.txn = Some(txn);
                 Ok(ResultSet::Begin { version })
             }
             super::parser::ast::Statement::Commit => {
-                let txn = self.txn.as_ref().unwrap();
+                // 无论提交是否成功，事务都已经结束
+                let txn = self.txn.take().unwrap();
                 let version = txn.version();
                 txn.commit()?;
-                self.txn = None;
+                self.stats.commits += 1;
                 Ok(ResultSet::Commit { version })
             }
             super::parser::ast::Statement::Rollback => {
-                let txn = self.txn.as_ref().unwrap();
+                let txn = self.txn.take().unwrap();
                 let version = txn.version();
                 txn.rollback()?;
-                self.txn = None;
+                self.stats.rollbacks += 1;
                 Ok(ResultSet::Rollback { version })
             }
-            stmt if self.txn.is_some() => Plan::build(stmt)?.execute(self.txn.as_mut().unwrap()),
+            super::parser::ast::Statement::Vacuum => Ok(ResultSet::Vacuum {
+                count: self.engine.vacuum()?,
+            }),
+            stmt if self.txn.is_some() => {
+                let deadline = Deadline::new(self.timeout);
+                let txn = self.txn.as_mut().unwrap();
+                Plan::build(stmt, txn)?.execute(txn, deadline)
+            }
             stmt => {
-                let mut txn = self.engine.begin()?;
+                let deadline = Deadline::new(self.timeout);
+                let mut txn = match self.read_only {
+                    true => self.engine.begin_read_only(None)?,
+                    false => self.engine.begin()?,
+                };
                 // 这里 execute 方法是使用执行器的工厂方法利用刚构建的事务创建执行器，并执行
                 // 执行器操作的数据视图是事务的视图(sqldb_rs::sql::engine::Transaction)
-                match Plan::build(stmt)?.execute(&mut txn) {
+                match Plan::build(stmt, &txn).and_then(|plan| plan.execute(&mut txn, deadline)) {
                     Ok(result) => {
                         txn.commit()?;
+                        self.stats.commits += 1;
                         Ok(result)
                     }
                     Err(err) => {
                         txn.rollback()?;
+                        self.stats.rollbacks += 1;
                         Err(err)
                     }
                 }
@@ -109,7 +309,20 @@ impl<E: Engine + 'static> Session<E> {
                 names
             }
         };
-        Ok(names.join("\n"))
+        Ok(names
+            .iter()
+            .map(|name| quote_ident(name))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+// 会话结束（例如客户端连接断开）时，回滚尚未提交的事务
+impl<E: Engine> Drop for Session<E> {
+    fn drop(&mut self) {
+        if let Some(txn) = self.txn.take() {
+            let _ = txn.rollback();
+        }
     }
 }
 
@@ -126,7 +339,12 @@ pub trait Transaction {
     fn version(&self) -> u64;
 
     // 创建行
-    fn create_row(&mut self, table_name: String, row: Row) -> Result<()>;
+    fn create_row(&mut self, table_name: String, row: Row) -> Result<()> {
+        self.create_rows(table_name, vec![row])
+    }
+
+    // 批量创建行，所有行一起写入
+    fn create_rows(&mut self, table_name: String, rows: Vec<Row>) -> Result<()>;
 
     // 更新行
     fn update_row(&mut self, table: &Table, id: &Value, row: Row) -> Result<()>;
@@ -134,8 +352,11 @@ pub trait Transaction {
     // 删除行
     fn delete_row(&mut self, table: &Table, id: &Value) -> Result<()>;
 
-    // 扫描表
-    fn scan_table(&self, table_name: String, filter: Option<Expression>) -> Result<Vec<Row>>;
+    // 按主键读取行
+    fn read_row_by_pk(&self, table: &Table, pk: &Value) -> Result<Option<Row>>;
+
+    // 扫描表，返回的迭代器按需反序列化和过滤数据行
+    fn scan_table(&self, table_name: String, filter: Option<Expression>) -> Result<Rows<'_>>;
 
     // DDL 相关操作
 
@@ -145,15 +366,31 @@ pub trait Transaction {
     // 创建表
     fn create_table(&mut self, table: Table) -> Result<()>;
 
+    // 删除表，同时删除表中的所有数据
+    fn drop_table(&mut self, table_name: String) -> Result<()>;
+
+    // 删除表中所有的行和索引条目，保留表结构和自增计数器，返回删除的行数
+    fn truncate_table(&mut self, table: &Table) -> Result<usize>;
+
+    // 在表的某一列上创建索引，并为表中已有的数据建立索引
+    fn create_index(&mut self, table_name: String, index: Index) -> Result<()>;
+
+    // 通过索引读取索引列等于 value 的所有行
+    fn scan_index(&self, table_name: String, column: String, value: Value) -> Result<Vec<Row>>;
+
     // 获取表信息
     fn get_table(&self, table_name: String) -> Result<Option<Table>>;
 
+    // 表的自增计数器，即自增列已经使用过的最大值，没有使用过时为 0
+    fn get_auto_increment(&self, table: &Table) -> Result<i64>;
+
+    // 更新表的自增计数器，和其他数据一样在事务提交时生效
+    fn set_auto_increment(&mut self, table: &Table, value: i64) -> Result<()>;
+
     // 获取表的信息，不存在则报错
     fn must_get_table(&self, table_name: String) -> Result<Table> {
         let t_table_name = table_name.clone();
-        self.get_table(table_name)?.ok_or(Error::Internal(format!(
-            "table {} does not exist",
-            t_table_name
-        )))
+        self.get_table(table_name)?
+            .ok_or(Error::TableNotFound(t_table_name))
     }
 }
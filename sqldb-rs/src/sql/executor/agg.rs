@@ -1,11 +1,14 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, hash_map::Entry},
+};
 
 use crate::{
     error::{Error, Result},
     sql::{
         engine::Transaction,
-        executor::{Executor, ResultSet},
-        parser::ast::Expression,
+        executor::{Deadline, Executor, ResultSet},
+        parser::ast::{Expression, evaluate_expr_single, find_column},
         types::Value,
     },
 };
@@ -13,108 +16,191 @@ use crate::{
 pub struct Aggregate<T: Transaction> {
     source: Box<dyn Executor<T>>,
     exprs: Vec<(Expression, Option<String>)>, // (表达式, 可选别名)
-    group_by: Option<Expression>,
+    group_by: Vec<Expression>,
+    having: Option<Expression>,
+    deadline: Deadline,
 }
 
 impl<T: Transaction> Aggregate<T> {
     pub fn new(
         source: Box<dyn Executor<T>>,
         select: Vec<(Expression, Option<String>)>,
-        group_by: Option<Expression>,
+        group_by: Vec<Expression>,
+        having: Option<Expression>,
+        deadline: Deadline,
     ) -> Box<Self> {
         Box::new(Self {
             source,
             exprs: select,
             group_by,
+            having,
+            deadline,
         })
     }
 }
 
 impl<T: Transaction> Executor<T> for Aggregate<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
-        if let ResultSet::Scan { columns, rows } = self.source.execute(txn)? {
+        if let ResultSet::Scan {
+            columns,
+            types,
+            rows,
+        } = self.source.execute(txn)?
+        {
+            // 输出列的类型只和表达式有关，没有数据时也可以确定
+            let new_types = self
+                .exprs
+                .iter()
+                .map(|(expr, _)| expr.data_type(&columns, &types))
+                .collect();
             let mut new_cols = Vec::new();
             let mut new_rows = Vec::new();
 
-            // 计算聚合函数
-            let mut calc = |col_val: Option<&Value>,
-                            rows: &Vec<Vec<Value>>|
-             -> Result<Vec<Value>> {
-                let mut new_row = Vec::new();
-                for (expr, alias) in &self.exprs {
-                    match expr {
-                        Expression::Function(func_name, col_name) => {
-                            let calculator = <dyn Calculator>::build(func_name)?;
-                            let val = calculator.calc(&col_name, &columns, rows)?;
-
-                            // min(a)               -> min
-                            // min(a) as min_val    -> min_val
-                            if new_cols.len() < self.exprs.len() {
-                                new_cols.push(if let Some(a) = alias {
-                                    a.clone()
-                                } else {
-                                    func_name.clone()
-                                });
-                            }
-                            new_row.push(val);
+            // group by 中每一列在数据中的位置，分组的 key 由这些列的值组成
+            let group_positions = self
+                .group_by
+                .iter()
+                .map(|expr| group_position(expr, &self.exprs, &columns))
+                .collect::<Result<Vec<_>>>()?;
+
+            // having 中的聚合函数作为隐藏列一起计算，having 中改为引用隐藏列，输出前再去掉隐藏列
+            // 例如 having count(a) > 1 会增加隐藏列 count(a)，条件变为 Field("count(a)") > 1
+            let mut exprs = self.exprs.clone();
+            let having = self.having.map(|having| {
+                having.transform(&mut |expr| match expr {
+                    Expression::Function(_, _) => {
+                        let name = expr.to_string();
+                        if !exprs.iter().any(|(_, alias)| alias.as_ref() == Some(&name)) {
+                            exprs.push((expr, Some(name.clone())));
                         }
-                        Expression::Field(col) => {
-                            if let Some(Expression::Field(group_col)) = &self.group_by {
-                                if *col != *group_col {
-                                    return Err(Error::Internal(format!(
-                                        "{} must appear in the GROUP BY clause or aggregate function",
-                                        col
-                                    )));
+                        Expression::Field(name)
+                    }
+                    expr => expr,
+                })
+            });
+            let exprs = exprs;
+
+            // 在读取数据之前确定每一个输出列的计算方式和列名
+            let mut outputs = Vec::new();
+            for (expr, alias) in &exprs {
+                match expr {
+                    Expression::Function(func_name, col_name) => {
+                        // 检查函数名和参数，count(*) 时参数的位置为 None
+                        <dyn Calculator>::build(func_name, col_name)?;
+                        let pos = if col_name == "*" {
+                            None
+                        } else {
+                            Some(find_column(&columns, col_name)?.ok_or_else(|| {
+                                Error::ColumnNotFound {
+                                    table: None,
+                                    column: col_name.clone(),
+                                    available: columns.clone(),
                                 }
-                            }
-                            if new_cols.len() < self.exprs.len() {
-                                new_cols.push(if let Some(a) = alias {
-                                    a.clone()
-                                } else {
-                                    col.clone()
-                                });
-                            }
-                            new_row.push(col_val.unwrap().clone());
-                        }
-                        _ => return Err(Error::Internal("Unexpected expression".into())),
+                            })?)
+                        };
+                        outputs.push(Output::Aggregate(func_name, col_name, pos));
+
+                        // min(a)               -> min
+                        // min(a) as min_val    -> min_val
+                        new_cols.push(alias.clone().unwrap_or_else(|| func_name.clone()));
+                    }
+                    Expression::Field(col) => {
+                        // 不在聚合函数中的列必须出现在 group by 中，输出分组的 key 中对应的值
+                        let pos = find_column(&columns, col)?;
+                        let Some(index) = group_positions.iter().position(|p| Some(*p) == pos)
+                        else {
+                            return Err(Error::Grouping(format!(
+                                "{} must appear in the GROUP BY clause or aggregate function",
+                                col
+                            )));
+                        };
+                        outputs.push(Output::GroupKey(index));
+                        new_cols.push(alias.clone().unwrap_or_else(|| col.clone()));
+                    }
+                    // 规划时已经检查过，聚合的结果中只能输出分组的列和聚合函数
+                    expr => {
+                        return Err(Error::NotSupported(format!(
+                            "expression {} over GROUP BY columns or aggregate functions",
+                            expr
+                        )));
+                    }
+                }
+            }
+
+            // 每个分组只保存聚合函数的中间状态，不保存分组中的行
+            let new_calculators = || -> Result<Vec<Box<dyn Calculator>>> {
+                let mut calculators = Vec::new();
+                for output in &outputs {
+                    if let Output::Aggregate(func_name, col_name, _) = output {
+                        calculators.push(<dyn Calculator>::build(func_name, col_name)?);
                     }
                 }
-                Ok(new_row)
+                Ok(calculators)
             };
 
             // 判断有没有 group by
-            // select c2, min(c1), max(c3) from t group by c2; 注意 select 中的 c2 必须与group by c2 一致
-            if let Some(Expression::Field(group_col)) = &self.group_by {
-                // 对数据进行分组，然后再计算每组的统计值
-                let pos = match columns.iter().position(|c| *c == *group_col) {
-                    Some(pos) => pos,
-                    None => {
-                        return Err(Error::Internal(format!(
-                            "group by column {} not in table",
-                            group_col
-                        )));
-                    }
+            // select c2, c3, min(c1) from t group by c2, c3; 注意 select 中的 c2、c3 必须出现在 group by 中
+            // 没有 group by 时所有的行为一个分组，key 为空，没有数据时也会输出一行
+            let mut groups: HashMap<Vec<Value>, Vec<Box<dyn Calculator>>> = HashMap::new();
+            if group_positions.is_empty() {
+                groups.insert(Vec::new(), new_calculators()?);
+            }
+            for (i, row) in rows.into_iter().enumerate() {
+                self.deadline.check(i)?;
+                let key = group_positions.iter().map(|p| row[*p].clone()).collect();
+                let calculators = match groups.entry(key) {
+                    Entry::Occupied(e) => e.into_mut(),
+                    Entry::Vacant(e) => e.insert(new_calculators()?),
                 };
-
-                // 针对 Group by 的列进行分组
-                let mut agg_map = HashMap::new();
-                for row in rows.iter() {
-                    let key = &row[pos];
-                    let value = agg_map.entry(key).or_insert(Vec::new());
-                    value.push(row.clone());
+                let args = outputs.iter().filter_map(|output| match output {
+                    Output::Aggregate(_, _, pos) => Some(pos),
+                    Output::GroupKey(_) => None,
+                });
+                for (calculator, pos) in calculators.iter_mut().zip(args) {
+                    // count(*) 不读取参数的值
+                    calculator.update(pos.map_or(&Value::Null, |pos| &row[pos]))?;
                 }
+            }
 
-                for (key, row) in agg_map {
-                    let row = calc(Some(key), &row)?;
-                    new_rows.push(row);
-                }
-            } else {
-                let row = calc(None, &rows)?;
+            // HashMap 的遍历顺序不固定，按分组的 key 逐列排序后输出，NULL 排在最前面
+            let mut groups = groups.into_iter().collect::<Vec<_>>();
+            groups.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+            for (key, calculators) in groups {
+                let mut values = calculators.into_iter().map(|c| c.finish());
+                let row = outputs
+                    .iter()
+                    .map(|output| match output {
+                        Output::Aggregate(..) => values.next().unwrap_or(Value::Null),
+                        Output::GroupKey(index) => key[*index].clone(),
+                    })
+                    .collect::<Vec<_>>();
                 new_rows.push(row);
             }
 
+            // 过滤不满足 having 条件的分组
+            if let Some(having) = having {
+                let mut filtered = Vec::new();
+                for row in new_rows {
+                    match evaluate_expr_single(&having, &new_cols, &row)? {
+                        Value::Null | Value::Boolean(false) => {}
+                        Value::Boolean(true) => filtered.push(row),
+                        _ => return Err(Error::Internal("Unexpected expression".into())),
+                    }
+                }
+                new_rows = filtered;
+            }
+
+            // 去掉隐藏列
+            let visible = self.exprs.len();
+            new_cols.truncate(visible);
+            for row in new_rows.iter_mut() {
+                row.truncate(visible);
+            }
+
             return Ok(ResultSet::Scan {
                 columns: new_cols,
+                types: new_types,
                 rows: new_rows,
             });
         }
@@ -123,202 +209,378 @@ impl<T: Transaction> Executor<T> for Aggregate<T> {
     }
 }
 
+// group by 中的列在数据中的位置，也可以使用 select 中列的别名，例如
+// select c1 as x, count(*) from t group by x;
+fn group_position(
+    expr: &Expression,
+    exprs: &[(Expression, Option<String>)],
+    columns: &Vec<String>,
+) -> Result<usize> {
+    let Expression::Field(name) = expr else {
+        return Err(Error::Grouping(format!(
+            "GROUP BY only supports columns, got {}",
+            expr
+        )));
+    };
+    if let Some(pos) = find_column(columns, name)? {
+        return Ok(pos);
+    }
+    let aliased = exprs.iter().find_map(|(expr, alias)| match expr {
+        Expression::Field(col) if alias.as_ref() == Some(name) => Some(col),
+        _ => None,
+    });
+    if let Some(col) = aliased
+        && let Some(pos) = find_column(columns, col)?
+    {
+        return Ok(pos);
+    }
+    Err(Error::ColumnNotFound {
+        table: None,
+        column: name.clone(),
+        available: columns.clone(),
+    })
+}
+
+// select 中每一项的计算方式
+enum Output<'a> {
+    // 聚合函数：(函数名, 参数, 参数在数据中的位置)，count(*) 时位置为 None
+    Aggregate(&'a String, &'a String, Option<usize>),
+    // 分组的 key 中的第几列
+    GroupKey(usize),
+}
+
 // >>>>>>>>>>>>>>>>>>> Calculator trait >>>>>>>>>>>>>>>>>
+// 聚合函数的计算器，保存计算的中间状态，每读取一行调用一次 update，读取完所有的行之后调用 finish 得到结果
 pub trait Calculator {
-    fn calc(&self, col_name: &String, cols: &Vec<String>, rows: &Vec<Vec<Value>>) -> Result<Value>;
+    fn update(&mut self, value: &Value) -> Result<()>;
+    fn finish(self: Box<Self>) -> Value;
 }
 
 impl dyn Calculator {
-    pub fn build(func_name: &String) -> Result<Box<dyn Calculator>> {
+    pub fn build(func_name: &String, col_name: &String) -> Result<Box<dyn Calculator>> {
+        // 只有 count 支持 * 参数
+        if col_name == "*" && !func_name.eq_ignore_ascii_case("count") {
+            return Err(Error::UnknownFunction(format!("{}(*)", func_name)));
+        }
         match func_name.to_lowercase().as_str() {
-            "count" => Ok(Box::new(Count::new())),
+            "count" => Ok(Box::new(Count::new(col_name == "*"))),
             "min" => Ok(Box::new(Min::new())),
             "max" => Ok(Box::new(Max::new())),
-            "sum" => Ok(Box::new(Sum::new())),
-            "avg" => Ok(Box::new(Avg::new())),
-            _ => Err(Error::Internal(format!("Unknown function: {}", func_name))),
+            "sum" => Ok(Box::new(Sum::new(col_name))),
+            "avg" => Ok(Box::new(Avg::new(col_name))),
+            _ => Err(Error::UnknownFunction(func_name.clone())),
         }
     }
 }
 
 // >>>>>>>>>>>>>>>>>>> Count >>>>>>>>>>>>>>>>>
-pub struct Count;
+pub struct Count {
+    count: i64,
+    all: bool, // count(*) 统计所有的行，包括 NULL
+}
 
 impl Count {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(all: bool) -> Self {
+        Self { count: 0, all }
     }
 }
 
 impl Calculator for Count {
-    fn calc(&self, col_name: &String, cols: &Vec<String>, rows: &Vec<Vec<Value>>) -> Result<Value> {
-        let pos = match cols.iter().position(|c| *c == *col_name) {
-            Some(pos) => pos,
-            None => return Err(Error::Internal("Column not found".into())),
-        };
-
-        // a b c
-        // 1 X 3.1
-        // 2 NULL 6.4
-        // 3 X 1.5
-        let mut count = 0;
-        for row in rows.iter() {
-            if row[pos] != Value::Null {
-                count += 1;
-            }
+    fn update(&mut self, value: &Value) -> Result<()> {
+        if self.all || *value != Value::Null {
+            self.count += 1;
         }
+        Ok(())
+    }
 
-        Ok(Value::Integer(count))
+    fn finish(self: Box<Self>) -> Value {
+        Value::Integer(self.count)
     }
 }
 
 // >>>>>>>>>>>>>>>>>>> Min >>>>>>>>>>>>>>>>>
-pub struct Min;
+pub struct Min {
+    min: Value,
+}
 
 impl Min {
     pub fn new() -> Self {
-        Self {}
+        Self { min: Value::Null }
     }
 }
 
 impl Calculator for Min {
-    fn calc(&self, col_name: &String, cols: &Vec<String>, rows: &Vec<Vec<Value>>) -> Result<Value> {
-        let pos = match cols.iter().position(|c| *c == *col_name) {
-            Some(pos) => pos,
-            None => return Err(Error::Internal("Column not found".into())),
-        };
-
-        // a b c
-        // 1 X 3.1
-        // 2 NULL 6.4
-        // 3 X 1.5
-        let mut min_value = Value::Null;
-        let mut values = Vec::new();
-        for row in rows.iter() {
-            if row[pos] != Value::Null {
-                values.push(&row[pos]);
-            }
-        }
-        if !values.is_empty() {
-            values.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            min_value = values[0].clone();
+    fn update(&mut self, value: &Value) -> Result<()> {
+        // 忽略 NULL，只保留当前最小的值
+        if *value != Value::Null
+            && (self.min == Value::Null || value.partial_cmp(&self.min) == Some(Ordering::Less))
+        {
+            self.min = value.clone();
         }
+        Ok(())
+    }
 
-        Ok(min_value)
+    fn finish(self: Box<Self>) -> Value {
+        self.min
     }
 }
 
 // >>>>>>>>>>>>>>>>>>> Max >>>>>>>>>>>>>>>>>
-pub struct Max;
+pub struct Max {
+    max: Value,
+}
 
 impl Max {
     pub fn new() -> Self {
-        Self {}
+        Self { max: Value::Null }
     }
 }
 
 impl Calculator for Max {
-    fn calc(&self, col_name: &String, cols: &Vec<String>, rows: &Vec<Vec<Value>>) -> Result<Value> {
-        let pos = match cols.iter().position(|c| *c == *col_name) {
-            Some(pos) => pos,
-            None => return Err(Error::Internal("Column not found".into())),
-        };
-
-        // a b c
-        // 1 X 3.1
-        // 2 NULL 6.4
-        // 3 X 1.5
-        let mut max_value = Value::Null;
-        let mut values = Vec::new();
-        for row in rows.iter() {
-            if row[pos] != Value::Null {
-                values.push(&row[pos]);
-            }
-        }
-        if !values.is_empty() {
-            // values.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            // min_value = values[values.len() - 1].clone();
-            values.sort_by(|a, b| b.partial_cmp(a).unwrap());
-            max_value = values[0].clone();
+    fn update(&mut self, value: &Value) -> Result<()> {
+        // 忽略 NULL，只保留当前最大的值
+        if *value != Value::Null
+            && (self.max == Value::Null || value.partial_cmp(&self.max) == Some(Ordering::Greater))
+        {
+            self.max = value.clone();
         }
+        Ok(())
+    }
 
-        Ok(max_value)
+    fn finish(self: Box<Self>) -> Value {
+        self.max
     }
 }
 
 // >>>>>>>>>>>>>>>>>>> Sum >>>>>>>>>>>>>>>>>
-pub struct Sum;
+pub struct Sum {
+    col_name: String,
+    sum: Value,
+}
 
 impl Sum {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(col_name: &String) -> Self {
+        Self {
+            col_name: col_name.clone(),
+            sum: Value::Null,
+        }
     }
 }
 
 impl Calculator for Sum {
-    fn calc(&self, col_name: &String, cols: &Vec<String>, rows: &Vec<Vec<Value>>) -> Result<Value> {
-        let pos = match cols.iter().position(|c| *c == *col_name) {
-            Some(pos) => pos,
-            None => return Err(Error::Internal("Column not found".into())),
-        };
-
-        // a b c
-        // 1 X 3.1
-        // 2 NULL 6.4
-        // 3 X 1.5
-        let mut sum = None;
-
-        for row in rows.iter() {
-            match row[pos] {
-                Value::Null => {}
-                Value::Integer(v) => {
-                    if sum == None {
-                        sum = Some(0.0);
-                    }
-                    // 注意，这里即便是整数，这里会转换成浮点数。所以返回的合法值类型也是浮点数。
-                    sum = Some(sum.unwrap() + v as f64);
-                }
-                Value::Float(v) => {
-                    if sum == None {
-                        sum = Some(0.0);
-                    }
-                    sum = Some(sum.unwrap() + v);
-                }
-                _ => {
-                    return Err(Error::Internal(format!(
-                        "can not calc column: {}",
-                        col_name
-                    )));
-                }
+    fn update(&mut self, value: &Value) -> Result<()> {
+        // 整数相加的结果仍然是整数，出现浮点数之后结果为浮点数，全部为 NULL 时结果为 NULL
+        self.sum = match (&self.sum, value) {
+            (_, Value::Null) => return Ok(()),
+            (Value::Null, Value::Integer(_) | Value::Float(_)) => value.clone(),
+            (Value::Integer(s), Value::Integer(v)) => {
+                Value::Integer(s.checked_add(*v).ok_or(Error::NumericOverflow)?)
             }
-        }
+            (Value::Integer(s), Value::Float(v)) => Value::Float(*s as f64 + v),
+            (Value::Float(s), Value::Integer(v)) => Value::Float(s + *v as f64),
+            (Value::Float(s), Value::Float(v)) => Value::Float(s + v),
+            _ => {
+                return Err(Error::OperatorMismatch(format!(
+                    "can not calc column: {}",
+                    self.col_name
+                )));
+            }
+        };
+        Ok(())
+    }
 
-        Ok(match sum {
-            Some(s) => Value::Float(s),
-            None => Value::Null,
-        })
+    fn finish(self: Box<Self>) -> Value {
+        self.sum
     }
 }
 
 // >>>>>>>>>>>>>>>>>>> Avg >>>>>>>>>>>>>>>>>
-pub struct Avg;
+pub struct Avg {
+    sum: Sum,
+    count: Count,
+}
 
 impl Avg {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(col_name: &String) -> Self {
+        Self {
+            sum: Sum::new(col_name),
+            count: Count::new(false),
+        }
     }
 }
 
 impl Calculator for Avg {
-    fn calc(&self, col_name: &String, cols: &Vec<String>, rows: &Vec<Vec<Value>>) -> Result<Value> {
-        let sum_value = Sum::new().calc(col_name, cols, rows)?;
-        let count_value = Count::new().calc(col_name, cols, rows)?;
+    fn update(&mut self, value: &Value) -> Result<()> {
+        self.sum.update(value)?;
+        self.count.update(value)
+    }
 
-        Ok(match (sum_value, count_value) {
-            (Value::Float(sum_value), Value::Integer(count_value)) => {
-                Value::Float(sum_value / count_value as f64)
-            }
+    // 平均值总是浮点数
+    fn finish(self: Box<Self>) -> Value {
+        match (self.sum.sum, self.count.count) {
+            (Value::Integer(sum), count) => Value::Float(sum as f64 / count as f64),
+            (Value::Float(sum), count) => Value::Float(sum / count as f64),
             _ => Value::Null,
-        })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Aggregate, Calculator};
+    use crate::{
+        error::Result,
+        sql::{
+            engine::{Engine, Transaction, kv::KVEngine},
+            executor::{Deadline, Executor, ResultSet},
+            parser::ast::Expression,
+            types::{DataType, Value},
+        },
+        storage::memory::MemoryEngine,
+    };
+
+    // 返回固定数据的执行器
+    struct Rows(Vec<Vec<Value>>);
+
+    impl<T: Transaction> Executor<T> for Rows {
+        fn execute(self: Box<Self>, _txn: &mut T) -> Result<ResultSet> {
+            Ok(ResultSet::Scan {
+                columns: vec!["k".into(), "v".into()],
+                types: vec![Some(DataType::Integer), Some(DataType::Integer)],
+                rows: self.0,
+            })
+        }
+    }
+
+    fn calc(func_name: &str, col_name: &str, values: &[Value]) -> Result<Value> {
+        let mut calculator = <dyn Calculator>::build(&func_name.into(), &col_name.into())?;
+        for value in values {
+            calculator.update(value)?;
+        }
+        Ok(calculator.finish())
+    }
+
+    // 原来的实现：先收集所有非 NULL 的值，排序后取最小、最大值，sum 总是浮点数
+    fn calc_by_sort(func_name: &str, values: &[Value]) -> Value {
+        let mut non_null = values
+            .iter()
+            .filter(|v| **v != Value::Null)
+            .collect::<Vec<_>>();
+        non_null.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let sum = non_null.iter().fold(None, |sum, v| match v {
+            Value::Integer(v) => Some(sum.unwrap_or(0.0) + *v as f64),
+            Value::Float(v) => Some(sum.unwrap_or(0.0) + v),
+            _ => sum,
+        });
+        match func_name {
+            "count" => Value::Integer(non_null.len() as i64),
+            "min" => non_null.first().map_or(Value::Null, |v| (*v).clone()),
+            "max" => non_null.last().map_or(Value::Null, |v| (*v).clone()),
+            "sum" => sum.map_or(Value::Null, Value::Float),
+            "avg" => sum.map_or(Value::Null, |s| Value::Float(s / non_null.len() as f64)),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_calculator_matches_sort() -> Result<()> {
+        let columns = vec![
+            vec![
+                Value::Integer(3),
+                Value::Null,
+                Value::Integer(-7),
+                Value::Integer(10),
+                Value::Null,
+                Value::Integer(0),
+            ],
+            vec![
+                Value::Float(1.5),
+                Value::Integer(2),
+                Value::Null,
+                Value::Float(-0.25),
+                Value::Integer(-3),
+            ],
+            vec![
+                Value::String("b".into()),
+                Value::Null,
+                Value::String("a".into()),
+                Value::String("c".into()),
+            ],
+            vec![Value::Null, Value::Null],
+            vec![],
+        ];
+        for values in &columns {
+            let numeric = values
+                .iter()
+                .all(|v| matches!(v, Value::Null | Value::Integer(_) | Value::Float(_)));
+            for func_name in ["count", "min", "max", "sum", "avg"] {
+                if !numeric && (func_name == "sum" || func_name == "avg") {
+                    assert!(calc(func_name, "c", values).is_err());
+                    continue;
+                }
+                let expected = calc_by_sort(func_name, values);
+                // 整数的 sum 改为返回整数，值和原来相同
+                match (calc(func_name, "c", values)?, expected) {
+                    (Value::Integer(v), Value::Float(e)) if func_name == "sum" => {
+                        assert_eq!(v as f64, e, "{:?}", values)
+                    }
+                    (v, e) => assert_eq!(v, e, "{}({:?})", func_name, values),
+                }
+            }
+        }
+
+        // count(*) 包括 NULL
+        assert_eq!(calc("count", "*", &columns[0])?, Value::Integer(6));
+        assert!(<dyn Calculator>::build(&"sum".into(), &"*".into()).is_err());
+        // 整数溢出时报错，出现浮点数之后结果为浮点数
+        assert!(calc("sum", "c", &[Value::Integer(i64::MAX), Value::Integer(1)]).is_err());
+        assert_eq!(
+            calc("sum", "c", &[Value::Integer(1), Value::Float(0.5)])?,
+            Value::Float(1.5)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_large_input() -> Result<()> {
+        // 每个分组只保存聚合的中间状态，行数再多内存占用也只和分组的数量有关
+        const ROWS: i64 = 300_000;
+        let rows = (0..ROWS)
+            .map(|i| vec![Value::Integer(i % 3), Value::Integer(i)])
+            .collect();
+        let field = |name: &str| Expression::Field(name.into());
+        let func = |name: &str, col: &str| Expression::Function(name.into(), col.into());
+        let aggregate = Aggregate::new(
+            Box::new(Rows(rows)),
+            vec![
+                (field("k"), None),
+                (func("count", "*"), None),
+                (func("sum", "v"), None),
+                (func("min", "v"), None),
+                (func("max", "v"), None),
+            ],
+            vec![field("k")],
+            None,
+            Deadline::default(),
+        );
+
+        let mut txn = KVEngine::new(MemoryEngine::new()).begin()?;
+        let ResultSet::Scan { rows, .. } = aggregate.execute(&mut txn)? else {
+            unreachable!()
+        };
+        let expected = (0..3)
+            .map(|k| {
+                let n = ROWS / 3;
+                vec![
+                    Value::Integer(k),
+                    Value::Integer(n),
+                    Value::Integer(3 * n * (n - 1) / 2 + k * n),
+                    Value::Integer(k),
+                    Value::Integer(ROWS - 3 + k),
+                ]
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows, expected);
+        Ok(())
     }
 }
@@ -0,0 +1,297 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use crate::{
+    error::{Error, Result},
+    sql::{
+        engine::Transaction,
+        executor::{Executor, ResultSet},
+        schema::{Column, Table},
+        types::{DataType, Row, Value, format_timestamp},
+    },
+};
+
+use super::mutation::{assign_auto_increment, make_row, pad_row};
+
+// 导入时每批写入的行数
+const COPY_BATCH_SIZE: usize = 1000;
+
+// CSV 中的一个字段，quoted 表示是否带引号，用于区分 NULL（,,）和空字符串（,"",）
+struct Field {
+    value: String,
+    quoted: bool,
+}
+
+// 从 CSV 文件导入数据
+pub struct CopyFrom {
+    table_name: String,
+    path: String,
+    header: bool,
+}
+
+impl CopyFrom {
+    pub fn new(table_name: String, path: String, header: bool) -> Box<Self> {
+        Box::new(Self {
+            table_name,
+            path,
+            header,
+        })
+    }
+}
+
+impl<T: Transaction> Executor<T> for CopyFrom {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_get_table(self.table_name.clone())?;
+        let content = std::fs::read_to_string(&self.path)?;
+        // 记录从 1 开始编号，和文件中的行对应（字段中没有换行时）
+        let mut records = (1..).zip(parse_csv(&content)?);
+
+        // 有列名时按列名对应表中的列，没有给出的列使用默认值
+        let columns = match self.header {
+            true => match records.next() {
+                Some((_, header)) => {
+                    let columns = header.into_iter().map(|f| f.value).collect::<Vec<_>>();
+                    for col_name in columns.iter() {
+                        if !table.columns.iter().any(|c| &c.name == col_name) {
+                            return Err(Error::ColumnNotFound {
+                                table: Some(table.name.clone()),
+                                column: col_name.clone(),
+                                available: Vec::new(),
+                            });
+                        }
+                    }
+                    Some(columns)
+                }
+                None => return Ok(ResultSet::Copy { count: 0 }),
+            },
+            false => None,
+        };
+
+        let mut count = 0;
+        let mut batch = Vec::with_capacity(COPY_BATCH_SIZE);
+        for (line, record) in records {
+            let expected = columns.as_ref().map_or(table.columns.len(), |c| c.len());
+            if record.len() > expected || (columns.is_some() && record.len() < expected) {
+                return Err(Error::BadCopyFormat(format!(
+                    "line {} of {} has {} fields, expected {}",
+                    line,
+                    self.path,
+                    record.len(),
+                    expected
+                )));
+            }
+
+            // 按列的类型解析每个字段，再补全没有给出的列
+            let row = match &columns {
+                Some(columns) => {
+                    let values = record
+                        .into_iter()
+                        .zip(columns.iter())
+                        .map(|(field, col_name)| {
+                            let col = &table.columns[table.get_col_index(col_name)?];
+                            parse_field(&table, col, field, line)
+                        })
+                        .collect::<Result<Row>>()?;
+                    make_row(&table, columns, &values, line)?
+                }
+                None => {
+                    let values = record
+                        .into_iter()
+                        .zip(table.columns.iter())
+                        .map(|(field, col)| parse_field(&table, col, field, line))
+                        .collect::<Result<Row>>()?;
+                    pad_row(&table, &values, line)?
+                }
+            };
+            batch.push(row);
+
+            if batch.len() == COPY_BATCH_SIZE {
+                count += write_batch(txn, &table, std::mem::take(&mut batch))?;
+            }
+        }
+        count += write_batch(txn, &table, batch)?;
+
+        Ok(ResultSet::Copy { count })
+    }
+}
+
+// 在当前事务中写入一批数据，返回写入的行数
+fn write_batch<T: Transaction>(txn: &mut T, table: &Table, mut rows: Vec<Row>) -> Result<usize> {
+    if rows.is_empty() {
+        return Ok(0);
+    }
+    if let Some(col_index) = table.columns.iter().position(|c| c.auto_increment) {
+        assign_auto_increment(txn, table, col_index, &mut rows)?;
+    }
+    let count = rows.len();
+    txn.create_rows(table.name.clone(), rows)?;
+    Ok(count)
+}
+
+// 按列的类型解析字段，不带引号的空字段表示 NULL
+// 不能为 NULL 的字符串列中，空字段按空字符串处理
+fn parse_field(table: &Table, col: &Column, field: Field, line: usize) -> Result<Value> {
+    if field.value.is_empty() && !field.quoted {
+        if col.nullable || col.datatype != DataType::String {
+            return Ok(Value::Null);
+        }
+        return Ok(Value::String(String::new()));
+    }
+    let value = field.value;
+    let mismatch = || Error::TypeMismatch {
+        table: table.name.clone(),
+        column: col.name.clone(),
+        expected: col.datatype.to_string(),
+        found: format!("value '{}' in line {}", value, line),
+    };
+    Ok(match col.datatype {
+        DataType::Boolean => match value.to_lowercase().as_str() {
+            "true" | "t" | "1" => Value::Boolean(true),
+            "false" | "f" | "0" => Value::Boolean(false),
+            _ => return Err(mismatch()),
+        },
+        DataType::Integer => Value::Integer(value.trim().parse().map_err(|_| mismatch())?),
+        DataType::Float => Value::Float(value.trim().parse().map_err(|_| mismatch())?),
+        DataType::String => Value::String(value),
+        DataType::Timestamp => Value::String(value.clone())
+            .coerce(&DataType::Timestamp)
+            .map_err(|_| mismatch())?,
+    })
+}
+
+// 解析 CSV 内容，字段之间用逗号分隔，记录之间用换行（\n 或 \r\n）分隔
+// 带引号的字段中可以包含逗号和换行，两个连续的引号表示一个引号
+fn parse_csv(content: &str) -> Result<Vec<Vec<Field>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = Field {
+        value: String::new(),
+        quoted: false,
+    };
+    let mut chars = content.chars().peekable();
+    // 当前记录中是否已经有内容，文件末尾的换行不产生空记录
+    let mut started = false;
+
+    while let Some(c) = chars.next() {
+        started = true;
+        match c {
+            '"' if field.value.is_empty() && !field.quoted => {
+                field.quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.value.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => field.value.push(c),
+                        None => {
+                            return Err(Error::BadCopyFormat(format!(
+                                "unterminated quoted field in record {}",
+                                records.len() + 1
+                            )));
+                        }
+                    }
+                }
+                // 引号之后只能是分隔符或者换行
+                if !matches!(chars.peek(), None | Some(',') | Some('\n') | Some('\r')) {
+                    return Err(Error::BadCopyFormat(format!(
+                        "unexpected character after quoted field in record {}",
+                        records.len() + 1
+                    )));
+                }
+            }
+            ',' => record.push(std::mem::replace(
+                &mut field,
+                Field {
+                    value: String::new(),
+                    quoted: false,
+                },
+            )),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::replace(
+                    &mut field,
+                    Field {
+                        value: String::new(),
+                        quoted: false,
+                    },
+                ));
+                records.push(std::mem::take(&mut record));
+                started = false;
+            }
+            c => field.value.push(c),
+        }
+    }
+    if started {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// 把 source 的输出导出为 CSV 文件，第一行是列名
+pub struct CopyTo<T: Transaction> {
+    table_name: String,
+    path: String,
+    source: Box<dyn Executor<T>>,
+}
+
+impl<T: Transaction> CopyTo<T> {
+    pub fn new(table_name: String, path: String, source: Box<dyn Executor<T>>) -> Box<Self> {
+        Box::new(Self {
+            table_name,
+            path,
+            source,
+        })
+    }
+}
+
+impl<T: Transaction> Executor<T> for CopyTo<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_get_table(self.table_name)?;
+        let (_, _, rows) = self.source.scan(txn)?;
+
+        let mut writer = BufWriter::new(File::create(&self.path)?);
+        let header = table
+            .columns
+            .iter()
+            .map(|c| quote_field(&c.name))
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", header.join(","))?;
+
+        let mut count = 0;
+        for row in rows {
+            let fields = row?.iter().map(format_field).collect::<Vec<_>>();
+            writeln!(writer, "{}", fields.join(","))?;
+            count += 1;
+        }
+        writer.flush()?;
+
+        Ok(ResultSet::Copy { count })
+    }
+}
+
+// NULL 导出为空字段，空字符串导出为 ""，导入时可以区分两者
+pub(super) fn format_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::String(s) if s.is_empty() => "\"\"".to_string(),
+        Value::String(s) => quote_field(s),
+        Value::Timestamp(t) => format_timestamp(*t),
+    }
+}
+
+// 包含逗号、引号、换行，或者首尾有空格的字段需要加引号，字段中的引号写成两个引号
+pub(super) fn quote_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) || s.trim() != s {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
@@ -3,7 +3,7 @@ use crate::sql::parser::ast::{Expression, evaluate_expr};
 use crate::sql::types::Value;
 use crate::sql::{
     engine::Transaction,
-    executor::{Executor, ResultSet},
+    executor::{Deadline, Executor, ResultSet},
 };
 
 pub struct NestedLoopJoin<T: Transaction + 'static> {
@@ -11,6 +11,7 @@ pub struct NestedLoopJoin<T: Transaction + 'static> {
     right: Box<dyn Executor<T>>,
     predicate: Option<Expression>,
     outer: bool,
+    deadline: Deadline,
 }
 
 impl<T: Transaction> NestedLoopJoin<T> {
@@ -19,12 +20,14 @@ impl<T: Transaction> NestedLoopJoin<T> {
         right: Box<dyn Executor<T>>,
         predicate: Option<Expression>,
         outer: bool,
+        deadline: Deadline,
     ) -> Box<Self> {
         Box::new(Self {
             left,
             right,
             predicate,
             outer,
+            deadline,
         })
     }
 }
@@ -34,22 +37,31 @@ impl<T: Transaction> Executor<T> for NestedLoopJoin<T> {
         // 先执行左边
         if let ResultSet::Scan {
             columns: lcolumns,
+            types: ltypes,
             rows: lrows,
         } = self.left.execute(txn)?
         {
             let mut new_columns = lcolumns.clone();
+            let mut new_types = ltypes;
             let mut new_rows = vec![];
             // 再执行右边
             if let ResultSet::Scan {
                 columns: rcolumns,
+                types: rtypes,
                 rows: rrows,
             } = self.right.execute(txn)?
             {
                 new_columns.extend(rcolumns.clone());
+                // 外连接中填充的 NULL 不改变列的类型
+                new_types.extend(rtypes);
 
+                // 没有 Join 条件时结果是两边行数的乘积，需要检查是否超时
+                let mut processed = 0;
                 for lrow in &lrows {
                     let mut matched = false;
                     for rrow in &rrows {
+                        self.deadline.check(processed)?;
+                        processed += 1;
                         let mut new_row = lrow.clone();
 
                         // 如果有 Join 条件，查看是否满足 Join 条件
@@ -67,20 +79,21 @@ impl<T: Transaction> Executor<T> for NestedLoopJoin<T> {
                         } else {
                             new_row.extend(rrow.clone());
                             new_rows.push(new_row);
+                            matched = true;
                         }
                     }
 
+                    // 外连接中没有匹配的行，右边的每一列都填充 NULL（右边可能没有数据，按照列数填充）
                     if self.outer && !matched {
                         let mut new_row = lrow.clone();
-                        for _ in 0..rrows[0].len() {
-                            new_row.push(Value::Null);
-                        }
+                        new_row.extend(vec![Value::Null; rcolumns.len()]);
                         new_rows.push(new_row);
                     }
                 }
             }
             return Ok(ResultSet::Scan {
                 columns: { new_columns },
+                types: new_types,
                 rows: new_rows,
             });
         }
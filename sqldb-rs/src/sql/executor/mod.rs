@@ -1,20 +1,32 @@
-use schema::CreateTable;
+use std::time::{Duration, Instant};
+
+use schema::{CreateIndex, CreateTable, DropTable};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    error::Result,
+    error::{Error, Result},
     sql::{
         engine::Transaction,
         executor::{
+            copy::{CopyFrom, CopyTo},
             join::NestedLoopJoin,
-            mutation::{Delete, Insert, Update},
-            query::{Filter, Limit, Offset, Order, Projection, Scan},
+            mutation::{Delete, Insert, Truncate, Update},
+            query::{
+                Distinct, Explain, Filter, InSubquery, IndexScan, KeyLookup, Limit, Nothing,
+                Offset, Order, Projection, Scan,
+            },
         },
+        parser::lexer::{Keyword, quote_ident},
     },
 };
 
-use super::{plan::Node, types::Row};
+use super::{
+    plan::Node,
+    types::{DEFAULT_FLOAT_PRECISION, DataType, Row, Rows, Value, format_float},
+};
 
 mod agg;
+mod copy;
 mod join;
 mod mutation;
 mod query;
@@ -23,6 +35,66 @@ mod schema;
 // 执行器定义
 pub trait Executor<T: Transaction> {
     fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet>;
+
+    // 以迭代器的方式输出查询结果（列名，列的类型，数据行），上层执行器可以按需读取数据
+    // 默认实现先执行得到完整的结果集，Scan、Filter、Projection、Distinct、Limit、Offset 会覆盖为按需读取的实现
+    fn scan(
+        self: Box<Self>,
+        txn: &mut T,
+    ) -> Result<(Vec<String>, Vec<Option<DataType>>, Rows<'_>)> {
+        match self.execute(txn)? {
+            ResultSet::Scan {
+                columns,
+                types,
+                rows,
+            } => Ok((columns, types, Box::new(rows.into_iter().map(Ok)))),
+            _ => Err(Error::Internal("Unexpected result set".into())),
+        }
+    }
+}
+
+// 语句的执行期限，Scan、NestedLoopJoin、Aggregate 的循环中每处理 CHECK_INTERVAL 行检查一次是否超时
+// 超时后返回 Error::Timeout，由上层回滚事务，连接可以继续使用
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deadline {
+    at: Option<Instant>,
+    timeout: Duration,
+}
+
+impl Deadline {
+    const CHECK_INTERVAL: usize = 1024;
+
+    // timeout 为 None 时不限制执行时间
+    pub fn new(timeout: Option<Duration>) -> Self {
+        match timeout {
+            Some(timeout) => Self {
+                at: Some(Instant::now() + timeout),
+                timeout,
+            },
+            None => Self::default(),
+        }
+    }
+
+    // processed 为当前循环已经处理的行数，每隔 CHECK_INTERVAL 行才读取一次时间
+    pub fn check(&self, processed: usize) -> Result<()> {
+        match self.at {
+            Some(at) if processed.is_multiple_of(Self::CHECK_INTERVAL) && Instant::now() >= at => {
+                Err(Error::Timeout(self.timeout.as_millis() as u64))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// 在结果集的边界上把按需读取的数据行收集起来
+fn collect_rows(
+    (columns, types, rows): (Vec<String>, Vec<Option<DataType>>, Rows),
+) -> Result<ResultSet> {
+    Ok(ResultSet::Scan {
+        columns,
+        types,
+        rows: rows.collect::<Result<_>>()?,
+    })
 }
 
 ///
@@ -51,16 +123,39 @@ pub trait Executor<T: Transaction> {
 ///     递归调用 → 返回 dyn Executor<T> → 需要 T: 'static。但 T 没有约束 → 编译错误！
 impl<T: Transaction + 'static> dyn Executor<T> {
     // 把sql计划转化为sql执行器
-    pub fn build(node: Node) -> Box<dyn Executor<T>> {
+    pub fn build(node: Node, deadline: Deadline) -> Box<dyn Executor<T>> {
         match node {
             Node::CreateTable { schema } => CreateTable::new(schema),
+            Node::DropTable { name, if_exists } => DropTable::new(name, if_exists),
+            Node::CreateIndex {
+                name,
+                table_name,
+                column,
+            } => CreateIndex::new(name, table_name, column),
             Node::Insert {
                 table_name,
                 columns,
                 values,
             } => Insert::new(table_name, columns, values),
-            Node::Scan { table_name, filter } => Scan::new(table_name, filter),
-            Node::Order { source, order_by } => Order::new(Self::build(*source), order_by),
+            Node::Scan {
+                table_name,
+                alias,
+                filter,
+            } => Scan::new(table_name, alias, filter, deadline),
+            Node::KeyLookup {
+                table_name,
+                alias,
+                value,
+            } => KeyLookup::new(table_name, alias, value),
+            Node::IndexScan {
+                table_name,
+                alias,
+                column,
+                value,
+            } => IndexScan::new(table_name, alias, column, value),
+            Node::Order { source, order_by } => {
+                Order::new(Self::build(*source, deadline), order_by)
+            }
             Node::Update {
                 table_name,
                 source,
@@ -68,46 +163,100 @@ impl<T: Transaction + 'static> dyn Executor<T> {
             } => Update::new(
                 table_name,
                 // 注意这里有一个递归，涉及到trait object的生命周期擦除
-                Self::build(*source),
+                Self::build(*source, deadline),
                 columns,
             ),
             Node::Delete { table_name, source } => Delete::new(
                 table_name,
                 // 注意这里有一个递归，涉及到trait object的生命周期擦除
-                Self::build(*source),
+                Self::build(*source, deadline),
             ),
-            Node::Limit { source, limit } => Limit::new(Self::build(*source), limit),
-            Node::Offset { source, offset } => Offset::new(Self::build(*source), offset),
-            Node::Projection { source, select } => Projection::new(Self::build(*source), select),
+            Node::Truncate { table_name } => Truncate::new(table_name),
+            Node::Limit { source, limit } => Limit::new(Self::build(*source, deadline), limit),
+            Node::Offset { source, offset } => Offset::new(Self::build(*source, deadline), offset),
+            Node::Projection { source, select } => {
+                Projection::new(Self::build(*source, deadline), select)
+            }
+            Node::Distinct { source } => Distinct::new(Self::build(*source, deadline)),
             Node::NestedLoopJoin {
                 left,
                 right,
                 predicate,
                 outer,
-            } => NestedLoopJoin::new(Self::build(*left), Self::build(*right), predicate, outer),
+            } => NestedLoopJoin::new(
+                Self::build(*left, deadline),
+                Self::build(*right, deadline),
+                predicate,
+                outer,
+                deadline,
+            ),
             Node::Aggregate {
                 source,
                 exprs,
                 group_by,
-            } => agg::Aggregate::new(Self::build(*source), exprs, group_by),
-            Node::Filter { source, predicate } => Filter::new(Self::build(*source), predicate),
+                having,
+            } => agg::Aggregate::new(
+                Self::build(*source, deadline),
+                exprs,
+                group_by,
+                having,
+                deadline,
+            ),
+            Node::Filter { source, predicate } => {
+                Filter::new(Self::build(*source, deadline), predicate)
+            }
+            Node::InSubquery {
+                source,
+                expr,
+                subquery,
+                negated,
+            } => InSubquery::new(
+                Self::build(*source, deadline),
+                expr,
+                Self::build(*subquery, deadline),
+                negated,
+            ),
+            Node::Nothing => Nothing::new(),
+            Node::Explain { source } => Explain::new(*source),
+            Node::CopyFrom {
+                table_name,
+                path,
+                header,
+            } => CopyFrom::new(table_name, path, header),
+            Node::CopyTo {
+                table_name,
+                path,
+                source,
+            } => CopyTo::new(table_name, path, Self::build(*source, deadline)),
         }
     }
 }
 
 // 执行结果集
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ResultSet {
     CreateTable {
         table_name: String,
     },
 
+    DropTable {
+        table_name: String,
+    },
+
+    CreateIndex {
+        index_name: String,
+    },
+
+    // keys 为按插入顺序排列的每一行的主键
     Insert {
         count: usize,
+        keys: Vec<Value>,
     },
 
+    // types 和 columns 一一对应，为每一列的类型，无法确定类型时为 None，例如 SELECT NULL
     Scan {
         columns: Vec<String>,
+        types: Vec<Option<DataType>>,
         rows: Vec<Row>,
     },
     Update {
@@ -125,59 +274,57 @@ pub enum ResultSet {
     Rollback {
         version: u64,
     },
+    Vacuum {
+        count: usize,
+    },
+    Explain {
+        plan: String,
+    },
+    // COPY 导入或导出的行数
+    Copy {
+        count: usize,
+    },
 }
 
+// INSERT 的结果中最多展示的主键数量
+const MAX_DISPLAY_KEYS: usize = 10;
+
 impl ResultSet {
     pub fn to_string(&self) -> String {
+        self.to_string_with_precision(DEFAULT_FLOAT_PRECISION)
+    }
+
+    // float_precision 为浮点数展示的有效数字位数
+    pub fn to_string_with_precision(&self, float_precision: usize) -> String {
         match self {
             ResultSet::CreateTable { table_name } => {
                 format!("CREATE TABLE {}", table_name)
             }
-            ResultSet::Insert { count } => {
-                format!("INSERT {} ROWS.", count)
+            ResultSet::DropTable { table_name } => {
+                format!("DROP TABLE {}", table_name)
             }
-            ResultSet::Scan { columns, rows } => {
-                let rows_len = rows.len();
-
-                // 找到每一列最大的长度
-                let mut max_len = columns.iter().map(|c| c.len()).collect::<Vec<_>>();
-                for one_row in rows {
-                    for (i, v) in one_row.iter().enumerate() {
-                        max_len[i] = max_len[i].max(v.to_string().len());
-                    }
-                }
-
-                // 展示列
-                let columns = columns
-                    .iter()
-                    .zip(max_len.iter())
-                    .map(|(col, &len)| format!("{:width$}", col, width = len))
-                    .collect::<Vec<_>>()
-                    .join(" |");
-
-                // 展示分隔符
-                let sep = max_len
-                    .iter()
-                    .map(|v| format!("{}", "-".repeat(*v + 1)))
-                    .collect::<Vec<_>>()
-                    .join("+");
-
-                // 展示列的数据
-                let rows = rows
+            ResultSet::CreateIndex { index_name } => {
+                format!("CREATE INDEX {}", index_name)
+            }
+            // 插入的行数不多时展示写入的主键
+            ResultSet::Insert { count, keys }
+                if !keys.is_empty() && keys.len() <= MAX_DISPLAY_KEYS =>
+            {
+                let keys = keys
                     .iter()
-                    .map(|row| {
-                        row.iter()
-                            .zip(max_len.iter())
-                            .map(|(v, &len)| format!("{:width$}", v.to_string(), width = len))
-                            .collect::<Vec<_>>()
-                            .join(" |")
-                    })
+                    .map(|k| format_cell(k, float_precision))
                     .collect::<Vec<_>>()
-                    .join("\n");
-
-                // 组合结果
-                format!("{}\n{}\n{}\n({} rows)", columns, sep, rows, rows_len)
+                    .join(", ");
+                format!("INSERT {} ROWS. keys: {}", count, keys)
+            }
+            ResultSet::Insert { count, .. } => {
+                format!("INSERT {} ROWS.", count)
             }
+            ResultSet::Scan {
+                columns,
+                types,
+                rows,
+            } => format_table(columns, types, rows, false, float_precision),
             ResultSet::Update { count } => {
                 format!("UPDATE {} ROWS.", count)
             }
@@ -187,6 +334,343 @@ impl ResultSet {
             ResultSet::Begin { version } => format!("TRANSACTION {} BEGIN", version),
             ResultSet::Commit { version } => format!("TRANSACTION {} COMMIT", version),
             ResultSet::Rollback { version } => format!("TRANSACTION {} ROLLBACK", version),
+            ResultSet::Vacuum { count } => format!("VACUUM {} VERSIONS.", count),
+            ResultSet::Explain { plan } => plan.clone(),
+            ResultSet::Copy { count } => format!("COPY {} ROWS.", count),
+        }
+    }
+
+    // 和 to_string 相同，但是查询结果中数值类型的列右对齐
+    pub fn to_table(&self) -> String {
+        self.to_table_with_precision(DEFAULT_FLOAT_PRECISION)
+    }
+
+    pub fn to_table_with_precision(&self, float_precision: usize) -> String {
+        match self {
+            ResultSet::Scan {
+                columns,
+                types,
+                rows,
+            } => format_table(columns, types, rows, true, float_precision),
+            rs => rs.to_string_with_precision(float_precision),
+        }
+    }
+
+    // 查询结果输出为 CSV，第一行是列名，格式和 COPY TO 导出的文件相同，其他语句的结果和 to_string 相同
+    pub fn to_csv(&self) -> String {
+        match self {
+            ResultSet::Scan { columns, rows, .. } => {
+                let mut lines = vec![
+                    columns
+                        .iter()
+                        .map(|c| copy::quote_field(c))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ];
+                for row in rows {
+                    lines.push(
+                        row.iter()
+                            .map(copy::format_field)
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    );
+                }
+                lines.join("\n")
+            }
+            rs => rs.to_string(),
+        }
+    }
+
+    // 结构化的结果，查询返回 {"columns": [...], "types": [...], "rows": [[...]]}，其他语句返回 {"message": "..."}
+    // types 为每一列的类型名称，例如 "INTEGER"，无法确定类型时为 null
+    pub fn to_json(&self) -> String {
+        let json = match self {
+            ResultSet::Scan {
+                columns,
+                types,
+                rows,
+            } => serde_json::json!({
+                "columns": columns,
+                "types": types
+                    .iter()
+                    .map(|t| t.map(|t| t.to_string()))
+                    .collect::<Vec<_>>(),
+                "rows": rows
+                    .iter()
+                    .map(|row| row.iter().map(Value::to_json).collect::<Vec<_>>())
+                    .collect::<Vec<_>>(),
+            }),
+            ResultSet::Insert { keys, .. } => serde_json::json!({
+                "message": self.to_string(),
+                "keys": keys.iter().map(Value::to_json).collect::<Vec<_>>(),
+            }),
+            rs => serde_json::json!({ "message": rs.to_string() }),
+        };
+        json.to_string()
+    }
+}
+
+// 结果中的 NULL 使用小写展示，避免和字符串 'NULL' 混淆
+// 浮点数按有效数字展示，计算得到的 NaN 没有意义，和 NULL 一样展示
+fn format_cell(v: &Value, float_precision: usize) -> String {
+    match v {
+        Value::Null => "null".to_string(),
+        Value::Float(f) if f.is_nan() => "null".to_string(),
+        Value::Float(f) => format_float(*f, float_precision),
+        v => v.to_string(),
+    }
+}
+
+// 在终端中的显示宽度，中日韩等全角字符占两列
+fn display_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+// 以表格的形式展示查询结果，align_numbers 为 true 时数值类型的列右对齐
+fn format_table(
+    columns: &[String],
+    types: &[Option<DataType>],
+    rows: &[Row],
+    align_numbers: bool,
+    float_precision: usize,
+) -> String {
+    let rows_len = rows.len();
+    let rows = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|v| format_cell(v, float_precision))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // 找到每一列最大的显示宽度
+    let columns = columns.iter().map(|c| format_header(c)).collect::<Vec<_>>();
+    let mut max_len = columns.iter().map(|c| display_width(c)).collect::<Vec<_>>();
+    for one_row in rows.iter() {
+        for (i, v) in one_row.iter().enumerate() {
+            max_len[i] = max_len[i].max(display_width(v));
         }
     }
+
+    // 展示列
+    let columns = columns
+        .iter()
+        .zip(max_len.iter())
+        .map(|(col, &len)| pad(col, len))
+        .collect::<Vec<_>>()
+        .join(" |");
+
+    // 展示分隔符
+    let sep = max_len
+        .iter()
+        .map(|v| format!("{}", "-".repeat(*v + 1)))
+        .collect::<Vec<_>>()
+        .join("+");
+
+    // 展示列的数据，没有数据时不输出空行
+    let right_align = (0..max_len.len())
+        .map(|i| {
+            align_numbers
+                && matches!(
+                    types.get(i),
+                    Some(Some(DataType::Integer | DataType::Float))
+                )
+        })
+        .collect::<Vec<_>>();
+    let rows = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(max_len.iter())
+                .zip(right_align.iter())
+                .map(|((v, &len), &right)| match right {
+                    true => pad_left(v, len),
+                    false => pad(v, len),
+                })
+                .collect::<Vec<_>>()
+                .join(" |")
+                + "\n"
+        })
+        .collect::<String>();
+
+    // 组合结果
+    format!("{}\n{}\n{}({} rows)", columns, sep, rows, rows_len)
+}
+
+// 列名是关键字或者包含空格时加上双引号，避免和表达式混淆
+// 表达式（例如 a + 1、count(a)、a IS NULL）原样展示
+fn format_header(col: &str) -> String {
+    let words = col.split(' ').collect::<Vec<_>>();
+    let is_name = col
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == ' ')
+        && (words.len() == 1 || words.iter().all(|w| Keyword::from_str(w).is_none()));
+    match is_name && (words.len() > 1 || Keyword::from_str(col).is_some()) {
+        true => quote_ident(col),
+        false => col.to_string(),
+    }
+}
+
+// 按显示宽度在右边补齐空格
+fn pad(s: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        s,
+        " ".repeat(width.saturating_sub(display_width(s)))
+    )
+}
+
+// 按显示宽度在左边补齐空格，用于右对齐
+fn pad_left(s: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        " ".repeat(width.saturating_sub(display_width(s))),
+        s
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResultSet, display_width};
+    use crate::sql::types::{DataType, Value};
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("中文"), 4);
+        assert_eq!(display_width("a中b"), 4);
+        assert_eq!(display_width("ｆｕｌｌ"), 8);
+        assert_eq!(display_width(""), 0);
+    }
+
+    #[test]
+    fn test_scan_to_string() {
+        let rs = ResultSet::Scan {
+            columns: vec!["id".to_string(), "名字".to_string(), "note".to_string()],
+            types: vec![
+                Some(DataType::Integer),
+                Some(DataType::String),
+                Some(DataType::String),
+            ],
+            rows: vec![
+                vec![
+                    Value::Integer(1),
+                    Value::String("张三".to_string()),
+                    Value::Null,
+                ],
+                vec![
+                    Value::Integer(100),
+                    Value::String("bob".to_string()),
+                    Value::String("NULL".to_string()),
+                ],
+            ],
+        };
+        assert_eq!(
+            rs.to_string(),
+            [
+                "id  |名字 |note",
+                "----+-----+-----",
+                "1   |张三 |null",
+                "100 |bob  |NULL",
+                "(2 rows)",
+            ]
+            .join("\n")
+        );
+
+        let rs = ResultSet::Scan {
+            columns: vec!["a".to_string(), "b".to_string()],
+            types: vec![Some(DataType::Integer), None],
+            rows: vec![],
+        };
+        assert_eq!(rs.to_string(), "a |b\n--+--\n(0 rows)");
+    }
+
+    #[test]
+    fn test_to_json() {
+        let rs = ResultSet::Scan {
+            columns: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            types: vec![Some(DataType::Integer), Some(DataType::String), None],
+            rows: vec![vec![
+                Value::Integer(1),
+                Value::String("张三 \"x\"".to_string()),
+                Value::Null,
+            ]],
+        };
+        assert_eq!(
+            rs.to_json(),
+            r#"{"columns":["a","b","c"],"rows":[[1,"张三 \"x\"",null]],"types":["INTEGER","STRING",null]}"#
+        );
+        assert_eq!(
+            ResultSet::Insert {
+                count: 2,
+                keys: vec![Value::Integer(1), Value::String("b".to_string())],
+            }
+            .to_json(),
+            r#"{"keys":[1,"b"],"message":"INSERT 2 ROWS. keys: 1, b"}"#
+        );
+    }
+
+    #[test]
+    fn test_to_table_and_csv() {
+        let rs = ResultSet::Scan {
+            columns: vec!["id".to_string(), "name".to_string(), "score".to_string()],
+            types: vec![
+                Some(DataType::Integer),
+                Some(DataType::String),
+                Some(DataType::Float),
+            ],
+            rows: vec![
+                vec![
+                    Value::Integer(1),
+                    Value::String("a, \"b\"".to_string()),
+                    Value::Float(12.5),
+                ],
+                vec![
+                    Value::Integer(100),
+                    Value::String("".to_string()),
+                    Value::Null,
+                ],
+            ],
+        };
+        // 数值类型的列右对齐
+        assert_eq!(
+            rs.to_table(),
+            [
+                "id  |name   |score",
+                "----+-------+------",
+                "  1 |a, \"b\" | 12.5",
+                "100 |       | null",
+                "(2 rows)",
+            ]
+            .join("\n")
+        );
+        // NULL 为空字段，空字符串为 ""，包含逗号和引号的字段加上引号
+        assert_eq!(
+            rs.to_csv(),
+            ["id,name,score", "1,\"a, \"\"b\"\"\",12.5", "100,\"\","].join("\n")
+        );
+
+        let rs = ResultSet::Update { count: 2 };
+        assert_eq!(rs.to_table(), rs.to_string());
+        assert_eq!(rs.to_csv(), rs.to_string());
+    }
 }
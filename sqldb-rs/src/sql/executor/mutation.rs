@@ -6,7 +6,7 @@ use crate::sql::types::{Row, Value};
 use crate::sql::{
     engine::Transaction,
     executor::{Executor, ResultSet},
-    parser::ast::Expression,
+    parser::ast::{Expression, evaluate_expr_single},
 };
 
 pub struct Insert {
@@ -32,38 +32,113 @@ impl Insert {
 impl<T: Transaction> Executor<T> for Insert {
     fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
         // 获取表的信息
-        let mut count = 0;
         let table = txn.must_get_table(self.table_name.clone())?;
+        let mut rows = Vec::with_capacity(self.values.len());
+
+        // 指定的列必须存在于表中
+        for col_name in self.columns.iter() {
+            if !table.columns.iter().any(|c| &c.name == col_name) {
+                return Err(Error::ColumnNotFound {
+                    table: Some(table.name.clone()),
+                    column: col_name.clone(),
+                    available: Vec::new(),
+                });
+            }
+        }
 
-        for exprs in self.values {
-            // 将 expression 表达式转换成 value
+        // row_num 为 VALUES 列表中的行号，从 1 开始，用于错误信息
+        for (row_num, exprs) in (1..).zip(self.values) {
+            // 先校验值的数量，避免多余的值被忽略
+            if self.columns.is_empty() && exprs.len() > table.columns.len() {
+                return Err(Error::InvalidRow(format!(
+                    "too many values in row {} for table {}: expected at most {}, got {}",
+                    row_num,
+                    table.name,
+                    table.columns.len(),
+                    exprs.len()
+                )));
+            }
+            if !self.columns.is_empty() && exprs.len() != self.columns.len() {
+                return Err(Error::InvalidRow(format!(
+                    "row {} for table {} has {} values, but {} columns were specified",
+                    row_num,
+                    table.name,
+                    exprs.len(),
+                    self.columns.len()
+                )));
+            }
+
+            // 将 expression 表达式计算成 value，插入的数据中不能引用列
             let row = exprs
-                .into_iter()
-                .map(|e| Value::from_expression(e))
-                .collect::<Vec<_>>();
+                .iter()
+                .map(|e| evaluate_expr_single(e, &[], &[]))
+                .collect::<Result<Vec<_>>>()?;
             // 如果没有指定插入的列
             let insert_row = if self.columns.is_empty() {
-                pad_row(&table, &row)?
+                pad_row(&table, &row, row_num)?
             } else {
                 // 指定了插入的列，需要对 value 信息进行整理
-                make_row(&table, &self.columns, &row)?
+                make_row(&table, &self.columns, &row, row_num)?
             };
 
-            // 插入数据
-            txn.create_row(self.table_name.clone(), insert_row)?;
-            count += 1;
+            // 转换为列的类型，例如字符串转换为时间戳
+            let insert_row = insert_row
+                .into_iter()
+                .zip(table.columns.iter())
+                .map(|(v, col)| v.coerce(&col.datatype))
+                .collect::<Result<Row>>()?;
+
+            rows.push(insert_row);
+        }
+
+        // 为自增列分配值
+        if let Some(col_index) = table.columns.iter().position(|c| c.auto_increment) {
+            assign_auto_increment(txn, &table, col_index, &mut rows)?;
         }
 
-        Ok(super::ResultSet::Insert { count: count })
+        // 批量插入数据，返回写入的主键
+        let count = rows.len();
+        let keys = rows
+            .iter()
+            .map(|row| table.get_primary_key(row))
+            .collect::<Result<Vec<_>>>()?;
+        txn.create_rows(self.table_name.clone(), rows)?;
+
+        Ok(super::ResultSet::Insert { count, keys })
     }
 }
 
-fn make_row(table: &Table, columns: &Vec<String>, values: &Row) -> Result<Row> {
-    // 判断列数是否和value数量一致
-    if columns.len() != values.len() {
-        return Err(Error::Internal(format!("columns and values num mismatch")));
+// 没有给出值或者值为 NULL 的行使用计数器的下一个值，给出的值大于计数器时更新计数器
+pub(super) fn assign_auto_increment<T: Transaction>(
+    txn: &mut T,
+    table: &Table,
+    col_index: usize,
+    rows: &mut [Row],
+) -> Result<()> {
+    let start = txn.get_auto_increment(table)?;
+    let mut counter = start;
+    for row in rows.iter_mut() {
+        match row[col_index] {
+            Value::Null => {
+                counter = counter.checked_add(1).ok_or(Error::NumericOverflow)?;
+                row[col_index] = Value::Integer(counter);
+            }
+            Value::Integer(v) => counter = counter.max(v),
+            _ => {}
+        }
+    }
+    if counter != start {
+        txn.set_auto_increment(table, counter)?;
     }
+    Ok(())
+}
 
+pub(super) fn make_row(
+    table: &Table,
+    columns: &Vec<String>,
+    values: &Row,
+    row_num: usize,
+) -> Result<Row> {
     let mut inputs = HashMap::new();
     for (i, col_name) in columns.iter().enumerate() {
         inputs.insert(col_name, values[i].clone());
@@ -75,32 +150,39 @@ fn make_row(table: &Table, columns: &Vec<String>, values: &Row) -> Result<Row> {
             results.push(value.clone());
         } else if let Some(value) = &col.default {
             results.push(value.clone());
+        } else if col.auto_increment {
+            results.push(Value::Null);
         } else {
-            return Err(Error::Internal(format!(
-                "no value given for the column {}",
-                col.name
-            )));
+            return Err(no_value_error(table, &col.name, row_num));
         }
     }
     Ok(results)
 }
 
-fn pad_row(table: &Table, row: &Row) -> Result<Row> {
+pub(super) fn pad_row(table: &Table, row: &Row, row_num: usize) -> Result<Row> {
     let mut results = row.clone();
     for column in table.columns.iter().skip(row.len()) {
         if let Some(default) = &column.default {
             results.push(default.clone()); // 防止返回引用，应该返回值
+        } else if column.auto_increment {
+            results.push(Value::Null);
         } else {
-            return Err(Error::Internal(format!(
-                "No default value for column {}",
-                column.name
-            )));
+            return Err(no_value_error(table, &column.name, row_num));
         }
     }
 
     Ok(results)
 }
 
+// 没有给出值，并且没有默认值的列
+fn no_value_error(table: &Table, col_name: &str, row_num: usize) -> Error {
+    Error::MissingValue {
+        table: table.name.clone(),
+        column: col_name.to_string(),
+        row: row_num,
+    }
+}
+
 // Update 执行器
 pub struct Update<T> {
     table_name: String,
@@ -128,16 +210,23 @@ impl<T: Transaction> Executor<T> for Update<T> {
 
         // 执行扫描操作，获取到扫描的结果
         match self.source.execute(txn)? {
-            ResultSet::Scan { columns, rows } => {
+            ResultSet::Scan { columns, rows, .. } => {
                 let table = txn.must_get_table(self.table_name)?;
+                // 按照表结构找到每个需要更新的列的位置，而不是依赖扫描结果中列的顺序
+                let targets = self
+                    .columns
+                    .iter()
+                    .map(|(col_name, expr)| Ok((table.get_col_index(col_name)?, expr)))
+                    .collect::<Result<Vec<_>>>()?;
+
                 // 遍历所有需要更新的行
                 for row in rows {
                     let mut new_rows = row.clone();
                     let pk = table.get_primary_key(&row)?;
-                    for (i, col) in columns.iter().enumerate() {
-                        if let Some(expr) = self.columns.get(col) {
-                            new_rows[i] = Value::from_expression(expr.clone());
-                        }
+                    for (i, expr) in targets.iter() {
+                        // 表达式中可以引用当前行的列，例如 a = a + 1
+                        new_rows[*i] = evaluate_expr_single(expr, &columns, &row)?
+                            .coerce(&table.columns[*i].datatype)?;
                     }
 
                     // 执行更新操作
@@ -154,6 +243,25 @@ impl<T: Transaction> Executor<T> for Update<T> {
     }
 }
 
+// Truncate 执行器，在存储层按前缀删除表中所有的数据，结果和 DELETE 相同
+pub struct Truncate {
+    table_name: String,
+}
+
+impl Truncate {
+    pub fn new(table_name: String) -> Box<Self> {
+        Box::new(Self { table_name })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Truncate {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_get_table(self.table_name)?;
+        let count = txn.truncate_table(&table)?;
+        Ok(ResultSet::Delete { count })
+    }
+}
+
 // Delete 执行器
 pub struct Delete<T: Transaction> {
     table_name: String,
@@ -169,7 +277,7 @@ impl<T: Transaction> Delete<T> {
 impl<T: Transaction> Executor<T> for Delete<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
         match self.source.execute(txn)? {
-            ResultSet::Scan { columns: _, rows } => {
+            ResultSet::Scan { rows, .. } => {
                 let mut count = 0;
                 let table = txn.must_get_table(self.table_name)?;
                 for row in rows {
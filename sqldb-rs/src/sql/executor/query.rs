@@ -1,34 +1,141 @@
-use std::{cmp::Ordering, collections::HashMap};
+use std::{cmp::Ordering, collections::HashSet};
 
 use crate::{
     error::Error,
     sql::{
         engine::Transaction,
         executor::ResultSet,
-        parser::ast::{Expression, OrderDirection, evaluate_expr},
-        types::Value,
+        parser::ast::{Expression, OrderDirection, evaluate_expr_single, find_column},
+        plan::Node,
+        types::{DataType, Rows, Value},
     },
 };
 
-use super::Executor;
+use super::{Deadline, Executor, collect_rows};
 
 pub struct Scan {
     table_name: String,
+    alias: Option<String>,
     filter: Option<Expression>,
+    deadline: Deadline,
 }
 
 impl Scan {
-    pub fn new(table_name: String, filter: Option<Expression>) -> Box<Self> {
-        Box::new(Self { table_name, filter })
+    pub fn new(
+        table_name: String,
+        alias: Option<String>,
+        filter: Option<Expression>,
+        deadline: Deadline,
+    ) -> Box<Self> {
+        Box::new(Self {
+            table_name,
+            alias,
+            filter,
+            deadline,
+        })
     }
 }
 
 impl<T: Transaction> Executor<T> for Scan {
+    fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<super::ResultSet> {
+        collect_rows(self.scan(txn)?)
+    }
+
+    fn scan(
+        self: Box<Self>,
+        txn: &mut T,
+    ) -> crate::error::Result<(Vec<String>, Vec<Option<DataType>>, Rows<'_>)> {
+        let table = txn.must_get_table(self.table_name.clone())?;
+        // 存储层按表名过滤数据，过滤条件中的别名需要替换为表名，例如 u.id -> users.id
+        let filter = match &self.alias {
+            Some(alias) => self.filter.map(|filter| {
+                filter.transform(&mut |expr| match expr {
+                    Expression::Field(col) => match col.split_once('.') {
+                        Some((t, c)) if t == alias => {
+                            Expression::Field(format!("{}.{}", self.table_name, c))
+                        }
+                        _ => Expression::Field(col),
+                    },
+                    expr => expr,
+                })
+            }),
+            None => self.filter,
+        };
+        let deadline = self.deadline;
+        let rows = txn
+            .scan_table(self.table_name.clone(), filter)?
+            .enumerate()
+            .map(move |(i, row)| deadline.check(i).and(row));
+        let qualifier = self.alias.unwrap_or(self.table_name);
+        Ok((
+            table.qualified_columns(&qualifier),
+            table.column_types(),
+            Box::new(rows),
+        ))
+    }
+}
+
+pub struct KeyLookup {
+    table_name: String,
+    alias: Option<String>,
+    value: Value,
+}
+
+impl KeyLookup {
+    pub fn new(table_name: String, alias: Option<String>, value: Value) -> Box<Self> {
+        Box::new(Self {
+            table_name,
+            alias,
+            value,
+        })
+    }
+}
+
+impl<T: Transaction> Executor<T> for KeyLookup {
+    fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<super::ResultSet> {
+        let table = txn.must_get_table(self.table_name)?;
+        let rows = txn
+            .read_row_by_pk(&table, &self.value)?
+            .into_iter()
+            .collect();
+        Ok(ResultSet::Scan {
+            columns: table.qualified_columns(self.alias.as_ref().unwrap_or(&table.name)),
+            types: table.column_types(),
+            rows,
+        })
+    }
+}
+
+pub struct IndexScan {
+    table_name: String,
+    alias: Option<String>,
+    column: String,
+    value: Value,
+}
+
+impl IndexScan {
+    pub fn new(
+        table_name: String,
+        alias: Option<String>,
+        column: String,
+        value: Value,
+    ) -> Box<Self> {
+        Box::new(Self {
+            table_name,
+            alias,
+            column,
+            value,
+        })
+    }
+}
+
+impl<T: Transaction> Executor<T> for IndexScan {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<super::ResultSet> {
         let table = txn.must_get_table(self.table_name.clone())?;
-        let rows = txn.scan_table(self.table_name.clone(), self.filter)?;
+        let rows = txn.scan_index(self.table_name, self.column, self.value)?;
         Ok(ResultSet::Scan {
-            columns: table.columns.into_iter().map(|c| c.name.clone()).collect(),
+            columns: table.qualified_columns(self.alias.as_ref().unwrap_or(&table.name)),
+            types: table.column_types(),
             rows,
         })
     }
@@ -36,11 +143,14 @@ impl<T: Transaction> Executor<T> for Scan {
 
 pub struct Order<T: Transaction> {
     source: Box<dyn Executor<T>>,
-    order_by: Vec<(String, OrderDirection)>,
+    order_by: Vec<(Expression, OrderDirection)>,
 }
 
 impl<T: Transaction> Order<T> {
-    pub fn new(source: Box<dyn Executor<T>>, order_by: Vec<(String, OrderDirection)>) -> Box<Self> {
+    pub fn new(
+        source: Box<dyn Executor<T>>,
+        order_by: Vec<(Expression, OrderDirection)>,
+    ) -> Box<Self> {
         Box::new(Self { source, order_by })
     }
 }
@@ -48,27 +158,33 @@ impl<T: Transaction> Order<T> {
 impl<T: Transaction> Executor<T> for Order<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
         match self.source.execute(txn)? {
-            ResultSet::Scan { columns, mut rows } => {
-                // 找到 order_by 的列对应表中的位置
-                let mut order_col_index = HashMap::new();
-                for (i, (col_name, _)) in self.order_by.iter().enumerate() {
-                    match columns.iter().position(|c| *c == *col_name) {
-                        Some(pos) => order_col_index.insert(i, pos),
-                        None => {
-                            return Err(Error::Internal(format!(
-                                "order by column {} is not in table",
-                                col_name
-                            )));
-                        }
-                    };
+            ResultSet::Scan {
+                columns,
+                types,
+                rows,
+            } => {
+                // 没有数据时也需要检查排序表达式中的列是否存在
+                for (expr, _) in self.order_by.iter() {
+                    check_columns(expr, &columns)?;
                 }
 
-                rows.sort_by(|a, b| {
+                // 每一行的排序键只计算一次，排序比较时不再重复计算表达式
+                let mut keyed_rows = rows
+                    .into_iter()
+                    .map(|row| {
+                        let keys = self
+                            .order_by
+                            .iter()
+                            .map(|(expr, _)| evaluate_expr_single(expr, &columns, &row))
+                            .collect::<crate::error::Result<Vec<_>>>()?;
+                        Ok((keys, row))
+                    })
+                    .collect::<crate::error::Result<Vec<_>>>()?;
+
+                // NULL 比其他值都小，升序时排在最前面，降序时排在最后面；不能比较的值视为相等
+                keyed_rows.sort_by(|(a, _), (b, _)| {
                     for (i, (_, direction)) in self.order_by.iter().enumerate() {
-                        let col_index = order_col_index.get(&i).unwrap();
-                        let x = &a[*col_index];
-                        let y = &b[*col_index];
-                        match x.partial_cmp(y) {
+                        match a[i].partial_cmp(&b[i]) {
                             Some(Ordering::Equal) => {}
                             Some(order) => {
                                 return if *direction == OrderDirection::Asc {
@@ -83,13 +199,40 @@ impl<T: Transaction> Executor<T> for Order<T> {
                     Ordering::Equal
                 });
 
-                Ok(ResultSet::Scan { columns, rows })
+                let rows = keyed_rows.into_iter().map(|(_, row)| row).collect();
+                Ok(ResultSet::Scan {
+                    columns,
+                    types,
+                    rows,
+                })
             }
             _ => return Err(Error::Internal("Unexpected result set".into())),
         }
     }
 }
 
+// 检查表达式中引用的列都存在并且没有歧义
+fn check_columns(expr: &Expression, columns: &[String]) -> crate::error::Result<()> {
+    let mut result = Ok(());
+    expr.clone().transform(&mut |expr| {
+        if let Expression::Field(name) = &expr
+            && result.is_ok()
+        {
+            result = match find_column(columns, name) {
+                Ok(Some(_)) => Ok(()),
+                Ok(None) => Err(Error::ColumnNotFound {
+                    table: None,
+                    column: name.clone(),
+                    available: columns.to_vec(),
+                }),
+                Err(err) => Err(err),
+            };
+        }
+        expr
+    });
+    result
+}
+
 pub struct Limit<T: Transaction> {
     source: Box<dyn Executor<T>>,
     limit: usize,
@@ -103,19 +246,16 @@ impl<T: Transaction> Limit<T> {
 
 impl<T: Transaction> Executor<T> for Limit<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
-        match self.source.execute(txn)? {
-            ResultSet::Scan { columns, rows } => {
-                // if rows.len() > self.limit {
-                //     rows.truncate(self.limit);
-                // }
-                // Ok(ResultSet::Scan { columns, rows })
-                Ok(ResultSet::Scan {
-                    columns: columns,
-                    rows: rows.into_iter().take(self.limit).collect(),
-                })
-            }
-            _ => return Err(Error::Internal("Unexpected result set".into())),
-        }
+        collect_rows(self.scan(txn)?)
+    }
+
+    fn scan(
+        self: Box<Self>,
+        txn: &mut T,
+    ) -> crate::error::Result<(Vec<String>, Vec<Option<DataType>>, Rows<'_>)> {
+        // 读取到 limit 行之后不再读取下游的数据
+        let (columns, types, rows) = self.source.scan(txn)?;
+        Ok((columns, types, Box::new(rows.take(self.limit))))
     }
 }
 
@@ -132,19 +272,46 @@ impl<T: Transaction> Offset<T> {
 
 impl<T: Transaction> Executor<T> for Offset<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
-        match self.source.execute(txn)? {
-            ResultSet::Scan { columns, rows } => {
-                // if rows.len() > self.offset {
-                //     rows.drain(0..self.offset);
-                // }
-                // Ok(ResultSet::Scan { columns, rows })
-                Ok(ResultSet::Scan {
-                    columns: columns,
-                    rows: rows.into_iter().skip(self.offset).collect(),
-                })
-            }
-            _ => return Err(Error::Internal("Unexpected result set".into())),
-        }
+        collect_rows(self.scan(txn)?)
+    }
+
+    fn scan(
+        self: Box<Self>,
+        txn: &mut T,
+    ) -> crate::error::Result<(Vec<String>, Vec<Option<DataType>>, Rows<'_>)> {
+        let (columns, types, rows) = self.source.scan(txn)?;
+        Ok((columns, types, Box::new(rows.skip(self.offset))))
+    }
+}
+
+pub struct Distinct<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+}
+
+impl<T: Transaction> Distinct<T> {
+    pub fn new(source: Box<dyn Executor<T>>) -> Box<Self> {
+        Box::new(Self { source })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Distinct<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
+        collect_rows(self.scan(txn)?)
+    }
+
+    fn scan(
+        self: Box<Self>,
+        txn: &mut T,
+    ) -> crate::error::Result<(Vec<String>, Vec<Option<DataType>>, Rows<'_>)> {
+        // 只输出第一次出现的行，保持原来的顺序，之后的 limit 可以提前结束读取
+        // 两个 NULL 视为相同的值
+        let (columns, types, rows) = self.source.scan(txn)?;
+        let mut seen = HashSet::new();
+        let rows = rows.filter(move |row| match row {
+            Ok(row) => seen.insert(row.clone()),
+            Err(_) => true,
+        });
+        Ok((columns, types, Box::new(rows)))
     }
 }
 
@@ -161,26 +328,164 @@ impl<T: Transaction> Filter<T> {
 
 impl<T: Transaction> Executor<T> for Filter<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
-        match self.source.execute(txn)? {
-            ResultSet::Scan { columns, rows } => {
-                let mut new_rows = Vec::new();
-                for row in rows {
-                    match evaluate_expr(&self.predicate, &columns, &row, &columns, &row)? {
-                        Value::Null => {}
-                        Value::Boolean(false) => {}
-                        Value::Boolean(true) => {
-                            new_rows.push(row);
-                        }
-                        _ => return Err(Error::Internal("Unexpected expression".into())),
-                    }
+        collect_rows(self.scan(txn)?)
+    }
+
+    fn scan(
+        self: Box<Self>,
+        txn: &mut T,
+    ) -> crate::error::Result<(Vec<String>, Vec<Option<DataType>>, Rows<'_>)> {
+        let (columns, types, rows) = self.source.scan(txn)?;
+        let predicate = self.predicate;
+        let cols = columns.clone();
+        let rows = rows.filter_map(move |row| {
+            let row = match row {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err)),
+            };
+            match evaluate_expr_single(&predicate, &cols, &row) {
+                Ok(Value::Null) | Ok(Value::Boolean(false)) => None,
+                Ok(Value::Boolean(true)) => Some(Ok(row)),
+                Ok(_) => Some(Err(Error::Internal("Unexpected expression".into()))),
+                Err(err) => Some(Err(err)),
+            }
+        });
+        Ok((columns, types, Box::new(rows)))
+    }
+}
+
+// expr [NOT] IN (子查询)，先执行子查询并把结果收集到哈希集合中，再逐行过滤 source 的输出
+pub struct InSubquery<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    expr: Expression,
+    subquery: Box<dyn Executor<T>>,
+    negated: bool,
+}
+
+impl<T: Transaction> InSubquery<T> {
+    pub fn new(
+        source: Box<dyn Executor<T>>,
+        expr: Expression,
+        subquery: Box<dyn Executor<T>>,
+        negated: bool,
+    ) -> Box<Self> {
+        Box::new(Self {
+            source,
+            expr,
+            subquery,
+            negated,
+        })
+    }
+}
+
+impl<T: Transaction> Executor<T> for InSubquery<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
+        collect_rows(self.scan(txn)?)
+    }
+
+    fn scan(
+        self: Box<Self>,
+        txn: &mut T,
+    ) -> crate::error::Result<(Vec<String>, Vec<Option<DataType>>, Rows<'_>)> {
+        let (columns, types, rows) = self.subquery.scan(txn)?;
+        if columns.len() != 1 {
+            return Err(Error::Plan(format!(
+                "subquery must return only one column, got {}",
+                columns.len()
+            )));
+        }
+        let mut set = ValueSet::new(types[0]);
+        for row in rows {
+            set.insert(row?.swap_remove(0));
+        }
+
+        let (columns, types, rows) = self.source.scan(txn)?;
+        let (expr, negated) = (self.expr, self.negated);
+        let cols = columns.clone();
+        let rows = rows.filter_map(move |row| {
+            let row = match row {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err)),
+            };
+            // NOT IN 对结果取反，结果为 NULL 时和 Filter 一样过滤掉
+            match evaluate_expr_single(&expr, &cols, &row).and_then(|v| set.lookup(v)) {
+                Ok(Value::Boolean(found)) if found != negated => Some(Ok(row)),
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            }
+        });
+        Ok((columns, types, Box::new(rows)))
+    }
+}
+
+// 子查询的结果，按照 IN 列表的比较规则查找：整数和浮点数按数值比较，
+// 时间戳和字符串比较时字符串按时间戳解析
+struct ValueSet {
+    datatype: Option<DataType>,
+    values: HashSet<Value>,
+    has_null: bool,
+    // 字符串按时间戳解析之后的集合，第一次和时间戳比较时才计算
+    timestamps: Option<HashSet<Value>>,
+}
+
+impl ValueSet {
+    fn new(datatype: Option<DataType>) -> Self {
+        Self {
+            datatype,
+            values: HashSet::new(),
+            has_null: false,
+            timestamps: None,
+        }
+    }
+
+    fn insert(&mut self, value: Value) {
+        match value {
+            Value::Null => self.has_null = true,
+            value => {
+                self.values.insert(normalize_number(value));
+            }
+        }
+    }
+
+    // 和 IN 列表的三值逻辑一致：有相等的值时为 true，否则结果中有 NULL 时为 NULL
+    // 子查询没有结果时为 false，否则 NULL IN (...) 为 NULL
+    fn lookup(&mut self, value: Value) -> crate::error::Result<Value> {
+        if self.values.is_empty() && !self.has_null {
+            return Ok(Value::Boolean(false));
+        }
+        let found = match value {
+            Value::Null => return Ok(Value::Null),
+            Value::Timestamp(_) if self.datatype == Some(DataType::String) => {
+                if self.timestamps.is_none() {
+                    let timestamps = self
+                        .values
+                        .iter()
+                        .map(|v| v.clone().coerce(&DataType::Timestamp))
+                        .collect::<crate::error::Result<_>>()?;
+                    self.timestamps = Some(timestamps);
                 }
-                Ok(ResultSet::Scan {
-                    columns,
-                    rows: new_rows,
-                })
+                self.timestamps.as_ref().is_some_and(|t| t.contains(&value))
             }
-            _ => return Err(Error::Internal(format!("Unexpected result set"))),
+            Value::String(_) if self.datatype == Some(DataType::Timestamp) => {
+                self.values.contains(&value.coerce(&DataType::Timestamp)?)
+            }
+            value => self.values.contains(&normalize_number(value)),
+        };
+        Ok(match found {
+            true => Value::Boolean(true),
+            false if self.has_null => Value::Null,
+            false => Value::Boolean(false),
+        })
+    }
+}
+
+// 没有小数部分的浮点数转换为整数，这样 1.0 和 1 在集合中是同一个值
+fn normalize_number(value: Value) -> Value {
+    match value {
+        Value::Float(f) if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 => {
+            Value::Integer(f as i64)
         }
+        value => value,
     }
 }
 
@@ -203,46 +508,92 @@ impl<T: Transaction> Projection<T> {
 
 impl<T: Transaction> Executor<T> for Projection<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
-        match self.source.execute(txn)? {
-            ResultSet::Scan { columns, rows } => {
-                // 找到需要输出哪些列
-                let mut selected = Vec::new();
-                let mut new_columns = Vec::new();
-                for (expr, alias) in self.exprs {
-                    if let Expression::Field(col_name) = expr {
-                        let pos = match columns.iter().position(|c| *c == col_name) {
-                            Some(pos) => pos,
-                            None => {
-                                return Err(Error::Internal(format!(
-                                    "projection column {} is not in table",
-                                    col_name
-                                )));
-                            }
-                        };
-                        selected.push(pos);
-                        new_columns.push(if alias.is_some() {
-                            alias.unwrap()
-                        } else {
-                            col_name
-                        });
-                    }
-                }
+        collect_rows(self.scan(txn)?)
+    }
 
-                let mut new_rows = Vec::new();
-                for row in rows.into_iter() {
-                    let mut new_row = Vec::new();
-                    for i in selected.iter() {
-                        new_row.push(row[*i].clone());
-                    }
-                    new_rows.push(new_row);
-                }
+    fn scan(
+        self: Box<Self>,
+        txn: &mut T,
+    ) -> crate::error::Result<(Vec<String>, Vec<Option<DataType>>, Rows<'_>)> {
+        let (columns, types, rows) = self.source.scan(txn)?;
 
-                Ok(ResultSet::Scan {
-                    columns: new_columns,
-                    rows: new_rows,
-                })
+        // 找到需要输出哪些列，常量等表达式直接计算，同时推导每一列的类型
+        // * 和 t.* 按输入中列的顺序展开为对应的列
+        let mut new_columns = Vec::new();
+        let mut new_types = Vec::new();
+        let mut exprs = Vec::with_capacity(self.exprs.len());
+        for (expr, alias) in self.exprs {
+            if let Expression::Wildcard(_) = expr {
+                for col_name in expr.expand_wildcard(&columns)? {
+                    let expr = Expression::Field(col_name.clone());
+                    new_types.push(expr.data_type(&columns, &types));
+                    new_columns.push(col_name);
+                    exprs.push(expr);
+                }
+                continue;
+            }
+            if let Expression::Field(col_name) = &expr
+                && find_column(&columns, col_name)?.is_none()
+            {
+                return Err(Error::ColumnNotFound {
+                    table: None,
+                    column: col_name.clone(),
+                    available: columns.clone(),
+                });
             }
-            _ => return Err(Error::Internal(format!("Unexpected result set"))),
+            new_columns.push(match alias {
+                Some(alias) => alias,
+                None => expr.to_string(),
+            });
+            new_types.push(expr.data_type(&columns, &types));
+            exprs.push(expr);
         }
+
+        let rows = rows.map(move |row| {
+            let row = row?;
+            exprs
+                .iter()
+                .map(|expr| evaluate_expr_single(expr, &columns, &row))
+                .collect()
+        });
+        Ok((new_columns, new_types, Box::new(rows)))
+    }
+}
+
+// 展示执行计划，不会执行其中的节点
+pub struct Explain {
+    source: Node,
+}
+
+impl Explain {
+    pub fn new(source: Node) -> Box<Self> {
+        Box::new(Self { source })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Explain {
+    fn execute(self: Box<Self>, _txn: &mut T) -> crate::error::Result<ResultSet> {
+        Ok(ResultSet::Explain {
+            plan: self.source.to_string(),
+        })
+    }
+}
+
+// 空数据源，没有 from 子句时输出一行没有列的数据，供上层的 Projection 计算常量表达式
+pub struct Nothing;
+
+impl Nothing {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+}
+
+impl<T: Transaction> Executor<T> for Nothing {
+    fn execute(self: Box<Self>, _txn: &mut T) -> crate::error::Result<ResultSet> {
+        Ok(ResultSet::Scan {
+            columns: Vec::new(),
+            types: Vec::new(),
+            rows: vec![Vec::new()],
+        })
     }
 }
@@ -1,6 +1,10 @@
 use crate::{
     error::Result,
-    sql::{engine::Transaction, executor::ResultSet, schema::Table},
+    sql::{
+        engine::Transaction,
+        executor::ResultSet,
+        schema::{Index, Table},
+    },
 };
 
 use super::Executor;
@@ -22,3 +26,51 @@ impl<T: Transaction> Executor<T> for CreateTable {
         Ok(ResultSet::CreateTable { table_name })
     }
 }
+
+pub struct CreateIndex {
+    table_name: String,
+    index: Index,
+}
+
+impl CreateIndex {
+    pub fn new(name: String, table_name: String, column: String) -> Box<Self> {
+        Box::new(Self {
+            table_name,
+            index: Index { name, column },
+        })
+    }
+}
+
+impl<T: Transaction> Executor<T> for CreateIndex {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<super::ResultSet> {
+        let index_name = self.index.name.clone();
+        txn.create_index(self.table_name, self.index)?;
+        Ok(ResultSet::CreateIndex { index_name })
+    }
+}
+
+pub struct DropTable {
+    name: String,
+    if_exists: bool,
+}
+
+impl DropTable {
+    pub fn new(name: String, if_exists: bool) -> Box<Self> {
+        Box::new(Self { name, if_exists })
+    }
+}
+
+impl<T: Transaction> Executor<T> for DropTable {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<super::ResultSet> {
+        // drop table if exists 时，表不存在直接返回
+        if self.if_exists && txn.get_table(self.name.clone())?.is_none() {
+            return Ok(ResultSet::DropTable {
+                table_name: self.name,
+            });
+        }
+        txn.drop_table(self.name.clone())?;
+        Ok(ResultSet::DropTable {
+            table_name: self.name,
+        })
+    }
+}
@@ -1,28 +1,39 @@
-use std::collections::BTreeMap;
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Display};
 
 use crate::{
     error::{Error, Result},
     sql::types::{DataType, Value},
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Statement {
     CreateTable {
         name: String,
         columns: Vec<Column>,
     },
+    DropTable {
+        name: String,
+        if_exists: bool,
+    },
+    CreateIndex {
+        name: String,
+        table_name: String,
+        column: String,
+    },
     Insert {
         table_name: String,
         columns: Option<Vec<String>>,
         values: Vec<Vec<Expression>>,
     },
     Select {
+        distinct: bool,                            // select distinct
         select: Vec<(Expression, Option<String>)>, // (表达式, 可选别名)
-        from: FromItem,
+        from: Option<FromItem>,                    // 没有 from 子句时为 None，例如 select 1;
         where_clause: Option<Expression>,
-        group_by: Option<Expression>,
+        group_by: Vec<Expression>, // 没有 group by 子句时为空
         having: Option<Expression>,
-        order_by: Vec<(String, OrderDirection)>,
+        // 排序表达式，单独的整数常量表示 SELECT 列表中的第几列（从 1 开始）
+        order_by: Vec<(Expression, OrderDirection)>,
         limit: Option<Expression>,
         offset: Option<Expression>,
     },
@@ -35,21 +46,145 @@ pub enum Statement {
         table_name: String,
         where_clause: Option<Expression>,
     },
-    Begin,
+    // BEGIN READ ONLY [AS OF version]
+    Begin {
+        read_only: bool,
+        as_of: Option<u64>,
+    },
     Commit,
     Rollback,
+    Vacuum,
+    Explain(Box<Statement>),
+    // COPY table FROM 'path' [WITH (HEADER true|false)]，从 CSV 文件导入数据
+    // header 为 true 时第一行是列名，按列名对应表中的列，否则按位置对应
+    CopyFrom {
+        table_name: String,
+        path: String,
+        header: bool,
+    },
+    // TRUNCATE [TABLE] table，删除表中所有的数据，保留表结构和索引
+    Truncate {
+        table_name: String,
+    },
+    // COPY table TO 'path'，把表中的数据导出为带列名的 CSV 文件
+    CopyTo {
+        table_name: String,
+        path: String,
+    },
 }
 
-#[derive(Debug, PartialEq)]
+impl Statement {
+    // 是否不会修改数据和表结构，只读会话中只能执行这些语句
+    // EXPLAIN 只生成执行计划，不会执行其中的语句；COPY TO 会在服务端创建文件，不是只读的
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Statement::Select { .. }
+                | Statement::Begin { .. }
+                | Statement::Commit
+                | Statement::Rollback
+                | Statement::Explain(_)
+        )
+    }
+
+    // 把语句中的 ? 占位符替换为参数的值，参数的数量需要和占位符的数量一致
+    pub fn bind_params(&mut self, params: &[Value]) -> Result<()> {
+        let mut count = 0;
+        let mut bind = |expr: Expression| match expr {
+            Expression::Parameter(i) => {
+                count = count.max(i + 1);
+                match params.get(i) {
+                    Some(value) => value.to_expression(),
+                    None => expr,
+                }
+            }
+            expr => expr,
+        };
+        self.transform_expressions(&mut bind);
+        if count != params.len() {
+            return Err(Error::Parse(format!(
+                "Expected {} parameters, but got {}",
+                count,
+                params.len()
+            )));
+        }
+        Ok(())
+    }
+
+    // 用 f 的返回值替换语句中的每一个表达式节点，包括子查询中的表达式
+    fn transform_expressions(&mut self, f: &mut impl FnMut(Expression) -> Expression) {
+        for expr in self.expressions_mut() {
+            let e = std::mem::replace(expr, Consts::Null.into());
+            *expr = e.transform(&mut |e| match e {
+                Expression::Subquery(mut stmt) => {
+                    stmt.transform_expressions(f);
+                    Expression::Subquery(stmt)
+                }
+                e => f(e),
+            });
+        }
+    }
+
+    // 语句中所有可能出现参数的表达式
+    fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Statement::CreateTable { columns, .. } => columns
+                .iter_mut()
+                .filter_map(|c| c.default.as_mut())
+                .collect(),
+            Statement::Insert { values, .. } => values.iter_mut().flatten().collect(),
+            Statement::Select {
+                select,
+                from,
+                where_clause,
+                group_by,
+                having,
+                order_by,
+                limit,
+                offset,
+                ..
+            } => {
+                let mut exprs = select.iter_mut().map(|(e, _)| e).collect::<Vec<_>>();
+                if let Some(from) = from {
+                    from.predicates_mut(&mut exprs);
+                }
+                exprs.extend(
+                    where_clause
+                        .iter_mut()
+                        .chain(group_by.iter_mut())
+                        .chain(having.iter_mut())
+                        .chain(order_by.iter_mut().map(|(e, _)| e))
+                        .chain(limit.iter_mut())
+                        .chain(offset.iter_mut()),
+                );
+                exprs
+            }
+            Statement::Update {
+                columns,
+                where_clause,
+                ..
+            } => columns
+                .values_mut()
+                .chain(where_clause.iter_mut())
+                .collect(),
+            Statement::Delete { where_clause, .. } => where_clause.iter_mut().collect(),
+            Statement::Explain(stmt) => stmt.expressions_mut(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum OrderDirection {
     Asc,
     Desc,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum FromItem {
     Table {
         name: String,
+        alias: Option<String>, // 表的别名，例如 from users u
     },
     Join {
         left: Box<FromItem>,
@@ -59,7 +194,24 @@ pub enum FromItem {
     },
 }
 
-#[derive(Debug, PartialEq)]
+impl FromItem {
+    // 收集 join 条件中的表达式
+    fn predicates_mut<'a>(&'a mut self, exprs: &mut Vec<&'a mut Expression>) {
+        if let FromItem::Join {
+            left,
+            right,
+            predicate,
+            ..
+        } = self
+        {
+            left.predicates_mut(exprs);
+            right.predicates_mut(exprs);
+            exprs.extend(predicate.iter_mut());
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum JoinType {
     Cross,
     Inner,
@@ -67,13 +219,15 @@ pub enum JoinType {
     Right,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Column {
     pub name: String,
     pub datatype: DataType,
     pub nullable: Option<bool>,
     pub default: Option<Expression>,
     pub primary_key: bool,
+    pub unique: bool,
+    pub auto_increment: bool,
 }
 
 // 表达式定义，目前只有常量和列名
@@ -83,6 +237,262 @@ pub enum Expression {
     Consts(Consts),
     Operation(Operation),     // 在 join 的情况下
     Function(String, String), // 在 agg 的情况下
+    Parameter(usize),         // 参数占位符 ?，按出现的顺序从 0 开始编号
+    Wildcard(Option<String>), // select 中的 * 或者 t.*，在投影时展开为输入中的列
+    Subquery(Box<Statement>), // a IN (SELECT ...) 中的子查询，只能返回一列
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Field(col_name) => write!(f, "{}", col_name),
+            Expression::Consts(Consts::String(s)) => write!(f, "'{}'", s.replace('\'', "''")),
+            Expression::Consts(c) => write!(f, "{}", Value::from_expression(c.clone().into())),
+            Expression::Operation(Operation::Equal(l, r)) => binary(f, l, "=", r),
+            Expression::Operation(Operation::NotEqual(l, r)) => binary(f, l, "!=", r),
+            Expression::Operation(Operation::GreaterThan(l, r)) => binary(f, l, ">", r),
+            Expression::Operation(Operation::GreaterThanOrEqual(l, r)) => binary(f, l, ">=", r),
+            Expression::Operation(Operation::LessThan(l, r)) => binary(f, l, "<", r),
+            Expression::Operation(Operation::LessThanOrEqual(l, r)) => binary(f, l, "<=", r),
+            Expression::Operation(Operation::And(l, r)) => write!(f, "({} AND {})", l, r),
+            Expression::Operation(Operation::Or(l, r)) => write!(f, "({} OR {})", l, r),
+            Expression::Operation(Operation::Not(e)) => write!(f, "NOT {}", e),
+            Expression::Operation(Operation::Add(l, r)) => binary(f, l, "+", r),
+            Expression::Operation(Operation::Subtract(l, r)) => binary(f, l, "-", r),
+            Expression::Operation(Operation::Multiply(l, r)) => binary(f, l, "*", r),
+            Expression::Operation(Operation::Divide(l, r)) => binary(f, l, "/", r),
+            Expression::Operation(Operation::Modulo(l, r)) => binary(f, l, "%", r),
+            Expression::Operation(Operation::Negate(e)) => {
+                write!(f, "-{}", Operand(e, e.precedence() < Precedence::Factor))
+            }
+            Expression::Operation(Operation::In(e, list)) => {
+                let list = list
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{} IN ({})", e, list)
+            }
+            Expression::Operation(Operation::Between(e, low, high)) => {
+                write!(f, "{} BETWEEN {} AND {}", e, low, high)
+            }
+            Expression::Operation(Operation::Like(l, r)) => write!(f, "{} LIKE {}", l, r),
+            Expression::Operation(Operation::IsNull(e, false)) => write!(f, "{} IS NULL", e),
+            Expression::Operation(Operation::IsNull(e, true)) => write!(f, "{} IS NOT NULL", e),
+            Expression::Function(func_name, col_name) => write!(f, "{}({})", func_name, col_name),
+            Expression::Parameter(_) => write!(f, "?"),
+            Expression::Wildcard(None) => write!(f, "*"),
+            Expression::Wildcard(Some(table)) => write!(f, "{}.*", table),
+            Expression::Subquery(_) => write!(f, "(SELECT ...)"),
+        }
+    }
+}
+
+// 二元运算符的优先级，和解析时相同，从低到高排列
+// AND、OR 展示时自带括号，和常量、列名一样不需要再加括号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Not,
+    Comparison,
+    Term,
+    Factor,
+    Atom,
+}
+
+impl Expression {
+    fn precedence(&self) -> Precedence {
+        match self {
+            Expression::Operation(op) => match op {
+                Operation::Not(_) => Precedence::Not,
+                Operation::Equal(_, _)
+                | Operation::NotEqual(_, _)
+                | Operation::GreaterThan(_, _)
+                | Operation::GreaterThanOrEqual(_, _)
+                | Operation::LessThan(_, _)
+                | Operation::LessThanOrEqual(_, _)
+                | Operation::In(_, _)
+                | Operation::Between(_, _, _)
+                | Operation::Like(_, _)
+                | Operation::IsNull(_, _) => Precedence::Comparison,
+                Operation::Add(_, _) | Operation::Subtract(_, _) => Precedence::Term,
+                Operation::Multiply(_, _) | Operation::Divide(_, _) | Operation::Modulo(_, _) => {
+                    Precedence::Factor
+                }
+                Operation::Negate(_) | Operation::And(_, _) | Operation::Or(_, _) => {
+                    Precedence::Atom
+                }
+            },
+            _ => Precedence::Atom,
+        }
+    }
+}
+
+// 展示二元运算，运算符都是左结合的：
+// 左操作数的优先级低于当前运算时加括号，右操作数的优先级低于或等于当前运算时加括号，
+// 例如 a - (b - c) 不能展示为 a - b - c，a * (b / c) 在整数除法时也不能去掉括号
+// 这样展示的结果重新解析之后得到相同的表达式树
+fn binary(
+    f: &mut std::fmt::Formatter<'_>,
+    l: &Expression,
+    op: &str,
+    r: &Expression,
+) -> std::fmt::Result {
+    let precedence = match op {
+        "+" | "-" => Precedence::Term,
+        "*" | "/" | "%" => Precedence::Factor,
+        _ => Precedence::Comparison,
+    };
+    let wrap_left = l.precedence() < precedence;
+    let wrap_right = r.precedence() <= precedence;
+    write!(
+        f,
+        "{} {} {}",
+        Operand(l, wrap_left),
+        op,
+        Operand(r, wrap_right)
+    )
+}
+
+// 第二个字段为 true 时加上括号
+struct Operand<'a>(&'a Expression, bool);
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.1 {
+            true => write!(f, "({})", self.0),
+            false => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl Expression {
+    // 自底向上遍历表达式树，用 f 的返回值替换每一个节点，子查询作为一个节点，不会进入其中的表达式
+    pub fn transform(self, f: &mut impl FnMut(Expression) -> Expression) -> Expression {
+        let mut t = |e: Box<Expression>| Box::new(e.transform(f));
+        let expr = match self {
+            Expression::Operation(op) => Expression::Operation(match op {
+                Operation::Equal(l, r) => Operation::Equal(t(l), t(r)),
+                Operation::NotEqual(l, r) => Operation::NotEqual(t(l), t(r)),
+                Operation::GreaterThan(l, r) => Operation::GreaterThan(t(l), t(r)),
+                Operation::GreaterThanOrEqual(l, r) => Operation::GreaterThanOrEqual(t(l), t(r)),
+                Operation::LessThan(l, r) => Operation::LessThan(t(l), t(r)),
+                Operation::LessThanOrEqual(l, r) => Operation::LessThanOrEqual(t(l), t(r)),
+                Operation::And(l, r) => Operation::And(t(l), t(r)),
+                Operation::Or(l, r) => Operation::Or(t(l), t(r)),
+                Operation::Not(e) => Operation::Not(t(e)),
+                Operation::Add(l, r) => Operation::Add(t(l), t(r)),
+                Operation::Subtract(l, r) => Operation::Subtract(t(l), t(r)),
+                Operation::Multiply(l, r) => Operation::Multiply(t(l), t(r)),
+                Operation::Divide(l, r) => Operation::Divide(t(l), t(r)),
+                Operation::Modulo(l, r) => Operation::Modulo(t(l), t(r)),
+                Operation::Negate(e) => Operation::Negate(t(e)),
+                Operation::In(e, list) => {
+                    Operation::In(t(e), list.into_iter().map(|e| e.transform(f)).collect())
+                }
+                Operation::Between(e, low, high) => Operation::Between(t(e), t(low), t(high)),
+                Operation::Like(l, r) => Operation::Like(t(l), t(r)),
+                Operation::IsNull(e, negated) => Operation::IsNull(t(e), negated),
+            }),
+            expr => expr,
+        };
+        f(expr)
+    }
+
+    // 表达式中是否有 * 或者 t.*
+    pub fn contains_wildcard(&self) -> bool {
+        let mut found = false;
+        self.clone().transform(&mut |expr| {
+            found |= matches!(expr, Expression::Wildcard(_));
+            expr
+        });
+        found
+    }
+
+    // 表达式中是否有子查询
+    pub fn contains_subquery(&self) -> bool {
+        let mut found = false;
+        self.clone().transform(&mut |expr| {
+            found |= matches!(expr, Expression::Subquery(_));
+            expr
+        });
+        found
+    }
+
+    // 表达式中是否有聚合函数
+    pub fn contains_aggregate(&self) -> bool {
+        let mut found = false;
+        self.clone().transform(&mut |expr| {
+            found |= matches!(expr, Expression::Function(_, _));
+            expr
+        });
+        found
+    }
+
+    // 把 * 或者 t.* 展开为输入中对应的列，t.* 只展开带有 t. 前缀的列
+    pub fn expand_wildcard(&self, columns: &[String]) -> Result<Vec<String>> {
+        let table = match self {
+            Expression::Wildcard(table) => table,
+            expr => return Err(Error::Internal(format!("{} is not a wildcard", expr))),
+        };
+        let expanded = columns
+            .iter()
+            .filter(|c| match table {
+                Some(table) => c
+                    .split_once('.')
+                    .is_some_and(|(qualifier, _)| qualifier == table),
+                None => true,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if let (Some(table), true) = (table, expanded.is_empty()) {
+            return Err(Error::TableNotFound(table.clone()));
+        }
+        Ok(expanded)
+    }
+
+    // 推导表达式结果的类型，columns 和 types 为输入中的列名和对应的类型
+    // 无法确定类型时返回 None，例如 NULL、参数占位符、不存在的列
+    pub fn data_type(&self, columns: &[String], types: &[Option<DataType>]) -> Option<DataType> {
+        match self {
+            Expression::Field(col) => match find_column(columns, col) {
+                Ok(Some(pos)) => types.get(pos).copied().flatten(),
+                _ => None,
+            },
+            Expression::Consts(c) => Value::from_expression(c.clone().into()).datatype(),
+            Expression::Parameter(_) | Expression::Wildcard(_) | Expression::Subquery(_) => None,
+            // 聚合函数：count 为整数，avg 为浮点数，整数列的 sum 为整数，其他列的 sum 为浮点数
+            // min 和 max 与输入的列相同
+            Expression::Function(func_name, col) => match func_name.to_lowercase().as_str() {
+                "count" => Some(DataType::Integer),
+                "sum" => match Expression::Field(col.clone()).data_type(columns, types) {
+                    Some(DataType::Integer) => Some(DataType::Integer),
+                    _ => Some(DataType::Float),
+                },
+                "avg" => Some(DataType::Float),
+                "min" | "max" => Expression::Field(col.clone()).data_type(columns, types),
+                _ => None,
+            },
+            Expression::Operation(op) => match op {
+                Operation::Add(l, r)
+                | Operation::Subtract(l, r)
+                | Operation::Multiply(l, r)
+                | Operation::Divide(l, r)
+                | Operation::Modulo(l, r) => {
+                    // 和 calc_values 一致：整数之间的运算结果为整数，有浮点数参与时为浮点数
+                    match (l.data_type(columns, types), r.data_type(columns, types)) {
+                        (Some(DataType::Float), _) | (_, Some(DataType::Float)) => {
+                            Some(DataType::Float)
+                        }
+                        (Some(DataType::Integer), Some(DataType::Integer) | None)
+                        | (None, Some(DataType::Integer)) => Some(DataType::Integer),
+                        _ => None,
+                    }
+                }
+                Operation::Negate(e) => e.data_type(columns, types),
+                _ => Some(DataType::Boolean),
+            },
+        }
+    }
 }
 
 impl From<Consts> for Expression {
@@ -103,29 +513,79 @@ pub enum Consts {
 #[derive(Debug, PartialEq, Clone)]
 pub enum Operation {
     Equal(Box<Expression>, Box<Expression>),
+    NotEqual(Box<Expression>, Box<Expression>),
     GreaterThan(Box<Expression>, Box<Expression>),
+    GreaterThanOrEqual(Box<Expression>, Box<Expression>),
     LessThan(Box<Expression>, Box<Expression>),
+    LessThanOrEqual(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Modulo(Box<Expression>, Box<Expression>),
+    Negate(Box<Expression>),
+    In(Box<Expression>, Vec<Expression>), // a IN (1, 2, 3)，NOT IN 表示为 Not(In)
+    Between(Box<Expression>, Box<Expression>, Box<Expression>), // a BETWEEN 1 AND 3
+    Like(Box<Expression>, Box<Expression>), // a LIKE 'x%'，NOT LIKE 表示为 Not(Like)
+    IsNull(Box<Expression>, bool),        // a IS NULL，a IS NOT NULL 时为 true
 }
 
+// 查找列在结果集中的位置，结果集中的列名带有表名前缀，例如 t.a
+// 带表名的列名需要完全匹配，不带表名的列名只有在唯一匹配时才能使用
+pub fn find_column(columns: &[String], name: &str) -> Result<Option<usize>> {
+    let mut matched = columns.iter().enumerate().filter(|(_, c)| {
+        *c == name
+            || (!name.contains('.') && c.rsplit_once('.').is_some_and(|(_, col)| col == name))
+    });
+    match (matched.next(), matched.next()) {
+        (None, _) => Ok(None),
+        (Some((pos, _)), None) => Ok(Some(pos)),
+        (Some(_), Some(_)) => Err(Error::AmbiguousColumn(name.to_string())),
+    }
+}
+
+// 在单个数据源的一行数据上计算表达式，例如 Filter、Projection 的输入
+// 列不存在时错误信息中带有可以使用的列
+pub fn evaluate_expr_single(expr: &Expression, cols: &[String], row: &[Value]) -> Result<Value> {
+    evaluate_expr(expr, cols, row, cols, row).map_err(|err| match err {
+        Error::ColumnNotFound {
+            table: None,
+            column,
+            ..
+        } => Error::ColumnNotFound {
+            table: None,
+            column,
+            available: cols.to_vec(),
+        },
+        err => err,
+    })
+}
+
+// 在 Join 的左右两行数据上计算表达式，列先在左边查找，再在右边查找
 pub fn evaluate_expr(
     expr: &Expression,
-    lcols: &Vec<String>,
-    lrow: &Vec<Value>,
-    rcols: &Vec<String>,
-    rrow: &Vec<Value>,
+    lcols: &[String],
+    lrow: &[Value],
+    rcols: &[String],
+    rrow: &[Value],
 ) -> Result<Value> {
     match expr {
         Expression::Field(col_name) => {
-            let lcol_pos = match lcols.iter().position(|c| *c == *col_name) {
-                Some(pos) => pos,
-                None => {
-                    return Err(Error::Internal(format!(
-                        "Column {} not found in table",
-                        col_name
-                    )));
-                }
-            };
-            Ok(lrow[lcol_pos].clone())
+            // 优先在左边查找，Join 条件中的列也可能来自右表，例如 on b.id = a.id
+            if let Some(pos) = find_column(lcols, col_name)? {
+                return Ok(lrow[pos].clone());
+            }
+            match find_column(rcols, col_name)? {
+                Some(pos) => Ok(rrow[pos].clone()),
+                None => Err(Error::ColumnNotFound {
+                    table: None,
+                    column: col_name.clone(),
+                    available: Vec::new(),
+                }),
+            }
         }
         Expression::Consts(consts) => Ok(match consts {
             Consts::Null => Value::Null,
@@ -138,69 +598,267 @@ pub fn evaluate_expr(
             Operation::Equal(lexpr, rexpr) => {
                 let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
                 let rv = evaluate_expr(rexpr, rcols, rrow, lcols, lrow)?;
-                Ok(match (lv, rv) {
-                    // (Value::Null, _) | (_, Value::Null) => Ok(Value::Bool(false)),
-                    (Value::Boolean(l), Value::Boolean(r)) => Value::Boolean(l == r),
-                    (Value::Integer(l), Value::Integer(r)) => Value::Boolean(l == r),
-                    (Value::Integer(l), Value::Float(r)) => Value::Boolean(l as f64 == r),
-                    (Value::Float(l), Value::Integer(r)) => Value::Boolean(l == r as f64),
-                    (Value::Float(l), Value::Float(r)) => Value::Boolean(l == r),
-                    (Value::String(l), Value::String(r)) => Value::Boolean(l == r),
-                    (_, Value::Null) => Value::Null,
-                    (Value::Null, _) => Value::Null,
-                    (l, r) => {
-                        return Err(Error::Internal(format!(
-                            "can not compare expression {} and {}",
-                            l, r
-                        )));
-                    }
-                })
+                compare_values(lv, rv, |o| o == Ordering::Equal)
+            }
+            Operation::NotEqual(lexpr, rexpr) => {
+                let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
+                let rv = evaluate_expr(rexpr, rcols, rrow, lcols, lrow)?;
+                compare_values(lv, rv, |o| o != Ordering::Equal)
             }
             Operation::GreaterThan(lexpr, rexpr) => {
                 let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
                 let rv = evaluate_expr(rexpr, rcols, rrow, lcols, lrow)?;
+                compare_values(lv, rv, |o| o == Ordering::Greater)
+            }
+            Operation::GreaterThanOrEqual(lexpr, rexpr) => {
+                let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
+                let rv = evaluate_expr(rexpr, rcols, rrow, lcols, lrow)?;
+                compare_values(lv, rv, |o| o != Ordering::Less)
+            }
+            Operation::LessThan(lexpr, rexpr) => {
+                let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
+                let rv = evaluate_expr(rexpr, rcols, rrow, lcols, lrow)?;
+                compare_values(lv, rv, |o| o == Ordering::Less)
+            }
+            Operation::LessThanOrEqual(lexpr, rexpr) => {
+                let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
+                let rv = evaluate_expr(rexpr, rcols, rrow, lcols, lrow)?;
+                compare_values(lv, rv, |o| o != Ordering::Greater)
+            }
+            // 三值逻辑：false AND NULL = false, true AND NULL = NULL
+            Operation::And(lexpr, rexpr) => {
+                let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
+                let rv = evaluate_expr(rexpr, lcols, lrow, rcols, rrow)?;
                 Ok(match (lv, rv) {
-                    // (Value::Null, _) | (_, Value::Null) => Ok(Value::Bool(false)),
-                    (Value::Boolean(l), Value::Boolean(r)) => Value::Boolean(l > r),
-                    (Value::Integer(l), Value::Integer(r)) => Value::Boolean(l > r),
-                    (Value::Integer(l), Value::Float(r)) => Value::Boolean(l as f64 > r),
-                    (Value::Float(l), Value::Integer(r)) => Value::Boolean(l > r as f64),
-                    (Value::Float(l), Value::Float(r)) => Value::Boolean(l > r),
-                    (Value::String(l), Value::String(r)) => Value::Boolean(l > r),
-                    (_, Value::Null) => Value::Null,
-                    (Value::Null, _) => Value::Null,
+                    (Value::Boolean(false), _) | (_, Value::Boolean(false)) => {
+                        Value::Boolean(false)
+                    }
+                    (Value::Boolean(true), Value::Boolean(true)) => Value::Boolean(true),
+                    (Value::Null | Value::Boolean(true), Value::Null | Value::Boolean(true)) => {
+                        Value::Null
+                    }
                     (l, r) => {
-                        return Err(Error::Internal(format!(
-                            "can not compare expression {} and {}",
+                        return Err(Error::OperatorMismatch(format!(
+                            "can not apply AND to {} and {}",
                             l, r
                         )));
                     }
                 })
             }
-            Operation::LessThan(lexpr, rexpr) => {
+            // 三值逻辑：true OR NULL = true, false OR NULL = NULL
+            Operation::Or(lexpr, rexpr) => {
                 let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
-                let rv = evaluate_expr(rexpr, rcols, rrow, lcols, lrow)?;
+                let rv = evaluate_expr(rexpr, lcols, lrow, rcols, rrow)?;
                 Ok(match (lv, rv) {
-                    // (Value::Null, _) | (_, Value::Null) => Ok(Value::Bool(false)),
-                    (Value::Boolean(l), Value::Boolean(r)) => Value::Boolean(l < r),
-                    (Value::Integer(l), Value::Integer(r)) => Value::Boolean(l < r),
-                    (Value::Integer(l), Value::Float(r)) => Value::Boolean((l as f64) < r),
-                    (Value::Float(l), Value::Integer(r)) => Value::Boolean(l < r as f64),
-                    (Value::Float(l), Value::Float(r)) => Value::Boolean(l < r),
-                    (Value::String(l), Value::String(r)) => Value::Boolean(l < r),
-                    (_, Value::Null) => Value::Null,
-                    (Value::Null, _) => Value::Null,
+                    (Value::Boolean(true), _) | (_, Value::Boolean(true)) => Value::Boolean(true),
+                    (Value::Boolean(false), Value::Boolean(false)) => Value::Boolean(false),
+                    (Value::Null | Value::Boolean(false), Value::Null | Value::Boolean(false)) => {
+                        Value::Null
+                    }
                     (l, r) => {
-                        return Err(Error::Internal(format!(
-                            "can not compare expression {} and {}",
+                        return Err(Error::OperatorMismatch(format!(
+                            "can not apply OR to {} and {}",
                             l, r
                         )));
                     }
                 })
             }
+            Operation::Not(expr) => Ok(match evaluate_expr(expr, lcols, lrow, rcols, rrow)? {
+                Value::Boolean(b) => Value::Boolean(!b),
+                Value::Null => Value::Null,
+                v => {
+                    return Err(Error::OperatorMismatch(format!(
+                        "can not apply NOT to {}",
+                        v
+                    )));
+                }
+            }),
+            Operation::Add(lexpr, rexpr)
+            | Operation::Subtract(lexpr, rexpr)
+            | Operation::Multiply(lexpr, rexpr)
+            | Operation::Divide(lexpr, rexpr)
+            | Operation::Modulo(lexpr, rexpr) => {
+                let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
+                let rv = evaluate_expr(rexpr, lcols, lrow, rcols, rrow)?;
+                calc_values(operation, lv, rv)
+            }
+            Operation::Negate(expr) => Ok(match evaluate_expr(expr, lcols, lrow, rcols, rrow)? {
+                Value::Integer(i) => match i.checked_neg() {
+                    Some(v) => Value::Integer(v),
+                    None => return Err(Error::NumericOverflow),
+                },
+                Value::Float(f) => Value::Float(-f),
+                Value::Null => Value::Null,
+                v => return Err(Error::OperatorMismatch(format!("can not negate {}", v))),
+            }),
+            // 三值逻辑：有相等的值时为 true，否则列表中有 NULL 时为 NULL，NULL IN (...) 为 NULL
+            Operation::In(expr, list) => {
+                let v = evaluate_expr(expr, lcols, lrow, rcols, rrow)?;
+                let mut res = Value::Boolean(false);
+                for item in list {
+                    let item = evaluate_expr(item, rcols, rrow, lcols, lrow)?;
+                    match compare_values(v.clone(), item, |o| o == Ordering::Equal)? {
+                        Value::Boolean(true) => return Ok(Value::Boolean(true)),
+                        Value::Null => res = Value::Null,
+                        _ => {}
+                    }
+                }
+                Ok(res)
+            }
+            // a BETWEEN low AND high 等价于 a >= low AND a <= high
+            Operation::Between(expr, low, high) => {
+                let v = evaluate_expr(expr, lcols, lrow, rcols, rrow)?;
+                let low = evaluate_expr(low, rcols, rrow, lcols, lrow)?;
+                let high = evaluate_expr(high, rcols, rrow, lcols, lrow)?;
+                let ge = compare_values(v.clone(), low, |o| o != Ordering::Less)?;
+                let le = compare_values(v, high, |o| o != Ordering::Greater)?;
+                Ok(match (ge, le) {
+                    (Value::Boolean(false), _) | (_, Value::Boolean(false)) => {
+                        Value::Boolean(false)
+                    }
+                    (Value::Boolean(true), Value::Boolean(true)) => Value::Boolean(true),
+                    _ => Value::Null,
+                })
+            }
+            Operation::Like(lexpr, rexpr) => {
+                let lv = evaluate_expr(lexpr, lcols, lrow, rcols, rrow)?;
+                let rv = evaluate_expr(rexpr, rcols, rrow, lcols, lrow)?;
+                match (lv, rv) {
+                    (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+                    (Value::String(s), Value::String(pattern)) => {
+                        Ok(Value::Boolean(like_match(&s, &pattern)))
+                    }
+                    (l, r) => Err(Error::OperatorMismatch(format!(
+                        "can not apply LIKE to {} and {}",
+                        l, r
+                    ))),
+                }
+            }
+            // 不论操作数是否为 NULL，结果都是 true 或者 false
+            Operation::IsNull(expr, negated) => {
+                let v = evaluate_expr(expr, lcols, lrow, rcols, rrow)?;
+                Ok(Value::Boolean((v == Value::Null) != *negated))
+            }
         },
         _ => Err(Error::Internal(
             "Unsupported expression in join predicate".into(),
         )),
     }
 }
+
+// LIKE 模式匹配，% 匹配任意个字符，_ 匹配单个字符，\% 和 \_ 匹配字符本身
+fn like_match(s: &str, pattern: &str) -> bool {
+    enum Pattern {
+        Any,
+        One,
+        Char(char),
+    }
+    let mut pat = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        pat.push(match c {
+            '%' => Pattern::Any,
+            '_' => Pattern::One,
+            '\\' => Pattern::Char(chars.next().unwrap_or('\\')),
+            c => Pattern::Char(c),
+        });
+    }
+
+    let s = s.chars().collect::<Vec<_>>();
+    let (mut i, mut p) = (0, 0);
+    // 最近一个 % 在模式中的位置，以及它已经匹配到的字符串位置，后面匹配失败时从这里回溯
+    let mut backtrack = None;
+    while i < s.len() {
+        match pat.get(p) {
+            Some(Pattern::Any) => {
+                backtrack = Some((p, i));
+                p += 1;
+            }
+            Some(Pattern::One) => {
+                i += 1;
+                p += 1;
+            }
+            Some(Pattern::Char(c)) if *c == s[i] => {
+                i += 1;
+                p += 1;
+            }
+            _ => match backtrack {
+                // 让 % 多匹配一个字符，然后重新匹配后面的模式
+                Some((bp, bi)) => {
+                    backtrack = Some((bp, bi + 1));
+                    p = bp + 1;
+                    i = bi + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pat[p..].iter().all(|c| matches!(c, Pattern::Any))
+}
+
+// 计算两个值的四则运算，任意一边为 NULL 时结果为 NULL，整数和浮点数运算时结果为浮点数
+fn calc_values(operation: &Operation, lv: Value, rv: Value) -> Result<Value> {
+    Ok(match (lv, rv) {
+        (Value::Null, _) | (_, Value::Null) => Value::Null,
+        (Value::Integer(l), Value::Integer(r)) => {
+            let res = match operation {
+                Operation::Add(_, _) => l.checked_add(r),
+                Operation::Subtract(_, _) => l.checked_sub(r),
+                Operation::Multiply(_, _) => l.checked_mul(r),
+                Operation::Divide(_, _) | Operation::Modulo(_, _) if r == 0 => {
+                    return Err(Error::DivisionByZero);
+                }
+                Operation::Divide(_, _) => l.checked_div(r),
+                Operation::Modulo(_, _) => l.checked_rem(r),
+                _ => unreachable!("not an arithmetic operation"),
+            };
+            match res {
+                Some(v) => Value::Integer(v),
+                None => return Err(Error::NumericOverflow),
+            }
+        }
+        (Value::Integer(l), Value::Float(r)) => calc_float(operation, l as f64, r)?,
+        (Value::Float(l), Value::Integer(r)) => calc_float(operation, l, r as f64)?,
+        (Value::Float(l), Value::Float(r)) => calc_float(operation, l, r)?,
+        (l, r) => {
+            return Err(Error::OperatorMismatch(format!(
+                "can not calculate expression {} and {}",
+                l, r
+            )));
+        }
+    })
+}
+
+fn calc_float(operation: &Operation, l: f64, r: f64) -> Result<Value> {
+    Ok(Value::Float(match operation {
+        Operation::Add(_, _) => l + r,
+        Operation::Subtract(_, _) => l - r,
+        Operation::Multiply(_, _) => l * r,
+        Operation::Divide(_, _) | Operation::Modulo(_, _) if r == 0.0 => {
+            return Err(Error::DivisionByZero);
+        }
+        Operation::Divide(_, _) => l / r,
+        Operation::Modulo(_, _) => l % r,
+        _ => unreachable!("not an arithmetic operation"),
+    }))
+}
+
+// 比较两个值，任意一边为 NULL 时结果为 NULL，整数和浮点数之间可以互相比较
+// 时间戳和字符串比较时，字符串按时间戳解析
+fn compare_values(lv: Value, rv: Value, pred: fn(Ordering) -> bool) -> Result<Value> {
+    let (lv, rv) = match (lv, rv) {
+        (lv @ Value::Timestamp(_), rv) => (lv, rv.coerce(&DataType::Timestamp)?),
+        (lv, rv @ Value::Timestamp(_)) => (lv.coerce(&DataType::Timestamp)?, rv),
+        (lv, rv) => (lv, rv),
+    };
+    match (&lv, &rv) {
+        (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+        _ => match lv.partial_cmp(&rv) {
+            Some(ordering) => Ok(Value::Boolean(pred(ordering))),
+            None => Err(Error::OperatorMismatch(format!(
+                "can not compare expression {} and {}",
+                lv, rv
+            ))),
+        },
+    }
+}
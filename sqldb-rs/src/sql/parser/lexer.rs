@@ -47,6 +47,31 @@ pub enum Keyword {
     Begin,
     Commit,
     Rollback,
+    Drop,
+    If,
+    Exists,
+    And,
+    Or,
+    Index,
+    Unique,
+    Timestamp,
+    Datetime,
+    Vacuum,
+    Explain,
+    In,
+    Between,
+    Like,
+    Is,
+    Read,
+    Only,
+    Of,
+    Autoincrement,
+    Distinct,
+    Copy,
+    To,
+    With,
+    Header,
+    Truncate,
 }
 
 impl Keyword {
@@ -96,6 +121,31 @@ impl Keyword {
             "BEGIN" => Self::Begin,
             "COMMIT" => Self::Commit,
             "ROLLBACK" => Self::Rollback,
+            "DROP" => Self::Drop,
+            "IF" => Self::If,
+            "EXISTS" => Self::Exists,
+            "AND" => Self::And,
+            "OR" => Self::Or,
+            "INDEX" => Self::Index,
+            "UNIQUE" => Self::Unique,
+            "TIMESTAMP" => Self::Timestamp,
+            "DATETIME" => Self::Datetime,
+            "VACUUM" => Self::Vacuum,
+            "EXPLAIN" => Self::Explain,
+            "IN" => Self::In,
+            "BETWEEN" => Self::Between,
+            "LIKE" => Self::Like,
+            "IS" => Self::Is,
+            "READ" => Self::Read,
+            "ONLY" => Self::Only,
+            "OF" => Self::Of,
+            "TRUNCATE" => Self::Truncate,
+            "AUTOINCREMENT" | "AUTO_INCREMENT" => Self::Autoincrement,
+            "DISTINCT" => Self::Distinct,
+            "COPY" => Self::Copy,
+            "TO" => Self::To,
+            "WITH" => Self::With,
+            "HEADER" => Self::Header,
             _ => return None,
         })
     }
@@ -146,6 +196,31 @@ impl Keyword {
             Self::Begin => "BEGIN",
             Self::Commit => "COMMIT",
             Self::Rollback => "ROLLBACK",
+            Self::Drop => "DROP",
+            Self::If => "IF",
+            Self::Exists => "EXISTS",
+            Self::And => "AND",
+            Self::Or => "OR",
+            Self::Index => "INDEX",
+            Self::Unique => "UNIQUE",
+            Self::Timestamp => "TIMESTAMP",
+            Self::Datetime => "DATETIME",
+            Self::Vacuum => "VACUUM",
+            Self::Explain => "EXPLAIN",
+            Self::In => "IN",
+            Self::Between => "BETWEEN",
+            Self::Like => "LIKE",
+            Self::Is => "IS",
+            Self::Read => "READ",
+            Self::Only => "ONLY",
+            Self::Of => "OF",
+            Self::Autoincrement => "AUTOINCREMENT",
+            Self::Distinct => "DISTINCT",
+            Self::Copy => "COPY",
+            Self::To => "TO",
+            Self::With => "WITH",
+            Self::Header => "HEADER",
+            Self::Truncate => "TRUNCATE",
         }
     }
 }
@@ -172,6 +247,8 @@ pub enum Token {
     CloseParen,
     // 逗号 ,
     Comma,
+    // 句点 .，用于 table.column
+    Period,
     // 分号 ;
     Semicolon,
     // 星号 *
@@ -182,12 +259,22 @@ pub enum Token {
     Minus,
     // 斜杠 /
     Slash,
+    // 百分号 %
+    Percent,
     // 等于 =
     Equal,
     // 大于
     GreaterThan,
     // 小于
     LessThan,
+    // 大于等于 >=
+    GreaterThanOrEqual,
+    // 小于等于 <=
+    LessThanOrEqual,
+    // 不等于 != 或 <>
+    NotEqual,
+    // 问号 ?，预编译语句中的参数占位符
+    Question,
 }
 
 impl Display for Token {
@@ -200,21 +287,63 @@ impl Display for Token {
             Token::OpenParen => "(",
             Token::CloseParen => ")",
             Token::Comma => ",",
+            Token::Period => ".",
             Token::Semicolon => ";",
             Token::Asterisk => "*",
             Token::Plus => "+",
             Token::Minus => "-",
             Token::Slash => "/",
+            Token::Percent => "%",
             Token::Equal => "=",
             Token::GreaterThan => ">",
             Token::LessThan => "<",
+            Token::GreaterThanOrEqual => ">=",
+            Token::LessThanOrEqual => "<=",
+            Token::NotEqual => "!=",
+            Token::Question => "?",
         })
     }
 }
 
+// 在需要时给标识符加上双引号，使它能被重新解析为同一个标识符
+// 关键字、包含大写字母（不加引号会被转为小写）或者特殊字符的标识符需要加引号，其中的双引号写成两个双引号
+pub fn quote_ident(name: &str) -> String {
+    let plain = name.starts_with(|c: char| c.is_alphabetic())
+        && name
+            .chars()
+            .all(|c| (c.is_alphanumeric() || c == '_') && !c.is_uppercase())
+        && Keyword::from_str(name).is_none();
+    match plain {
+        true => name.to_string(),
+        false => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+// Token 在 SQL 中的位置，offset 为字节偏移量，line 和 column 从 1 开始，column 按字符计数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Span {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
 // See README.md for lexer grammar
 pub struct Lexer<'a> {
     iter: Peekable<Chars<'a>>,
+    // 下一个字符的位置
+    pos: Span,
+    // 最近扫描的 token 的起始位置，扫描出错时为出错的 token 的起始位置
+    start: Span,
 }
 
 impl<'a> Iterator for Lexer<'a> {
@@ -236,18 +365,72 @@ impl<'a> Lexer<'a> {
     pub fn new(sql_text: &'a str) -> Self {
         Self {
             iter: sql_text.chars().peekable(),
+            pos: Span::default(),
+            start: Span::default(),
         }
     }
 
+    // 最近扫描的 token 的起始位置，扫描到输入末尾时为末尾的位置
+    pub fn span(&self) -> Span {
+        self.start
+    }
+
+    // 读取下一个字符，并更新位置
+    fn bump(&mut self) -> Option<char> {
+        let c = self.iter.next()?;
+        self.pos.offset += c.len_utf8();
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
+
     // 清楚空白字符
     fn erase_whitespace(&mut self) {
         self.next_while(|c| c.is_whitespace());
     }
 
+    // 清除空白和注释，注释有两种：-- 到行尾，/* 到 */（可以跨行）
+    fn erase_whitespace_and_comments(&mut self) -> Result<()> {
+        loop {
+            self.erase_whitespace();
+            if self.starts_with("--") {
+                self.next_while(|c| c != '\n');
+            } else if self.starts_with("/*") {
+                // 出错时指向注释的开始
+                self.start = self.pos;
+                self.bump();
+                self.bump();
+                loop {
+                    match self.bump() {
+                        Some('*') if self.next_if(|c| c == '/').is_some() => break,
+                        Some(_) => {}
+                        None => {
+                            return Err(Error::Parse("[Lexer] Unterminated block comment".into()));
+                        }
+                    }
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    // 剩余的输入是否以 prefix 开头，不消费字符
+    fn starts_with(&self, prefix: &str) -> bool {
+        self.iter
+            .clone()
+            .take(prefix.chars().count())
+            .eq(prefix.chars())
+    }
+
     fn next_if<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<char> {
         // 这里的 |&c| 是模式匹配：把 &&char 解引用一次变成 &char，命名为 c
         self.iter.peek().filter(|&c| predicate(*c))?;
-        self.iter.next()
+        self.bump()
     }
 
     fn next_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<String> {
@@ -261,18 +444,21 @@ impl<'a> Lexer<'a> {
     // 只有是 Token 类型，才能跳转下一个，并返回 Token
     fn next_if_token<F: Fn(char) -> Option<Token>>(&mut self, predicate: F) -> Option<Token> {
         let value = self.iter.peek().and_then(|c| predicate(*c))?;
-        self.iter.next();
+        self.bump();
         Some(value)
     }
 
     // 扫描得到下一个 Token
     fn scan(&mut self) -> Result<Option<Token>> {
-        // 清除字符串中空白的部分
-        self.erase_whitespace();
+        // 清除字符串中空白和注释的部分
+        self.erase_whitespace_and_comments()?;
+        self.start = self.pos;
 
         match self.iter.peek() {
             // 扫描字符串
             Some('\'') => self.scan_string(),
+            // 扫描带双引号的标识符
+            Some('"') => self.scan_quoted_ident(),
             // 扫描数字
             Some(c) if c.is_ascii_digit() => self.scan_number(), // 扫描数字
             Some(c) if c.is_alphabetic() => self.scan_ident_or_keyword(), // 扫描 Ident
@@ -283,19 +469,38 @@ impl<'a> Lexer<'a> {
 
     // 扫描符号
     fn scan_symbol(&mut self) -> Result<Option<Token>> {
-        Ok(self.next_if_token(|c| match c {
+        // 感叹号只能和等号组成 !=
+        if self.next_if(|c| c == '!').is_some() {
+            return match self.next_if(|c| c == '=') {
+                Some(_) => Ok(Some(Token::NotEqual)),
+                None => Err(Error::Parse("[Lexer] Expected = after !".into())),
+            };
+        }
+
+        let token = self.next_if_token(|c| match c {
             '*' => Some(Token::Asterisk),
             '(' => Some(Token::OpenParen),
             ')' => Some(Token::CloseParen),
             ',' => Some(Token::Comma),
+            '.' => Some(Token::Period),
             ';' => Some(Token::Semicolon),
             '+' => Some(Token::Plus),
             '-' => Some(Token::Minus),
             '/' => Some(Token::Slash),
+            '%' => Some(Token::Percent),
             '=' => Some(Token::Equal),
             '>' => Some(Token::GreaterThan),
             '<' => Some(Token::LessThan),
+            '?' => Some(Token::Question),
             _ => None,
+        });
+
+        // 两个字符组成的比较运算符：>=、<=、<>
+        Ok(token.map(|token| match token {
+            Token::GreaterThan if self.next_if(|c| c == '=').is_some() => Token::GreaterThanOrEqual,
+            Token::LessThan if self.next_if(|c| c == '=').is_some() => Token::LessThanOrEqual,
+            Token::LessThan if self.next_if(|c| c == '>').is_some() => Token::NotEqual,
+            token => token,
         }))
     }
 
@@ -353,7 +558,9 @@ impl<'a> Lexer<'a> {
 
         let mut val = String::new();
         loop {
-            match self.iter.next() {
+            match self.bump() {
+                // 字符串中连续的两个单引号表示一个单引号，例如 'it''s'
+                Some('\'') if self.next_if(|c| c == '\'').is_some() => val.push('\''),
                 Some('\'') => break,
                 Some(c) => val.push(c),
                 None => return Err(Error::Parse(format!("[Lexer] Unexpected end of string"))),
@@ -362,6 +569,39 @@ impl<'a> Lexer<'a> {
 
         Ok(Some(Token::String(val)))
     }
+
+    // 扫描带双引号的标识符，保留原始的大小写，可以是关键字或者包含空格等字符，例如 "order"、"First Name"
+    // 列名中的 . 用于分隔表名，所以标识符中不能包含 .
+    fn scan_quoted_ident(&mut self) -> Result<Option<Token>> {
+        if self.next_if(|c| c == '"').is_none() {
+            return Ok(None);
+        }
+
+        let mut val = String::new();
+        loop {
+            match self.bump() {
+                // 连续的两个双引号表示一个双引号
+                Some('"') if self.next_if(|c| c == '"').is_some() => val.push('"'),
+                Some('"') => break,
+                Some('.') => {
+                    return Err(Error::Parse(
+                        "[Lexer] Quoted identifier cannot contain '.'".into(),
+                    ));
+                }
+                Some(c) => val.push(c),
+                None => {
+                    return Err(Error::Parse(
+                        "[Lexer] Unexpected end of quoted identifier".into(),
+                    ));
+                }
+            }
+        }
+        if val.is_empty() {
+            return Err(Error::Parse("[Lexer] Empty quoted identifier".into()));
+        }
+
+        Ok(Some(Token::Ident(val)))
+    }
 }
 
 #[cfg(test)]
@@ -611,4 +851,226 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_lexer_compare_operators() -> Result<()> {
+        let tokens = Lexer::new("a>=1 and b <= 2 or c != 3 and d > 4 and e < 5")
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Ident("a".to_string()),
+                Token::GreaterThanOrEqual,
+                Token::Number("1".to_string()),
+                Token::Keyword(Keyword::And),
+                Token::Ident("b".to_string()),
+                Token::LessThanOrEqual,
+                Token::Number("2".to_string()),
+                Token::Keyword(Keyword::Or),
+                Token::Ident("c".to_string()),
+                Token::NotEqual,
+                Token::Number("3".to_string()),
+                Token::Keyword(Keyword::And),
+                Token::Ident("d".to_string()),
+                Token::GreaterThan,
+                Token::Number("4".to_string()),
+                Token::Keyword(Keyword::And),
+                Token::Ident("e".to_string()),
+                Token::LessThan,
+                Token::Number("5".to_string()),
+            ]
+        );
+
+        assert!(Lexer::new("a ! b").collect::<Result<Vec<_>>>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lexer_two_char_operators() -> Result<()> {
+        let tokens = Lexer::new("select * from t where a <> 1 and b>=2 and c<=3 and d!=4;")
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Keyword(Keyword::Select),
+                Token::Asterisk,
+                Token::Keyword(Keyword::From),
+                Token::Ident("t".to_string()),
+                Token::Keyword(Keyword::Where),
+                Token::Ident("a".to_string()),
+                Token::NotEqual,
+                Token::Number("1".to_string()),
+                Token::Keyword(Keyword::And),
+                Token::Ident("b".to_string()),
+                Token::GreaterThanOrEqual,
+                Token::Number("2".to_string()),
+                Token::Keyword(Keyword::And),
+                Token::Ident("c".to_string()),
+                Token::LessThanOrEqual,
+                Token::Number("3".to_string()),
+                Token::Keyword(Keyword::And),
+                Token::Ident("d".to_string()),
+                Token::NotEqual,
+                Token::Number("4".to_string()),
+                Token::Semicolon,
+            ]
+        );
+
+        // 中间有空格的时候是两个独立的 Token
+        let tokens = Lexer::new("> = < =").collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            tokens,
+            vec![
+                Token::GreaterThan,
+                Token::Equal,
+                Token::LessThan,
+                Token::Equal
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lexer_string_escape() -> Result<()> {
+        let tokens = Lexer::new("'it''s' '' '''' 'a;b' 'x'''").collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            tokens,
+            vec![
+                Token::String("it's".to_string()),
+                Token::String("".to_string()),
+                Token::String("'".to_string()),
+                Token::String("a;b".to_string()),
+                Token::String("x'".to_string()),
+            ]
+        );
+
+        // 转义之后缺少结束的单引号
+        assert!(Lexer::new("'it''s").collect::<Result<Vec<_>>>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lexer_span() -> Result<()> {
+        let mut lexer = Lexer::new("select a,\n  'é' >= 1");
+        let mut spans = Vec::new();
+        while let Some(token) = lexer.next() {
+            let span = lexer.span();
+            spans.push((token?, span.offset, span.line, span.column));
+        }
+        assert_eq!(
+            spans,
+            vec![
+                (Token::Keyword(Keyword::Select), 0, 1, 1),
+                (Token::Ident("a".to_string()), 7, 1, 8),
+                (Token::Comma, 8, 1, 9),
+                (Token::String("é".to_string()), 12, 2, 3),
+                (Token::GreaterThanOrEqual, 17, 2, 7),
+                (Token::Number("1".to_string()), 20, 2, 10),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lexer_comment() -> Result<()> {
+        let tokens = Lexer::new(
+            "-- 创建表
+            select a, -- 行尾的注释
+                /* 块注释 */ b /* 跨行的
+                * 注释 ** */ from t1;/**/
+            -- 最后一行没有换行",
+        )
+        .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Keyword(Keyword::Select),
+                Token::Ident("a".to_string()),
+                Token::Comma,
+                Token::Ident("b".to_string()),
+                Token::Keyword(Keyword::From),
+                Token::Ident("t1".to_string()),
+                Token::Semicolon,
+            ]
+        );
+
+        // 单独的 - 和 / 仍然是运算符，字符串中的注释符号不是注释
+        let tokens = Lexer::new("1 - 2 / 3 '--' '/*'").collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Number("1".to_string()),
+                Token::Minus,
+                Token::Number("2".to_string()),
+                Token::Slash,
+                Token::Number("3".to_string()),
+                Token::String("--".to_string()),
+                Token::String("/*".to_string()),
+            ]
+        );
+
+        // 没有结束的块注释
+        let mut lexer = Lexer::new("select a\n  /* 注释 * / from t1;");
+        assert_eq!(lexer.next(), Some(Ok(Token::Keyword(Keyword::Select))));
+        assert_eq!(lexer.next(), Some(Ok(Token::Ident("a".to_string()))));
+        assert_eq!(
+            lexer.next(),
+            Some(Err(Error::Parse(
+                "[Lexer] Unterminated block comment".into()
+            )))
+        );
+        assert_eq!((lexer.span().line, lexer.span().column), (2, 3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lexer_quoted_ident() -> Result<()> {
+        let tokens = Lexer::new(r#"select "order", "First Name", "a""b", Order from "Table";"#)
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Keyword(Keyword::Select),
+                Token::Ident("order".to_string()),
+                Token::Comma,
+                Token::Ident("First Name".to_string()),
+                Token::Comma,
+                Token::Ident("a\"b".to_string()),
+                Token::Comma,
+                Token::Keyword(Keyword::Order),
+                Token::Keyword(Keyword::From),
+                Token::Ident("Table".to_string()),
+                Token::Semicolon,
+            ]
+        );
+
+        assert!(Lexer::new(r#""order"#).collect::<Result<Vec<_>>>().is_err());
+        assert!(Lexer::new("\"\"").collect::<Result<Vec<_>>>().is_err());
+        assert!(Lexer::new(r#""t.a""#).collect::<Result<Vec<_>>>().is_err());
+
+        // 加上引号之后能被重新解析为同一个标识符
+        for (name, quoted) in [
+            ("name", "name"),
+            ("order", r#""order""#),
+            ("Name", r#""Name""#),
+            ("first name", r#""first name""#),
+            ("a\"b", r#""a""b""#),
+            ("1a", r#""1a""#),
+        ] {
+            assert_eq!(quote_ident(name), quoted);
+            assert_eq!(
+                Lexer::new(quoted).collect::<Result<Vec<_>>>()?,
+                vec![Token::Ident(name.to_string())]
+            );
+        }
+
+        Ok(())
+    }
 }
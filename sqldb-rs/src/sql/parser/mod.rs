@@ -1,27 +1,72 @@
 use crate::error::{Error, Result};
 use crate::sql::parser::ast::{Column, Expression, FromItem, JoinType, Operation, OrderDirection};
-use crate::sql::parser::lexer::{Keyword, Lexer, Token};
+use crate::sql::parser::lexer::{Keyword, Lexer, Span, Token};
 use crate::sql::types::DataType;
 use std::collections::BTreeMap;
-use std::iter::Peekable;
 
 pub mod ast;
 pub mod lexer;
 
 // 解析器定义
 pub struct Parser<'a> {
-    lexer: Peekable<Lexer<'a>>,
+    input: &'a str,
+    lexer: Lexer<'a>,
+    // 已经从 lexer 中读取，但还没有被消费的 token
+    peeked: Option<Option<Result<Token>>>,
+    // 已经解析的参数占位符数量
+    params: usize,
+    // 正在解析 select 中的表达式，只有这时允许 t.*
+    select_item: bool,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
         Parser {
-            lexer: Lexer::new(input).peekable(),
+            input,
+            lexer: Lexer::new(input),
+            peeked: None,
+            params: 0,
+            select_item: false,
         }
     }
 
     // 解析，获取抽象语法树
     pub fn parse(&mut self) -> Result<ast::Statement> {
+        self.parse_one().map_err(|e| self.locate(e))
+    }
+
+    // 解析多条以分号结尾的语句，直到输入结束
+    pub fn parse_all(&mut self) -> Result<Vec<ast::Statement>> {
+        self.parse_many().map_err(|e| self.locate(e))
+    }
+
+    // 在解析错误中加上出错的位置，以及标出出错位置的那一行 sql
+    // lexer 最多只会预读一个 token，所以它最近扫描的 token 就是出错时正在处理的 token
+    fn locate(&self, err: Error) -> Error {
+        let msg = match err {
+            Error::Parse(msg) => msg,
+            err => return err,
+        };
+        let Span { line, column, .. } = self.lexer.span();
+        let text = self
+            .input
+            .split('\n')
+            .nth(line - 1)
+            .unwrap_or_default()
+            .trim_end_matches('\r');
+        // 制表符原样保留，这样 ^ 才能和出错的字符对齐
+        let pad = text
+            .chars()
+            .take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        Error::Parse(format!(
+            "at line {}, column {}: {}\n  {}\n  {}^",
+            line, column, msg, text, pad
+        ))
+    }
+
+    fn parse_one(&mut self) -> Result<ast::Statement> {
         let stmt = self.parse_statement()?;
 
         // 期望 sql 语句的最后有一个分号
@@ -32,11 +77,23 @@ impl<'a> Parser<'a> {
         Ok(stmt)
     }
 
+    fn parse_many(&mut self) -> Result<Vec<ast::Statement>> {
+        let mut stmts = Vec::new();
+        while self.peek()?.is_some() {
+            // 每条语句的参数占位符都从 0 开始编号
+            self.params = 0;
+            stmts.push(self.parse_statement()?);
+            self.next_expect(Token::Semicolon)?;
+        }
+        Ok(stmts)
+    }
+
     // 核心方法，把sql转为stmt
     fn parse_statement(&mut self) -> Result<ast::Statement> {
         // 查看第一个 Token 类型
         match self.peek()? {
             Some(Token::Keyword(Keyword::Create)) => self.parse_ddl(),
+            Some(Token::Keyword(Keyword::Drop)) => self.parse_ddl(),
             Some(Token::Keyword(Keyword::Select)) => self.parse_select(),
             Some(Token::Keyword(Keyword::Insert)) => self.parse_insert(),
             Some(Token::Keyword(Keyword::Update)) => self.parse_update(),
@@ -44,6 +101,13 @@ impl<'a> Parser<'a> {
             Some(Token::Keyword(Keyword::Begin)) => self.parse_transaction(),
             Some(Token::Keyword(Keyword::Commit)) => self.parse_transaction(),
             Some(Token::Keyword(Keyword::Rollback)) => self.parse_transaction(),
+            Some(Token::Keyword(Keyword::Explain)) => self.parse_explain(),
+            Some(Token::Keyword(Keyword::Copy)) => self.parse_copy(),
+            Some(Token::Keyword(Keyword::Truncate)) => self.parse_truncate(),
+            Some(Token::Keyword(Keyword::Vacuum)) => {
+                self.next()?;
+                Ok(ast::Statement::Vacuum)
+            }
             Some(t) => Err(Error::Parse(format!("[Parser] Unexpected token: {:?}", t))),
             None => Err(Error::Parse(format!("[Parser] Unexpected end of input"))),
         }
@@ -63,10 +127,107 @@ impl<'a> Parser<'a> {
         })
     }
 
+    // 解析 truncate 类型，TABLE 关键字可以省略
+    fn parse_truncate(&mut self) -> Result<ast::Statement> {
+        self.next_expect(Token::Keyword(Keyword::Truncate))?;
+        self.next_if_token(Token::Keyword(Keyword::Table));
+        Ok(ast::Statement::Truncate {
+            table_name: self.next_indent()?,
+        })
+    }
+
+    // 解析 copy 类型
+    // COPY table FROM 'path' [WITH (HEADER true|false)]，没有 WITH 时默认第一行是列名
+    // COPY table TO 'path'
+    fn parse_copy(&mut self) -> Result<ast::Statement> {
+        self.next_expect(Token::Keyword(Keyword::Copy))?;
+        let table_name = self.next_indent()?;
+        let to = match self.next()? {
+            Token::Keyword(Keyword::From) => false,
+            Token::Keyword(Keyword::To) => true,
+            token => {
+                return Err(Error::Parse(format!(
+                    "[Parser] Expected FROM or TO, got {}",
+                    token
+                )));
+            }
+        };
+        let path = match self.next()? {
+            Token::String(path) => path,
+            token => {
+                return Err(Error::Parse(format!(
+                    "[Parser] Expected file path string, got {}",
+                    token
+                )));
+            }
+        };
+        if to {
+            return Ok(ast::Statement::CopyTo { table_name, path });
+        }
+
+        let mut header = true;
+        if self.next_if_token(Token::Keyword(Keyword::With)).is_some() {
+            self.next_expect(Token::OpenParen)?;
+            self.next_expect(Token::Keyword(Keyword::Header))?;
+            header = match self.next()? {
+                Token::Keyword(Keyword::True) => true,
+                Token::Keyword(Keyword::False) => false,
+                token => {
+                    return Err(Error::Parse(format!(
+                        "[Parser] Expected TRUE or FALSE for HEADER, got {}",
+                        token
+                    )));
+                }
+            };
+            self.next_expect(Token::CloseParen)?;
+        }
+        Ok(ast::Statement::CopyFrom {
+            table_name,
+            path,
+            header,
+        })
+    }
+
+    // 解析 explain 类型，只支持查询、更新和删除语句
+    fn parse_explain(&mut self) -> Result<ast::Statement> {
+        self.next_expect(Token::Keyword(Keyword::Explain))?;
+        match self.parse_statement()? {
+            stmt @ (ast::Statement::Select { .. }
+            | ast::Statement::Update { .. }
+            | ast::Statement::Delete { .. }) => Ok(ast::Statement::Explain(Box::new(stmt))),
+            _ => Err(Error::Parse(
+                "[Parser] EXPLAIN only supports SELECT, UPDATE and DELETE".into(),
+            )),
+        }
+    }
+
     // 解析 transaction 类型
     fn parse_transaction(&mut self) -> Result<ast::Statement> {
         Ok(match self.next()? {
-            Token::Keyword(Keyword::Begin) => ast::Statement::Begin,
+            Token::Keyword(Keyword::Begin) => {
+                // BEGIN READ ONLY [AS OF version]
+                let mut read_only = false;
+                let mut as_of = None;
+                if self.next_if_token(Token::Keyword(Keyword::Read)).is_some() {
+                    self.next_expect(Token::Keyword(Keyword::Only))?;
+                    read_only = true;
+                    if self.next_if_token(Token::Keyword(Keyword::As)).is_some() {
+                        self.next_expect(Token::Keyword(Keyword::Of))?;
+                        as_of = Some(match self.next()? {
+                            Token::Number(n) => n.parse::<u64>().map_err(|_| {
+                                Error::Parse(format!("[Parser] invalid version {}", n))
+                            })?,
+                            token => {
+                                return Err(Error::Parse(format!(
+                                    "[Parser] Expected version number, got {}",
+                                    token
+                                )));
+                            }
+                        });
+                    }
+                }
+                ast::Statement::Begin { read_only, as_of }
+            }
             Token::Keyword(Keyword::Commit) => ast::Statement::Commit,
             Token::Keyword(Keyword::Rollback) => ast::Statement::Rollback,
             _ => return Err(Error::Internal("unknown transaction command".into())),
@@ -88,7 +249,7 @@ impl<'a> Parser<'a> {
             self.next_expect(Token::Equal)?;
             let value = self.parse_expression()?;
             if columns.contains_key(&col) {
-                return Err(Error::Internal(format!(
+                return Err(Error::Parse(format!(
                     "[Parser] Duplicate column: {} for update",
                     col
                 )));
@@ -120,7 +281,8 @@ impl<'a> Parser<'a> {
     }
 
     // 解析 order by 子句
-    fn parse_order_by_clause(&mut self) -> Result<Vec<(String, OrderDirection)>> {
+    // 排序的可以是任意表达式，例如 order by a + b；也可以是 SELECT 列表中的列序号，例如 order by 2 desc
+    fn parse_order_by_clause(&mut self) -> Result<Vec<(Expression, OrderDirection)>> {
         let mut orders = Vec::new();
         if self.next_if_token(Token::Keyword(Keyword::Order)).is_none() {
             return Ok(orders);
@@ -128,7 +290,7 @@ impl<'a> Parser<'a> {
 
         self.next_expect(Token::Keyword(Keyword::By))?;
         loop {
-            let col = self.next_indent()?;
+            let expr = self.parse_expression()?;
             let ord = match self.next_if(|it| {
                 matches!(
                     it,
@@ -139,7 +301,7 @@ impl<'a> Parser<'a> {
                 Some(Token::Keyword(Keyword::Desc)) => OrderDirection::Desc,
                 _ => OrderDirection::Asc,
             };
-            orders.push((col, ord));
+            orders.push((expr, ord));
 
             if self.next_if_token(Token::Comma).is_none() {
                 break;
@@ -213,37 +375,67 @@ impl<'a> Parser<'a> {
 
     // 解析 select 类型
     fn parse_select(&mut self) -> Result<ast::Statement> {
+        self.next_expect(Token::Keyword(Keyword::Select))?;
+        // select distinct 去掉结果中重复的行
+        let distinct = self
+            .next_if_token(Token::Keyword(Keyword::Distinct))
+            .is_some();
         // 解析 select 的列信息
         let select = self.parse_select_clause()?;
 
-        // self.next_expect(Token::Keyword(Keyword::From))?;
+        // from 子句是可选的，例如 select 1;
         let from = self.parse_from_clause()?;
 
+        let where_clause = self.parse_where_clause()?;
+        let group_by = self.parse_group_clause()?;
+        let having = self.parse_having_clause()?;
+        let order_by = self.parse_order_by_clause()?;
+        let (limit, offset) = self.parse_limit_clause()?;
+
         Ok(ast::Statement::Select {
+            distinct,
             select,
             from,
-            where_clause: self.parse_where_clause()?,
-            group_by: self.parse_group_clause()?,
-            having: self.parse_having_clause()?,
-            order_by: self.parse_order_by_clause()?,
-            limit: {
-                if self.next_if_token(Token::Keyword(Keyword::Limit)).is_some() {
-                    Some(self.parse_expression()?)
+            where_clause,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    // 解析 limit 和 offset 子句，返回 (limit, offset)
+    // 支持 LIMIT x OFFSET y、OFFSET y LIMIT x，以及 MySQL 风格的 LIMIT y, x
+    fn parse_limit_clause(&mut self) -> Result<(Option<Expression>, Option<Expression>)> {
+        let mut limit = None;
+        let mut offset = None;
+        loop {
+            if limit.is_none() && self.next_if_token(Token::Keyword(Keyword::Limit)).is_some() {
+                let expr = self.parse_expression()?;
+                if self.next_if_token(Token::Comma).is_some() {
+                    // LIMIT y, x 中第一个表达式是 offset
+                    if offset.is_some() {
+                        return Err(Error::Parse(
+                            "[Parser] LIMIT y, x can not be used together with OFFSET".to_string(),
+                        ));
+                    }
+                    offset = Some(expr);
+                    limit = Some(self.parse_expression()?);
                 } else {
-                    None
+                    limit = Some(expr);
                 }
-            },
-            offset: {
-                if self
+            } else if offset.is_none()
+                && self
                     .next_if_token(Token::Keyword(Keyword::Offset))
                     .is_some()
-                {
-                    Some(self.parse_expression()?)
-                } else {
-                    None
-                }
-            },
-        })
+            {
+                offset = Some(self.parse_expression()?);
+            } else {
+                break;
+            }
+        }
+        Ok((limit, offset))
     }
 
     // 解析 DDL 类型
@@ -252,6 +444,14 @@ impl<'a> Parser<'a> {
             //  再读入一个 token
             Token::Keyword(Keyword::Create) => match self.next()? {
                 Token::Keyword(Keyword::Table) => self.parse_ddl_create_table(),
+                Token::Keyword(Keyword::Index) => self.parse_ddl_create_index(),
+                token => Err(Error::Parse(format!(
+                    "[Parser] Unexpected token: {}",
+                    token
+                ))),
+            },
+            Token::Keyword(Keyword::Drop) => match self.next()? {
+                Token::Keyword(Keyword::Table) => self.parse_ddl_drop_table(),
                 token => Err(Error::Parse(format!(
                     "[Parser] Unexpected token: {}",
                     token
@@ -288,6 +488,38 @@ impl<'a> Parser<'a> {
         })
     }
 
+    // 解析 create index 语句：create index idx_name on table_name (column_name)
+    fn parse_ddl_create_index(&mut self) -> Result<ast::Statement> {
+        let name = self.next_indent()?;
+        self.next_expect(Token::Keyword(Keyword::On))?;
+        let table_name = self.next_indent()?;
+        self.next_expect(Token::OpenParen)?;
+        let column = self.next_indent()?;
+        self.next_expect(Token::CloseParen)?;
+
+        Ok(ast::Statement::CreateIndex {
+            name,
+            table_name,
+            column,
+        })
+    }
+
+    // 解析 drop table 语句
+    fn parse_ddl_drop_table(&mut self) -> Result<ast::Statement> {
+        // 是否有 if exists
+        let if_exists = if self.next_if_token(Token::Keyword(Keyword::If)).is_some() {
+            self.next_expect(Token::Keyword(Keyword::Exists))?;
+            true
+        } else {
+            false
+        };
+
+        Ok(ast::Statement::DropTable {
+            name: self.next_indent()?,
+            if_exists,
+        })
+    }
+
     fn parse_ddl_column(&mut self) -> Result<ast::Column> {
         let mut column = Column {
             name: self.next_indent()?,
@@ -302,6 +534,9 @@ impl<'a> Parser<'a> {
                 Token::Keyword(Keyword::String)
                 | Token::Keyword(Keyword::Text)
                 | Token::Keyword(Keyword::Varchar) => DataType::String,
+                Token::Keyword(Keyword::Timestamp) | Token::Keyword(Keyword::Datetime) => {
+                    DataType::Timestamp
+                }
                 token => {
                     return Err(Error::Parse(format!(
                         "[Parser] Unexpected token: {}",
@@ -312,6 +547,8 @@ impl<'a> Parser<'a> {
             nullable: None,
             default: None,
             primary_key: false,
+            unique: false,
+            auto_increment: false,
         };
 
         // 解析列的默认值和是否可以为空
@@ -328,6 +565,8 @@ impl<'a> Parser<'a> {
                     self.next_expect(Token::Keyword(Keyword::Key))?;
                     column.primary_key = true;
                 }
+                Keyword::Unique => column.unique = true,
+                Keyword::Autoincrement => column.auto_increment = true,
                 k => return Err(Error::Parse(format!("[Parser] Unexpected keyword: {}", k))),
             };
         }
@@ -336,9 +575,11 @@ impl<'a> Parser<'a> {
     }
 
     // pattern: from table1 cross join table2 on field1 = field2
-    fn parse_from_clause(&mut self) -> Result<ast::FromItem> {
-        // from 关键字
-        self.next_expect(Token::Keyword(Keyword::From))?;
+    fn parse_from_clause(&mut self) -> Result<Option<ast::FromItem>> {
+        // from 关键字，没有则说明不需要从表中读取数据
+        if self.next_if_token(Token::Keyword(Keyword::From)).is_none() {
+            return Ok(None);
+        }
 
         // 第一个表名
         let mut item = self.parse_from_table_clause()?;
@@ -369,16 +610,24 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(item)
+        Ok(Some(item))
     }
 
-    fn parse_group_clause(&mut self) -> Result<Option<Expression>> {
+    // pattern: group by expr [, expr ...]
+    fn parse_group_clause(&mut self) -> Result<Vec<Expression>> {
+        let mut group_by = Vec::new();
         if self.next_if_token(Token::Keyword(Keyword::Group)).is_none() {
-            return Ok(None);
+            return Ok(group_by);
         }
 
         self.next_expect(Token::Keyword(Keyword::By))?;
-        Ok(Some(self.parse_expression()?))
+        loop {
+            group_by.push(self.parse_expression()?);
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        Ok(group_by)
     }
 
     fn parse_having_clause(&mut self) -> Result<Option<Expression>> {
@@ -391,10 +640,17 @@ impl<'a> Parser<'a> {
         Ok(Some(self.parse_operation_expr()?))
     }
 
+    // pattern: table_name [[as] alias]
     fn parse_from_table_clause(&mut self) -> Result<FromItem> {
-        Ok(ast::FromItem::Table {
-            name: self.next_indent()?,
-        })
+        let name = self.next_indent()?;
+        let alias = match self.next_if_token(Token::Keyword(Keyword::As)) {
+            Some(_) => Some(self.next_indent()?),
+            None => match self.next_if(|t| matches!(t, Token::Ident(_))) {
+                Some(Token::Ident(alias)) => Some(alias),
+                _ => None,
+            },
+        };
+        Ok(ast::FromItem::Table { name, alias })
     }
 
     fn parse_from_clause_join(&mut self) -> Result<Option<JoinType>> {
@@ -421,19 +677,34 @@ impl<'a> Parser<'a> {
 
     // 解析 select 子句
     fn parse_select_clause(&mut self) -> Result<Vec<(Expression, Option<String>)>> {
-        self.next_expect(Token::Keyword(Keyword::Select))?;
-
         let mut select = Vec::new();
 
-        // select *
-        if self.next_if_token(Token::Asterisk).is_some() {
-            return Ok(select);
-        }
-
         loop {
-            let expr = self.parse_expression()?;
-            // 查看是否有别名
+            // * 和 t.* 可以和其他表达式一起出现，例如 select *, a 或者 select t.*, u.b
+            let expr = match self.next_if_token(Token::Asterisk) {
+                Some(_) => Expression::Wildcard(None),
+                None => {
+                    self.select_item = true;
+                    let expr = self.parse_expression();
+                    self.select_item = false;
+                    expr?
+                }
+            };
+            let is_wildcard = matches!(expr, Expression::Wildcard(_));
+            if !is_wildcard && expr.contains_wildcard() {
+                return Err(Error::Parse(format!(
+                    "[Parser] Wildcard can only be used alone in select list: {}",
+                    expr
+                )));
+            }
+            // 查看是否有别名，* 和 t.* 不能有别名
             let alias = match self.next_if_token(Token::Keyword(Keyword::As)) {
+                Some(_) if is_wildcard => {
+                    return Err(Error::Parse(format!(
+                        "[Parser] Wildcard {} cannot have an alias",
+                        expr
+                    )));
+                }
                 Some(_) => Some(self.next_indent()?),
                 None => None,
             };
@@ -444,57 +715,240 @@ impl<'a> Parser<'a> {
             }
         }
 
+        // 只有一个 * 时和之前一样用空的列表表示，输出 from 中的所有列
+        if let [(Expression::Wildcard(None), None)] = select.as_slice() {
+            select.clear();
+        }
         Ok(select)
     }
 
+    // 解析条件表达式，优先级从低到高依次为：OR、AND、NOT、比较运算
     fn parse_operation_expr(&mut self) -> Result<Expression> {
+        self.parse_or_expr()
+    }
+
+    // a OR b OR c
+    fn parse_or_expr(&mut self) -> Result<Expression> {
+        let mut left = self.parse_and_expr()?;
+        while self.next_if_token(Token::Keyword(Keyword::Or)).is_some() {
+            let right = self.parse_and_expr()?;
+            left = Expression::Operation(Operation::Or(Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    // a AND b AND c
+    fn parse_and_expr(&mut self) -> Result<Expression> {
+        let mut left = self.parse_not_expr()?;
+        while self.next_if_token(Token::Keyword(Keyword::And)).is_some() {
+            let right = self.parse_not_expr()?;
+            left = Expression::Operation(Operation::And(Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    // NOT a
+    fn parse_not_expr(&mut self) -> Result<Expression> {
+        if self.next_if_token(Token::Keyword(Keyword::Not)).is_some() {
+            return Ok(Expression::Operation(Operation::Not(Box::new(
+                self.parse_not_expr()?,
+            ))));
+        }
+        self.parse_compare_expr()
+    }
+
+    // a = b、a > b、a >= b、a < b、a <= b、a != b，或者单独的表达式（例如布尔类型的列）
+    // 以及 a [NOT] IN (1, 2)、a [NOT] BETWEEN 1 AND 2、a [NOT] LIKE 'x%'、a IS [NOT] NULL
+    fn parse_compare_expr(&mut self) -> Result<Expression> {
         let left = self.parse_expression()?;
 
-        Ok(match self.next()? {
-            Token::Equal => Expression::Operation(Operation::Equal(
-                Box::new(left),
-                Box::new(self.parse_expression()?),
-            )),
-            Token::GreaterThan => Expression::Operation(Operation::GreaterThan(
-                Box::new(left),
-                Box::new(self.parse_expression()?),
-            )),
-            Token::LessThan => Expression::Operation(Operation::LessThan(
+        if self.next_if_token(Token::Keyword(Keyword::Is)).is_some() {
+            let negated = self.next_if_token(Token::Keyword(Keyword::Not)).is_some();
+            self.next_expect(Token::Keyword(Keyword::Null))?;
+            return Ok(Expression::Operation(Operation::IsNull(
                 Box::new(left),
-                Box::new(self.parse_expression()?),
-            )),
-            _ => return Err(Error::Internal("Unexpected token".into())),
+                negated,
+            )));
+        }
+
+        // NOT IN、NOT BETWEEN、NOT LIKE 表示为 Not(In)、Not(Between)、Not(Like)
+        let negated = self.next_if_token(Token::Keyword(Keyword::Not)).is_some();
+        let op = match self.next_if(|t| {
+            matches!(
+                t,
+                Token::Keyword(Keyword::In)
+                    | Token::Keyword(Keyword::Between)
+                    | Token::Keyword(Keyword::Like)
+            )
+        }) {
+            Some(Token::Keyword(Keyword::In)) => {
+                self.next_expect(Token::OpenParen)?;
+                let mut list = Vec::new();
+                if let Some(Token::Keyword(Keyword::Select)) = self.peek()? {
+                    // IN (SELECT ...)，子查询只能单独出现在括号中
+                    list.push(Expression::Subquery(Box::new(self.parse_select()?)));
+                } else {
+                    loop {
+                        list.push(self.parse_expression()?);
+                        if self.next_if_token(Token::Comma).is_none() {
+                            break;
+                        }
+                    }
+                }
+                self.next_expect(Token::CloseParen)?;
+                Operation::In(Box::new(left), list)
+            }
+            Some(Token::Keyword(Keyword::Like)) => {
+                Operation::Like(Box::new(left), Box::new(self.parse_expression()?))
+            }
+            Some(_) => {
+                let low = self.parse_expression()?;
+                self.next_expect(Token::Keyword(Keyword::And))?;
+                let high = self.parse_expression()?;
+                Operation::Between(Box::new(left), Box::new(low), Box::new(high))
+            }
+            None if negated => {
+                return Err(Error::Parse(
+                    "[Parser] Expected IN, BETWEEN or LIKE after NOT".into(),
+                ));
+            }
+            None => return self.parse_comparison(left),
+        };
+        let expr = Expression::Operation(op);
+        Ok(match negated {
+            true => Expression::Operation(Operation::Not(Box::new(expr))),
+            false => expr,
         })
     }
 
-    // 解析表达式
+    // a = b、a > b 等比较运算，left 为已经解析的左边的表达式
+    fn parse_comparison(&mut self, left: Expression) -> Result<Expression> {
+        let build: fn(Box<Expression>, Box<Expression>) -> Operation = match self.peek()? {
+            Some(Token::Equal) => Operation::Equal,
+            Some(Token::NotEqual) => Operation::NotEqual,
+            Some(Token::GreaterThan) => Operation::GreaterThan,
+            Some(Token::GreaterThanOrEqual) => Operation::GreaterThanOrEqual,
+            Some(Token::LessThan) => Operation::LessThan,
+            Some(Token::LessThanOrEqual) => Operation::LessThanOrEqual,
+            _ => return Ok(left),
+        };
+        self.next()?;
+        let right = self.parse_expression()?;
+        Ok(Expression::Operation(build(
+            Box::new(left),
+            Box::new(right),
+        )))
+    }
+
+    // 解析表达式，支持四则运算，优先级从低到高依次为：+ -、* / %
     fn parse_expression(&mut self) -> Result<ast::Expression> {
+        let mut left = self.parse_term_expr()?;
+        loop {
+            let build: fn(Box<Expression>, Box<Expression>) -> Operation = match self.peek()? {
+                Some(Token::Plus) => Operation::Add,
+                Some(Token::Minus) => Operation::Subtract,
+                _ => return Ok(left),
+            };
+            self.next()?;
+            let right = self.parse_term_expr()?;
+            left = Expression::Operation(build(Box::new(left), Box::new(right)));
+        }
+    }
+
+    // a * b、a / b、a % b
+    fn parse_term_expr(&mut self) -> Result<ast::Expression> {
+        let mut left = self.parse_atom_expr()?;
+        loop {
+            let build: fn(Box<Expression>, Box<Expression>) -> Operation = match self.peek()? {
+                Some(Token::Asterisk) => Operation::Multiply,
+                Some(Token::Slash) => Operation::Divide,
+                Some(Token::Percent) => Operation::Modulo,
+                _ => return Ok(left),
+            };
+            self.next()?;
+            let right = self.parse_atom_expr()?;
+            left = Expression::Operation(build(Box::new(left), Box::new(right)));
+        }
+    }
+
+    // 解析单个的表达式：列名、常量、函数，或者括号内的表达式
+    fn parse_atom_expr(&mut self) -> Result<ast::Expression> {
         Ok(match self.next()? {
+            // 括号内是一个完整的表达式，也可以是条件表达式
+            Token::OpenParen => {
+                let expr = self.parse_or_expr()?;
+                self.next_expect(Token::CloseParen)?;
+                expr
+            }
             Token::Ident(ident) => {
                 // 函数的情况
-                // count(col_name)
+                // count(col_name)、count(*)
                 if self.next_if_token(Token::OpenParen).is_some() {
-                    let col_name = self.next_indent()?;
+                    let col_name = match self.next_if_token(Token::Asterisk) {
+                        Some(_) => "*".to_string(),
+                        None => self.parse_column_name()?,
+                    };
                     self.next_expect(Token::CloseParen)?;
                     ast::Expression::Function(ident, col_name)
+                } else if self.next_if_token(Token::Period).is_some() {
+                    // select 中的 t.*，表示表 t 的所有列
+                    if self.select_item && self.next_if_token(Token::Asterisk).is_some() {
+                        return Ok(ast::Expression::Wildcard(Some(ident)));
+                    }
+                    // 带表名的列名，例如 t.a
+                    ast::Expression::Field(format!("{}.{}", ident, self.next_indent()?))
                 } else {
                     // 列名
                     ast::Expression::Field(ident)
                 }
             }
-            Token::Number(n) => {
-                if n.chars().all(|c| c.is_ascii_digit()) {
-                    // 整数
-                    ast::Consts::Integer(n.parse()?).into()
-                } else {
-                    // 浮点数
-                    ast::Consts::Float(n.parse()?).into()
+            Token::Number(n) => Self::parse_number(&n)?,
+            // 负号后面直接是数字时作为负数常量解析，这样可以表示 i64 的最小值
+            Token::Minus => match self.peek()? {
+                Some(Token::Number(n)) => {
+                    self.next()?;
+                    Self::parse_number(&format!("-{}", n))?
                 }
-            }
+                _ => match self.parse_atom_expr()? {
+                    ast::Expression::Consts(ast::Consts::Integer(i)) => match i.checked_neg() {
+                        Some(i) => ast::Consts::Integer(i).into(),
+                        None => {
+                            return Err(Error::Parse(format!(
+                                "[Parser] Integer overflow when negating {}",
+                                i
+                            )));
+                        }
+                    },
+                    ast::Expression::Consts(ast::Consts::Float(f)) => ast::Consts::Float(-f).into(),
+                    ast::Expression::Consts(ast::Consts::Null) => ast::Consts::Null.into(),
+                    ast::Expression::Consts(c) => {
+                        return Err(Error::Parse(format!(
+                            "[Parser] Unexpected operand for unary minus: {}",
+                            ast::Expression::Consts(c)
+                        )));
+                    }
+                    expr => ast::Expression::Operation(Operation::Negate(Box::new(expr))),
+                },
+            },
+            // 正号不改变表达式的值，只允许用于数字
+            Token::Plus => match self.parse_atom_expr()? {
+                ast::Expression::Consts(c @ (ast::Consts::String(_) | ast::Consts::Boolean(_))) => {
+                    return Err(Error::Parse(format!(
+                        "[Parser] Unexpected operand for unary plus: {}",
+                        ast::Expression::Consts(c)
+                    )));
+                }
+                expr => expr,
+            },
             Token::String(s) => ast::Consts::String(s).into(),
             Token::Keyword(Keyword::True) => ast::Consts::Boolean(true).into(),
             Token::Keyword(Keyword::False) => ast::Consts::Boolean(false).into(),
             Token::Keyword(Keyword::Null) => ast::Consts::Null.into(),
+            // 参数占位符，执行前绑定为具体的值
+            Token::Question => {
+                self.params += 1;
+                ast::Expression::Parameter(self.params - 1)
+            }
             t => {
                 return Err(Error::Parse(format!(
                     "[Parse] Unexpected expression token {}",
@@ -504,13 +958,31 @@ impl<'a> Parser<'a> {
         })
     }
 
+    // 解析数字常量，全部是数字的为整数，否则为浮点数
+    fn parse_number(n: &str) -> Result<ast::Expression> {
+        if n.trim_start_matches('-')
+            .chars()
+            .all(|c| c.is_ascii_digit())
+        {
+            // 整数
+            Ok(ast::Consts::Integer(n.parse()?).into())
+        } else {
+            // 浮点数
+            Ok(ast::Consts::Float(n.parse()?).into())
+        }
+    }
+
     fn peek(&mut self) -> Result<Option<Token>> {
-        self.lexer.peek().cloned().transpose()
+        self.peeked
+            .get_or_insert_with(|| self.lexer.next())
+            .clone()
+            .transpose()
     }
 
     fn next(&mut self) -> Result<Token> {
-        self.lexer
-            .next()
+        self.peeked
+            .take()
+            .unwrap_or_else(|| self.lexer.next())
             .unwrap_or_else(|| Err(Error::Parse(format!("[Parser] unexpected end of input"))))
     }
 
@@ -533,6 +1005,15 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // 解析列名，可以带表名前缀，例如 t.a
+    fn parse_column_name(&mut self) -> Result<String> {
+        let name = self.next_indent()?;
+        if self.next_if_token(Token::Period).is_some() {
+            return Ok(format!("{}.{}", name, self.next_indent()?));
+        }
+        Ok(name)
+    }
+
     /// 只有当前token是指定的token的时候返回，否则报错(返回Err)
     /// 检查下一个标记是否与期望的标记相符，如果不相符则返回错误。
     ///
@@ -614,6 +1095,8 @@ mod tests {
                         nullable: None,
                         default: Some(Expression::Consts(ast::Consts::Integer(100))),
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                     Column {
                         name: "b".to_string(),
@@ -621,6 +1104,8 @@ mod tests {
                         nullable: Some(false),
                         default: None,
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                     Column {
                         name: "c".to_string(),
@@ -628,6 +1113,8 @@ mod tests {
                         nullable: Some(true),
                         default: None,
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                     Column {
                         name: "d".to_string(),
@@ -635,6 +1122,42 @@ mod tests {
                         nullable: None,
                         default: Some(Expression::Consts(ast::Consts::Boolean(true))),
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
+                    },
+                ],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_create_table_with_unique() -> Result<()> {
+        let stmt1 = Parser::new("create table tbl1 (a int primary key, b text unique not null);")
+            .parse()?;
+        assert_eq!(
+            stmt1,
+            Statement::CreateTable {
+                name: "tbl1".to_string(),
+                columns: vec![
+                    Column {
+                        name: "a".to_string(),
+                        datatype: DataType::Integer,
+                        nullable: None,
+                        default: None,
+                        primary_key: true,
+                        unique: false,
+                        auto_increment: false,
+                    },
+                    Column {
+                        name: "b".to_string(),
+                        datatype: DataType::String,
+                        nullable: Some(false),
+                        default: None,
+                        primary_key: false,
+                        unique: true,
+                        auto_increment: false,
                     },
                 ],
             }
@@ -643,6 +1166,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_create_index() -> Result<()> {
+        let stmt1 = Parser::new("create index idx_b on tbl1 (b);").parse()?;
+        assert_eq!(
+            stmt1,
+            Statement::CreateIndex {
+                name: "idx_b".to_string(),
+                table_name: "tbl1".to_string(),
+                column: "b".to_string(),
+            }
+        );
+
+        assert!(
+            Parser::new("create index idx_b on tbl1 b;")
+                .parse()
+                .is_err()
+        );
+        assert!(Parser::new("create index on tbl1 (b);").parse().is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_parse_create_table_with_primary() -> Result<()> {
         let sql1 = "
@@ -665,6 +1210,8 @@ mod tests {
                         nullable: None,
                         default: None,
                         primary_key: true,
+                        unique: false,
+                        auto_increment: false,
                     },
                     Column {
                         name: "b".to_string(),
@@ -672,6 +1219,8 @@ mod tests {
                         nullable: Some(false),
                         default: None,
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                     Column {
                         name: "c".to_string(),
@@ -679,6 +1228,8 @@ mod tests {
                         nullable: Some(true),
                         default: None,
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                     Column {
                         name: "d".to_string(),
@@ -686,6 +1237,8 @@ mod tests {
                         nullable: None,
                         default: Some(Expression::Consts(ast::Consts::Boolean(true))),
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                 ],
             }
@@ -718,6 +1271,8 @@ mod tests {
                         nullable: None,
                         default: Some(Expression::Consts(ast::Consts::Integer(100))),
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                     Column {
                         name: "b".to_string(),
@@ -725,6 +1280,8 @@ mod tests {
                         nullable: Some(false),
                         default: None,
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                     Column {
                         name: "c".to_string(),
@@ -732,6 +1289,8 @@ mod tests {
                         nullable: Some(true),
                         default: None,
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                     Column {
                         name: "d".to_string(),
@@ -739,6 +1298,8 @@ mod tests {
                         nullable: None,
                         default: Some(Expression::Consts(ast::Consts::Boolean(true))),
                         primary_key: false,
+                        unique: false,
+                        auto_increment: false,
                     },
                 ],
             }
@@ -763,7 +1324,10 @@ mod tests {
             Ok(_) => println!("ok"),
             Err(e) => {
                 println!("err: {}", e);
-                assert_eq!(e.to_string(), "parse error [Parser] Unexpected token: tabl");
+                assert_eq!(
+                    e.to_string().lines().next(),
+                    Some("parse error at line 2, column 20: [Parser] Unexpected token: tabl")
+                );
             }
         }
 
@@ -787,8 +1351,8 @@ mod tests {
             Err(e) => {
                 println!("err: {}", e);
                 assert_eq!(
-                    e.to_string(),
-                    "parse error [Parser] Unexpected token CREATE"
+                    e.to_string().lines().next(),
+                    Some("parse error at line 7, column 12: [Parser] Unexpected token CREATE")
                 );
             }
         }
@@ -797,43 +1361,157 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_insert0() -> Result<()> {
-        let sql1 = "
-            insert into tbl1 values (1, 2.0, 'hello', true);
-        ";
-        let stmt1_or_err = Parser::new(sql1).parse()?;
+    fn test_parse_drop_table() -> Result<()> {
+        let stmt1 = Parser::new("drop table tbl1;").parse()?;
         assert_eq!(
-            stmt1_or_err,
-            Statement::Insert {
-                table_name: "tbl1".to_string(),
-                columns: None,
-                values: vec![vec![
-                    Expression::Consts(ast::Consts::Integer(1)),
-                    Expression::Consts(ast::Consts::Float(2.0)),
-                    Expression::Consts(ast::Consts::String("hello".to_string())),
-                    Expression::Consts(ast::Consts::Boolean(true)),
-                ]]
+            stmt1,
+            Statement::DropTable {
+                name: "tbl1".to_string(),
+                if_exists: false,
+            }
+        );
+
+        let stmt2 = Parser::new("DROP TABLE IF EXISTS tbl1;").parse()?;
+        assert_eq!(
+            stmt2,
+            Statement::DropTable {
+                name: "tbl1".to_string(),
+                if_exists: true,
             }
         );
 
+        assert!(Parser::new("drop table if tbl1;").parse().is_err());
+
+        // TRUNCATE 中的 TABLE 可以省略
+        for sql in ["truncate table tbl1;", "TRUNCATE tbl1;"] {
+            assert_eq!(
+                Parser::new(sql).parse()?,
+                Statement::Truncate {
+                    table_name: "tbl1".to_string(),
+                }
+            );
+        }
+        assert!(Parser::new("truncate table;").parse().is_err());
+
         Ok(())
     }
 
     #[test]
-    fn test_parse_insert1() -> Result<()> {
-        let sql1 = "
-            insert into tbl1 (a, b, c, d) values (1, 2.0, 'hello', true);
-        ";
-        let stmt1_or_err = Parser::new(sql1).parse()?;
+    fn test_parse_explain() -> Result<()> {
         assert_eq!(
-            stmt1_or_err,
-            Statement::Insert {
+            Parser::new("explain delete from tbl1;").parse()?,
+            Statement::Explain(Box::new(Statement::Delete {
                 table_name: "tbl1".to_string(),
-                columns: Some(vec![
-                    "a".to_string(),
-                    "b".to_string(),
-                    "c".to_string(),
-                    "d".to_string(),
+                where_clause: None,
+            }))
+        );
+        assert!(matches!(
+            Parser::new("EXPLAIN SELECT * FROM tbl1;").parse()?,
+            Statement::Explain(stmt) if matches!(*stmt, Statement::Select { .. })
+        ));
+        assert!(
+            Parser::new("explain create table tbl1 (a int);")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("explain explain select * from tbl1;")
+                .parse()
+                .is_err()
+        );
+        assert!(Parser::new("explain;").parse().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_vacuum() -> Result<()> {
+        assert_eq!(Parser::new("vacuum;").parse()?, Statement::Vacuum);
+        assert_eq!(Parser::new("VACUUM;").parse()?, Statement::Vacuum);
+        assert!(Parser::new("vacuum t;").parse().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_copy() -> Result<()> {
+        assert_eq!(
+            Parser::new("copy tbl1 from '/tmp/a.csv';").parse()?,
+            Statement::CopyFrom {
+                table_name: "tbl1".to_string(),
+                path: "/tmp/a.csv".to_string(),
+                header: true,
+            }
+        );
+        assert_eq!(
+            Parser::new("COPY tbl1 FROM 'a.csv' WITH (HEADER false);").parse()?,
+            Statement::CopyFrom {
+                table_name: "tbl1".to_string(),
+                path: "a.csv".to_string(),
+                header: false,
+            }
+        );
+        assert_eq!(
+            Parser::new("copy tbl1 to 'out.csv';").parse()?,
+            Statement::CopyTo {
+                table_name: "tbl1".to_string(),
+                path: "out.csv".to_string(),
+            }
+        );
+        assert!(Parser::new("copy tbl1 from a.csv;").parse().is_err());
+        assert!(Parser::new("copy tbl1 'a.csv';").parse().is_err());
+        assert!(
+            Parser::new("copy tbl1 to 'a.csv' with (header true);")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("copy tbl1 from 'a.csv' with (header);")
+                .parse()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_insert0() -> Result<()> {
+        let sql1 = "
+            insert into tbl1 values (1, 2.0, 'hello', true);
+        ";
+        let stmt1_or_err = Parser::new(sql1).parse()?;
+        assert_eq!(
+            stmt1_or_err,
+            Statement::Insert {
+                table_name: "tbl1".to_string(),
+                columns: None,
+                values: vec![vec![
+                    Expression::Consts(ast::Consts::Integer(1)),
+                    Expression::Consts(ast::Consts::Float(2.0)),
+                    Expression::Consts(ast::Consts::String("hello".to_string())),
+                    Expression::Consts(ast::Consts::Boolean(true)),
+                ]]
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_insert1() -> Result<()> {
+        let sql1 = "
+            insert into tbl1 (a, b, c, d) values (1, 2.0, 'hello', true);
+        ";
+        let stmt1_or_err = Parser::new(sql1).parse()?;
+        assert_eq!(
+            stmt1_or_err,
+            Statement::Insert {
+                table_name: "tbl1".to_string(),
+                columns: Some(vec![
+                    "a".to_string(),
+                    "b".to_string(),
+                    "c".to_string(),
+                    "d".to_string(),
                 ]),
                 values: vec![vec![
                     Expression::Consts(ast::Consts::Integer(1)),
@@ -892,12 +1570,14 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![],
-                from: ast::FromItem::Table {
+                from: Some(ast::FromItem::Table {
                     name: "tbl1".to_string(),
-                },
+                    alias: None,
+                }),
                 where_clause: None,
-                group_by: None,
+                group_by: vec![],
                 having: None,
                 order_by: vec![],
                 limit: None,
@@ -912,15 +1592,17 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![],
-                from: ast::FromItem::Table {
+                from: Some(ast::FromItem::Table {
                     name: "tbl1".to_string(),
-                },
+                    alias: None,
+                }),
                 where_clause: Some(Expression::Operation(Operation::Equal(
                     Box::new(Expression::Field("a".to_string())),
                     Box::new(Expression::Consts(Consts::Integer(100))),
                 ))),
-                group_by: None,
+                group_by: vec![],
                 having: None,
                 order_by: vec![],
                 limit: Some(Expression::Consts(Consts::Integer(10))),
@@ -931,6 +1613,469 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_qualified_column() -> Result<()> {
+        let sql = "
+            select t1.a, count(t2.b) from t1 join t2 on t1.a = t2.a group by t1.a order by t1.a desc;
+        ";
+        assert_eq!(
+            Parser::new(sql).parse()?,
+            Statement::Select {
+                distinct: false,
+                select: vec![
+                    (Expression::Field("t1.a".to_string()), None),
+                    (
+                        Expression::Function("count".to_string(), "t2.b".to_string()),
+                        None
+                    ),
+                ],
+                from: Some(FromItem::Join {
+                    left: Box::new(FromItem::Table {
+                        name: "t1".to_string(),
+                        alias: None,
+                    }),
+                    right: Box::new(FromItem::Table {
+                        name: "t2".to_string(),
+                        alias: None,
+                    }),
+                    join_type: JoinType::Inner,
+                    predicate: Some(Expression::Operation(Operation::Equal(
+                        Box::new(Expression::Field("t1.a".to_string())),
+                        Box::new(Expression::Field("t2.a".to_string())),
+                    ))),
+                }),
+                where_clause: None,
+                group_by: vec![Expression::Field("t1.a".to_string())],
+                having: None,
+                order_by: vec![(Expression::Field("t1.a".to_string()), OrderDirection::Desc)],
+                limit: None,
+                offset: None,
+            }
+        );
+
+        // 句点后面必须是列名，select 中也可以是 *
+        assert!(Parser::new("select t1. from t1;").parse().is_err());
+        assert!(Parser::new("select count(t1.*) from t1;").parse().is_err());
+
+        // select distinct
+        assert!(matches!(
+            Parser::new("select distinct c2 from t;").parse()?,
+            Statement::Select { distinct: true, ref select, .. } if select.len() == 1
+        ));
+        assert!(matches!(
+            Parser::new("SELECT DISTINCT * FROM t;").parse()?,
+            Statement::Select { distinct: true, ref select, .. } if select.is_empty()
+        ));
+        assert!(Parser::new("select distinct from t;").parse().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_table_alias() -> Result<()> {
+        let sql = "select u.name from users u join orders as o on u.id = o.user_id;";
+        match Parser::new(sql).parse()? {
+            Statement::Select {
+                from: Some(FromItem::Join { left, right, .. }),
+                ..
+            } => {
+                assert_eq!(
+                    *left,
+                    FromItem::Table {
+                        name: "users".to_string(),
+                        alias: Some("u".to_string()),
+                    }
+                );
+                assert_eq!(
+                    *right,
+                    FromItem::Table {
+                        name: "orders".to_string(),
+                        alias: Some("o".to_string()),
+                    }
+                );
+            }
+            stmt => panic!("unexpected statement {:?}", stmt),
+        }
+
+        // 别名后面可以直接跟 where 等子句
+        assert!(matches!(
+            Parser::new("select * from users u where u.id = 1;").parse()?,
+            Statement::Select {
+                from: Some(FromItem::Table { alias: Some(_), .. }),
+                where_clause: Some(_),
+                ..
+            }
+        ));
+        assert!(Parser::new("select * from users as;").parse().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_select_without_from() -> Result<()> {
+        let sql1 = "
+            select 1, 'hello' as greeting;
+        ";
+        let stmt1_or_err = Parser::new(sql1).parse()?;
+        assert_eq!(
+            stmt1_or_err,
+            Statement::Select {
+                distinct: false,
+                select: vec![
+                    (Expression::Consts(Consts::Integer(1)), None),
+                    (
+                        Expression::Consts(Consts::String("hello".to_string())),
+                        Some("greeting".to_string())
+                    ),
+                ],
+                from: None,
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_select_where() -> Result<()> {
+        let sql1 = "
+            select * from tbl1 where a > 5 and b <= 10 or not c != 'x';
+        ";
+        let stmt1_or_err = Parser::new(sql1).parse()?;
+        let field = |name: &str| Box::new(Expression::Field(name.to_string()));
+        assert_eq!(
+            stmt1_or_err,
+            Statement::Select {
+                distinct: false,
+                select: vec![],
+                from: Some(FromItem::Table {
+                    name: "tbl1".to_string(),
+                    alias: None,
+                }),
+                where_clause: Some(Expression::Operation(Operation::Or(
+                    Box::new(Expression::Operation(Operation::And(
+                        Box::new(Expression::Operation(Operation::GreaterThan(
+                            field("a"),
+                            Box::new(Expression::Consts(Consts::Integer(5))),
+                        ))),
+                        Box::new(Expression::Operation(Operation::LessThanOrEqual(
+                            field("b"),
+                            Box::new(Expression::Consts(Consts::Integer(10))),
+                        ))),
+                    ))),
+                    Box::new(Expression::Operation(Operation::Not(Box::new(
+                        Expression::Operation(Operation::NotEqual(
+                            field("c"),
+                            Box::new(Expression::Consts(Consts::String("x".to_string()))),
+                        ))
+                    )))),
+                ))),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            }
+        );
+
+        // 括号改变优先级
+        let sql2 = "
+            select * from tbl1 where a >= 5 and (b < 10 or c = 1);
+        ";
+        match Parser::new(sql2).parse()? {
+            Statement::Select {
+                where_clause: Some(Expression::Operation(Operation::And(_, r))),
+                ..
+            } => assert!(matches!(*r, Expression::Operation(Operation::Or(_, _)))),
+            stmt => panic!("unexpected statement {:?}", stmt),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_in_between_like() -> Result<()> {
+        let field = |name: &str| Box::new(Expression::Field(name.to_string()));
+        let int = |i: i64| Expression::Consts(Consts::Integer(i));
+        let where_clause = |sql: &str| -> Result<Option<Expression>> {
+            match Parser::new(sql).parse()? {
+                Statement::Select { where_clause, .. } => Ok(where_clause),
+                stmt => panic!("unexpected statement {:?}", stmt),
+            }
+        };
+
+        assert_eq!(
+            where_clause("select * from tbl1 where a in (1, 2 + 1) and b not in ('x');")?,
+            Some(Expression::Operation(Operation::And(
+                Box::new(Expression::Operation(Operation::In(
+                    field("a"),
+                    vec![
+                        int(1),
+                        Expression::Operation(Operation::Add(Box::new(int(2)), Box::new(int(1)))),
+                    ],
+                ))),
+                Box::new(Expression::Operation(Operation::Not(Box::new(
+                    Expression::Operation(Operation::In(
+                        field("b"),
+                        vec![Expression::Consts(Consts::String("x".to_string()))],
+                    ))
+                )))),
+            )))
+        );
+
+        // IN (SELECT ...) 为子查询
+        match where_clause("select * from tbl1 where a in (select b from tbl2 where c in (1));")? {
+            Some(Expression::Operation(Operation::In(expr, list))) => {
+                assert_eq!(expr, field("a"));
+                assert!(
+                    matches!(list.as_slice(), [Expression::Subquery(stmt)] if matches!(**stmt, Statement::Select { .. })),
+                    "{:?}",
+                    list
+                );
+            }
+            expr => panic!("unexpected expression {:?}", expr),
+        }
+        assert!(
+            Parser::new("select * from tbl1 where a in (1, select b from tbl2);")
+                .parse()
+                .is_err()
+        );
+
+        // BETWEEN 中的 AND 不会和逻辑运算的 AND 混淆
+        assert_eq!(
+            where_clause("select * from tbl1 where a not between 1 and 3 and b = 1;")?,
+            Some(Expression::Operation(Operation::And(
+                Box::new(Expression::Operation(Operation::Not(Box::new(
+                    Expression::Operation(Operation::Between(
+                        field("a"),
+                        Box::new(int(1)),
+                        Box::new(int(3)),
+                    ))
+                )))),
+                Box::new(Expression::Operation(Operation::Equal(
+                    field("b"),
+                    Box::new(int(1)),
+                ))),
+            )))
+        );
+
+        assert!(
+            Parser::new("select * from tbl1 where a in ();")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("select * from tbl1 where a in 1;")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("select * from tbl1 where a between 1;")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("select * from tbl1 where a not 1;")
+                .parse()
+                .is_err()
+        );
+
+        assert_eq!(
+            where_clause("select * from tbl1 where a not like 'x%';")?,
+            Some(Expression::Operation(Operation::Not(Box::new(
+                Expression::Operation(Operation::Like(
+                    field("a"),
+                    Box::new(Expression::Consts(Consts::String("x%".to_string()))),
+                ))
+            ))))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_is_null() -> Result<()> {
+        let field = |name: &str| Box::new(Expression::Field(name.to_string()));
+        let where_clause = |sql: &str| -> Result<Option<Expression>> {
+            match Parser::new(sql).parse()? {
+                Statement::Select { where_clause, .. } => Ok(where_clause),
+                stmt => panic!("unexpected statement {:?}", stmt),
+            }
+        };
+
+        // IS [NOT] NULL 的优先级高于 NOT 和 AND
+        let expr = where_clause("select * from tbl1 where a is null and not b is not null;")?;
+        assert_eq!(
+            expr,
+            Some(Expression::Operation(Operation::And(
+                Box::new(Expression::Operation(Operation::IsNull(field("a"), false))),
+                Box::new(Expression::Operation(Operation::Not(Box::new(
+                    Expression::Operation(Operation::IsNull(field("b"), true))
+                )))),
+            )))
+        );
+        assert_eq!(
+            expr.unwrap().to_string(),
+            "(a IS NULL AND NOT b IS NOT NULL)"
+        );
+
+        // 操作数可以是表达式
+        assert_eq!(
+            where_clause("select * from tbl1 where a + 1 IS NOT NULL;")?,
+            Some(Expression::Operation(Operation::IsNull(
+                Box::new(Expression::Operation(Operation::Add(
+                    field("a"),
+                    Box::new(Expression::Consts(Consts::Integer(1))),
+                ))),
+                true,
+            )))
+        );
+
+        assert!(
+            Parser::new("select * from tbl1 where a is 1;")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("select * from tbl1 where a is not;")
+                .parse()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_arithmetic() -> Result<()> {
+        let sql1 = "
+            select a + b * 2 - 1, (a + b) % 3 as m from tbl1 where a / 2 > b - 1;
+        ";
+        let stmt1_or_err = Parser::new(sql1).parse()?;
+        let field = |name: &str| Box::new(Expression::Field(name.to_string()));
+        let int = |i: i64| Box::new(Expression::Consts(Consts::Integer(i)));
+        assert_eq!(
+            stmt1_or_err,
+            Statement::Select {
+                distinct: false,
+                select: vec![
+                    (
+                        Expression::Operation(Operation::Subtract(
+                            Box::new(Expression::Operation(Operation::Add(
+                                field("a"),
+                                Box::new(Expression::Operation(Operation::Multiply(
+                                    field("b"),
+                                    int(2)
+                                ))),
+                            ))),
+                            int(1),
+                        )),
+                        None
+                    ),
+                    (
+                        Expression::Operation(Operation::Modulo(
+                            Box::new(Expression::Operation(Operation::Add(
+                                field("a"),
+                                field("b")
+                            ))),
+                            int(3),
+                        )),
+                        Some("m".to_string())
+                    ),
+                ],
+                from: Some(FromItem::Table {
+                    name: "tbl1".to_string(),
+                    alias: None,
+                }),
+                where_clause: Some(Expression::Operation(Operation::GreaterThan(
+                    Box::new(Expression::Operation(Operation::Divide(field("a"), int(2)))),
+                    Box::new(Expression::Operation(Operation::Subtract(
+                        field("b"),
+                        int(1)
+                    ))),
+                ))),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_unary_operators() -> Result<()> {
+        let parse_select = |sql: &str| -> Result<Expression> {
+            match Parser::new(sql).parse()? {
+                Statement::Select { mut select, .. } => Ok(select.remove(0).0),
+                _ => unreachable!(),
+            }
+        };
+        let int = |i: i64| Expression::Consts(Consts::Integer(i));
+
+        assert_eq!(parse_select("select -5;")?, int(-5));
+        assert_eq!(parse_select("select +5;")?, int(5));
+        assert_eq!(parse_select("select - -5;")?, int(5));
+        assert_eq!(
+            parse_select("select -1.5;")?,
+            Expression::Consts(Consts::Float(-1.5))
+        );
+        assert_eq!(parse_select("select -9223372036854775808;")?, int(i64::MIN));
+        assert_eq!(
+            parse_select("select 3 - -2;")?,
+            Expression::Operation(Operation::Subtract(Box::new(int(3)), Box::new(int(-2))))
+        );
+        assert_eq!(
+            parse_select("select -a * 2;")?,
+            Expression::Operation(Operation::Multiply(
+                Box::new(Expression::Operation(Operation::Negate(Box::new(
+                    Expression::Field("a".to_string())
+                )))),
+                Box::new(int(2))
+            ))
+        );
+
+        assert!(Parser::new("select -'a';").parse().is_err());
+        assert!(Parser::new("select +true;").parse().is_err());
+        assert!(
+            Parser::new("select - -9223372036854775808;")
+                .parse()
+                .is_err()
+        );
+        assert!(Parser::new("select 1 -;").parse().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_count_star() -> Result<()> {
+        match Parser::new("select count(*), count(a) as c from tbl1;").parse()? {
+            Statement::Select { select, .. } => assert_eq!(
+                select,
+                vec![
+                    (
+                        Expression::Function("count".to_string(), "*".to_string()),
+                        None
+                    ),
+                    (
+                        Expression::Function("count".to_string(), "a".to_string()),
+                        Some("c".to_string())
+                    ),
+                ]
+            ),
+            _ => unreachable!(),
+        }
+        assert!(Parser::new("select count(* a) from tbl1;").parse().is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_parse_select_order_by() -> Result<()> {
         let sql1 = "
@@ -940,16 +2085,18 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![],
-                from: FromItem::Table {
-                    name: "tbl1".to_string()
-                },
+                from: Some(FromItem::Table {
+                    name: "tbl1".to_string(),
+                    alias: None,
+                }),
                 where_clause: None,
-                group_by: None,
+                group_by: vec![],
                 order_by: vec![
-                    ("a".to_string(), OrderDirection::Asc),
-                    ("b".to_string(), OrderDirection::Asc),
-                    ("c".to_string(), OrderDirection::Desc)
+                    (Expression::Field("a".to_string()), OrderDirection::Asc),
+                    (Expression::Field("b".to_string()), OrderDirection::Asc),
+                    (Expression::Field("c".to_string()), OrderDirection::Desc)
                 ],
                 having: None,
                 limit: None,
@@ -957,6 +2104,25 @@ mod tests {
             }
         );
 
+        // 排序可以是表达式，也可以是 SELECT 列表中的列序号
+        let sql2 = "select a, b from tbl1 order by 2 desc, a + b;";
+        match Parser::new(sql2).parse()? {
+            Statement::Select { order_by, .. } => assert_eq!(
+                order_by,
+                vec![
+                    (Expression::Consts(Consts::Integer(2)), OrderDirection::Desc),
+                    (
+                        Expression::Operation(Operation::Add(
+                            Box::new(Expression::Field("a".to_string())),
+                            Box::new(Expression::Field("b".to_string()))
+                        )),
+                        OrderDirection::Asc
+                    ),
+                ]
+            ),
+            stmt => panic!("unexpected statement {:?}", stmt),
+        }
+
         Ok(())
     }
 
@@ -969,12 +2135,14 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![],
-                from: FromItem::Table {
-                    name: "tbl1".to_string()
-                },
+                from: Some(FromItem::Table {
+                    name: "tbl1".to_string(),
+                    alias: None,
+                }),
                 where_clause: None,
-                group_by: None,
+                group_by: vec![],
                 having: None,
                 order_by: vec![],
                 limit: Expression::Consts(ast::Consts::Integer(10)).into(),
@@ -982,6 +2150,50 @@ mod tests {
             }
         );
 
+        // offset 写在 limit 前面，以及 MySQL 风格的 LIMIT offset, count，结果相同
+        let sql2 = "select * from tbl1 offset 20 limit 10;";
+        assert_eq!(Parser::new(sql2).parse()?, stmt1_or_err);
+        let sql3 = "select * from tbl1 limit 20, 10;";
+        assert_eq!(Parser::new(sql3).parse()?, stmt1_or_err);
+
+        // limit 和 offset 可以是表达式
+        let sql4 = "select * from tbl1 offset 1 + 1;";
+        match Parser::new(sql4).parse()? {
+            Statement::Select { limit, offset, .. } => {
+                assert_eq!(limit, None);
+                assert_eq!(
+                    offset,
+                    Some(Expression::Operation(Operation::Add(
+                        Box::new(Expression::Consts(ast::Consts::Integer(1))),
+                        Box::new(Expression::Consts(ast::Consts::Integer(1))),
+                    )))
+                );
+            }
+            stmt => panic!("unexpected statement {:?}", stmt),
+        }
+
+        // 重复的子句，以及 LIMIT y, x 和 OFFSET 同时出现
+        assert!(
+            Parser::new("select * from tbl1 limit 1 limit 2;")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("select * from tbl1 offset 1 offset 2;")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("select * from tbl1 offset 1 limit 2, 3;")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("select * from tbl1 limit 2, 3 offset 1;")
+                .parse()
+                .is_err()
+        );
+
         Ok(())
     }
 
@@ -994,16 +2206,18 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![
                     (Expression::Field("a".to_string()), Some("col1".to_string())),
                     (Expression::Field("b".to_string()), Some("col2".to_string())),
                     (Expression::Field("c".to_string()), Some("col3".to_string())),
                 ],
-                from: FromItem::Table {
-                    name: "tbl1".to_string()
-                },
+                from: Some(FromItem::Table {
+                    name: "tbl1".to_string(),
+                    alias: None,
+                }),
                 where_clause: None,
-                group_by: None,
+                group_by: vec![],
                 having: None,
                 order_by: vec![],
                 limit: Expression::Consts(ast::Consts::Integer(10)).into(),
@@ -1023,23 +2237,26 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![
                     (Expression::Field("a".to_string()), Some("col1".to_string())),
                     (Expression::Field("b".to_string()), Some("col2".to_string())),
                     (Expression::Field("c".to_string()), Some("col3".to_string())),
                 ],
-                from: FromItem::Join {
+                from: Some(FromItem::Join {
                     left: Box::new(FromItem::Table {
-                        name: "tbl1".to_string()
+                        name: "tbl1".to_string(),
+                        alias: None,
                     }),
                     right: Box::new(FromItem::Table {
-                        name: "tbl2".to_string()
+                        name: "tbl2".to_string(),
+                        alias: None,
                     }),
                     join_type: JoinType::Cross {},
                     predicate: None,
-                },
+                }),
                 where_clause: None,
-                group_by: None,
+                group_by: vec![],
                 having: None,
                 order_by: vec![],
                 limit: Expression::Consts(ast::Consts::Integer(10)).into(),
@@ -1054,30 +2271,34 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![
                     (Expression::Field("a".to_string()), Some("col1".to_string())),
                     (Expression::Field("b".to_string()), Some("col2".to_string())),
                     (Expression::Field("c".to_string()), Some("col3".to_string())),
                 ],
-                from: FromItem::Join {
+                from: Some(FromItem::Join {
                     left: Box::new(FromItem::Join {
                         left: Box::new(FromItem::Table {
-                            name: "tbl1".to_string()
+                            name: "tbl1".to_string(),
+                            alias: None,
                         }),
                         right: Box::new(FromItem::Table {
-                            name: "tbl2".to_string()
+                            name: "tbl2".to_string(),
+                            alias: None,
                         }),
                         join_type: JoinType::Cross {},
                         predicate: None,
                     }),
                     right: Box::new(FromItem::Table {
-                        name: "tbl3".to_string()
+                        name: "tbl3".to_string(),
+                        alias: None,
                     }),
                     join_type: JoinType::Cross {},
                     predicate: None,
-                },
+                }),
                 where_clause: None,
-                group_by: None,
+                group_by: vec![],
                 having: None,
                 order_by: vec![],
                 limit: Expression::Consts(ast::Consts::Integer(10)).into(),
@@ -1098,6 +2319,7 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![
                     (
                         Expression::Function("count".to_string(), "a".to_string()),
@@ -1112,11 +2334,12 @@ mod tests {
                         None
                     )
                 ],
-                from: FromItem::Table {
-                    name: "tbl1".to_string()
-                },
+                from: Some(FromItem::Table {
+                    name: "tbl1".to_string(),
+                    alias: None,
+                }),
                 where_clause: None,
-                group_by: None,
+                group_by: vec![],
                 having: None,
                 order_by: vec![],
                 limit: Expression::Consts(ast::Consts::Integer(10)).into(),
@@ -1137,6 +2360,7 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![
                     (
                         Expression::Function("count".to_string(), "a".to_string()),
@@ -1152,11 +2376,12 @@ mod tests {
                     )
                 ],
 
-                from: FromItem::Table {
-                    name: "tbl1".to_string()
-                },
+                from: Some(FromItem::Table {
+                    name: "tbl1".to_string(),
+                    alias: None,
+                }),
                 where_clause: None,
-                group_by: Some(ast::Expression::Field("a".into())),
+                group_by: vec![ast::Expression::Field("a".into())],
                 having: None,
                 order_by: vec![],
                 limit: None,
@@ -1164,6 +2389,23 @@ mod tests {
             }
         );
 
+        let sql2 = "select a, b, count(c) from tbl1 group by a, b;";
+        match Parser::new(sql2).parse()? {
+            Statement::Select { group_by, .. } => assert_eq!(
+                group_by,
+                vec![
+                    ast::Expression::Field("a".into()),
+                    ast::Expression::Field("b".into())
+                ]
+            ),
+            _ => unreachable!(),
+        }
+        assert!(
+            Parser::new("select a, count(c) from tbl1 group by a,;")
+                .parse()
+                .is_err()
+        );
+
         Ok(())
     }
 
@@ -1177,6 +2419,7 @@ mod tests {
         assert_eq!(
             stmt1_or_err,
             Statement::Select {
+                distinct: false,
                 select: vec![
                     (
                         Expression::Function("count".to_string(), "a".to_string()),
@@ -1192,11 +2435,12 @@ mod tests {
                     )
                 ],
 
-                from: FromItem::Table {
-                    name: "tbl1".to_string()
-                },
+                from: Some(FromItem::Table {
+                    name: "tbl1".to_string(),
+                    alias: None,
+                }),
                 where_clause: None,
-                group_by: Some(ast::Expression::Field("a".into())),
+                group_by: vec![ast::Expression::Field("a".into())],
                 having: Some(ast::Expression::Operation(Operation::Equal(
                     Box::new(Expression::Field("count".into())),
                     Box::new(Expression::Consts(Consts::Integer(10)))
@@ -1236,4 +2480,184 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_parameter() -> Result<()> {
+        let stmt = Parser::new("insert into tbl1 values (?, 'a?', ?);").parse()?;
+        assert_eq!(
+            stmt,
+            Statement::Insert {
+                table_name: "tbl1".to_string(),
+                columns: None,
+                values: vec![vec![
+                    Expression::Parameter(0),
+                    Expression::Consts(Consts::String("a?".to_string())),
+                    Expression::Parameter(1),
+                ]],
+            }
+        );
+
+        // 占位符按照出现的顺序编号
+        let stmt = Parser::new("select * from tbl1 where a = ? and b > ? + 1;").parse()?;
+        match stmt {
+            Statement::Select { where_clause, .. } => {
+                assert_eq!(where_clause.unwrap().to_string(), "(a = ? AND b > ? + 1)")
+            }
+            stmt => panic!("unexpected statement {:?}", stmt),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_all() -> Result<()> {
+        let stmts = Parser::new(
+            "create table t (a int primary key);\n insert into t values (1);\nbegin; commit;",
+        )
+        .parse_all()?;
+        assert_eq!(stmts.len(), 4);
+        assert_eq!(
+            stmts[2],
+            Statement::Begin {
+                read_only: false,
+                as_of: None
+            }
+        );
+        assert_eq!(stmts[3], Statement::Commit);
+
+        assert_eq!(
+            Parser::new("begin read only as of 42;").parse()?,
+            Statement::Begin {
+                read_only: true,
+                as_of: Some(42)
+            }
+        );
+        assert_eq!(
+            Parser::new("BEGIN READ ONLY;").parse()?,
+            Statement::Begin {
+                read_only: true,
+                as_of: None
+            }
+        );
+        assert!(Parser::new("begin read;").parse().is_err());
+        assert!(Parser::new("begin read only as of x;").parse().is_err());
+
+        assert_eq!(Parser::new("  ").parse_all()?, vec![]);
+        // 每条语句都需要以分号结尾
+        assert!(Parser::new("begin; commit").parse_all().is_err());
+        assert!(Parser::new("begin;; commit;").parse_all().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_error_position() -> Result<()> {
+        let err = |sql: &str| match Parser::new(sql).parse_all() {
+            Ok(stmts) => panic!("unexpected success {:?}", stmts),
+            Err(e) => e.to_string(),
+        };
+
+        // 语句中间的错误，用 ^ 标出出错的 token
+        let sql = "select a,\n       b\n  from t1\n where a = = 1;";
+        assert_eq!(
+            err(sql),
+            "parse error at line 4, column 12: [Parse] Unexpected expression token =\n   where a = = 1;\n             ^"
+        );
+
+        // 多条语句中，后面语句的错误
+        let sql = "insert into t1 values (1);\nupdate t1\n\tset a = 2,\n\twhere b = 1;";
+        assert_eq!(
+            err(sql),
+            "parse error at line 4, column 2: [Parser] Expected indent, but got token WHERE\n  \twhere b = 1;\n  \t^"
+        );
+
+        // 词法错误，位置是出错的 token 的开始
+        let sql = "select *\n  from t1\n  where a ! 1;";
+        assert!(
+            err(sql).starts_with("parse error at line 3, column 11: [Lexer] Expected = after !\n")
+        );
+        let sql = "select *\nfrom t1 where a = 'abc;\n";
+        assert!(
+            err(sql)
+                .starts_with("parse error at line 2, column 19: [Lexer] Unexpected end of string")
+        );
+
+        // 输入提前结束，位置是输入的末尾
+        let sql = "select *\nfrom t1\nwhere";
+        assert!(err(sql).starts_with("parse error at line 3, column 6: "));
+
+        // 多字节字符按一个字符计算列号
+        let sql = "select 'é' from t1 where a = = 1;";
+        assert!(err(sql).starts_with("parse error at line 1, column 30: "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_wildcard() -> Result<()> {
+        let select = |sql: &str| match Parser::new(sql).parse()? {
+            Statement::Select { select, .. } => Ok(select),
+            stmt => Err(Error::Internal(format!("unexpected statement {:?}", stmt))),
+        };
+
+        // 单独的 * 仍然用空的列表表示
+        assert_eq!(select("select * from t1;")?, vec![]);
+        assert_eq!(
+            select("select *, a, t1.* from t1;")?,
+            vec![
+                (Expression::Wildcard(None), None),
+                (Expression::Field("a".to_string()), None),
+                (Expression::Wildcard(Some("t1".to_string())), None),
+            ]
+        );
+        assert_eq!(
+            select("select t2.*, t1.a as x from t1 join t2 on t1.a = t2.a;")?,
+            vec![
+                (Expression::Wildcard(Some("t2".to_string())), None),
+                (Expression::Field("t1.a".to_string()), Some("x".to_string())),
+            ]
+        );
+
+        // 通配符不能有别名，不能参与运算，不能出现在 select 之外
+        assert!(Parser::new("select * as x from t1;").parse().is_err());
+        assert!(Parser::new("select t1.* as x from t1;").parse().is_err());
+        assert!(Parser::new("select t1.* + 1 from t1;").parse().is_err());
+        assert!(Parser::new("select a in (t1.*) from t1;").parse().is_err());
+        assert!(
+            Parser::new("select * from t1 where t1.* = 1;")
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Parser::new("select * from t1 order by t1.*;")
+                .parse()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expression_display_round_trip() -> Result<()> {
+        // 展示的结果重新解析之后得到相同的表达式，右操作数的括号不能省略
+        for sql in [
+            "a - (b - c)",
+            "a - b - c",
+            "a / (b * c)",
+            "a * (b / c)",
+            "a % (b % 2)",
+            "a + (b + c)",
+            "(a + b) * c",
+            "a + b * c",
+            "a * b - c / d",
+            "(a - b) - (c - d)",
+            "-(a + b) * 2",
+        ] {
+            let expr = Parser::new(sql).parse_expression()?;
+            let displayed = expr.to_string();
+            assert_eq!(displayed, sql.replace("(a - b) - ", "a - b - "));
+            assert_eq!(Parser::new(&displayed).parse_expression()?, expr);
+        }
+        Ok(())
+    }
 }
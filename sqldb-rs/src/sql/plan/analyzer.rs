@@ -0,0 +1,509 @@
+use crate::{
+    error::{Error, Result},
+    sql::{
+        engine::Transaction,
+        parser::ast::{Expression, Operation, find_column},
+        plan::Node,
+        types::{DataType, Value},
+    },
+};
+
+// 在执行之前检查执行计划：表达式中引用的列是否存在，运算符和操作数的类型是否匹配
+// 按照执行器的方式推导每个节点输出的列名和类型，无法确定类型时（例如 NULL、参数）不检查
+pub struct Analyzer<'a, T: Transaction> {
+    txn: &'a T,
+}
+
+impl<'a, T: Transaction> Analyzer<'a, T> {
+    pub fn new(txn: &'a T) -> Self {
+        Self { txn }
+    }
+
+    pub fn analyze(&self, node: &Node) -> Result<()> {
+        self.analyze_node(node).map(|_| ())
+    }
+
+    // 检查节点及其子节点，返回节点输出的列
+    fn analyze_node(&self, node: &Node) -> Result<Scope> {
+        Ok(match node {
+            Node::CreateTable { .. }
+            | Node::DropTable { .. }
+            | Node::CreateIndex { .. }
+            | Node::Insert { .. }
+            | Node::CopyFrom { .. }
+            | Node::Nothing => Scope::default(),
+            Node::Truncate { table_name } => {
+                self.txn.must_get_table(table_name.clone())?;
+                Scope::default()
+            }
+            Node::Scan {
+                table_name,
+                alias,
+                filter,
+            } => {
+                let scope = self.table_scope(table_name, alias)?;
+                if let Some(filter) = filter {
+                    scope.check_predicate(filter)?;
+                }
+                scope
+            }
+            Node::KeyLookup {
+                table_name, alias, ..
+            }
+            | Node::IndexScan {
+                table_name, alias, ..
+            } => self.table_scope(table_name, alias)?,
+            Node::Update {
+                table_name,
+                source,
+                columns,
+            } => {
+                let scope = self.analyze_node(source)?;
+                let table = self.txn.must_get_table(table_name.clone())?;
+                for (col_name, expr) in columns {
+                    let Some(column) = table.columns.iter().find(|c| &c.name == col_name) else {
+                        return Err(Error::ColumnNotFound {
+                            table: Some(table.name.clone()),
+                            column: col_name.clone(),
+                            available: Vec::new(),
+                        });
+                    };
+                    // 新的值需要可以转换为列的类型
+                    if let Some(datatype) = scope.check(expr)?
+                        && !assignable(datatype, column.datatype)
+                    {
+                        return Err(Error::TypeMismatch {
+                            table: table.name.clone(),
+                            column: column.name.clone(),
+                            expected: column.datatype.to_string(),
+                            found: format!("{} {}", datatype, expr),
+                        });
+                    }
+                }
+                Scope::default()
+            }
+            Node::Delete { source, .. }
+            | Node::CopyTo { source, .. }
+            | Node::Explain { source } => {
+                self.analyze_node(source)?;
+                Scope::default()
+            }
+            Node::Order { source, order_by } => {
+                let scope = self.analyze_node(source)?;
+                for (expr, _) in order_by {
+                    scope.check(expr)?;
+                }
+                scope
+            }
+            Node::Limit { source, .. }
+            | Node::Offset { source, .. }
+            | Node::Distinct { source } => self.analyze_node(source)?,
+            Node::Projection { source, select } => {
+                let scope = self.analyze_node(source)?;
+                let mut output = Scope::default();
+                for (expr, alias) in select {
+                    if let Expression::Wildcard(_) = expr {
+                        for col_name in expr.expand_wildcard(&scope.columns)? {
+                            let datatype = scope.check(&Expression::Field(col_name.clone()))?;
+                            output.push(col_name, datatype);
+                        }
+                        continue;
+                    }
+                    let datatype = scope.check(expr)?;
+                    output.push(alias.clone().unwrap_or_else(|| expr.to_string()), datatype);
+                }
+                output
+            }
+            Node::NestedLoopJoin {
+                left,
+                right,
+                predicate,
+                ..
+            } => {
+                let left = self.analyze_node(left)?;
+                let right = self.analyze_node(right)?;
+                if let Some(predicate) = predicate {
+                    check_predicate(predicate, &left, &right)?;
+                }
+                left.join(right)
+            }
+            Node::Aggregate {
+                source,
+                exprs,
+                group_by,
+                having,
+            } => {
+                let source = self.analyze_node(source)?;
+                analyze_aggregate(&source, exprs, group_by, having)?
+            }
+            Node::Filter { source, predicate } => {
+                let scope = self.analyze_node(source)?;
+                scope.check_predicate(predicate)?;
+                scope
+            }
+            Node::InSubquery {
+                source,
+                expr,
+                subquery,
+                ..
+            } => {
+                let scope = self.analyze_node(source)?;
+                let inner = self.analyze_node(subquery)?;
+                if inner.columns.len() != 1 {
+                    return Err(Error::Plan(format!(
+                        "subquery must return only one column, got {} ({})",
+                        inner.columns.len(),
+                        inner.columns.join(", ")
+                    )));
+                }
+                if let (Some(datatype), Some(inner_type)) = (scope.check(expr)?, inner.types[0])
+                    && !comparable(datatype, inner_type)
+                {
+                    return Err(Error::OperatorMismatch(format!(
+                        "cannot compare {} with subquery column {} ({})",
+                        describe(expr, datatype, &scope, &Scope::default()),
+                        inner.columns[0],
+                        inner_type
+                    )));
+                }
+                scope
+            }
+        })
+    }
+
+    // 表扫描输出的列，有别名时使用别名作为前缀
+    fn table_scope(&self, table_name: &str, alias: &Option<String>) -> Result<Scope> {
+        let table = self.txn.must_get_table(table_name.to_string())?;
+        Ok(Scope {
+            columns: table.qualified_columns(alias.as_deref().unwrap_or(table_name)),
+            types: table.column_types(),
+        })
+    }
+}
+
+// 聚合节点输出 select 中的每一项，列名和执行器一致：有别名时使用别名，否则为函数名或者列名
+fn analyze_aggregate(
+    source: &Scope,
+    exprs: &[(Expression, Option<String>)],
+    group_by: &[Expression],
+    having: &Option<Expression>,
+) -> Result<Scope> {
+    // group by 中可以使用 select 中列的别名
+    for expr in group_by {
+        if let Expression::Field(name) = expr
+            && find_column(&source.columns, name)?.is_none()
+            && !exprs.iter().any(|(expr, alias)| {
+                matches!(expr, Expression::Field(_)) && alias.as_ref() == Some(name)
+            })
+        {
+            return Err(source.not_found(name));
+        }
+    }
+
+    let mut output = Scope::default();
+    for (expr, alias) in exprs {
+        let (name, datatype) = match expr {
+            Expression::Function(func_name, col_name) => (
+                func_name.clone(),
+                check_function(func_name, col_name, source)?,
+            ),
+            Expression::Field(col_name) => (col_name.clone(), source.check(expr)?),
+            // 其他的表达式由执行器报错
+            expr => (expr.to_string(), None),
+        };
+        output.push(alias.clone().unwrap_or(name), datatype);
+    }
+
+    // having 中的聚合函数作为隐藏列计算，例如 having count(a) > 1 中的 count(a)
+    if let Some(having) = having {
+        let mut scope = output.clone();
+        let mut result = Ok(());
+        let having = having.clone().transform(&mut |expr| match expr {
+            Expression::Function(func_name, col_name) => {
+                let name = format!("{}({})", func_name, col_name);
+                if !scope.columns.contains(&name) && result.is_ok() {
+                    match check_function(&func_name, &col_name, source) {
+                        Ok(datatype) => scope.push(name.clone(), datatype),
+                        Err(err) => result = Err(err),
+                    }
+                }
+                Expression::Field(name)
+            }
+            expr => expr,
+        });
+        result?;
+        scope.check_predicate(&having)?;
+    }
+    Ok(output)
+}
+
+// 检查聚合函数的参数，返回函数结果的类型，sum 和 avg 只能用于数值类型的列
+fn check_function(func_name: &str, col_name: &str, source: &Scope) -> Result<Option<DataType>> {
+    let func = Expression::Function(func_name.to_string(), col_name.to_string());
+    if col_name != "*" {
+        let datatype = source.check(&Expression::Field(col_name.to_string()))?;
+        if let Some(datatype) = datatype
+            && matches!(func_name.to_lowercase().as_str(), "sum" | "avg")
+            && !is_numeric(datatype)
+        {
+            return Err(Error::OperatorMismatch(format!(
+                "{} expects a numeric column, got {}",
+                func,
+                describe(
+                    &Expression::Field(col_name.to_string()),
+                    datatype,
+                    source,
+                    &Scope::default()
+                )
+            )));
+        }
+    }
+    Ok(func.data_type(&source.columns, &source.types))
+}
+
+// 计划节点输出的列名和对应的类型
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    columns: Vec<String>,
+    types: Vec<Option<DataType>>,
+}
+
+impl Scope {
+    fn push(&mut self, column: String, datatype: Option<DataType>) {
+        self.columns.push(column);
+        self.types.push(datatype);
+    }
+
+    // Join 输出左右两边所有的列
+    fn join(mut self, other: Scope) -> Scope {
+        self.columns.extend(other.columns);
+        self.types.extend(other.types);
+        self
+    }
+
+    fn not_found(&self, name: &str) -> Error {
+        Error::ColumnNotFound {
+            table: None,
+            column: name.to_string(),
+            available: self.columns.clone(),
+        }
+    }
+
+    // 检查在这些列上计算的表达式，返回表达式结果的类型
+    fn check(&self, expr: &Expression) -> Result<Option<DataType>> {
+        check_expr(expr, self, &Scope::default())
+    }
+
+    fn check_predicate(&self, expr: &Expression) -> Result<()> {
+        check_predicate(expr, self, &Scope::default())
+    }
+}
+
+// 查找列，先在 primary 中查找，再在 secondary 中查找，和 Join 中计算表达式的方式一致
+// 返回带有表名前缀的列名和列的类型
+fn lookup(name: &str, primary: &Scope, secondary: &Scope) -> Result<(String, Option<DataType>)> {
+    for scope in [primary, secondary] {
+        if let Some(pos) = find_column(&scope.columns, name)? {
+            return Ok((scope.columns[pos].clone(), scope.types[pos]));
+        }
+    }
+    Err(primary.clone().join(secondary.clone()).not_found(name))
+}
+
+// 过滤条件和 Join 条件的结果必须是布尔值
+fn check_predicate(expr: &Expression, l: &Scope, r: &Scope) -> Result<()> {
+    match check_expr(expr, l, r)? {
+        Some(datatype) if datatype != DataType::Boolean => Err(Error::OperatorMismatch(format!(
+            "condition must be BOOLEAN, got {}",
+            describe(expr, datatype, l, r)
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// 检查表达式并推导结果的类型，和 evaluate_expr 一致：
+// 比较运算的左边先在 l 中查找列，右边先在 r 中查找列，其他运算都先在 l 中查找
+fn check_expr(expr: &Expression, l: &Scope, r: &Scope) -> Result<Option<DataType>> {
+    let op = match expr {
+        Expression::Field(name) => return Ok(lookup(name, l, r)?.1),
+        Expression::Consts(c) => return Ok(Value::from_expression(c.clone().into()).datatype()),
+        // 聚合函数在聚合节点中检查
+        Expression::Parameter(_) | Expression::Wildcard(_) | Expression::Function(_, _) => {
+            return Ok(None);
+        }
+        // 子查询在生成计划时被替换为 InSubquery 节点
+        Expression::Subquery(_) => {
+            return Err(Error::Internal(format!("unexpected subquery in {}", expr)));
+        }
+        Expression::Operation(op) => op,
+    };
+    Ok(match op {
+        Operation::Equal(lexpr, rexpr)
+        | Operation::NotEqual(lexpr, rexpr)
+        | Operation::GreaterThan(lexpr, rexpr)
+        | Operation::GreaterThanOrEqual(lexpr, rexpr)
+        | Operation::LessThan(lexpr, rexpr)
+        | Operation::LessThanOrEqual(lexpr, rexpr) => {
+            check_comparison(lexpr, rexpr, l, r)?;
+            Some(DataType::Boolean)
+        }
+        Operation::In(expr, list) => {
+            check_expr(expr, l, r)?;
+            for item in list {
+                check_comparison(expr, item, l, r)?;
+            }
+            Some(DataType::Boolean)
+        }
+        Operation::Between(expr, low, high) => {
+            check_comparison(expr, low, l, r)?;
+            check_comparison(expr, high, l, r)?;
+            Some(DataType::Boolean)
+        }
+        Operation::And(lexpr, rexpr) | Operation::Or(lexpr, rexpr) => {
+            let name = match op {
+                Operation::And(_, _) => "AND",
+                _ => "OR",
+            };
+            check_operand(name, lexpr, DataType::Boolean, l, r)?;
+            check_operand(name, rexpr, DataType::Boolean, l, r)?;
+            Some(DataType::Boolean)
+        }
+        Operation::Not(expr) => {
+            check_operand("NOT", expr, DataType::Boolean, l, r)?;
+            Some(DataType::Boolean)
+        }
+        Operation::Like(lexpr, rexpr) => {
+            check_operand("LIKE", lexpr, DataType::String, l, r)?;
+            check_operand("LIKE", rexpr, DataType::String, r, l)?;
+            Some(DataType::Boolean)
+        }
+        Operation::IsNull(expr, _) => {
+            check_expr(expr, l, r)?;
+            Some(DataType::Boolean)
+        }
+        Operation::Add(lexpr, rexpr)
+        | Operation::Subtract(lexpr, rexpr)
+        | Operation::Multiply(lexpr, rexpr)
+        | Operation::Divide(lexpr, rexpr)
+        | Operation::Modulo(lexpr, rexpr) => {
+            let lt = check_expr(lexpr, l, r)?;
+            let rt = check_expr(rexpr, l, r)?;
+            if [lt, rt].into_iter().flatten().any(|t| !is_numeric(t)) {
+                return Err(arithmetic_error(op, lexpr, lt, rexpr, rt, l, r));
+            }
+            match (lt, rt) {
+                // 整数之间的运算结果为整数，有浮点数参与时为浮点数
+                (Some(DataType::Float), _) | (_, Some(DataType::Float)) => Some(DataType::Float),
+                (Some(_), _) | (_, Some(_)) => Some(DataType::Integer),
+                (None, None) => None,
+            }
+        }
+        Operation::Negate(expr) => match check_expr(expr, l, r)? {
+            Some(datatype) if !is_numeric(datatype) => {
+                return Err(Error::OperatorMismatch(format!(
+                    "cannot negate {}",
+                    describe(expr, datatype, l, r)
+                )));
+            }
+            datatype => datatype,
+        },
+    })
+}
+
+// 比较运算的两边需要可以比较：类型相同、整数和浮点数、时间戳和字符串
+fn check_comparison(lexpr: &Expression, rexpr: &Expression, l: &Scope, r: &Scope) -> Result<()> {
+    let lt = check_expr(lexpr, l, r)?;
+    let rt = check_expr(rexpr, r, l)?;
+    if let (Some(lt), Some(rt)) = (lt, rt)
+        && !comparable(lt, rt)
+    {
+        return Err(Error::OperatorMismatch(format!(
+            "cannot compare {} with {}",
+            describe(lexpr, lt, l, r),
+            describe(rexpr, rt, r, l)
+        )));
+    }
+    Ok(())
+}
+
+fn check_operand(
+    name: &str,
+    expr: &Expression,
+    expected: DataType,
+    l: &Scope,
+    r: &Scope,
+) -> Result<()> {
+    match check_expr(expr, l, r)? {
+        Some(datatype) if datatype != expected => Err(Error::OperatorMismatch(format!(
+            "{} expects {}, got {}",
+            name,
+            expected,
+            describe(expr, datatype, l, r)
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn arithmetic_error(
+    op: &Operation,
+    lexpr: &Expression,
+    lt: Option<DataType>,
+    rexpr: &Expression,
+    rt: Option<DataType>,
+    l: &Scope,
+    r: &Scope,
+) -> Error {
+    let symbol = match op {
+        Operation::Add(_, _) => "+",
+        Operation::Subtract(_, _) => "-",
+        Operation::Multiply(_, _) => "*",
+        Operation::Divide(_, _) => "/",
+        _ => "%",
+    };
+    let operand = |expr: &Expression, datatype: Option<DataType>| match datatype {
+        Some(datatype) => describe(expr, datatype, l, r),
+        None => expr.to_string(),
+    };
+    Error::OperatorMismatch(format!(
+        "cannot apply {} to {} and {}",
+        symbol,
+        operand(lexpr, lt),
+        operand(rexpr, rt)
+    ))
+}
+
+// 错误信息中的操作数，列带上表名，例如 column t.a (INTEGER)、STRING 'hello'
+fn describe(expr: &Expression, datatype: DataType, l: &Scope, r: &Scope) -> String {
+    match expr {
+        Expression::Field(name) => match lookup(name, l, r) {
+            Ok((column, _)) => format!("column {} ({})", column, datatype),
+            Err(_) => format!("column {} ({})", name, datatype),
+        },
+        Expression::Consts(_) => format!("{} {}", datatype, expr),
+        expr => format!("{} ({})", expr, datatype),
+    }
+}
+
+fn is_numeric(datatype: DataType) -> bool {
+    matches!(datatype, DataType::Integer | DataType::Float)
+}
+
+fn comparable(l: DataType, r: DataType) -> bool {
+    l == r
+        || matches!(
+            (l, r),
+            (DataType::Integer, DataType::Float)
+                | (DataType::Float, DataType::Integer)
+                | (DataType::Timestamp, DataType::String)
+                | (DataType::String, DataType::Timestamp)
+        )
+}
+
+// UPDATE 中的值可以转换为列的类型，和 Value::coerce 一致
+fn assignable(from: DataType, to: DataType) -> bool {
+    from == to
+        || matches!(
+            (from, to),
+            (DataType::Integer, DataType::Float) | (DataType::String, DataType::Timestamp)
+        )
+}
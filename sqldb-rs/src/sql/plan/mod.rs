@@ -1,16 +1,19 @@
 use std::collections::BTreeMap;
+use std::fmt::Display;
 
 use crate::error::Result;
 use crate::sql::engine::Transaction;
-use crate::sql::executor::Executor;
+use crate::sql::executor::{Deadline, Executor};
 use crate::sql::parser::ast::OrderDirection;
 use crate::sql::{
     executor::ResultSet,
     parser::ast::{self, Expression},
-    plan::planner::Planner,
+    plan::{analyzer::Analyzer, planner::Planner},
     schema::Table,
+    types::Value,
 };
 
+pub mod analyzer;
 pub mod planner;
 
 #[derive(Debug, PartialEq)]
@@ -20,6 +23,19 @@ pub enum Node {
         schema: Table,
     },
 
+    // 删除表
+    DropTable {
+        name: String,
+        if_exists: bool,
+    },
+
+    // 创建索引
+    CreateIndex {
+        name: String,
+        table_name: String,
+        column: String,
+    },
+
     // 插入数据
     Insert {
         table_name: String,
@@ -27,12 +43,28 @@ pub enum Node {
         values: Vec<Vec<Expression>>,
     },
 
-    // 扫描节点
+    // 扫描节点，有别名时输出的列名使用别名作为前缀
     Scan {
         table_name: String,
+        alias: Option<String>,
         filter: Option<Expression>,
     },
 
+    // 主键查询节点，读取主键等于 value 的数据
+    KeyLookup {
+        table_name: String,
+        alias: Option<String>,
+        value: Value,
+    },
+
+    // 索引扫描节点，读取索引列等于 value 的数据
+    IndexScan {
+        table_name: String,
+        alias: Option<String>,
+        column: String,
+        value: Value,
+    },
+
     // 更新节点
     Update {
         table_name: String,
@@ -46,10 +78,15 @@ pub enum Node {
         source: Box<Node>,
     },
 
+    // 清空表节点，直接删除表中所有的数据，不经过扫描
+    Truncate {
+        table_name: String,
+    },
+
     // 排序节点
     Order {
         source: Box<Node>,
-        order_by: Vec<(String, OrderDirection)>, // 列名，排序方式
+        order_by: Vec<(Expression, OrderDirection)>, // 排序表达式，排序方式
     },
 
     // limit节点
@@ -70,6 +107,11 @@ pub enum Node {
         select: Vec<(Expression, Option<String>)>, // (表达式, 可选别名)
     },
 
+    // 去重节点，去掉完全相同的行
+    Distinct {
+        source: Box<Node>,
+    },
+
     // 嵌套循环 Join 节点
     NestedLoopJoin {
         left: Box<Node>,
@@ -82,13 +124,239 @@ pub enum Node {
     Aggregate {
         source: Box<Node>,
         exprs: Vec<(Expression, Option<String>)>, // (表达式, 可选别名)
-        group_by: Option<Expression>,
+        group_by: Vec<Expression>,
+        having: Option<Expression>,
     },
 
     Filter {
         source: Box<Node>,
         predicate: Expression,
     },
+
+    // expr [NOT] IN (子查询) 过滤节点，子查询只能输出一列，和外层的查询无关
+    InSubquery {
+        source: Box<Node>,
+        expr: Expression,
+        subquery: Box<Node>,
+        negated: bool,
+    },
+
+    // 空数据源节点，没有 from 子句时使用，输出一行没有列的数据
+    Nothing,
+
+    // 展示执行计划，不执行
+    Explain {
+        source: Box<Node>,
+    },
+
+    // 从 CSV 文件导入数据
+    CopyFrom {
+        table_name: String,
+        path: String,
+        header: bool,
+    },
+
+    // 把 source 的输出导出为 CSV 文件
+    CopyTo {
+        table_name: String,
+        path: String,
+        source: Box<Node>,
+    },
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.format(f, 0)
+    }
+}
+
+impl Node {
+    // 以缩进的树形结构展示执行计划，子节点以 -> 开头
+    fn format(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        if depth > 0 {
+            write!(f, "\n{}-> ", "  ".repeat(depth))?;
+        }
+        match self {
+            Node::CreateTable { schema } => write!(f, "Create Table: {}", schema.name),
+            Node::DropTable { name, .. } => write!(f, "Drop Table: {}", name),
+            Node::CreateIndex {
+                name,
+                table_name,
+                column,
+            } => write!(f, "Create Index: {} on {} ({})", name, table_name, column),
+            Node::Insert {
+                table_name, values, ..
+            } => write!(f, "Insert: {} ({} rows)", table_name, values.len()),
+            Node::Scan {
+                table_name,
+                alias,
+                filter,
+            } => {
+                write!(f, "Scan: {}", table_name)?;
+                format_alias(f, alias)?;
+                if let Some(filter) = filter {
+                    write!(f, " (filter: {})", filter)?;
+                }
+                Ok(())
+            }
+            Node::KeyLookup {
+                table_name,
+                alias,
+                value,
+            } => {
+                write!(f, "Key Lookup: {}", table_name)?;
+                format_alias(f, alias)?;
+                write!(f, " (primary key = {})", format_value(value))
+            }
+            Node::IndexScan {
+                table_name,
+                alias,
+                column,
+                value,
+            } => {
+                write!(f, "Index Scan: {}", table_name)?;
+                format_alias(f, alias)?;
+                write!(f, " ({} = {})", column, format_value(value))
+            }
+            Node::Update {
+                table_name,
+                source,
+                columns,
+            } => {
+                let set = columns
+                    .iter()
+                    .map(|(col, expr)| format!("{} = {}", col, expr))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "Update: {} (set {})", table_name, set)?;
+                source.format(f, depth + 1)
+            }
+            Node::Delete { table_name, source } => {
+                write!(f, "Delete: {}", table_name)?;
+                source.format(f, depth + 1)
+            }
+            Node::Truncate { table_name } => write!(f, "Truncate: {}", table_name),
+            Node::Order { source, order_by } => {
+                let order = order_by
+                    .iter()
+                    .map(|(col, direction)| match direction {
+                        OrderDirection::Asc => format!("{} ASC", col),
+                        OrderDirection::Desc => format!("{} DESC", col),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "Order: {}", order)?;
+                source.format(f, depth + 1)
+            }
+            Node::Limit { source, limit } => {
+                write!(f, "Limit: {}", limit)?;
+                source.format(f, depth + 1)
+            }
+            Node::Offset { source, offset } => {
+                write!(f, "Offset: {}", offset)?;
+                source.format(f, depth + 1)
+            }
+            Node::Projection { source, select } => {
+                write!(f, "Projection: {}", format_exprs(select))?;
+                source.format(f, depth + 1)
+            }
+            Node::Distinct { source } => {
+                write!(f, "Distinct")?;
+                source.format(f, depth + 1)
+            }
+            Node::NestedLoopJoin {
+                left,
+                right,
+                predicate,
+                outer,
+            } => {
+                match (predicate, outer) {
+                    (None, false) => write!(f, "Nested Loop Join: CROSS")?,
+                    (None, true) => write!(f, "Nested Loop Join: LEFT OUTER")?,
+                    (Some(p), false) => write!(f, "Nested Loop Join: INNER (on: {})", p)?,
+                    (Some(p), true) => write!(f, "Nested Loop Join: LEFT OUTER (on: {})", p)?,
+                }
+                left.format(f, depth + 1)?;
+                right.format(f, depth + 1)
+            }
+            Node::Aggregate {
+                source,
+                exprs,
+                group_by,
+                having,
+            } => {
+                write!(f, "Aggregate: {}", format_exprs(exprs))?;
+                if !group_by.is_empty() {
+                    let group_by = group_by.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                    write!(f, " (group by: {})", group_by.join(", "))?;
+                }
+                if let Some(having) = having {
+                    write!(f, " (having: {})", having)?;
+                }
+                source.format(f, depth + 1)
+            }
+            Node::Filter { source, predicate } => {
+                write!(f, "Filter: {}", predicate)?;
+                source.format(f, depth + 1)
+            }
+            Node::InSubquery {
+                source,
+                expr,
+                subquery,
+                negated,
+            } => {
+                let op = if *negated { "NOT IN" } else { "IN" };
+                write!(f, "In Subquery: {} {}", expr, op)?;
+                source.format(f, depth + 1)?;
+                subquery.format(f, depth + 1)
+            }
+            Node::Nothing => write!(f, "Nothing"),
+            Node::Explain { source } => {
+                write!(f, "Explain")?;
+                source.format(f, depth + 1)
+            }
+            Node::CopyFrom {
+                table_name, path, ..
+            } => write!(f, "Copy: {} from '{}'", table_name, path),
+            Node::CopyTo {
+                table_name,
+                path,
+                source,
+            } => {
+                write!(f, "Copy: {} to '{}'", table_name, path)?;
+                source.format(f, depth + 1)
+            }
+        }
+    }
+}
+
+// 展示 select 中的表达式和别名
+fn format_exprs(exprs: &[(Expression, Option<String>)]) -> String {
+    exprs
+        .iter()
+        .map(|(expr, alias)| match alias {
+            Some(alias) => format!("{} AS {}", expr, alias),
+            None => expr.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// 展示表的别名，例如 Scan: users AS u
+fn format_alias(f: &mut std::fmt::Formatter<'_>, alias: &Option<String>) -> std::fmt::Result {
+    match alias {
+        Some(alias) => write!(f, " AS {}", alias),
+        None => Ok(()),
+    }
+}
+
+// 展示常量值，字符串和时间戳加上引号，字符串中的单引号需要转义
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("'{}'", s.replace('\'', "''")),
+        Value::Timestamp(_) => format!("'{}'", value),
+        v => v.to_string(),
+    }
 }
 
 // 执行计划定义，底层是不同类型执行节点
@@ -96,28 +364,67 @@ pub enum Node {
 pub struct Plan(pub Node);
 
 impl Plan {
-    // 使用 AST 创建一个 Plan（其中有一个node）
-    pub fn build(stmt: ast::Statement) -> Result<Self> {
-        Planner::new().build(stmt)
+    // 使用 AST 创建一个 Plan（其中有一个node），需要通过事务读取表的元数据（例如索引信息）
+    // 创建之后检查计划中引用的列和表达式的类型，有错误时在执行之前返回
+    pub fn build<T: Transaction>(stmt: ast::Statement, txn: &T) -> Result<Self> {
+        let plan = Planner::new(txn).build(stmt)?;
+        Analyzer::new(txn).analyze(&plan.0)?;
+        Ok(plan)
     }
 
     // 当这个 PLAN 执行的时候，获取其中的 Node，构建一个执行器(构建的时候进行类型自适应构建)并执行
-    pub fn execute<T: Transaction + 'static>(self, txn: &mut T) -> Result<ResultSet> {
+    // 超过 deadline 时执行器返回 Error::Timeout
+    pub fn execute<T: Transaction + 'static>(
+        self,
+        txn: &mut T,
+        deadline: Deadline,
+    ) -> Result<ResultSet> {
         // let exec = <dyn Executor<T>>::build(self.0);
-        let exec = Box::new(<dyn Executor<T>>::build(self.0));
-        exec.execute(txn)
+        let exec = Box::new(<dyn Executor<T>>::build(self.0, deadline));
+        match exec.execute(txn)? {
+            // 执行过程中列名带有表名前缀，输出时去掉，例如 t.a -> a
+            ResultSet::Scan {
+                columns,
+                types,
+                rows,
+            } => Ok(ResultSet::Scan {
+                columns: columns.into_iter().map(unqualify_column).collect(),
+                types,
+                rows,
+            }),
+            result => Ok(result),
+        }
+    }
+}
+
+// 只处理 table.column 形式的列名，常量（例如 1.5）和表达式保持不变
+fn unqualify_column(name: String) -> String {
+    let is_ident = |s: &str| {
+        s.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+    };
+    match name.split_once('.') {
+        Some((table, col)) if is_ident(table) && is_ident(col) => col.to_string(),
+        _ => name,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        error::Result,
-        sql::{parser::Parser, plan::Plan},
+        error::{Error, Result},
+        sql::{
+            engine::{Engine, Session, kv::KVEngine},
+            executor::ResultSet,
+            parser::Parser,
+            plan::{Node, Plan},
+        },
+        storage::memory::MemoryEngine,
     };
 
     #[test]
     fn test_plan_create_table() -> Result<()> {
+        let txn = KVEngine::new(MemoryEngine::new()).begin()?;
         let sql1 = "
             create table tbl1 (
                 a int default 100,
@@ -128,7 +435,7 @@ mod tests {
         ";
 
         let stmt1 = Parser::new(sql1).parse()?;
-        let p1 = Plan::build(stmt1)?;
+        let p1 = Plan::build(stmt1, &txn)?;
         println!("{:?}", p1);
 
         let sql2 = "
@@ -141,7 +448,7 @@ mod tests {
         ";
 
         let stmt2 = Parser::new(sql2).parse()?;
-        let p2 = Plan::build(stmt2)?;
+        let p2 = Plan::build(stmt2, &txn)?;
         println!("{:?}", p2);
 
         Ok(())
@@ -149,16 +456,17 @@ mod tests {
 
     #[test]
     fn test_plan_insert() -> Result<()> {
+        let txn = KVEngine::new(MemoryEngine::new()).begin()?;
         let sql1 = "
             insert into tbl1 values (1, 2, 3, 'a', true);";
         let stmt1 = Parser::new(sql1).parse()?;
-        let p1 = Plan::build(stmt1)?;
+        let p1 = Plan::build(stmt1, &txn)?;
         println!("{:?}", p1);
 
         let sql2 = "
             insert into tbl1 values (1, 2, 3, 'a', true);";
         let stmt2 = Parser::new(sql2).parse()?;
-        let p2 = Plan::build(stmt2)?;
+        let p2 = Plan::build(stmt2, &txn)?;
         println!("{:?}", p2);
 
         Ok(())
@@ -166,19 +474,288 @@ mod tests {
 
     #[test]
     fn test_plan_select() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        kvengine
+            .session()?
+            .execute("create table tbl1 (a int primary key);")?;
+        let txn = kvengine.begin()?;
         let sql1 = "select * from tbl1;";
         let stmt1 = Parser::new(sql1).parse()?;
-        let p1 = Plan::build(stmt1)?;
+        let p1 = Plan::build(stmt1, &txn)?;
         println!("{:?}", p1);
 
         assert_eq!(
             p1,
             Plan(crate::sql::plan::Node::Scan {
                 table_name: "tbl1".to_string(),
+                alias: None,
                 filter: None,
             })
         );
 
         Ok(())
     }
+
+    #[test]
+    fn test_plan_limit_offset() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        kvengine
+            .session()?
+            .execute("create table tbl1 (a int primary key);")?;
+        let txn = kvengine.begin()?;
+        let plan = |sql: &str| Plan::build(Parser::new(sql).parse()?, &txn);
+
+        // 不论子句的顺序如何，Offset 都在 Limit 的下面，先跳过再截取
+        let expected = Plan(Node::Limit {
+            source: Box::new(Node::Offset {
+                source: Box::new(Node::Scan {
+                    table_name: "tbl1".to_string(),
+                    alias: None,
+                    filter: None,
+                }),
+                offset: 20,
+            }),
+            limit: 10,
+        });
+        assert_eq!(plan("select * from tbl1 limit 10 offset 20;")?, expected);
+        assert_eq!(plan("select * from tbl1 offset 20 limit 10;")?, expected);
+        assert_eq!(plan("select * from tbl1 limit 20, 10;")?, expected);
+        assert_eq!(
+            plan("select * from tbl1 limit 2 * 5 offset 25 - 5;")?,
+            expected
+        );
+
+        // 只有 offset 或者只有 limit
+        assert_eq!(
+            plan("select * from tbl1 offset 0;")?,
+            Plan(Node::Offset {
+                source: Box::new(Node::Scan {
+                    table_name: "tbl1".to_string(),
+                    alias: None,
+                    filter: None,
+                }),
+                offset: 0,
+            })
+        );
+        assert_eq!(
+            plan("select * from tbl1 limit 0;")?,
+            Plan(Node::Limit {
+                source: Box::new(Node::Scan {
+                    table_name: "tbl1".to_string(),
+                    alias: None,
+                    filter: None,
+                }),
+                limit: 0,
+            })
+        );
+
+        // 负数、非整数以及引用列的表达式都是错误
+        for sql in [
+            "select * from tbl1 limit 'abc';",
+            "select * from tbl1 limit -1;",
+            "select * from tbl1 limit 1.5;",
+            "select * from tbl1 limit null;",
+            "select * from tbl1 offset -5;",
+            "select * from tbl1 offset true;",
+            "select * from tbl1 limit a;",
+        ] {
+            match plan(sql) {
+                Err(Error::Plan(msg)) => assert!(
+                    msg.contains("must be a non-negative integer"),
+                    "{}: {}",
+                    sql,
+                    msg
+                ),
+                res => panic!("{}: unexpected result {:?}", sql, res),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn explain<E: Engine + 'static>(s: &mut Session<E>, sql: &str) -> Result<String> {
+        match s.execute(sql)? {
+            ResultSet::Explain { plan } => Ok(plan),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+    }
+
+    #[test]
+    fn test_plan_explain() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b text, c int);")?;
+        s.execute("create table t2 (x int primary key, d int);")?;
+        s.execute("create index idx_c on t1 (c);")?;
+        s.execute("insert into t1 values (1, 'x', 10), (2, 'y', 20);")?;
+
+        assert_eq!(
+            explain(
+                &mut s,
+                "explain select b, count(d) as cnt from t1 left join t2 on a = x \
+                 where c > 5 group by b having cnt > 0 order by b desc limit 10 offset 1;"
+            )?,
+            "Limit: 10
+  -> Offset: 1
+    -> Order: b DESC
+      -> Aggregate: b, count(d) AS cnt (group by: b) (having: cnt > 0)
+        -> Filter: c > 5
+          -> Nested Loop Join: LEFT OUTER (on: a = x)
+            -> Scan: t1
+            -> Scan: t2"
+        );
+
+        // right join 交换了左右两边
+        assert_eq!(
+            explain(&mut s, "explain select * from t1 right join t2 on a = x;")?,
+            "Nested Loop Join: LEFT OUTER (on: x = a)
+  -> Scan: t2
+  -> Scan: t1"
+        );
+
+        assert_eq!(
+            explain(&mut s, "explain select a + 1 as x from t1 where a = 1;")?,
+            "Projection: a + 1 AS x
+  -> Key Lookup: t1 (primary key = 1)"
+        );
+        assert_eq!(
+            explain(
+                &mut s,
+                "explain update t1 set b = 'z' where c = 10 and b != 'x';"
+            )?,
+            "Update: t1 (set b = 'z')
+  -> Filter: b != 'x'
+    -> Index Scan: t1 (c = 10)"
+        );
+
+        // 子查询在其他条件过滤之后执行
+        assert_eq!(
+            explain(
+                &mut s,
+                "explain select a from t1 where a not in (select x from t2 where d > 1) and c > 5;"
+            )?,
+            "Projection: a
+  -> In Subquery: a NOT IN
+    -> Scan: t1 (filter: c > 5)
+    -> Projection: x
+      -> Scan: t2 (filter: d > 1)"
+        );
+
+        // 不会执行语句
+        assert_eq!(
+            explain(&mut s, "explain delete from t1;")?,
+            "Delete: t1
+  -> Scan: t1"
+        );
+        match s.execute("select * from t1;")? {
+            ResultSet::Scan { rows, .. } => assert_eq!(rows.len(), 2),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_analyze() -> Result<()> {
+        let kvengine = KVEngine::new(MemoryEngine::new());
+        let mut s = kvengine.session()?;
+        s.execute("create table t1 (a int primary key, b text, c float);")?;
+        s.execute("create table t2 (x int primary key, d int);")?;
+
+        // 类型不匹配的运算在执行之前报错，即使表中没有数据
+        for (sql, expected) in [
+            (
+                "select * from t1 where a > 'hello';",
+                "cannot compare column t1.a (INTEGER) with STRING 'hello'",
+            ),
+            (
+                "select * from t1 join t2 on t1.b = t2.x;",
+                "cannot compare column t1.b (STRING) with column t2.x (INTEGER)",
+            ),
+            (
+                "select a from t1 where a in (1, true);",
+                "cannot compare column t1.a (INTEGER) with BOOLEAN TRUE",
+            ),
+            (
+                "select a + b from t1;",
+                "cannot apply + to column t1.a (INTEGER) and column t1.b (STRING)",
+            ),
+            ("select -b from t1;", "cannot negate column t1.b (STRING)"),
+            (
+                "select * from t1 where a and true;",
+                "AND expects BOOLEAN, got column t1.a (INTEGER)",
+            ),
+            (
+                "select * from t1 where a + 1;",
+                "condition must be BOOLEAN, got a + 1 (INTEGER)",
+            ),
+            (
+                "select sum(b) from t1;",
+                "sum(b) expects a numeric column, got column t1.b (STRING)",
+            ),
+            (
+                "select count(*) from t1 group by b having count(*) > 'x';",
+                "cannot compare column count(*) (INTEGER) with STRING 'x'",
+            ),
+        ] {
+            match s.execute(sql) {
+                Err(Error::OperatorMismatch(msg)) => assert_eq!(msg, expected, "{}", sql),
+                res => panic!("{}: unexpected result {:?}", sql, res),
+            }
+        }
+
+        // 每个子句中引用不存在的列都会报错
+        for sql in [
+            "select z from t1;",
+            "select * from t1 where z = 1;",
+            "select * from t1 join t2 on t1.a = t2.z;",
+            "select count(*) from t1 group by z;",
+            "select b, count(*) from t1 group by b having count(z) > 1;",
+            "select b, count(*) from t1 group by b having z > 1;",
+            "select * from t1 order by z;",
+            "select max(z) from t1;",
+        ] {
+            match s.execute(sql) {
+                Err(Error::ColumnNotFound { column, .. }) => {
+                    assert!(column.ends_with('z'), "{}: {}", sql, column)
+                }
+                res => panic!("{}: unexpected result {:?}", sql, res),
+            }
+        }
+
+        // UPDATE 的值需要可以转换为列的类型
+        match s.execute("update t1 set c = 'abc';") {
+            Err(Error::TypeMismatch {
+                table,
+                column,
+                expected,
+                found,
+            }) => {
+                assert_eq!((table.as_str(), column.as_str()), ("t1", "c"));
+                assert_eq!(
+                    (expected.as_str(), found.as_str()),
+                    ("FLOAT", "STRING 'abc'")
+                );
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        // 合法的查询不受影响
+        s.execute("insert into t1 values (1, 'x', 1.5), (2, 'y', null);")?;
+        s.execute("insert into t2 values (1, 10);")?;
+        for sql in [
+            "select * from t1 where a > 1.5 and c is null;",
+            "select a * 2 + c, -a from t1 where b like 'x%' or a in (1, 2.0);",
+            "select * from t1 join t2 on a = x where d between 1 and 20;",
+            "select t.a from t1 as t order by t.a desc;",
+            "select b as name, count(*), avg(c) from t1 group by name having sum(a) > 1;",
+            "update t1 set c = a where a > null;",
+        ] {
+            if let Err(err) = s.execute(sql) {
+                panic!("{}: unexpected error {:?}", sql, err)
+            }
+        }
+
+        Ok(())
+    }
 }
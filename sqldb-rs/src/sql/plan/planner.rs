@@ -1,20 +1,30 @@
+use std::collections::HashSet;
+
 use crate::{
     error::Error,
     sql::{
-        parser::ast::{self, Expression, JoinType, Operation},
+        engine::Transaction,
+        parser::ast::{
+            self, Expression, JoinType, Operation, OrderDirection, evaluate_expr_single,
+        },
         plan::{Node, Plan},
         schema::{self, Table},
-        types::Value,
+        types::{DataType, Value},
     },
 };
 
 use crate::error::Result;
 
-pub struct Planner;
+// WHERE 中的子查询条件：(expr, 子查询节点, 是否为 NOT IN)
+type SubqueryFilter = (Expression, Node, bool);
+
+pub struct Planner<'a, T: Transaction> {
+    txn: &'a T,
+}
 
-impl Planner {
-    pub fn new() -> Self {
-        Self {}
+impl<'a, T: Transaction> Planner<'a, T> {
+    pub fn new(txn: &'a T) -> Self {
+        Self { txn }
     }
 
     pub fn build(&mut self, stmt: ast::Statement) -> Result<Plan> {
@@ -32,32 +42,49 @@ impl Planner {
                         .map(|c| {
                             let nullable = c.nullable.unwrap_or(!c.primary_key);
                             let default = match c.default {
-                                Some(expr) => Some(Value::from_expression(expr)),
+                                Some(expr) => Some(evaluate_default(&c.name, &c.datatype, &expr)?),
                                 None if nullable => Some(Value::Null),
                                 None => None,
                             };
 
-                            schema::Column {
+                            Ok(schema::Column {
                                 name: c.name,
                                 datatype: c.datatype,
                                 nullable,
                                 default,
                                 primary_key: c.primary_key,
-                            }
+                                unique: c.unique,
+                                auto_increment: c.auto_increment,
+                            })
                         })
-                        .collect(),
+                        .collect::<Result<_>>()?,
+                    indexes: Vec::new(),
                 },
             },
+            ast::Statement::CreateIndex {
+                name,
+                table_name,
+                column,
+            } => Node::CreateIndex {
+                name,
+                table_name,
+                column,
+            },
+            ast::Statement::DropTable { name, if_exists } => Node::DropTable { name, if_exists },
             ast::Statement::Insert {
                 table_name,
                 columns,
                 values,
-            } => Node::Insert {
-                table_name,
-                columns: columns.unwrap_or_default(),
-                values,
-            },
+            } => {
+                reject_subqueries(values.iter().flatten())?;
+                Node::Insert {
+                    table_name,
+                    columns: columns.unwrap_or_default(),
+                    values,
+                }
+            }
             ast::Statement::Select {
+                distinct,
                 select,
                 from,
                 where_clause,
@@ -75,8 +102,83 @@ impl Planner {
                 //     filter: None,
                 // };
 
+                // * 和 t.* 在投影时展开，聚合的结果中没有原来的列
+                if let Some((expr, _)) = select
+                    .iter()
+                    .find(|(e, _)| matches!(e, Expression::Wildcard(_)))
+                {
+                    if !group_by.is_empty()
+                        || select
+                            .iter()
+                            .any(|(e, _)| matches!(e, Expression::Function(_, _)))
+                    {
+                        return Err(Error::Plan(format!(
+                            "wildcard {} cannot be used with aggregate functions or GROUP BY",
+                            expr
+                        )));
+                    }
+                    if from.is_none() {
+                        return Err(Error::Plan(format!(
+                            "SELECT {} with no tables specified is not valid",
+                            expr
+                        )));
+                    }
+                }
+
+                // WHERE 中的 IN (SELECT ...) 条件单独规划，其他子句中不能使用子查询
+                let (where_clause, subqueries) = self.build_subqueries(where_clause)?;
+                reject_subqueries(
+                    select
+                        .iter()
+                        .map(|(e, _)| e)
+                        .chain(from.iter().flat_map(join_predicates))
+                        .chain(&group_by)
+                        .chain(&having)
+                        .chain(order_by.iter().map(|(e, _)| e))
+                        .chain(&limit)
+                        .chain(&offset),
+                )?;
+
+                check_aggregate_select(&select, &group_by)?;
+
+                // order by 中的列序号需要在 from 被使用之前解析
+                let order_by = self.resolve_order_by(order_by, &select, &from, &group_by)?;
+
                 // from
-                let mut node = self.build_from_item(from, &where_clause)?;
+                let mut node = match from {
+                    // 单表的过滤条件直接下推到 Scan 节点
+                    Some(ast::FromItem::Table { name, alias }) => {
+                        self.build_scan(name, alias, where_clause)?
+                    }
+                    // 多表 Join 的过滤条件可能引用多张表的列，需要在 Join 之后过滤
+                    Some(from) => {
+                        check_table_names(&from, &mut HashSet::new())?;
+                        let node = self.build_from_item(from)?;
+                        match where_clause {
+                            Some(predicate) => Node::Filter {
+                                source: Box::new(node),
+                                predicate,
+                            },
+                            None => node,
+                        }
+                    }
+                    // 没有 from 子句，只计算 select 中的常量表达式
+                    None => {
+                        if select.is_empty() {
+                            return Err(Error::Plan(
+                                "SELECT * with no tables specified is not valid".into(),
+                            ));
+                        }
+                        match where_clause {
+                            Some(predicate) => Node::Filter {
+                                source: Box::new(Node::Nothing),
+                                predicate,
+                            },
+                            None => Node::Nothing,
+                        }
+                    }
+                };
+                node = filter_subqueries(node, subqueries);
 
                 // aggregate\group by
                 let mut has_agg = false;
@@ -88,20 +190,20 @@ impl Planner {
                             break;
                         }
                     }
-                    if group_by.is_some() {
+                    if !group_by.is_empty() {
                         has_agg = true;
                     }
-                    if has_agg {
-                        node = Node::Aggregate {
-                            source: Box::new(node),
-                            exprs: select.clone(),
-                            group_by,
-                        }
-                    }
                 }
 
-                // having
-                if let Some(expr) = having {
+                // having 可以引用聚合函数，在聚合节点中计算
+                if has_agg {
+                    node = Node::Aggregate {
+                        source: Box::new(node),
+                        exprs: select.clone(),
+                        group_by,
+                        having,
+                    }
+                } else if let Some(expr) = having {
                     node = Node::Filter {
                         source: Box::new(node),
                         predicate: expr,
@@ -116,14 +218,28 @@ impl Planner {
                     }
                 }
 
+                // distinct 对投影之后的行去重，需要在 offset 和 limit 之前执行
+                // 投影和去重都不改变行的顺序，排序的结果仍然保留
+                let mut projected = false;
+                if distinct {
+                    if !select.is_empty() && !has_agg {
+                        node = Node::Projection {
+                            source: Box::new(node),
+                            select: select.clone(),
+                        };
+                        projected = true;
+                    }
+                    node = Node::Distinct {
+                        source: Box::new(node),
+                    }
+                }
+
+                // 无论子句的书写顺序如何，都是先跳过 offset 行，再取 limit 行
                 // offset
                 if let Some(expr) = offset {
                     node = Node::Offset {
                         source: Box::new(node),
-                        offset: match Value::from_expression(expr) {
-                            Value::Integer(i) if i >= 0 => i as usize,
-                            _ => 0,
-                        },
+                        offset: evaluate_count("OFFSET", &expr)?,
                     }
                 }
 
@@ -131,15 +247,12 @@ impl Planner {
                 if let Some(expr) = limit {
                     node = Node::Limit {
                         source: Box::new(node),
-                        limit: match Value::from_expression(expr) {
-                            Value::Integer(i) if i >= 0 => i as usize,
-                            _ => usize::MAX,
-                        },
+                        limit: evaluate_count("LIMIT", &expr)?,
                     }
                 }
 
                 // projection
-                if !select.is_empty() && !has_agg {
+                if !select.is_empty() && !has_agg && !projected {
                     node = Node::Projection {
                         source: Box::new(node),
                         select: select,
@@ -152,35 +265,279 @@ impl Planner {
                 table_name,
                 columns,
                 where_clause,
-            } => Node::Update {
-                table_name: table_name.clone(),
-                columns,
-                source: Box::new(Node::Scan {
+            } => {
+                reject_subqueries(columns.values())?;
+                let (where_clause, subqueries) = self.build_subqueries(where_clause)?;
+                let source = self.build_scan(table_name.clone(), None, where_clause)?;
+                Node::Update {
                     table_name,
-                    filter: where_clause,
-                }),
-            },
+                    columns,
+                    source: Box::new(filter_subqueries(source, subqueries)),
+                }
+            }
             ast::Statement::Delete {
                 table_name,
                 where_clause,
-            } => Node::Delete {
+            } => {
+                let (where_clause, subqueries) = self.build_subqueries(where_clause)?;
+                let source = self.build_scan(table_name.clone(), None, where_clause)?;
+                Node::Delete {
+                    table_name,
+                    source: Box::new(filter_subqueries(source, subqueries)),
+                }
+            }
+            ast::Statement::Truncate { table_name } => Node::Truncate { table_name },
+            ast::Statement::Begin { .. } | ast::Statement::Commit | ast::Statement::Rollback => {
+                return Err(Error::Internal("unexpected transaction command".into()));
+            }
+            ast::Statement::Explain(stmt) => Node::Explain {
+                source: Box::new(self.build_statment(*stmt)?),
+            },
+            ast::Statement::Vacuum => {
+                return Err(Error::Internal("unexpected vacuum command".into()));
+            }
+            ast::Statement::CopyFrom {
+                table_name,
+                path,
+                header,
+            } => {
+                self.txn.must_get_table(table_name.clone())?;
+                Node::CopyFrom {
+                    table_name,
+                    path,
+                    header,
+                }
+            }
+            ast::Statement::CopyTo { table_name, path } => Node::CopyTo {
                 table_name: table_name.clone(),
+                path,
                 source: Box::new(Node::Scan {
                     table_name,
-                    filter: where_clause,
+                    alias: None,
+                    filter: None,
                 }),
             },
-            ast::Statement::Begin | ast::Statement::Commit | ast::Statement::Rollback => {
-                return Err(Error::Internal("unexpected transaction command".into()));
+        })
+    }
+
+    // 构建单表扫描节点
+    // 如果过滤条件中有主键和常量的等值比较，则直接按主键读取数据；
+    // 如果有索引列和常量的等值比较，则使用索引扫描代替全表扫描。其余条件在读取数据之后过滤
+    fn build_scan(
+        &self,
+        table_name: String,
+        alias: Option<String>,
+        filter: Option<Expression>,
+    ) -> Result<Node> {
+        let filter = match filter {
+            Some(filter) => filter,
+            None => {
+                return Ok(Node::Scan {
+                    table_name,
+                    alias,
+                    filter: None,
+                });
+            }
+        };
+        let table = match self.txn.get_table(table_name.clone())? {
+            Some(table) => table,
+            None => {
+                return Ok(Node::Scan {
+                    table_name,
+                    alias,
+                    filter: Some(filter),
+                });
+            }
+        };
+        // 有别名时条件中的列使用别名作为前缀
+        let qualifier = alias.clone().unwrap_or_else(|| table_name.clone());
+
+        // 把 AND 连接的条件拆开，找到可以使用主键或索引的等值条件
+        let mut predicates = Vec::new();
+        split_and(filter, &mut predicates);
+
+        let node = if let Some((i, _, value)) =
+            find_equality(&table, &qualifier, &predicates, |c| c.primary_key)
+        {
+            predicates.remove(i);
+            Node::KeyLookup {
+                table_name,
+                alias,
+                value,
             }
+        } else if let Some((i, column, value)) =
+            find_equality(&table, &qualifier, &predicates, |c| {
+                table.get_index(&c.name).is_some()
+            })
+        {
+            predicates.remove(i);
+            Node::IndexScan {
+                table_name,
+                alias,
+                column,
+                value,
+            }
+        } else {
+            return Ok(Node::Scan {
+                table_name,
+                alias,
+                filter: join_and(predicates),
+            });
+        };
+
+        Ok(match join_and(predicates) {
+            Some(predicate) => Node::Filter {
+                source: Box::new(node),
+                predicate,
+            },
+            None => node,
         })
     }
 
-    fn build_from_item(&self, item: ast::FromItem, filter: &Option<Expression>) -> Result<Node> {
+    // 把 WHERE 中 AND 连接的 expr [NOT] IN (SELECT ...) 条件拆出来，子查询规划为单独的节点
+    // 返回剩下的条件，以及拆出来的子查询条件
+    // 子查询不能引用外层查询的列，OR 等其他位置的子查询不支持
+    fn build_subqueries(
+        &self,
+        where_clause: Option<Expression>,
+    ) -> Result<(Option<Expression>, Vec<SubqueryFilter>)> {
+        let Some(where_clause) = where_clause else {
+            return Ok((None, Vec::new()));
+        };
+        let mut predicates = Vec::new();
+        split_and(where_clause, &mut predicates);
+        let mut rest = Vec::new();
+        let mut subqueries = Vec::new();
+        for predicate in predicates {
+            match split_in_subquery(predicate) {
+                Ok((expr, stmt, negated)) => {
+                    reject_subqueries([&expr])?;
+                    subqueries.push((expr, self.build_statment(stmt)?, negated));
+                }
+                Err(predicate) => rest.push(predicate),
+            }
+        }
+        reject_subqueries(&rest)?;
+        Ok((join_and(rest), subqueries))
+    }
+
+    // 把 ORDER BY 中的列序号替换为排序使用的表达式，列序号从 1 开始
+    // 没有聚合时排序在投影之前执行，替换为 SELECT 中的表达式，SELECT * 时为 FROM 中对应的列；
+    // 有聚合时排序在聚合之后执行，替换为聚合结果中对应的列名
+    fn resolve_order_by(
+        &self,
+        order_by: Vec<(Expression, OrderDirection)>,
+        select: &[(Expression, Option<String>)],
+        from: &Option<ast::FromItem>,
+        group_by: &[Expression],
+    ) -> Result<Vec<(Expression, OrderDirection)>> {
+        let has_agg = !select.is_empty()
+            && (!group_by.is_empty()
+                || select
+                    .iter()
+                    .any(|(expr, _)| matches!(expr, Expression::Function(_, _))));
+        // 列序号按照 * 和 t.* 展开之后的列计算
+        let expanded;
+        let select = match (from, select.iter().any(|(e, _)| e.contains_wildcard())) {
+            (Some(from), true) => {
+                let from_columns = self.columns_of_from_item(from)?;
+                let mut items = Vec::new();
+                for (expr, alias) in select {
+                    match expr {
+                        Expression::Wildcard(_) => items.extend(
+                            expr.expand_wildcard(&from_columns)?
+                                .into_iter()
+                                .map(|c| (Expression::Field(c), None)),
+                        ),
+                        expr => items.push((expr.clone(), alias.clone())),
+                    }
+                }
+                expanded = items;
+                &expanded[..]
+            }
+            _ => select,
+        };
+        let mut resolved = Vec::with_capacity(order_by.len());
+        for (expr, direction) in order_by {
+            let position = match expr {
+                Expression::Consts(ast::Consts::Integer(position)) => position,
+                expr => {
+                    resolved.push((expr, direction));
+                    continue;
+                }
+            };
+            let from_columns = match (select.is_empty(), from) {
+                (true, Some(from)) => self.columns_of_from_item(from)?,
+                _ => Vec::new(),
+            };
+            let len = if select.is_empty() {
+                from_columns.len()
+            } else {
+                select.len()
+            };
+            if position < 1 || position as usize > len {
+                return Err(Error::Plan(format!(
+                    "ORDER BY position {} is not in select list",
+                    position
+                )));
+            }
+            let index = position as usize - 1;
+
+            let expr = if select.is_empty() {
+                Expression::Field(from_columns[index].clone())
+            } else if !has_agg {
+                select[index].0.clone()
+            } else {
+                match &select[index] {
+                    (Expression::Function(_, _), _) if group_by.is_empty() => {
+                        return Err(Error::Plan(format!(
+                            "ORDER BY position {} refers to aggregate {} in a query without GROUP BY",
+                            position, select[index].0
+                        )));
+                    }
+                    (_, Some(alias)) => Expression::Field(alias.clone()),
+                    (Expression::Function(func_name, _), None) => {
+                        Expression::Field(func_name.clone())
+                    }
+                    (expr, None) => expr.clone(),
+                }
+            };
+            resolved.push((expr, direction));
+        }
+        Ok(resolved)
+    }
+
+    // FROM 子句输出的列名，带有表名（或别名）前缀，和 build_from_item 构建的节点输出的列一致
+    fn columns_of_from_item(&self, item: &ast::FromItem) -> Result<Vec<String>> {
         Ok(match item {
-            ast::FromItem::Table { name } => Node::Scan {
+            ast::FromItem::Table { name, alias } => self
+                .txn
+                .must_get_table(name.clone())?
+                .qualified_columns(alias.as_ref().unwrap_or(name)),
+            ast::FromItem::Join {
+                left,
+                right,
+                join_type,
+                ..
+            } => {
+                // Right Join 交换了左右两边
+                let (left, right) = match join_type {
+                    JoinType::Right => (right, left),
+                    _ => (left, right),
+                };
+                let mut columns = self.columns_of_from_item(left)?;
+                columns.extend(self.columns_of_from_item(right)?);
+                columns
+            }
+        })
+    }
+
+    fn build_from_item(&self, item: ast::FromItem) -> Result<Node> {
+        Ok(match item {
+            ast::FromItem::Table { name, alias } => Node::Scan {
                 table_name: name,
-                filter: filter.clone(),
+                alias,
+                filter: None,
             },
             ast::FromItem::Join {
                 left,
@@ -213,8 +570,8 @@ impl Planner {
                 };
 
                 Node::NestedLoopJoin {
-                    left: Box::new(self.build_from_item(*left, filter)?),
-                    right: Box::new(self.build_from_item(*right, filter)?),
+                    left: Box::new(self.build_from_item(*left)?),
+                    right: Box::new(self.build_from_item(*right)?),
                     predicate,
                     outer,
                 }
@@ -222,3 +579,189 @@ impl Planner {
         })
     }
 }
+
+// 在 node 的输出上依次执行子查询条件的过滤
+fn filter_subqueries(node: Node, subqueries: Vec<SubqueryFilter>) -> Node {
+    subqueries
+        .into_iter()
+        .fold(node, |node, (expr, subquery, negated)| Node::InSubquery {
+            source: Box::new(node),
+            expr,
+            subquery: Box::new(subquery),
+            negated,
+        })
+}
+
+// 拆出 expr IN (SELECT ...) 中的表达式和子查询，NOT 每出现一次对结果取反一次
+// 不是子查询条件时原样返回
+fn split_in_subquery(
+    predicate: Expression,
+) -> std::result::Result<(Expression, ast::Statement, bool), Expression> {
+    match predicate {
+        Expression::Operation(Operation::In(expr, mut list))
+            if matches!(list.as_slice(), [Expression::Subquery(_)]) =>
+        {
+            match list.pop() {
+                Some(Expression::Subquery(stmt)) => Ok((*expr, *stmt, false)),
+                _ => unreachable!("IN list has exactly one subquery"),
+            }
+        }
+        Expression::Operation(Operation::Not(expr)) => match split_in_subquery(*expr) {
+            Ok((expr, stmt, negated)) => Ok((expr, stmt, !negated)),
+            Err(expr) => Err(Expression::Operation(Operation::Not(Box::new(expr)))),
+        },
+        predicate => Err(predicate),
+    }
+}
+
+// 子查询只能作为 WHERE 中 AND 连接的 IN 条件，例如 WHERE a IN (SELECT ...) AND b > 1
+// 聚合的结果中每个分组只有分组的列和聚合函数的值，select 中只能直接引用它们
+// 例如 select g + 1 from t group by g、select count(*) + 1 from t 还不支持
+fn check_aggregate_select(
+    select: &[(Expression, Option<String>)],
+    group_by: &[Expression],
+) -> Result<()> {
+    if group_by.is_empty() && !select.iter().any(|(e, _)| e.contains_aggregate()) {
+        return Ok(());
+    }
+    match select
+        .iter()
+        .find(|(e, _)| !matches!(e, Expression::Field(_) | Expression::Function(_, _)))
+    {
+        Some((expr, _)) => Err(Error::NotSupported(format!(
+            "expression {} over GROUP BY columns or aggregate functions, only columns and aggregate functions can be selected",
+            expr
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn reject_subqueries<'a>(exprs: impl IntoIterator<Item = &'a Expression>) -> Result<()> {
+    match exprs.into_iter().find(|e| e.contains_subquery()) {
+        Some(expr) => Err(Error::Plan(format!(
+            "subquery is only supported as a WHERE condition expr [NOT] IN (SELECT ...), got {}",
+            expr
+        ))),
+        None => Ok(()),
+    }
+}
+
+// Join 条件中的表达式
+fn join_predicates(item: &ast::FromItem) -> Vec<&Expression> {
+    match item {
+        ast::FromItem::Table { .. } => Vec::new(),
+        ast::FromItem::Join {
+            left,
+            right,
+            predicate,
+            ..
+        } => {
+            let mut exprs = join_predicates(left);
+            exprs.extend(join_predicates(right));
+            exprs.extend(predicate);
+            exprs
+        }
+    }
+}
+
+// 把 a AND b AND c 拆分为 [a, b, c]
+fn split_and(expr: Expression, predicates: &mut Vec<Expression>) {
+    match expr {
+        Expression::Operation(Operation::And(l, r)) => {
+            split_and(*l, predicates);
+            split_and(*r, predicates);
+        }
+        expr => predicates.push(expr),
+    }
+}
+
+// 把 [a, b, c] 重新组合为 a AND b AND c
+fn join_and(predicates: Vec<Expression>) -> Option<Expression> {
+    predicates
+        .into_iter()
+        .reduce(|l, r| Expression::Operation(Operation::And(Box::new(l), Box::new(r))))
+}
+
+// 找到 column = const 形式的条件，并且 column 满足 accept，返回条件的位置、列名和常量值
+fn find_equality(
+    table: &Table,
+    qualifier: &str,
+    predicates: &[Expression],
+    accept: impl Fn(&schema::Column) -> bool,
+) -> Option<(usize, String, Value)> {
+    predicates.iter().enumerate().find_map(|(i, expr)| {
+        let (column, value) = match expr {
+            Expression::Operation(Operation::Equal(l, r)) => match (&**l, &**r) {
+                (Expression::Field(col), Expression::Consts(c))
+                | (Expression::Consts(c), Expression::Field(col)) => {
+                    (col, Value::from_expression(Expression::Consts(c.clone())))
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        // 列名可以带本表的表名（或别名）前缀，例如 t.a
+        let column = match column.split_once('.') {
+            Some((t, c)) if t == qualifier => c,
+            Some(_) => return None,
+            None => column.as_str(),
+        };
+        let col = table.columns.iter().find(|c| c.name == column)?;
+        if !accept(col) {
+            return None;
+        }
+        // 类型不一致时（例如整数列和浮点数比较）需要按值比较，不能直接按 key 读取
+        match value.datatype() {
+            Some(dt) if dt == col.datatype => Some((i, col.name.clone(), value)),
+            _ => None,
+        }
+    })
+}
+
+// 计算列的默认值并转换为列的类型，在建表时就报告类型不一致的默认值
+fn evaluate_default(col_name: &str, datatype: &DataType, expr: &Expression) -> Result<Value> {
+    let invalid = |reason: String| {
+        Error::Plan(format!(
+            "invalid default value {} for column {}: {}",
+            expr, col_name, reason
+        ))
+    };
+    let value = evaluate_expr_single(expr, &[], &[])
+        .and_then(|v| v.coerce(datatype))
+        .map_err(|e| invalid(e.to_string()))?;
+    match value.datatype() {
+        Some(dt) if dt != *datatype => Err(invalid(format!("expects {}, got {}", datatype, dt))),
+        _ => Ok(value),
+    }
+}
+
+// 计算 limit/offset 的值，必须是不引用任何列的非负整数表达式，例如 10、2 * 5
+fn evaluate_count(clause: &str, expr: &Expression) -> Result<usize> {
+    match evaluate_expr_single(expr, &[], &[]) {
+        Ok(Value::Integer(i)) if i >= 0 => Ok(i as usize),
+        _ => Err(Error::Plan(format!(
+            "{} must be a non-negative integer, got {}",
+            clause, expr
+        ))),
+    }
+}
+
+// Join 中的表名（有别名时为别名）不能重复，否则无法区分列来自哪张表
+fn check_table_names(item: &ast::FromItem, names: &mut HashSet<String>) -> Result<()> {
+    match item {
+        ast::FromItem::Table { name, alias } => {
+            let name = alias.as_ref().unwrap_or(name);
+            if !names.insert(name.clone()) {
+                return Err(Error::Plan(format!(
+                    "table name {} specified more than once",
+                    name
+                )));
+            }
+            Ok(())
+        }
+        ast::FromItem::Join { left, right, .. } => {
+            check_table_names(left, names)?;
+            check_table_names(right, names)
+        }
+    }
+}
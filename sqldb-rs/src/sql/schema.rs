@@ -4,13 +4,17 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     error::{Error, Result},
-    sql::types::{DataType, Row, Value},
+    sql::{
+        parser::lexer::quote_ident,
+        types::{DataType, Row, Value},
+    },
 };
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Table {
     pub name: String,
     pub columns: Vec<Column>,
+    pub indexes: Vec<Index>,
 }
 
 impl Table {
@@ -18,7 +22,7 @@ impl Table {
     pub fn validate(&self) -> Result<()> {
         if self.columns.is_empty() {
             // 校验是否有列信息
-            return Err(Error::Internal(format!(
+            return Err(Error::InvalidDefinition(format!(
                 "table {} has no columns",
                 self.name
             )));
@@ -28,13 +32,13 @@ impl Table {
         match self.columns.iter().filter(|c| c.primary_key).count() {
             1 => {}
             0 => {
-                return Err(Error::Internal(format!(
+                return Err(Error::InvalidDefinition(format!(
                     "No primary key found for table {}",
                     self.name
                 )));
             }
             _ => {
-                return Err(Error::Internal(format!(
+                return Err(Error::InvalidDefinition(format!(
                     "Multiple primary keys found for table {}",
                     self.name
                 )));
@@ -45,20 +49,37 @@ impl Table {
         for column in &self.columns {
             // 主键不能为空
             if column.primary_key && column.nullable {
-                return Err(Error::Internal(format!(
+                return Err(Error::InvalidDefinition(format!(
                     "Primary key {} cannot be null for table {}",
                     column.name, self.name
                 )));
             }
+            // 自增列必须是整数类型的主键，并且不能有默认值
+            if column.auto_increment {
+                if !column.primary_key || column.datatype != DataType::Integer {
+                    return Err(Error::InvalidDefinition(format!(
+                        "AUTOINCREMENT column {} of table {} must be an integer primary key",
+                        column.name, self.name
+                    )));
+                }
+                if column.default.is_some() {
+                    return Err(Error::InvalidDefinition(format!(
+                        "AUTOINCREMENT column {} of table {} cannot have a default value",
+                        column.name, self.name
+                    )));
+                }
+            }
             // 校验默认值是否和列类型一致
             if let Some(default_value) = &column.default {
                 match default_value.datatype() {
                     Some(dt) => {
                         if dt != column.datatype {
-                            return Err(Error::Internal(format!(
-                                "Default value for column {} mismatch in table {}",
-                                column.name, self.name
-                            )));
+                            return Err(Error::TypeMismatch {
+                                table: self.name.clone(),
+                                column: column.name.clone(),
+                                expected: column.datatype.to_string(),
+                                found: format!("{} default value {}", dt, default_value),
+                            });
                         }
                     }
                     None => {}
@@ -79,14 +100,33 @@ impl Table {
         Ok(row[position].clone())
     }
 
+    // 获取列上的索引
+    pub fn get_index(&self, col_name: &str) -> Option<&Index> {
+        self.indexes.iter().find(|i| i.column == col_name)
+    }
+
     pub fn get_col_index(&self, col_name: &str) -> Result<usize> {
         self.columns
             .iter()
             .position(|c| c.name == col_name)
-            .ok_or(Error::Internal(format!(
-                "column {} does not exist",
-                col_name
-            )))
+            .ok_or_else(|| Error::ColumnNotFound {
+                table: Some(self.name.clone()),
+                column: col_name.to_string(),
+                available: Vec::new(),
+            })
+    }
+
+    // 扫描结果中的列名，带有表名（或别名）前缀，例如 t.a
+    pub fn qualified_columns(&self, qualifier: &str) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|c| format!("{}.{}", qualifier, c.name))
+            .collect()
+    }
+
+    // 扫描结果中每一列的类型，和 qualified_columns 一一对应
+    pub fn column_types(&self) -> Vec<Option<DataType>> {
+        self.columns.iter().map(|c| Some(c.datatype)).collect()
     }
 }
 
@@ -98,10 +138,33 @@ impl Display for Table {
             .map(|c| format!("{}", c))
             .collect::<Vec<_>>()
             .join(",\n");
-        write!(f, "CREATE TABLE {} (\n{})", self.name, all_col_desc)
+        // 表名、列名等在需要时加上双引号，输出的语句可以直接执行
+        write!(
+            f,
+            "CREATE TABLE {} (\n{})",
+            quote_ident(&self.name),
+            all_col_desc
+        )?;
+        for index in &self.indexes {
+            write!(
+                f,
+                "\nCREATE INDEX {} ON {} ({})",
+                quote_ident(&index.name),
+                quote_ident(&self.name),
+                quote_ident(&index.column)
+            )?;
+        }
+        Ok(())
     }
 }
 
+// 二级索引，索引数据中保存列值到主键的映射
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Index {
+    pub name: String,
+    pub column: String,
+}
+
 // 关联到 Plan
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Column {
@@ -110,19 +173,33 @@ pub struct Column {
     pub nullable: bool,
     pub default: Option<Value>,
     pub primary_key: bool,
+    pub unique: bool,
+    // 插入时没有给出值或者为 NULL 时，使用表的自增计数器分配值
+    pub auto_increment: bool,
 }
 
 impl Display for Column {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut col_desc = format!("    {} {:?}", self.name, self.datatype);
+        let mut col_desc = format!("    {} {}", quote_ident(&self.name), self.datatype);
         if self.primary_key {
             col_desc += " PRIMARY KEY";
-        }
-        if !self.nullable && !self.primary_key {
+        } else if self.nullable {
+            col_desc += " NULL";
+        } else {
             col_desc += " NOT NULL";
         }
-        if let Some(v) = &self.default {
-            col_desc += &format!(" DEFAULT {}", v.to_string());
+        if self.unique && !self.primary_key {
+            col_desc += " UNIQUE";
+        }
+        if self.auto_increment {
+            col_desc += " AUTOINCREMENT";
+        }
+        match &self.default {
+            Some(v @ (Value::String(_) | Value::Timestamp(_))) => {
+                col_desc += &format!(" DEFAULT '{}'", v)
+            }
+            Some(v) => col_desc += &format!(" DEFAULT {}", v),
+            None => {}
         }
         write!(f, "{}", col_desc)
     }
@@ -2,14 +2,28 @@ use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::hash::Hash;
 
+use crate::error::{Error, Result};
 use crate::sql::parser::ast::{Consts, Expression};
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
 pub enum DataType {
     Boolean,
     Integer,
     Float,
     String,
+    Timestamp,
+}
+
+impl Display for DataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Boolean => write!(f, "BOOLEAN"),
+            Self::Integer => write!(f, "INTEGER"),
+            Self::Float => write!(f, "FLOAT"),
+            Self::String => write!(f, "STRING"),
+            Self::Timestamp => write!(f, "TIMESTAMP"),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
@@ -19,6 +33,8 @@ pub enum Value {
     Integer(i64),
     Float(f64),
     String(String),
+    // 时间戳，保存 UTC 1970-01-01 00:00:00 以来的秒数
+    Timestamp(i64),
 }
 
 impl Value {
@@ -33,6 +49,18 @@ impl Value {
         }
     }
 
+    // 转换为常量表达式，用于绑定参数，时间戳转换为字符串，使用时再按照列的类型转换
+    pub fn to_expression(&self) -> Expression {
+        match self {
+            Self::Null => Consts::Null.into(),
+            Self::Boolean(b) => Consts::Boolean(*b).into(),
+            Self::Integer(i) => Consts::Integer(*i).into(),
+            Self::Float(f) => Consts::Float(*f).into(),
+            Self::String(s) => Consts::String(s.clone()).into(),
+            Self::Timestamp(t) => Consts::String(format_timestamp(*t)).into(),
+        }
+    }
+
     pub fn datatype(&self) -> Option<DataType> {
         match self {
             Self::Null => None,
@@ -40,6 +68,29 @@ impl Value {
             Self::Integer(_) => Some(DataType::Integer),
             Self::Float(_) => Some(DataType::Float),
             Self::String(_) => Some(DataType::String),
+            Self::Timestamp(_) => Some(DataType::Timestamp),
+        }
+    }
+
+    // 转换为 JSON 中的值，时间戳使用和表格展示相同的字符串格式
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Null => serde_json::Value::Null,
+            Self::Boolean(b) => (*b).into(),
+            Self::Integer(i) => (*i).into(),
+            Self::Float(f) => (*f).into(),
+            Self::String(s) => s.as_str().into(),
+            Self::Timestamp(t) => format_timestamp(*t).into(),
+        }
+    }
+
+    // 把值转换为列的类型，支持把字符串转换为时间戳、整数转换为浮点数，其他情况保持原值
+    pub fn coerce(self, datatype: &DataType) -> Result<Value> {
+        match (self, datatype) {
+            (Self::String(s), DataType::Timestamp) => Ok(Self::Timestamp(parse_timestamp(&s)?)),
+            // 整数可以无损地用作浮点数，其他类型不一致的值由调用方报错
+            (Self::Integer(i), DataType::Float) => Ok(Self::Float(i as f64)),
+            (v, _) => Ok(v),
         }
     }
 }
@@ -53,6 +104,7 @@ impl Display for Value {
             Self::Integer(i) => write!(f, "{}", i),
             Self::Float(d) => write!(f, "{}", d),
             Self::String(s) => write!(f, "{}", s),
+            Self::Timestamp(t) => write!(f, "{}", format_timestamp(*t)),
         }
     }
 }
@@ -69,6 +121,7 @@ impl PartialOrd for Value {
             (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
             (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
             (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+            (Value::Timestamp(a), Value::Timestamp(b)) => a.partial_cmp(b),
             (_, _) => None, // 不可比较
         }
     }
@@ -88,12 +141,18 @@ impl Hash for Value {
             }
             Value::Float(v) => {
                 state.write_u8(3);
+                // 0.0 和 -0.0 相等，哈希也要相同
+                let v = if *v == 0.0 { 0.0 } else { *v };
                 v.to_be_bytes().hash(state);
             }
             Value::String(v) => {
                 state.write_u8(4);
                 v.hash(state);
             }
+            Value::Timestamp(v) => {
+                state.write_u8(5);
+                v.hash(state);
+            }
         }
     }
 }
@@ -101,3 +160,229 @@ impl Hash for Value {
 impl Eq for Value {}
 
 pub type Row = Vec<Value>;
+
+// 按需读取的数据行，读取时可能出错，生命周期通常和事务绑定
+pub type Rows<'a> = Box<dyn Iterator<Item = Result<Row>> + 'a>;
+
+// 查询结果中浮点数默认展示的有效数字位数
+pub const DEFAULT_FLOAT_PRECISION: usize = 12;
+
+// 按有效数字位数展示浮点数，用于查询结果的展示，不影响存储的值
+// 去掉小数末尾的 0，例如 2.50 展示为 2.5，0.1 + 0.2 展示为 0.3；-0 展示为 0
+// 数值太大或者太小时使用科学计数法，例如 1e20；无穷大展示为 Infinity 和 -Infinity
+pub fn format_float(f: f64, precision: usize) -> String {
+    let precision = precision.max(1);
+    if f.is_nan() {
+        return "NaN".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    if f == 0.0 {
+        return "0".to_string();
+    }
+
+    // 先按有效数字四舍五入，再根据指数决定展示的方式
+    let sci = format!("{:.*e}", precision - 1, f);
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let exp = exp.parse::<i32>().unwrap_or(0);
+    if exp < -5 || exp >= precision as i32 {
+        return format!("{}e{}", trim_zeros(mantissa), exp);
+    }
+    let decimals = (precision as i32 - 1 - exp).max(0) as usize;
+    trim_zeros(&format!("{:.*}", decimals, f)).to_string()
+}
+
+// 去掉小数末尾的 0 和小数点
+fn trim_zeros(s: &str) -> &str {
+    match s.contains('.') {
+        true => s.trim_end_matches('0').trim_end_matches('.'),
+        false => s,
+    }
+}
+
+// 解析时间戳字符串，支持 YYYY-MM-DD、YYYY-MM-DD HH:MM:SS 和 YYYY-MM-DDTHH:MM:SS 三种格式
+pub fn parse_timestamp(s: &str) -> Result<i64> {
+    let invalid = || Error::Parse(format!("invalid timestamp '{}'", s));
+
+    let (date, time) = match s.trim().split_once([' ', 'T']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (s.trim(), None),
+    };
+
+    // 每一部分都必须是固定长度的数字
+    let parse_parts = |part: &str, sep: char, lens: [usize; 3]| -> Result<[i64; 3]> {
+        let mut nums = [0; 3];
+        let mut iter = part.split(sep);
+        for (i, len) in lens.iter().enumerate() {
+            let num = iter.next().ok_or_else(invalid)?;
+            if num.len() != *len || !num.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            nums[i] = num.parse()?;
+        }
+        if iter.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(nums)
+    };
+
+    let [year, month, day] = parse_parts(date, '-', [4, 2, 2])?;
+    let [hour, minute, second] = match time {
+        Some(time) => parse_parts(time, ':', [2, 2, 2])?,
+        None => [0, 0, 0],
+    };
+    if !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(invalid());
+    }
+
+    Ok(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+// 按 ISO-8601 格式展示时间戳，日期和时间之间用空格分隔，例如 2024-01-01 08:30:00
+pub fn format_timestamp(ts: i64) -> String {
+    let (year, month, day) = civil_from_days(ts.div_euclid(86400));
+    let secs = ts.rem_euclid(86400);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// 公历日期转换为 1970-01-01 以来的天数
+// 参考 http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// 1970-01-01 以来的天数转换为公历日期
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{BuildHasher, RandomState};
+
+    use super::{DEFAULT_FLOAT_PRECISION, Value, format_float, format_timestamp, parse_timestamp};
+    use crate::error::Result;
+
+    #[test]
+    fn test_timestamp() -> Result<()> {
+        assert_eq!(parse_timestamp("1970-01-01")?, 0);
+        assert_eq!(parse_timestamp("1970-01-02 00:00:01")?, 86401);
+        assert_eq!(parse_timestamp("1969-12-31T23:59:59")?, -1);
+        assert_eq!(parse_timestamp("2024-02-29 12:30:45")?, 1709209845);
+
+        for s in [
+            "1970-01-01 00:00:00",
+            "1969-12-31 23:59:59",
+            "2000-02-29 00:00:00",
+            "2024-01-01 08:30:00",
+            "1900-03-01 01:02:03",
+            "9999-12-31 23:59:59",
+            "0001-01-01 00:00:00",
+        ] {
+            assert_eq!(format_timestamp(parse_timestamp(s)?), s);
+        }
+
+        for s in [
+            "",
+            "2024",
+            "2024-1-01",
+            "2024-13-01",
+            "2023-02-29",
+            "2024-01-01 24:00:00",
+            "2024-01-01 00:60:00",
+            "2024-01-01 00:00",
+            "2024-01-01 00:00:00:00",
+            "2024-01-01x00:00:00",
+            "abcd-01-01",
+        ] {
+            assert!(parse_timestamp(s).is_err(), "{}", s);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_float() {
+        let format = |f: f64| format_float(f, DEFAULT_FLOAT_PRECISION);
+        assert_eq!(format(2.50), "2.5");
+        assert_eq!(format(0.1 + 0.2), "0.3");
+        assert_eq!(format(1.0 / 3.0), "0.333333333333");
+        assert_eq!(format(2.0 / 3.0), "0.666666666667");
+        assert_eq!(format(100.0), "100");
+        assert_eq!(format(-1.5), "-1.5");
+        assert_eq!(format(-0.0), "0");
+        assert_eq!(format(123456789.125), "123456789.125");
+        assert_eq!(format(0.0001), "0.0001");
+        assert_eq!(format(0.000001234), "1.234e-6");
+        assert_eq!(format(1e20), "1e20");
+        assert_eq!(format(-2.5e15), "-2.5e15");
+        assert_eq!(format(999999999999.9), "1e12");
+        assert_eq!(format(f64::INFINITY), "Infinity");
+        assert_eq!(format(f64::NEG_INFINITY), "-Infinity");
+        assert_eq!(format(f64::NAN), "NaN");
+
+        assert_eq!(format_float(1.0 / 3.0, 3), "0.333");
+        assert_eq!(format_float(2.6, 1), "3");
+        assert_eq!(format_float(12345.0, 3), "1.23e4");
+    }
+
+    #[test]
+    fn test_float_storage() -> Result<()> {
+        // 展示的精度不影响存储，序列化前后的值完全相同
+        for f in [0.1 + 0.2, 1.0 / 3.0, -0.0, 1e-300, f64::MAX] {
+            let value = Value::Float(f);
+            let decoded: Value = bincode::deserialize(&bincode::serialize(&value)?)?;
+            match decoded {
+                Value::Float(d) => assert_eq!(d.to_bits(), f.to_bits()),
+                v => panic!("unexpected value {:?}", v),
+            }
+        }
+
+        // 0.0 和 -0.0 相等，哈希也相同
+        let state = RandomState::new();
+        assert_eq!(Value::Float(0.0), Value::Float(-0.0));
+        assert_eq!(
+            state.hash_one(Value::Float(0.0)),
+            state.hash_one(Value::Float(-0.0))
+        );
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     fs::{File, OpenOptions},
     io::{BufReader, BufWriter, Read, Seek, Write},
     path::PathBuf,
+    time::{Duration, Instant},
 };
 
 // <key_binary, (file_value_binary_offset, val_binary_size)>
@@ -10,25 +11,151 @@ pub type KeyDir = BTreeMap<Vec<u8>, (u64, u32)>;
 
 use fs4::FileExt;
 
-use crate::{error::Result, storage::engine::EngineIterator};
+use crate::{
+    error::{Error, Result},
+    storage::engine::{EngineIterator, EngineStats, WriteBatch, normalize_range},
+};
 
+// 旧格式的记录头：key len(4) + val len(4)
 const LOG_HEADER_SIZE: u32 = 8;
+// 带校验和的记录头：key len(4) + val len(4) + 长度的 crc32(4) + crc32(4)
+// 长度单独校验，损坏的长度不会被当成写入到一半的记录
+const LOG_HEADER_SIZE_CRC: u32 = 16;
+// 新建的日志文件以一个格式版本号开头，旧格式的文件没有版本号，第一个字节是 key len 的最高位，总是 0
+const LOG_FORMAT_VERSION: u8 = 1;
+
+// 自动压缩的配置，日志文件的大小和垃圾数据的比例都达到阈值时，在写入后触发压缩
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompactionOptions {
+    // 垃圾数据（被覆盖和删除的数据）占日志文件大小的最小比例
+    pub min_garbage_ratio: f64,
+    // 触发压缩的日志文件最小字节数
+    pub min_file_size: u64,
+}
+
+impl Default for CompactionOptions {
+    fn default() -> Self {
+        Self {
+            min_garbage_ratio: 0.5,
+            min_file_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+// 写入日志之后什么时候调用 sync_data 把数据持久化到磁盘
+// 不论哪种策略，事务提交时都会通过 Engine::sync 持久化
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SyncPolicy {
+    // 每次写入之后
+    Always,
+    // 每写入 N 条记录
+    EveryN(usize),
+    // 写入时距离上次 sync 超过了指定的毫秒数
+    IntervalMs(u64),
+    // 写入时不 sync，只在提交事务时 sync
+    #[default]
+    Never,
+}
 
 // 磁盘存储引擎定义
 pub struct DiskEngine {
     keydir: KeyDir,
-    // +-------------+-------------+----------------+----------------+​
-    // | key len(4)    val len(4)     key(varint)       val(varint)  |​
-    // +-------------+-------------+----------------+----------------+
+    // +---------+-------------+-------------+------------------+-----------+----------------+----------------+​
+    // | version | key len(4)    val len(4)    header crc32(4)    crc32(4)     key(varint)       val(varint)  | ...
+    // +---------+-------------+-------------+------------------+-----------+----------------+----------------+
+    // header crc32 覆盖 key len 和 val len，crc32 覆盖 key len、val len、key 和 value
+    // 旧格式的文件没有 version 和两个 crc32
     log: Log,
+    options: CompactionOptions,
+    // 有效数据的字节数
+    live_bytes: u64,
+    // 日志文件的总字节数
+    total_bytes: u64,
+    // 已经执行的压缩次数
+    compactions: usize,
+    // 打开时从日志末尾截断的不完整记录的字节数
+    recovered_bytes: u64,
 }
 
 impl DiskEngine {
     pub fn new(file_path: PathBuf) -> Result<Self> {
+        Self::with_options(file_path, CompactionOptions::default())
+    }
+
+    pub fn with_options(file_path: PathBuf, options: CompactionOptions) -> Result<Self> {
         let mut log = Log::new(file_path)?;
         // 从 log 中去恢复的 keydir
-        let keydir = log.build_keydir()?;
-        Ok(Self { keydir, log })
+        let (keydir, recovered_bytes) = log.build_keydir()?;
+        let live_bytes = keydir
+            .iter()
+            .map(|(key, (_, val_size))| log.entry_size(key, *val_size))
+            .sum();
+        let total_bytes = log.file.metadata()?.len();
+        Ok(Self {
+            keydir,
+            log,
+            options,
+            live_bytes,
+            total_bytes,
+            compactions: 0,
+            recovered_bytes,
+        })
+    }
+
+    // 打开时丢弃的日志末尾不完整（写入过程中进程退出）的字节数，日志完整时为 0
+    pub fn recovered_bytes(&self) -> u64 {
+        self.recovered_bytes
+    }
+
+    // 已经执行的压缩次数，包括自动触发和手动调用的
+    pub fn compaction_count(&self) -> usize {
+        self.compactions
+    }
+
+    // 日志文件 flush 的次数
+    pub fn flush_count(&self) -> u64 {
+        self.log.flush_count
+    }
+
+    // 设置写入时的 sync 策略
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.log.sync_policy = policy;
+        self
+    }
+
+    // 日志文件 sync 到磁盘的次数，不包括压缩时新文件的 sync
+    pub fn sync_count(&self) -> u64 {
+        self.log.sync_count
+    }
+
+    // 写入日志之后更新内存索引和数据大小的统计，val_size 为 None 表示删除
+    fn apply_entry(&mut self, key: Vec<u8>, val_size: Option<u32>, offset: u64, size: u32) {
+        self.total_bytes += size as u64;
+        let old = match val_size {
+            Some(val_size) => {
+                self.live_bytes += size as u64;
+                // keydir 中的value表示数据value的偏移量
+                self.keydir.insert(
+                    key.clone(),
+                    (offset + size as u64 - val_size as u64, val_size),
+                )
+            }
+            None => self.keydir.remove(&key),
+        };
+        if let Some((_, old_size)) = old {
+            self.live_bytes -= self.log.entry_size(&key, old_size);
+        }
+    }
+
+    // 写入之后检查是否需要自动压缩
+    fn maybe_compact(&mut self) -> Result<()> {
+        let garbage = self.total_bytes.saturating_sub(self.live_bytes);
+        if self.total_bytes >= self.options.min_file_size
+            && garbage as f64 >= self.total_bytes as f64 * self.options.min_garbage_ratio
+        {
+            self.compact()?;
+        }
+        Ok(())
     }
 
     pub fn new_compact(file_path: PathBuf) -> Result<Self> {
@@ -37,13 +164,15 @@ impl DiskEngine {
         Ok(eng)
     }
 
-    // 使用 keydir 的信息构建新的临时 keydir 和 log 文件
-    fn compact(&mut self) -> Result<()> {
-        // 新打开一个临时日志文件
+    // 使用 keydir 的信息构建新的临时 keydir 和 log 文件，返回回收的字节数
+    pub fn compact(&mut self) -> Result<u64> {
+        // 新打开一个临时日志文件，清空上次压缩中断时可能残留的数据
+        // 压缩后的文件总是使用最新的格式，旧格式的文件在压缩时升级
         let mut new_path = self.log.file_path.clone();
         new_path.set_extension("compact");
 
         let mut new_log = Log::new(new_path)?;
+        new_log.reset()?;
         let mut new_keydir = KeyDir::new();
 
         // 重写数据到临时文件中
@@ -63,16 +192,41 @@ impl DiskEngine {
             );
         }
 
-        // 将临时文件更改为正式文件
-        // std::fs::rename(new_log.file_path, self.log.file_path);
-        std::fs::rename(&new_log.file_path, &self.log.file_path)?;
+        // 新文件落盘之后再替换，避免压缩过程中崩溃丢失数据
+        new_log.file.sync_all()?;
+        let reclaimed = self
+            .log
+            .file
+            .metadata()?
+            .len()
+            .saturating_sub(new_log.file.metadata()?.len());
 
-        // new_log.file_path = self.log.file_path;
-        new_log.file_path = self.log.file_path.clone();
+        // 先关闭旧文件的句柄（同时释放文件锁），再将临时文件更改为正式文件
+        // 新文件的锁在 Log::new 时已经获取，重命名后仍然由新的文件句柄持有
+        let file_path = self.log.file_path.clone();
+        new_log.flush_count += self.log.flush_count;
+        new_log.sync_count += self.log.sync_count;
+        new_log.sync_policy = self.log.sync_policy;
+        drop(std::mem::replace(&mut self.log, new_log));
         self.keydir = new_keydir;
-        self.log = new_log;
+        std::fs::rename(&self.log.file_path, &file_path)?;
+        self.log.file_path = file_path;
+        self.total_bytes = self.log.file.metadata()?.len();
+        self.live_bytes = self.total_bytes - self.log.data_offset();
+        self.compactions += 1;
 
-        Ok(())
+        // 目录也需要落盘，保证重命名操作持久化
+        #[cfg(unix)]
+        if let Some(dir) = self.log.file_path.parent() {
+            let dir = if dir.as_os_str().is_empty() {
+                std::path::Path::new(".")
+            } else {
+                dir
+            };
+            File::open(dir)?.sync_all()?;
+        }
+
+        Ok(reclaimed)
     }
 }
 
@@ -83,12 +237,19 @@ impl super::engine::Engine for DiskEngine {
         // 先写日志记录
         let (offset, size) = self.log.write_entry(&key, Some(&value))?;
         // 更新内存索引
-        let val_size = value.len() as u32;
-        // keydir 中的value表示数据value的偏移量
-        self.keydir
-            .insert(key, (offset + size as u64 - val_size as u64, val_size));
+        self.apply_entry(key, Some(value.len() as u32), offset, size);
 
-        Ok(())
+        self.maybe_compact()
+    }
+
+    fn set_batch(&mut self, entries: WriteBatch) -> Result<()> {
+        // 一次性写入所有的日志记录，只 flush 一次
+        let positions = self.log.write_batch(&entries)?;
+        for ((key, value), (offset, size)) in entries.into_iter().zip(positions) {
+            self.apply_entry(key, value.map(|v| v.len() as u32), offset, size);
+        }
+
+        self.maybe_compact()
     }
 
     fn get(&mut self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
@@ -111,14 +272,34 @@ impl super::engine::Engine for DiskEngine {
     }
 
     fn delete(&mut self, key: Vec<u8>) -> Result<()> {
-        self.log.write_entry(&key, None)?;
-        self.keydir.remove(&key);
-        Ok(())
+        let (offset, size) = self.log.write_entry(&key, None)?;
+        self.apply_entry(key, None, offset, size);
+        self.maybe_compact()
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        self.log.sync()
+    }
+
+    // 除了文件开头的版本号，有效记录之外的部分都是垃圾数据
+    fn stats(&self) -> EngineStats {
+        EngineStats {
+            key_count: self.keydir.len() as u64,
+            logical_bytes: self
+                .keydir
+                .iter()
+                .map(|(key, (_, val_size))| key.len() as u64 + *val_size as u64)
+                .sum(),
+            file_bytes: self.total_bytes,
+            garbage_bytes: self
+                .total_bytes
+                .saturating_sub(self.live_bytes + self.log.data_offset()),
+        }
     }
 
     fn scan(&mut self, range: impl std::ops::RangeBounds<Vec<u8>>) -> Self::EngineIterator<'_> {
         DiskEngineIterator {
-            inner: self.keydir.range(range),
+            inner: self.keydir.range(normalize_range(range)),
             log: &mut self.log,
         }
     }
@@ -157,6 +338,32 @@ impl<'a> DoubleEndedIterator for DiskEngineIterator<'a> {
 struct Log {
     file_path: PathBuf,
     file: std::fs::File,
+    // flush 的次数
+    flush_count: u64,
+    // 文件的格式版本，0 表示没有版本号和校验和的旧格式
+    version: u8,
+    sync_policy: SyncPolicy,
+    // sync 的次数
+    sync_count: u64,
+    // 上次 sync 之后写入的记录数，以及上次 sync 的时间
+    unsynced: usize,
+    last_sync: Instant,
+}
+
+// 从日志文件中读取的一条记录
+enum LogEntry {
+    // 完整的记录，val_size 为 -1 表示删除，size 为记录的总字节数
+    Valid {
+        key: Vec<u8>,
+        val_size: i32,
+        size: u64,
+    },
+    // 记录超出了文件末尾，写入过程中进程退出，长度通过了校验
+    Truncated,
+    // 长度不合法或者校验和不一致，size 为按长度计算的记录字节数，长度不合法时为 None
+    Corrupted {
+        size: Option<u64>,
+    },
 }
 
 impl Log {
@@ -179,40 +386,150 @@ impl Log {
         // 使用第三库 fs4
         file.try_lock_exclusive()?;
 
-        Ok(Self { file, file_path })
+        let mut log = Self {
+            file,
+            file_path,
+            flush_count: 0,
+            version: LOG_FORMAT_VERSION,
+            sync_policy: SyncPolicy::default(),
+            sync_count: 0,
+            unsynced: 0,
+            last_sync: Instant::now(),
+        };
+        // 新文件写入版本号，已有的文件按第一个字节判断格式
+        if log.file.metadata()?.len() == 0 {
+            log.reset()?;
+        } else {
+            let mut version = [0; 1];
+            log.file.seek(std::io::SeekFrom::Start(0))?;
+            log.file.read_exact(&mut version)?;
+            log.version = match version[0] {
+                0 => 0,
+                LOG_FORMAT_VERSION => LOG_FORMAT_VERSION,
+                v => {
+                    return Err(Error::Internal(format!(
+                        "unsupported log format version {} in {}",
+                        v,
+                        log.file_path.display()
+                    )));
+                }
+            };
+        }
+        Ok(log)
+    }
+
+    // 清空文件，写入最新格式的版本号
+    fn reset(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.seek(std::io::SeekFrom::Start(0))?;
+        self.file.write_all(&[LOG_FORMAT_VERSION])?;
+        self.version = LOG_FORMAT_VERSION;
+        Ok(())
+    }
+
+    // 把上次 sync 之后写入的数据持久化到磁盘
+    fn sync(&mut self) -> Result<()> {
+        if self.unsynced > 0 {
+            self.file.sync_data()?;
+            self.sync_count += 1;
+            self.unsynced = 0;
+        }
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    // 写入 count 条记录之后，按照 sync 策略决定是否 sync
+    fn maybe_sync(&mut self, count: usize) -> Result<()> {
+        self.unsynced += count;
+        let sync = match self.sync_policy {
+            SyncPolicy::Always => true,
+            SyncPolicy::EveryN(n) => self.unsynced >= n,
+            SyncPolicy::IntervalMs(ms) => self.last_sync.elapsed() >= Duration::from_millis(ms),
+            SyncPolicy::Never => false,
+        };
+        if sync { self.sync() } else { Ok(()) }
+    }
+
+    // 第一条记录的偏移量，旧格式的文件没有版本号
+    fn data_offset(&self) -> u64 {
+        match self.version {
+            0 => 0,
+            _ => 1,
+        }
+    }
+
+    fn header_size(&self) -> u32 {
+        match self.version {
+            0 => LOG_HEADER_SIZE,
+            _ => LOG_HEADER_SIZE_CRC,
+        }
+    }
+
+    // 一条有效记录在日志文件中占用的字节数
+    fn entry_size(&self, key: &[u8], val_size: u32) -> u64 {
+        self.header_size() as u64 + key.len() as u64 + val_size as u64
     }
 
     // 遍历数据文件，构建内存索引（并“删除”数据的过滤）
-    fn build_keydir(&mut self) -> Result<KeyDir> {
+    // 末尾不完整的记录（写入过程中进程退出）会被截断，返回 keydir 和截断的字节数
+    // 文件中间的记录损坏时返回错误，不丢弃后面的数据
+    fn build_keydir(&mut self) -> Result<(KeyDir, u64)> {
         let mut keydir = KeyDir::new();
         let file_size = self.file.metadata()?.len();
         let mut buf_reader: BufReader<&File> = BufReader::new(&self.file);
 
-        let mut offset = 0;
-        loop {
-            if offset >= file_size {
-                break;
-            }
+        let mut offset = self.data_offset();
+        while offset < file_size {
+            let (key, val_size, size) = match self.read_entry(&mut buf_reader, offset, file_size)? {
+                LogEntry::Valid {
+                    key,
+                    val_size,
+                    size,
+                } => (key, val_size, size),
+                LogEntry::Truncated => break,
+                // 损坏的记录是最后一条，或者之后全部是 0（崩溃后文件系统用 0 填充未写入的块），按不完整的记录处理
+                LogEntry::Corrupted { size }
+                    if size.is_some_and(|size| offset + size == file_size)
+                        || Self::is_zero_filled(&mut buf_reader, offset)? =>
+                {
+                    break;
+                }
+                LogEntry::Corrupted { .. } => {
+                    return Err(Error::Internal(format!(
+                        "corrupted log entry at offset {} in {}",
+                        offset,
+                        self.file_path.display()
+                    )));
+                }
+            };
 
-            let (key, val_size) = Self::read_entry(&mut buf_reader, offset)?;
-            let key_size = key.len() as u32;
             // value_size == -1 means the key is deleted
             if val_size == -1 {
                 keydir.remove(&key);
-                offset += key_size as u64 + LOG_HEADER_SIZE as u64;
             } else {
-                keydir.insert(
-                    key,
-                    (
-                        offset + LOG_HEADER_SIZE as u64 + key_size as u64, // 这里存储的是 value 的偏移量
-                        val_size as u32, // 这里存储的是 value 的大小
-                    ),
-                );
-                offset += LOG_HEADER_SIZE as u64 + key_size as u64 + val_size as u64;
+                // 这里存储的是 value 的偏移量和 value 的大小
+                keydir.insert(key, (offset + size - val_size as u64, val_size as u32));
             }
+            offset += size;
+        }
+        drop(buf_reader);
+
+        // 截断到最后一条完整记录的末尾，之后的写入追加在完整的记录之后
+        let recovered = file_size.saturating_sub(offset);
+        if recovered > 0 {
+            self.file.set_len(offset)?;
+            self.file.sync_all()?;
         }
 
-        Ok(keydir)
+        Ok((keydir, recovered))
+    }
+
+    // 从 offset 开始到文件末尾是否全部是 0
+    fn is_zero_filled(buf_reader: &mut BufReader<&File>, offset: u64) -> Result<bool> {
+        buf_reader.seek(std::io::SeekFrom::Start(offset))?;
+        let mut rest = Vec::new();
+        buf_reader.read_to_end(&mut rest)?;
+        Ok(rest.iter().all(|b| *b == 0))
     }
 }
 
@@ -225,9 +542,10 @@ impl Log {
     /// 3. 按顺序写入：
     ///    - key 长度（u32，大端）
     ///    - value 长度（i32，大端；若 value 为 `None` 则写 `-1`）
+    ///    - 校验和（u32，大端；旧格式的文件没有）
     ///    - key 本身
     ///    - value（若存在）
-    /// 4. 立即 flush，保证数据落盘。
+    /// 4. 立即 flush 到操作系统，再按照 sync 策略决定是否 sync 到磁盘。
     ///
     /// # 参数
     /// - `key`:   要写入的键，以 `&Vec<u8>` 形式传入。
@@ -241,24 +559,81 @@ impl Log {
     fn write_entry(&mut self, key: &Vec<u8>, value: Option<&Vec<u8>>) -> Result<(u64, u32)> {
         // 首先把文件偏移移动到文件末尾
         let offset = self.file.seek(std::io::SeekFrom::End(0))?;
-        let key_size = key.len() as u32;
-        let val_size = value.map_or(0, |v| v.len() as u32);
-        // 这里的 LOG_HEADER_SIZE 是 key_size 和 val_size 的二进制拼接
-        let total_size = LOG_HEADER_SIZE + key_size + val_size;
+        let total_size = self.entry_size(key, value.map_or(0, |v| v.len() as u32)) as u32;
 
-        // 分别写入 key size, value size, key, value
+        // 分别写入 key size, value size, crc, key, value
         let mut writer = BufWriter::with_capacity(total_size as usize, &self.file);
-        writer.write_all(&key_size.to_be_bytes())?;
-        writer.write_all(&value.map_or(-1, |v| v.len() as i32).to_be_bytes())?;
-        writer.write_all(&key)?;
-        if let Some(val) = value {
-            writer.write_all(val)?;
-        }
+        Self::encode_entry(&mut writer, self.version, key, value.map(|v| v.as_slice()))?;
         writer.flush()?;
+        drop(writer);
+        self.flush_count += 1;
+        self.maybe_sync(1)?;
 
         Ok((offset, total_size))
     }
 
+    /// 在日志文件末尾批量追加多条记录，记录格式与 `write_entry` 相同。
+    ///
+    /// 只移动一次文件游标，所有记录通过同一个 `BufWriter` 写入，最后只 flush 一次。
+    /// 所有记录都计入 sync 策略的记录数，`EveryN` 策略下一个批次最多 sync 一次。
+    ///
+    /// # 参数
+    /// - `entries`: 要写入的 key/value 列表，value 为 `None` 表示删除。
+    ///
+    /// # 返回
+    /// 与 `entries` 一一对应的 `(offset, total_size)` 列表。
+    ///
+    fn write_batch(&mut self, entries: &[(Vec<u8>, Option<Vec<u8>>)]) -> Result<Vec<(u64, u32)>> {
+        let mut offset = self.file.seek(std::io::SeekFrom::End(0))?;
+        let mut positions = Vec::with_capacity(entries.len());
+
+        let mut writer = BufWriter::new(&self.file);
+        for (key, value) in entries {
+            let total_size =
+                self.entry_size(key, value.as_ref().map_or(0, |v| v.len() as u32)) as u32;
+            Self::encode_entry(&mut writer, self.version, key, value.as_deref())?;
+
+            positions.push((offset, total_size));
+            offset += total_size as u64;
+        }
+        writer.flush()?;
+        drop(writer);
+        self.flush_count += 1;
+        self.maybe_sync(entries.len())?;
+
+        Ok(positions)
+    }
+
+    // 按文件的格式写入一条记录，version 为 0 时不写校验和
+    fn encode_entry(
+        writer: &mut impl Write,
+        version: u8,
+        key: &[u8],
+        value: Option<&[u8]>,
+    ) -> Result<()> {
+        let key_size = (key.len() as u32).to_be_bytes();
+        let val_size = value.map_or(-1, |v| v.len() as i32).to_be_bytes();
+        writer.write_all(&key_size)?;
+        writer.write_all(&val_size)?;
+        if version > 0 {
+            let mut header_crc = Crc32::new();
+            header_crc.update(&key_size);
+            header_crc.update(&val_size);
+            writer.write_all(&header_crc.finish().to_be_bytes())?;
+            let mut crc = Crc32::new();
+            crc.update(&key_size);
+            crc.update(&val_size);
+            crc.update(key);
+            crc.update(value.unwrap_or_default());
+            writer.write_all(&crc.finish().to_be_bytes())?;
+        }
+        writer.write_all(key)?;
+        if let Some(val) = value {
+            writer.write_all(val)?;
+        }
+        Ok(())
+    }
+
     /// Reads a value of specified size from a given offset in the file.
     ///
     /// # Arguments
@@ -287,25 +662,39 @@ impl Log {
     /// The entry is expected to be stored in the following binary format:
     /// 1. 4-byte big-endian key size (u32)
     /// 2. 4-byte big-endian value size (i32)
-    /// 3. Key data (bytes)
-    /// (Note: The actual value data is not read by this function)
+    /// 3. 4-byte big-endian CRC32 of the two sizes above
+    ///    (only in files with a format version)
+    /// 4. 4-byte big-endian CRC32 of the sizes, the key and the value
+    ///    (only in files with a format version)
+    /// 5. Key data (bytes)
+    /// (Note: The value data is only read to verify the checksum)
     ///
     /// # Arguments
     /// * `buf_reader` - A buffered reader for the file containing the entries
     /// * `offset` - The byte offset in the file where the entry begins
+    /// * `file_size` - The size of the file, entries must end within it
     ///
     /// # Returns
-    /// - `Ok((Vec<u8>, i32))` containing (key_bytes, value_size) if successful
-    /// - `Err` if any I/O operation fails or if the data is malformed
+    /// - `Ok(LogEntry::Valid)` containing the key, value size and entry size
+    /// - `Ok(LogEntry::Truncated)` if the entry extends past the end of the file
+    ///   and its sizes pass the header checksum
+    /// - `Ok(LogEntry::Corrupted)` if the sizes are invalid or a checksum does not match
     ///
     /// # Errors
     /// This function will return an error if:
     /// - Seeking to the specified offset fails
-    /// - Reading either the key size or value size fails
-    /// - Reading the key bytes fails
-    /// - The file ends unexpectedly during reading
+    /// - Reading from the file fails
     ///
-    fn read_entry(buf_reader: &mut BufReader<&File>, offset: u64) -> Result<(Vec<u8>, i32)> {
+    fn read_entry(
+        &self,
+        buf_reader: &mut BufReader<&File>,
+        offset: u64,
+        file_size: u64,
+    ) -> Result<LogEntry> {
+        let header_size = self.header_size() as u64;
+        if file_size - offset < header_size {
+            return Ok(LogEntry::Truncated);
+        }
         buf_reader.seek(std::io::SeekFrom::Start(offset))?;
         let mut len_buf = [0; 4];
 
@@ -313,15 +702,97 @@ impl Log {
         buf_reader.read_exact(&mut len_buf)?;
         let key_size = u32::from_be_bytes(len_buf);
 
-        // 读取 value size
+        // 读取 value size，-1 表示删除，其他的负数不合法
         buf_reader.read_exact(&mut len_buf)?;
         let val_size = i32::from_be_bytes(len_buf);
 
+        // 先校验长度，长度损坏时不能判断记录是否超出了文件末尾
+        let crc = match self.version {
+            0 => None,
+            _ => {
+                let mut crc_buf = [0; 4];
+                buf_reader.read_exact(&mut crc_buf)?;
+                let mut header_crc = Crc32::new();
+                header_crc.update(&key_size.to_be_bytes());
+                header_crc.update(&val_size.to_be_bytes());
+                if header_crc.finish() != u32::from_be_bytes(crc_buf) {
+                    return Ok(LogEntry::Corrupted { size: None });
+                }
+                buf_reader.read_exact(&mut crc_buf)?;
+                Some(u32::from_be_bytes(crc_buf))
+            }
+        };
+        if val_size < -1 {
+            return Ok(LogEntry::Corrupted { size: None });
+        }
+        // 旧格式的文件没有校验和，长度超出文件末尾时只能按不完整的记录处理
+        let size = header_size + key_size as u64 + val_size.max(0) as u64;
+        if offset + size > file_size {
+            return Ok(LogEntry::Truncated);
+        }
+
         // 读取 key
         let mut key = vec![0; key_size as usize];
         buf_reader.read_exact(&mut key)?;
 
-        Ok((key, val_size))
+        // 读取 value 并校验
+        if let Some(crc) = crc {
+            let mut value = vec![0; val_size.max(0) as usize];
+            buf_reader.read_exact(&mut value)?;
+            let mut actual = Crc32::new();
+            actual.update(&key_size.to_be_bytes());
+            actual.update(&val_size.to_be_bytes());
+            actual.update(&key);
+            actual.update(&value);
+            if actual.finish() != crc {
+                return Ok(LogEntry::Corrupted { size: Some(size) });
+            }
+        }
+
+        Ok(LogEntry::Valid {
+            key,
+            val_size,
+            size,
+        })
+    }
+}
+
+// CRC-32（IEEE 802.3，和 zlib、gzip 相同），用于检测日志记录的损坏
+struct Crc32(u32);
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl Crc32 {
+    fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for b in data {
+            self.0 = CRC32_TABLE[((self.0 ^ *b as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        self.0 ^ 0xFFFF_FFFF
     }
 }
 
@@ -407,4 +878,435 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_disk_engine_compact_in_place() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        let mut eng = DiskEngine::new(p.clone())?;
+
+        eng.set(b"key1".to_vec(), b"value1".to_vec())?;
+        eng.set(b"key2".to_vec(), b"value2".to_vec())?;
+        eng.set(b"key2".to_vec(), b"value3".to_vec())?;
+        eng.set(b"key3".to_vec(), b"value4".to_vec())?;
+        eng.delete(b"key3".to_vec())?;
+
+        // 被覆盖和删除的数据都被清理
+        let size = std::fs::metadata(&p)?.len();
+        let reclaimed = eng.compact()?;
+        assert!(reclaimed > 0);
+        assert_eq!(std::fs::metadata(&p)?.len(), size - reclaimed);
+        assert!(!p.with_extension("compact").exists());
+        assert_eq!(eng.compact()?, 0);
+
+        // 不需要重新打开，继续使用同一个 engine
+        assert_eq!(eng.get(b"key2".to_vec())?, Some(b"value3".to_vec()));
+        eng.set(b"key4".to_vec(), b"value5".to_vec())?;
+        eng.delete(b"key1".to_vec())?;
+        assert_eq!(eng.get(b"key1".to_vec())?, None);
+        assert_eq!(
+            eng.scan(..).collect::<Result<Vec<_>>>()?,
+            vec![
+                (b"key2".to_vec(), b"value3".to_vec()),
+                (b"key4".to_vec(), b"value5".to_vec()),
+            ]
+        );
+
+        // 文件锁由新的文件句柄持有
+        assert!(DiskEngine::new(p.clone()).is_err());
+
+        drop(eng);
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(
+            eng.scan(..).collect::<Result<Vec<_>>>()?,
+            vec![
+                (b"key2".to_vec(), b"value3".to_vec()),
+                (b"key4".to_vec(), b"value5".to_vec()),
+            ]
+        );
+        drop(eng);
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_engine_auto_compact() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        let options = CompactionOptions {
+            min_garbage_ratio: 0.5,
+            min_file_size: 1024,
+        };
+        let mut eng = DiskEngine::with_options(p.clone(), options)?;
+
+        // 文件较小时不会触发压缩
+        for i in 0..10 {
+            eng.set(b"key".to_vec(), format!("value{}", i).into_bytes())?;
+        }
+        assert_eq!(eng.compaction_count(), 0);
+
+        // 反复覆盖同一个 key，垃圾数据超过阈值后自动压缩
+        for i in 0..1000 {
+            eng.set(b"key".to_vec(), format!("value{}", i).into_bytes())?;
+        }
+        assert!(eng.compaction_count() > 0);
+        assert!(std::fs::metadata(&p)?.len() < 2048);
+        assert_eq!(eng.get(b"key".to_vec())?, Some(b"value999".to_vec()));
+
+        // 删除同样会触发压缩
+        let count = eng.compaction_count();
+        for i in 0..200 {
+            let key = format!("key{:03}", i).into_bytes();
+            eng.set(key.clone(), b"value".to_vec())?;
+            if i % 2 == 0 {
+                eng.delete(key)?;
+            }
+        }
+        assert!(eng.compaction_count() > count);
+        assert_eq!(eng.scan_prefix(b"key0".to_vec()).count(), 50);
+        drop(eng);
+
+        // 重新打开后数据不变
+        let mut eng = DiskEngine::with_options(p.clone(), options)?;
+        assert_eq!(eng.get(b"key".to_vec())?, Some(b"value999".to_vec()));
+        assert_eq!(eng.get(b"key001".to_vec())?, Some(b"value".to_vec()));
+        assert_eq!(eng.get(b"key002".to_vec())?, None);
+        drop(eng);
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    // 在文件末尾追加字节，模拟写入过程中进程退出留下的数据
+    fn append_bytes(p: &PathBuf, data: &[u8]) -> Result<()> {
+        let mut file = OpenOptions::new().append(true).open(p)?;
+        file.write_all(data)?;
+        Ok(())
+    }
+
+    // 修改文件中某个位置的一个字节
+    fn flip_byte(p: &PathBuf, offset: u64) -> Result<()> {
+        let mut data = std::fs::read(p)?;
+        data[offset as usize] ^= 0xFF;
+        std::fs::write(p, data)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_crc32() {
+        let mut crc = Crc32::new();
+        crc.update(b"12345");
+        crc.update(b"6789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+        assert_eq!(Crc32::new().finish(), 0);
+    }
+
+    #[test]
+    fn test_disk_engine_truncated_tail() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        let mut eng = DiskEngine::new(p.clone())?;
+        eng.set(b"key1".to_vec(), b"value1".to_vec())?;
+        eng.set(b"key2".to_vec(), b"value2".to_vec())?;
+        assert_eq!(eng.recovered_bytes(), 0);
+        drop(eng);
+        let good_size = std::fs::metadata(&p)?.len();
+        // 版本号 + 两条记录，每条记录 16 字节的头
+        assert_eq!(good_size, 1 + 2 * (16 + 4 + 6));
+        assert_eq!(std::fs::read(&p)?[0], LOG_FORMAT_VERSION);
+
+        // 最后一条记录只写入了一部分
+        let mut eng = DiskEngine::new(p.clone())?;
+        eng.set(b"key3".to_vec(), b"value3".to_vec())?;
+        drop(eng);
+        let file = OpenOptions::new().write(true).open(&p)?;
+        file.set_len(good_size + 15)?;
+        drop(file);
+
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.recovered_bytes(), 15);
+        assert_eq!(std::fs::metadata(&p)?.len(), good_size);
+        assert_eq!(eng.get(b"key3".to_vec())?, None);
+        assert_eq!(
+            eng.scan(..).collect::<Result<Vec<_>>>()?,
+            vec![
+                (b"key1".to_vec(), b"value1".to_vec()),
+                (b"key2".to_vec(), b"value2".to_vec()),
+            ]
+        );
+        // 截断之后可以继续写入
+        eng.set(b"key3".to_vec(), b"value4".to_vec())?;
+        drop(eng);
+
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.recovered_bytes(), 0);
+        assert_eq!(eng.get(b"key3".to_vec())?, Some(b"value4".to_vec()));
+        drop(eng);
+
+        // 记录头不完整
+        append_bytes(&p, &[0, 0, 0, 4, 0])?;
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.recovered_bytes(), 5);
+        assert_eq!(eng.get(b"key3".to_vec())?, Some(b"value4".to_vec()));
+        drop(eng);
+
+        // 长度超出了文件末尾
+        let mut data = Vec::new();
+        let mut header_crc = Crc32::new();
+        header_crc.update(&4u32.to_be_bytes());
+        header_crc.update(&1000i32.to_be_bytes());
+        data.extend_from_slice(&4u32.to_be_bytes());
+        data.extend_from_slice(&1000i32.to_be_bytes());
+        data.extend_from_slice(&header_crc.finish().to_be_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(b"key5value");
+        append_bytes(&p, &data)?;
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.recovered_bytes(), data.len() as u64);
+        assert_eq!(eng.get(b"key5".to_vec())?, None);
+        drop(eng);
+
+        // 崩溃后文件末尾被 0 填充
+        append_bytes(&p, &[0; 4096])?;
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.recovered_bytes(), 4096);
+        assert_eq!(eng.scan(..).count(), 3);
+        drop(eng);
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_engine_corrupted_entry() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        let mut eng = DiskEngine::new(p.clone())?;
+        eng.set(b"key1".to_vec(), b"value1".to_vec())?;
+        eng.set(b"key2".to_vec(), b"value2".to_vec())?;
+        eng.set(b"key3".to_vec(), b"value3".to_vec())?;
+        drop(eng);
+        let size = std::fs::metadata(&p)?.len();
+
+        // 最后一条记录的 value 损坏，按不完整的记录截断
+        flip_byte(&p, size - 1)?;
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.recovered_bytes(), 16 + 4 + 6);
+        assert_eq!(eng.get(b"key3".to_vec())?, None);
+        assert_eq!(eng.get(b"key2".to_vec())?, Some(b"value2".to_vec()));
+        drop(eng);
+
+        // 中间的记录损坏时报错，不会读到错误的数据，也不会丢弃后面的数据
+        flip_byte(&p, 1 + 16 + 4)?;
+        match DiskEngine::new(p.clone()) {
+            Err(Error::Internal(msg)) => {
+                assert!(
+                    msg.starts_with("corrupted log entry at offset 1 in"),
+                    "{}",
+                    msg
+                )
+            }
+            Err(err) => panic!("unexpected error {:?}", err),
+            Ok(_) => panic!("corrupted log should not be opened"),
+        }
+        assert_eq!(std::fs::metadata(&p)?.len(), size - (16 + 4 + 6));
+
+        // 不合法的 value 长度
+        flip_byte(&p, 1 + 16 + 4)?;
+        flip_byte(&p, 1 + 4)?;
+        assert!(DiskEngine::new(p.clone()).is_err());
+        flip_byte(&p, 1 + 4)?;
+        assert_eq!(DiskEngine::new(p.clone())?.scan(..).count(), 2);
+
+        // 不支持的格式版本
+        flip_byte(&p, 0)?;
+        assert!(DiskEngine::new(p.clone()).is_err());
+        flip_byte(&p, 0)?;
+
+        // 中间记录的长度损坏后超出了文件末尾，不能当成不完整的记录截断后面的数据
+        let size = std::fs::metadata(&p)?.len();
+        let entry_size = 16 + 4 + 6;
+        flip_byte(&p, 1 + entry_size)?;
+        match DiskEngine::new(p.clone()) {
+            Err(Error::Internal(msg)) => assert!(
+                msg.starts_with(&format!(
+                    "corrupted log entry at offset {} in",
+                    1 + entry_size
+                )),
+                "{}",
+                msg
+            ),
+            Err(err) => panic!("unexpected error {:?}", err),
+            Ok(_) => panic!("corrupted log should not be opened"),
+        }
+        assert_eq!(std::fs::metadata(&p)?.len(), size);
+        flip_byte(&p, 1 + entry_size)?;
+        assert_eq!(DiskEngine::new(p.clone())?.scan(..).count(), 2);
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_engine_legacy_format() -> Result<()> {
+        // 旧格式的记录：没有版本号和校验和
+        fn legacy_entry(key: &[u8], value: Option<&[u8]>) -> Vec<u8> {
+            let mut data = Vec::new();
+            data.extend_from_slice(&(key.len() as u32).to_be_bytes());
+            data.extend_from_slice(&value.map_or(-1, |v| v.len() as i32).to_be_bytes());
+            data.extend_from_slice(key);
+            data.extend_from_slice(value.unwrap_or_default());
+            data
+        }
+
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        let mut data = Vec::new();
+        data.extend(legacy_entry(b"key1", Some(b"value1")));
+        data.extend(legacy_entry(b"key2", Some(b"value2")));
+        data.extend(legacy_entry(b"key1", None));
+        let partial = legacy_entry(b"key3", Some(b"value3"));
+        data.extend_from_slice(&partial[..10]);
+        std::fs::write(&p, &data)?;
+
+        // 旧格式的文件同样截断不完整的记录，新写入的记录仍然使用旧格式
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.recovered_bytes(), 10);
+        assert_eq!(eng.get(b"key1".to_vec())?, None);
+        assert_eq!(eng.get(b"key2".to_vec())?, Some(b"value2".to_vec()));
+        eng.set(b"key3".to_vec(), b"value3".to_vec())?;
+        drop(eng);
+        assert_eq!(
+            std::fs::read(&p)?[data.len() - 10..],
+            legacy_entry(b"key3", Some(b"value3"))
+        );
+
+        // 压缩之后升级为新格式
+        let mut eng = DiskEngine::new(p.clone())?;
+        eng.compact()?;
+        eng.set(b"key4".to_vec(), b"value4".to_vec())?;
+        drop(eng);
+        assert_eq!(std::fs::read(&p)?[0], LOG_FORMAT_VERSION);
+        assert_eq!(std::fs::metadata(&p)?.len(), 1 + 3 * (16 + 4 + 6));
+
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.recovered_bytes(), 0);
+        assert_eq!(
+            eng.scan(..).collect::<Result<Vec<_>>>()?,
+            vec![
+                (b"key2".to_vec(), b"value2".to_vec()),
+                (b"key3".to_vec(), b"value3".to_vec()),
+                (b"key4".to_vec(), b"value4".to_vec()),
+            ]
+        );
+        drop(eng);
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_engine_sync_policy() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+
+        // 每 10 条记录 sync 一次，批量写入的每条记录都计数
+        let mut eng = DiskEngine::new(p.clone())?.with_sync_policy(SyncPolicy::EveryN(10));
+        for i in 0..25 {
+            eng.set(format!("key{:02}", i).into_bytes(), b"value".to_vec())?;
+        }
+        assert_eq!(eng.sync_count(), 2);
+        eng.set_batch(
+            (25..35)
+                .map(|i| (format!("key{:02}", i).into_bytes(), Some(b"value".to_vec())))
+                .collect(),
+        )?;
+        assert_eq!(eng.sync_count(), 3);
+        eng.delete(b"key00".to_vec())?;
+        // 手动 sync 剩余的记录，没有新的写入时不需要 sync
+        eng.sync()?;
+        assert_eq!(eng.sync_count(), 4);
+        eng.sync()?;
+        assert_eq!(eng.sync_count(), 4);
+        drop(eng);
+
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.scan(..).count(), 34);
+        assert_eq!(eng.get(b"key00".to_vec())?, None);
+        assert_eq!(eng.get(b"key34".to_vec())?, Some(b"value".to_vec()));
+        drop(eng);
+
+        // 其他的策略
+        let mut eng = DiskEngine::new(p.clone())?.with_sync_policy(SyncPolicy::Always);
+        eng.set(b"a".to_vec(), b"1".to_vec())?;
+        eng.set(b"b".to_vec(), b"2".to_vec())?;
+        assert_eq!(eng.sync_count(), 2);
+        drop(eng);
+
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.log.sync_policy, SyncPolicy::Never);
+        eng.set(b"a".to_vec(), b"3".to_vec())?;
+        assert_eq!(eng.sync_count(), 0);
+        // 压缩之后保留策略和计数
+        eng = eng.with_sync_policy(SyncPolicy::IntervalMs(60_000));
+        eng.sync()?;
+        eng.compact()?;
+        eng.set(b"a".to_vec(), b"4".to_vec())?;
+        assert_eq!(eng.sync_count(), 1);
+        eng = eng.with_sync_policy(SyncPolicy::IntervalMs(0));
+        eng.set(b"a".to_vec(), b"5".to_vec())?;
+        assert_eq!(eng.sync_count(), 2);
+        drop(eng);
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_engine_stats() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        let mut eng = DiskEngine::new(p.clone())?;
+        assert_eq!(
+            eng.stats(),
+            EngineStats {
+                key_count: 0,
+                logical_bytes: 0,
+                file_bytes: 1,
+                garbage_bytes: 0,
+            }
+        );
+
+        for i in 0..10 {
+            eng.set(format!("key{}", i).into_bytes(), b"value".to_vec())?;
+        }
+        let stats = eng.stats();
+        assert_eq!(stats.key_count, 10);
+        assert_eq!(stats.logical_bytes, 10 * (4 + 5));
+        assert_eq!(stats.file_bytes, std::fs::metadata(&p)?.len());
+        assert_eq!(stats.garbage_bytes, 0);
+
+        // 覆盖和删除之后垃圾数据增加，有效数据不变或者减少
+        for _ in 0..3 {
+            eng.set(b"key0".to_vec(), b"value".to_vec())?;
+        }
+        eng.delete(b"key1".to_vec())?;
+        let overwritten = eng.stats();
+        assert_eq!(overwritten.key_count, 9);
+        assert_eq!(overwritten.logical_bytes, 9 * (4 + 5));
+        // 3 条被覆盖的记录、1 条被删除的记录以及删除标记本身
+        assert_eq!(overwritten.garbage_bytes, 4 * (16 + 4 + 5) + 16 + 4);
+        assert_eq!(overwritten.file_bytes, std::fs::metadata(&p)?.len());
+
+        // 压缩之后没有垃圾数据
+        let reclaimed = eng.compact()?;
+        let compacted = eng.stats();
+        assert_eq!(reclaimed, overwritten.garbage_bytes);
+        assert_eq!(compacted.garbage_bytes, 0);
+        assert_eq!(compacted.file_bytes, overwritten.file_bytes - reclaimed);
+        assert_eq!(compacted.logical_bytes, overwritten.logical_bytes);
+        drop(eng);
+
+        // 重新打开之后统计不变
+        let eng = DiskEngine::new(p.clone())?;
+        assert_eq!(eng.stats(), compacted);
+        drop(eng);
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
 }
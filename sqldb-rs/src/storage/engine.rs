@@ -2,6 +2,22 @@ use std::ops::{Bound, RangeBounds};
 
 use crate::error::Result;
 
+// 批量写入的数据，value 为 None 表示删除对应的 key
+pub type WriteBatch = Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
+// 存储引擎的大小信息，都是近似值
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EngineStats {
+    // 有效的 key 数量
+    pub key_count: u64,
+    // 有效的 key 和 value 的字节数之和
+    pub logical_bytes: u64,
+    // 数据文件的字节数，内存引擎没有数据文件，为 0
+    pub file_bytes: u64,
+    // 数据文件中被覆盖和删除的数据的字节数，可以通过压缩回收
+    pub garbage_bytes: u64,
+}
+
 // 抽象存储引擎接口定义，接入不同的存储引擎，目前支持内存和简单的磁盘 KV 存储
 pub trait Engine {
     type EngineIterator<'a>: EngineIterator
@@ -17,48 +33,83 @@ pub trait Engine {
     // 删除 key 对应的数据, 如果不存在话则忽略
     fn delete(&mut self, key: Vec<u8>) -> Result<()>;
 
+    // 批量写入 key/value，value 为 None 表示删除对应的 key
+    fn set_batch(&mut self, entries: WriteBatch) -> Result<()>;
+
     // 扫描指定范围内的 key/value
     fn scan(&mut self, range: impl RangeBounds<Vec<u8>>) -> Self::EngineIterator<'_>;
 
+    // 存储引擎的大小信息
+    fn stats(&self) -> EngineStats;
+
+    // 把已经写入的数据持久化到磁盘，事务提交时调用，不需要持久化的引擎可以不实现
+    fn sync(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     // 前缀扫描
     fn scan_prefix(&mut self, prefix: Vec<u8>) -> Self::EngineIterator<'_> {
-        // start:   aaaa
-        // end:     aaab
-        let start = Bound::Included(prefix.clone());
-        let mut bound_prefix = prefix.clone();
-        // 前缀包含了 255， 例如 10, 2, 3, 255
-        // 右边界就是 10, 2, 4
-        // 如果全部是 255
-        // 右边界  Unbounded
-        // if let Some(last) = bound_prefix.iter_mut().last() {
-        //     *last += 1;
-        // }
-        // let end = Bound::Excluded(bound_prefix);
-        // 这里主要利用 scan 中的 BTreeMap的range方法（字典顺序的比较）
-        let end = match bound_prefix.iter().rposition(|b| *b != 255) {
-            Some(pos) => {
-                bound_prefix[pos] += 1;
-                bound_prefix.truncate(pos + 1);
-                // 思考，这里的 truncate 其实就是赋值0。整体等于运算中的归零进位操作
-                Bound::Excluded(bound_prefix)
-            },
-            None => Bound::Unbounded,
-        };
-
         // 注意这里scan是利用了BtreeMap的range方法，并且BTreeMap的key是字典序（字节序）排序的。类似于字符串的比较方式
-        self.scan((start, end))
+        self.scan(prefix_range(prefix))
     }
 }
 
+// 转换扫描范围，起始位置大于结束位置等空范围会转换为一个合法的空范围
+// BTreeMap::range 在这些情况下会 panic，所有的存储引擎都应该返回空的结果
+pub fn normalize_range(range: impl RangeBounds<Vec<u8>>) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+    let start = range.start_bound().cloned();
+    let end = range.end_bound().cloned();
+    let empty = match (&start, &end) {
+        (Bound::Included(s), Bound::Included(e)) => s > e,
+        (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e)) => {
+            s >= e
+        }
+        _ => false,
+    };
+    if empty {
+        // 大于 [] 并且小于等于 [] 的 key 不存在
+        (Bound::Excluded(vec![]), Bound::Included(vec![]))
+    } else {
+        (start, end)
+    }
+}
+
+// 计算前缀扫描的范围
+// start:   aaaa
+// end:     aaab
+pub fn prefix_range(prefix: Vec<u8>) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+    let start = Bound::Included(prefix.clone());
+    let mut bound_prefix = prefix;
+    // 前缀包含了 255， 例如 10, 2, 3, 255
+    // 右边界就是 10, 2, 4
+    // 如果全部是 255（或者前缀为空）
+    // 右边界  Unbounded
+    // 这里主要利用 scan 中的 BTreeMap的range方法（字典顺序的比较）
+    let end = match bound_prefix.iter().rposition(|b| *b != 255) {
+        Some(pos) => {
+            bound_prefix[pos] += 1;
+            bound_prefix.truncate(pos + 1);
+            // 思考，这里的 truncate 其实就是赋值0。整体等于运算中的归零进位操作
+            Bound::Excluded(bound_prefix)
+        }
+        None => Bound::Unbounded,
+    };
+    (start, end)
+}
+
 // DoubleEndedIterator 是一个双向迭代器，可以向前和向后迭代
 pub trait EngineIterator: DoubleEndedIterator<Item = Result<(Vec<u8>, Vec<u8>)>> {}
 
 #[cfg(test)]
 mod tests {
-    use super::Engine;
+    use super::{Engine, prefix_range};
     use crate::{
         error::Result,
-        storage::{disk::DiskEngine, memory::MemoryEngine},
+        storage::{
+            disk::DiskEngine,
+            memory::MemoryEngine,
+            mvcc::{MvccKey, MvccKeyPrefix},
+        },
     };
     use std::{env, ops::Bound};
 
@@ -145,11 +196,335 @@ mod tests {
         Ok(())
     }
 
+    // 测试覆盖写入和删除后的扫描
+    fn test_overwrite(mut eng: impl Engine) -> Result<()> {
+        eng.set(b"a".to_vec(), b"value1".to_vec())?;
+        eng.set(b"b".to_vec(), b"value2".to_vec())?;
+        eng.set(b"c".to_vec(), b"value3".to_vec())?;
+        eng.set(b"b".to_vec(), b"value4".to_vec())?;
+        eng.delete(b"a".to_vec())?;
+        // 删除不存在的 key
+        eng.delete(b"not exist".to_vec())?;
+        assert_eq!(
+            eng.scan(..).collect::<Result<Vec<_>>>()?,
+            vec![
+                (b"b".to_vec(), b"value4".to_vec()),
+                (b"c".to_vec(), b"value3".to_vec()),
+            ]
+        );
+
+        // 删除后重新写入
+        eng.set(b"a".to_vec(), b"value5".to_vec())?;
+        eng.delete(b"c".to_vec())?;
+        assert_eq!(
+            eng.scan(..).collect::<Result<Vec<_>>>()?,
+            vec![
+                (b"a".to_vec(), b"value5".to_vec()),
+                (b"b".to_vec(), b"value4".to_vec()),
+            ]
+        );
+        Ok(())
+    }
+
+    // 测试反向扫描
+    fn test_reverse_scan(mut eng: impl Engine) -> Result<()> {
+        for key in [b"a", b"b", b"c", b"d", b"e"] {
+            eng.set(key.to_vec(), key.to_vec())?;
+        }
+        let keys = |iter: &mut dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>>| {
+            iter.map(|r| r.map(|(k, _)| k)).collect::<Result<Vec<_>>>()
+        };
+
+        assert_eq!(
+            keys(&mut eng.scan(..).rev())?,
+            vec![
+                b"e".to_vec(),
+                b"d".to_vec(),
+                b"c".to_vec(),
+                b"b".to_vec(),
+                b"a".to_vec()
+            ]
+        );
+        assert_eq!(
+            keys(&mut eng.scan(b"b".to_vec()..=b"d".to_vec()).rev())?,
+            vec![b"d".to_vec(), b"c".to_vec(), b"b".to_vec()]
+        );
+        assert_eq!(
+            keys(&mut eng.scan_prefix(b"c".to_vec()).rev())?,
+            vec![b"c".to_vec()]
+        );
+
+        // 从两端交替读取，不会重复读取同一个 key
+        let mut iter = eng.scan(..);
+        assert_eq!(
+            iter.next().transpose()?.map(|(k, _)| k),
+            Some(b"a".to_vec())
+        );
+        assert_eq!(
+            iter.next_back().transpose()?.map(|(k, _)| k),
+            Some(b"e".to_vec())
+        );
+        assert_eq!(
+            iter.next().transpose()?.map(|(k, _)| k),
+            Some(b"b".to_vec())
+        );
+        assert_eq!(
+            iter.next_back().transpose()?.map(|(k, _)| k),
+            Some(b"d".to_vec())
+        );
+        assert_eq!(
+            iter.next().transpose()?.map(|(k, _)| k),
+            Some(b"c".to_vec())
+        );
+        assert!(iter.next_back().is_none());
+        assert!(iter.next().is_none());
+        Ok(())
+    }
+
+    // 测试空范围的扫描
+    fn test_empty_range(mut eng: impl Engine) -> Result<()> {
+        assert_eq!(eng.scan(..).count(), 0);
+        assert_eq!(eng.scan_prefix(b"a".to_vec()).count(), 0);
+
+        eng.set(b"a".to_vec(), b"value1".to_vec())?;
+        eng.set(b"b".to_vec(), b"value2".to_vec())?;
+        eng.set(b"c".to_vec(), b"value3".to_vec())?;
+
+        assert_eq!(eng.scan(b"b".to_vec()..b"b".to_vec()).count(), 0);
+        assert_eq!(eng.scan(b"c".to_vec()..b"a".to_vec()).count(), 0);
+        assert_eq!(eng.scan(b"c".to_vec()..=b"a".to_vec()).rev().count(), 0);
+        assert_eq!(
+            eng.scan((
+                Bound::Excluded(b"b".to_vec()),
+                Bound::Excluded(b"b".to_vec())
+            ))
+            .count(),
+            0
+        );
+        assert_eq!(
+            eng.scan((
+                Bound::Excluded(b"b".to_vec()),
+                Bound::Included(b"b".to_vec())
+            ))
+            .count(),
+            0
+        );
+        assert_eq!(eng.scan(b"d".to_vec()..).count(), 0);
+        assert_eq!(eng.scan(..b"a".to_vec()).count(), 0);
+        assert_eq!(
+            eng.scan(b"b".to_vec()..=b"b".to_vec())
+                .collect::<Result<Vec<_>>>()?,
+            vec![(b"b".to_vec(), b"value2".to_vec())]
+        );
+        Ok(())
+    }
+
+    // 测试包含 0 的 key
+    fn test_zero_bytes(mut eng: impl Engine) -> Result<()> {
+        let keys = vec![
+            vec![],
+            vec![0],
+            vec![0, 0],
+            vec![0, 1],
+            vec![1],
+            vec![1, 0],
+            vec![1, 0, 0],
+        ];
+        for (i, key) in keys.iter().enumerate().rev() {
+            eng.set(key.clone(), vec![i as u8, 0])?;
+        }
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(eng.get(key.clone())?, Some(vec![i as u8, 0]));
+        }
+        assert_eq!(
+            eng.scan(..)
+                .map(|r| r.map(|(k, _)| k))
+                .collect::<Result<Vec<_>>>()?,
+            keys
+        );
+        assert_eq!(
+            eng.scan(vec![0]..vec![1])
+                .map(|r| r.map(|(k, _)| k))
+                .collect::<Result<Vec<_>>>()?,
+            vec![vec![0], vec![0, 0], vec![0, 1]]
+        );
+        assert_eq!(
+            eng.scan_prefix(vec![1, 0])
+                .map(|r| r.map(|(k, _)| k))
+                .collect::<Result<Vec<_>>>()?,
+            vec![vec![1, 0], vec![1, 0, 0]]
+        );
+
+        eng.delete(vec![0])?;
+        assert_eq!(eng.get(vec![0])?, None);
+        assert_eq!(eng.get(vec![0, 0])?, Some(vec![2, 0]));
+        Ok(())
+    }
+
+    // 测试批量写入
+    fn test_set_batch(mut eng: impl Engine) -> Result<()> {
+        eng.set(b"aa".to_vec(), b"value1".to_vec())?;
+        eng.set(b"bb".to_vec(), b"value2".to_vec())?;
+
+        eng.set_batch(vec![
+            (b"cc".to_vec(), Some(b"value3".to_vec())),
+            (b"aa".to_vec(), None),
+            (b"bb".to_vec(), Some(b"value4".to_vec())),
+            (b"dd".to_vec(), Some(b"value5".to_vec())),
+            (b"dd".to_vec(), None),
+        ])?;
+        assert_eq!(
+            eng.scan(..).collect::<Result<Vec<_>>>()?,
+            vec![
+                (b"bb".to_vec(), b"value4".to_vec()),
+                (b"cc".to_vec(), b"value3".to_vec()),
+            ]
+        );
+
+        eng.set_batch(vec![])?;
+        assert_eq!(eng.get(b"cc".to_vec())?, Some(b"value3".to_vec()));
+
+        Ok(())
+    }
+
+    // 测试包含 0 和 255 的前缀扫描
+    fn test_scan_prefix_bytes(mut eng: impl Engine) -> Result<()> {
+        let keys = vec![
+            vec![0],
+            vec![0, 0],
+            vec![0, 255],
+            vec![1, 255],
+            vec![1, 255, 0],
+            vec![1, 255, 255],
+            vec![2],
+            vec![255],
+            vec![255, 255],
+            vec![255, 255, 0],
+        ];
+        for key in keys.iter() {
+            eng.set(key.clone(), vec![])?;
+        }
+        let mut scan = |prefix: Vec<u8>| -> Result<Vec<Vec<u8>>> {
+            eng.scan_prefix(prefix).map(|r| r.map(|(k, _)| k)).collect()
+        };
+        assert_eq!(scan(vec![0])?, vec![vec![0], vec![0, 0], vec![0, 255]]);
+        assert_eq!(scan(vec![0, 255])?, vec![vec![0, 255]]);
+        assert_eq!(
+            scan(vec![1, 255])?,
+            vec![vec![1, 255], vec![1, 255, 0], vec![1, 255, 255]]
+        );
+        assert_eq!(scan(vec![1, 255, 255])?, vec![vec![1, 255, 255]]);
+        assert_eq!(
+            scan(vec![255])?,
+            vec![vec![255], vec![255, 255], vec![255, 255, 0]]
+        );
+        assert_eq!(scan(vec![255, 255, 255])?, Vec::<Vec<u8>>::new());
+        assert_eq!(scan(vec![])?, keys);
+
+        Ok(())
+    }
+
+    // 测试 keycode 编码后的 MVCC key 的前缀扫描，编码后的 key 中包含 0 和 255
+    fn test_scan_prefix_mvcc_key(mut eng: impl Engine) -> Result<()> {
+        let raw_keys = vec![
+            vec![0],
+            vec![0, 0],
+            vec![0, 255],
+            vec![1],
+            vec![255],
+            vec![255, 0],
+        ];
+        for raw_key in raw_keys.iter() {
+            for version in [0, 255, u64::MAX] {
+                eng.set(MvccKey::Version(raw_key.clone(), version).encode()?, vec![])?;
+            }
+        }
+        eng.set(MvccKey::TxnActive(u64::MAX).encode()?, vec![])?;
+
+        let mut scan = |prefix: Vec<u8>| -> Result<Vec<(Vec<u8>, u64)>> {
+            let mut enc_prefix = MvccKeyPrefix::Version(prefix).encode()?;
+            enc_prefix.truncate(enc_prefix.len() - 2);
+            eng.scan_prefix(enc_prefix)
+                .map(|r| match MvccKey::decode(r?.0)? {
+                    MvccKey::Version(raw_key, version) => Ok((raw_key, version)),
+                    key => panic!("unexpected key {:?}", key),
+                })
+                .collect()
+        };
+        let expect = |keys: &[Vec<u8>]| -> Vec<(Vec<u8>, u64)> {
+            keys.iter()
+                .flat_map(|k| [0, 255, u64::MAX].map(|v| (k.clone(), v)))
+                .collect()
+        };
+        assert_eq!(scan(vec![0])?, expect(&[vec![0], vec![0, 0], vec![0, 255]]));
+        assert_eq!(scan(vec![0, 0])?, expect(&[vec![0, 0]]));
+        assert_eq!(scan(vec![0, 255])?, expect(&[vec![0, 255]]));
+        assert_eq!(scan(vec![255])?, expect(&[vec![255], vec![255, 0]]));
+        assert_eq!(scan(vec![])?, expect(&raw_keys));
+
+        Ok(())
+    }
+
+    // 测试大小信息，key 和 value 的字节数与存储方式无关
+    fn test_stats(mut eng: impl Engine) -> Result<()> {
+        assert_eq!(eng.stats().key_count, 0);
+        assert_eq!(eng.stats().logical_bytes, 0);
+
+        eng.set(b"a".to_vec(), b"value1".to_vec())?;
+        eng.set(b"b".to_vec(), b"value2".to_vec())?;
+        eng.set(b"b".to_vec(), b"value22".to_vec())?;
+        eng.set_batch(vec![(b"c".to_vec(), Some(vec![])), (b"a".to_vec(), None)])?;
+        let stats = eng.stats();
+        assert_eq!(stats.key_count, 2);
+        assert_eq!(stats.logical_bytes, 1 + 7 + 1);
+        assert!(stats.file_bytes >= stats.logical_bytes || stats.file_bytes == 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefix_range() {
+        assert_eq!(
+            prefix_range(vec![1, 2, 3]),
+            (
+                Bound::Included(vec![1, 2, 3]),
+                Bound::Excluded(vec![1, 2, 4])
+            )
+        );
+        assert_eq!(
+            prefix_range(vec![10, 2, 3, 255]),
+            (
+                Bound::Included(vec![10, 2, 3, 255]),
+                Bound::Excluded(vec![10, 2, 4])
+            )
+        );
+        assert_eq!(
+            prefix_range(vec![0, 255, 255]),
+            (Bound::Included(vec![0, 255, 255]), Bound::Excluded(vec![1]))
+        );
+        assert_eq!(
+            prefix_range(vec![255, 255]),
+            (Bound::Included(vec![255, 255]), Bound::Unbounded)
+        );
+        assert_eq!(
+            prefix_range(vec![]),
+            (Bound::Included(vec![]), Bound::Unbounded)
+        );
+    }
+
     #[test]
     fn test_memory() -> Result<()> {
         test_point_opt(MemoryEngine::new())?;
         test_scan(MemoryEngine::new())?;
         test_scan_prefix(MemoryEngine::new())?;
+        test_set_batch(MemoryEngine::new())?;
+        test_overwrite(MemoryEngine::new())?;
+        test_reverse_scan(MemoryEngine::new())?;
+        test_empty_range(MemoryEngine::new())?;
+        test_zero_bytes(MemoryEngine::new())?;
+        test_scan_prefix_bytes(MemoryEngine::new())?;
+        test_scan_prefix_mvcc_key(MemoryEngine::new())?;
+        test_stats(MemoryEngine::new())?;
         Ok(())
     }
 
@@ -167,6 +542,30 @@ mod tests {
         test_scan_prefix(DiskEngine::new(db_path.clone())?)?;
         std::fs::remove_file(&db_path)?;
 
+        test_set_batch(DiskEngine::new(db_path.clone())?)?;
+        std::fs::remove_file(&db_path)?;
+
+        test_overwrite(DiskEngine::new(db_path.clone())?)?;
+        std::fs::remove_file(&db_path)?;
+
+        test_reverse_scan(DiskEngine::new(db_path.clone())?)?;
+        std::fs::remove_file(&db_path)?;
+
+        test_empty_range(DiskEngine::new(db_path.clone())?)?;
+        std::fs::remove_file(&db_path)?;
+
+        test_zero_bytes(DiskEngine::new(db_path.clone())?)?;
+        std::fs::remove_file(&db_path)?;
+
+        test_scan_prefix_bytes(DiskEngine::new(db_path.clone())?)?;
+        std::fs::remove_file(&db_path)?;
+
+        test_scan_prefix_mvcc_key(DiskEngine::new(db_path.clone())?)?;
+        std::fs::remove_file(&db_path)?;
+
+        test_stats(DiskEngine::new(db_path.clone())?)?;
+        std::fs::remove_file(&db_path)?;
+
         Ok(())
     }
 }
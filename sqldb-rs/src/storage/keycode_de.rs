@@ -70,25 +70,32 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_bool(v != 0)
     }
 
-    fn deserialize_i8<V>(self, _visitor: V) -> Result<V::Value>
+    // 编码时翻转了符号位，这里再翻转回来
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        let bytes = self.take_bytes(1);
+        let v = i8::from_be_bytes(bytes.try_into()?) ^ i8::MIN;
+        visitor.visit_i8(v)
     }
 
-    fn deserialize_i16<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        let bytes = self.take_bytes(2);
+        let v = i16::from_be_bytes(bytes.try_into()?) ^ i16::MIN;
+        visitor.visit_i16(v)
     }
 
-    fn deserialize_i32<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        let bytes = self.take_bytes(4);
+        let v = i32::from_be_bytes(bytes.try_into()?) ^ i32::MIN;
+        visitor.visit_i32(v)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
@@ -96,7 +103,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: de::Visitor<'de>,
     {
         let bytes = self.take_bytes(8);
-        let v = i64::from_be_bytes(bytes.try_into()?);
+        let v = i64::from_be_bytes(bytes.try_into()?) ^ i64::MIN;
         visitor.visit_i64(v)
     }
 
@@ -139,11 +146,19 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         todo!()
     }
 
-    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    // 编码时符号位为 1 的是正数，只翻转了符号位；否则是负数，翻转了所有位
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        let bytes = self.take_bytes(8);
+        let bits = u64::from_be_bytes(bytes.try_into()?);
+        let bits = if bits >> 63 == 1 {
+            bits ^ (1 << 63)
+        } else {
+            !bits
+        };
+        visitor.visit_f64(f64::from_bits(bits))
     }
 
     fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
@@ -161,11 +176,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_str(&String::from_utf8(bytes)?)
     }
 
-    fn deserialize_string<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        let bytes = self.next_bytes()?;
+        visitor.visit_string(String::from_utf8(bytes)?)
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
@@ -182,11 +198,15 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_byte_buf(self.next_bytes()?)
     }
 
-    fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        match self.take_bytes(1)[0] {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            b => Err(Error::Internal(format!("Unexpected option byte {}", b))),
+        }
     }
 
     fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
@@ -347,7 +367,10 @@ impl<'de, 'a> de::VariantAccess<'de> for &mut Deserializer<'de> {
 #[cfg(test)]
 mod tests {
 
-    use crate::storage::{keycode_de::deserialize_key, mvcc::MvccKey};
+    use crate::{
+        sql::types::Value,
+        storage::{keycode_de::deserialize_key, keycode_se::serialize_key, mvcc::MvccKey},
+    };
 
     #[test]
     fn test_u8_convert() {
@@ -375,4 +398,63 @@ mod tests {
             vec![3, 97, 98, 99, 0, 0, 0, 0, 0, 0, 0, 0, 0, 11],
         );
     }
+    // 编码后再解码得到原来的值
+    fn round_trip<T>(values: Vec<T>)
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        for v in values {
+            let bytes = serialize_key(&v).unwrap();
+            let res: T = deserialize_key(&bytes).unwrap();
+            assert_eq!(res, v, "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(vec![false, true]);
+        round_trip(vec![i8::MIN, -1, 0, 1, i8::MAX]);
+        round_trip(vec![i16::MIN, -1, 0, 1, i16::MAX]);
+        round_trip(vec![i32::MIN, -1, 0, 1, i32::MAX]);
+        round_trip(vec![i64::MIN, -256, -1, 0, 1, 256, i64::MAX]);
+        round_trip(vec![
+            f64::NEG_INFINITY,
+            f64::MIN,
+            -1.5,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.5,
+            f64::MAX,
+            f64::INFINITY,
+        ]);
+        round_trip(vec![
+            String::new(),
+            "abc".to_string(),
+            "a\0b\0\0".to_string(),
+            "中文".to_string(),
+        ]);
+        round_trip(vec![None, Some(-1i64), Some(0), Some(i64::MAX)]);
+        round_trip(vec![None, Some("a\0".to_string())]);
+        round_trip(vec![
+            ("t".to_string(), Value::Null, Value::Integer(-1)),
+            ("t".to_string(), Value::Boolean(true), Value::Integer(0)),
+            (
+                "t".to_string(),
+                Value::Float(-2.5),
+                Value::String("k".into()),
+            ),
+            (
+                "t\0".to_string(),
+                Value::String("v\0".into()),
+                Value::Timestamp(-60),
+            ),
+        ]);
+
+        // -0.0 和 0.0 相等，需要比较位确认符号被保留
+        let res: f64 = deserialize_key(&serialize_key(-0.0f64).unwrap()).unwrap();
+        assert!(res.is_sign_negative());
+        let res: f64 = deserialize_key(&serialize_key(f64::NAN).unwrap()).unwrap();
+        assert!(res.is_nan());
+    }
 }
@@ -38,20 +38,27 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         Ok(())
     }
 
-    fn serialize_i8(self, _v: i8) -> Result<()> {
-        todo!()
+    // 有符号整数翻转符号位后按大端写入，负数的编码小于正数
+    // -1 -> 7f ff ff ff ff ff ff ff
+    //  0 -> 80 00 00 00 00 00 00 00
+    //  1 -> 80 00 00 00 00 00 00 01
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.output.extend((v ^ i8::MIN).to_be_bytes());
+        Ok(())
     }
 
-    fn serialize_i16(self, _v: i16) -> Result<()> {
-        todo!()
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.output.extend((v ^ i16::MIN).to_be_bytes());
+        Ok(())
     }
 
-    fn serialize_i32(self, _v: i32) -> Result<()> {
-        todo!()
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.output.extend((v ^ i32::MIN).to_be_bytes());
+        Ok(())
     }
 
     fn serialize_i64(self, value: i64) -> Result<()> {
-        self.output.extend(value.to_be_bytes());
+        self.output.extend((value ^ i64::MIN).to_be_bytes());
         Ok(())
     }
 
@@ -76,8 +83,17 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         todo!()
     }
 
-    fn serialize_f64(self, _v: f64) -> Result<()> {
-        todo!()
+    // 正数（符号位为 0）只翻转符号位，负数翻转所有位，
+    // 这样编码按字节比较的顺序与 f64::total_cmp 一致：-NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        let bits = v.to_bits();
+        let bits = if bits >> 63 == 1 {
+            !bits
+        } else {
+            bits ^ (1 << 63)
+        };
+        self.output.extend(bits.to_be_bytes());
+        Ok(())
     }
 
     fn serialize_char(self, _v: char) -> Result<()> {
@@ -108,15 +124,18 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         Ok(())
     }
 
+    // Option 先写一个字节表示是否有值，None 为 0 排在所有 Some 之前
     fn serialize_none(self) -> Result<()> {
-        todo!()
+        self.output.push(0);
+        Ok(())
     }
 
-    fn serialize_some<T>(self, _value: &T) -> Result<()>
+    fn serialize_some<T>(self, value: &T) -> Result<()>
     where
         T: ?Sized + ser::Serialize,
     {
-        todo!()
+        self.output.push(1);
+        value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<()> {
@@ -265,7 +284,10 @@ mod tests {
 
     use super::serialize_key;
 
-    use crate::storage::mvcc::{MvccKey, MvccKeyPrefix};
+    use crate::{
+        sql::types::Value,
+        storage::mvcc::{MvccKey, MvccKeyPrefix},
+    };
 
     #[test]
     fn test_encode() {
@@ -341,4 +363,100 @@ mod tests {
             vec![3, 97, 98, 0, 0],
         );
     }
+    // 按顺序排列的值，编码后的字节也必须是递增的
+    fn assert_sorted<T: serde::Serialize + std::fmt::Debug>(values: &[T]) {
+        let encoded = values
+            .iter()
+            .map(|v| serialize_key(v).unwrap())
+            .collect::<Vec<_>>();
+        for (i, pair) in encoded.windows(2).enumerate() {
+            assert!(
+                pair[0] < pair[1],
+                "{:?} {:?} should sort before {:?} {:?}",
+                values[i],
+                pair[0],
+                values[i + 1],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_primitives() {
+        assert_eq!(serialize_key(false).unwrap(), vec![0]);
+        assert_eq!(serialize_key(true).unwrap(), vec![1]);
+        assert_eq!(serialize_key(-1i8).unwrap(), vec![0x7f]);
+        assert_eq!(serialize_key(1i16).unwrap(), vec![0x80, 1]);
+        assert_eq!(serialize_key(0i32).unwrap(), vec![0x80, 0, 0, 0]);
+        assert_eq!(
+            serialize_key(-1i64).unwrap(),
+            vec![0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(
+            serialize_key(1.0f64).unwrap(),
+            vec![0xbf, 0xf0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            serialize_key(-1.0f64).unwrap(),
+            vec![0x40, 0x0f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(serialize_key("a\0b").unwrap(), vec![97, 0, 255, 98, 0, 0]);
+        assert_eq!(serialize_key(None::<i64>).unwrap(), vec![0]);
+        assert_eq!(serialize_key(Some(true)).unwrap(), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_encode_order() {
+        assert_sorted(&[false, true]);
+        assert_sorted(&[i8::MIN, -1, 0, 1, i8::MAX]);
+        assert_sorted(&[i16::MIN, -300, -1, 0, 1, 300, i16::MAX]);
+        assert_sorted(&[i32::MIN, -70000, -1, 0, 1, 70000, i32::MAX]);
+        assert_sorted(&[
+            i64::MIN,
+            -1 << 40,
+            -256,
+            -255,
+            -1,
+            0,
+            1,
+            255,
+            256,
+            1 << 40,
+            i64::MAX,
+        ]);
+        assert_sorted(&[
+            f64::NEG_INFINITY,
+            f64::MIN,
+            -1.5,
+            -1.0,
+            -f64::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.0,
+            1.5,
+            f64::MAX,
+            f64::INFINITY,
+            f64::NAN,
+        ]);
+        assert_sorted(&["", "\0", "\0\0", "\0a", "a", "a\0", "a\0b", "aa", "b"]);
+        assert_sorted(&[None, Some(i64::MIN), Some(-1), Some(0), Some(i64::MAX)]);
+
+        // 索引键中包含 Value，同一类型的值按大小排序
+        assert_sorted(&[
+            ("t", "c", Value::Null, Value::Integer(2)),
+            ("t", "c", Value::Boolean(false), Value::Integer(1)),
+            ("t", "c", Value::Boolean(true), Value::Integer(1)),
+            ("t", "c", Value::Integer(-5), Value::Integer(3)),
+            ("t", "c", Value::Integer(-5), Value::Integer(4)),
+            ("t", "c", Value::Integer(0), Value::Integer(1)),
+            ("t", "c", Value::Integer(7), Value::Integer(1)),
+            ("t", "c", Value::Float(-0.5), Value::Integer(1)),
+            ("t", "c", Value::Float(2.5), Value::Integer(1)),
+            ("t", "c", Value::String("a".into()), Value::Integer(1)),
+            ("t", "c", Value::String("ab".into()), Value::Integer(1)),
+            ("t", "d", Value::Null, Value::Integer(1)),
+            ("u", "c", Value::Null, Value::Integer(1)),
+        ]);
+    }
 }
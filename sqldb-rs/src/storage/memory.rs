@@ -1,4 +1,7 @@
-use crate::error::Result;
+use crate::{
+    error::Result,
+    storage::engine::{EngineStats, WriteBatch, normalize_range},
+};
 use std::collections::{BTreeMap, btree_map};
 
 // 内存存储引擎定义
@@ -14,6 +17,12 @@ impl MemoryEngine {
     }
 }
 
+impl Default for MemoryEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl super::engine::Engine for MemoryEngine {
     type EngineIterator<'a> = MemoryEngineIterator<'a>;
 
@@ -32,9 +41,32 @@ impl super::engine::Engine for MemoryEngine {
         Ok(())
     }
 
+    fn set_batch(&mut self, entries: WriteBatch) -> Result<()> {
+        for (key, value) in entries {
+            match value {
+                Some(value) => self.set(key, value)?,
+                None => self.delete(key)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn stats(&self) -> EngineStats {
+        EngineStats {
+            key_count: self.data.len() as u64,
+            logical_bytes: self
+                .data
+                .iter()
+                .map(|(key, value)| (key.len() + value.len()) as u64)
+                .sum(),
+            file_bytes: 0,
+            garbage_bytes: 0,
+        }
+    }
+
     fn scan(&mut self, range: impl std::ops::RangeBounds<Vec<u8>>) -> Self::EngineIterator<'_> {
         MemoryEngineIterator {
-            inner: self.data.range(range),
+            inner: self.data.range(normalize_range(range)),
         }
     }
 }
@@ -1,6 +1,6 @@
 pub mod disk;
 pub mod engine;
+pub mod keycode_de;
+pub mod keycode_se;
 pub mod memory;
 pub mod mvcc;
-pub mod keycode_se;
-pub mod keycode_de;
@@ -1,11 +1,16 @@
 use std::{
     collections::{BTreeMap, HashSet},
-    sync::{Arc, Mutex, MutexGuard},
+    fmt::Display,
+    ops::{Bound, RangeBounds},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
 };
 
 use serde::{Deserialize, Serialize};
 
-use super::engine::Engine as StorageEngine;
+use super::engine::{Engine as StorageEngine, EngineStats, WriteBatch, prefix_range};
 use crate::{
     error::{Error, Result},
     storage::{keycode_de, keycode_se},
@@ -13,15 +18,43 @@ use crate::{
 
 pub type Version = u64;
 
+// 存储中 key 的编码格式版本，编码方式改变时递增，旧格式的数据无法按新的格式解码和排序
+// 1: keycode 编码，有符号整数翻转符号位，浮点数按位翻转
+pub const KEY_FORMAT_VERSION: u32 = 1;
+
+// 存储引擎的大小信息，以及活跃事务的数量
+// 存储引擎中的 key 包括每个 key 的所有版本以及事务的元数据
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MvccStats {
+    pub storage: EngineStats,
+    pub active_txns: usize,
+}
+
+impl Display for MvccStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "keys: {}", self.storage.key_count)?;
+        writeln!(f, "logical bytes: {}", self.storage.logical_bytes)?;
+        writeln!(f, "file bytes: {}", self.storage.file_bytes)?;
+        writeln!(f, "garbage bytes: {}", self.storage.garbage_bytes)?;
+        write!(f, "active transactions: {}", self.active_txns)
+    }
+}
+
 pub struct Mvcc<E: StorageEngine> {
     // 这里是 storage_engine
-    storage_engine: Arc<Mutex<E>>,
+    pub(crate) storage_engine: Arc<Mutex<E>>,
+    // 当前实例分配出去的最大版本号，用于检测 NextVersion 是否被其他实例改写
+    allocated_version: Arc<Mutex<Version>>,
+    // 只读事务的快照，只读事务不写入 TxnActive，vacuum 通过这里保留它们读取的版本
+    snapshots: Snapshots,
 }
 
 impl<E: StorageEngine> Clone for Mvcc<E> {
     fn clone(&self) -> Self {
         Self {
             storage_engine: self.storage_engine.clone(),
+            allocated_version: self.allocated_version.clone(),
+            snapshots: self.snapshots.clone(),
         }
     }
 }
@@ -30,18 +63,160 @@ impl<E: StorageEngine> Mvcc<E> {
     pub fn new(eng: E) -> Self {
         Self {
             storage_engine: Arc::new(Mutex::new(eng)),
+            allocated_version: Arc::new(Mutex::new(0)),
+            snapshots: Snapshots::default(),
         }
     }
 
     pub fn begin(&self) -> Result<MvccTransaction<E>> {
         // Ok(MvccTransaction::begin(self.engine.clone()))
-        MvccTransaction::begin(self.storage_engine.clone())
+        MvccTransaction::begin(self.storage_engine.clone(), self.allocated_version.clone())
+    }
+
+    // 开启只读事务，读取最新的已提交数据
+    pub fn begin_read_only(&self) -> Result<MvccTransaction<E>> {
+        MvccTransaction::begin_read_only(self.storage_engine.clone(), &self.snapshots, None)
+    }
+
+    // 开启只读事务，读取指定版本时的数据，即版本号不大于 version 的事务写入的数据
+    pub fn begin_read_only_at(&self, version: Version) -> Result<MvccTransaction<E>> {
+        MvccTransaction::begin_read_only(
+            self.storage_engine.clone(),
+            &self.snapshots,
+            Some(version),
+        )
+    }
+
+    // 把存储引擎中已经写入的数据持久化到磁盘，服务关闭前调用
+    pub fn sync(&self) -> Result<()> {
+        self.storage_engine.lock()?.sync()
+    }
+
+    pub fn stats(&self) -> Result<MvccStats> {
+        let mut storage_engine = self.storage_engine.lock()?;
+        Ok(MvccStats {
+            storage: storage_engine.stats(),
+            active_txns: MvccTransaction::scan_active(&mut storage_engine)?.len(),
+        })
+    }
+
+    // 清理旧版本数据，返回删除的版本数量
+    // 水位线为所有活跃事务和只读事务的版本号，以及它们开始时的活跃事务版本号中最小的一个，
+    // 版本号小于水位线的数据都已经提交，并且对当前和之后的事务都可见，
+    // 所以每个 key 在水位线以下只需要保留最新的一个版本；如果这个版本是删除标记，则也可以删除
+    pub fn vacuum(&self) -> Result<usize> {
+        let mut storage_engine = self.storage_engine.lock()?;
+        let next_version: Version = match storage_engine.get(MvccKey::NextVersion.encode()?)? {
+            Some(value) => bincode::deserialize(&value)?,
+            None => return Ok(0),
+        };
+        let watermark = [
+            MvccTransaction::scan_watermark(&mut storage_engine)?,
+            self.snapshots.watermark()?,
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(next_version);
+
+        // 扫描所有的 Version 数据，去掉最后的 [0, 0] 后缀
+        let mut prefix = MvccKeyPrefix::Version(vec![]).encode()?;
+        prefix.truncate(prefix.len() - 2);
+
+        let mut delete_keys = Vec::new();
+        // 同一个 key 的版本按照版本号从小到大排列，记录水位线以下最近的一个版本：(原始 key, 编码后的 key, 是否为删除标记)
+        let mut last: Option<(Vec<u8>, Vec<u8>, bool)> = None;
+        let mut iter = storage_engine.scan_prefix(prefix);
+        while let Some((key, value)) = iter.next().transpose()? {
+            match MvccKey::decode(key.clone())? {
+                MvccKey::Version(raw_key, version) => {
+                    if version >= watermark {
+                        continue;
+                    }
+                    // 上一个版本被同一个 key 的新版本覆盖，或者是最后的删除标记
+                    if let Some((last_raw_key, last_key, deleted)) = last.take()
+                        && (last_raw_key == raw_key || deleted)
+                    {
+                        delete_keys.push(last_key);
+                    }
+                    let deleted = bincode::deserialize::<Option<Vec<u8>>>(&value)?.is_none();
+                    last = Some((raw_key, key, deleted));
+                }
+                _ => {
+                    return Err(Error::Internal(format!(
+                        "Unexpected key: {:?}",
+                        String::from_utf8(key)
+                    )));
+                }
+            }
+        }
+        if let Some((_, last_key, true)) = last {
+            delete_keys.push(last_key);
+        }
+        drop(iter);
+
+        let count = delete_keys.len();
+        // 批量删除
+        storage_engine.set_batch(delete_keys.into_iter().map(|key| (key, None)).collect())?;
+
+        Ok(count)
     }
 }
 
 pub struct MvccTransaction<E: StorageEngine> {
     engine: Arc<Mutex<E>>,
     state: TransactionState, // 事务状态
+    // 只读事务注册的快照，提交、回滚或者释放事务时注销
+    snapshot: Option<SnapshotGuard>,
+}
+
+// 只读事务的快照需要的最小版本号，以及使用这个版本号的只读事务数量
+// 只保存在内存中，重启之后不存在只读事务
+#[derive(Clone, Default)]
+struct Snapshots(Arc<Mutex<BTreeMap<Version, usize>>>);
+
+impl Snapshots {
+    fn register(&self, version: Version) -> Result<SnapshotGuard> {
+        *self.0.lock()?.entry(version).or_insert(0) += 1;
+        Ok(SnapshotGuard {
+            snapshots: self.clone(),
+            version,
+            released: AtomicBool::new(false),
+        })
+    }
+
+    fn watermark(&self) -> Result<Option<Version>> {
+        Ok(self.0.lock()?.keys().next().copied())
+    }
+}
+
+struct SnapshotGuard {
+    snapshots: Snapshots,
+    version: Version,
+    // commit 和 rollback 只能拿到 &self，注销之后 drop 时不再重复注销
+    released: AtomicBool,
+}
+
+impl SnapshotGuard {
+    fn release(&self) -> Result<()> {
+        if self.released.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let mut snapshots = self.snapshots.0.lock()?;
+        if let Some(count) = snapshots.get_mut(&self.version) {
+            *count -= 1;
+            if *count == 0 {
+                snapshots.remove(&self.version);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SnapshotGuard {
+    fn drop(&mut self) {
+        let _ = self.release();
+    }
 }
 
 pub struct TransactionState {
@@ -49,10 +224,17 @@ pub struct TransactionState {
     pub version: Version,
     // 当前活跃事务的版本列表
     pub active_versions: HashSet<Version>,
+    // 只读事务不分配新的版本号，version 为读取的快照版本
+    pub read_only: bool,
 }
 
 impl TransactionState {
     fn is_visible(&self, version: Version) -> bool {
+        // 当前事务自己写入的版本总是可见的，例如同一个事务中删除之后再插入相同的主键
+        // 只读事务的 version 可能属于一个还没有提交的事务，需要检查活跃事务列表
+        if version == self.version && !self.read_only {
+            return true;
+        }
         if self.active_versions.contains(&version) {
             return false;
         } else {
@@ -69,6 +251,8 @@ pub enum MvccKey {
     TxnActive(Version),
     TxnWrite(Version, #[serde(with = "serde_bytes")] Vec<u8>),
     Version(#[serde(with = "serde_bytes")] Vec<u8>, Version),
+    // 存储中 key 的编码格式版本
+    Format,
 }
 
 impl MvccKey {
@@ -89,6 +273,7 @@ pub enum MvccKeyPrefix {
     TxnActive,
     TxnWrite(Version),
     Version(#[serde(with = "serde_bytes")] Vec<u8>),
+    Format,
 }
 
 impl MvccKeyPrefix {
@@ -104,16 +289,38 @@ impl<E: StorageEngine> MvccTransaction<E> {
     }
 
     // 开启事务
-    pub fn begin(eng: Arc<Mutex<E>>) -> Result<Self> {
+    pub fn begin(eng: Arc<Mutex<E>>, allocated_version: Arc<Mutex<Version>>) -> Result<Self> {
         // Self { engine: eng }
 
         // 获取存储引擎
         let mut storage_engine = eng.lock()?;
+        let mut allocated_version = allocated_version.lock()?;
+        // 当前实例的第一个事务，检查存储中 key 的编码格式
+        if *allocated_version == 0 {
+            Self::check_format(&mut storage_engine)?;
+        }
         //  获取最新的版本号
         let next_version = match storage_engine.get(MvccKey::NextVersion.encode()?)? {
             Some(value) => bincode::deserialize(&value)?,
             None => 1,
         };
+
+        // 检查 NextVersion 是否过期：
+        // 1. 不能小于等于当前实例已经分配过的版本号
+        // 2. 该版本号不能已经是活跃事务
+        // 否则说明有其他实例在操作同一份存储，继续分配会导致版本号重复
+        if next_version <= *allocated_version
+            || storage_engine
+                .get(MvccKey::TxnActive(next_version).encode()?)?
+                .is_some()
+        {
+            return Err(Error::Internal(format!(
+                "stale next version {}, version {} has already been allocated",
+                next_version,
+                (*allocated_version).max(next_version)
+            )));
+        }
+        *allocated_version = next_version;
         // 保存下一个version
         storage_engine.set(
             MvccKey::NextVersion.encode()?,
@@ -123,8 +330,16 @@ impl<E: StorageEngine> MvccTransaction<E> {
         // 获取当前活跃的事务列表
         let active_versions = Self::scan_active(&mut storage_engine)?;
 
-        // 当前事务加入到活跃事务列表中
-        storage_engine.set(MvccKey::TxnActive(next_version).encode()?, vec![])?;
+        // 当前事务加入到活跃事务列表中，值为这个事务的快照需要的最小版本号：
+        // 开始时还没有提交的事务对它不可见，vacuum 需要保留这些事务之前的版本
+        let snapshot_min = active_versions
+            .iter()
+            .min()
+            .map_or(next_version, |v| next_version.min(*v));
+        storage_engine.set(
+            MvccKey::TxnActive(next_version).encode()?,
+            bincode::serialize(&snapshot_min)?,
+        )?;
 
         // 返回事务对象
         Ok(Self {
@@ -132,13 +347,62 @@ impl<E: StorageEngine> MvccTransaction<E> {
             state: TransactionState {
                 version: next_version,
                 active_versions: active_versions,
+                read_only: false,
             },
+            snapshot: None,
+        })
+    }
+
+    // 开启只读事务，不分配版本号，也不加入活跃事务列表，快照注册在 snapshots 中，vacuum 不会清理它读取的版本
+    // 当前还没有提交的事务不可见，因此读到的是版本号不大于 as_of 并且已经提交的事务写入的数据
+    // 注意 as_of 在开始之前可能已经低于水位线，这时读到的是 vacuum 保留下来的最近的版本
+    fn begin_read_only(
+        eng: Arc<Mutex<E>>,
+        snapshots: &Snapshots,
+        as_of: Option<Version>,
+    ) -> Result<Self> {
+        let mut storage_engine = eng.lock()?;
+        let next_version = match storage_engine.get(MvccKey::NextVersion.encode()?)? {
+            Some(value) => bincode::deserialize(&value)?,
+            None => 1,
+        };
+        let version = match as_of {
+            Some(version) if version >= next_version => {
+                return Err(Error::VersionNotFound {
+                    version,
+                    latest: next_version - 1,
+                });
+            }
+            Some(version) => version,
+            None => next_version - 1,
+        };
+        let active_versions = Self::scan_active(&mut storage_engine)?;
+        // 版本号不大于 version，并且不属于活跃事务的数据都可见，需要保留其中最新的版本
+        // 持有存储引擎的锁时注册，vacuum 不会在这之间清理数据
+        let snapshot_min = active_versions
+            .iter()
+            .min()
+            .map_or(version + 1, |v| (version + 1).min(*v));
+        let snapshot = snapshots.register(snapshot_min)?;
+
+        Ok(Self {
+            engine: eng.clone(),
+            state: TransactionState {
+                version,
+                active_versions,
+                read_only: true,
+            },
+            snapshot: Some(snapshot),
         })
     }
 
     // 提交事务
     pub fn commit(&self) -> Result<()> {
         // Ok(())
+        // 只读事务没有写入数据，也不在活跃事务列表中，只需要注销快照
+        if let Some(snapshot) = &self.snapshot {
+            return snapshot.release();
+        }
 
         // 获取存储引擎
         let mut storage_engine = self.engine.lock()?;
@@ -152,19 +416,24 @@ impl<E: StorageEngine> MvccTransaction<E> {
         }
         drop(iter); // iter 内部持有了对 storage_engine 的引用，所以需要提前 drop，否则 storage_engine 的可变引用与下面的 storeage_engine.delete 冲突
 
-        for key in delete_keys.into_iter() {
-            storage_engine.delete(key)?;
-        }
+        // 批量删除
+        storage_engine.set_batch(delete_keys.into_iter().map(|key| (key, None)).collect())?;
 
         // 从活跃事务列表中删除
         storage_engine.delete(MvccKey::TxnActive(self.state.version).encode()?)?;
 
+        // 无论存储引擎的 sync 策略是什么，提交的事务都需要持久化
+        storage_engine.sync()?;
+
         Ok(())
     }
 
     // 回滚事务
     pub fn rollback(&self) -> Result<()> {
         // Ok(())
+        if let Some(snapshot) = &self.snapshot {
+            return snapshot.release();
+        }
 
         // 获取存储引擎
         let mut storage_engine = self.engine.lock()?;
@@ -191,9 +460,8 @@ impl<E: StorageEngine> MvccTransaction<E> {
         }
         drop(iter); // iter 内部持有了对 storage_engine 的引用，所以需要提前 drop，否则 storage_engine 的可变引用与下面的 storeage_engine.delete 冲突
 
-        for key in delete_keys.into_iter() {
-            storage_engine.delete(key)?;
-        }
+        // 批量删除
+        storage_engine.set_batch(delete_keys.into_iter().map(|key| (key, None)).collect())?;
 
         // 从活跃事务列表中删除
         storage_engine.delete(MvccKey::TxnActive(self.state.version).encode()?)?;
@@ -211,6 +479,27 @@ impl<E: StorageEngine> MvccTransaction<E> {
         self.write_inner(key, None)
     }
 
+    // 批量写入数据，value 为 None 表示删除
+    pub fn set_batch(&self, entries: WriteBatch) -> Result<()> {
+        self.write_batch_inner(entries)
+    }
+
+    // 删除前缀下所有对当前事务可见的 key，返回删除的 key 的数量
+    // 扫描一次前缀，然后和 set_batch 一样检查冲突并一次性写入所有的删除标记
+    pub fn delete_prefix(&self, prefix: Vec<u8>) -> Result<usize> {
+        if self.state.read_only {
+            return Err(Error::ReadOnly);
+        }
+        let entries = self
+            .scan_prefix(prefix)?
+            .into_iter()
+            .map(|result| (result.key, None))
+            .collect::<WriteBatch>();
+        let count = entries.len();
+        self.write_batch_inner(entries)?;
+        Ok(count)
+    }
+
     pub fn get(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
         // let mut storage_engine = self.engine.lock()?;
         // storage_engine.get(key)
@@ -244,7 +533,6 @@ impl<E: StorageEngine> MvccTransaction<E> {
     }
 
     pub fn scan_prefix(&self, prefix: Vec<u8>) -> Result<Vec<ScanResult>> {
-        let mut storage_engine = self.engine.lock()?;
         let mut enc_prefix = MvccKeyPrefix::Version(prefix).encode()?;
         // 原始值           编码后
         // 97 98 99     -> 97 98 99 0 0
@@ -253,18 +541,56 @@ impl<E: StorageEngine> MvccTransaction<E> {
         // 去掉最后的 [0, 0] 后缀, 如果包括后面的 [0, 0], 则不再是前缀匹配
         enc_prefix.truncate(enc_prefix.len() - 2);
 
-        let mut iter = storage_engine.scan_prefix(enc_prefix);
-        let mut results = BTreeMap::new();
+        self.scan_versions(prefix_range(enc_prefix))
+    }
+
+    // 扫描原始 key 在 range 范围内的数据
+    // 同一个 key 的所有版本编码后是连续的，并且版本号从小到大排列，
+    // 所以 Version(key, 0) 到 Version(key, u64::MAX) 包含了这个 key 的所有版本
+    pub fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Result<Vec<ScanResult>> {
+        let from = match range.start_bound() {
+            Bound::Included(key) => Bound::Included(MvccKey::Version(key.clone(), 0).encode()?),
+            Bound::Excluded(key) => {
+                Bound::Excluded(MvccKey::Version(key.clone(), Version::MAX).encode()?)
+            }
+            Bound::Unbounded => Bound::Included(MvccKeyPrefix::Version(vec![]).encode()?),
+        };
+        let to = match range.end_bound() {
+            Bound::Included(key) => {
+                Bound::Included(MvccKey::Version(key.clone(), Version::MAX).encode()?)
+            }
+            Bound::Excluded(key) => Bound::Excluded(MvccKey::Version(key.clone(), 0).encode()?),
+            // 所有 Version 数据之后的位置
+            Bound::Unbounded => Bound::Excluded(MvccKeyPrefix::Format.encode()?),
+        };
+        self.scan_versions((from, to))
+    }
+
+    // 扫描范围内的 Version 数据，每个原始 key 只返回对当前事务可见的最新版本，被删除的 key 不返回
+    // 扫描期间需要持有存储引擎的锁，所以会把结果全部收集起来再返回，内存占用和范围内可见的数据量成正比；
+    // 如果返回持有锁的迭代器，读取的过程中同一个事务的其他读写（例如索引和主键查询）会发生死锁
+    fn scan_versions(&self, range: (Bound<Vec<u8>>, Bound<Vec<u8>>)) -> Result<Vec<ScanResult>> {
+        let mut storage_engine = self.engine.lock()?;
+        let mut iter = storage_engine.scan(range);
+        let mut results = Vec::new();
+        // 当前 key 最新的可见版本的值，None 表示已经被删除
+        let mut last: Option<(Vec<u8>, Option<Vec<u8>>)> = None;
         while let Some((key, value)) = iter.next().transpose()? {
-            // results.push(ScanResult { key, value });
             match MvccKey::decode(key.clone())? {
                 MvccKey::Version(raw_key, version) => {
-                    if self.state.is_visible(version) {
-                        match bincode::deserialize(&value)? {
-                            Some(raw_value) => results.insert(raw_key, raw_value),
-                            None => results.remove(&raw_key),
-                        };
+                    if !self.state.is_visible(version) {
+                        continue;
                     }
+                    // 扫描到了下一个 key，上一个 key 的可见版本已经确定
+                    if let Some((last_key, Some(last_value))) =
+                        last.take_if(|(last_key, _)| *last_key != raw_key)
+                    {
+                        results.push(ScanResult {
+                            key: last_key,
+                            value: last_value,
+                        });
+                    }
+                    last = Some((raw_key, bincode::deserialize(&value)?));
                 }
                 _ => {
                     return Err(Error::Internal(format!(
@@ -274,11 +600,10 @@ impl<E: StorageEngine> MvccTransaction<E> {
                 }
             }
         }
-        let v = results
-            .into_iter()
-            .map(|(key, value)| ScanResult { key, value })
-            .collect();
-        Ok(v)
+        if let Some((key, Some(value))) = last {
+            results.push(ScanResult { key, value });
+        }
+        Ok(results)
     }
 
     // 更新/删除数据
@@ -293,24 +618,58 @@ impl<E: StorageEngine> MvccTransaction<E> {
     /// 以 Version(key, version) 的形式存储数据
     /// 如果是删除操作，value会被序列化为None
     fn write_inner(&self, key: Vec<u8>, value: Option<Vec<u8>>) -> Result<()> {
+        self.write_batch_inner(vec![(key, value)])
+    }
+
+    // 批量写入，所有 key 都通过冲突检查后，才通过存储引擎的 set_batch 一次性写入
+    fn write_batch_inner(&self, entries: WriteBatch) -> Result<()> {
+        if self.state.read_only {
+            return Err(Error::ReadOnly);
+        }
         // 获取存储引擎
         let mut storage_engine = self.engine.lock()?;
 
+        let mut batch = Vec::with_capacity(entries.len() * 2);
+        for (key, value) in entries {
+            Self::check_conflict(&self.state, &mut storage_engine, &key)?;
+
+            // 记录这个 version 写入了哪些 key， 用于回滚事务
+            batch.push((
+                MvccKey::TxnWrite(self.state.version, key.clone()).encode()?,
+                Some(vec![]),
+            ));
+
+            // 写入实际的 key/value 数据
+            batch.push((
+                MvccKey::Version(key, self.state.version).encode()?,
+                Some(bincode::serialize(&value)?),
+            ));
+        }
+
+        storage_engine.set_batch(batch)
+    }
+
+    // 检查写冲突
+    fn check_conflict(
+        state: &TransactionState,
+        storage_engine: &mut MutexGuard<E>,
+        key: &[u8],
+    ) -> Result<()> {
         // 检查冲突
         // 3 4 5
         // 6
         // key1-3 key2-4 key3-5
         let from = MvccKey::Version(
-            key.clone(),
-            self.state
+            key.to_vec(),
+            state
                 .active_versions
                 .iter()
                 .min()
                 .copied()
-                .unwrap_or(self.state.version + 1),
+                .unwrap_or(state.version + 1),
         )
         .encode()?;
-        let to = MvccKey::Version(key.clone(), u64::MAX).encode()?;
+        let to = MvccKey::Version(key.to_vec(), u64::MAX).encode()?;
         // 当前活跃事务列表 3 4 5
         // 当前事务 6
         // 只需要判断最后一个版本号
@@ -321,7 +680,7 @@ impl<E: StorageEngine> MvccTransaction<E> {
             match MvccKey::decode(k.clone()) {
                 Ok(MvccKey::Version(_, version)) => {
                     // 检测这个 version 是否可见的
-                    if !self.state.is_visible(version) {
+                    if !state.is_visible(version) {
                         return Err(Error::WriteConflict);
                     }
                 }
@@ -334,21 +693,59 @@ impl<E: StorageEngine> MvccTransaction<E> {
             }
         }
 
-        // 记录这个 version 写入了哪些 key， 用于回滚事务
-        storage_engine.set(
-            MvccKey::TxnWrite(self.state.version, key.clone()).encode()?,
-            vec![],
-        )?;
-
-        // 写入实际的 key/value 数据
-        storage_engine.set(
-            MvccKey::Version(key.clone(), self.state.version).encode()?,
-            bincode::serialize(&value)?,
-        )?;
+        Ok(())
+    }
 
+    // 空的存储写入当前的格式版本；非空的存储必须带有相同的格式版本，
+    // 没有格式版本的是旧版本（bincode 编码的 key，或者整数未翻转符号位的 keycode）写入的数据
+    fn check_format(engine: &mut MutexGuard<E>) -> Result<()> {
+        match engine.get(MvccKey::Format.encode()?)? {
+            Some(value) => {
+                let format: u32 = bincode::deserialize(&value)?;
+                if format != KEY_FORMAT_VERSION {
+                    return Err(Error::Internal(format!(
+                        "unsupported key format version {}, expected {}",
+                        format, KEY_FORMAT_VERSION
+                    )));
+                }
+            }
+            None => {
+                if engine.scan(..).next().is_some() {
+                    return Err(Error::Internal(format!(
+                        "storage was written with an older key encoding, key format version {} is required",
+                        KEY_FORMAT_VERSION
+                    )));
+                }
+                engine.set(
+                    MvccKey::Format.encode()?,
+                    bincode::serialize(&KEY_FORMAT_VERSION)?,
+                )?;
+            }
+        }
         Ok(())
     }
 
+    // 所有活跃事务的快照需要的最小版本号，没有活跃事务时为 None
+    // 旧的数据中 TxnActive 的值为空，只使用事务自己的版本号
+    fn scan_watermark(engine: &mut MutexGuard<E>) -> Result<Option<Version>> {
+        let mut watermark: Option<Version> = None;
+        let mut iter = engine.scan_prefix(MvccKeyPrefix::TxnActive.encode()?);
+        while let Some((key, value)) = iter.next().transpose()? {
+            let MvccKey::TxnActive(version) = MvccKey::decode(key.clone())? else {
+                return Err(Error::Internal(format!(
+                    "Unexpected key: {:?}",
+                    String::from_utf8(key)
+                )));
+            };
+            let snapshot_min = match value.is_empty() {
+                true => version,
+                false => bincode::deserialize::<Version>(&value)?.min(version),
+            };
+            watermark = Some(watermark.map_or(snapshot_min, |w| w.min(snapshot_min)));
+        }
+        Ok(watermark)
+    }
+
     // 扫描获取指定活跃的事务列表
     fn scan_active(engine: &mut MutexGuard<E>) -> Result<HashSet<Version>> {
         let mut active_versions = HashSet::new();
@@ -380,10 +777,22 @@ pub struct ScanResult {
 mod tests {
     use crate::{
         error::Result,
-        storage::{disk::DiskEngine, engine::Engine, memory::MemoryEngine},
+        storage::{
+            disk::{DiskEngine, SyncPolicy},
+            engine::Engine,
+            memory::MemoryEngine,
+        },
+    };
+
+    use std::{
+        ops::Bound,
+        sync::{Arc, Mutex},
     };
 
-    use super::Mvcc;
+    use super::{
+        KEY_FORMAT_VERSION, Mvcc, MvccKey, MvccKeyPrefix, MvccTransaction, ScanResult, Version,
+    };
+    use crate::error::Error;
 
     // 1. Get
     fn get(eng: impl Engine) -> Result<()> {
@@ -763,6 +1172,55 @@ mod tests {
         Ok(())
     }
 
+    fn delete_prefix(eng: impl Engine) -> Result<()> {
+        let mvcc = Mvcc::new(eng);
+        let tx = mvcc.begin()?;
+        tx.set(b"aa".to_vec(), b"val1".to_vec())?;
+        tx.set(b"ab".to_vec(), b"val2".to_vec())?;
+        tx.set(b"ac".to_vec(), b"val3".to_vec())?;
+        tx.set(b"b".to_vec(), b"val4".to_vec())?;
+        tx.delete(b"ac".to_vec())?;
+        tx.commit()?;
+
+        // 只删除可见的 key，已经删除的 key 不计数
+        let tx1 = mvcc.begin()?;
+        let tx2 = mvcc.begin()?;
+        assert_eq!(tx1.delete_prefix(b"a".to_vec())?, 2);
+        assert_eq!(tx1.scan_prefix(b"a".to_vec())?, vec![]);
+        assert_eq!(tx1.get(b"b".to_vec())?, Some(b"val4".to_vec()));
+
+        // 其他事务仍然可以读取，修改被删除的 key 时冲突
+        assert_eq!(tx2.scan_prefix(b"a".to_vec())?.len(), 2);
+        assert_eq!(
+            tx2.set(b"ab".to_vec(), b"val2-1".to_vec()),
+            Err(super::Error::WriteConflict)
+        );
+        tx2.rollback()?;
+
+        // 回滚之后数据恢复
+        tx1.rollback()?;
+        let tx3 = mvcc.begin()?;
+        assert_eq!(tx3.scan_prefix(b"a".to_vec())?.len(), 2);
+        assert_eq!(tx3.delete_prefix(b"a".to_vec())?, 2);
+        tx3.commit()?;
+        assert_eq!(mvcc.begin()?.scan_prefix(b"a".to_vec())?, vec![]);
+
+        assert_eq!(
+            mvcc.begin_read_only()?.delete_prefix(b"b".to_vec()),
+            Err(super::Error::ReadOnly)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_prefix() -> Result<()> {
+        delete_prefix(MemoryEngine::new())?;
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        delete_prefix(DiskEngine::new(p.clone())?)?;
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
     // 9. dirty read
     fn dirty_read(eng: impl Engine) -> Result<()> {
         let mvcc = Mvcc::new(eng);
@@ -915,4 +1373,578 @@ mod tests {
         std::fs::remove_dir_all(p.parent().unwrap())?;
         Ok(())
     }
+
+    // 13. version counter
+    #[test]
+    fn test_next_version_persisted() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        {
+            let mvcc = Mvcc::new(DiskEngine::new(p.clone())?);
+            let tx = mvcc.begin()?;
+            tx.set(b"key1".to_vec(), b"val1".to_vec())?;
+            tx.commit()?;
+            let tx1 = mvcc.begin()?;
+            assert_eq!(tx1.version(), 2);
+            tx1.rollback()?;
+        }
+
+        // 重新打开后，版本号从持久化的 NextVersion 继续分配
+        let mvcc = Mvcc::new(DiskEngine::new(p.clone())?);
+        let tx = mvcc.begin()?;
+        assert_eq!(tx.version(), 3);
+        assert_eq!(tx.get(b"key1".to_vec())?, Some(b"val1".to_vec()));
+        tx.commit()?;
+        assert_eq!(mvcc.begin()?.version(), 4);
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_stale_next_version() -> Result<()> {
+        let mvcc = Mvcc::new(MemoryEngine::new());
+        let tx = mvcc.begin()?;
+        tx.commit()?;
+        let tx1 = mvcc.begin()?;
+        assert_eq!(tx1.version(), 2);
+
+        // 模拟其他实例把 NextVersion 改回了旧值
+        mvcc.storage_engine.lock()?.set(
+            MvccKey::NextVersion.encode()?,
+            bincode::serialize(&(2 as Version))?,
+        )?;
+        assert_eq!(
+            mvcc.begin().err(),
+            Some(Error::Internal(
+                "stale next version 2, version 2 has already been allocated".into()
+            ))
+        );
+
+        // 另一个实例看到的 NextVersion 是活跃事务的版本号
+        let other = Mvcc {
+            storage_engine: mvcc.storage_engine.clone(),
+            allocated_version: Arc::new(Mutex::new(0)),
+            snapshots: Default::default(),
+        };
+        assert!(other.begin().is_err());
+
+        tx1.commit()?;
+        Ok(())
+    }
+
+    // 14. vacuum
+    fn count_versions<E: Engine>(mvcc: &Mvcc<E>) -> Result<usize> {
+        let mut prefix = MvccKeyPrefix::Version(vec![]).encode()?;
+        prefix.truncate(prefix.len() - 2);
+        let mut engine = mvcc.storage_engine.lock()?;
+        Ok(engine.scan_prefix(prefix).count())
+    }
+
+    fn vacuum(eng: impl Engine) -> Result<()> {
+        let mvcc = Mvcc::new(eng);
+
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"val0".to_vec())?;
+        tx.set(b"key2".to_vec(), b"val0".to_vec())?;
+        tx.set(b"key3".to_vec(), b"val0".to_vec())?;
+        tx.commit()?;
+
+        for i in 1..=100 {
+            let tx = mvcc.begin()?;
+            tx.set(b"key1".to_vec(), format!("val{}", i).into_bytes())?;
+            tx.set(b"key2".to_vec(), format!("val{}", i).into_bytes())?;
+            tx.commit()?;
+            mvcc.vacuum()?;
+            // 旧版本的数据被清理后，版本数量不会一直增长
+            assert_eq!(count_versions(&mvcc)?, 3);
+        }
+
+        // 活跃事务能看到的版本不会被清理
+        let reader = mvcc.begin()?;
+        for i in 101..=110 {
+            let tx = mvcc.begin()?;
+            tx.set(b"key1".to_vec(), format!("val{}", i).into_bytes())?;
+            tx.commit()?;
+            mvcc.vacuum()?;
+        }
+        assert_eq!(reader.get(b"key1".to_vec())?, Some(b"val100".to_vec()));
+        assert_eq!(reader.get(b"key2".to_vec())?, Some(b"val100".to_vec()));
+
+        let tx = mvcc.begin()?;
+        tx.delete(b"key3".to_vec())?;
+        tx.commit()?;
+        assert_eq!(reader.get(b"key3".to_vec())?, Some(b"val0".to_vec()));
+        reader.commit()?;
+
+        // 没有活跃事务后，每个 key 只保留最新的版本，删除标记也被清理
+        mvcc.vacuum()?;
+        assert_eq!(count_versions(&mvcc)?, 2);
+        assert_eq!(mvcc.vacuum()?, 0);
+
+        let tx = mvcc.begin()?;
+        assert_eq!(tx.get(b"key1".to_vec())?, Some(b"val110".to_vec()));
+        assert_eq!(tx.get(b"key2".to_vec())?, Some(b"val100".to_vec()));
+        assert_eq!(tx.get(b"key3".to_vec())?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vacuum() -> Result<()> {
+        vacuum(MemoryEngine::new())?;
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        vacuum(DiskEngine::new(p.clone())?)?;
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    // 活跃事务开始时还没有提交的事务，提交之后仍然对它不可见，vacuum 需要保留之前的版本
+    fn vacuum_overlapping(eng: impl Engine) -> Result<()> {
+        let mvcc = Mvcc::new(eng);
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"val0".to_vec())?;
+        tx.commit()?;
+
+        let writer = mvcc.begin()?;
+        let reader = mvcc.begin()?;
+        writer.set(b"key1".to_vec(), b"val1".to_vec())?;
+        writer.commit()?;
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"val2".to_vec())?;
+        tx.commit()?;
+
+        mvcc.vacuum()?;
+        assert_eq!(reader.get(b"key1".to_vec())?, Some(b"val0".to_vec()));
+        assert_eq!(
+            reader.scan_prefix(b"key".to_vec())?,
+            vec![ScanResult {
+                key: b"key1".to_vec(),
+                value: b"val0".to_vec(),
+            }]
+        );
+        reader.commit()?;
+
+        // 读事务结束之后只保留最新的版本
+        mvcc.vacuum()?;
+        assert_eq!(count_versions(&mvcc)?, 1);
+        assert_eq!(mvcc.begin()?.get(b"key1".to_vec())?, Some(b"val2".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_vacuum_overlapping() -> Result<()> {
+        vacuum_overlapping(MemoryEngine::new())?;
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        vacuum_overlapping(DiskEngine::new(p.clone())?)?;
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    // 只读事务和 AS OF 事务读取的版本不会被 vacuum 清理，结束之后才会清理
+    fn vacuum_read_only(eng: impl Engine) -> Result<()> {
+        let mvcc = Mvcc::new(eng);
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"old".to_vec())?;
+        tx.commit()?;
+        let version = tx.version();
+
+        // 只读事务开始时还没有提交的写事务，提交之后对它仍然不可见
+        let pending = mvcc.begin()?;
+        pending.set(b"key2".to_vec(), b"pending".to_vec())?;
+        let reader = mvcc.begin_read_only()?;
+        let as_of = mvcc.begin_read_only_at(version)?;
+        pending.commit()?;
+
+        for i in 0..3 {
+            let tx = mvcc.begin()?;
+            tx.set(b"key1".to_vec(), format!("new{}", i).into_bytes())?;
+            tx.delete(b"key2".to_vec())?;
+            tx.commit()?;
+            mvcc.vacuum()?;
+        }
+        for tx in [&reader, &as_of] {
+            assert_eq!(tx.get(b"key1".to_vec())?, Some(b"old".to_vec()));
+            assert_eq!(tx.get(b"key2".to_vec())?, None);
+        }
+
+        // 提交、回滚或者释放只读事务之后，旧版本可以被清理
+        reader.commit()?;
+        reader.commit()?;
+        as_of.rollback()?;
+        drop(mvcc.begin_read_only()?);
+        mvcc.vacuum()?;
+        assert_eq!(count_versions(&mvcc)?, 1);
+        assert_eq!(
+            mvcc.begin_read_only()?.get(b"key1".to_vec())?,
+            Some(b"new2".to_vec())
+        );
+
+        // 释放事务时也会注销快照
+        let reader = mvcc.begin_read_only()?;
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"new3".to_vec())?;
+        tx.commit()?;
+        mvcc.vacuum()?;
+        assert_eq!(count_versions(&mvcc)?, 2);
+        drop(reader);
+        mvcc.vacuum()?;
+        assert_eq!(count_versions(&mvcc)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vacuum_read_only() -> Result<()> {
+        vacuum_read_only(MemoryEngine::new())?;
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        vacuum_read_only(DiskEngine::new(p.clone())?)?;
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    // 15. rewrite own deleted key
+    fn rewrite_own_write(eng: impl Engine) -> Result<()> {
+        let mvcc = Mvcc::new(eng);
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"val1".to_vec())?;
+        tx.commit()?;
+
+        // 同一个事务中删除之后再写入，不会和自己之前的写入冲突
+        let tx1 = mvcc.begin()?;
+        let tx2 = mvcc.begin()?;
+        tx1.delete(b"key1".to_vec())?;
+        assert_eq!(tx1.get(b"key1".to_vec())?, None);
+        tx1.set(b"key1".to_vec(), b"val2".to_vec())?;
+        tx1.delete(b"key1".to_vec())?;
+        tx1.set(b"key1".to_vec(), b"val3".to_vec())?;
+        assert_eq!(tx1.get(b"key1".to_vec())?, Some(b"val3".to_vec()));
+
+        // 其他事务仍然会冲突
+        assert_eq!(tx2.delete(b"key1".to_vec()), Err(Error::WriteConflict));
+        assert_eq!(tx2.get(b"key1".to_vec())?, Some(b"val1".to_vec()));
+        tx2.rollback()?;
+        tx1.commit()?;
+
+        let tx = mvcc.begin()?;
+        assert_eq!(tx.get(b"key1".to_vec())?, Some(b"val3".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_own_write() -> Result<()> {
+        rewrite_own_write(MemoryEngine::new())?;
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        rewrite_own_write(DiskEngine::new(p.clone())?)?;
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    // 15. key format
+    #[test]
+    fn test_key_format() -> Result<()> {
+        // 新的存储在第一个事务中写入格式版本，重新打开时检查通过
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        {
+            let mvcc = Mvcc::new(DiskEngine::new(p.clone())?);
+            let tx = mvcc.begin()?;
+            tx.set(b"key1".to_vec(), b"val1".to_vec())?;
+            tx.commit()?;
+        }
+        let mvcc = Mvcc::new(DiskEngine::new(p.clone())?);
+        assert_eq!(mvcc.begin()?.get(b"key1".to_vec())?, Some(b"val1".to_vec()));
+        assert_eq!(
+            mvcc.storage_engine.lock()?.get(MvccKey::Format.encode()?)?,
+            Some(bincode::serialize(&KEY_FORMAT_VERSION)?)
+        );
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+
+        // 旧版本使用 bincode 编码 key 写入的数据
+        let mut eng = MemoryEngine::new();
+        eng.set(
+            bincode::serialize(&MvccKey::NextVersion)?,
+            bincode::serialize(&(2 as Version))?,
+        )?;
+        eng.set(
+            bincode::serialize(&MvccKey::Version(b"key1".to_vec(), 1))?,
+            bincode::serialize(&Some(b"val1".to_vec()))?,
+        )?;
+        let err = Mvcc::new(eng).begin().err().unwrap();
+        assert!(err.to_string().contains("older key encoding"), "{}", err);
+
+        // 旧版本的 keycode 编码同样没有格式版本
+        let mut eng = MemoryEngine::new();
+        eng.set(
+            MvccKey::NextVersion.encode()?,
+            bincode::serialize(&(2 as Version))?,
+        )?;
+        assert!(Mvcc::new(eng).begin().is_err());
+
+        // 不支持的格式版本
+        let mut eng = MemoryEngine::new();
+        eng.set(MvccKey::Format.encode()?, bincode::serialize(&99u32)?)?;
+        assert_eq!(
+            Mvcc::new(eng).begin().err(),
+            Some(Error::Internal(format!(
+                "unsupported key format version 99, expected {}",
+                KEY_FORMAT_VERSION
+            )))
+        );
+
+        Ok(())
+    }
+
+    // 16. read only
+    fn read_only_at(eng: impl Engine) -> Result<()> {
+        let mvcc = Mvcc::new(eng);
+        // 版本 1、2、3 依次写入 key1，版本 4 删除 key1
+        for i in 1..=3 {
+            let tx = mvcc.begin()?;
+            tx.set(b"key1".to_vec(), format!("val{}", i).into_bytes())?;
+            if i == 2 {
+                tx.set(b"key2".to_vec(), b"val2".to_vec())?;
+            }
+            tx.commit()?;
+        }
+        let tx = mvcc.begin()?;
+        tx.delete(b"key1".to_vec())?;
+        tx.commit()?;
+        // 版本 5 还没有提交，只读事务看不到它写入的数据
+        let tx5 = mvcc.begin()?;
+        tx5.set(b"key2".to_vec(), b"val5".to_vec())?;
+
+        let read = |version: Version, key: &[u8]| -> Result<Option<Vec<u8>>> {
+            mvcc.begin_read_only_at(version)?.get(key.to_vec())
+        };
+        assert_eq!(read(0, b"key1")?, None);
+        assert_eq!(read(1, b"key1")?, Some(b"val1".to_vec()));
+        assert_eq!(read(2, b"key1")?, Some(b"val2".to_vec()));
+        assert_eq!(read(3, b"key1")?, Some(b"val3".to_vec()));
+        assert_eq!(read(4, b"key1")?, None);
+        assert_eq!(read(1, b"key2")?, None);
+        assert_eq!(read(5, b"key2")?, Some(b"val2".to_vec()));
+
+        let tx = mvcc.begin_read_only_at(2)?;
+        assert_eq!(tx.version(), 2);
+        assert_eq!(
+            tx.scan_prefix(b"key".to_vec())?,
+            vec![
+                ScanResult {
+                    key: b"key1".to_vec(),
+                    value: b"val2".to_vec(),
+                },
+                ScanResult {
+                    key: b"key2".to_vec(),
+                    value: b"val2".to_vec(),
+                },
+            ]
+        );
+        // 只读事务不能写入数据，提交和回滚不影响其他事务
+        assert_eq!(tx.set(b"key3".to_vec(), vec![]), Err(Error::ReadOnly));
+        assert_eq!(tx.delete(b"key1".to_vec()), Err(Error::ReadOnly));
+        tx.commit()?;
+        mvcc.begin_read_only_at(5)?.rollback()?;
+
+        // 还没有分配的版本号
+        assert!(matches!(
+            mvcc.begin_read_only_at(6),
+            Err(Error::VersionNotFound {
+                version: 6,
+                latest: 5
+            })
+        ));
+        // 不指定版本时读取最新的已提交数据
+        let tx = mvcc.begin_read_only()?;
+        assert_eq!(tx.version(), 5);
+        assert_eq!(tx.get(b"key2".to_vec())?, Some(b"val2".to_vec()));
+
+        // 只读事务不影响版本号的分配
+        tx5.commit()?;
+        assert_eq!(mvcc.begin()?.version(), 6);
+        assert_eq!(read(5, b"key2")?, Some(b"val5".to_vec()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_at() -> Result<()> {
+        read_only_at(MemoryEngine::new())?;
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        read_only_at(DiskEngine::new(p.clone())?)?;
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    // 17. scan range
+    fn scan_range(eng: impl Engine) -> Result<()> {
+        let mvcc = Mvcc::new(eng);
+        let tx = mvcc.begin()?;
+        tx.set(b"a".to_vec(), b"a1".to_vec())?;
+        tx.set(b"b".to_vec(), b"b1".to_vec())?;
+        tx.set(b"b\0".to_vec(), b"b\0-1".to_vec())?;
+        tx.set(b"c".to_vec(), b"c1".to_vec())?;
+        tx.set(b"d".to_vec(), b"d1".to_vec())?;
+        tx.commit()?;
+
+        // 同一个 key 有多个版本以及删除标记
+        let tx1 = mvcc.begin()?;
+        tx1.set(b"b".to_vec(), b"b2".to_vec())?;
+        tx1.set(b"b".to_vec(), b"b3".to_vec())?;
+        tx1.delete(b"c".to_vec())?;
+        tx1.commit()?;
+
+        // 快照之后开始的事务和没有提交的事务的修改都不可见
+        let tx2 = mvcc.begin()?;
+        let tx3 = mvcc.begin()?;
+        tx3.set(b"a".to_vec(), b"a3".to_vec())?;
+        tx3.delete(b"d".to_vec())?;
+        tx3.set(b"e".to_vec(), b"e3".to_vec())?;
+        tx3.commit()?;
+        let tx4 = mvcc.begin()?;
+        tx4.set(b"c".to_vec(), b"c4".to_vec())?;
+
+        let scan = |tx: &MvccTransaction<_>,
+                    range: (Bound<Vec<u8>>, Bound<Vec<u8>>)|
+         -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+            Ok(tx
+                .scan(range)?
+                .into_iter()
+                .map(|r| (r.key, r.value))
+                .collect())
+        };
+        let kv = |k: &[u8], v: &[u8]| (k.to_vec(), v.to_vec());
+
+        assert_eq!(
+            scan(&tx2, (Bound::Unbounded, Bound::Unbounded))?,
+            vec![
+                kv(b"a", b"a1"),
+                kv(b"b", b"b3"),
+                kv(b"b\0", b"b\0-1"),
+                kv(b"d", b"d1")
+            ]
+        );
+        assert_eq!(
+            scan(
+                &tx2,
+                (
+                    Bound::Excluded(b"a".to_vec()),
+                    Bound::Included(b"d".to_vec())
+                )
+            )?,
+            vec![kv(b"b", b"b3"), kv(b"b\0", b"b\0-1"), kv(b"d", b"d1")]
+        );
+        assert_eq!(
+            scan(
+                &tx2,
+                (
+                    Bound::Included(b"b".to_vec()),
+                    Bound::Excluded(b"d".to_vec())
+                )
+            )?,
+            vec![kv(b"b", b"b3"), kv(b"b\0", b"b\0-1")]
+        );
+        assert_eq!(
+            scan(
+                &tx2,
+                (
+                    Bound::Excluded(b"b".to_vec()),
+                    Bound::Excluded(b"b\0".to_vec())
+                )
+            )?,
+            vec![]
+        );
+        assert_eq!(tx2.scan(b"a".to_vec()..b"b".to_vec())?.len(), 1);
+
+        // tx4 可以看到已经提交的 tx3 以及自己的修改
+        assert_eq!(
+            scan(&tx4, (Bound::Unbounded, Bound::Unbounded))?,
+            vec![
+                kv(b"a", b"a3"),
+                kv(b"b", b"b3"),
+                kv(b"b\0", b"b\0-1"),
+                kv(b"c", b"c4"),
+                kv(b"e", b"e3")
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_range() -> Result<()> {
+        scan_range(MemoryEngine::new())?;
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        scan_range(DiskEngine::new(p.clone())?)?;
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_sync() -> Result<()> {
+        let p = tempfile::tempdir()?.keep().join("sqldb-log");
+        let mvcc = Mvcc::new(DiskEngine::new(p.clone())?.with_sync_policy(SyncPolicy::Never));
+        let sync_count = || -> Result<u64> { Ok(mvcc.storage_engine.lock()?.sync_count()) };
+
+        // 写入时不 sync，提交时 sync 一次
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"val1".to_vec())?;
+        tx.set(b"key2".to_vec(), b"val2".to_vec())?;
+        assert_eq!(sync_count()?, 0);
+        tx.commit()?;
+        assert_eq!(sync_count()?, 1);
+        drop(tx);
+
+        // 只读事务不需要 sync
+        let tx = mvcc.begin_read_only()?;
+        assert_eq!(tx.get(b"key1".to_vec())?, Some(b"val1".to_vec()));
+        tx.commit()?;
+        assert_eq!(sync_count()?, 1);
+        drop(tx);
+
+        // 回滚不需要 sync
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"val3".to_vec())?;
+        tx.rollback()?;
+        assert_eq!(sync_count()?, 1);
+
+        // 事务持有存储引擎的引用，全部释放之后才能重新打开文件
+        drop(tx);
+        drop(mvcc);
+        let mvcc = Mvcc::new(DiskEngine::new(p.clone())?);
+        let tx = mvcc.begin_read_only()?;
+        assert_eq!(tx.get(b"key1".to_vec())?, Some(b"val1".to_vec()));
+        assert_eq!(tx.get(b"key2".to_vec())?, Some(b"val2".to_vec()));
+        drop(tx);
+        drop(mvcc);
+
+        std::fs::remove_dir_all(p.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> Result<()> {
+        let mvcc = Mvcc::new(MemoryEngine::new());
+        assert_eq!(mvcc.stats()?.active_txns, 0);
+
+        let tx1 = mvcc.begin()?;
+        let tx2 = mvcc.begin()?;
+        // 只读事务不在活跃事务列表中
+        let tx3 = mvcc.begin_read_only()?;
+        tx1.set(b"key1".to_vec(), b"val1".to_vec())?;
+        let stats = mvcc.stats()?;
+        assert_eq!(stats.active_txns, 2);
+        assert!(stats.storage.key_count > 0);
+
+        tx1.commit()?;
+        tx2.rollback()?;
+        tx3.commit()?;
+        let stats = mvcc.stats()?;
+        assert_eq!(stats.active_txns, 0);
+        assert_eq!(
+            stats.to_string(),
+            format!(
+                "keys: {}\nlogical bytes: {}\nfile bytes: 0\ngarbage bytes: 0\nactive transactions: 0",
+                stats.storage.key_count, stats.storage.logical_bytes
+            )
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,40 @@
+// 每个集成测试都是单独的 crate，只用到其中一部分辅助函数，其余的会被认为是未使用的代码
+#![allow(dead_code)]
+
+use futures::{SinkExt, TryStreamExt};
+use sqldb_rs::protocol::{ClientCodec, Reply, Request, Response};
+use sqldb_rs::server::{Server, ServerHandle};
+use sqldb_rs::sql::engine::kv::KVEngine;
+use sqldb_rs::storage::disk::DiskEngine;
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
+
+pub type Conn = Framed<TcpStream, ClientCodec>;
+
+// 每个测试使用单独的数据文件
+pub fn new_engine() -> KVEngine<DiskEngine> {
+    let path = tempfile::tempdir().unwrap().keep().join("sqldb-log");
+    KVEngine::new(DiskEngine::new(path).unwrap())
+}
+
+// 在当前进程中启动服务，由系统分配空闲端口
+pub async fn start_server() -> ServerHandle {
+    Server::start("127.0.0.1:0", new_engine()).await.unwrap()
+}
+
+// 建立一个新的客户端连接
+pub async fn connect(server: &ServerHandle) -> Conn {
+    let stream = TcpStream::connect(server.addr()).await.unwrap();
+    Framed::new(stream, ClientCodec::new())
+}
+
+// 发送一条命令，读取对应的响应以及服务端的执行时间
+pub async fn send(conn: &mut Conn, cmd: &str) -> Reply {
+    conn.send(Request::Execute(cmd.to_string())).await.unwrap();
+    conn.try_next().await.unwrap().unwrap()
+}
+
+// 发送一条命令，读取对应的响应
+pub async fn request(conn: &mut Conn, cmd: &str) -> Response {
+    send(conn, cmd).await.response
+}
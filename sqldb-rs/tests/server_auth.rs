@@ -0,0 +1,121 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{Conn, connect, new_engine, request, start_server};
+use futures::{SinkExt, TryStreamExt};
+use sqldb_rs::auth::Credentials;
+use sqldb_rs::error::Error;
+use sqldb_rs::protocol::{Request, Response};
+use sqldb_rs::server::{Server, ServerHandle};
+
+async fn start_auth_server() -> ServerHandle {
+    let mut credentials = Credentials::new();
+    credentials.add_user("admin", "secret").unwrap();
+    Server::new(new_engine())
+        .with_credentials(credentials)
+        .with_auth_timeout(Duration::from_millis(200))
+        .listen("127.0.0.1:0")
+        .await
+        .unwrap()
+}
+
+async fn auth(conn: &mut Conn, user: &str, password: &str) -> Response {
+    conn.send(Request::Auth {
+        user: user.to_string(),
+        password: password.to_string(),
+    })
+    .await
+    .unwrap();
+    conn.try_next().await.unwrap().unwrap().response
+}
+
+// 读取服务端发来的下一个响应，连接断开时返回 None
+async fn next(conn: &mut Conn) -> Option<Response> {
+    conn.try_next()
+        .await
+        .ok()
+        .flatten()
+        .map(|reply| reply.response)
+}
+
+#[tokio::test]
+async fn test_auth_success() {
+    let server = start_auth_server().await;
+    let mut conn = connect(&server).await;
+
+    assert_eq!(
+        auth(&mut conn, "admin", "secret").await,
+        Response::Text("AUTH OK".to_string())
+    );
+    request(&mut conn, "create table t (a int primary key);").await;
+    request(&mut conn, "insert into t values (1);").await;
+    assert!(matches!(
+        request(&mut conn, "select * from t;").await,
+        Response::Ok(_)
+    ));
+
+    // 认证之后不能切换用户，连接可以继续使用
+    match auth(&mut conn, "admin", "secret").await {
+        Response::Error { code, .. } => assert_eq!(code, "28000"),
+        resp => panic!("unexpected response {:?}", resp),
+    }
+    assert!(matches!(
+        request(&mut conn, "select * from t;").await,
+        Response::Ok(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_auth_rejected() {
+    let server = start_auth_server().await;
+
+    // 密码错误，或者用户不存在时返回错误并断开连接
+    for (user, password) in [("admin", "wrong"), ("nobody", "secret")] {
+        let mut conn = connect(&server).await;
+        match auth(&mut conn, user, password).await {
+            Response::Error { code, error } => {
+                assert_eq!(code, "28000");
+                assert!(matches!(error, Error::AuthFailed(_)), "{:?}", error);
+            }
+            resp => panic!("unexpected response {:?}", resp),
+        }
+        assert_eq!(next(&mut conn).await, None);
+    }
+
+    // 没有认证时不能执行语句
+    let mut conn = connect(&server).await;
+    assert_eq!(
+        request(&mut conn, "drop table t;").await,
+        Response::error(Error::AuthFailed("authentication required".to_string()))
+    );
+    assert_eq!(next(&mut conn).await, None);
+
+    // 没有在规定的时间内认证的连接被断开
+    let mut conn = connect(&server).await;
+    assert_eq!(
+        next(&mut conn).await,
+        Some(Response::error(Error::AuthFailed(
+            "authentication timeout".to_string()
+        )))
+    );
+    assert_eq!(next(&mut conn).await, None);
+}
+
+#[tokio::test]
+async fn test_no_auth() {
+    // 没有开启认证时可以直接执行语句，认证请求总是成功
+    let server = start_server().await;
+    let mut conn = connect(&server).await;
+    request(&mut conn, "create table t (a int primary key);").await;
+
+    let mut conn2 = connect(&server).await;
+    assert_eq!(
+        auth(&mut conn2, "anyone", "").await,
+        Response::Text("AUTH OK".to_string())
+    );
+    assert!(matches!(
+        request(&mut conn2, "select * from t;").await,
+        Response::Ok(_)
+    ));
+}
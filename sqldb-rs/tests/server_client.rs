@@ -0,0 +1,273 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use common::start_server;
+use sqldb_rs::client::{Client, Output, OutputMode, split_statements};
+use sqldb_rs::server::{Server, ServerHandle};
+use sqldb_rs::sql::engine::kv::KVEngine;
+use sqldb_rs::storage::disk::DiskEngine;
+
+// 执行一条命令，返回渲染后的文本，错误时返回 Err
+async fn execute(client: &mut Client, cmd: &str) -> Result<String, String> {
+    match client.execute_sql(cmd).await.unwrap() {
+        Output::Ok { text, .. } => Ok(text),
+        Output::Error(text) => Err(text),
+    }
+}
+
+// 使用指定的数据文件启动服务，重启之后数据还在
+async fn start_at(addr: &str, path: &Path) -> ServerHandle {
+    let engine = KVEngine::new(DiskEngine::new(path.to_path_buf()).unwrap());
+    Server::start(addr, engine).await.unwrap()
+}
+
+fn data_path() -> PathBuf {
+    tempfile::tempdir().unwrap().keep().join("sqldb-log")
+}
+
+// 关闭服务之后在同一个端口上重新启动
+async fn restart(server: ServerHandle, path: &Path) -> ServerHandle {
+    let addr = server.addr().to_string();
+    server.shutdown().await.unwrap();
+    start_at(&addr, path).await
+}
+
+#[tokio::test]
+async fn test_client_script() {
+    let server = start_server().await;
+    let mut client = Client::new(server.addr(), None).await.unwrap();
+
+    // 和交互模式一样，按分号拆分之后逐条发送
+    let script = "
+        create table t (a int primary key, b string);
+        insert into t values (1, 'x'), (2, 'a;b'), (3, null);
+        update t set b = 'y' where a = 1;
+        delete from t where a = 3;
+        select * from t order by a;
+    ";
+    let (stmts, rest) = split_statements(script);
+    assert_eq!(rest, "");
+    let mut outputs = Vec::new();
+    for stmt in &stmts {
+        outputs.push(execute(&mut client, stmt).await.unwrap());
+    }
+    assert_eq!(
+        outputs,
+        vec![
+            "CREATE TABLE t",
+            "INSERT 3 ROWS. keys: 1, 2, 3",
+            "UPDATE 1 ROWS.",
+            "DELETE 1 ROWS.",
+            "a |b  \n--+----\n1 |y  \n2 |a;b\n(2 rows)",
+        ]
+    );
+
+    // 一次请求中的多条语句，每条语句的结果单独一块
+    assert_eq!(
+        execute(
+            &mut client,
+            "insert into t values (4, 'z'); select count(*) from t;"
+        )
+        .await
+        .unwrap(),
+        "INSERT 1 ROWS. keys: 4\ncount\n------\n    3\n(1 rows)"
+    );
+
+    // 服务端处理的命令
+    assert_eq!(execute(&mut client, "SHOW TABLES").await.unwrap(), "t");
+
+    // 未知的命令和语法错误返回错误，连接可以继续使用
+    let err = execute(&mut client, "frobnicate t;").await.unwrap_err();
+    assert!(err.starts_with("ERROR 42601: parse error at line 1, column 1"));
+    let err = execute(&mut client, "SHOW TABLE missing")
+        .await
+        .unwrap_err();
+    assert!(err.starts_with("ERROR 42P01: "));
+    assert_eq!(
+        execute(&mut client, "select a from t where a = 4;")
+            .await
+            .unwrap(),
+        "a\n--\n4\n(1 rows)"
+    );
+
+    // 事务的状态由客户端根据结果记录
+    execute(&mut client, "begin;").await.unwrap();
+    assert!(client.txn_version().is_some());
+    execute(&mut client, "delete from t;").await.unwrap();
+    execute(&mut client, "rollback;").await.unwrap();
+    assert_eq!(client.txn_version(), None);
+
+    // 其他输出格式
+    client.set_output(OutputMode::Csv);
+    assert_eq!(
+        execute(&mut client, "select * from t order by a;")
+            .await
+            .unwrap(),
+        "a,b\n1,y\n2,a;b\n4,z"
+    );
+    client.set_output(OutputMode::Json);
+    assert_eq!(
+        execute(&mut client, "select b from t where a = 1;")
+            .await
+            .unwrap(),
+        r#"{"columns":["b"],"rows":[["y"]],"types":["STRING"]}"#
+    );
+    let err = execute(&mut client, "select * from missing;")
+        .await
+        .unwrap_err();
+    assert!(err.starts_with(r#"{"code":"42P01","error":"#));
+}
+
+#[tokio::test]
+async fn test_client_large_result() {
+    let server = start_server().await;
+    let mut client = Client::new(server.addr(), None).await.unwrap();
+    client.set_output(OutputMode::Csv);
+
+    // 结果比 TCP 的缓冲区大，需要分多次读取完整的一帧
+    execute(&mut client, "create table t (a int primary key, b string);")
+        .await
+        .unwrap();
+    let value = "v".repeat(1000);
+    let values = (0..500)
+        .map(|i| format!("({}, '{}')", i, value))
+        .collect::<Vec<_>>()
+        .join(", ");
+    execute(&mut client, &format!("insert into t values {};", values))
+        .await
+        .unwrap();
+    let text = execute(&mut client, "select * from t;").await.unwrap();
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 501);
+    assert_eq!(lines[500], format!("499,{}", value));
+
+    // 之后的请求和响应仍然一一对应
+    assert_eq!(
+        execute(&mut client, "select count(*) from t;")
+            .await
+            .unwrap(),
+        "count\n500"
+    );
+}
+
+#[tokio::test]
+async fn test_client_reconnect() {
+    let path = data_path();
+    let server = start_at("127.0.0.1:0", &path).await;
+    let mut client = Client::new(server.addr(), None).await.unwrap();
+    execute(&mut client, "create table t (a int primary key);")
+        .await
+        .unwrap();
+    execute(&mut client, "insert into t values (1), (2);")
+        .await
+        .unwrap();
+
+    // 重启之后旧的连接上只剩关闭通知，客户端重连之后重新执行命令
+    let server = restart(server, &path).await;
+    assert_eq!(
+        execute(&mut client, "select * from t;").await.unwrap(),
+        "a\n--\n1\n2\n(2 rows)"
+    );
+    execute(&mut client, "insert into t values (3);")
+        .await
+        .unwrap();
+
+    // 服务没有启动时返回连接错误，启动之后可以继续使用
+    let addr = server.addr().to_string();
+    server.shutdown().await.unwrap();
+    assert!(client.execute_sql("select * from t;").await.is_err());
+    let _server = start_at(&addr, &path).await;
+    assert_eq!(
+        execute(&mut client, "select count(*) from t;")
+            .await
+            .unwrap(),
+        "count\n------\n    3\n(1 rows)"
+    );
+}
+
+#[tokio::test]
+async fn test_client_reconnect_in_transaction() {
+    let path = data_path();
+    let server = start_at("127.0.0.1:0", &path).await;
+    let mut client = Client::new(server.addr(), None).await.unwrap();
+    execute(&mut client, "create table t (a int primary key);")
+        .await
+        .unwrap();
+    execute(&mut client, "begin;").await.unwrap();
+    execute(&mut client, "insert into t values (1);")
+        .await
+        .unwrap();
+
+    // 事务被服务端回滚，事务中的语句不能在新的连接上自动执行
+    let _server = restart(server, &path).await;
+    assert_eq!(
+        execute(&mut client, "insert into t values (2);").await,
+        Err(
+            "ERROR 08006: connection to server was lost, the transaction was rolled back"
+                .to_string()
+        )
+    );
+    assert_eq!(client.txn_version(), None);
+    assert_eq!(
+        execute(&mut client, "select count(*) from t;")
+            .await
+            .unwrap(),
+        "count\n------\n    0\n(1 rows)"
+    );
+}
+
+#[tokio::test]
+async fn test_client_result_too_large() {
+    let engine = KVEngine::new(DiskEngine::new(data_path()).unwrap());
+    let server = Server::new(engine)
+        .with_max_frame_length(64 * 1024)
+        .listen("127.0.0.1:0")
+        .await
+        .unwrap();
+    let mut client = Client::new(server.addr(), None).await.unwrap();
+    client.set_max_frame_length(64 * 1024);
+
+    execute(&mut client, "create table t (a int primary key, b string);")
+        .await
+        .unwrap();
+    let value = "v".repeat(1000);
+    for i in 0..100 {
+        execute(
+            &mut client,
+            &format!("insert into t values ({}, '{}');", i, value),
+        )
+        .await
+        .unwrap();
+    }
+
+    // 结果超过最大帧长度时返回错误，而不是让客户端一直等待
+    let err = execute(&mut client, "select * from t;").await.unwrap_err();
+    assert!(
+        err.starts_with("ERROR 54000: message too large: "),
+        "{}",
+        err
+    );
+
+    // 请求太长时客户端直接返回错误，不会发送到服务端
+    let values = (100..200)
+        .map(|i| format!("({}, '{}')", i, value))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let err = execute(&mut client, &format!("insert into t values {};", values))
+        .await
+        .unwrap_err();
+    assert!(
+        err.starts_with("ERROR 54000: message too large: "),
+        "{}",
+        err
+    );
+
+    // 之后的请求和响应仍然一一对应
+    assert_eq!(
+        execute(&mut client, "select count(*) from t;")
+            .await
+            .unwrap(),
+        "count\n------\n  100\n(1 rows)"
+    );
+}
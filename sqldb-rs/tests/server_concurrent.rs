@@ -0,0 +1,142 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{connect, request, start_server};
+use sqldb_rs::error::Error;
+use sqldb_rs::protocol::Response;
+use sqldb_rs::sql::{executor::ResultSet, types::Value};
+
+const TASKS: i64 = 8;
+const ROWS: i64 = 20;
+
+// 查询单个整数值
+async fn query_int(conn: &mut common::Conn, sql: &str) -> i64 {
+    match request(conn, sql).await {
+        Response::Ok(ResultSet::Scan { rows, .. }) => match rows[0][0] {
+            Value::Integer(v) => v,
+            ref v => panic!("unexpected value {:?}", v),
+        },
+        resp => panic!("unexpected response {:?}", resp),
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_sessions() {
+    let server = start_server().await;
+    let mut conn = connect(&server).await;
+    request(&mut conn, "create table t (a int primary key, b int);").await;
+    request(&mut conn, "insert into t values (-1, 0);").await;
+
+    // 多个连接同时写入不同的数据，并读取自己写入的数据
+    let mut handles = Vec::new();
+    for i in 0..TASKS {
+        let mut conn = connect(&server).await;
+        handles.push(tokio::spawn(async move {
+            for j in 0..ROWS {
+                let a = i * ROWS + j;
+                let resp = request(
+                    &mut conn,
+                    &format!("insert into t values ({}, {});", a, i + 1),
+                )
+                .await;
+                assert_eq!(
+                    resp,
+                    Response::Ok(ResultSet::Insert {
+                        count: 1,
+                        keys: vec![Value::Integer(a)],
+                    })
+                );
+            }
+            let sql = format!("select count(a) from t where b = {};", i + 1);
+            assert_eq!(query_int(&mut conn, &sql).await, ROWS);
+        }));
+    }
+    for handle in handles {
+        tokio::time::timeout(Duration::from_secs(30), handle)
+            .await
+            .expect("concurrent sessions deadlocked")
+            .unwrap();
+    }
+    assert_eq!(
+        query_int(&mut conn, "select count(a) from t where a >= 0;").await,
+        TASKS * ROWS
+    );
+
+    // 多个事务同时修改同一行，冲突的事务返回 WriteConflict，其余事务正常提交
+    let mut handles = Vec::new();
+    for _ in 0..TASKS {
+        let mut conn = connect(&server).await;
+        handles.push(tokio::spawn(async move {
+            request(&mut conn, "begin;").await;
+            match request(&mut conn, "update t set b = b + 1 where a = -1;").await {
+                Response::Ok(ResultSet::Update { count: 1 }) => {
+                    let resp = request(&mut conn, "commit;").await;
+                    assert!(
+                        matches!(resp, Response::Ok(ResultSet::Commit { .. })),
+                        "{:?}",
+                        resp
+                    );
+                    1
+                }
+                Response::Error {
+                    error: Error::WriteConflict,
+                    ..
+                } => {
+                    request(&mut conn, "rollback;").await;
+                    0
+                }
+                resp => panic!("unexpected response {:?}", resp),
+            }
+        }));
+    }
+    let mut committed = 0;
+    for handle in handles {
+        committed += tokio::time::timeout(Duration::from_secs(30), handle)
+            .await
+            .expect("concurrent transactions deadlocked")
+            .unwrap();
+    }
+    assert!(committed >= 1);
+    assert_eq!(
+        query_int(&mut conn, "select b from t where a = -1;").await,
+        committed
+    );
+}
+
+#[tokio::test]
+async fn test_write_conflict() {
+    let server = start_server().await;
+    let mut conn1 = connect(&server).await;
+    let mut conn2 = connect(&server).await;
+    request(&mut conn1, "create table t (a int primary key, b int);").await;
+    request(&mut conn1, "insert into t values (1, 1);").await;
+
+    request(&mut conn1, "begin;").await;
+    request(&mut conn2, "begin;").await;
+    assert_eq!(
+        request(&mut conn1, "update t set b = 2 where a = 1;").await,
+        Response::Ok(ResultSet::Update { count: 1 })
+    );
+    assert_eq!(
+        request(&mut conn2, "update t set b = 3 where a = 1;").await,
+        Response::error(Error::WriteConflict)
+    );
+    request(&mut conn2, "rollback;").await;
+    request(&mut conn1, "commit;").await;
+
+    // 冲突之后，已有连接和新连接都可以继续正常使用
+    assert_eq!(
+        query_int(&mut conn2, "select b from t where a = 1;").await,
+        2
+    );
+    let mut conn3 = connect(&server).await;
+    assert_eq!(
+        request(&mut conn3, "update t set b = 4 where a = 1;").await,
+        Response::Ok(ResultSet::Update { count: 1 })
+    );
+    assert_eq!(
+        query_int(&mut conn1, "select b from t where a = 1;").await,
+        4
+    );
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::{Conn, connect, request, start_server};
+use sqldb_rs::error::Error;
+use sqldb_rs::protocol::Response;
+use sqldb_rs::sql::{
+    executor::ResultSet,
+    types::{DataType, Value},
+};
+
+// JSON 模式下每个响应是一个 JSON 文档
+async fn request_json(conn: &mut Conn, cmd: &str) -> serde_json::Value {
+    match request(conn, cmd).await {
+        Response::Text(text) => serde_json::from_str(&text).unwrap(),
+        resp => panic!("unexpected response {:?}", resp),
+    }
+}
+
+#[tokio::test]
+async fn test_json_format() {
+    let server = start_server().await;
+    let mut conn = connect(&server).await;
+
+    // 默认返回结果集
+    request(
+        &mut conn,
+        "create table t1 (a int primary key, b text, c float);",
+    )
+    .await;
+    request(
+        &mut conn,
+        "insert into t1 values (1, 'Hello', 1.5), (2, null, null);",
+    )
+    .await;
+    match request(&mut conn, "select * from t1;").await {
+        Response::Ok(ResultSet::Scan { rows, .. }) => assert_eq!(rows.len(), 2),
+        resp => panic!("unexpected response {:?}", resp),
+    }
+
+    assert_eq!(
+        request_json(&mut conn, "format json;").await,
+        serde_json::json!({ "message": "FORMAT JSON" })
+    );
+    assert_eq!(
+        request_json(&mut conn, "select * from t1 order by a desc;").await,
+        serde_json::json!({
+            "columns": ["a", "b", "c"],
+            "types": ["INTEGER", "STRING", "FLOAT"],
+            "rows": [[2, null, null], [1, "Hello", 1.5]],
+        })
+    );
+    assert_eq!(
+        request_json(&mut conn, "insert into t1 values (3, 'x', 0.0);").await,
+        serde_json::json!({ "message": "INSERT 1 ROWS. keys: 3", "keys": [3] })
+    );
+
+    // 错误也返回 JSON，带有错误码
+    assert_eq!(
+        request_json(&mut conn, "select * from t2;").await,
+        serde_json::json!({ "code": "42P01", "error": "table t2 does not exist" })
+    );
+    let err = request_json(&mut conn, "select * from;").await;
+    assert_eq!(err["code"], "42601", "{}", err);
+    assert!(err["error"].is_string(), "{}", err);
+
+    // 切换回表格
+    assert_eq!(
+        request(&mut conn, "FORMAT TEXT;").await,
+        Response::Text("FORMAT TEXT".to_string())
+    );
+    let resp = request(&mut conn, "select a from t1 where a = 3;").await;
+    assert_eq!(
+        resp,
+        Response::Ok(ResultSet::Scan {
+            columns: vec!["a".to_string()],
+            types: vec![Some(DataType::Integer)],
+            rows: vec![vec![Value::Integer(3)]],
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_round_trip() {
+    let server = start_server().await;
+    let mut conn = connect(&server).await;
+
+    request(&mut conn, "create table t (a int primary key, b text);").await;
+    // 字符串中包含换行、中文以及原来的结束标志
+    let text = "第一行\n第二行 !!!end!!!\r\nlast";
+    request(&mut conn, &format!("insert into t values (1, '{}');", text)).await;
+    assert_eq!(
+        request(&mut conn, "select b from t;").await,
+        Response::Ok(ResultSet::Scan {
+            columns: vec!["b".to_string()],
+            types: vec![Some(DataType::String)],
+            rows: vec![vec![Value::String(text.to_string())]],
+        })
+    );
+
+    // 字符串中包含单引号和分号
+    request(&mut conn, "insert into t values (2, 'it''s; ok;');").await;
+    assert_eq!(
+        request(&mut conn, "select b from t where a = 2;").await,
+        Response::Ok(ResultSet::Scan {
+            columns: vec!["b".to_string()],
+            types: vec![Some(DataType::String)],
+            rows: vec![vec![Value::String("it's; ok;".to_string())]],
+        })
+    );
+
+    // 一次请求中的多条语句依次执行，每条语句返回一个结果
+    assert_eq!(
+        request(
+            &mut conn,
+            "insert into t values (3, 'x'); select b from t where a = 3;"
+        )
+        .await,
+        Response::Batch(vec![
+            ResultSet::Insert {
+                count: 1,
+                keys: vec![Value::Integer(3)],
+            },
+            ResultSet::Scan {
+                columns: vec!["b".to_string()],
+                types: vec![Some(DataType::String)],
+                rows: vec![vec![Value::String("x".to_string())]],
+            },
+        ])
+    );
+
+    // 错误以类型化的响应返回，带有稳定的错误码
+    assert_eq!(
+        request(&mut conn, "select * from missing;").await,
+        Response::Error {
+            code: "42P01".to_string(),
+            error: Error::TableNotFound("missing".to_string()),
+        }
+    );
+    match request(&mut conn, "insert into t values (1, 'dup');").await {
+        Response::Error { code, error } => {
+            assert_eq!(code, "23505");
+            assert_eq!(
+                error.to_string(),
+                "duplicate value 1 for key column a in table t"
+            );
+        }
+        resp => panic!("unexpected response {:?}", resp),
+    }
+    // 多条语句中的错误保留原来的错误码
+    match request(&mut conn, "select b from t; select c from t;").await {
+        Response::Error { code, error } => {
+            assert_eq!(code, "42703");
+            assert_eq!(
+                error.to_string(),
+                "statement 2 of 2 failed: column c does not exist (available columns: t.a, t.b)"
+            );
+        }
+        resp => panic!("unexpected response {:?}", resp),
+    }
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::{Conn, connect, new_engine, request, start_server};
+use sqldb_rs::error::Error;
+use sqldb_rs::protocol::{ClientCodec, Response};
+use sqldb_rs::server::Server;
+use sqldb_rs::sql::{executor::ResultSet, types::Value};
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
+
+// 查询第一列的值
+async fn first_column(conn: &mut Conn, sql: &str) -> Vec<Value> {
+    match request(conn, sql).await {
+        Response::Ok(ResultSet::Scan { rows, .. }) => {
+            rows.into_iter().map(|r| r[0].clone()).collect()
+        }
+        resp => panic!("unexpected response {:?}", resp),
+    }
+}
+
+#[tokio::test]
+async fn test_read_only_listener() {
+    let server = Server::new(new_engine())
+        .with_read_only_listener("127.0.0.1:0".parse().unwrap())
+        .listen("127.0.0.1:0")
+        .await
+        .unwrap();
+    let mut conn = connect(&server).await;
+    request(&mut conn, "create table t (a int primary key, b int);").await;
+    request(&mut conn, "insert into t values (1, 10), (2, 20);").await;
+
+    let addr = server.read_only_addr().unwrap();
+    let mut ro = Framed::new(TcpStream::connect(addr).await.unwrap(), ClientCodec::new());
+
+    // 只读地址上的连接不能修改数据，也不能关闭只读和服务
+    for sql in [
+        "insert into t values (3, 30);",
+        "update t set b = 0;",
+        "delete from t;",
+        "truncate table t;",
+        "create table t2 (a int primary key);",
+        "drop table t;",
+        "set readonly = false;",
+        "shutdown;",
+    ] {
+        assert_eq!(
+            request(&mut ro, sql).await,
+            Response::error(Error::ReadOnly),
+            "{}",
+            sql
+        );
+    }
+
+    // 查询不受影响，可以看到其他连接写入的数据
+    assert_eq!(
+        first_column(&mut ro, "select b from t;").await,
+        vec![Value::Integer(10), Value::Integer(20)]
+    );
+    request(&mut conn, "insert into t values (3, 30);").await;
+    assert_eq!(
+        first_column(&mut ro, "select count(*) from t;").await,
+        vec![Value::Integer(3)]
+    );
+    assert!(matches!(
+        request(&mut ro, "show tables;").await,
+        Response::Text(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_set_read_only() {
+    let server = start_server().await;
+    assert_eq!(server.read_only_addr(), None);
+    let mut conn = connect(&server).await;
+    request(&mut conn, "create table t (a int primary key);").await;
+
+    // 普通的连接可以切换只读
+    assert_eq!(
+        request(&mut conn, "SET readonly = true;").await,
+        Response::Text("SET READONLY = true".to_string())
+    );
+    assert_eq!(
+        request(&mut conn, "insert into t values (1);").await,
+        Response::error(Error::ReadOnly)
+    );
+    assert_eq!(
+        first_column(&mut conn, "select count(*) from t;").await,
+        vec![Value::Integer(0)]
+    );
+    assert_eq!(
+        request(&mut conn, "set readonly = false").await,
+        Response::Text("SET READONLY = false".to_string())
+    );
+    request(&mut conn, "insert into t values (1);").await;
+    assert_eq!(
+        first_column(&mut conn, "select count(*) from t;").await,
+        vec![Value::Integer(1)]
+    );
+
+    match request(&mut conn, "set readonly = yes;").await {
+        Response::Error { code, .. } => assert_eq!(code, "42601"),
+        resp => panic!("unexpected response {:?}", resp),
+    }
+}
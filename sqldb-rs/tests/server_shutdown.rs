@@ -0,0 +1,116 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{Conn, connect, new_engine, request, start_server};
+use futures::{SinkExt, TryStreamExt};
+use sqldb_rs::error::Error;
+use sqldb_rs::protocol::{Request, Response};
+use sqldb_rs::server::{Server, ServerHandle};
+use tokio::net::TcpStream;
+
+// 2700 万行的 cross join，在超时之前不会结束
+const LONG_QUERY: &str = "select count(*) from t x cross join t y cross join t z;";
+
+async fn start_with_grace_period(grace_period: Duration) -> ServerHandle {
+    Server::new(new_engine())
+        .with_grace_period(grace_period)
+        .listen("127.0.0.1:0")
+        .await
+        .unwrap()
+}
+
+async fn prepare(conn: &mut Conn) {
+    request(conn, "create table t (a int primary key, b int);").await;
+    let values = (0..300)
+        .map(|i| format!("({}, {})", i, i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    request(conn, &format!("insert into t values {};", values)).await;
+}
+
+// 读取服务端发来的下一个响应，连接断开时返回 None
+async fn next(conn: &mut Conn) -> Option<Response> {
+    conn.try_next()
+        .await
+        .ok()
+        .flatten()
+        .map(|reply| reply.response)
+}
+
+#[tokio::test]
+async fn test_shutdown_waits_for_statement() {
+    let server = start_with_grace_period(Duration::from_secs(30)).await;
+    let addr = server.addr();
+    let mut conn = connect(&server).await;
+    let mut idle = connect(&server).await;
+    prepare(&mut conn).await;
+
+    // 语句在 1 秒后超时结束，关闭服务时需要等待它
+    request(&mut conn, "set timeout = 1000;").await;
+    conn.send(Request::Execute(LONG_QUERY.to_string()))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let start = Instant::now();
+    assert_eq!(server.shutdown().await.unwrap(), 0);
+    assert!(start.elapsed() >= Duration::from_millis(500));
+
+    // 执行中的语句正常返回，之后收到关闭通知并断开
+    assert_eq!(
+        next(&mut conn).await,
+        Some(Response::error(Error::Timeout(1000)))
+    );
+    assert_eq!(
+        next(&mut conn).await,
+        Some(Response::error(Error::ShuttingDown))
+    );
+    assert_eq!(next(&mut conn).await, None);
+
+    // 空闲的连接直接收到关闭通知
+    assert_eq!(
+        next(&mut idle).await,
+        Some(Response::error(Error::ShuttingDown))
+    );
+    assert_eq!(next(&mut idle).await, None);
+
+    // 不再接收新的连接
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn test_shutdown_grace_period() {
+    let server = start_with_grace_period(Duration::from_millis(100)).await;
+    let mut conn = connect(&server).await;
+    prepare(&mut conn).await;
+
+    // 超时时间比等待时间长，超过等待时间后连接被断开
+    request(&mut conn, "set timeout = 3000;").await;
+    conn.send(Request::Execute(LONG_QUERY.to_string()))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let start = Instant::now();
+    assert_eq!(server.shutdown().await.unwrap(), 1);
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(next(&mut conn).await, None);
+}
+
+#[tokio::test]
+async fn test_shutdown_command() {
+    let server = start_server().await;
+    let mut conn = connect(&server).await;
+    prepare(&mut conn).await;
+
+    assert_eq!(
+        request(&mut conn, "SHUTDOWN;").await,
+        Response::Text("SHUTDOWN".to_string())
+    );
+    assert_eq!(server.wait().await.unwrap(), 0);
+    assert_eq!(
+        next(&mut conn).await,
+        Some(Response::error(Error::ShuttingDown))
+    );
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::{connect, request, send, start_server};
+use sqldb_rs::protocol::Response;
+
+#[tokio::test]
+async fn test_show_stats() {
+    let server = start_server().await;
+    let mut conn = connect(&server).await;
+
+    request(&mut conn, "create table t (a int primary key, b int);").await;
+    request(&mut conn, "insert into t values (1, 1), (2, 2);").await;
+    request(&mut conn, "select * from t; select * from t where a = 1;").await;
+    request(&mut conn, "insert into t values (1, 1);").await;
+
+    // 每个响应都带有服务端的执行时间
+    let reply = send(&mut conn, "show stats;").await;
+    assert!(!reply.elapsed.is_zero());
+    let text = match reply.response {
+        Response::Text(text) => text,
+        resp => panic!("unexpected response {:?}", resp),
+    };
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[..7],
+        [
+            "statements: 5",
+            "errors: 1",
+            "rows read: 3",
+            "rows written: 2",
+            "commits: 4",
+            "rollbacks: 1",
+            "write conflicts: 0",
+        ]
+    );
+    assert!(lines[7].starts_with("elapsed: "), "{}", text);
+
+    // 统计信息只属于当前连接
+    let mut conn2 = connect(&server).await;
+    match request(&mut conn2, "SHOW STATS").await {
+        Response::Text(text) => assert!(text.starts_with("statements: 0\n"), "{}", text),
+        resp => panic!("unexpected response {:?}", resp),
+    }
+
+    assert_eq!(
+        request(&mut conn, "RESET STATS;").await,
+        Response::Text("RESET STATS".to_string())
+    );
+    request(&mut conn, "select * from t;").await;
+    match request(&mut conn, "show stats;").await {
+        Response::Text(text) => {
+            assert!(
+                text.starts_with("statements: 1\nerrors: 0\nrows read: 2\n"),
+                "{}",
+                text
+            )
+        }
+        resp => panic!("unexpected response {:?}", resp),
+    }
+}
+
+#[tokio::test]
+async fn test_show_status() {
+    let server = start_server().await;
+    let mut conn = connect(&server).await;
+    let mut conn2 = connect(&server).await;
+
+    request(&mut conn, "create table t (a int primary key, b text);").await;
+    request(&mut conn, "insert into t values (1, 'a'), (2, 'b');").await;
+    let status = |text: String| {
+        text.lines()
+            .map(|line| {
+                let (name, value) = line.split_once(": ").unwrap();
+                (name.to_string(), value.parse::<u64>().unwrap())
+            })
+            .collect::<std::collections::HashMap<_, _>>()
+    };
+
+    // 存储引擎的状态所有连接共享，可以看到其他连接中的活跃事务
+    request(&mut conn2, "begin;").await;
+    let before = match request(&mut conn, "show status;").await {
+        Response::Text(text) => status(text),
+        resp => panic!("unexpected response {:?}", resp),
+    };
+    assert_eq!(before["active transactions"], 1);
+    assert!(before["keys"] > 0);
+    assert!(before["file bytes"] >= before["logical bytes"]);
+
+    // 覆盖写入产生垃圾数据
+    request(&mut conn2, "commit;").await;
+    request(&mut conn, "update t set b = 'c';").await;
+    let after = match request(&mut conn, "SHOW STATUS").await {
+        Response::Text(text) => status(text),
+        resp => panic!("unexpected response {:?}", resp),
+    };
+    assert_eq!(after["active transactions"], 0);
+    assert!(after["garbage bytes"] > before["garbage bytes"]);
+    assert!(after["file bytes"] > before["file bytes"]);
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{connect, request, start_server};
+use sqldb_rs::error::Error;
+use sqldb_rs::protocol::Response;
+use sqldb_rs::sql::{
+    executor::ResultSet,
+    types::{DataType, Value},
+};
+
+#[tokio::test]
+async fn test_statement_timeout() {
+    let server = start_server().await;
+    let mut conn = connect(&server).await;
+
+    request(&mut conn, "create table t (a int primary key, b int);").await;
+    let values = (0..300)
+        .map(|i| format!("({}, {})", i, i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    request(&mut conn, &format!("insert into t values {};", values)).await;
+
+    assert_eq!(
+        request(&mut conn, "SET timeout = 50;").await,
+        Response::Text("SET TIMEOUT = 50".to_string())
+    );
+
+    // 2700 万行的 cross join，超时后返回错误
+    assert_eq!(
+        request(
+            &mut conn,
+            "select count(*) from t x cross join t y cross join t z;"
+        )
+        .await,
+        Response::error(Error::Timeout(50))
+    );
+
+    // 超时之后连接可以继续使用
+    let count = |n: i64| {
+        Response::Ok(ResultSet::Scan {
+            columns: vec!["count".to_string()],
+            types: vec![Some(DataType::Integer)],
+            rows: vec![vec![Value::Integer(n)]],
+        })
+    };
+    assert_eq!(
+        request(&mut conn, "select count(*) from t;").await,
+        count(300)
+    );
+
+    // 超时只对当前连接有效
+    let mut conn2 = connect(&server).await;
+    assert_eq!(
+        request(&mut conn2, "select count(*) from t x cross join t y;").await,
+        count(90000)
+    );
+
+    // 0 表示不限制，非法的值返回错误
+    match request(&mut conn, "set timeout = abc;").await {
+        Response::Error { code, error } => {
+            assert_eq!(code, "42601");
+            assert!(error.to_string().contains("abc"), "{}", error);
+        }
+        resp => panic!("unexpected response {:?}", resp),
+    }
+    request(&mut conn, "set timeout = 0;").await;
+    assert_eq!(
+        request(&mut conn, "select count(*) from t x cross join t y;").await,
+        count(90000)
+    );
+}
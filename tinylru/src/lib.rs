@@ -0,0 +1,1398 @@
+//! A small LRU cache backed by a vector of entries linked in recency order.
+//!
+//! ```
+//! use tinylru::LRU;
+//!
+//! let mut lru = LRU::with_size(2);
+//! lru.set("a", 1);
+//! lru.set("b", 2);
+//! lru.get(&"a");
+//! lru.set("c", 3); // evicts "b"
+//! assert_eq!(lru.keys().copied().collect::<Vec<_>>(), vec!["c", "a"]);
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::time::{Duration, Instant};
+
+// Default size for the LRU cache
+const DEFAULT_SIZE: usize = 256;
+
+// Source of the current time used for entry expiration
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+// Clock backed by Instant::now
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// Internal LRU item structure
+struct LruItem<K, V> {
+    key: K,
+    value: V,
+    // None means the item never expires
+    expires_at: Option<Instant>,
+    // Weight of the item, 1 unless a weigher is set
+    weight: usize,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+// Computes the weight of an item, for example the size of the value in bytes
+pub type Weigher<K, V> = Arc<dyn Fn(&K, &V) -> usize + Send + Sync>;
+
+// Main LRU cache structure
+pub struct LRU<K, V> {
+    size: usize,
+    items: HashMap<K, usize>,
+    // Freed slots are None until reused, so removing an item moves its value out
+    entries: Vec<Option<LruItem<K, V>>>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    free_list: Vec<usize>,
+    clock: Arc<dyn Clock>,
+    // Total weight of the items and the maximum allowed total weight
+    weight: usize,
+    max_weight: usize,
+    weigher: Option<Weigher<K, V>>,
+}
+
+// Thread-safe wrapper for the LRU
+#[derive(Clone)]
+pub struct ConcurrentLRU<K, V> {
+    inner: Arc<Mutex<LRU<K, V>>>,
+}
+
+impl<K: Eq + Hash + Clone, V> LRU<K, V> {
+    // Create a new LRU with default size
+    pub fn new() -> Self {
+        Self::with_size(DEFAULT_SIZE)
+    }
+
+    // Create a new LRU with specified size
+    pub fn with_size(size: usize) -> Self {
+        Self::with_clock(size, Arc::new(SystemClock))
+    }
+
+    // Create a new LRU with specified size and clock used for expiration
+    pub fn with_clock(size: usize, clock: Arc<dyn Clock>) -> Self {
+        if size == 0 {
+            panic!("invalid size");
+        }
+        Self {
+            size,
+            items: HashMap::new(),
+            entries: Vec::new(),
+            head: None,
+            tail: None,
+            free_list: Vec::new(),
+            clock,
+            weight: 0,
+            max_weight: usize::MAX,
+            weigher: None,
+        }
+    }
+
+    // Create a new LRU bounded by the total weight of the items instead of their count.
+    // An item heavier than max_weight is rejected without evicting anything else: it is not
+    // stored and set_evicted_many returns it as evicted. If its key was already present, the
+    // old value is removed and returned as the previous value, so it is never read back stale.
+    pub fn with_weight_capacity<F>(max_weight: usize, weigher: F) -> Self
+    where
+        F: Fn(&K, &V) -> usize + Send + Sync + 'static,
+    {
+        if max_weight == 0 {
+            panic!("invalid weight");
+        }
+        let mut lru = Self::with_size(usize::MAX);
+        lru.max_weight = max_weight;
+        lru.weigher = Some(Arc::new(weigher));
+        lru
+    }
+
+    // Resize the LRU, evicting items if necessary
+    pub fn resize(&mut self, size: usize) -> (Vec<K>, Vec<V>) {
+        if size == 0 {
+            panic!("invalid size");
+        }
+
+        let mut evicted_keys = Vec::new();
+        let mut evicted_values = Vec::new();
+
+        while size < self.items.len() {
+            if let Some((key, value)) = self.evict() {
+                evicted_keys.push(key);
+                evicted_values.push(value);
+            }
+        }
+
+        self.size = size;
+        (evicted_keys, evicted_values)
+    }
+
+    // Change the maximum total weight, evicting items if necessary
+    pub fn resize_weight(&mut self, max_weight: usize) -> (Vec<K>, Vec<V>) {
+        if max_weight == 0 {
+            panic!("invalid weight");
+        }
+
+        self.max_weight = max_weight;
+        self.evict_overweight().into_iter().unzip()
+    }
+
+    // Get the total weight of the items, equal to len() unless a weigher is set
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    // Get current length, expired items count until they are removed
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    // Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // Set or replace a value with eviction info, only the first evicted item is
+    // returned, use set_evicted_many when several items may be evicted by weight
+    pub fn set_evicted(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> (Option<V>, bool, Option<K>, Option<V>, bool) {
+        let (prev, replaced, evicted) = self.set_inner(key, value, None);
+        match evicted.into_iter().next() {
+            Some((k, v)) => (prev, replaced, Some(k), Some(v), true),
+            None => (prev, replaced, None, None, false),
+        }
+    }
+
+    // Set or replace a value, returning all evicted items from least recently used
+    pub fn set_evicted_many(&mut self, key: K, value: V) -> (Option<V>, bool, Vec<(K, V)>) {
+        self.set_inner(key, value, None)
+    }
+
+    // Set or replace a value that expires after ttl
+    pub fn set_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> (Option<V>, bool) {
+        let expires_at = self.clock.now() + ttl;
+        let (prev, replaced, _) = self.set_inner(key, value, Some(expires_at));
+        (prev, replaced)
+    }
+
+    // Internal: Set or replace a value with the given expiration
+    fn set_inner(
+        &mut self,
+        key: K,
+        value: V,
+        expires_at: Option<Instant>,
+    ) -> (Option<V>, bool, Vec<(K, V)>) {
+        // An expired value is not returned as the previous value
+        if let Some(&index) = self.items.get(&key)
+            && self.is_expired(index)
+        {
+            self.remove_index(index);
+        }
+
+        let weight = match &self.weigher {
+            Some(weigher) => weigher(&key, &value),
+            None => 1,
+        };
+        // Reject an item that can never fit before touching the other entries
+        if weight > self.max_weight {
+            let prev = self
+                .items
+                .get(&key)
+                .copied()
+                .map(|index| self.remove_index(index).1);
+            let replaced = prev.is_some();
+            return (prev, replaced, vec![(key, value)]);
+        }
+        let mut evicted = Vec::new();
+        let (prev, replaced) = if let Some(index) = self.items.get(&key) {
+            // Key already exists - replace value
+            let index = *index;
+            let entry = self.entry_mut(index);
+            let prev_value = std::mem::replace(&mut entry.value, value);
+            entry.expires_at = expires_at;
+            let prev_weight = std::mem::replace(&mut entry.weight, weight);
+            self.weight = self.weight - prev_weight + weight;
+            self.move_to_front(index);
+            (Some(prev_value), true)
+        } else {
+            // Key doesn't exist - insert new entry
+            if self.items.len() >= self.size {
+                evicted.extend(self.evict());
+            }
+
+            let index = self.allocate_entry(key.clone(), value, expires_at, weight);
+            self.items.insert(key, index);
+            self.weight += weight;
+            self.push_front(index);
+            (None, false)
+        };
+
+        evicted.extend(self.evict_overweight());
+        (prev, replaced, evicted)
+    }
+
+    // Set or replace a value
+    pub fn set(&mut self, key: K, value: V) -> (Option<V>, bool) {
+        let (prev, replaced, _, _, _) = self.set_evicted(key, value);
+        (prev, replaced)
+    }
+
+    // Get a reference to a value and mark as recently used
+    pub fn get_ref(&mut self, key: &K) -> Option<&V> {
+        let index = match self.items.get(key) {
+            Some(&index) => index,
+            None => return None,
+        };
+
+        // Lazily remove an expired item
+        if self.is_expired(index) {
+            self.remove_index(index);
+            return None;
+        }
+
+        self.move_to_front(index);
+        Some(&self.entry(index).value)
+    }
+
+    // Check if key exists and is not expired
+    pub fn contains(&self, key: &K) -> bool {
+        self.items
+            .get(key)
+            .is_some_and(|&index| !self.is_expired(index))
+    }
+
+    // Peek at a reference to a value without marking as recently used
+    pub fn peek_ref(&self, key: &K) -> Option<&V> {
+        self.items
+            .get(key)
+            .filter(|&&index| !self.is_expired(index))
+            .map(|&index| &self.entry(index).value)
+    }
+
+    // Delete a key-value pair
+    pub fn delete(&mut self, key: &K) -> (Option<V>, bool) {
+        if let Some(&index) = self.items.get(key) {
+            let expired = self.is_expired(index);
+            let (_, value) = self.remove_index(index);
+            if expired {
+                (None, false)
+            } else {
+                (Some(value), true)
+            }
+        } else {
+            (None, false)
+        }
+    }
+
+    // Remove a key and take ownership of its value
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.delete(key).0
+    }
+
+    // Mark a key as recently used without touching its value, returns false if it is missing
+    pub fn touch(&mut self, key: &K) -> bool {
+        self.get_ref(key).is_some()
+    }
+
+    // Remove and return the least recently used item, expired items are dropped on the way
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        self.pop_with(|lru| lru.tail)
+    }
+
+    // Remove and return the most recently used item, expired items are dropped on the way
+    pub fn pop_mru(&mut self) -> Option<(K, V)> {
+        self.pop_with(|lru| lru.head)
+    }
+
+    // Internal: Remove items at the end picked by next until one has not expired
+    fn pop_with(&mut self, next: fn(&Self) -> Option<usize>) -> Option<(K, V)> {
+        while let Some(index) = next(self) {
+            let expired = self.is_expired(index);
+            let item = self.remove_index(index);
+            if !expired {
+                return Some(item);
+            }
+        }
+        None
+    }
+
+    // Remove all expired items, sweeping from least to most recently used
+    pub fn purge_expired(&mut self) -> (Vec<K>, Vec<V>) {
+        let mut expired_keys = Vec::new();
+        let mut expired_values = Vec::new();
+
+        let mut current = self.tail;
+        while let Some(index) = current {
+            current = self.entry(index).prev;
+            if self.is_expired(index) {
+                let (key, value) = self.remove_index(index);
+                expired_keys.push(key);
+                expired_values.push(value);
+            }
+        }
+
+        (expired_keys, expired_values)
+    }
+
+    // Clear all entries
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.entries.clear();
+        self.head = None;
+        self.tail = None;
+        self.free_list.clear();
+        self.weight = 0;
+    }
+
+    /// Iterates from most to least recently used, skipping expired items.
+    ///
+    /// ```
+    /// use tinylru::LRU;
+    ///
+    /// let mut lru = LRU::with_size(3);
+    /// lru.set(1, "one");
+    /// lru.set(2, "two");
+    /// lru.set(3, "three");
+    /// let items: Vec<_> = lru.iter().filter(|(k, _)| **k != 2).collect();
+    /// assert_eq!(items, vec![(&3, &"three"), (&1, &"one")]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            lru: self,
+            current: self.head,
+            forward: true,
+        }
+    }
+
+    // Iterate from least to most recently used, skipping expired items
+    pub fn iter_rev(&self) -> Iter<'_, K, V> {
+        Iter {
+            lru: self,
+            current: self.tail,
+            forward: false,
+        }
+    }
+
+    /// Keys from most to least recently used, in the same order as [`LRU::iter`].
+    ///
+    /// Setting or getting a key moves it to the front, peeking does not.
+    ///
+    /// ```
+    /// use tinylru::LRU;
+    ///
+    /// let mut lru = LRU::with_size(3);
+    /// lru.set(1, "one");
+    /// lru.set(2, "two");
+    /// lru.set(3, "three");
+    /// lru.get(&1);
+    /// lru.peek(&2);
+    /// assert_eq!(lru.keys().copied().collect::<Vec<_>>(), vec![1, 3, 2]);
+    /// assert_eq!(lru.pop_lru(), Some((2, "two")));
+    /// ```
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    // Values from most to least recently used
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    // Iterate from most to least recently used until iter returns false
+    pub fn range<F>(&self, mut iter: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for (k, v) in self.iter() {
+            if !iter(k, v) {
+                return;
+            }
+        }
+    }
+
+    // Iterate from least to most recently used until iter returns false
+    pub fn reverse<F>(&self, mut iter: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for (k, v) in self.iter_rev() {
+            if !iter(k, v) {
+                return;
+            }
+        }
+    }
+
+    // Internal: Evict least recently used item
+    fn evict(&mut self) -> Option<(K, V)> {
+        self.tail.map(|tail| self.remove_index(tail))
+    }
+
+    // Internal: Evict least recently used items until the total weight fits
+    fn evict_overweight(&mut self) -> Vec<(K, V)> {
+        let mut evicted = Vec::new();
+        while self.weight > self.max_weight {
+            match self.evict() {
+                Some(item) => evicted.push(item),
+                None => break,
+            }
+        }
+        evicted
+    }
+
+    // Internal: Remove an item from the map and the list, moving it out of its entry
+    fn remove_index(&mut self, index: usize) -> (K, V) {
+        self.remove_entry(index);
+        let entry = self.entries[index].take().expect("entry is in use");
+        self.weight -= entry.weight;
+        self.items.remove(&entry.key);
+        self.free_list.push(index);
+        (entry.key, entry.value)
+    }
+
+    // Internal: Get an entry that is in use
+    fn entry(&self, index: usize) -> &LruItem<K, V> {
+        self.entries[index].as_ref().expect("entry is in use")
+    }
+
+    // Internal: Get a mutable entry that is in use
+    fn entry_mut(&mut self, index: usize) -> &mut LruItem<K, V> {
+        self.entries[index].as_mut().expect("entry is in use")
+    }
+
+    // Internal: Check if an entry has expired
+    fn is_expired(&self, index: usize) -> bool {
+        self.entry(index)
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= self.clock.now())
+    }
+
+    // Internal: Move an entry to the front
+    fn move_to_front(&mut self, index: usize) {
+        if self.head == Some(index) {
+            return;
+        }
+        self.remove_entry(index);
+        self.push_front(index);
+    }
+
+    // Internal: Remove an entry from the linked list (but keep in entries vec)
+    fn remove_entry(&mut self, index: usize) {
+        let prev = self.entry(index).prev;
+        let next = self.entry(index).next;
+
+        if let Some(prev) = prev {
+            self.entry_mut(prev).next = next;
+        } else {
+            self.head = next;
+        }
+
+        if let Some(next) = next {
+            self.entry_mut(next).prev = prev;
+        } else {
+            self.tail = prev;
+        }
+    }
+
+    // Internal: Push an entry to the front
+    fn push_front(&mut self, index: usize) {
+        let head = self.head;
+        let entry = self.entry_mut(index);
+        entry.prev = None;
+        entry.next = head;
+
+        if let Some(head) = self.head {
+            self.entry_mut(head).prev = Some(index);
+        } else {
+            self.tail = Some(index);
+        }
+
+        self.head = Some(index);
+    }
+
+    // Internal: Allocate a new entry
+    fn allocate_entry(
+        &mut self,
+        key: K,
+        value: V,
+        expires_at: Option<Instant>,
+        weight: usize,
+    ) -> usize {
+        if let Some(index) = self.free_list.pop() {
+            self.entries[index] = Some(LruItem {
+                key,
+                value,
+                expires_at,
+                weight,
+                prev: None,
+                next: None,
+            });
+            index
+        } else {
+            let index = self.entries.len();
+            self.entries.push(Some(LruItem {
+                key,
+                value,
+                expires_at,
+                weight,
+                prev: None,
+                next: None,
+            }));
+            index
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LRU<K, V> {
+    // Get a value and mark as recently used
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        self.get_ref(key).cloned()
+    }
+
+    // Peek at a value without marking as recently used
+    pub fn peek(&self, key: &K) -> Option<V> {
+        self.peek_ref(key).cloned()
+    }
+
+    // Get a value, or insert the one computed by f if the key is missing
+    pub fn get_or_insert_with<F>(&mut self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> V,
+    {
+        let (value, _, _, _) = self.get_or_insert_with_evicted(key, f);
+        value
+    }
+
+    // Get a value, or insert the one computed by f with eviction info
+    pub fn get_or_insert_with_evicted<F>(&mut self, key: K, f: F) -> (V, Option<K>, Option<V>, bool)
+    where
+        F: FnOnce() -> V,
+    {
+        if let Some(value) = self.get(&key) {
+            return (value, None, None, false);
+        }
+        let value = f();
+        let (_, _, evicted_key, evicted_value, evicted) = self.set_evicted(key, value.clone());
+        (value, evicted_key, evicted_value, evicted)
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> Default for LRU<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Iterator over the entries of an LRU in recency order
+pub struct Iter<'a, K, V> {
+    lru: &'a LRU<K, V>,
+    current: Option<usize>,
+    forward: bool,
+}
+
+impl<'a, K: Eq + Hash + Clone, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(index) = self.current {
+            let entry = self.lru.entry(index);
+            self.current = if self.forward { entry.next } else { entry.prev };
+            if !self.lru.is_expired(index) {
+                return Some((&entry.key, &entry.value));
+            }
+        }
+        None
+    }
+}
+
+impl<'a, K: Eq + Hash + Clone, V> IntoIterator for &'a LRU<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// Owning iterator over the entries of an LRU, from most to least recently used
+pub struct IntoIter<K, V>(std::vec::IntoIter<(K, V)>);
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> IntoIterator for LRU<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Consumes the cache, yielding entries from most to least recently used.
+    ///
+    /// ```
+    /// use tinylru::LRU;
+    ///
+    /// let mut lru = LRU::with_size(2);
+    /// lru.set("a".to_string(), vec![1]);
+    /// lru.set("b".to_string(), vec![2]);
+    /// let items: Vec<(String, Vec<i32>)> = lru.into_iter().collect();
+    /// assert_eq!(items, vec![("b".to_string(), vec![2]), ("a".to_string(), vec![1])]);
+    /// ```
+    fn into_iter(self) -> Self::IntoIter {
+        let order = self.iter().map(|(k, _)| self.items[k]).collect::<Vec<_>>();
+        let mut entries = self.entries;
+        let items = order
+            .into_iter()
+            .filter_map(|index| entries[index].take())
+            .map(|entry| (entry.key, entry.value))
+            .collect::<Vec<_>>();
+        IntoIter(items.into_iter())
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send + 'static, V: Send + 'static> ConcurrentLRU<K, V> {
+    pub fn new() -> Self {
+        Self::with_size(DEFAULT_SIZE)
+    }
+
+    pub fn with_size(size: usize) -> Self {
+        Self::with_clock(size, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(size: usize, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(LRU::with_clock(size, clock))),
+        }
+    }
+
+    pub fn with_weight_capacity<F>(max_weight: usize, weigher: F) -> Self
+    where
+        F: Fn(&K, &V) -> usize + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(Mutex::new(LRU::with_weight_capacity(max_weight, weigher))),
+        }
+    }
+
+    pub fn resize(&self, size: usize) -> (Vec<K>, Vec<V>) {
+        self.lock().resize(size)
+    }
+
+    pub fn resize_weight(&self, max_weight: usize) -> (Vec<K>, Vec<V>) {
+        self.lock().resize_weight(max_weight)
+    }
+
+    pub fn weight(&self) -> usize {
+        self.lock().weight()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn set_evicted(&self, key: K, value: V) -> (Option<V>, bool, Option<K>, Option<V>, bool) {
+        self.lock().set_evicted(key, value)
+    }
+
+    pub fn set_evicted_many(&self, key: K, value: V) -> (Option<V>, bool, Vec<(K, V)>) {
+        self.lock().set_evicted_many(key, value)
+    }
+
+    pub fn set(&self, key: K, value: V) -> (Option<V>, bool) {
+        self.lock().set(key, value)
+    }
+
+    pub fn set_with_ttl(&self, key: K, value: V, ttl: Duration) -> (Option<V>, bool) {
+        self.lock().set_with_ttl(key, value, ttl)
+    }
+
+    pub fn purge_expired(&self) -> (Vec<K>, Vec<V>) {
+        self.lock().purge_expired()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.lock().contains(key)
+    }
+
+    pub fn delete(&self, key: &K) -> (Option<V>, bool) {
+        self.lock().delete(key)
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.lock().remove(key)
+    }
+
+    pub fn touch(&self, key: &K) -> bool {
+        self.lock().touch(key)
+    }
+
+    pub fn pop_lru(&self) -> Option<(K, V)> {
+        self.lock().pop_lru()
+    }
+
+    pub fn pop_mru(&self) -> Option<(K, V)> {
+        self.lock().pop_mru()
+    }
+
+    // Run f on the value under the lock instead of cloning it, marks the key as recently used
+    pub fn with<R, F>(&self, key: &K, f: F) -> Option<R>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        self.lock().get_ref(key).map(f)
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+
+    // Iterate from most to least recently used until iter returns false. The lock is held
+    // for the whole walk, so every other cache operation waits on iter; use snapshot when
+    // the callback is slow and a consistent view is not needed
+    pub fn for_each_while<F>(&self, iter: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.lock().range(iter)
+    }
+
+    // Same as for_each_while
+    pub fn range<F>(&self, iter: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.for_each_while(iter)
+    }
+
+    // Iterate from least to most recently used until iter returns false, holding the lock
+    pub fn reverse<F>(&self, iter: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.lock().reverse(iter)
+    }
+
+    fn lock(&self) -> MutexGuard<LRU<K, V>> {
+        self.inner.lock().unwrap()
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send + 'static, V: Clone + Send + 'static> ConcurrentLRU<K, V> {
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.lock().get(key)
+    }
+
+    // The lock is held while f runs, so f is called at most once per missing key
+    pub fn get_or_insert_with<F>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> V,
+    {
+        self.lock().get_or_insert_with(key, f)
+    }
+
+    pub fn get_or_insert_with_evicted<F>(&self, key: K, f: F) -> (V, Option<K>, Option<V>, bool)
+    where
+        F: FnOnce() -> V,
+    {
+        self.lock().get_or_insert_with_evicted(key, f)
+    }
+
+    // Like get_or_insert_with, but returns None instead of waiting when the lock is held
+    pub fn try_get_or_insert_with<F>(&self, key: K, f: F) -> Option<V>
+    where
+        F: FnOnce() -> V,
+    {
+        match self.inner.try_lock() {
+            Ok(mut lru) => Some(lru.get_or_insert_with(key, f)),
+            Err(TryLockError::WouldBlock) => None,
+            Err(TryLockError::Poisoned(err)) => panic!("{}", err),
+        }
+    }
+
+    pub fn peek(&self, key: &K) -> Option<V> {
+        self.lock().peek(key)
+    }
+
+    // Clone the entries from most to least recently used, the cache can not hand out
+    // references across the lock. The lock is only held while cloning, so callers can
+    // iterate the result without blocking writers
+    pub fn snapshot(&self) -> Vec<(K, V)> {
+        self.lock()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    // Same as snapshot
+    pub fn to_vec(&self) -> Vec<(K, V)> {
+        self.snapshot()
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send + 'static, V: Send + 'static> Default for ConcurrentLRU<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::{Clock, ConcurrentLRU, LRU};
+
+    // Clock that only moves when advanced by the test
+    struct MockClock(Mutex<Instant>);
+
+    impl MockClock {
+        fn advance(&self, duration: Duration) {
+            *self.0.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn collect(lru: &LRU<i32, &'static str>) -> Vec<(i32, &'static str)> {
+        let mut items = Vec::new();
+        lru.range(|k, v| {
+            items.push((*k, *v));
+            true
+        });
+        items
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut lru = LRU::with_size(2);
+        assert_eq!(lru.get_or_insert_with(1, || "one".to_string()), "one");
+        // The cached value is returned without calling f
+        assert_eq!(
+            lru.get_or_insert_with(1, || unreachable!("loaded twice")),
+            "one"
+        );
+
+        assert_eq!(
+            lru.get_or_insert_with_evicted(2, || "two".to_string()),
+            ("two".to_string(), None, None, false)
+        );
+        assert_eq!(
+            lru.get_or_insert_with_evicted(3, || "three".to_string()),
+            ("three".to_string(), Some(1), Some("one".to_string()), true)
+        );
+        assert_eq!(lru.len(), 2);
+        assert!(!lru.contains(&1));
+    }
+
+    #[test]
+    fn test_concurrent_get_or_insert_with() {
+        let lru = ConcurrentLRU::<i32, String>::with_size(16);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(8));
+
+        let handles = (0..8)
+            .map(|_| {
+                let lru = lru.clone();
+                let calls = calls.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    (0..100)
+                        .map(|_| {
+                            lru.get_or_insert_with(1, || {
+                                calls.fetch_add(1, Ordering::SeqCst);
+                                thread::sleep(Duration::from_millis(10));
+                                "one".to_string()
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            assert!(handle.join().unwrap().iter().all(|v| v == "one"));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(lru.len(), 1);
+    }
+
+    #[test]
+    fn test_try_get_or_insert_with() {
+        let lru = ConcurrentLRU::<i32, String>::with_size(2);
+        assert_eq!(
+            lru.try_get_or_insert_with(1, || "one".to_string()),
+            Some("one".to_string())
+        );
+
+        // Another holder of the lock makes the call return immediately
+        let guard = lru.lock();
+        let other = lru.clone();
+        let result =
+            thread::spawn(move || other.try_get_or_insert_with(2, || unreachable!("lock is held")))
+                .join()
+                .unwrap();
+        assert_eq!(result, None);
+        drop(guard);
+
+        assert!(!lru.contains(&2));
+        assert_eq!(
+            lru.try_get_or_insert_with(1, || unreachable!("already cached")),
+            Some("one".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reuse_after_move_to_front() {
+        let mut lru = LRU::with_size(3);
+        lru.set(1, "one");
+        lru.set(2, "two");
+        assert_eq!(lru.get(&1), Some("one"));
+        lru.set(3, "three");
+        assert_eq!(collect(&lru), vec![(3, "three"), (1, "one"), (2, "two")]);
+        assert_eq!(lru.peek(&2), Some("two"));
+    }
+
+    #[test]
+    fn test_ttl() {
+        let clock = Arc::new(MockClock(Mutex::new(Instant::now())));
+        let mut lru = LRU::with_clock(4, clock.clone());
+        lru.set_with_ttl(1, "one", Duration::from_secs(10));
+        lru.set(2, "two");
+        lru.set_with_ttl(3, "three", Duration::from_secs(20));
+        lru.set_with_ttl(4, "four", Duration::from_secs(10));
+        // set keeps entries immortal, replacing the ttl of an existing entry
+        lru.set(4, "FOUR");
+
+        clock.advance(Duration::from_secs(15));
+        assert!(!lru.contains(&1));
+        assert_eq!(lru.peek(&1), None);
+        assert_eq!(collect(&lru), vec![(4, "FOUR"), (3, "three"), (2, "two")]);
+        // Expired items are counted until they are removed
+        assert_eq!(lru.len(), 4);
+        assert_eq!(lru.get(&1), None);
+        assert_eq!(lru.len(), 3);
+        assert_eq!(lru.get(&1), None);
+        assert_eq!(lru.len(), 3);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(lru.purge_expired(), (vec![3], vec!["three"]));
+        assert_eq!(lru.purge_expired(), (vec![], vec![]));
+        assert_eq!(lru.len(), 2);
+        assert_eq!(collect(&lru), vec![(4, "FOUR"), (2, "two")]);
+
+        // An expired value is not reported as replaced
+        lru.set_with_ttl(5, "five", Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lru.set(5, "FIVE"), (None, false));
+        assert_eq!(lru.delete(&5), (Some("FIVE"), true));
+        lru.set_with_ttl(6, "six", Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lru.delete(&6), (None, false));
+        assert_eq!(lru.len(), 2);
+
+        // Freed slots are reused correctly after expiration
+        for i in 10..14 {
+            lru.set_with_ttl(i, "x", Duration::from_secs(5));
+        }
+        assert_eq!(lru.len(), 4);
+        assert_eq!(collect(&lru).len(), 4);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(lru.purge_expired().0.len(), 4);
+        assert!(lru.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_ttl() {
+        let clock = Arc::new(MockClock(Mutex::new(Instant::now())));
+        let lru = ConcurrentLRU::with_clock(2, clock.clone());
+        lru.set_with_ttl(1, "one".to_string(), Duration::from_secs(1));
+        assert_eq!(lru.get(&1), Some("one".to_string()));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lru.get(&1), None);
+        assert!(lru.is_empty());
+    }
+
+    #[test]
+    fn test_iter() {
+        let clock = Arc::new(MockClock(Mutex::new(Instant::now())));
+        let mut lru = LRU::with_clock(4, clock.clone());
+        lru.set(1, "one");
+        lru.set_with_ttl(2, "two", Duration::from_secs(1));
+        lru.set(3, "three");
+        lru.get(&1);
+
+        assert_eq!(
+            lru.iter().collect::<Vec<_>>(),
+            vec![(&1, &"one"), (&3, &"three"), (&2, &"two")]
+        );
+        assert_eq!(
+            lru.iter_rev().map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec![2, 3, 1]
+        );
+        assert_eq!(lru.keys().count(), 3);
+        assert_eq!(
+            (&lru).into_iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec![1, 3, 2]
+        );
+
+        // Expired entries are skipped by all iterators
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lru.keys().copied().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(
+            lru.values().copied().collect::<Vec<_>>(),
+            vec!["one", "three"]
+        );
+        let mut reversed = Vec::new();
+        lru.reverse(|k, _| {
+            reversed.push(*k);
+            true
+        });
+        assert_eq!(reversed, vec![3, 1]);
+        assert_eq!(
+            lru.into_iter().collect::<Vec<_>>(),
+            vec![(1, "one"), (3, "three")]
+        );
+    }
+
+    #[test]
+    fn test_concurrent_to_vec() {
+        let lru = ConcurrentLRU::with_size(2);
+        lru.set(1, "one".to_string());
+        lru.set(2, "two".to_string());
+        lru.set(3, "three".to_string());
+        assert_eq!(
+            lru.to_vec(),
+            vec![(3, "three".to_string()), (2, "two".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_pop() {
+        let clock = Arc::new(MockClock(Mutex::new(Instant::now())));
+        let mut lru = LRU::with_clock(4, clock.clone());
+        assert_eq!(lru.pop_lru(), None);
+        assert_eq!(lru.pop_mru(), None);
+
+        lru.set(1, "one".to_string());
+        lru.set(2, "two".to_string());
+        lru.set(3, "three".to_string());
+        assert!(lru.touch(&1));
+        assert!(!lru.touch(&4));
+        assert_eq!(lru.pop_lru(), Some((2, "two".to_string())));
+        assert_eq!(lru.pop_mru(), Some((1, "one".to_string())));
+        assert_eq!(lru.len(), 1);
+
+        // Freed slots are reused by later sets
+        lru.set(4, "four".to_string());
+        lru.set(5, "five".to_string());
+        assert_eq!(lru.entries.len(), 3);
+        assert_eq!(lru.keys().copied().collect::<Vec<_>>(), vec![5, 4, 3]);
+
+        // Expired items are dropped instead of returned
+        lru.set_with_ttl(6, "six".to_string(), Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+        assert!(!lru.touch(&6));
+        lru.set_with_ttl(7, "seven".to_string(), Duration::from_secs(1));
+        lru.get(&3);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lru.pop_mru(), Some((3, "three".to_string())));
+        assert_eq!(lru.pop_mru(), Some((5, "five".to_string())));
+        assert_eq!(lru.pop_lru(), Some((4, "four".to_string())));
+        assert_eq!(lru.pop_lru(), None);
+        assert!(lru.is_empty());
+
+        let lru = ConcurrentLRU::with_size(2);
+        lru.set(1, "one".to_string());
+        lru.set(2, "two".to_string());
+        assert!(lru.touch(&1));
+        assert_eq!(lru.pop_lru(), Some((2, "two".to_string())));
+        assert_eq!(lru.pop_mru(), Some((1, "one".to_string())));
+        assert!(lru.is_empty());
+    }
+
+    #[test]
+    fn test_pop_model() {
+        const SIZE: usize = 8;
+        let mut lru = LRU::with_size(SIZE);
+        // Front is the most recently used key
+        let mut model = VecDeque::new();
+        let mut seed = 0x2545_f491_u64;
+        let mut next = |n: u64| {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) % n
+        };
+
+        for step in 0..10_000 {
+            let key = next(16) as i32;
+            match next(5) {
+                0 | 1 => {
+                    let value = key * 100 + step % 100;
+                    if let Some(pos) = model.iter().position(|(k, _)| *k == key) {
+                        model.remove(pos);
+                    } else if model.len() == SIZE {
+                        model.pop_back();
+                    }
+                    model.push_front((key, value));
+                    lru.set(key, value);
+                }
+                2 => assert_eq!(lru.pop_lru(), model.pop_back()),
+                3 => {
+                    let touched = match model.iter().position(|(k, _)| *k == key) {
+                        Some(pos) => {
+                            let item = model.remove(pos).unwrap();
+                            model.push_front(item);
+                            true
+                        }
+                        None => false,
+                    };
+                    assert_eq!(lru.touch(&key), touched);
+                }
+                _ => {
+                    if next(2) == 0 {
+                        assert_eq!(lru.pop_mru(), model.pop_front());
+                    } else {
+                        let expected = model
+                            .iter()
+                            .position(|(k, _)| *k == key)
+                            .and_then(|pos| model.remove(pos))
+                            .map(|(_, v)| v);
+                        assert_eq!(lru.remove(&key), expected);
+                    }
+                }
+            }
+
+            assert_eq!(lru.len(), model.len());
+            assert!(lru.entries.len() <= SIZE);
+            assert_eq!(
+                lru.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+                model.iter().copied().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_concurrent_range_borrows_locals() {
+        let lru = ConcurrentLRU::with_size(4);
+        for i in 1..=4 {
+            lru.set(i, i * 10);
+        }
+
+        let mut seen = Vec::new();
+        lru.range(|k, v| {
+            seen.push((*k, *v));
+            true
+        });
+        assert_eq!(seen, vec![(4, 40), (3, 30), (2, 20), (1, 10)]);
+
+        let mut keys = Vec::new();
+        lru.for_each_while(|k, _| {
+            keys.push(*k);
+            keys.len() < 2
+        });
+        assert_eq!(keys, vec![4, 3]);
+
+        keys.clear();
+        lru.reverse(|k, _| {
+            keys.push(*k);
+            true
+        });
+        assert_eq!(keys, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_concurrent_snapshot() {
+        let lru = ConcurrentLRU::with_size(64);
+        for i in 0..64 {
+            lru.set(i, i);
+        }
+        let barrier = Arc::new(Barrier::new(2));
+
+        let writer = {
+            let lru = lru.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                for i in 0..1000 {
+                    lru.set(i % 128, i);
+                    lru.delete(&((i + 64) % 128));
+                }
+            })
+        };
+
+        barrier.wait();
+        for _ in 0..100 {
+            // Writers are free to run while the snapshot is iterated
+            for (k, v) in lru.snapshot() {
+                assert!(k < 128);
+                lru.peek(&k);
+                assert!(v < 1000);
+            }
+        }
+        writer.join().unwrap();
+
+        let snapshot = lru.snapshot();
+        assert_eq!(snapshot.len(), lru.len());
+        assert_eq!(snapshot, lru.to_vec());
+    }
+
+    #[test]
+    fn test_weight_capacity() {
+        let mut lru = LRU::with_weight_capacity(10, |_: &i32, v: &String| v.len());
+        lru.set(1, "aaaa".to_string());
+        lru.set(2, "bbbb".to_string());
+        assert_eq!(lru.len(), 2);
+        assert_eq!(lru.weight(), 8);
+
+        // Several items are evicted from the tail until the new item fits
+        let (prev, replaced, evicted) = lru.set_evicted_many(3, "cccccccc".to_string());
+        assert_eq!((prev, replaced), (None, false));
+        assert_eq!(
+            evicted,
+            vec![(1, "aaaa".to_string()), (2, "bbbb".to_string())]
+        );
+        assert_eq!(lru.len(), 1);
+        assert_eq!(lru.weight(), 8);
+
+        // Replacing a value adjusts the weight
+        let (_, _, evicted) = lru.set_evicted_many(3, "cc".to_string());
+        assert!(evicted.is_empty());
+        assert_eq!(lru.weight(), 2);
+        lru.set(4, "dddd".to_string());
+        lru.set(5, "ee".to_string());
+        assert_eq!(lru.weight(), 8);
+
+        // set_evicted only reports the first evicted item
+        let (_, _, key, value, evicted) = lru.set_evicted(6, "ffffff".to_string());
+        assert_eq!(
+            (key, value, evicted),
+            (Some(3), Some("cc".to_string()), true)
+        );
+        assert_eq!(lru.keys().copied().collect::<Vec<_>>(), vec![6, 5]);
+        assert_eq!(lru.weight(), 8);
+
+        // An item heavier than the capacity is rejected, the earlier entries survive
+        let (prev, replaced, evicted) = lru.set_evicted_many(7, "g".repeat(11));
+        assert_eq!((prev, replaced), (None, false));
+        assert_eq!(evicted, vec![(7, "g".repeat(11))]);
+        assert_eq!(lru.keys().copied().collect::<Vec<_>>(), vec![6, 5]);
+        assert_eq!(lru.weight(), 8);
+        assert_eq!(lru.get(&7), None);
+
+        // Replacing a value with one that is too heavy removes the old value
+        let (prev, replaced, evicted) = lru.set_evicted_many(5, "e".repeat(11));
+        assert_eq!((prev, replaced), (Some("ee".to_string()), true));
+        assert_eq!(evicted, vec![(5, "e".repeat(11))]);
+        assert_eq!(lru.get(&5), None);
+        assert_eq!(lru.keys().copied().collect::<Vec<_>>(), vec![6]);
+        assert_eq!(lru.weight(), 6);
+        lru.clear();
+
+        lru.set(8, "hhh".to_string());
+        lru.set(9, "iii".to_string());
+        assert_eq!(lru.delete(&8), (Some("hhh".to_string()), true));
+        assert_eq!(lru.weight(), 3);
+        lru.clear();
+        assert_eq!(lru.weight(), 0);
+    }
+
+    #[test]
+    fn test_resize_weight() {
+        let lru = ConcurrentLRU::with_weight_capacity(10, |_: &i32, v: &i32| *v as usize);
+        lru.set(1, 3);
+        lru.set(2, 3);
+        lru.set(3, 3);
+        assert_eq!(lru.weight(), 9);
+
+        assert_eq!(lru.resize_weight(5), (vec![1, 2], vec![3, 3]));
+        assert_eq!(lru.len(), 1);
+        assert_eq!(lru.weight(), 3);
+
+        assert_eq!(lru.resize_weight(20), (vec![], vec![]));
+        assert_eq!(lru.set_evicted_many(4, 10), (None, false, vec![]));
+        assert_eq!(lru.weight(), 13);
+
+        // Without a weigher every item weighs 1
+        let mut lru = LRU::with_size(2);
+        lru.set(1, "one");
+        lru.set(2, "two");
+        lru.set(3, "three");
+        assert_eq!((lru.len(), lru.weight()), (2, 2));
+    }
+
+    // Value that can not be cloned
+    #[derive(Debug, PartialEq)]
+    struct Blob(Vec<u8>);
+
+    #[test]
+    fn test_ref_access() {
+        let mut lru = LRU::with_size(2);
+        lru.set(1, Blob(vec![1]));
+        lru.set(2, Blob(vec![2]));
+
+        assert_eq!(lru.peek_ref(&1), Some(&Blob(vec![1])));
+        // get_ref marks 1 as recently used, so 2 is evicted
+        assert_eq!(lru.get_ref(&1), Some(&Blob(vec![1])));
+        let (prev, replaced, evicted) = lru.set_evicted_many(3, Blob(vec![3]));
+        assert_eq!((prev, replaced), (None, false));
+        assert_eq!(evicted, vec![(2, Blob(vec![2]))]);
+        assert_eq!(lru.get_ref(&2), None);
+
+        // Replacing and removing hand back the owned value
+        assert_eq!(lru.set(3, Blob(vec![4])), (Some(Blob(vec![3])), true));
+        assert_eq!(lru.remove(&3), Some(Blob(vec![4])));
+        assert_eq!(lru.remove(&3), None);
+        assert_eq!(lru.len(), 1);
+
+        // Removed slots are reused
+        lru.set(4, Blob(vec![5]));
+        lru.set(5, Blob(vec![6]));
+        assert_eq!(lru.keys().copied().collect::<Vec<_>>(), vec![5, 4]);
+        assert_eq!(
+            lru.into_iter().collect::<Vec<_>>(),
+            vec![(5, Blob(vec![6])), (4, Blob(vec![5]))]
+        );
+    }
+
+    #[test]
+    fn test_concurrent_with() {
+        let lru = ConcurrentLRU::with_size(2);
+        lru.set(1, Blob(vec![1, 2, 3]));
+        lru.set(2, Blob(vec![4]));
+
+        assert_eq!(lru.with(&1, |blob| blob.0.len()), Some(3));
+        assert_eq!(lru.with(&3, |blob| blob.0.len()), None);
+
+        // with marks the key as recently used
+        let (_, _, key, value, evicted) = lru.set_evicted(3, Blob(vec![5]));
+        assert_eq!((key, value, evicted), (Some(2), Some(Blob(vec![4])), true));
+        assert_eq!(lru.remove(&1), Some(Blob(vec![1, 2, 3])));
+    }
+}